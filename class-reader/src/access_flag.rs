@@ -1,5 +1,37 @@
-#[derive(Debug, PartialEq, Eq, Clone)]
-enum AccessFlag {
+/// A flag of the access flags of a class, a field or a method.
+pub trait Flag: Copy {
+    /// The bit of the flag in the access flags mask.
+    fn mask(&self) -> u16;
+
+    /// The name of the flag in lower case, e.g. `"public"`.
+    fn name(&self) -> &'static str;
+}
+
+/// The access flags of a class, a field or a method, as the set flags.
+pub trait AccessFlags {
+    type Flag: Flag;
+
+    /// The set flags, in the order of their bits.
+    fn flags(&self) -> &[Self::Flag];
+
+    /// The access flags mask the flags are encoded as.
+    fn mask(&self) -> u16 {
+        self.flags().iter().fold(0, |mask, flag| mask | flag.mask())
+    }
+
+    /// The names of the set flags, e.g. `["public", "final"]`.
+    fn names(&self) -> Vec<String> {
+        self.flags().iter().map(|flag| flag.name().to_owned()).collect()
+    }
+
+    /// Whether `flag` is set.
+    fn contains(&self, flag: Self::Flag) -> bool {
+        self.mask() & flag.mask() != 0
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ClassFlag {
     Public,
     Final,
     Super,
@@ -11,9 +43,9 @@ enum AccessFlag {
     Module,
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ClassFileAccessFlags {
-    flags: Vec<AccessFlag>,
+    flags: Vec<ClassFlag>,
 }
 
 impl ClassFileAccessFlags {
@@ -21,45 +53,83 @@ impl ClassFileAccessFlags {
         let mut flags = Vec::new();
 
         if mask & 0x0001 != 0 {
-            flags.push(AccessFlag::Public);
+            flags.push(ClassFlag::Public);
         }
 
         if mask & 0x0010 != 0 {
-            flags.push(AccessFlag::Final);
+            flags.push(ClassFlag::Final);
         }
 
         if mask & 0x0020 != 0 {
-            flags.push(AccessFlag::Super);
+            flags.push(ClassFlag::Super);
         }
 
         if mask & 0x0200 != 0 {
-            flags.push(AccessFlag::Interface);
+            flags.push(ClassFlag::Interface);
         }
 
         if mask & 0x0400 != 0 {
-            flags.push(AccessFlag::Abstract);
+            flags.push(ClassFlag::Abstract);
         }
 
         if mask & 0x1000 != 0 {
-            flags.push(AccessFlag::Synthetic);
+            flags.push(ClassFlag::Synthetic);
         }
 
         if mask & 0x2000 != 0 {
-            flags.push(AccessFlag::Annotation);
+            flags.push(ClassFlag::Annotation);
         }
 
         if mask & 0x4000 != 0 {
-            flags.push(AccessFlag::Enum);
+            flags.push(ClassFlag::Enum);
         }
 
         if mask & 0x8000 != 0 {
-            flags.push(AccessFlag::Module);
+            flags.push(ClassFlag::Module);
         }
 
         ClassFileAccessFlags { flags }
     }
 }
 
+impl AccessFlags for ClassFileAccessFlags {
+    type Flag = ClassFlag;
+
+    fn flags(&self) -> &[ClassFlag] {
+        &self.flags
+    }
+}
+
+impl Flag for ClassFlag {
+    fn mask(&self) -> u16 {
+        match self {
+            ClassFlag::Public => 0x0001,
+            ClassFlag::Final => 0x0010,
+            ClassFlag::Super => 0x0020,
+            ClassFlag::Interface => 0x0200,
+            ClassFlag::Abstract => 0x0400,
+            ClassFlag::Synthetic => 0x1000,
+            ClassFlag::Annotation => 0x2000,
+            ClassFlag::Enum => 0x4000,
+            ClassFlag::Module => 0x8000,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ClassFlag::Public => "public",
+            ClassFlag::Final => "final",
+            ClassFlag::Super => "super",
+            ClassFlag::Interface => "interface",
+            ClassFlag::Abstract => "abstract",
+            ClassFlag::Synthetic => "synthetic",
+            ClassFlag::Annotation => "annotation",
+            ClassFlag::Enum => "enum",
+            ClassFlag::Module => "module",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -70,8 +140,27 @@ mod tests {
         let flags = ClassFileAccessFlags::new(mask).flags;
 
         assert_eq!(flags.len(), 3);
-        assert!(flags.contains(&AccessFlag::Public));
-        assert!(flags.contains(&AccessFlag::Final));
-        assert!(flags.contains(&AccessFlag::Super));
+        assert!(flags.contains(&ClassFlag::Public));
+        assert!(flags.contains(&ClassFlag::Final));
+        assert!(flags.contains(&ClassFlag::Super));
+    }
+
+    #[test]
+    fn mask_round_trip_test() {
+        let mask = 0x4631;
+        assert_eq!(ClassFileAccessFlags::new(mask).mask(), mask);
+    }
+
+    #[test]
+    fn contains_test() {
+        let flags = ClassFileAccessFlags::new(0x0601);
+        assert!(flags.contains(ClassFlag::Interface));
+        assert!(!flags.contains(ClassFlag::Final));
+    }
+
+    #[test]
+    fn names_test() {
+        let flags = ClassFileAccessFlags::new(0x4631);
+        assert_eq!(flags.names(), ["public", "final", "super", "interface", "abstract", "enum"]);
     }
 }
@@ -6,6 +6,7 @@ use crate::predefined_attributes::{
 };
 
 #[derive(Debug, Clone, From, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Attribute {
     ConstantValue(ConstantValue),
     Code(Code),
@@ -21,7 +22,28 @@ pub enum Attribute {
     BootstrapMethods(BootstrapMethods),
 }
 
+impl Attribute {
+    /// The name the attribute is stored under in the class file.
+    pub fn name(&self) -> &str {
+        match self {
+            Attribute::ConstantValue(_) => "ConstantValue",
+            Attribute::Code(_) => "Code",
+            Attribute::StackMapTable(_) => "StackMapTable",
+            Attribute::LineNumberTable(_) => "LineNumberTable",
+            Attribute::LocalVariableTable(_) => "LocalVariableTable",
+            Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+            Attribute::NestHost(_) => "NestHost",
+            Attribute::NestMembers(_) => "NestMembers",
+            Attribute::PermittedSubclasses(_) => "PermittedSubclasses",
+            Attribute::UserDefined(attribute) => attribute.name(),
+            Attribute::SourceFile(_) => "SourceFile",
+            Attribute::BootstrapMethods(_) => "BootstrapMethods",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UserDefinedAttribute {
     name: String,
     info: Vec<u8>,
@@ -31,4 +53,12 @@ impl UserDefinedAttribute {
     pub fn new(name: String, info: &[u8]) -> Self {
         UserDefinedAttribute { name, info: info.to_vec() }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn info(&self) -> &[u8] {
+        &self.info
+    }
 }
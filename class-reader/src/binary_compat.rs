@@ -0,0 +1,569 @@
+//! Binary compatibility between two versions of a library, following the rules of JLS
+//! chapter 13: changes after which classes compiled against the old version fail to link or
+//! behave differently against the new one.
+//!
+//! Only the API is checked: public classes, and their public and protected members. A member
+//! that moved to a supertype is still found, as the JVM resolves members through the
+//! hierarchy.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crate::access_flag::{AccessFlags, ClassFlag};
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::constant_pool::Constant;
+use crate::field::{self, Field, FieldAccessFlags};
+use crate::method::{Method, MethodAccessFlags, MethodFlag};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Severity {
+    /// Compatible, but worth knowing about.
+    Info,
+    /// Links, but may change behaviour, e.g. because clients inlined a constant.
+    Warning,
+    /// Existing clients may fail with a linkage error.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, strum_macros::IntoStaticStr)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[strum(serialize_all = "snake_case")]
+pub enum FindingKind {
+    ClassRemoved,
+    ClassAccessNarrowed,
+    ClassMadeFinal,
+    ClassMadeAbstract,
+    /// A class became an interface or the other way round.
+    ClassKindChanged,
+    SuperclassRemoved,
+    SuperinterfaceRemoved,
+    ClassSealed,
+    ClassUnsealed,
+    PermittedSubclassRemoved,
+    PermittedSubclassAdded,
+    FieldRemoved,
+    FieldTypeChanged,
+    FieldMadeFinal,
+    ConstantValueChanged,
+    MethodRemoved,
+    /// A method is gone, but one with the same name and other parameters exists.
+    MethodDescriptorChanged,
+    ReturnTypeChanged,
+    MethodMadeFinal,
+    MethodMadeAbstract,
+    AbstractMethodAdded,
+    MemberAccessNarrowed,
+    StaticChanged,
+}
+
+impl FindingKind {
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            FindingKind::ClassUnsealed => Severity::Info,
+            FindingKind::PermittedSubclassAdded
+            | FindingKind::ConstantValueChanged
+            | FindingKind::AbstractMethodAdded => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Finding {
+    pub severity: Severity,
+    pub kind: FindingKind,
+    /// The internal name of the class in the old version.
+    pub class: String,
+    /// The field or method as `name:descriptor`, `None` for findings about the class itself.
+    pub member: Option<String>,
+    pub message: String,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: [{}] {}", self.severity, self.kind.name(), self.class)?;
+        if let Some(member) = &self.member {
+            write!(f, ".{}", member)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompatibilityReport {
+    /// Findings grouped by class, in order of class name.
+    pub findings: Vec<Finding>,
+}
+
+impl CompatibilityReport {
+    /// The most severe finding, `None` if the versions are fully compatible.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|finding| finding.severity == severity).count()
+    }
+
+    /// Whether any finding is at least as severe as `threshold`, e.g. to fail a build.
+    pub fn has_findings(&self, threshold: Severity) -> bool {
+        self.max_severity().is_some_and(|severity| severity >= threshold)
+    }
+}
+
+impl Display for CompatibilityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for finding in &self.findings {
+            writeln!(f, "{}", finding)?;
+        }
+        writeln!(
+            f,
+            "{} errors, {} warnings, {} infos",
+            self.count(Severity::Error),
+            self.count(Severity::Warning),
+            self.count(Severity::Info)
+        )
+    }
+}
+
+/// Checks every API class of `old` against its counterpart in `new`. Classes are paired by
+/// name; supertypes missing from a side, such as JDK classes, end the hierarchy walk there.
+pub fn check_compatibility<'a, I, J>(old: I, new: J) -> CompatibilityReport
+where
+    I: IntoIterator<Item = &'a ClassFile>,
+    J: IntoIterator<Item = &'a ClassFile>,
+{
+    let old = Library::new(old);
+    let new = Library::new(new);
+    let mut checker = Checker { old: &old, new: &new, findings: Vec::new() };
+
+    let mut names = old.classes.keys().copied().collect::<Vec<_>>();
+    names.sort_unstable();
+    for name in names {
+        let class = old.classes[name];
+        if class.flags.contains(ClassFlag::Public) && name != "module-info" {
+            checker.check_class(class);
+        }
+    }
+    CompatibilityReport { findings: checker.findings }
+}
+
+struct Library<'a> {
+    classes: HashMap<&'a str, &'a ClassFile>,
+}
+
+impl<'a> Library<'a> {
+    fn new<I: IntoIterator<Item = &'a ClassFile>>(classes: I) -> Self {
+        Library {
+            classes: classes.into_iter().map(|class| (class.this_class.as_str(), class)).collect(),
+        }
+    }
+
+    /// The transitive superclasses of `class`, nearest first, including the first one that is
+    /// not part of the library.
+    fn superclasses(&self, class: &'a ClassFile) -> Vec<&'a str> {
+        let mut superclasses = Vec::new();
+        let mut current = class;
+        while let Some(superclass) = current.super_class.as_deref() {
+            if superclasses.contains(&superclass) {
+                break;
+            }
+            superclasses.push(superclass);
+            match self.classes.get(superclass) {
+                Some(class) => current = class,
+                None => break,
+            }
+        }
+        superclasses
+    }
+
+    /// The transitive superinterfaces of `class` and of its superclasses.
+    fn superinterfaces(&self, class: &'a ClassFile) -> BTreeSet<&'a str> {
+        let mut interfaces = BTreeSet::new();
+        let mut pending = vec![class];
+        pending.extend(self.superclasses(class).iter().filter_map(|name| self.classes.get(name)));
+        while let Some(class) = pending.pop() {
+            for interface in &class.interfaces {
+                if interfaces.insert(interface.as_str()) {
+                    pending.extend(self.classes.get(interface.as_str()));
+                }
+            }
+        }
+        interfaces
+    }
+
+    /// The supertypes of `class` in resolution order: the class itself, its superclasses and
+    /// then its superinterfaces, limited to those in the library.
+    fn resolution_order(&self, class: &'a ClassFile) -> Vec<&'a ClassFile> {
+        let mut order = vec![class];
+        let supertypes = self.superclasses(class).into_iter().chain(self.superinterfaces(class));
+        order.extend(supertypes.filter_map(|name| self.classes.get(name).copied()));
+        order
+    }
+
+    fn find_field(&self, class: &'a ClassFile, name: &str) -> Option<&'a Field> {
+        self.resolution_order(class)
+            .into_iter()
+            .find_map(|class| class.fields.iter().find(|field| field.name == name))
+    }
+
+    fn find_method(
+        &self,
+        class: &'a ClassFile,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<&'a Method> {
+        self.resolution_order(class).into_iter().find_map(|class| {
+            class.methods.iter().find(|method| {
+                method.name == name && method.type_descriptor.to_string() == descriptor
+            })
+        })
+    }
+}
+
+struct Checker<'a, 'l> {
+    old: &'l Library<'a>,
+    new: &'l Library<'a>,
+    findings: Vec<Finding>,
+}
+
+impl<'a> Checker<'a, '_> {
+    fn report(&mut self, kind: FindingKind, class: &str, member: Option<String>, message: String) {
+        self.findings.push(Finding {
+            severity: kind.severity(),
+            kind,
+            class: class.to_string(),
+            member,
+            message,
+        });
+    }
+
+    fn check_class(&mut self, old: &'a ClassFile) {
+        let name = old.this_class.as_str();
+        let Some(&new) = self.new.classes.get(name) else {
+            self.report(FindingKind::ClassRemoved, name, None, "class removed".to_string());
+            return;
+        };
+        let (old_flags, new_flags) = (&old.flags, &new.flags);
+        if !new_flags.contains(ClassFlag::Public) {
+            let message = "class is no longer public".to_string();
+            self.report(FindingKind::ClassAccessNarrowed, name, None, message);
+            return;
+        }
+        let is_interface = old_flags.contains(ClassFlag::Interface);
+        if is_interface != new_flags.contains(ClassFlag::Interface) {
+            let message = match is_interface {
+                true => "interface became a class",
+                false => "class became an interface",
+            };
+            self.report(FindingKind::ClassKindChanged, name, None, message.to_string());
+            return;
+        }
+        if !is_interface && added(old_flags, new_flags, ClassFlag::Final) {
+            let message = "class became final, subclasses fail to load".to_string();
+            self.report(FindingKind::ClassMadeFinal, name, None, message);
+        }
+        if !is_interface && added(old_flags, new_flags, ClassFlag::Abstract) {
+            let message = "class became abstract, instantiations fail".to_string();
+            self.report(FindingKind::ClassMadeAbstract, name, None, message);
+        }
+
+        self.check_supertypes(old, new);
+        self.check_permitted_subclasses(old, new);
+        self.check_fields(old, new);
+        self.check_methods(old, new);
+    }
+
+    fn check_supertypes(&mut self, old: &'a ClassFile, new: &'a ClassFile) {
+        let name = old.this_class.as_str();
+        let new_superclasses = self.new.superclasses(new);
+        for superclass in self.old.superclasses(old) {
+            if !new_superclasses.contains(&superclass) {
+                let message = format!("{} is no longer a superclass", superclass);
+                self.report(FindingKind::SuperclassRemoved, name, None, message);
+            }
+        }
+        let new_interfaces = self.new.superinterfaces(new);
+        for interface in self.old.superinterfaces(old) {
+            if !new_interfaces.contains(interface) {
+                let message = format!("{} is no longer a superinterface", interface);
+                self.report(FindingKind::SuperinterfaceRemoved, name, None, message);
+            }
+        }
+    }
+
+    fn check_permitted_subclasses(&mut self, old: &ClassFile, new: &ClassFile) {
+        let name = old.this_class.as_str();
+        match (permitted_subclasses(old), permitted_subclasses(new)) {
+            (None, Some(_)) => {
+                let message = "class became sealed, other subclasses fail to load".to_string();
+                self.report(FindingKind::ClassSealed, name, None, message);
+            }
+            (Some(_), None) => {
+                let message = "class is no longer sealed".to_string();
+                self.report(FindingKind::ClassUnsealed, name, None, message);
+            }
+            (Some(old_permitted), Some(new_permitted)) => {
+                for subclass in old_permitted.difference(&new_permitted) {
+                    let message = format!("{} is no longer a permitted subclass", subclass);
+                    self.report(FindingKind::PermittedSubclassRemoved, name, None, message);
+                }
+                for subclass in new_permitted.difference(&old_permitted) {
+                    let message = format!(
+                        "{} became a permitted subclass, exhaustive switches may not cover it",
+                        subclass
+                    );
+                    self.report(FindingKind::PermittedSubclassAdded, name, None, message);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn check_fields(&mut self, old: &'a ClassFile, new: &'a ClassFile) {
+        let name = old.this_class.as_str();
+        let class_is_final = old.flags.contains(ClassFlag::Final);
+        for old_field in &old.fields {
+            let old_flags = &old_field.flags;
+            if !is_api(Access::of_field(old_flags), class_is_final) {
+                continue;
+            }
+            let member = Some(format!("{}:{}", old_field.name, old_field.type_descriptor));
+            let Some(new_field) = self.new.find_field(new, &old_field.name) else {
+                self.report(FindingKind::FieldRemoved, name, member, "field removed".to_string());
+                continue;
+            };
+            if new_field.type_descriptor != old_field.type_descriptor {
+                let message = format!("field type changed to {}", new_field.type_descriptor);
+                self.report(FindingKind::FieldTypeChanged, name, member, message);
+                continue;
+            }
+            let new_flags = &new_field.flags;
+            let (old_access, new_access) =
+                (Access::of_field(old_flags), Access::of_field(new_flags));
+            if let Some(message) = narrowed_access(old_access, new_access) {
+                self.report(FindingKind::MemberAccessNarrowed, name, member.clone(), message);
+            }
+            if old_flags.is_static() != new_flags.is_static() {
+                let message = static_message(new_flags.is_static());
+                self.report(FindingKind::StaticChanged, name, member.clone(), message);
+            }
+            if added(old_flags, new_flags, field::AccessFlag::Final) {
+                let message = "field became final, writes fail".to_string();
+                self.report(FindingKind::FieldMadeFinal, name, member.clone(), message);
+            }
+            let old_value = constant_value(old, old_field);
+            let new_value = constant_value(new, new_field);
+            if old_value.is_some() && old_value != new_value {
+                let message = format!(
+                    "constant changed from {} to {}, clients keep the inlined old value",
+                    old_value.unwrap_or_default(),
+                    new_value.unwrap_or_else(|| "a non-constant".to_string())
+                );
+                self.report(FindingKind::ConstantValueChanged, name, member, message);
+            }
+        }
+    }
+
+    fn check_methods(&mut self, old: &'a ClassFile, new: &'a ClassFile) {
+        let name = old.this_class.as_str();
+        let class_is_final = old.flags.contains(ClassFlag::Final);
+        for old_method in &old.methods {
+            let old_flags = &old_method.flags;
+            if !is_api(Access::of_method(old_flags), class_is_final)
+                || old_method.name == "<clinit>"
+            {
+                continue;
+            }
+            let descriptor = old_method.type_descriptor.to_string();
+            let member = Some(format!("{}:{}", old_method.name, descriptor));
+            // Constructors are not inherited.
+            let new_method = match old_method.name.as_str() {
+                "<init>" => new.methods.iter().find(|method| {
+                    method.name == "<init>" && method.type_descriptor.to_string() == descriptor
+                }),
+                _ => self.new.find_method(new, &old_method.name, &descriptor),
+            };
+            let Some(new_method) = new_method else {
+                let (kind, message) = self.missing_method(old_method, new);
+                self.report(kind, name, member, message);
+                continue;
+            };
+            let new_flags = &new_method.flags;
+            let (old_access, new_access) =
+                (Access::of_method(old_flags), Access::of_method(new_flags));
+            if let Some(message) = narrowed_access(old_access, new_access) {
+                self.report(FindingKind::MemberAccessNarrowed, name, member.clone(), message);
+            }
+            if old_flags.is_static() != new_flags.is_static() {
+                let message = static_message(new_flags.is_static());
+                self.report(FindingKind::StaticChanged, name, member.clone(), message);
+            }
+            if !class_is_final && added(old_flags, new_flags, MethodFlag::Final) {
+                let message = "method became final, overriding subclasses fail to load";
+                self.report(
+                    FindingKind::MethodMadeFinal,
+                    name,
+                    member.clone(),
+                    message.to_string(),
+                );
+            }
+            if added(old_flags, new_flags, MethodFlag::Abstract) {
+                let message = "method became abstract, invocations may fail";
+                self.report(FindingKind::MethodMadeAbstract, name, member, message.to_string());
+            }
+        }
+
+        for new_method in &new.methods {
+            let flags = &new_method.flags;
+            let descriptor = new_method.type_descriptor.to_string();
+            let declared_before = old.methods.iter().any(|method| {
+                method.name == new_method.name && method.type_descriptor.to_string() == descriptor
+            });
+            if flags.contains(MethodFlag::Abstract)
+                && is_api(Access::of_method(flags), class_is_final)
+                && !declared_before
+            {
+                let member = Some(format!("{}:{}", new_method.name, descriptor));
+                let message = "abstract method added, existing implementations lack it";
+                self.report(FindingKind::AbstractMethodAdded, name, member, message.to_string());
+            }
+        }
+    }
+
+    /// Tells a removed method apart from one whose descriptor changed.
+    fn missing_method(&self, old_method: &Method, new: &ClassFile) -> (FindingKind, String) {
+        let candidates = new
+            .methods
+            .iter()
+            .filter(|method| {
+                method.name == old_method.name && !method.flags.contains(MethodFlag::Private)
+            })
+            .collect::<Vec<_>>();
+        let old_parameters = old_method.type_descriptor.parameters();
+        if let Some(method) =
+            candidates.iter().find(|method| method.type_descriptor.parameters() == old_parameters)
+        {
+            let message =
+                format!("return type changed to {}", method.type_descriptor.return_type());
+            return (FindingKind::ReturnTypeChanged, message);
+        }
+        if !candidates.is_empty() {
+            let descriptors = candidates
+                .iter()
+                .map(|method| method.type_descriptor.to_string())
+                .collect::<Vec<_>>();
+            let message = format!("descriptor changed, now {}", descriptors.join(", "));
+            return (FindingKind::MethodDescriptorChanged, message);
+        }
+        (FindingKind::MethodRemoved, "method removed".to_string())
+    }
+}
+
+fn added<F: AccessFlags>(old_flags: &F, new_flags: &F, flag: F::Flag) -> bool {
+    !old_flags.contains(flag) && new_flags.contains(flag)
+}
+
+/// The access level of a member, from the narrowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Access {
+    Private,
+    PackagePrivate,
+    Protected,
+    Public,
+}
+
+impl Access {
+    fn of_field(flags: &FieldAccessFlags) -> Self {
+        match flags {
+            _ if flags.contains(field::AccessFlag::Public) => Access::Public,
+            _ if flags.contains(field::AccessFlag::Protected) => Access::Protected,
+            _ if flags.contains(field::AccessFlag::Private) => Access::Private,
+            _ => Access::PackagePrivate,
+        }
+    }
+
+    fn of_method(flags: &MethodAccessFlags) -> Self {
+        match flags {
+            _ if flags.contains(MethodFlag::Public) => Access::Public,
+            _ if flags.contains(MethodFlag::Protected) => Access::Protected,
+            _ if flags.contains(MethodFlag::Private) => Access::Private,
+            _ => Access::PackagePrivate,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Access::Private => "private",
+            Access::PackagePrivate => "package-private",
+            Access::Protected => "protected",
+            Access::Public => "public",
+        }
+    }
+}
+
+/// Public members, and protected members subclasses can see.
+fn is_api(access: Access, class_is_final: bool) -> bool {
+    access == Access::Public || (access == Access::Protected && !class_is_final)
+}
+
+fn narrowed_access(old_access: Access, new_access: Access) -> Option<String> {
+    (new_access < old_access)
+        .then(|| format!("access narrowed from {} to {}", old_access.name(), new_access.name()))
+}
+
+fn static_message(is_static: bool) -> String {
+    match is_static {
+        false => "member is no longer static".to_string(),
+        true => "member became static".to_string(),
+    }
+}
+
+fn permitted_subclasses(class: &ClassFile) -> Option<BTreeSet<&str>> {
+    class.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::PermittedSubclasses(attribute) => {
+            Some(attribute.names.iter().map(String::as_str).collect())
+        }
+        _ => None,
+    })
+}
+
+/// The `ConstantValue` of a static final field, which compilers inline into clients.
+fn constant_value(class: &ClassFile, field: &Field) -> Option<String> {
+    if !field.flags.is_static() || !field.flags.contains(field::AccessFlag::Final) {
+        return None;
+    }
+    let value = field.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::ConstantValue(attribute) => Some(&attribute.value),
+        _ => None,
+    })?;
+    Some(match value {
+        Constant::StringIndex(index) => format!("{:?}", class.constant_pool.utf8(*index as usize)?),
+        Constant::Integer(value) => value.to_string(),
+        Constant::Long(value) => format!("{}L", value),
+        Constant::Float(value) => format!("{}f", value),
+        Constant::Double(value) => format!("{}d", value),
+        constant => format!("{:?}", constant),
+    })
+}
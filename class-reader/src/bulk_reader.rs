@@ -0,0 +1,236 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use thiserror::Error;
+
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+use crate::jar_file::{JarError, JarFile};
+
+#[derive(Debug, Error)]
+pub enum BulkError {
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Error reading archive: {0}")]
+    #[non_exhaustive]
+    Archive(#[from] JarError),
+    #[error("{0}")]
+    #[non_exhaustive]
+    Class(#[from] ContextualError),
+}
+
+impl BulkError {
+    /// The [`ClassReaderError`](crate::class_file_reader::ClassReaderError) kind for parse
+    /// failures, `Io` or `Archive` for entries that could not be read at all.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BulkError::Io(_) => "Io",
+            BulkError::Archive(_) => "Archive",
+            BulkError::Class(err) => err.error().kind(),
+        }
+    }
+}
+
+/// Where a class was read from: a class file, or an entry of a jar.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntryLocation {
+    pub path: PathBuf,
+    /// The internal name of the class inside a jar, `None` for class files.
+    pub entry: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ParsedClass {
+    pub location: EntryLocation,
+    pub class: ClassFile,
+}
+
+#[derive(Debug)]
+pub struct ParseFailure {
+    pub location: EntryLocation,
+    pub error: BulkError,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BulkStats {
+    pub classes: usize,
+    /// Parsed classes by `(major, minor)` class file version.
+    pub versions: BTreeMap<(u16, u16), usize>,
+    /// Failures by [`BulkError::kind`].
+    pub failures: BTreeMap<&'static str, usize>,
+}
+
+impl BulkStats {
+    pub fn failure_count(&self) -> usize {
+        self.failures.values().sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BulkResult {
+    /// Parsed classes in the order their sources were added, then by path or entry name.
+    pub classes: Vec<ParsedClass>,
+    pub failures: Vec<ParseFailure>,
+    pub stats: BulkStats,
+}
+
+/// Parses every class of a set of directories, jars and class files across threads.
+///
+/// Failing entries are collected into [`BulkResult::failures`] instead of stopping the run.
+#[derive(Debug, Clone, Default)]
+pub struct BulkReader {
+    paths: Vec<PathBuf>,
+    threads: Option<NonZeroUsize>,
+}
+
+enum Job {
+    File(PathBuf),
+    JarEntry(usize, String),
+}
+
+impl BulkReader {
+    pub fn new() -> Self {
+        BulkReader::default()
+    }
+
+    pub fn add_path<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Limits the number of worker threads, which defaults to the available parallelism.
+    pub fn threads(&mut self, threads: NonZeroUsize) -> &mut Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    pub fn read(&self) -> BulkResult {
+        let mut failures = Vec::new();
+        let (jobs, jars) = self.collect_jobs(&mut failures);
+
+        let threads = self
+            .threads
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+            .min(jobs.len().max(1));
+        let next_job = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(jobs.len()));
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let mut open_jars = HashMap::new();
+                    let mut local = Vec::new();
+                    loop {
+                        let index = next_job.fetch_add(1, Ordering::Relaxed);
+                        let Some(job) = jobs.get(index) else {
+                            break;
+                        };
+                        local.push((index, run_job(job, &jars, &mut open_jars)));
+                    }
+                    results.lock().unwrap().extend(local);
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable_by_key(|(index, _)| *index);
+
+        let mut result = BulkResult::default();
+        for (index, outcome) in results {
+            let location = match &jobs[index] {
+                Job::File(path) => EntryLocation { path: path.clone(), entry: None },
+                Job::JarEntry(jar, entry) => {
+                    EntryLocation { path: jars[*jar].clone(), entry: Some(entry.clone()) }
+                }
+            };
+            match outcome {
+                Ok(class) => result.classes.push(ParsedClass { location, class }),
+                Err(error) => failures.push(ParseFailure { location, error }),
+            }
+        }
+        result.failures = failures;
+
+        result.stats.classes = result.classes.len();
+        for parsed in &result.classes {
+            let version = (parsed.class.version.major(), parsed.class.version.minor());
+            *result.stats.versions.entry(version).or_default() += 1;
+        }
+        for failure in &result.failures {
+            *result.stats.failures.entry(failure.error.kind()).or_default() += 1;
+        }
+        result
+    }
+
+    /// Expands the configured paths into one job per class, listing directories and jar
+    /// indexes up front so that the work can be shared evenly between threads.
+    fn collect_jobs(&self, failures: &mut Vec<ParseFailure>) -> (Vec<Job>, Vec<PathBuf>) {
+        let mut jobs = Vec::new();
+        let mut jars = Vec::new();
+        let mut pending =
+            self.paths.iter().rev().map(|path| (path.clone(), path.is_dir())).collect::<Vec<_>>();
+        while let Some((path, is_dir)) = pending.pop() {
+            let location = EntryLocation { path: path.clone(), entry: None };
+            if is_dir {
+                match list_directory(&path) {
+                    Ok(children) => pending.extend(children.into_iter().rev()),
+                    Err(err) => failures.push(ParseFailure { location, error: err.into() }),
+                }
+                continue;
+            }
+            match path.extension().and_then(OsStr::to_str) {
+                Some("jar" | "zip") => match JarFile::open(&path) {
+                    Ok(jar) => {
+                        let names = jar.class_names();
+                        jobs.extend(
+                            names
+                                .into_iter()
+                                .map(|name| Job::JarEntry(jars.len(), name.to_string())),
+                        );
+                        jars.push(path);
+                    }
+                    Err(err) => failures.push(ParseFailure { location, error: err.into() }),
+                },
+                Some("class") => jobs.push(Job::File(path)),
+                _ => {}
+            }
+        }
+        (jobs, jars)
+    }
+}
+
+/// The entries of a directory in path order, and whether each is a directory. Symbolic links
+/// to directories are not followed, as they may loop.
+fn list_directory(path: &Path) -> std::io::Result<Vec<(PathBuf, bool)>> {
+    let mut children = fs::read_dir(path)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?.is_dir()))))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    children.sort_unstable();
+    Ok(children)
+}
+
+fn run_job(
+    job: &Job,
+    jars: &[PathBuf],
+    open_jars: &mut HashMap<usize, JarFile<BufReader<File>>>,
+) -> Result<ClassFile, BulkError> {
+    let bytes = match job {
+        Job::File(path) => fs::read(path)?,
+        Job::JarEntry(index, name) => {
+            let jar = match open_jars.entry(*index) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(JarFile::open(&jars[*index])?),
+            };
+            jar.class_bytes(name)?.ok_or_else(|| JarError::EntryNotFound(name.clone()))?
+        }
+    };
+    Ok(ClassFileReader::read_class(&bytes)?)
+}
@@ -76,11 +76,15 @@ impl<'a> ByteReader<'a> {
         Ok(f64::from_be_bytes(slice.try_into().unwrap()))
     }
 
-    pub fn read_utf8(&mut self, len: u32) -> Result<Cow<str>> {
+    pub fn read_utf8(&mut self, len: u32) -> Result<Cow<'a, str>> {
         let modified_utf_bytes = self.read_bytes(len as usize)?;
         cesu8::from_java_cesu8(modified_utf_bytes).map_err(ReadError::Cesu8DecodingError)
     }
 
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
     pub fn snippet(&self) -> Vec<u8> {
         let start = if self.prev_pos > 1 { self.prev_pos - 2 } else { 0 };
         let end = if self.pos + 2 <= self.buf.len() { self.pos + 1 } else { self.buf.len() - 1 };
@@ -0,0 +1,95 @@
+#[derive(Debug, Default, Clone)]
+pub struct ByteWriter {
+    buf: Vec<u8>,
+}
+
+impl ByteWriter {
+    pub fn new() -> Self {
+        ByteWriter { buf: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    pub fn write_pair_u16(&mut self, (first, second): (u16, u16)) {
+        self.write_u16(first);
+        self.write_u16(second);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    pub fn write_i64(&mut self, value: i64) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&value.to_be_bytes());
+    }
+
+    /// Writes the length-prefixed modified UTF-8 encoding of `string`.
+    pub fn write_utf8(&mut self, string: &str) {
+        let modified_utf_bytes = cesu8::to_java_cesu8(string);
+        self.write_u16(modified_utf_bytes.len() as u16);
+        self.write_bytes(&modified_utf_bytes);
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ByteWriter;
+    use crate::byte_reader::ByteReader;
+
+    #[test]
+    fn test_magic_success() {
+        let mut writer = ByteWriter::new();
+        writer.write_u32(0xCAFEBABE);
+
+        assert_eq!(writer.into_bytes(), [0xCA, 0xFE, 0xBA, 0xBE]);
+    }
+
+    #[test]
+    fn test_utf8_round_trip() {
+        let mut writer = ByteWriter::new();
+        writer.write_utf8("nul\0 and \u{1F600}");
+        let bytes = writer.into_bytes();
+
+        let mut reader = ByteReader::new(&bytes);
+        let length = reader.read_u16().unwrap();
+        assert_eq!(reader.read_utf8(length as u32).unwrap(), "nul\0 and \u{1F600}");
+    }
+}
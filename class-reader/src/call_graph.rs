@@ -0,0 +1,454 @@
+//! Call graphs built from the invoke instructions of method bodies.
+//!
+//! Virtual and interface calls are resolved against a [`ClassHierarchy`]: with class
+//! hierarchy analysis every concrete subtype of the static receiver type is a possible
+//! receiver, with rapid type analysis only the types instantiated by reachable code are.
+//! Calls to classes outside the analysed set keep their symbolic target, so calls into a
+//! library show up even when the library itself was not analysed.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter, Write};
+
+use crate::access_flag::{AccessFlags, ClassFlag};
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::class_hierarchy::{ClassHierarchy, ClassNode};
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::instruction::Instruction;
+use crate::predefined_attributes::{BootstrapMethod, Code};
+
+const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+
+/// A method named by its owner's internal name, its name and its descriptor.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MethodId {
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodId {
+    pub fn new(owner: &str, name: &str, descriptor: &str) -> Self {
+        MethodId {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        }
+    }
+
+    /// Parses the `owner.name:descriptor` form produced by `Display`, e.g.
+    /// `java/io/PrintStream.println:(I)V`.
+    pub fn parse(method: &str) -> Option<Self> {
+        let (qualified_name, descriptor) = method.split_once(':')?;
+        let (owner, name) = qualified_name.rsplit_once('.')?;
+        Some(MethodId::new(owner, name, descriptor))
+    }
+}
+
+impl Display for MethodId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}:{}", self.owner, self.name, self.descriptor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CallKind {
+    Virtual,
+    Interface,
+    Special,
+    Static,
+    /// A lambda or method reference created by `invokedynamic`, pointing at its implementation.
+    Lambda,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallEdge {
+    pub caller: MethodId,
+    pub callee: MethodId,
+    pub kind: CallKind,
+    /// The offset of the call site in the caller's code.
+    pub pc: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Precision {
+    /// Class hierarchy analysis: any concrete subtype of the receiver type may be called.
+    #[default]
+    Cha,
+    /// Rapid type analysis: only subtypes instantiated by reachable code may be called.
+    Rta,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallGraph {
+    /// The methods whose bodies were analysed, i.e. the reachable methods.
+    pub methods: BTreeSet<MethodId>,
+    /// Call edges ordered by caller, callee and call site.
+    pub edges: Vec<CallEdge>,
+}
+
+impl CallGraph {
+    /// The calls of `callee`, e.g. to find the users of a deprecated method.
+    pub fn callers(&self, callee: &MethodId) -> Vec<&CallEdge> {
+        self.edges.iter().filter(|edge| edge.callee == *callee).collect()
+    }
+
+    pub fn callees(&self, caller: &MethodId) -> Vec<&CallEdge> {
+        self.edges.iter().filter(|edge| edge.caller == *caller).collect()
+    }
+
+    /// Graphviz source with one node per method and one edge per caller and callee pair.
+    /// Lambda edges are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph calls {\n    node [shape=box];\n");
+        let mut seen = BTreeSet::new();
+        for edge in &self.edges {
+            if seen.insert((&edge.caller, &edge.callee, edge.kind == CallKind::Lambda)) {
+                let style = match edge.kind {
+                    CallKind::Lambda => " [style=dashed]",
+                    _ => "",
+                };
+                let caller = edge.caller.to_string();
+                let callee = edge.callee.to_string();
+                writeln!(dot, "    {:?} -> {:?}{};", caller, callee, style).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Display for CallGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for edge in &self.edges {
+            writeln!(f, "{} -> {} ({:?} at pc {})", edge.caller, edge.callee, edge.kind, edge.pc)?;
+        }
+        Ok(())
+    }
+}
+
+/// A call site whose targets depend on the receiver type.
+struct VirtualSite {
+    caller: MethodId,
+    kind: CallKind,
+    method: MethodId,
+    pc: u32,
+}
+
+/// Builds a [`CallGraph`] over a set of classes.
+pub struct CallGraphBuilder<'a> {
+    /// The methods with a body, with their class.
+    bodies: HashMap<MethodId, (&'a ClassFile, &'a Code)>,
+    hierarchy: ClassHierarchy,
+    precision: Precision,
+    entry_points: Vec<MethodId>,
+}
+
+impl<'a> CallGraphBuilder<'a> {
+    pub fn new<I: IntoIterator<Item = &'a ClassFile>>(classes: I) -> Self {
+        let mut bodies = HashMap::new();
+        let mut hierarchy = ClassHierarchy::new();
+        for class in classes {
+            hierarchy.add_class(class);
+            for method in &class.methods {
+                let Some(code) = method.code() else {
+                    continue;
+                };
+                let descriptor = method.type_descriptor.to_string();
+                let id = MethodId::new(&class.this_class, &method.name, &descriptor);
+                bodies.insert(id, (class, code));
+            }
+        }
+        CallGraphBuilder {
+            bodies,
+            hierarchy,
+            precision: Precision::default(),
+            entry_points: Vec::new(),
+        }
+    }
+
+    /// Adds classes, e.g. of the JDK, used to resolve supertypes without being analysed.
+    pub fn library_classes<'c, I: IntoIterator<Item = &'c ClassFile>>(
+        &mut self,
+        classes: I,
+    ) -> &mut Self {
+        for class in classes {
+            self.hierarchy.add_class(class);
+        }
+        self
+    }
+
+    pub fn precision(&mut self, precision: Precision) -> &mut Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Adds a method analysis starts from. Without entry points, every method with a body is
+    /// one.
+    pub fn entry_point(&mut self, method: MethodId) -> &mut Self {
+        self.entry_points.push(method);
+        self
+    }
+
+    pub fn build(&self) -> CallGraph {
+        let mut state = State {
+            builder: self,
+            methods: BTreeSet::new(),
+            worklist: Vec::new(),
+            instantiated: BTreeSet::new(),
+            virtual_sites: HashMap::new(),
+            edges: BTreeSet::new(),
+        };
+        match self.entry_points.is_empty() {
+            true => {
+                let mut methods = self.bodies.keys().collect::<Vec<_>>();
+                methods.sort_unstable();
+                methods.into_iter().for_each(|method| state.reach(method.clone()));
+            }
+            false => self.entry_points.iter().for_each(|method| state.reach(method.clone())),
+        }
+
+        while let Some(method) = state.worklist.pop() {
+            state.scan(&method);
+        }
+        CallGraph { methods: state.methods, edges: state.edges.into_iter().collect() }
+    }
+
+    fn code(&self, method: &MethodId) -> Option<(&'a ClassFile, &'a Code)> {
+        self.bodies.get(method).copied()
+    }
+
+    /// The declaration a symbolic reference resolves to, or the reference itself if it leads
+    /// outside the known classes.
+    fn resolve(&self, method: &MethodId) -> MethodId {
+        match self.hierarchy.resolve_method(&method.owner, &method.name, &method.descriptor) {
+            Ok(Some(owner)) => MethodId::new(owner, &method.name, &method.descriptor),
+            _ => method.clone(),
+        }
+    }
+}
+
+struct State<'b, 'a> {
+    builder: &'b CallGraphBuilder<'a>,
+    methods: BTreeSet<MethodId>,
+    worklist: Vec<MethodId>,
+    instantiated: BTreeSet<String>,
+    /// With rapid type analysis, the virtual call sites scanned so far by the type they are
+    /// called on, which the types instantiated later add targets to.
+    virtual_sites: HashMap<String, Vec<VirtualSite>>,
+    edges: BTreeSet<CallEdge>,
+}
+
+impl State<'_, '_> {
+    /// Queues a method for scanning the first time it is reached, if its body is known.
+    fn reach(&mut self, method: MethodId) {
+        if self.builder.code(&method).is_some() && self.methods.insert(method.clone()) {
+            self.worklist.push(method);
+        }
+    }
+
+    fn call(&mut self, caller: &MethodId, callee: MethodId, kind: CallKind, pc: u32) {
+        self.reach(callee.clone());
+        self.edges.insert(CallEdge { caller: caller.clone(), callee, kind, pc });
+    }
+
+    fn scan(&mut self, caller: &MethodId) {
+        let Some((class, code)) = self.builder.code(caller) else {
+            return;
+        };
+        let pool = &class.constant_pool;
+        for (instruction, pc) in &code.code {
+            let (kind, index) = match instruction {
+                Instruction::Invokevirtual(index) => (CallKind::Virtual, *index),
+                Instruction::Invokeinterface(index, _) => (CallKind::Interface, *index),
+                Instruction::Invokespecial(index) => (CallKind::Special, *index),
+                Instruction::Invokestatic(index) => (CallKind::Static, *index),
+                Instruction::Invokedynamic(index) => {
+                    self.scan_lambda(caller, class, *index, *pc);
+                    continue;
+                }
+                Instruction::New(index) => {
+                    if let Some(name) = pool.class_name(*index as usize) {
+                        self.instantiate(name);
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let Some(method) = method_ref(pool, index) else {
+                continue;
+            };
+            match kind {
+                CallKind::Virtual | CallKind::Interface => {
+                    self.virtual_call(VirtualSite {
+                        caller: caller.clone(),
+                        kind,
+                        method,
+                        pc: *pc,
+                    });
+                }
+                _ => {
+                    let callee = self.builder.resolve(&method);
+                    self.call(caller, callee, kind, *pc);
+                }
+            }
+        }
+    }
+
+    /// Links a lambda or method reference to the method implementing it, taken from the
+    /// `LambdaMetafactory` bootstrap arguments.
+    fn scan_lambda(&mut self, caller: &MethodId, class: &ClassFile, index: u16, pc: u32) {
+        let pool = &class.constant_pool;
+        let Ok(Constant::InvokeDynamic(bootstrap_index, _)) = pool.get(index as usize) else {
+            return;
+        };
+        let Some(bootstrap) = bootstrap_methods(class).get(*bootstrap_index as usize) else {
+            return;
+        };
+        let Some((_, factory)) = method_handle(pool, bootstrap.bootstrap_method_ref) else {
+            return;
+        };
+        if factory.owner != "java/lang/invoke/LambdaMetafactory" {
+            return;
+        }
+        let implementation = bootstrap.bootstrap_arguments.get(1);
+        let Some((kind, target)) = implementation.and_then(|index| method_handle(pool, *index))
+        else {
+            return;
+        };
+        if kind == REF_NEW_INVOKE_SPECIAL {
+            self.instantiate(&target.owner);
+        }
+        let callee = self.builder.resolve(&target);
+        self.call(caller, callee, CallKind::Lambda, pc);
+    }
+
+    /// Adds the targets of a virtual call site for the receivers known so far. With rapid type
+    /// analysis, the site is kept for the types instantiated later.
+    fn virtual_call(&mut self, site: VirtualSite) {
+        for callee in self.targets(&site.method) {
+            self.call(&site.caller, callee, site.kind, site.pc);
+        }
+        let owner = &site.method.owner;
+        if self.builder.precision == Precision::Rta && self.builder.hierarchy.contains(owner) {
+            self.virtual_sites.entry(owner.clone()).or_default().push(site);
+        }
+    }
+
+    /// Records a type created by reachable code. With rapid type analysis, a concrete type
+    /// adds its implementations to the virtual call sites scanned so far on its supertypes.
+    fn instantiate(&mut self, name: &str) {
+        if !self.instantiated.insert(name.to_string()) || self.builder.precision == Precision::Cha {
+            return;
+        }
+        let hierarchy = &self.builder.hierarchy;
+        let Some(node) = hierarchy.get(name) else {
+            return;
+        };
+        if !is_instantiable(node) {
+            return;
+        }
+        // Supertypes missing from the hierarchy hide theirs, as they do from `subtypes`.
+        let mut supertypes = BTreeSet::from([name]);
+        let mut pending = vec![node];
+        while let Some(node) = pending.pop() {
+            for supertype in node.direct_supertypes() {
+                if supertypes.insert(supertype) {
+                    pending.extend(hierarchy.get(supertype));
+                }
+            }
+        }
+        let mut calls = Vec::new();
+        for supertype in supertypes {
+            for site in self.virtual_sites.get(supertype).into_iter().flatten() {
+                let method = &site.method;
+                let selected = hierarchy.select_method(name, &method.name, &method.descriptor);
+                if let Ok(Some(owner)) = selected {
+                    let callee = MethodId::new(owner, &method.name, &method.descriptor);
+                    calls.push((site.caller.clone(), callee, site.kind, site.pc));
+                }
+            }
+        }
+        for (caller, callee, kind, pc) in calls {
+            self.call(&caller, callee, kind, pc);
+        }
+    }
+
+    fn targets(&self, method: &MethodId) -> BTreeSet<MethodId> {
+        let hierarchy = &self.builder.hierarchy;
+        if !hierarchy.contains(&method.owner) {
+            return BTreeSet::from([method.clone()]);
+        }
+        let mut receivers = hierarchy.subtypes(&method.owner);
+        receivers.insert(&method.owner);
+        let targets = receivers
+            .into_iter()
+            .filter(|receiver| {
+                hierarchy.get(receiver).is_some_and(|node| {
+                    is_instantiable(node)
+                        && (self.builder.precision == Precision::Cha
+                            || self.instantiated.contains(*receiver))
+                })
+            })
+            .filter_map(|receiver| {
+                hierarchy.select_method(receiver, &method.name, &method.descriptor).ok().flatten()
+            })
+            .map(|owner| MethodId::new(owner, &method.name, &method.descriptor))
+            .collect::<BTreeSet<_>>();
+        match (targets.is_empty(), self.builder.precision) {
+            (true, Precision::Cha) => BTreeSet::from([self.builder.resolve(method)]),
+            _ => targets,
+        }
+    }
+}
+
+/// Whether the class can have instances of its own, i.e. is neither an interface nor abstract.
+fn is_instantiable(node: &ClassNode) -> bool {
+    !node.is_interface() && !node.flags.contains(ClassFlag::Abstract)
+}
+
+fn method_ref(pool: &ConstantPool, index: u16) -> Option<MethodId> {
+    let (owner, name, descriptor) = pool.member_ref(index as usize)?;
+    Some(MethodId::new(owner, name, descriptor))
+}
+
+fn method_handle(pool: &ConstantPool, index: u16) -> Option<(u8, MethodId)> {
+    match pool.get(index as usize) {
+        Ok(Constant::MethodHandle(kind, reference)) => Some((*kind, method_ref(pool, *reference)?)),
+        _ => None,
+    }
+}
+
+fn bootstrap_methods(class: &ClassFile) -> &[BootstrapMethod] {
+    class
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(attribute) => Some(&attribute.bootstrap_methods[..]),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MethodId;
+
+    #[test]
+    fn test_method_id_parse() {
+        let method = MethodId::parse("java/io/PrintStream.println:(Ljava/lang/String;)V").unwrap();
+        assert_eq!(
+            method,
+            MethodId::new("java/io/PrintStream", "println", "(Ljava/lang/String;)V")
+        );
+        assert_eq!(method.to_string(), "java/io/PrintStream.println:(Ljava/lang/String;)V");
+        assert_eq!(MethodId::parse("missing.descriptor"), None);
+    }
+}
@@ -0,0 +1,1186 @@
+//! Structural comparison of two versions of a class, or of two sets of classes such as jars.
+//!
+//! Everything is compared in resolved form: instructions, attributes and exception handlers
+//! are rendered with their constant pool references replaced by the symbols they name, and
+//! code offsets are replaced by instruction indices. Reordering the constant pool, or a
+//! `ldc_w` turning into `ldc`, therefore does not show up as a change.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::access_flag::AccessFlags;
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::constant_pool::{Constant, ConstantPool, reference_kind_name};
+use crate::field::Field;
+use crate::instruction::{Instruction, WideInstruction};
+use crate::method::Method;
+use crate::predefined_attributes::{BootstrapMethod, Code, StackMapFrame, VerificationTypeInfo};
+
+/// Attributes that only carry debugging information, skipped with
+/// [`ClassDiffer::ignore_debug_info`].
+const DEBUG_ATTRIBUTES: [&str; 5] = [
+    "LineNumberTable",
+    "LocalVariableTable",
+    "LocalVariableTypeTable",
+    "SourceFile",
+    "SourceDebugExtension",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValueChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: PartialEq> ValueChange<T> {
+    fn between(old: T, new: T) -> Option<Self> {
+        (old != new).then_some(ValueChange { old, new })
+    }
+}
+
+/// The differences between two versions of a class; empty if they are equivalent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassDiff {
+    /// The internal name of the new version of the class.
+    pub name: String,
+    pub version: Option<ValueChange<String>>,
+    pub flags: Option<ValueChange<Vec<String>>>,
+    pub super_class: Option<ValueChange<Option<String>>>,
+    pub added_interfaces: Vec<String>,
+    pub removed_interfaces: Vec<String>,
+    pub fields: Vec<MemberDiff>,
+    pub methods: Vec<MemberDiff>,
+    pub attributes: Vec<AttributeDiff>,
+}
+
+impl ClassDiff {
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none()
+            && self.flags.is_none()
+            && self.super_class.is_none()
+            && self.added_interfaces.is_empty()
+            && self.removed_interfaces.is_empty()
+            && self.fields.is_empty()
+            && self.methods.is_empty()
+            && self.attributes.is_empty()
+    }
+}
+
+/// A field or method, identified by name and descriptor, that was added, removed or changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemberDiff {
+    pub name: String,
+    pub descriptor: String,
+    pub change: MemberChange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MemberChange {
+    Added,
+    Removed,
+    Changed(MemberChanges),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemberChanges {
+    pub flags: Option<ValueChange<Vec<String>>>,
+    /// Changes of the member's attributes and of the attributes nested in its `Code`.
+    pub attributes: Vec<AttributeDiff>,
+    pub code: Option<CodeDiff>,
+}
+
+impl MemberChanges {
+    fn is_empty(&self) -> bool {
+        self.flags.is_none() && self.attributes.is_empty() && self.code.is_none()
+    }
+}
+
+/// An attribute in rendered form; `old` is `None` for added and `new` for removed attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttributeDiff {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeDiff {
+    pub max_stack: Option<ValueChange<u16>>,
+    pub max_locals: Option<ValueChange<u16>>,
+    /// A minimal edit script turning the old instructions into the new ones.
+    pub instructions: Vec<InstructionEdit>,
+    pub exception_table: Option<ValueChange<Vec<String>>>,
+}
+
+impl CodeDiff {
+    fn is_empty(&self) -> bool {
+        self.max_stack.is_none()
+            && self.max_locals.is_none()
+            && self.instructions.is_empty()
+            && self.exception_table.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EditKind {
+    Added,
+    Removed,
+}
+
+/// One instruction of an edit script. `index` is the position of the instruction in the new
+/// code for additions and in the old code for removals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstructionEdit {
+    pub kind: EditKind,
+    pub index: usize,
+    pub instruction: String,
+}
+
+/// The differences between two sets of classes, such as two versions of a jar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArchiveDiff {
+    pub added_classes: Vec<String>,
+    pub removed_classes: Vec<String>,
+    pub changed_classes: Vec<ClassDiff>,
+}
+
+impl ArchiveDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_classes.is_empty()
+            && self.removed_classes.is_empty()
+            && self.changed_classes.is_empty()
+    }
+}
+
+/// Compares classes member by member and method bodies instruction by instruction.
+#[derive(Debug, Clone, Default)]
+pub struct ClassDiffer {
+    ignore_debug_info: bool,
+}
+
+impl ClassDiffer {
+    pub fn new() -> Self {
+        ClassDiffer::default()
+    }
+
+    /// Skips line numbers, local variable tables and source file names, which change with
+    /// every edit to a source file without changing behaviour.
+    pub fn ignore_debug_info(&mut self, ignore: bool) -> &mut Self {
+        self.ignore_debug_info = ignore;
+        self
+    }
+
+    pub fn diff(&self, old: &ClassFile, new: &ClassFile) -> ClassDiff {
+        let old_resolver = Resolver::new(old);
+        let new_resolver = Resolver::new(new);
+        let interfaces = |of: &ClassFile, not_in: &ClassFile| {
+            of.interfaces
+                .iter()
+                .filter(|interface| !not_in.interfaces.contains(interface))
+                .cloned()
+                .collect()
+        };
+        ClassDiff {
+            name: new.this_class.clone(),
+            version: ValueChange::between(
+                format!("{}.{}", old.version.major(), old.version.minor()),
+                format!("{}.{}", new.version.major(), new.version.minor()),
+            ),
+            flags: ValueChange::between(old.flags.names(), new.flags.names()),
+            super_class: ValueChange::between(old.super_class.clone(), new.super_class.clone()),
+            added_interfaces: interfaces(new, old),
+            removed_interfaces: interfaces(old, new),
+            fields: self.diff_members(
+                field_members(&old.fields),
+                field_members(&new.fields),
+                &old_resolver,
+                &new_resolver,
+            ),
+            methods: self.diff_members(
+                method_members(&old.methods),
+                method_members(&new.methods),
+                &old_resolver,
+                &new_resolver,
+            ),
+            attributes: self.diff_attributes(
+                self.render_attributes(&old.attributes, &old_resolver, None),
+                self.render_attributes(&new.attributes, &new_resolver, None),
+            ),
+        }
+    }
+
+    /// Pairs classes by name; classes present on both sides are listed only if they differ.
+    pub fn diff_archives<'a, I, J>(&self, old: I, new: J) -> ArchiveDiff
+    where
+        I: IntoIterator<Item = &'a ClassFile>,
+        J: IntoIterator<Item = &'a ClassFile>,
+    {
+        let old = old.into_iter().map(|class| (class.this_class.as_str(), class)).collect();
+        let new = new.into_iter().map(|class| (class.this_class.as_str(), class)).collect();
+        let (old, new): (BTreeMap<_, _>, BTreeMap<_, _>) = (old, new);
+
+        let mut diff = ArchiveDiff::default();
+        for (name, old_class) in &old {
+            match new.get(name) {
+                Some(new_class) => {
+                    let class_diff = self.diff(old_class, new_class);
+                    if !class_diff.is_empty() {
+                        diff.changed_classes.push(class_diff);
+                    }
+                }
+                None => diff.removed_classes.push(name.to_string()),
+            }
+        }
+        diff.added_classes = new
+            .keys()
+            .filter(|name| !old.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect();
+        diff
+    }
+
+    fn diff_members(
+        &self,
+        old: Members,
+        new: Members,
+        old_resolver: &Resolver,
+        new_resolver: &Resolver,
+    ) -> Vec<MemberDiff> {
+        let mut diffs = Vec::new();
+        for ((name, descriptor), old_member) in &old {
+            let change = match new.get(&(*name, descriptor.clone())) {
+                Some(new_member) => {
+                    let changes =
+                        self.diff_member(old_member, new_member, old_resolver, new_resolver);
+                    if changes.is_empty() {
+                        continue;
+                    }
+                    MemberChange::Changed(changes)
+                }
+                None => MemberChange::Removed,
+            };
+            diffs.push(MemberDiff {
+                name: name.to_string(),
+                descriptor: descriptor.clone(),
+                change,
+            });
+        }
+        for (name, descriptor) in new.keys().filter(|key| !old.contains_key(*key)) {
+            diffs.push(MemberDiff {
+                name: name.to_string(),
+                descriptor: descriptor.clone(),
+                change: MemberChange::Added,
+            });
+        }
+        diffs.sort_by(|a, b| (&a.name, &a.descriptor).cmp(&(&b.name, &b.descriptor)));
+        diffs
+    }
+
+    fn diff_member(
+        &self,
+        old: &Member,
+        new: &Member,
+        old_resolver: &Resolver,
+        new_resolver: &Resolver,
+    ) -> MemberChanges {
+        let old_code = code(old.attributes);
+        let new_code = code(new.attributes);
+        let mut old_attributes = self.render_attributes(old.attributes, old_resolver, None);
+        let mut new_attributes = self.render_attributes(new.attributes, new_resolver, None);
+        if let Some(code) = old_code {
+            let index = CodeIndex::new(code);
+            old_attributes.extend(self.render_attributes(
+                &code.attributes,
+                old_resolver,
+                Some(&index),
+            ));
+        }
+        if let Some(code) = new_code {
+            let index = CodeIndex::new(code);
+            new_attributes.extend(self.render_attributes(
+                &code.attributes,
+                new_resolver,
+                Some(&index),
+            ));
+        }
+
+        let code = match (old_code, new_code) {
+            (Some(old_code), Some(new_code)) => {
+                Some(diff_code(old_code, new_code, old_resolver, new_resolver))
+                    .filter(|diff| !diff.is_empty())
+            }
+            _ => None,
+        };
+        MemberChanges {
+            flags: ValueChange::between(old.flags.clone(), new.flags.clone()),
+            attributes: self.diff_attributes(old_attributes, new_attributes),
+            code,
+        }
+    }
+
+    fn diff_attributes(
+        &self,
+        old: BTreeMap<String, String>,
+        new: BTreeMap<String, String>,
+    ) -> Vec<AttributeDiff> {
+        let mut diffs = Vec::new();
+        for (name, old_value) in &old {
+            match new.get(name) {
+                Some(new_value) if new_value == old_value => {}
+                new_value => diffs.push(AttributeDiff {
+                    name: name.clone(),
+                    old: Some(old_value.clone()),
+                    new: new_value.cloned(),
+                }),
+            }
+        }
+        for (name, new_value) in new.iter().filter(|(name, _)| !old.contains_key(*name)) {
+            diffs.push(AttributeDiff {
+                name: name.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+        }
+        diffs.sort_by(|a, b| a.name.cmp(&b.name));
+        diffs
+    }
+
+    /// Renders attributes by name; `Code` is compared separately and repeated attributes are
+    /// rendered one per line.
+    fn render_attributes(
+        &self,
+        attributes: &[Attribute],
+        resolver: &Resolver,
+        code: Option<&CodeIndex>,
+    ) -> BTreeMap<String, String> {
+        let mut rendered = BTreeMap::<String, String>::new();
+        for attribute in attributes {
+            let name = attribute.name();
+            if matches!(attribute, Attribute::Code(_))
+                || (self.ignore_debug_info && DEBUG_ATTRIBUTES.contains(&name))
+            {
+                continue;
+            }
+            let value = render_attribute(attribute, resolver, code);
+            rendered
+                .entry(name.to_string())
+                .and_modify(|existing| {
+                    existing.push('\n');
+                    existing.push_str(&value);
+                })
+                .or_insert(value);
+        }
+        rendered
+    }
+}
+
+struct Member<'a> {
+    flags: Vec<String>,
+    attributes: &'a [Attribute],
+}
+
+type Members<'a> = BTreeMap<(&'a str, String), Member<'a>>;
+
+fn field_members(fields: &[Field]) -> Members<'_> {
+    fields
+        .iter()
+        .map(|field| {
+            let member = Member { flags: field.flags.names(), attributes: &field.attributes };
+            ((field.name.as_str(), field.type_descriptor.to_string()), member)
+        })
+        .collect()
+}
+
+fn method_members(methods: &[Method]) -> Members<'_> {
+    methods
+        .iter()
+        .map(|method| {
+            let member = Member { flags: method.flags.names(), attributes: &method.attributes };
+            ((method.name.as_str(), method.type_descriptor.to_string()), member)
+        })
+        .collect()
+}
+
+fn code(attributes: &[Attribute]) -> Option<&Code> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Code(code) => Some(code),
+        _ => None,
+    })
+}
+
+fn diff_code(old: &Code, new: &Code, old_resolver: &Resolver, new_resolver: &Resolver) -> CodeDiff {
+    let old_index = CodeIndex::new(old);
+    let new_index = CodeIndex::new(new);
+    let old_instructions = render_instructions(old, &old_index, old_resolver);
+    let new_instructions = render_instructions(new, &new_index, new_resolver);
+
+    let instructions = diff_sequences(&old_instructions, &new_instructions)
+        .into_iter()
+        .filter_map(|edit| match edit {
+            Edit::Same => None,
+            Edit::Remove(index) => Some(InstructionEdit {
+                kind: EditKind::Removed,
+                index,
+                instruction: old_instructions[index].clone(),
+            }),
+            Edit::Insert(index) => Some(InstructionEdit {
+                kind: EditKind::Added,
+                index,
+                instruction: new_instructions[index].clone(),
+            }),
+        })
+        .collect();
+
+    CodeDiff {
+        max_stack: ValueChange::between(old.max_stack, new.max_stack),
+        max_locals: ValueChange::between(old.max_locals, new.max_locals),
+        instructions,
+        exception_table: ValueChange::between(
+            render_exception_table(old, &old_index, old_resolver),
+            render_exception_table(new, &new_index, new_resolver),
+        ),
+    }
+}
+
+/// Maps code offsets to instruction indices.
+struct CodeIndex {
+    pcs: Vec<u32>,
+}
+
+impl CodeIndex {
+    fn new(code: &Code) -> Self {
+        CodeIndex { pcs: code.code.iter().map(|(_, pc)| *pc).collect() }
+    }
+
+    /// The instruction index at `pc`. An offset past the last instruction, as used for the
+    /// exclusive end of ranges, maps to the instruction count.
+    fn label(&self, pc: i64) -> String {
+        match u32::try_from(pc).map(|pc| self.pcs.binary_search(&pc)) {
+            Ok(Ok(index)) => index.to_string(),
+            Ok(Err(index)) if index == self.pcs.len() => index.to_string(),
+            _ => format!("pc {}", pc),
+        }
+    }
+
+    /// A branch target relative to the instruction at `index`, e.g. `+3` or `-12`, so that
+    /// code added elsewhere in the method does not change the branch.
+    fn target(&self, index: usize, pc: u32, offset: i32) -> String {
+        let target = pc as i64 + offset as i64;
+        match u32::try_from(target).map(|target| self.pcs.binary_search(&target)) {
+            Ok(Ok(target_index)) => format!("{:+}", target_index as i64 - index as i64),
+            _ => format!("pc {}", target),
+        }
+    }
+}
+
+/// Resolves constant pool references into the symbols they name.
+struct Resolver<'a> {
+    constant_pool: &'a ConstantPool,
+    bootstrap_methods: &'a [BootstrapMethod],
+}
+
+impl<'a> Resolver<'a> {
+    fn new(class: &'a ClassFile) -> Self {
+        let bootstrap_methods = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::BootstrapMethods(attribute) => Some(&attribute.bootstrap_methods[..]),
+                _ => None,
+            })
+            .unwrap_or_default();
+        Resolver { constant_pool: &class.constant_pool, bootstrap_methods }
+    }
+
+    fn entry(&self, index: u16) -> String {
+        self.expanded_entry(index, true)
+    }
+
+    fn expanded_entry(&self, index: u16, expand_bootstrap: bool) -> String {
+        match self.constant_pool.get(index as usize) {
+            Ok(constant) => self.constant(constant, expand_bootstrap),
+            Err(_) => format!("#{}", index),
+        }
+    }
+
+    fn utf8(&self, index: u16) -> String {
+        match self.constant_pool.utf8(index as usize) {
+            Some(string) => string.to_string(),
+            None => format!("#{}", index),
+        }
+    }
+
+    fn class(&self, index: u16) -> String {
+        match self.constant_pool.class_name(index as usize) {
+            Some(name) => name.to_string(),
+            None => format!("#{}", index),
+        }
+    }
+
+    fn constant(&self, constant: &Constant, expand_bootstrap: bool) -> String {
+        let pool = self.constant_pool;
+        match constant {
+            Constant::Utf8(string) => string.clone(),
+            Constant::Integer(value) => value.to_string(),
+            Constant::Float(value) => format!("{}f", value),
+            Constant::Long(value) => format!("{}L", value),
+            Constant::Double(value) => format!("{}d", value),
+            Constant::ClassIndex(name_index) => format!("class {}", self.utf8(*name_index)),
+            Constant::StringIndex(string_index) => match pool.utf8(*string_index as usize) {
+                Some(string) => format!("{:?}", string),
+                None => format!("#{}", string_index),
+            },
+            Constant::MethodType(descriptor_index) => {
+                format!("methodtype {}", self.utf8(*descriptor_index))
+            }
+            Constant::FieldRef(class_index, name_and_type_index)
+            | Constant::MethodRef(class_index, name_and_type_index)
+            | Constant::InterfaceMethodRef(class_index, name_and_type_index) => {
+                format!("{}.{}", self.class(*class_index), self.name_and_type(*name_and_type_index))
+            }
+            Constant::NameAndType(name_index, descriptor_index) => {
+                format!("{}:{}", self.utf8(*name_index), self.utf8(*descriptor_index))
+            }
+            Constant::MethodHandle(kind, reference_index) => format!(
+                "{} {}",
+                reference_kind_name(*kind).unwrap_or("REF_?"),
+                self.expanded_entry(*reference_index, expand_bootstrap)
+            ),
+            Constant::Dynamic(bootstrap_index, name_and_type_index)
+            | Constant::InvokeDynamic(bootstrap_index, name_and_type_index) => {
+                let prefix = match constant {
+                    Constant::Dynamic(..) => "dynamic ",
+                    _ => "",
+                };
+                let name_and_type = self.name_and_type(*name_and_type_index);
+                match expand_bootstrap {
+                    true => {
+                        format!("{}{} {}", prefix, name_and_type, self.bootstrap(*bootstrap_index))
+                    }
+                    false => format!("{}{}", prefix, name_and_type),
+                }
+            }
+            Constant::Module(name_index) => format!("module {}", self.utf8(*name_index)),
+            Constant::Package(name_index) => format!("package {}", self.utf8(*name_index)),
+            Constant::Unsuable => "unusable".to_string(),
+        }
+    }
+
+    fn name_and_type(&self, index: u16) -> String {
+        match self.constant_pool.name_and_type(index as usize) {
+            Some((name, descriptor)) => format!("{}:{}", name, descriptor),
+            None => format!("#{}", index),
+        }
+    }
+
+    /// The bootstrap method with its static arguments, e.g.
+    /// `REF_invokeStatic LambdaMetafactory.metafactory:(..) [methodtype ()V, ..]`. Dynamic
+    /// constants among the arguments are not expanded further, which would not terminate for
+    /// malformed, cyclic bootstrap methods.
+    fn bootstrap(&self, index: u16) -> String {
+        let Some(method) = self.bootstrap_methods.get(index as usize) else {
+            return format!("bootstrap #{}", index);
+        };
+        let arguments = method
+            .bootstrap_arguments
+            .iter()
+            .map(|argument| self.expanded_entry(*argument, false))
+            .collect::<Vec<_>>();
+        format!(
+            "{} [{}]",
+            self.expanded_entry(method.bootstrap_method_ref, false),
+            arguments.join(", ")
+        )
+    }
+}
+
+fn render_instructions(code: &Code, index: &CodeIndex, resolver: &Resolver) -> Vec<String> {
+    code.code
+        .iter()
+        .enumerate()
+        .map(|(position, (instruction, pc))| {
+            render_instruction(instruction, position, *pc, index, resolver)
+        })
+        .collect()
+}
+
+fn render_instruction(
+    instruction: &Instruction,
+    position: usize,
+    pc: u32,
+    index: &CodeIndex,
+    resolver: &Resolver,
+) -> String {
+    let mnemonic = instruction.mnemonic();
+    match instruction {
+        Instruction::Getfield(reference)
+        | Instruction::Getstatic(reference)
+        | Instruction::Putfield(reference)
+        | Instruction::Putstatic(reference)
+        | Instruction::Invokevirtual(reference)
+        | Instruction::Invokespecial(reference)
+        | Instruction::Invokestatic(reference)
+        | Instruction::Invokeinterface(reference, _)
+        | Instruction::Invokedynamic(reference)
+        | Instruction::Ldc2_w(reference) => format!("{} {}", mnemonic, resolver.entry(*reference)),
+        Instruction::Ldc(reference) => format!("ldc {}", resolver.entry(*reference as u16)),
+        Instruction::Ldc_w(reference) => format!("ldc {}", resolver.entry(*reference)),
+        Instruction::New(class)
+        | Instruction::Anewarray(class)
+        | Instruction::Checkcast(class)
+        | Instruction::Instanceof(class) => format!("{} {}", mnemonic, resolver.class(*class)),
+        Instruction::Multianewarray(class, dimensions) => {
+            format!("{} {} {}", mnemonic, resolver.class(*class), dimensions)
+        }
+        Instruction::Goto_w(offset) => format!("goto {}", index.target(position, pc, *offset)),
+        Instruction::Jsr_w(offset) => format!("jsr {}", index.target(position, pc, *offset)),
+        Instruction::Tableswitch { default, low, offsets, .. } => {
+            let targets = offsets
+                .iter()
+                .enumerate()
+                .map(|(case, offset)| {
+                    format!(
+                        "{}: {}",
+                        *low as i64 + case as i64,
+                        index.target(position, pc, *offset)
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "tableswitch {{ {}, default: {} }}",
+                targets.join(", "),
+                index.target(position, pc, *default)
+            )
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let targets = pairs
+                .iter()
+                .map(|(key, offset)| format!("{}: {}", key, index.target(position, pc, *offset)))
+                .collect::<Vec<_>>();
+            format!(
+                "lookupswitch {{ {}, default: {} }}",
+                targets.join(", "),
+                index.target(position, pc, *default)
+            )
+        }
+        Instruction::Aload(local)
+        | Instruction::Astore(local)
+        | Instruction::Dload(local)
+        | Instruction::Dstore(local)
+        | Instruction::Fload(local)
+        | Instruction::Fstore(local)
+        | Instruction::Iload(local)
+        | Instruction::Istore(local)
+        | Instruction::Lload(local)
+        | Instruction::Lstore(local)
+        | Instruction::Ret(local) => format!("{} {}", mnemonic, local),
+        Instruction::Iinc(local, increment) => format!("iinc {}, {}", local, increment),
+        Instruction::Bipush(value) => format!("bipush {}", *value as i8),
+        Instruction::Sipush(value) => format!("sipush {}", value),
+        Instruction::Newarray(atype) => format!("newarray {}", array_type(*atype)),
+        Instruction::Wide(wide) => match wide {
+            WideInstruction::Aload(local)
+            | WideInstruction::Astore(local)
+            | WideInstruction::Dload(local)
+            | WideInstruction::Dstore(local)
+            | WideInstruction::Fload(local)
+            | WideInstruction::Fstore(local)
+            | WideInstruction::Iload(local)
+            | WideInstruction::Istore(local)
+            | WideInstruction::Lload(local)
+            | WideInstruction::Lstore(local)
+            | WideInstruction::Ret(local) => format!("wide {} {}", wide.mnemonic(), local),
+            WideInstruction::Iinc(local, increment) => {
+                format!("wide iinc {}, {}", local, increment)
+            }
+        },
+        _ => match instruction.branch_offset() {
+            Some(offset) => format!("{} {}", mnemonic, index.target(position, pc, offset)),
+            None => mnemonic.to_string(),
+        },
+    }
+}
+
+/// The element type of a `newarray` instruction, or its code if it is invalid.
+fn array_type(atype: u8) -> String {
+    let name = match atype {
+        4 => "boolean",
+        5 => "char",
+        6 => "float",
+        7 => "double",
+        8 => "byte",
+        9 => "short",
+        10 => "int",
+        11 => "long",
+        _ => return atype.to_string(),
+    };
+    name.to_string()
+}
+
+fn render_exception_table(code: &Code, index: &CodeIndex, resolver: &Resolver) -> Vec<String> {
+    code.exception_table
+        .iter()
+        .map(|handler| {
+            let catch_type = match handler.catch_type {
+                0 => "any".to_string(),
+                catch_type => resolver.class(catch_type),
+            };
+            format!(
+                "[{}, {}) -> {} {}",
+                index.label(handler.start_pc as i64),
+                index.label(handler.end_pc as i64),
+                index.label(handler.handler_pc as i64),
+                catch_type
+            )
+        })
+        .collect()
+}
+
+/// Renders an attribute with pool references resolved and, for attributes nested in `Code`,
+/// offsets turned into instruction indices.
+fn render_attribute(
+    attribute: &Attribute,
+    resolver: &Resolver,
+    code: Option<&CodeIndex>,
+) -> String {
+    let label = |pc: i64| match code {
+        Some(index) => index.label(pc),
+        None => format!("pc {}", pc),
+    };
+    match attribute {
+        Attribute::ConstantValue(attribute) => resolver.constant(&attribute.value, false),
+        Attribute::Code(_) => String::new(),
+        Attribute::StackMapTable(attribute) => {
+            let mut pc = -1i64;
+            let frames = attribute
+                .frames
+                .iter()
+                .map(|frame| {
+                    let (offset_delta, description) = render_frame(frame, resolver, &label);
+                    pc += offset_delta as i64 + 1;
+                    format!("@{} {}", label(pc), description)
+                })
+                .collect::<Vec<_>>();
+            frames.join("\n")
+        }
+        Attribute::LineNumberTable(attribute) => attribute
+            .line_number_table
+            .iter()
+            .map(|line| format!("line {}: {}", line.line_number, label(line.start_pc as i64)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Attribute::LocalVariableTable(attribute) => attribute
+            .local_variable_table
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{}: {} {} [{}, {})",
+                    variable.index,
+                    resolver.utf8(variable.name_index),
+                    resolver.utf8(variable.descriptor_index),
+                    label(variable.start_pc as i64),
+                    label(variable.start_pc as i64 + variable.length as i64)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Attribute::LocalVariableTypeTable(attribute) => attribute
+            .local_variable_type_table
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{}: {} {} [{}, {})",
+                    variable.index,
+                    resolver.utf8(variable.name_index),
+                    resolver.utf8(variable.signature_index),
+                    label(variable.start_pc as i64),
+                    label(variable.start_pc as i64 + variable.length as i64)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Attribute::NestHost(attribute) => attribute.name.clone(),
+        Attribute::NestMembers(attribute) => attribute.names.join("\n"),
+        Attribute::PermittedSubclasses(attribute) => attribute.names.join("\n"),
+        Attribute::SourceFile(attribute) => attribute.file_name.clone(),
+        Attribute::BootstrapMethods(_) => (0..resolver.bootstrap_methods.len())
+            .map(|index| resolver.bootstrap(index as u16))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Attribute::UserDefined(attribute) => {
+            render_user_defined(attribute.name(), attribute.info(), resolver)
+        }
+    }
+}
+
+/// Returns the offset delta of a frame along with its description.
+fn render_frame(
+    frame: &StackMapFrame,
+    resolver: &Resolver,
+    label: &impl Fn(i64) -> String,
+) -> (u16, String) {
+    let types = |types: &[VerificationTypeInfo]| {
+        let types = types.iter().map(|info| render_type(info, resolver, label)).collect::<Vec<_>>();
+        format!("[{}]", types.join(", "))
+    };
+    match frame {
+        StackMapFrame::SameFrame { frame_type } => (*frame_type as u16, "same".to_string()),
+        StackMapFrame::SameFrameExtended { offset_delta, .. } => {
+            (*offset_delta, "same".to_string())
+        }
+        StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => (
+            *frame_type as u16 - 64,
+            format!("same_locals stack {}", types(std::slice::from_ref(stack))),
+        ),
+        StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, stack, .. } => {
+            (*offset_delta, format!("same_locals stack {}", types(std::slice::from_ref(stack))))
+        }
+        StackMapFrame::ChopFrame { frame_type, offset_delta } => {
+            (*offset_delta, format!("chop {}", 251 - *frame_type as u16))
+        }
+        StackMapFrame::AppendFrame { offset_delta, locals, .. } => {
+            (*offset_delta, format!("append {}", types(locals)))
+        }
+        StackMapFrame::FullFrame { offset_delta, locals, stack, .. } => {
+            (*offset_delta, format!("full locals {} stack {}", types(locals), types(stack)))
+        }
+    }
+}
+
+fn render_type(
+    info: &VerificationTypeInfo,
+    resolver: &Resolver,
+    label: &impl Fn(i64) -> String,
+) -> String {
+    match info {
+        VerificationTypeInfo::Object { constant: Constant::ClassIndex(name_index) } => {
+            resolver.utf8(*name_index)
+        }
+        VerificationTypeInfo::Object { constant } => resolver.constant(constant, false),
+        VerificationTypeInfo::Uninitialized { offset } => {
+            format!("uninitialized {}", label(*offset as i64))
+        }
+        _ => format!("{:?}", info).to_lowercase(),
+    }
+}
+
+/// Resolves the pool references of the common attributes this crate does not model, and
+/// renders any other attribute as hex.
+fn render_user_defined(name: &str, info: &[u8], resolver: &Resolver) -> String {
+    let u16s = info.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+    let resolved = match name {
+        "Signature" if info.len() == 2 => u16s.map(|index| resolver.utf8(index)).next(),
+        "Exceptions" => {
+            Some(u16s.skip(1).map(|index| resolver.class(index)).collect::<Vec<_>>().join("\n"))
+        }
+        "EnclosingMethod" if info.len() == 4 => {
+            let indices = u16s.collect::<Vec<_>>();
+            Some(match indices[1] {
+                0 => resolver.class(indices[0]),
+                method => {
+                    format!("{}.{}", resolver.class(indices[0]), resolver.name_and_type(method))
+                }
+            })
+        }
+        "InnerClasses" => {
+            let indices = u16s.skip(1).collect::<Vec<_>>();
+            let classes = indices
+                .chunks_exact(4)
+                .map(|entry| {
+                    let optional = |index: u16, resolve: &dyn Fn(u16) -> String| match index {
+                        0 => "-".to_string(),
+                        index => resolve(index),
+                    };
+                    format!(
+                        "{} outer {} name {} flags {:#06x}",
+                        resolver.class(entry[0]),
+                        optional(entry[1], &|index| resolver.class(index)),
+                        optional(entry[2], &|index| resolver.utf8(index)),
+                        entry[3]
+                    )
+                })
+                .collect::<Vec<_>>();
+            Some(classes.join("\n"))
+        }
+        _ => None,
+    };
+    resolved.unwrap_or_else(|| info.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Same,
+    Remove(usize),
+    Insert(usize),
+}
+
+/// The shortest edit script between two sequences, by Myers' O(ND) algorithm.
+fn diff_sequences<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    let mut distance = None;
+    'search: for d in 0..=max {
+        // Round d only reads the diagonals next to -d..=d, so the trace keeps those.
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let down =
+                k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = match down {
+                true => v[(offset + k + 1) as usize],
+                false => v[(offset + k - 1) as usize] + 1,
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                distance = Some(d);
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (0..=distance.unwrap_or(0)).rev() {
+        let v = |k: isize| trace[d as usize][(k + d + 1) as usize];
+        let k = x - y;
+        let down = k == -d || (k != d && v(k - 1) < v(k + 1));
+        let previous_k = if down { k + 1 } else { k - 1 };
+        let previous_x = v(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Same);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            match x == previous_x {
+                true => edits.push(Edit::Insert((y - 1) as usize)),
+                false => edits.push(Edit::Remove((x - 1) as usize)),
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    edits.reverse();
+    edits
+}
+
+impl Display for ArchiveDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for name in &self.removed_classes {
+            writeln!(f, "- class {}", name)?;
+        }
+        for name in &self.added_classes {
+            writeln!(f, "+ class {}", name)?;
+        }
+        for class in &self.changed_classes {
+            write!(f, "{}", class)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ClassDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+        writeln!(f, "~ class {}", self.name)?;
+        if let Some(version) = &self.version {
+            writeln!(f, "    version: {} -> {}", version.old, version.new)?;
+        }
+        if let Some(flags) = &self.flags {
+            writeln!(f, "    flags: [{}] -> [{}]", flags.old.join(" "), flags.new.join(" "))?;
+        }
+        if let Some(super_class) = &self.super_class {
+            let name = |name: &Option<String>| name.clone().unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "    super class: {} -> {}",
+                name(&super_class.old),
+                name(&super_class.new)
+            )?;
+        }
+        for interface in &self.removed_interfaces {
+            writeln!(f, "    - interface {}", interface)?;
+        }
+        for interface in &self.added_interfaces {
+            writeln!(f, "    + interface {}", interface)?;
+        }
+        write_attributes(f, &self.attributes, 4)?;
+        for field in &self.fields {
+            write_member(f, "field", field)?;
+        }
+        for method in &self.methods {
+            write_member(f, "method", method)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_member(f: &mut Formatter<'_>, kind: &str, member: &MemberDiff) -> fmt::Result {
+    let changes = match &member.change {
+        MemberChange::Added => {
+            return writeln!(f, "    + {} {}:{}", kind, member.name, member.descriptor);
+        }
+        MemberChange::Removed => {
+            return writeln!(f, "    - {} {}:{}", kind, member.name, member.descriptor);
+        }
+        MemberChange::Changed(changes) => changes,
+    };
+    writeln!(f, "    ~ {} {}:{}", kind, member.name, member.descriptor)?;
+    if let Some(flags) = &changes.flags {
+        writeln!(f, "        flags: [{}] -> [{}]", flags.old.join(" "), flags.new.join(" "))?;
+    }
+    if let Some(code) = &changes.code {
+        if let Some(max_stack) = &code.max_stack {
+            writeln!(f, "        max_stack: {} -> {}", max_stack.old, max_stack.new)?;
+        }
+        if let Some(max_locals) = &code.max_locals {
+            writeln!(f, "        max_locals: {} -> {}", max_locals.old, max_locals.new)?;
+        }
+        for edit in &code.instructions {
+            let sign = match edit.kind {
+                EditKind::Added => '+',
+                EditKind::Removed => '-',
+            };
+            writeln!(f, "        {} {}: {}", sign, edit.index, edit.instruction)?;
+        }
+        if let Some(exception_table) = &code.exception_table {
+            writeln!(f, "        exception table:")?;
+            for handler in &exception_table.old {
+                writeln!(f, "          - {}", handler)?;
+            }
+            for handler in &exception_table.new {
+                writeln!(f, "          + {}", handler)?;
+            }
+        }
+    }
+    write_attributes(f, &changes.attributes, 8)
+}
+
+fn write_attributes(
+    f: &mut Formatter<'_>,
+    attributes: &[AttributeDiff],
+    indent: usize,
+) -> fmt::Result {
+    for attribute in attributes {
+        let sign = match (&attribute.old, &attribute.new) {
+            (None, _) => '+',
+            (_, None) => '-',
+            _ => '~',
+        };
+        writeln!(f, "{:indent$}{} attribute {}", "", sign, attribute.name, indent = indent)?;
+        for (sign, value) in [('-', &attribute.old), ('+', &attribute.new)] {
+            for line in value.iter().flat_map(|value| value.lines()) {
+                writeln!(f, "{:indent$}  {} {}", "", sign, line, indent = indent)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeIndex, Edit, Resolver, diff_sequences, render_instructions};
+    use crate::class_file::ClassFile;
+    use crate::instruction::{Instruction, WideInstruction};
+    use crate::predefined_attributes::Code;
+
+    fn apply(old: &[char], new: &[char], edits: &[Edit]) -> Vec<char> {
+        let (mut result, mut position) = (Vec::new(), 0);
+        for edit in edits {
+            match edit {
+                Edit::Same => {
+                    result.push(old[position]);
+                    position += 1;
+                }
+                Edit::Remove(index) => {
+                    assert_eq!(*index, position);
+                    position += 1;
+                }
+                Edit::Insert(index) => result.push(new[*index]),
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_diff_sequences() {
+        let cases = [("ABCABBA", "CBABAC"), ("", "abc"), ("abc", ""), ("same", "same"), ("", "")];
+        for (old, new) in cases {
+            let (old, new) = (old.chars().collect::<Vec<_>>(), new.chars().collect::<Vec<_>>());
+            let edits = diff_sequences(&old, &new);
+            assert_eq!(apply(&old, &new, &edits), new);
+            if old == new {
+                assert!(edits.iter().all(|edit| *edit == Edit::Same));
+            }
+        }
+        let edits =
+            diff_sequences(&['a', 'b', 'c', 'a', 'b', 'b', 'a'], &['c', 'b', 'a', 'b', 'a', 'c']);
+        assert_eq!(edits.iter().filter(|edit| **edit != Edit::Same).count(), 5);
+    }
+
+    #[test]
+    fn test_render_plain_operands() {
+        let instructions = [
+            Instruction::Bipush(0xff),
+            Instruction::Sipush(-300),
+            Instruction::Iload(4),
+            Instruction::Iinc(1, -1),
+            Instruction::Newarray(10),
+            Instruction::Wide(WideInstruction::Iinc(300, -2)),
+            Instruction::Wide(WideInstruction::Astore(256)),
+            Instruction::Ireturn,
+        ];
+        let mut pc = 0;
+        let code = Code {
+            max_stack: 1,
+            max_locals: 301,
+            code: instructions
+                .into_iter()
+                .map(|instruction| {
+                    let start = pc;
+                    pc += instruction.length(pc);
+                    (instruction, start)
+                })
+                .collect(),
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let class = ClassFile::default();
+        assert_eq!(
+            render_instructions(&code, &CodeIndex::new(&code), &Resolver::new(&class)),
+            [
+                "bipush -1",
+                "sipush -300",
+                "iload 4",
+                "iinc 1, -1",
+                "newarray int",
+                "wide iinc 300, -2",
+                "wide astore 256",
+                "ireturn"
+            ]
+        );
+    }
+}
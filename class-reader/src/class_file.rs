@@ -2,12 +2,15 @@ use std::fmt::Display;
 
 use crate::access_flag::ClassFileAccessFlags;
 use crate::attribute::Attribute;
+use crate::class_file_reader::AttributeLocation;
 use crate::class_file_version::ClassFileVersion;
-use crate::constant_pool::ConstantPool;
+use crate::class_visitor::{ClassHeader, ClassVisitor, FieldHeader, MethodHeader};
+use crate::constant_pool::{Constant, ConstantPool};
 use crate::field::Field;
 use crate::method::Method;
 
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub struct ClassFile {
     pub version: ClassFileVersion,
     pub constant_pool: ConstantPool,
@@ -20,6 +23,82 @@ pub struct ClassFile {
     pub attributes: Vec<Attribute>,
 }
 
+impl ClassFile {
+    /// The name declared by the `Module` attribute of a `module-info` class.
+    pub fn module_name(&self) -> Option<&str> {
+        let info = self.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::UserDefined(attribute) if attribute.name() == "Module" => {
+                Some(attribute.info())
+            }
+            _ => None,
+        })?;
+        let module_index = u16::from_be_bytes(info.get(..2)?.try_into().ok()?) as usize;
+        let Ok(Constant::Module(name_index)) = self.constant_pool.get(module_index) else {
+            return None;
+        };
+        match self.constant_pool.get(*name_index as usize) {
+            Ok(Constant::Utf8(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Reports the contents of this class file to `visitor`, in the order they would be read.
+    pub fn accept(&self, visitor: &mut dyn ClassVisitor) {
+        visitor.visit_class(ClassHeader {
+            version: self.version,
+            constant_pool: self.constant_pool.clone(),
+            flags: self.flags.clone(),
+            this_class: self.this_class.clone(),
+            super_class: self.super_class.clone(),
+            interfaces: self.interfaces.clone(),
+        });
+
+        for field in &self.fields {
+            visitor.visit_field(FieldHeader {
+                flags: field.flags.clone(),
+                name: field.name.clone(),
+                type_descriptor: field.type_descriptor.clone(),
+            });
+            let location = AttributeLocation::Field(field.type_descriptor.clone());
+            for attribute in &field.attributes {
+                visitor.visit_attribute(&location, attribute.clone());
+            }
+            visitor.visit_field_end();
+        }
+
+        for method in &self.methods {
+            visitor.visit_method(MethodHeader {
+                flags: method.flags.clone(),
+                name: method.name.clone(),
+                type_descriptor: method.type_descriptor.clone(),
+            });
+            for attribute in &method.attributes {
+                let Attribute::Code(code) = attribute else {
+                    visitor.visit_attribute(&AttributeLocation::Method, attribute.clone());
+                    continue;
+                };
+                visitor.visit_code(code.max_stack, code.max_locals);
+                for (instruction, pc) in &code.code {
+                    visitor.visit_instruction(instruction.clone(), *pc);
+                }
+                for handler in &code.exception_table {
+                    visitor.visit_exception_handler(handler.clone());
+                }
+                for attribute in &code.attributes {
+                    visitor.visit_attribute(&AttributeLocation::Code, attribute.clone());
+                }
+                visitor.visit_code_end();
+            }
+            visitor.visit_method_end();
+        }
+
+        for attribute in &self.attributes {
+            visitor.visit_attribute(&AttributeLocation::Class, attribute.clone());
+        }
+        visitor.visit_end();
+    }
+}
+
 impl Display for ClassFile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Class file version: {}", self.version)
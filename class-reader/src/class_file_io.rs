@@ -0,0 +1,115 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ClassReaderError, ContextualError};
+use crate::lazy_class_file::LazyClassFile;
+
+type Result<T> = std::result::Result<T, ContextualError>;
+
+/// Largest class file accepted from a stream by [`ClassFileReader::read_from`].
+pub const DEFAULT_READ_LIMIT: u64 = 64 * 1024 * 1024;
+
+impl ClassFileReader<'_> {
+    /// Reads a class file from a stream, buffering at most [`DEFAULT_READ_LIMIT`] bytes.
+    pub fn read_from<R: Read>(reader: R) -> Result<ClassFile> {
+        Self::read_from_with_limit(reader, DEFAULT_READ_LIMIT)
+    }
+
+    /// Reads a class file from a stream, failing once more than `limit` bytes are buffered.
+    pub fn read_from_with_limit<R: Read>(reader: R, limit: u64) -> Result<ClassFile> {
+        let mut data = Vec::new();
+        reader.take(limit.saturating_add(1)).read_to_end(&mut data)?;
+        if data.len() as u64 > limit {
+            return Err(ClassReaderError::ClassFileTooLarge(limit).into());
+        }
+        ClassFileReader::read_class(&data)
+    }
+
+    /// Reads the class file at `path` through a memory mapping.
+    pub fn read_path<P: AsRef<Path>>(path: P) -> Result<ClassFile> {
+        MappedClassFile::open(path)?.read()
+    }
+
+    /// Recursively reads every `.class` file below `directory`, in path order. Symbolic links
+    /// to directories below `directory` are not followed, so links back up the tree cannot
+    /// make the walk loop.
+    pub fn read_directory<P: AsRef<Path>>(directory: P) -> ClassDirectory {
+        ClassDirectory::new(directory)
+    }
+}
+
+/// A memory-mapped class file, which can also be parsed lazily without copying it.
+#[derive(Debug)]
+pub struct MappedClassFile {
+    mmap: Mmap,
+}
+
+impl MappedClassFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is read-only; like every reader of class files on disk, we assume
+        // the file is not truncated or rewritten while it is being read.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(MappedClassFile { mmap })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.mmap
+    }
+
+    pub fn read(&self) -> Result<ClassFile> {
+        ClassFileReader::read_class(self.bytes())
+    }
+
+    pub fn lazy(&self) -> Result<LazyClassFile<'_>> {
+        LazyClassFile::parse(self.bytes())
+    }
+}
+
+/// Iterator over the class files of a directory tree, see [`ClassFileReader::read_directory`].
+///
+/// Errors are reported per path: an unreadable directory yields its own path with the error
+/// and the walk continues with its siblings.
+#[derive(Debug)]
+pub struct ClassDirectory {
+    /// Paths left to visit, in reverse path order, and whether each is a directory.
+    pending: Vec<(PathBuf, bool)>,
+}
+
+impl ClassDirectory {
+    fn new<P: AsRef<Path>>(directory: P) -> Self {
+        let directory = directory.as_ref();
+        ClassDirectory { pending: vec![(directory.to_path_buf(), directory.is_dir())] }
+    }
+
+    fn push_children(&mut self, directory: &Path) -> std::io::Result<()> {
+        let mut children = fs::read_dir(directory)?
+            .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?.is_dir()))))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        children.sort_unstable_by(|a, b| b.cmp(a));
+        self.pending.extend(children);
+        Ok(())
+    }
+}
+
+impl Iterator for ClassDirectory {
+    type Item = (PathBuf, Result<ClassFile>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, is_dir)) = self.pending.pop() {
+            if is_dir {
+                if let Err(err) = self.push_children(&path) {
+                    return Some((path, Err(err.into())));
+                }
+            } else if path.extension().is_some_and(|extension| extension == "class") {
+                let result = ClassFileReader::read_path(&path);
+                return Some((path, result));
+            }
+        }
+        None
+    }
+}
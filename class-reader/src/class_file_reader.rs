@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt;
 
 use crate::access_flag::ClassFileAccessFlags;
@@ -5,12 +6,15 @@ use crate::attribute::{Attribute, UserDefinedAttribute};
 use crate::byte_reader::{ByteReader, ReadError};
 use crate::class_file::ClassFile;
 use crate::class_file_version::{ClassFileVersion, FileVersionError};
-use crate::constant_pool::{Constant, ConstantPoolError};
+use crate::class_visitor::{ClassHeader, ClassVisitor, CodeCollector, FieldHeader, MethodHeader};
+use crate::code_limits::{CodeLimits, CodeLimitsError};
+use crate::constant_pool::{Constant, ConstantPool, ConstantPoolError};
 use crate::field::{BaseType, Field, FieldAccessFlags, FieldError, FieldType};
-use crate::instruction::Instruction;
+use crate::instruction::{Instruction, WideInstruction};
+use crate::lazy_class_file::LazyConstantPool;
 use crate::method::{Method, MethodAccessFlags, MethodDescriptor, MethodParsingError};
 use crate::predefined_attributes::{
-    BootstrapMethod, BootstrapMethods, Code, ConstantValue, ExceptionHandler, LineNumber,
+    BootstrapMethod, BootstrapMethods, ConstantValue, ExceptionHandler, LineNumber,
     LineNumberTable, LocalVariable, LocalVariableTable, LocalVariableType, LocalVariableTypeTable,
     NestHost, NestMembers, PetrmittedSubclasses, SourceFile, StackMapFrame, StackMapTable,
     VerificationTypeInfo,
@@ -18,7 +22,7 @@ use crate::predefined_attributes::{
 
 type Result<T> = std::result::Result<T, ClassReaderError>;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, thiserror::Error, strum_macros::IntoStaticStr)]
 pub enum ClassReaderError {
     #[error("Invalid magic number {0}")]
     #[non_exhaustive]
@@ -47,6 +51,15 @@ pub enum ClassReaderError {
     )]
     #[non_exhaustive]
     InvalidSourceFileString(String),
+    #[error("Invalid opcode {0:#04x}")]
+    #[non_exhaustive]
+    InvalidOpcode(u8),
+    #[error("Opcode {0:#04x} cannot be modified by wide")]
+    #[non_exhaustive]
+    InvalidWideOpcode(u8),
+    #[error("Invalid tableswitch bounds: low {0}, high {1}")]
+    #[non_exhaustive]
+    InvalidTableswitchBounds(i32, i32),
     #[error("Invalid attribute name index, must represent the string '{0}', actual: {1}")]
     InvalidAttributeNameIndex(String, String),
     #[error("Error encountered during reading: {0}")]
@@ -64,6 +77,28 @@ pub enum ClassReaderError {
     #[error("Error while parsing method: {0}")]
     #[non_exhaustive]
     MethodParsingError(#[from] MethodParsingError),
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Class file is larger than the limit of {0} bytes")]
+    #[non_exhaustive]
+    ClassFileTooLarge(u64),
+    #[error("Method {method} declares max_stack {declared} but needs {required}")]
+    #[non_exhaustive]
+    MaxStackTooSmall { method: String, declared: u16, required: u16 },
+    #[error("Method {method} declares max_locals {declared} but needs {required}")]
+    #[non_exhaustive]
+    MaxLocalsTooSmall { method: String, declared: u16, required: u16 },
+    #[error("Invalid code in method {0}: {1}")]
+    #[non_exhaustive]
+    InvalidCode(String, #[source] CodeLimitsError),
+}
+
+impl ClassReaderError {
+    /// The name of the variant, e.g. `InvalidOpcode`, for grouping errors.
+    pub fn kind(&self) -> &'static str {
+        self.into()
+    }
 }
 
 pub struct ContextualError {
@@ -72,9 +107,42 @@ pub struct ContextualError {
 }
 
 impl ContextualError {
-    fn new(err: ClassReaderError, snippet: Vec<u8>) -> Self {
+    pub(crate) fn new(err: ClassReaderError, snippet: Vec<u8>) -> Self {
         ContextualError { err, snippet }
     }
+
+    pub fn error(&self) -> &ClassReaderError {
+        &self.err
+    }
+
+    /// Bytes around the position where reading failed, empty for errors not caused by the data.
+    pub fn snippet(&self) -> &[u8] {
+        &self.snippet
+    }
+}
+
+impl From<ClassReaderError> for ContextualError {
+    fn from(err: ClassReaderError) -> Self {
+        ContextualError::new(err, Vec::new())
+    }
+}
+
+impl From<std::io::Error> for ContextualError {
+    fn from(err: std::io::Error) -> Self {
+        ClassReaderError::Io(err).into()
+    }
+}
+
+impl fmt::Display for ContextualError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.err)
+    }
+}
+
+impl std::error::Error for ContextualError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.err)
+    }
 }
 
 impl fmt::Debug for ContextualError {
@@ -90,15 +158,57 @@ impl fmt::Debug for ContextualError {
     }
 }
 
+/// Where an attribute is attached, since the set of predefined attributes recognized by the
+/// reader depends on it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeLocation {
+    Class,
+    Field(FieldType),
+    Method,
+    Code,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClassFileReader<'a> {
     byte_reader: ByteReader<'a>,
+    constant_pool: Cow<'a, ConstantPool>,
+    /// The pool of a [`LazyClassFile`](crate::lazy_class_file::LazyClassFile), whose entries
+    /// are decoded as the fragment refers to them instead of from `constant_pool`.
+    lazy_constant_pool: Option<&'a LazyConstantPool<'a>>,
     class_file: ClassFile,
+    /// Whether to check the `max_stack` and `max_locals` of each method against its code.
+    checked: bool,
 }
 
 impl<'a> ClassFileReader<'a> {
     fn new(data: &'a [u8]) -> Self {
-        ClassFileReader { byte_reader: ByteReader::new(data), class_file: ClassFile::default() }
+        ClassFileReader {
+            byte_reader: ByteReader::new(data),
+            constant_pool: Cow::Owned(ConstantPool::default()),
+            lazy_constant_pool: None,
+            class_file: ClassFile::default(),
+            checked: false,
+        }
+    }
+
+    /// Creates a reader over a fragment of a class file whose constant pool has already been read.
+    pub(crate) fn with_constant_pool(data: &'a [u8], constant_pool: &'a ConstantPool) -> Self {
+        ClassFileReader {
+            byte_reader: ByteReader::new(data),
+            constant_pool: Cow::Borrowed(constant_pool),
+            lazy_constant_pool: None,
+            class_file: ClassFile::default(),
+            checked: false,
+        }
+    }
+
+    /// Creates a reader over a fragment of a lazily read class file, which decodes only the
+    /// constants the fragment refers to.
+    pub(crate) fn with_lazy_constant_pool(
+        data: &'a [u8],
+        constant_pool: &'a LazyConstantPool<'a>,
+    ) -> Self {
+        ClassFileReader { lazy_constant_pool: Some(constant_pool), ..ClassFileReader::new(data) }
     }
 
     pub fn read_class(data: &[u8]) -> std::result::Result<ClassFile, ContextualError> {
@@ -113,6 +223,26 @@ impl<'a> ClassFileReader<'a> {
         }
     }
 
+    /// Reads the class file at `data` like [`ClassFileReader::read_class`], and also rejects
+    /// methods whose code is inconsistent or needs a larger operand stack or more local
+    /// variables than its `Code` attribute declares.
+    pub fn read_class_checked(data: &[u8]) -> std::result::Result<ClassFile, ContextualError> {
+        let mut class_reader = ClassFileReader { checked: true, ..ClassFileReader::new(data) };
+        class_reader.read().map_err(|err| ContextualError::new(err, class_reader.snippet()))
+    }
+
+    /// Reads the class file at `data`, reporting its contents to `visitor` as they are decoded
+    /// instead of building a [`ClassFile`].
+    pub fn accept(
+        data: &[u8],
+        visitor: &mut dyn ClassVisitor,
+    ) -> std::result::Result<(), ContextualError> {
+        let mut class_reader = ClassFileReader::new(data);
+        class_reader
+            .stream(visitor)
+            .map_err(|err| ContextualError::new(err, class_reader.snippet()))
+    }
+
     fn snippet(&self) -> Vec<u8> {
         self.byte_reader.snippet()
     }
@@ -128,7 +258,87 @@ impl<'a> ClassFileReader<'a> {
         self.read_fields()?;
         self.read_methods()?;
         self.read_class_attributes()?;
-        Ok(self.class_file.clone())
+        self.class_file.constant_pool = std::mem::take(&mut self.constant_pool).into_owned();
+        Ok(std::mem::take(&mut self.class_file))
+    }
+
+    fn stream(&mut self, visitor: &mut dyn ClassVisitor) -> Result<()> {
+        self.read_magic_number()?;
+        self.read_version()?;
+        self.read_constant_pool()?;
+        self.read_access_flags()?;
+        self.read_this_class()?;
+        self.read_super_class()?;
+        self.read_interfaces()?;
+        let class_file = std::mem::take(&mut self.class_file);
+        visitor.visit_class(ClassHeader {
+            version: class_file.version,
+            constant_pool: self.constant_pool.clone().into_owned(),
+            flags: class_file.flags,
+            this_class: class_file.this_class,
+            super_class: class_file.super_class,
+            interfaces: class_file.interfaces,
+        });
+
+        let fields_count = self.byte_reader.read_u16()?;
+        for _ in 0..fields_count {
+            self.stream_field(visitor)?;
+        }
+        let methods_count = self.byte_reader.read_u16()?;
+        for _ in 0..methods_count {
+            self.stream_method(visitor)?;
+        }
+        let attributes_count = self.byte_reader.read_u16()?;
+        for _ in 0..attributes_count {
+            let name_index = self.byte_reader.read_u16()?;
+            let attribute = self.read_attribute(name_index, &AttributeLocation::Class)?;
+            visitor.visit_attribute(&AttributeLocation::Class, attribute);
+        }
+        visitor.visit_end();
+        Ok(())
+    }
+
+    fn stream_field(&mut self, visitor: &mut dyn ClassVisitor) -> Result<()> {
+        let (access_flags, name_index) = self.byte_reader.read_pair_u16()?;
+        let flags = FieldAccessFlags::new(access_flags);
+        let name = self.get_utf8(name_index)?;
+
+        let (descriptor_index, attributes_count) = self.byte_reader.read_pair_u16()?;
+        let field_descriptor_utf8 = self.get_utf8(descriptor_index)?;
+        let type_descriptor = FieldType::try_from(&mut field_descriptor_utf8.chars().peekable())?;
+        let location = AttributeLocation::Field(type_descriptor.clone());
+        visitor.visit_field(FieldHeader { flags, name, type_descriptor });
+
+        for _ in 0..attributes_count {
+            let name_index = self.byte_reader.read_u16()?;
+            let attribute = self.read_attribute(name_index, &location)?;
+            visitor.visit_attribute(&location, attribute);
+        }
+        visitor.visit_field_end();
+        Ok(())
+    }
+
+    fn stream_method(&mut self, visitor: &mut dyn ClassVisitor) -> Result<()> {
+        let (access_flag, name_index) = self.byte_reader.read_pair_u16()?;
+        let flags = MethodAccessFlags::new(access_flag);
+        let name = self.get_utf8(name_index)?;
+
+        let (descriptor_index, attributes_count) = self.byte_reader.read_pair_u16()?;
+        let type_descriptor =
+            MethodDescriptor::try_from(&mut self.get_utf8(descriptor_index)?.chars().peekable())?;
+        visitor.visit_method(MethodHeader { flags, name, type_descriptor });
+
+        for _ in 0..attributes_count {
+            let name_index = self.byte_reader.read_u16()?;
+            if self.get_utf8(name_index)? == "Code" {
+                self.stream_code_attr(visitor)?;
+            } else {
+                let attribute = self.read_attribute(name_index, &AttributeLocation::Method)?;
+                visitor.visit_attribute(&AttributeLocation::Method, attribute);
+            }
+        }
+        visitor.visit_method_end();
+        Ok(())
     }
 
     pub fn read_magic_number(&mut self) -> Result<()> {
@@ -148,38 +358,40 @@ impl<'a> ClassFileReader<'a> {
 
     pub fn read_constant_pool(&mut self) -> Result<()> {
         let constant_pool_count = self.byte_reader.read_u16()?;
-        for mut _index in 1..constant_pool_count {
-            let tag = self.byte_reader.read_u8()?;
-            let constant = match tag {
-                1 => self.read_string_constant()?,
-                3 => self.read_int_constant()?,
-                4 => self.read_float_constant()?,
-                5 => self.read_long_constant()?,
-                6 => self.read_double_constant()?,
-                7 => self.read_class_index()?,
-                8 => self.read_string_info()?,
-                9 => self.read_field_ref()?,
-                10 => self.read_method_ref()?,
-                11 => self.read_interface_method_ref()?,
-                12 => self.read_name_and_type()?,
-                15 => self.read_method_handle()?,
-                16 => self.read_method_type()?,
-                17 => self.read_dynamic()?,
-                18 => self.read_invoke_dynamic()?,
-                19 => self.read_module()?,
-                20 => self.read_package()?,
-                _ => return Err(ClassReaderError::TagNotSupported(tag)),
-            };
-
-            self.class_file.constant_pool.add(constant.clone());
-
-            if matches!(constant, Constant::Long(_) | Constant::Double(_)) {
-                _index += 1;
-            }
+        let mut index = 1;
+        while index < constant_pool_count {
+            let constant = self.read_constant()?;
+            index +=
+                if matches!(constant, Constant::Long(_) | Constant::Double(_)) { 2 } else { 1 };
+            self.constant_pool.to_mut().add(constant);
         }
         Ok(())
     }
 
+    pub(crate) fn read_constant(&mut self) -> Result<Constant> {
+        let tag = self.byte_reader.read_u8()?;
+        match tag {
+            1 => self.read_string_constant(),
+            3 => self.read_int_constant(),
+            4 => self.read_float_constant(),
+            5 => self.read_long_constant(),
+            6 => self.read_double_constant(),
+            7 => self.read_class_index(),
+            8 => self.read_string_info(),
+            9 => self.read_field_ref(),
+            10 => self.read_method_ref(),
+            11 => self.read_interface_method_ref(),
+            12 => self.read_name_and_type(),
+            15 => self.read_method_handle(),
+            16 => self.read_method_type(),
+            17 => self.read_dynamic(),
+            18 => self.read_invoke_dynamic(),
+            19 => self.read_module(),
+            20 => self.read_package(),
+            _ => Err(ClassReaderError::TagNotSupported(tag)),
+        }
+    }
+
     fn read_string_constant(&mut self) -> Result<Constant> {
         let length = self.byte_reader.read_u16()?;
         Ok(Constant::Utf8(self.byte_reader.read_utf8(length as u32)?.into_owned()))
@@ -273,9 +485,16 @@ impl<'a> ClassFileReader<'a> {
         Ok(())
     }
 
+    fn constant(&self, index: u16) -> Result<Cow<'_, Constant>> {
+        match self.lazy_constant_pool {
+            Some(constant_pool) => constant_pool.get(index).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(self.constant_pool.get(index as usize)?)),
+        }
+    }
+
     fn get_utf8(&mut self, name_index: u16) -> Result<String> {
-        let constant = self.class_file.constant_pool.get(name_index as usize)?;
-        match constant {
+        let constant = self.constant(name_index)?;
+        match constant.as_ref() {
             Constant::Utf8(utf8_content) => Ok(utf8_content.clone()),
             _ => Err(ClassReaderError::UnexpectedConstant {
                 expected: "Utf8".to_string(),
@@ -294,9 +513,9 @@ impl<'a> ClassFileReader<'a> {
     }
 
     fn get_class_name(&mut self, name_index: u16) -> Result<String> {
-        let constant = self.class_file.constant_pool.get(name_index as usize)?;
+        let constant = self.constant(name_index)?.into_owned();
         match constant {
-            Constant::ClassIndex(class_index) => self.get_utf8(*class_index),
+            Constant::ClassIndex(class_index) => self.get_utf8(class_index),
             _ => Err(ClassReaderError::UnexpectedConstant {
                 expected: "ClassIndex".to_string(),
                 actual: constant.name(),
@@ -349,16 +568,8 @@ impl<'a> ClassFileReader<'a> {
         let field_descriptor_utf8 = self.get_utf8(descriptor_index)?;
         let type_descriptor = FieldType::try_from(&mut field_descriptor_utf8.chars().peekable())?;
 
-        let mut attributes = Vec::new();
-        for _ in 0..attributes_count {
-            let name_index = self.byte_reader.read_u16()?;
-            let name = self.get_utf8(name_index)?;
-            let attr = match name.as_str() {
-                "ConstantValue" => self.read_constant_value_attr(type_descriptor.clone())?,
-                _ => self.read_user_defined_attr(name)?,
-            };
-            attributes.push(attr);
-        }
+        let location = AttributeLocation::Field(type_descriptor.clone());
+        let attributes = self.read_attributes(attributes_count, &location)?;
         Ok(Field::new(flags, name, type_descriptor, attributes))
     }
 
@@ -381,17 +592,35 @@ impl<'a> ClassFileReader<'a> {
         let type_descriptor =
             MethodDescriptor::try_from(&mut self.get_utf8(descriptor_index)?.chars().peekable())?;
 
-        let mut attributes = Vec::with_capacity(attributes_count as usize);
-        for _ in 0..attributes_count {
-            let name_index = self.byte_reader.read_u16()?;
-            let name = self.get_utf8(name_index)?;
-            let attr = match name.as_str() {
-                "Code" => self.read_code_attr()?,
-                _ => self.read_user_defined_attr(name)?,
-            };
-            attributes.push(attr);
+        let attributes = self.read_attributes(attributes_count, &AttributeLocation::Method)?;
+        let method = Method { flags, name, type_descriptor, attributes };
+        if self.checked {
+            self.check_code_limits(&method)?;
         }
-        Ok(Method { flags, name, type_descriptor, attributes })
+        Ok(method)
+    }
+
+    fn check_code_limits(&self, method: &Method) -> Result<()> {
+        let Some(code) = method.code() else {
+            return Ok(());
+        };
+        let limits = CodeLimits::compute(&self.constant_pool, method)
+            .map_err(|err| ClassReaderError::InvalidCode(method.name.clone(), err))?;
+        if code.max_stack < limits.max_stack {
+            return Err(ClassReaderError::MaxStackTooSmall {
+                method: method.name.clone(),
+                declared: code.max_stack,
+                required: limits.max_stack,
+            });
+        }
+        if code.max_locals < limits.max_locals {
+            return Err(ClassReaderError::MaxLocalsTooSmall {
+                method: method.name.clone(),
+                declared: code.max_locals,
+                required: limits.max_locals,
+            });
+        }
+        Ok(())
     }
 
     fn read_constant_value_attr(&mut self, field_type: FieldType) -> Result<Attribute> {
@@ -400,8 +629,8 @@ impl<'a> ClassFileReader<'a> {
             return Err(ClassReaderError::InvalidAttributeSize(length, 2));
         }
         let constantvalue_index = self.byte_reader.read_u16()?;
-        let constant_value = self.class_file.constant_pool.get(constantvalue_index as usize)?;
-        match (field_type.clone(), constant_value) {
+        let constant_value = self.constant(constantvalue_index)?.into_owned();
+        match (field_type.clone(), &constant_value) {
             (FieldType::Base(BaseType::Int), Constant::Integer(_))
             | (FieldType::Base(BaseType::Short), Constant::Integer(_))
             | (FieldType::Base(BaseType::Char), Constant::Integer(_))
@@ -418,7 +647,7 @@ impl<'a> ClassFileReader<'a> {
             (FieldType::Base(BaseType::Double), Constant::Double(_)) => {
                 Ok(Attribute::ConstantValue(ConstantValue::new(constant_value.clone())))
             }
-            (FieldType::Object(ref class_name), Constant::Utf8(_))
+            (FieldType::Object(ref class_name), Constant::StringIndex(_))
                 if class_name == "java/lang/String" =>
             {
                 Ok(Attribute::ConstantValue(ConstantValue::new(constant_value.clone())))
@@ -428,51 +657,38 @@ impl<'a> ClassFileReader<'a> {
     }
 
     fn read_code_attr(&mut self) -> Result<Attribute> {
+        let mut collector = CodeCollector::default();
+        self.stream_code_attr(&mut collector)?;
+        Ok(Attribute::Code(collector.code.expect("visit_code precedes other code events")))
+    }
+
+    fn stream_code_attr(&mut self, visitor: &mut dyn ClassVisitor) -> Result<()> {
         let _length = self.byte_reader.read_u32()?;
         let max_stack = self.byte_reader.read_u16()?;
         let max_locals = self.byte_reader.read_u16()?;
+        visitor.visit_code(max_stack, max_locals);
+
         let code_length = self.byte_reader.read_u32()?;
-        let mut instructions = Vec::new();
         let mut bytes_read = 0;
-
         while bytes_read < code_length {
             let index = self.byte_reader.read_u8()?;
-            instructions.push(self.read_instruction(index, &mut bytes_read)?);
+            let (instruction, pc) = self.read_instruction(index, &mut bytes_read)?;
+            visitor.visit_instruction(instruction, pc);
         }
 
         let exception_table_length = self.byte_reader.read_u16()?;
-        let mut exception_table = Vec::new();
-
         for _ in 0..exception_table_length {
-            let start_pc = self.byte_reader.read_u16()?;
-            let end_pc = self.byte_reader.read_u16()?;
-            let handler_pc = self.byte_reader.read_u16()?;
-            let catch_type = self.byte_reader.read_u16()?;
-            exception_table.push(ExceptionHandler::new(start_pc, end_pc, handler_pc, catch_type));
+            visitor.visit_exception_handler(self.read_exception_handler()?);
         }
 
-        let _attributes_count = self.byte_reader.read_u16()?;
-        let mut attributes = Vec::new();
-        for _ in 0.._attributes_count {
+        let attributes_count = self.byte_reader.read_u16()?;
+        for _ in 0..attributes_count {
             let name_index = self.byte_reader.read_u16()?;
-            let name = self.get_utf8(name_index)?;
-            let attr = match name.as_str() {
-                "LineNumberTable" => self.read_line_number_table_attr()?,
-                "LocalVariableTable" => self.read_local_variable_table_attr()?,
-                "LocalVariableTypeTable" => self.read_local_variable_type_table_attr()?,
-                "StackMapTable" => self.read_stack_map_table_attr()?,
-                _ => self.read_user_defined_attr(name)?,
-            };
-            attributes.push(attr);
-        }
-
-        Ok(Attribute::Code(Code {
-            max_stack,
-            max_locals,
-            code: instructions,
-            exception_table,
-            attributes,
-        }))
+            let attribute = self.read_attribute(name_index, &AttributeLocation::Code)?;
+            visitor.visit_attribute(&AttributeLocation::Code, attribute);
+        }
+        visitor.visit_code_end();
+        Ok(())
     }
 
     fn read_instruction(&mut self, index: u8, address: &mut u32) -> Result<(Instruction, u32)> {
@@ -564,8 +780,8 @@ impl<'a> ClassFileReader<'a> {
             0x66 => Instruction::Fsub,
             0xb4 => Instruction::Getfield(self.read_instruction_u16(address)?),
             0xb2 => Instruction::Getstatic(self.read_instruction_u16(address)?),
-            0xa7 => todo!("Goto"),
-            0xc8 => todo!("Goto_w"),
+            0xa7 => Instruction::Goto(self.read_instruction_u16(address)?),
+            0xc8 => Instruction::Goto_w(self.read_instruction_i32(address)?),
             0x91 => Instruction::I2b,
             0x92 => Instruction::I2c,
             0x87 => Instruction::I2d,
@@ -612,11 +828,17 @@ impl<'a> ClassFileReader<'a> {
             0x68 => Instruction::Imul,
             0x74 => Instruction::Ineg,
             0xc1 => Instruction::Instanceof(self.read_instruction_u16(address)?),
-            0xba => Instruction::Invokedynamic(self.read_instruction_u16(address)?),
-            0xb9 => Instruction::Invokeinterface(
-                self.read_instruction_u16(address)?,
-                self.read_instruction_u8(address)?,
-            ),
+            0xba => {
+                let index = self.read_instruction_u16(address)?;
+                self.read_instruction_u16(address)?;
+                Instruction::Invokedynamic(index)
+            }
+            0xb9 => {
+                let index = self.read_instruction_u16(address)?;
+                let count = self.read_instruction_u8(address)?;
+                self.read_instruction_u8(address)?;
+                Instruction::Invokeinterface(index, count)
+            }
             0xb7 => Instruction::Invokespecial(self.read_instruction_u16(address)?),
             0xb8 => Instruction::Invokestatic(self.read_instruction_u16(address)?),
             0xb6 => Instruction::Invokevirtual(self.read_instruction_u16(address)?),
@@ -633,8 +855,8 @@ impl<'a> ClassFileReader<'a> {
             0x64 => Instruction::Isub,
             0x7c => Instruction::Iushr,
             0x82 => Instruction::Ixor,
-            0xa8 => todo!("Jsr"),
-            0xc9 => todo!("Jsr_w"),
+            0xa8 => Instruction::Jsr(self.read_instruction_u16(address)?),
+            0xc9 => Instruction::Jsr_w(self.read_instruction_i32(address)?),
             0x8a => Instruction::L2d,
             0x89 => Instruction::L2f,
             0x88 => Instruction::L2i,
@@ -656,7 +878,7 @@ impl<'a> ClassFileReader<'a> {
             0x21 => Instruction::Lload_3,
             0x69 => Instruction::Lmul,
             0x75 => Instruction::Lneg,
-            0xab => todo!("Lookupswitch"),
+            0xab => self.read_lookupswitch(address)?,
             0x81 => Instruction::Lor,
             0x71 => Instruction::Lrem,
             0xad => Instruction::Lreturn,
@@ -677,7 +899,7 @@ impl<'a> ClassFileReader<'a> {
                 self.read_instruction_u8(address)?,
             ),
             0xbb => Instruction::New(self.read_instruction_u16(address)?),
-            0xbc => todo!("Newarray"),
+            0xbc => Instruction::Newarray(self.read_instruction_u8(address)?),
             0x00 => Instruction::Nop,
             0x57 => Instruction::Pop,
             0x58 => Instruction::Pop2,
@@ -689,9 +911,9 @@ impl<'a> ClassFileReader<'a> {
             0x56 => Instruction::Sastore,
             0x11 => Instruction::Sipush(self.read_instruction_i16(address)?),
             0x5f => Instruction::Swap,
-            0xaa => todo!("Tableswitch"),
-            0xc4 => todo!("Wide"),
-            _ => panic!("at the disco"), // refactor
+            0xaa => self.read_tableswitch(address)?,
+            0xc4 => Instruction::Wide(self.read_wide(address)?),
+            _ => return Err(ClassReaderError::InvalidOpcode(index)),
         };
         Ok((instruction, current_address))
     }
@@ -707,6 +929,67 @@ impl<'a> ClassFileReader<'a> {
         Ok((index_byte1 << 8) | index_byte2)
     }
 
+    fn read_instruction_i32(&mut self, address: &mut u32) -> Result<i32> {
+        let high = self.read_instruction_u16(address)? as u32;
+        let low = self.read_instruction_u16(address)? as u32;
+        Ok(((high << 16) | low) as i32)
+    }
+
+    fn skip_switch_padding(&mut self, address: &mut u32) -> Result<()> {
+        while !address.is_multiple_of(4) {
+            self.read_instruction_u8(address)?;
+        }
+        Ok(())
+    }
+
+    fn read_lookupswitch(&mut self, address: &mut u32) -> Result<Instruction> {
+        self.skip_switch_padding(address)?;
+        let default = self.read_instruction_i32(address)?;
+        let npairs = self.read_instruction_i32(address)?;
+        let mut pairs = Vec::with_capacity(npairs.max(0) as usize);
+        for _ in 0..npairs {
+            let key = self.read_instruction_i32(address)?;
+            let offset = self.read_instruction_i32(address)?;
+            pairs.push((key, offset));
+        }
+        Ok(Instruction::Lookupswitch { default, pairs })
+    }
+
+    fn read_tableswitch(&mut self, address: &mut u32) -> Result<Instruction> {
+        self.skip_switch_padding(address)?;
+        let default = self.read_instruction_i32(address)?;
+        let low = self.read_instruction_i32(address)?;
+        let high = self.read_instruction_i32(address)?;
+        if high < low {
+            return Err(ClassReaderError::InvalidTableswitchBounds(low, high));
+        }
+        let mut offsets = Vec::with_capacity((high as i64 - low as i64 + 1) as usize);
+        for _ in low..=high {
+            offsets.push(self.read_instruction_i32(address)?);
+        }
+        Ok(Instruction::Tableswitch { default, low, high, offsets })
+    }
+
+    fn read_wide(&mut self, address: &mut u32) -> Result<WideInstruction> {
+        let opcode = self.read_instruction_u8(address)?;
+        let index = self.read_instruction_u16(address)?;
+        Ok(match opcode {
+            0x15 => WideInstruction::Iload(index),
+            0x16 => WideInstruction::Lload(index),
+            0x17 => WideInstruction::Fload(index),
+            0x18 => WideInstruction::Dload(index),
+            0x19 => WideInstruction::Aload(index),
+            0x36 => WideInstruction::Istore(index),
+            0x37 => WideInstruction::Lstore(index),
+            0x38 => WideInstruction::Fstore(index),
+            0x39 => WideInstruction::Dstore(index),
+            0x3a => WideInstruction::Astore(index),
+            0xa9 => WideInstruction::Ret(index),
+            0x84 => WideInstruction::Iinc(index, self.read_instruction_i16(address)?),
+            _ => return Err(ClassReaderError::InvalidWideOpcode(opcode)),
+        })
+    }
+
     fn read_instruction_i8(&mut self, address: &mut u32) -> Result<i8> {
         let byte = self.read_instruction_u8(address)?;
         Ok(byte as i8)
@@ -853,8 +1136,8 @@ impl<'a> ClassFileReader<'a> {
             6 => VerificationTypeInfo::UninitializedThis,
             7 => {
                 let cpool_index = self.byte_reader.read_u16()?;
-                let constant = self.class_file.constant_pool.get(cpool_index as usize)?;
-                VerificationTypeInfo::Object { constant: constant.clone() }
+                let constant = self.constant(cpool_index)?.into_owned();
+                VerificationTypeInfo::Object { constant }
             }
             8 => {
                 let offset = self.byte_reader.read_u16()?;
@@ -948,39 +1231,62 @@ impl<'a> ClassFileReader<'a> {
 
     fn read_class_attributes(&mut self) -> Result<()> {
         let attributes_count = self.byte_reader.read_u16()?;
-        let mut attributes = Vec::new();
+        self.class_file.attributes =
+            self.read_attributes(attributes_count, &AttributeLocation::Class)?;
+        Ok(())
+    }
+
+    fn read_attributes(
+        &mut self,
+        attributes_count: u16,
+        location: &AttributeLocation,
+    ) -> Result<Vec<Attribute>> {
+        let mut attributes = Vec::with_capacity(attributes_count as usize);
         for _ in 0..attributes_count {
             let name_index = self.byte_reader.read_u16()?;
-            let name = self.get_utf8(name_index)?;
-            let attr = match name.as_str() {
-                "NestHost" => self.read_nest_host_attr()?,
-                "NestMembers" => self.read_nest_members_attr()?,
-                "PermittedSubclasses" => self.read_permitted_subclasses_attr()?,
-                "SourceFile" => self.read_source_file_attr()?,
-                "BootstrapMethods" => self.read_bootstrap_methods_attr()?,
-                _ => self.read_user_defined_attr(name)?,
-            };
-            attributes.push(attr);
-        }
-        self.class_file.attributes = attributes;
-        Ok(())
+            attributes.push(self.read_attribute(name_index, location)?);
+        }
+        Ok(attributes)
+    }
+
+    /// Reads the length and body of an attribute whose name index has already been consumed.
+    pub(crate) fn read_attribute(
+        &mut self,
+        name_index: u16,
+        location: &AttributeLocation,
+    ) -> Result<Attribute> {
+        use AttributeLocation::*;
+
+        let name = self.get_utf8(name_index)?;
+        match (location, name.as_str()) {
+            (Field(field_type), "ConstantValue") => {
+                self.read_constant_value_attr(field_type.clone())
+            }
+            (Method, "Code") => self.read_code_attr(),
+            (Code, "LineNumberTable") => self.read_line_number_table_attr(),
+            (Code, "LocalVariableTable") => self.read_local_variable_table_attr(),
+            (Code, "LocalVariableTypeTable") => self.read_local_variable_type_table_attr(),
+            (Code, "StackMapTable") => self.read_stack_map_table_attr(),
+            (Class, "NestHost") => self.read_nest_host_attr(),
+            (Class, "NestMembers") => self.read_nest_members_attr(),
+            (Class, "PermittedSubclasses") => self.read_permitted_subclasses_attr(),
+            (Class, "SourceFile") => self.read_source_file_attr(),
+            (Class, "BootstrapMethods") => self.read_bootstrap_methods_attr(),
+            _ => self.read_user_defined_attr(name),
+        }
     }
 
     fn expect_method_handle(&self, index: u16) -> Result<()> {
-        match self.class_file.constant_pool.get(index as usize) {
-            Ok(Constant::MethodHandle(..)) => Ok(()),
-            Ok(actual) => Err(ClassReaderError::UnexpectedConstant {
+        match self.constant(index)?.as_ref() {
+            Constant::MethodHandle(..) => Ok(()),
+            actual => Err(ClassReaderError::UnexpectedConstant {
                 expected: "MethodHandle".to_string(),
                 actual: actual.name(),
             }),
-            Err(e) => Err(ClassReaderError::ConstantPoolError(e)),
         }
     }
 
     fn expect_constant(&self, index: u16) -> Result<()> {
-        match self.class_file.constant_pool.get(index as usize) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(ClassReaderError::ConstantPoolError(e)),
-        }
+        self.constant(index).map(|_| ())
     }
 }
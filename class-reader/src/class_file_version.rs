@@ -31,6 +31,14 @@ impl ClassFileVersion {
             Ok(ClassFileVersion(major_version, minor))
         }
     }
+
+    pub fn major(&self) -> u16 {
+        self.0.number()
+    }
+
+    pub fn minor(&self) -> u16 {
+        self.1
+    }
 }
 
 #[repr(u16)]
@@ -63,6 +71,13 @@ pub enum MajorVersion {
     JavaSE_23,
 }
 
+impl MajorVersion {
+    /// The `major_version` item of a class file, e.g. 61 for Java SE 17.
+    pub fn number(&self) -> u16 {
+        *self as u16 + 45
+    }
+}
+
 impl TryFrom<u16> for MajorVersion {
     type Error = FileVersionError;
 
@@ -121,6 +136,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_major_number_round_trip() {
+        assert_eq!(MajorVersion::try_from(67).unwrap().number(), 67);
+        assert_eq!(ClassFileVersion::from(45, 0).unwrap().major(), 45);
+    }
+
     #[test]
     fn test_minor_success() {
         assert!((ClassFileVersion::from(45, 3).is_err()));
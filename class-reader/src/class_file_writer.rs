@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+
+use crate::access_flag::AccessFlags;
+use crate::attribute::Attribute;
+use crate::byte_writer::ByteWriter;
+use crate::class_file::ClassFile;
+use crate::class_file_reader::AttributeLocation;
+use crate::class_file_version::ClassFileVersion;
+use crate::class_visitor::{ClassHeader, ClassVisitor, CodeCollector, FieldHeader, MethodHeader};
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::instruction::{Instruction, WideInstruction};
+use crate::predefined_attributes::{Code, ExceptionHandler, StackMapFrame, VerificationTypeInfo};
+
+type Result<T> = std::result::Result<T, ClassWriterError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClassWriterError {
+    #[error("Unexpected event {0}, the visitor events are out of order")]
+    #[non_exhaustive]
+    UnexpectedEvent(&'static str),
+    #[error("Constant pool cannot hold more than 65535 entries")]
+    #[non_exhaustive]
+    ConstantPoolOverflow,
+    #[error("Code of method {0} is {1} bytes long, the limit is 65535")]
+    #[non_exhaustive]
+    CodeTooLarge(String, usize),
+}
+
+/// Serializes class files, either from a [`ClassFile`] or as the last visitor of a chain.
+///
+/// The constant pool of the visited class is kept as is, so constant pool indices carried by
+/// instructions and attributes stay valid. Names that are not in the pool yet are appended to it.
+#[derive(Debug, Default)]
+pub struct ClassFileWriter {
+    version: ClassFileVersion,
+    constant_pool: ConstantPool,
+    utf8_indices: HashMap<String, u16>,
+    class_indices: HashMap<String, u16>,
+    header: ByteWriter,
+    fields: ByteWriter,
+    fields_count: u16,
+    methods: ByteWriter,
+    methods_count: u16,
+    attributes: ByteWriter,
+    attributes_count: u16,
+    member: Option<MemberWriter>,
+    code: CodeCollector,
+    started: bool,
+    error: Option<ClassWriterError>,
+}
+
+#[derive(Debug)]
+struct MemberWriter {
+    name: String,
+    header: ByteWriter,
+    attributes: ByteWriter,
+    attributes_count: u16,
+}
+
+impl ClassFileWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(class_file: &ClassFile) -> Result<Vec<u8>> {
+        let mut writer = ClassFileWriter::new();
+        class_file.accept(&mut writer);
+        writer.finish()
+    }
+
+    /// Returns the serialized class, or the first error encountered while visiting it.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !self.started || self.member.is_some() {
+            return Err(ClassWriterError::UnexpectedEvent("finish"));
+        }
+
+        let mut bytes = ByteWriter::new();
+        bytes.write_u32(0xCAFEBABE);
+        bytes.write_u16(self.version.minor());
+        bytes.write_u16(self.version.major());
+        bytes.write_u16(self.constant_pool.constants.len() as u16 + 1);
+        for constant in &self.constant_pool.constants {
+            write_constant(&mut bytes, constant);
+        }
+        bytes.write_bytes(self.header.as_slice());
+        bytes.write_u16(self.fields_count);
+        bytes.write_bytes(self.fields.as_slice());
+        bytes.write_u16(self.methods_count);
+        bytes.write_bytes(self.methods.as_slice());
+        bytes.write_u16(self.attributes_count);
+        bytes.write_bytes(self.attributes.as_slice());
+        Ok(bytes.into_bytes())
+    }
+
+    fn fail(&mut self, error: ClassWriterError) {
+        self.error.get_or_insert(error);
+    }
+
+    fn add_constant(&mut self, constant: Constant) -> u16 {
+        let wide = matches!(constant, Constant::Long(_) | Constant::Double(_));
+        if self.constant_pool.constants.len() + if wide { 2 } else { 1 } >= u16::MAX as usize {
+            self.fail(ClassWriterError::ConstantPoolOverflow);
+            return 0;
+        }
+        self.constant_pool.add(constant);
+        let slots = self.constant_pool.constants.len();
+        (if wide { slots - 1 } else { slots }) as u16
+    }
+
+    fn utf8_index(&mut self, string: &str) -> u16 {
+        if let Some(&index) = self.utf8_indices.get(string) {
+            return index;
+        }
+        let index = self.add_constant(Constant::Utf8(string.to_string()));
+        self.utf8_indices.insert(string.to_string(), index);
+        index
+    }
+
+    fn class_index(&mut self, class_name: &str) -> u16 {
+        if let Some(&index) = self.class_indices.get(class_name) {
+            return index;
+        }
+        let name_index = self.utf8_index(class_name);
+        let index = self.add_constant(Constant::ClassIndex(name_index));
+        self.class_indices.insert(class_name.to_string(), index);
+        index
+    }
+
+    fn constant_index(&mut self, constant: &Constant) -> u16 {
+        if let Constant::Utf8(string) = constant {
+            return self.utf8_index(string);
+        }
+        match self.constant_pool.constants.iter().position(|c| is_same_constant(c, constant)) {
+            Some(position) => position as u16 + 1,
+            None => self.add_constant(constant.clone()),
+        }
+    }
+
+    fn index_constant_pool(&mut self) {
+        for (position, constant) in self.constant_pool.constants.iter().enumerate() {
+            if let Constant::Utf8(string) = constant {
+                self.utf8_indices.entry(string.clone()).or_insert(position as u16 + 1);
+            }
+        }
+        for (position, constant) in self.constant_pool.constants.iter().enumerate() {
+            if let Constant::ClassIndex(name_index) = constant {
+                if let Ok(Constant::Utf8(name)) = self.constant_pool.get(*name_index as usize) {
+                    self.class_indices.entry(name.clone()).or_insert(position as u16 + 1);
+                }
+            }
+        }
+    }
+
+    fn start_member(&mut self, event: &'static str, flags: u16, name: String, descriptor: String) {
+        if !self.started || self.member.is_some() {
+            self.fail(ClassWriterError::UnexpectedEvent(event));
+            return;
+        }
+        let mut header = ByteWriter::new();
+        header.write_u16(flags);
+        header.write_u16(self.utf8_index(&name));
+        header.write_u16(self.utf8_index(&descriptor));
+        self.member =
+            Some(MemberWriter { name, header, attributes: ByteWriter::new(), attributes_count: 0 });
+    }
+
+    fn end_member(&mut self, event: &'static str) -> Option<ByteWriter> {
+        let Some(member) = self.member.take() else {
+            self.fail(ClassWriterError::UnexpectedEvent(event));
+            return None;
+        };
+        let mut bytes = member.header;
+        bytes.write_u16(member.attributes_count);
+        bytes.write_bytes(member.attributes.as_slice());
+        Some(bytes)
+    }
+
+    fn write_attribute(&mut self, attribute: &Attribute) -> ByteWriter {
+        let name_index = self.utf8_index(attribute.name());
+        let body = self.attribute_body(attribute);
+        let mut bytes = ByteWriter::new();
+        bytes.write_u16(name_index);
+        bytes.write_u32(body.len() as u32);
+        bytes.write_bytes(body.as_slice());
+        bytes
+    }
+
+    fn attribute_body(&mut self, attribute: &Attribute) -> ByteWriter {
+        let mut body = ByteWriter::new();
+        match attribute {
+            Attribute::ConstantValue(constant_value) => {
+                body.write_u16(self.constant_index(&constant_value.value));
+            }
+            Attribute::Code(code) => self.write_code(&mut body, code),
+            Attribute::StackMapTable(stack_map_table) => {
+                body.write_u16(stack_map_table.frames.len() as u16);
+                for frame in &stack_map_table.frames {
+                    self.write_stack_map_frame(&mut body, frame);
+                }
+            }
+            Attribute::LineNumberTable(line_number_table) => {
+                body.write_u16(line_number_table.line_number_table.len() as u16);
+                for line_number in &line_number_table.line_number_table {
+                    body.write_pair_u16((line_number.start_pc, line_number.line_number));
+                }
+            }
+            Attribute::LocalVariableTable(local_variable_table) => {
+                body.write_u16(local_variable_table.local_variable_table.len() as u16);
+                for variable in &local_variable_table.local_variable_table {
+                    body.write_pair_u16((variable.start_pc, variable.length));
+                    body.write_pair_u16((variable.name_index, variable.descriptor_index));
+                    body.write_u16(variable.index);
+                }
+            }
+            Attribute::LocalVariableTypeTable(local_variable_type_table) => {
+                body.write_u16(local_variable_type_table.local_variable_type_table.len() as u16);
+                for variable in &local_variable_type_table.local_variable_type_table {
+                    body.write_pair_u16((variable.start_pc, variable.length));
+                    body.write_pair_u16((variable.name_index, variable.signature_index));
+                    body.write_u16(variable.index);
+                }
+            }
+            Attribute::NestHost(nest_host) => body.write_u16(self.class_index(&nest_host.name)),
+            Attribute::NestMembers(nest_members) => {
+                self.write_class_names(&mut body, &nest_members.names)
+            }
+            Attribute::PermittedSubclasses(permitted_subclasses) => {
+                self.write_class_names(&mut body, &permitted_subclasses.names)
+            }
+            Attribute::UserDefined(user_defined) => body.write_bytes(user_defined.info()),
+            Attribute::SourceFile(source_file) => {
+                body.write_u16(self.utf8_index(&source_file.file_name))
+            }
+            Attribute::BootstrapMethods(bootstrap_methods) => {
+                body.write_u16(bootstrap_methods.bootstrap_methods.len() as u16);
+                for bootstrap_method in &bootstrap_methods.bootstrap_methods {
+                    body.write_u16(bootstrap_method.bootstrap_method_ref);
+                    body.write_u16(bootstrap_method.bootstrap_arguments.len() as u16);
+                    for &argument in &bootstrap_method.bootstrap_arguments {
+                        body.write_u16(argument);
+                    }
+                }
+            }
+        }
+        body
+    }
+
+    fn write_class_names(&mut self, body: &mut ByteWriter, names: &[String]) {
+        body.write_u16(names.len() as u16);
+        for name in names {
+            body.write_u16(self.class_index(name));
+        }
+    }
+
+    fn write_code(&mut self, body: &mut ByteWriter, code: &Code) {
+        body.write_pair_u16((code.max_stack, code.max_locals));
+
+        let mut instructions = ByteWriter::new();
+        for (instruction, _) in &code.code {
+            write_instruction(&mut instructions, instruction);
+        }
+        if instructions.len() > u16::MAX as usize {
+            let name = self.member.as_ref().map(|member| member.name.clone()).unwrap_or_default();
+            self.fail(ClassWriterError::CodeTooLarge(name, instructions.len()));
+        }
+        body.write_u32(instructions.len() as u32);
+        body.write_bytes(instructions.as_slice());
+
+        body.write_u16(code.exception_table.len() as u16);
+        for ExceptionHandler { start_pc, end_pc, handler_pc, catch_type } in &code.exception_table {
+            body.write_pair_u16((*start_pc, *end_pc));
+            body.write_pair_u16((*handler_pc, *catch_type));
+        }
+
+        body.write_u16(code.attributes.len() as u16);
+        for attribute in &code.attributes {
+            let attribute = self.write_attribute(attribute);
+            body.write_bytes(attribute.as_slice());
+        }
+    }
+
+    fn write_stack_map_frame(&mut self, body: &mut ByteWriter, frame: &StackMapFrame) {
+        match frame {
+            StackMapFrame::SameFrame { frame_type } => body.write_u8(*frame_type),
+            StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
+                body.write_u8(*frame_type);
+                self.write_verification_type(body, stack);
+            }
+            StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type,
+                offset_delta,
+                stack,
+            } => {
+                body.write_u8(*frame_type);
+                body.write_u16(*offset_delta);
+                self.write_verification_type(body, stack);
+            }
+            StackMapFrame::ChopFrame { frame_type, offset_delta }
+            | StackMapFrame::SameFrameExtended { frame_type, offset_delta } => {
+                body.write_u8(*frame_type);
+                body.write_u16(*offset_delta);
+            }
+            StackMapFrame::AppendFrame { frame_type, offset_delta, locals } => {
+                body.write_u8(*frame_type);
+                body.write_u16(*offset_delta);
+                for local in locals {
+                    self.write_verification_type(body, local);
+                }
+            }
+            StackMapFrame::FullFrame { frame_type, offset_delta, locals, stack } => {
+                body.write_u8(*frame_type);
+                body.write_u16(*offset_delta);
+                body.write_u16(locals.len() as u16);
+                for local in locals {
+                    self.write_verification_type(body, local);
+                }
+                body.write_u16(stack.len() as u16);
+                for item in stack {
+                    self.write_verification_type(body, item);
+                }
+            }
+        }
+    }
+
+    fn write_verification_type(&mut self, body: &mut ByteWriter, info: &VerificationTypeInfo) {
+        match info {
+            VerificationTypeInfo::Top => body.write_u8(0),
+            VerificationTypeInfo::Integer => body.write_u8(1),
+            VerificationTypeInfo::Float => body.write_u8(2),
+            VerificationTypeInfo::Double => body.write_u8(3),
+            VerificationTypeInfo::Long => body.write_u8(4),
+            VerificationTypeInfo::Null => body.write_u8(5),
+            VerificationTypeInfo::UninitializedThis => body.write_u8(6),
+            VerificationTypeInfo::Object { constant } => {
+                body.write_u8(7);
+                body.write_u16(self.constant_index(constant));
+            }
+            VerificationTypeInfo::Uninitialized { offset } => {
+                body.write_u8(8);
+                body.write_u16(*offset);
+            }
+        }
+    }
+}
+
+impl ClassVisitor for ClassFileWriter {
+    fn visit_class(&mut self, header: ClassHeader) {
+        if self.started {
+            self.fail(ClassWriterError::UnexpectedEvent("visit_class"));
+            return;
+        }
+        self.started = true;
+        self.version = header.version;
+        self.constant_pool = header.constant_pool;
+        self.index_constant_pool();
+
+        let this_class = self.class_index(&header.this_class);
+        let super_class = header.super_class.map_or(0, |name| self.class_index(&name));
+        let interfaces =
+            header.interfaces.iter().map(|name| self.class_index(name)).collect::<Vec<_>>();
+
+        self.header.write_u16(header.flags.mask());
+        self.header.write_pair_u16((this_class, super_class));
+        self.header.write_u16(interfaces.len() as u16);
+        for interface in interfaces {
+            self.header.write_u16(interface);
+        }
+    }
+
+    fn visit_field(&mut self, header: FieldHeader) {
+        let descriptor = header.type_descriptor.to_string();
+        self.start_member("visit_field", header.flags.mask(), header.name, descriptor);
+    }
+
+    fn visit_field_end(&mut self) {
+        if let Some(bytes) = self.end_member("visit_field_end") {
+            self.fields.write_bytes(bytes.as_slice());
+            self.fields_count += 1;
+        }
+    }
+
+    fn visit_method(&mut self, header: MethodHeader) {
+        let descriptor = header.type_descriptor.to_string();
+        self.start_member("visit_method", header.flags.mask(), header.name, descriptor);
+    }
+
+    fn visit_code(&mut self, max_stack: u16, max_locals: u16) {
+        if self.member.is_none() || self.code.code.is_some() {
+            self.fail(ClassWriterError::UnexpectedEvent("visit_code"));
+            return;
+        }
+        self.code.visit_code(max_stack, max_locals);
+    }
+
+    fn visit_instruction(&mut self, instruction: Instruction, pc: u32) {
+        self.code.visit_instruction(instruction, pc);
+    }
+
+    fn visit_exception_handler(&mut self, handler: ExceptionHandler) {
+        self.code.visit_exception_handler(handler);
+    }
+
+    fn visit_code_end(&mut self) {
+        match self.code.code.take() {
+            Some(code) => self.visit_attribute(&AttributeLocation::Method, Attribute::Code(code)),
+            None => self.fail(ClassWriterError::UnexpectedEvent("visit_code_end")),
+        }
+    }
+
+    fn visit_method_end(&mut self) {
+        if let Some(bytes) = self.end_member("visit_method_end") {
+            self.methods.write_bytes(bytes.as_slice());
+            self.methods_count += 1;
+        }
+    }
+
+    fn visit_attribute(&mut self, location: &AttributeLocation, attribute: Attribute) {
+        if !self.started {
+            self.fail(ClassWriterError::UnexpectedEvent("visit_attribute"));
+            return;
+        }
+        if let AttributeLocation::Code = location {
+            self.code.visit_attribute(location, attribute);
+            return;
+        }
+        let bytes = self.write_attribute(&attribute);
+        match (location, self.member.as_mut()) {
+            (AttributeLocation::Class, None) => {
+                self.attributes.write_bytes(bytes.as_slice());
+                self.attributes_count += 1;
+            }
+            (AttributeLocation::Field(_) | AttributeLocation::Method, Some(member)) => {
+                member.attributes.write_bytes(bytes.as_slice());
+                member.attributes_count += 1;
+            }
+            _ => self.fail(ClassWriterError::UnexpectedEvent("visit_attribute")),
+        }
+    }
+}
+
+fn write_constant(bytes: &mut ByteWriter, constant: &Constant) {
+    match constant {
+        Constant::Utf8(string) => {
+            bytes.write_u8(1);
+            bytes.write_utf8(string);
+        }
+        Constant::Integer(integer) => {
+            bytes.write_u8(3);
+            bytes.write_i32(*integer);
+        }
+        Constant::Float(float) => {
+            bytes.write_u8(4);
+            bytes.write_f32(*float);
+        }
+        Constant::Long(long) => {
+            bytes.write_u8(5);
+            bytes.write_i64(*long);
+        }
+        Constant::Double(double) => {
+            bytes.write_u8(6);
+            bytes.write_f64(*double);
+        }
+        Constant::ClassIndex(name_index) => {
+            bytes.write_u8(7);
+            bytes.write_u16(*name_index);
+        }
+        Constant::StringIndex(string_index) => {
+            bytes.write_u8(8);
+            bytes.write_u16(*string_index);
+        }
+        Constant::FieldRef(class_index, name_and_type_index) => {
+            bytes.write_u8(9);
+            bytes.write_pair_u16((*class_index, *name_and_type_index));
+        }
+        Constant::MethodRef(class_index, name_and_type_index) => {
+            bytes.write_u8(10);
+            bytes.write_pair_u16((*class_index, *name_and_type_index));
+        }
+        Constant::InterfaceMethodRef(class_index, name_and_type_index) => {
+            bytes.write_u8(11);
+            bytes.write_pair_u16((*class_index, *name_and_type_index));
+        }
+        Constant::NameAndType(name_index, descriptor_index) => {
+            bytes.write_u8(12);
+            bytes.write_pair_u16((*name_index, *descriptor_index));
+        }
+        Constant::MethodHandle(reference_kind, reference_index) => {
+            bytes.write_u8(15);
+            bytes.write_u8(*reference_kind);
+            bytes.write_u16(*reference_index);
+        }
+        Constant::MethodType(descriptor_index) => {
+            bytes.write_u8(16);
+            bytes.write_u16(*descriptor_index);
+        }
+        Constant::Dynamic(bootstrap_method_attr_index, name_and_type_index) => {
+            bytes.write_u8(17);
+            bytes.write_pair_u16((*bootstrap_method_attr_index, *name_and_type_index));
+        }
+        Constant::InvokeDynamic(bootstrap_method_attr_index, name_and_type_index) => {
+            bytes.write_u8(18);
+            bytes.write_pair_u16((*bootstrap_method_attr_index, *name_and_type_index));
+        }
+        Constant::Module(name_index) => {
+            bytes.write_u8(19);
+            bytes.write_u16(*name_index);
+        }
+        Constant::Package(name_index) => {
+            bytes.write_u8(20);
+            bytes.write_u16(*name_index);
+        }
+        Constant::Unsuable => {}
+    }
+}
+
+/// Encodes `instruction` at the end of `code`, which must start at pc 0 so that switch padding
+/// is computed correctly.
+pub(crate) fn write_instruction(code: &mut ByteWriter, instruction: &Instruction) {
+    code.write_u8(instruction.opcode());
+    match instruction {
+        Instruction::Aload(operand)
+        | Instruction::Astore(operand)
+        | Instruction::Bipush(operand)
+        | Instruction::Dload(operand)
+        | Instruction::Dstore(operand)
+        | Instruction::Fload(operand)
+        | Instruction::Fstore(operand)
+        | Instruction::Iload(operand)
+        | Instruction::Istore(operand)
+        | Instruction::Ldc(operand)
+        | Instruction::Lload(operand)
+        | Instruction::Lstore(operand)
+        | Instruction::Newarray(operand)
+        | Instruction::Ret(operand) => code.write_u8(*operand),
+        Instruction::Anewarray(operand)
+        | Instruction::Checkcast(operand)
+        | Instruction::Getfield(operand)
+        | Instruction::Getstatic(operand)
+        | Instruction::Goto(operand)
+        | Instruction::If_acmpeq(operand)
+        | Instruction::If_acmpne(operand)
+        | Instruction::If_icmpeq(operand)
+        | Instruction::If_icmpne(operand)
+        | Instruction::If_icmplt(operand)
+        | Instruction::If_icmpge(operand)
+        | Instruction::If_icmpgt(operand)
+        | Instruction::If_icmple(operand)
+        | Instruction::Ifeq(operand)
+        | Instruction::Ifne(operand)
+        | Instruction::Iflt(operand)
+        | Instruction::Ifge(operand)
+        | Instruction::Ifgt(operand)
+        | Instruction::Ifle(operand)
+        | Instruction::Ifnonnull(operand)
+        | Instruction::Ifnull(operand)
+        | Instruction::Instanceof(operand)
+        | Instruction::Invokespecial(operand)
+        | Instruction::Invokestatic(operand)
+        | Instruction::Invokevirtual(operand)
+        | Instruction::Jsr(operand)
+        | Instruction::Ldc_w(operand)
+        | Instruction::Ldc2_w(operand)
+        | Instruction::New(operand)
+        | Instruction::Putfield(operand)
+        | Instruction::Putstatic(operand) => code.write_u16(*operand),
+        Instruction::Goto_w(operand) | Instruction::Jsr_w(operand) => code.write_i32(*operand),
+        Instruction::Sipush(operand) => code.write_u16(*operand as u16),
+        Instruction::Iinc(index, value) => {
+            code.write_u8(*index);
+            code.write_u8(*value as u8);
+        }
+        Instruction::Multianewarray(index, dimensions) => {
+            code.write_u16(*index);
+            code.write_u8(*dimensions);
+        }
+        Instruction::Invokedynamic(index) => {
+            code.write_u16(*index);
+            code.write_u16(0);
+        }
+        Instruction::Invokeinterface(index, count) => {
+            code.write_u16(*index);
+            code.write_u8(*count);
+            code.write_u8(0);
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            write_switch_padding(code);
+            code.write_i32(*default);
+            code.write_i32(pairs.len() as i32);
+            for (key, offset) in pairs {
+                code.write_i32(*key);
+                code.write_i32(*offset);
+            }
+        }
+        Instruction::Tableswitch { default, low, high, offsets } => {
+            write_switch_padding(code);
+            code.write_i32(*default);
+            code.write_i32(*low);
+            code.write_i32(*high);
+            for offset in offsets {
+                code.write_i32(*offset);
+            }
+        }
+        Instruction::Wide(wide) => {
+            code.write_u8(wide.opcode());
+            match wide {
+                WideInstruction::Iinc(index, value) => {
+                    code.write_u16(*index);
+                    code.write_u16(*value as u16);
+                }
+                WideInstruction::Aload(index)
+                | WideInstruction::Astore(index)
+                | WideInstruction::Dload(index)
+                | WideInstruction::Dstore(index)
+                | WideInstruction::Fload(index)
+                | WideInstruction::Fstore(index)
+                | WideInstruction::Iload(index)
+                | WideInstruction::Istore(index)
+                | WideInstruction::Lload(index)
+                | WideInstruction::Lstore(index)
+                | WideInstruction::Ret(index) => code.write_u16(*index),
+            }
+        }
+        _ => {}
+    }
+}
+
+fn write_switch_padding(code: &mut ByteWriter) {
+    while !code.len().is_multiple_of(4) {
+        code.write_u8(0);
+    }
+}
+
+/// Compares floating point constants by their bits, so that a NaN matches itself and `0.0`
+/// does not match `-0.0`.
+pub(crate) fn is_same_constant(a: &Constant, b: &Constant) -> bool {
+    match (a, b) {
+        (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+        (Constant::Double(a), Constant::Double(b)) => a.to_bits() == b.to_bits(),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::class_file_reader::ClassFileReader;
+
+    #[test]
+    fn test_code_round_trip() {
+        let code = Code {
+            max_stack: 2,
+            max_locals: 400,
+            code: vec![
+                (Instruction::Wide(WideInstruction::Iinc(300, -2)), 0),
+                (Instruction::Wide(WideInstruction::Iload(300)), 6),
+                (
+                    Instruction::Tableswitch {
+                        default: 28,
+                        low: 0,
+                        high: 1,
+                        offsets: vec![24, 26],
+                    },
+                    10,
+                ),
+                (Instruction::Lookupswitch { default: 10, pairs: vec![(-5, 8)] }, 32),
+                (Instruction::Goto_w(-52), 52),
+                (Instruction::Return, 57),
+            ],
+            exception_table: vec![],
+            attributes: vec![],
+        };
+
+        let mut writer = ClassFileWriter::new();
+        writer.started = true;
+        writer.constant_pool.add(Constant::Utf8("Code".to_string()));
+        writer.index_constant_pool();
+        let bytes = writer.write_attribute(&Attribute::Code(code.clone())).into_bytes();
+
+        let constant_pool = writer.constant_pool;
+        let mut reader = ClassFileReader::with_constant_pool(&bytes[2..], &constant_pool);
+        let attribute = reader.read_attribute(1, &AttributeLocation::Method).unwrap();
+        assert_eq!(attribute, Attribute::Code(code));
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{BTreeSet, HashMap};
+
+use thiserror::Error;
+
+use crate::access_flag::{AccessFlags, ClassFileAccessFlags, ClassFlag};
+use crate::class_file::ClassFile;
+use crate::class_file_reader::ClassFileReader;
+use crate::classpath::{Classpath, ClasspathError};
+use crate::method::{MethodAccessFlags, MethodFlag};
+
+type Result<T> = std::result::Result<T, HierarchyError>;
+
+const OBJECT: &str = "java/lang/Object";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HierarchyError {
+    #[error("Class {0} is not part of the hierarchy")]
+    #[non_exhaustive]
+    MissingClass(String),
+}
+
+/// What the hierarchy keeps of a class: its supertypes and method signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassNode {
+    pub name: String,
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+    pub flags: ClassFileAccessFlags,
+    pub methods: Vec<MethodEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodEntry {
+    pub name: String,
+    pub descriptor: String,
+    pub flags: MethodAccessFlags,
+}
+
+impl ClassNode {
+    pub fn new(class: &ClassFile) -> Self {
+        let methods = class
+            .methods
+            .iter()
+            .map(|method| MethodEntry {
+                name: method.name.clone(),
+                descriptor: method.type_descriptor.to_string(),
+                flags: method.flags.clone(),
+            })
+            .collect();
+        ClassNode {
+            name: class.this_class.clone(),
+            super_class: class.super_class.clone(),
+            interfaces: class.interfaces.clone(),
+            flags: class.flags.clone(),
+            methods,
+        }
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.flags.contains(ClassFlag::Interface)
+    }
+
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&MethodEntry> {
+        self.methods.iter().find(|method| method.name == name && method.descriptor == descriptor)
+    }
+
+    /// The supertypes named by this class: its superclass followed by its interfaces.
+    pub fn direct_supertypes(&self) -> impl Iterator<Item = &str> {
+        self.super_class.iter().chain(&self.interfaces).map(String::as_str)
+    }
+}
+
+impl MethodEntry {
+    pub fn is_abstract(&self) -> bool {
+        self.flags.contains(MethodFlag::Abstract)
+    }
+
+    /// Whether the method takes part in overriding, i.e. is neither private nor static.
+    pub fn is_virtual(&self) -> bool {
+        !self.flags.contains(MethodFlag::Private) && !self.flags.is_static()
+    }
+}
+
+/// The subtype graph of a set of classes, such as a whole classpath.
+///
+/// Queries that walk up the hierarchy fail with [`HierarchyError::MissingClass`] when they
+/// reach a class that was never added, except for `java/lang/Object`, which is known to have
+/// no supertypes. Overriding is decided by name and descriptor alone; package-private methods
+/// are treated like protected ones.
+#[derive(Debug, Clone, Default)]
+pub struct ClassHierarchy {
+    nodes: HashMap<String, ClassNode>,
+    direct_subtypes: HashMap<String, BTreeSet<String>>,
+}
+
+impl ClassHierarchy {
+    pub fn new() -> Self {
+        ClassHierarchy::default()
+    }
+
+    pub fn from_classes<'a, I: IntoIterator<Item = &'a ClassFile>>(classes: I) -> Self {
+        let mut hierarchy = ClassHierarchy::new();
+        for class in classes {
+            hierarchy.add_class(class);
+        }
+        hierarchy
+    }
+
+    /// Reads every class of `classpath`.
+    pub fn from_classpath(classpath: &mut Classpath) -> std::result::Result<Self, ClasspathError> {
+        let mut hierarchy = ClassHierarchy::new();
+        for name in classpath.class_names()? {
+            if let Some(bytes) = classpath.class_bytes(&name)? {
+                let class = ClassFileReader::read_class(&bytes)
+                    .map_err(|err| ClasspathError::Class(name.clone(), err))?;
+                hierarchy.add_class(&class);
+            }
+        }
+        Ok(hierarchy)
+    }
+
+    /// Adds a class unless a class of the same name was added before, mirroring the first-wins
+    /// lookup of a classpath.
+    pub fn add_class(&mut self, class: &ClassFile) -> &mut Self {
+        self.add_node(ClassNode::new(class))
+    }
+
+    pub fn add_node(&mut self, node: ClassNode) -> &mut Self {
+        if self.nodes.contains_key(&node.name) {
+            return self;
+        }
+        for supertype in node.direct_supertypes() {
+            self.direct_subtypes
+                .entry(supertype.to_string())
+                .or_default()
+                .insert(node.name.clone());
+        }
+        self.nodes.insert(node.name.clone(), node);
+        self
+    }
+
+    /// Adds the class `name` along with all of its supertypes found on `classpath`. Supertypes
+    /// the classpath lacks are left out and show up in [`missing_classes`](Self::missing_classes).
+    pub fn add_with_supertypes(
+        &mut self,
+        classpath: &mut Classpath,
+        name: &str,
+    ) -> std::result::Result<&mut Self, ClasspathError> {
+        let mut pending = vec![name.replace('.', "/")];
+        while let Some(name) = pending.pop() {
+            if self.nodes.contains_key(&name) {
+                continue;
+            }
+            if let Some(class) = classpath.load(&name)? {
+                pending.extend(class.super_class.iter().chain(&class.interfaces).cloned());
+                self.add_class(&class);
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.nodes.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ClassNode> {
+        self.nodes.get(name)
+    }
+
+    fn node(&self, name: &str) -> Result<&ClassNode> {
+        self.nodes.get(name).ok_or_else(|| HierarchyError::MissingClass(name.to_string()))
+    }
+
+    /// Supertypes referenced by added classes that were not added themselves, in sorted order.
+    pub fn missing_classes(&self) -> BTreeSet<&str> {
+        self.direct_subtypes
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !self.nodes.contains_key(*name) && *name != OBJECT)
+            .collect()
+    }
+
+    /// The superclasses of `name`, nearest first and ending with `java/lang/Object`.
+    pub fn superclasses(&self, name: &str) -> Result<Vec<&str>> {
+        let mut superclasses = Vec::new();
+        let mut current = self.node(name)?;
+        while let Some(superclass) = current.super_class.as_deref() {
+            if superclasses.contains(&superclass) {
+                break;
+            }
+            superclasses.push(superclass);
+            if superclass == OBJECT {
+                break;
+            }
+            current = self.node(superclass)?;
+        }
+        Ok(superclasses)
+    }
+
+    /// Every superclass and superinterface of `name`, direct or inherited.
+    pub fn supertypes(&self, name: &str) -> Result<BTreeSet<&str>> {
+        let mut supertypes = BTreeSet::new();
+        let mut pending = vec![self.node(name)?];
+        while let Some(node) = pending.pop() {
+            for supertype in node.direct_supertypes() {
+                if supertypes.insert(supertype) && supertype != OBJECT {
+                    pending.push(self.node(supertype)?);
+                }
+            }
+        }
+        Ok(supertypes)
+    }
+
+    /// The classes and interfaces that directly extend or implement `name`.
+    pub fn direct_subtypes(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.direct_subtypes.get(name).into_iter().flatten().map(String::as_str)
+    }
+
+    /// Every class and interface that extends or implements `name`, directly or not. Only
+    /// added classes are known, so this never fails.
+    pub fn subtypes(&self, name: &str) -> BTreeSet<&str> {
+        let mut subtypes = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            for subtype in self.direct_subtypes(name) {
+                if subtypes.insert(subtype) {
+                    pending.push(subtype);
+                }
+            }
+        }
+        subtypes
+    }
+
+    /// The classes, not interfaces, that implement the interface `name`, directly, through a
+    /// superclass or through a subinterface.
+    pub fn implementors(&self, name: &str) -> BTreeSet<&str> {
+        self.subtypes(name)
+            .into_iter()
+            .filter(|subtype| self.nodes.get(*subtype).is_some_and(|node| !node.is_interface()))
+            .collect()
+    }
+
+    /// Whether a value of type `name` can be assigned to `supertype`.
+    pub fn is_subtype(&self, name: &str, supertype: &str) -> Result<bool> {
+        Ok(name == supertype || supertype == OBJECT || self.supertypes(name)?.contains(supertype))
+    }
+
+    /// The nearest class both types extend, as the verifier merges them: `java/lang/Object`
+    /// if either is an interface or an array, unless one type is assignable to the other.
+    pub fn least_common_superclass(&self, first: &str, second: &str) -> Result<String> {
+        if first == second {
+            return Ok(first.to_string());
+        }
+        if first.starts_with('[') || second.starts_with('[') {
+            return Ok(OBJECT.to_string());
+        }
+        if self.is_subtype(second, first)? {
+            return Ok(first.to_string());
+        }
+        if self.is_subtype(first, second)? {
+            return Ok(second.to_string());
+        }
+        if self.node(first)?.is_interface() || self.node(second)?.is_interface() {
+            return Ok(OBJECT.to_string());
+        }
+        let second_superclasses = self.superclasses(second)?;
+        let common = self
+            .superclasses(first)?
+            .into_iter()
+            .find(|superclass| second_superclasses.contains(superclass))
+            .unwrap_or(OBJECT);
+        Ok(common.to_string())
+    }
+
+    /// The class or interface a reference to `name:descriptor` on `class` resolves to, per
+    /// JVMS 5.4.3.3: the class and its superclasses first, then a maximally specific
+    /// superinterface method, preferring one with a body.
+    pub fn resolve_method(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<&str>> {
+        let mut owners = vec![class];
+        owners.extend(self.superclasses(class)?);
+        for owner in owners {
+            if owner == OBJECT && !self.contains(OBJECT) {
+                break;
+            }
+            if self.node(owner)?.method(name, descriptor).is_some() {
+                return Ok(Some(self.node(owner)?.name.as_str()));
+            }
+        }
+        let candidates = self.maximally_specific(class, name, descriptor)?;
+        let concrete = candidates.iter().find(|owner| {
+            self.nodes[**owner].method(name, descriptor).is_some_and(|method| !method.is_abstract())
+        });
+        Ok(concrete.or(candidates.first()).copied())
+    }
+
+    /// The class whose method an invocation of `name:descriptor` on an instance of `receiver`
+    /// runs, per JVMS 5.4.6: the nearest concrete declaration in the superclass chain, or
+    /// else the single maximally specific default method. `None` if no body is found or the
+    /// default methods conflict.
+    pub fn select_method(
+        &self,
+        receiver: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Option<&str>> {
+        let mut owners = vec![receiver];
+        owners.extend(self.superclasses(receiver)?);
+        for owner in owners {
+            if owner == OBJECT && !self.contains(OBJECT) {
+                break;
+            }
+            let node = self.node(owner)?;
+            if let Some(method) = node.method(name, descriptor) {
+                if method.is_virtual() && !method.is_abstract() {
+                    return Ok(Some(node.name.as_str()));
+                }
+            }
+        }
+        let defaults = self
+            .maximally_specific(receiver, name, descriptor)?
+            .into_iter()
+            .filter(|owner| {
+                self.nodes[*owner]
+                    .method(name, descriptor)
+                    .is_some_and(|method| !method.is_abstract())
+            })
+            .collect::<Vec<_>>();
+        Ok(match defaults[..] {
+            [owner] => Some(owner),
+            _ => None,
+        })
+    }
+
+    /// The superinterfaces of `class` declaring a virtual `name:descriptor` that no other such
+    /// interface overrides.
+    fn maximally_specific(&self, class: &str, name: &str, descriptor: &str) -> Result<Vec<&str>> {
+        let mut candidates = Vec::new();
+        for interface in self.supertypes(class)? {
+            if interface == OBJECT && !self.contains(OBJECT) {
+                continue;
+            }
+            let node = self.node(interface)?;
+            if node.is_interface()
+                && node.method(name, descriptor).is_some_and(MethodEntry::is_virtual)
+            {
+                candidates.push(node.name.as_str());
+            }
+        }
+        let mut specific = Vec::new();
+        for candidate in &candidates {
+            let mut overridden = false;
+            for other in &candidates {
+                if other != candidate && self.supertypes(other)?.contains(candidate) {
+                    overridden = true;
+                    break;
+                }
+            }
+            if !overridden {
+                specific.push(*candidate);
+            }
+        }
+        Ok(specific)
+    }
+
+    /// The supertypes of `class` declaring a method that its `name:descriptor` overrides, in
+    /// sorted order.
+    pub fn overridden_methods(
+        &self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<Vec<&str>> {
+        let mut owners = Vec::new();
+        for supertype in self.supertypes(class)? {
+            if supertype == OBJECT && !self.contains(OBJECT) {
+                continue;
+            }
+            let node = self.node(supertype)?;
+            if node.method(name, descriptor).is_some_and(MethodEntry::is_virtual) {
+                owners.push(node.name.as_str());
+            }
+        }
+        Ok(owners)
+    }
+
+    /// `class` and its subtypes that declare a concrete, virtual `name:descriptor`, in sorted
+    /// order: the bodies a virtual call of the method on `class` may run.
+    pub fn implementations(&self, class: &str, name: &str, descriptor: &str) -> Vec<&str> {
+        let mut types = self.subtypes(class);
+        types.insert(class);
+        types
+            .into_iter()
+            .filter_map(|name| self.nodes.get(name))
+            .filter(|node| {
+                node.method(name, descriptor)
+                    .is_some_and(|method| method.is_virtual() && !method.is_abstract())
+            })
+            .map(|node| node.name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClassHierarchy, ClassNode, HierarchyError, MethodEntry};
+    use crate::access_flag::ClassFileAccessFlags;
+    use crate::method::MethodAccessFlags;
+
+    fn node(name: &str, super_class: Option<&str>, interfaces: &[&str], flags: u16) -> ClassNode {
+        ClassNode {
+            name: name.to_string(),
+            super_class: super_class.map(str::to_string),
+            interfaces: interfaces.iter().map(|name| name.to_string()).collect(),
+            flags: ClassFileAccessFlags::new(flags),
+            methods: Vec::new(),
+        }
+    }
+
+    fn with_method(mut node: ClassNode, name: &str, flags: u16) -> ClassNode {
+        let descriptor = "()V".to_string();
+        node.methods.push(MethodEntry {
+            name: name.to_string(),
+            descriptor,
+            flags: MethodAccessFlags::new(flags),
+        });
+        node
+    }
+
+    #[test]
+    fn test_missing_classes() {
+        let mut hierarchy = ClassHierarchy::new();
+        hierarchy
+            .add_node(node("a/A", Some("java/lang/Object"), &["a/Missing"], 0x0021))
+            .add_node(node("a/B", Some("a/A"), &[], 0x0021));
+
+        assert_eq!(hierarchy.superclasses("a/B").unwrap(), ["a/A", "java/lang/Object"]);
+        assert_eq!(hierarchy.missing_classes().into_iter().collect::<Vec<_>>(), ["a/Missing"]);
+        assert_eq!(
+            hierarchy.supertypes("a/B"),
+            Err(HierarchyError::MissingClass("a/Missing".to_string()))
+        );
+        assert!(hierarchy.least_common_superclass("a/B", "a/Unknown").is_err());
+        assert_eq!(hierarchy.subtypes("a/Missing").into_iter().collect::<Vec<_>>(), ["a/A", "a/B"]);
+    }
+
+    #[test]
+    fn test_default_method_selection() {
+        let mut hierarchy = ClassHierarchy::new();
+        hierarchy
+            .add_node(with_method(node("I", Some("java/lang/Object"), &[], 0x0601), "m", 0x0001))
+            .add_node(with_method(node("J", Some("java/lang/Object"), &["I"], 0x0601), "m", 0x0001))
+            .add_node(with_method(node("K", Some("java/lang/Object"), &[], 0x0601), "m", 0x0401))
+            .add_node(node("C", Some("java/lang/Object"), &["I", "J"], 0x0021))
+            .add_node(node("D", Some("java/lang/Object"), &["J", "K"], 0x0021));
+
+        // J overrides I's default, and an abstract K does not conflict with J.
+        assert_eq!(hierarchy.select_method("C", "m", "()V").unwrap(), Some("J"));
+        assert_eq!(hierarchy.select_method("D", "m", "()V").unwrap(), Some("J"));
+        assert_eq!(hierarchy.resolve_method("D", "m", "()V").unwrap(), Some("J"));
+        assert_eq!(hierarchy.implementors("I").into_iter().collect::<Vec<_>>(), ["C", "D"]);
+        assert_eq!(hierarchy.overridden_methods("J", "m", "()V").unwrap(), ["I"]);
+        assert_eq!(hierarchy.least_common_superclass("C", "D").unwrap(), "java/lang/Object");
+    }
+}
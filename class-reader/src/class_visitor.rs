@@ -0,0 +1,164 @@
+use crate::access_flag::ClassFileAccessFlags;
+use crate::attribute::Attribute;
+use crate::class_file_reader::AttributeLocation;
+use crate::class_file_version::ClassFileVersion;
+use crate::constant_pool::ConstantPool;
+use crate::field::{FieldAccessFlags, FieldType};
+use crate::instruction::Instruction;
+use crate::method::{MethodAccessFlags, MethodDescriptor};
+use crate::predefined_attributes::{Code, ExceptionHandler};
+
+#[derive(Debug, Default, Clone)]
+pub struct ClassHeader {
+    pub version: ClassFileVersion,
+    pub constant_pool: ConstantPool,
+    pub flags: ClassFileAccessFlags,
+    pub this_class: String,
+    pub super_class: Option<String>,
+    pub interfaces: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldHeader {
+    pub flags: FieldAccessFlags,
+    pub name: String,
+    pub type_descriptor: FieldType,
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodHeader {
+    pub flags: MethodAccessFlags,
+    pub name: String,
+    pub type_descriptor: MethodDescriptor,
+}
+
+/// Receives the contents of a class file as a sequence of events.
+///
+/// Events arrive in this order:
+///
+/// ```text
+/// visit_class
+/// (visit_field visit_attribute* visit_field_end)*
+/// (visit_method
+///     (visit_attribute
+///         | visit_code visit_instruction* visit_exception_handler* visit_attribute*
+///             visit_code_end)*
+///     visit_method_end)*
+/// visit_attribute*
+/// visit_end
+/// ```
+///
+/// The `Code` attribute of a method arrives among its other attributes, in the order the class
+/// file stores them, so writing the events back preserves that order.
+///
+/// Every event is forwarded to [`ClassVisitor::delegate`] unless overridden, so a visitor that
+/// wraps another one only needs to implement the events it transforms. Instruction events carry
+/// the pc they were read at; transformations that change code size must adjust branch offsets.
+pub trait ClassVisitor {
+    /// The next visitor in the chain, if any.
+    fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+        None
+    }
+
+    fn visit_class(&mut self, header: ClassHeader) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_class(header);
+        }
+    }
+
+    fn visit_field(&mut self, header: FieldHeader) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_field(header);
+        }
+    }
+
+    fn visit_field_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_field_end();
+        }
+    }
+
+    fn visit_method(&mut self, header: MethodHeader) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_method(header);
+        }
+    }
+
+    fn visit_code(&mut self, max_stack: u16, max_locals: u16) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_code(max_stack, max_locals);
+        }
+    }
+
+    fn visit_instruction(&mut self, instruction: Instruction, pc: u32) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_instruction(instruction, pc);
+        }
+    }
+
+    fn visit_exception_handler(&mut self, handler: ExceptionHandler) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_exception_handler(handler);
+        }
+    }
+
+    fn visit_code_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_code_end();
+        }
+    }
+
+    fn visit_method_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_method_end();
+        }
+    }
+
+    fn visit_attribute(&mut self, location: &AttributeLocation, attribute: Attribute) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_attribute(location, attribute);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_end();
+        }
+    }
+}
+
+/// Assembles the events of a single `Code` attribute back into a [`Code`].
+#[derive(Debug, Default)]
+pub(crate) struct CodeCollector {
+    pub(crate) code: Option<Code>,
+}
+
+impl ClassVisitor for CodeCollector {
+    fn visit_code(&mut self, max_stack: u16, max_locals: u16) {
+        self.code = Some(Code {
+            max_stack,
+            max_locals,
+            code: Vec::new(),
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        });
+    }
+
+    fn visit_instruction(&mut self, instruction: Instruction, pc: u32) {
+        if let Some(code) = self.code.as_mut() {
+            code.code.push((instruction, pc));
+        }
+    }
+
+    fn visit_exception_handler(&mut self, handler: ExceptionHandler) {
+        if let Some(code) = self.code.as_mut() {
+            code.exception_table.push(handler);
+        }
+    }
+
+    fn visit_attribute(&mut self, _location: &AttributeLocation, attribute: Attribute) {
+        if let Some(code) = self.code.as_mut() {
+            code.attributes.push(attribute);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+use crate::jar_file::{JarError, JarFile, internal_name};
+use crate::jimage::{JImage, JImageError};
+use crate::jmod_file::JmodFile;
+
+type Result<T> = std::result::Result<T, ClasspathError>;
+
+#[derive(Debug, Error)]
+pub enum ClasspathError {
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Error reading archive: {0}")]
+    #[non_exhaustive]
+    Jar(#[from] JarError),
+    #[error("Error reading runtime image: {0}")]
+    #[non_exhaustive]
+    JImage(#[from] JImageError),
+    #[error("Invalid class {0}: {1}")]
+    #[non_exhaustive]
+    Class(String, ContextualError),
+    #[error("Unsupported classpath entry {0}")]
+    #[non_exhaustive]
+    UnsupportedEntry(PathBuf),
+}
+
+/// One entry of a [`Classpath`]. Classes are named by internal name (`java/util/List`) and
+/// resources by their path inside the entry (`META-INF/services/java.sql.Driver`).
+pub trait ClassSource: Send {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>>;
+
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Internal names of every class this entry provides.
+    fn class_names(&mut self) -> Result<Vec<String>>;
+}
+
+/// A directory of class files laid out by package.
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        DirectorySource { root: root.as_ref().to_path_buf() }
+    }
+}
+
+impl ClassSource for DirectorySource {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>> {
+        self.resource(&format!("{}.class", internal_name))
+    }
+
+    /// Paths that are absolute or go up with `..` name nothing inside the directory.
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = Path::new(path);
+        if !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Ok(None);
+        }
+        match fs::read(self.root.join(path)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn class_names(&mut self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut pending = vec![self.root.clone()];
+        while let Some(directory) = pending.pop() {
+            for entry in fs::read_dir(&directory)? {
+                let entry = entry?;
+                let path = entry.path();
+                // Symbolic links to directories are not followed, as they may loop.
+                if entry.file_type()?.is_dir() {
+                    pending.push(path);
+                } else if path.extension() == Some(OsStr::new("class")) {
+                    let relative = path.strip_prefix(&self.root).unwrap().with_extension("");
+                    names.push(relative.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/"));
+                }
+            }
+        }
+        names.sort_unstable();
+        Ok(names)
+    }
+}
+
+/// Classes and resources held in memory, mostly useful for tests.
+#[derive(Debug, Default, Clone)]
+pub struct MemorySource {
+    entries: HashMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        MemorySource::default()
+    }
+
+    pub fn insert_class(&mut self, internal_name: &str, bytes: Vec<u8>) -> &mut Self {
+        self.insert_resource(&format!("{}.class", internal_name), bytes)
+    }
+
+    pub fn insert_resource(&mut self, path: &str, bytes: Vec<u8>) -> &mut Self {
+        self.entries.insert(path.to_string(), bytes);
+        self
+    }
+}
+
+impl ClassSource for MemorySource {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>> {
+        self.resource(&format!("{}.class", internal_name))
+    }
+
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.entries.get(path).cloned())
+    }
+
+    fn class_names(&mut self) -> Result<Vec<String>> {
+        let mut names = self
+            .entries
+            .keys()
+            .filter_map(|path| path.strip_suffix(".class"))
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        Ok(names)
+    }
+}
+
+impl<R: Read + Seek + Send> ClassSource for JarFile<R> {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(JarFile::class_bytes(self, internal_name)?)
+    }
+
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(JarFile::resource(self, path)?)
+    }
+
+    fn class_names(&mut self) -> Result<Vec<String>> {
+        Ok(JarFile::class_names(self).into_iter().map(str::to_string).collect())
+    }
+}
+
+impl<R: Read + Seek + Send> ClassSource for JmodFile<R> {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(JmodFile::class_bytes(self, internal_name)?)
+    }
+
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(JmodFile::resource(self, path)?)
+    }
+
+    fn class_names(&mut self) -> Result<Vec<String>> {
+        Ok(JmodFile::class_names(self).into_iter().map(str::to_string).collect())
+    }
+}
+
+impl ClassSource for JImage {
+    fn class_bytes(&mut self, internal_name: &str) -> Result<Option<Vec<u8>>> {
+        Ok(JImage::class_bytes(self, internal_name)?)
+    }
+
+    fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.find_resource(path)? {
+            Some(location) => Ok(Some(JImage::resource(self, &location)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn class_names(&mut self) -> Result<Vec<String>> {
+        let mut names = self
+            .modules()
+            .flat_map(|module| JImage::class_names(self, module))
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        Ok(names)
+    }
+}
+
+/// An ordered list of class sources, searched first to last; the first source that provides
+/// a class wins. Parsed classes are cached, so each class is read at most once.
+#[derive(Default)]
+pub struct Classpath {
+    sources: Vec<Box<dyn ClassSource>>,
+    cache: HashMap<String, Arc<ClassFile>>,
+}
+
+impl Classpath {
+    pub fn new() -> Self {
+        Classpath::default()
+    }
+
+    /// Builds a classpath from a platform path list such as the `CLASSPATH` variable.
+    pub fn from_path_list<S: AsRef<OsStr>>(paths: S) -> Result<Self> {
+        let mut classpath = Classpath::new();
+        for path in std::env::split_paths(&paths) {
+            classpath.add_path(path)?;
+        }
+        Ok(classpath)
+    }
+
+    pub fn add_source<S: ClassSource + 'static>(&mut self, source: S) -> &mut Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    /// Adds a directory, a `.jar`/`.zip` archive, a `.jmod` file or a `modules` runtime image,
+    /// chosen by the kind and name of `path`.
+    pub fn add_path<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Ok(self.add_source(DirectorySource::new(path)));
+        }
+        let extension = path.extension().and_then(OsStr::to_str).unwrap_or_default();
+        match extension {
+            "jar" | "zip" => Ok(self.add_source(JarFile::open(path)?)),
+            "jmod" => Ok(self.add_source(JmodFile::open(path)?)),
+            _ if path.file_name() == Some(OsStr::new("modules")) => {
+                Ok(self.add_source(JImage::open(path)?))
+            }
+            _ => Err(ClasspathError::UnsupportedEntry(path.to_path_buf())),
+        }
+    }
+
+    /// Adds a jar resolving versioned entries for the given release.
+    pub fn add_jar_for_release<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        release: u16,
+    ) -> Result<&mut Self> {
+        let jar = JarFile::with_release(BufReader::new(File::open(path)?), release)?;
+        Ok(self.add_source(jar))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// The bytes of a class, looked up by binary (`a.b.C`) or internal (`a/b/C`) name.
+    pub fn class_bytes(&mut self, binary_name: &str) -> Result<Option<Vec<u8>>> {
+        let name = internal_name(binary_name);
+        for source in &mut self.sources {
+            if let Some(bytes) = source.class_bytes(&name)? {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The parsed class, read from the first source providing it and cached afterwards.
+    pub fn load(&mut self, binary_name: &str) -> Result<Option<Arc<ClassFile>>> {
+        let name = internal_name(binary_name);
+        if let Some(class) = self.cache.get(name.as_ref()) {
+            return Ok(Some(Arc::clone(class)));
+        }
+        let Some(bytes) = self.class_bytes(&name)? else {
+            return Ok(None);
+        };
+        let class = ClassFileReader::read_class(&bytes)
+            .map_err(|err| ClasspathError::Class(name.to_string(), err))?;
+        let class = Arc::new(class);
+        self.cache.insert(name.into_owned(), Arc::clone(&class));
+        Ok(Some(class))
+    }
+
+    /// The bytes of a resource from the first source providing it.
+    pub fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = path.trim_start_matches('/');
+        for source in &mut self.sources {
+            if let Some(bytes) = source.resource(path)? {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Internal names of every class visible on the classpath, in sorted order.
+    pub fn class_names(&mut self) -> Result<BTreeSet<String>> {
+        let mut names = BTreeSet::new();
+        for source in &mut self.sources {
+            names.extend(source.class_names()?);
+        }
+        Ok(names)
+    }
+
+    /// Drops all cached classes, e.g. after the sources changed on disk.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Classpath, MemorySource};
+
+    #[test]
+    fn test_first_source_wins() {
+        let mut first = MemorySource::new();
+        first.insert_resource("a/b/config.txt", b"first".to_vec());
+        let mut second = MemorySource::new();
+        second
+            .insert_resource("a/b/config.txt", b"second".to_vec())
+            .insert_resource("a/b/other.txt", b"other".to_vec())
+            .insert_class("a/b/C", vec![0xCA, 0xFE]);
+
+        let mut classpath = Classpath::new();
+        classpath.add_source(first).add_source(second);
+
+        assert_eq!(classpath.resource("/a/b/config.txt").unwrap().unwrap(), b"first");
+        assert_eq!(classpath.resource("a/b/other.txt").unwrap().unwrap(), b"other");
+        assert_eq!(classpath.class_bytes("a.b.C").unwrap().unwrap(), [0xCA, 0xFE]);
+        assert!(classpath.load("a/b/C").is_err());
+        assert!(classpath.load("a/b/Missing").unwrap().is_none());
+        assert_eq!(classpath.class_names().unwrap().into_iter().collect::<Vec<_>>(), ["a/b/C"]);
+    }
+}
@@ -0,0 +1,320 @@
+//! The operand stack depth and local variable slots the code of a method actually needs, to
+//! check or recompute the `max_stack` and `max_locals` of its `Code` attribute.
+//!
+//! Stack heights are propagated along every path from the start of the method and from each
+//! exception handler, which starts with the thrown exception alone on the stack. Heights are
+//! counted in slots, so long and double values count twice. A subroutine called by `jsr` is
+//! assumed to consume its return address, so the instruction after the `jsr` continues at the
+//! height before it.
+
+use thiserror::Error;
+
+use crate::code_view::CodeView;
+use crate::constant_pool::ConstantPool;
+use crate::dataflow::local_access;
+use crate::field::FieldType;
+use crate::instruction::Instruction;
+use crate::method::Method;
+use crate::predefined_attributes::Code;
+
+type Result<T> = std::result::Result<T, CodeLimitsError>;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum CodeLimitsError {
+    #[error("Method {0} has no code")]
+    #[non_exhaustive]
+    MissingCode(String),
+    #[error("Operand stack underflow at pc {0}")]
+    #[non_exhaustive]
+    StackUnderflow(u32),
+    #[error(
+        "Stack height {actual} reaching pc {pc} differs from the height {expected} on another path"
+    )]
+    #[non_exhaustive]
+    StackHeightMismatch { pc: u32, expected: u16, actual: u16 },
+    #[error("Invalid constant pool reference at pc {0}")]
+    #[non_exhaustive]
+    InvalidConstant(u32),
+    #[error("Instruction at pc {0} transfers control to an offset that is not an instruction")]
+    #[non_exhaustive]
+    InvalidTarget(u32),
+    #[error("Execution falls off the end of the code after pc {0}")]
+    #[non_exhaustive]
+    FallsOffEnd(u32),
+    #[error("Operand stack of more than 65535 slots at pc {0}")]
+    #[non_exhaustive]
+    StackTooDeep(u32),
+}
+
+/// The limits a `Code` attribute must declare for its instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CodeLimits {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+impl CodeLimits {
+    /// Computes the limits of the code of `method`, whose constant pool references are
+    /// resolved against `constant_pool`. Unreachable instructions count towards `max_locals`
+    /// but not `max_stack`.
+    pub fn compute(constant_pool: &ConstantPool, method: &Method) -> Result<Self> {
+        let code =
+            method.code().ok_or_else(|| CodeLimitsError::MissingCode(method.name.clone()))?;
+        let receiver = !method.flags.is_static() as u16;
+        let parameters = method.type_descriptor.parameters().iter().map(FieldType::slots);
+        let max_locals = code
+            .code
+            .iter()
+            .filter_map(|(instruction, _)| local_access(instruction))
+            .map(|(local, size, _)| local as u32 + size as u32)
+            .fold(receiver as u32 + parameters.sum::<u16>() as u32, u32::max)
+            .min(u16::MAX as u32) as u16;
+        let max_stack = max_stack(constant_pool, code)?;
+        Ok(CodeLimits { max_stack, max_locals })
+    }
+
+    /// Whether `code` declares limits at least as large as these.
+    pub fn fit(&self, code: &Code) -> bool {
+        code.max_stack >= self.max_stack && code.max_locals >= self.max_locals
+    }
+}
+
+fn max_stack(constant_pool: &ConstantPool, code: &Code) -> Result<u16> {
+    let view = CodeView::new(code);
+    let mut heights = vec![None::<u16>; code.code.len()];
+    let mut worklist = Vec::new();
+    if code.code.is_empty() {
+        return Ok(0);
+    }
+    merge(code, &mut heights, &mut worklist, 0, 0)?;
+
+    let mut max_stack = 0;
+    while let Some(position) = worklist.pop() {
+        let (instruction, pc) = &code.code[position];
+        let pc = *pc;
+        let height = heights[position].expect("queued instructions have a height");
+        let effect =
+            instruction.stack_effect(constant_pool).ok_or(CodeLimitsError::InvalidConstant(pc))?;
+        let after = height
+            .checked_sub(effect.pops)
+            .ok_or(CodeLimitsError::StackUnderflow(pc))?
+            .checked_add(effect.pushes)
+            .ok_or(CodeLimitsError::StackTooDeep(pc))?;
+        max_stack = max_stack.max(height).max(after);
+
+        for handler in &code.exception_table {
+            if (handler.start_pc as u32..handler.end_pc as u32).contains(&pc) {
+                let target = view
+                    .position(handler.handler_pc as u32)
+                    .ok_or(CodeLimitsError::InvalidTarget(pc))?;
+                merge(code, &mut heights, &mut worklist, target, 1)?;
+            }
+        }
+
+        let target = |offset: i32| {
+            u32::try_from(pc as i64 + offset as i64)
+                .ok()
+                .and_then(|target| view.position(target))
+                .ok_or(CodeLimitsError::InvalidTarget(pc))
+        };
+        let mut successors = Vec::new();
+        match instruction {
+            Instruction::Tableswitch { default, offsets, .. } => {
+                successors.push((target(*default)?, after));
+                for offset in offsets {
+                    successors.push((target(*offset)?, after));
+                }
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                successors.push((target(*default)?, after));
+                for (_, offset) in pairs {
+                    successors.push((target(*offset)?, after));
+                }
+            }
+            Instruction::Jsr(_) | Instruction::Jsr_w(_) => {
+                let offset = instruction.branch_offset().expect("jsr has an offset");
+                successors.push((target(offset)?, after));
+                successors.push((next(code, position)?, height));
+            }
+            _ => {
+                if let Some(offset) = instruction.branch_offset() {
+                    successors.push((target(offset)?, after));
+                }
+                if !instruction.is_terminator() {
+                    successors.push((next(code, position)?, after));
+                }
+            }
+        }
+        for (successor, height) in successors {
+            merge(code, &mut heights, &mut worklist, successor, height)?;
+        }
+    }
+    Ok(max_stack)
+}
+
+/// Records that the instruction at `position` is reached with `height` slots on the stack,
+/// queueing it when it is reached for the first time.
+fn merge(
+    code: &Code,
+    heights: &mut [Option<u16>],
+    worklist: &mut Vec<usize>,
+    position: usize,
+    height: u16,
+) -> Result<()> {
+    match heights[position] {
+        None => {
+            heights[position] = Some(height);
+            worklist.push(position);
+            Ok(())
+        }
+        Some(expected) if expected == height => Ok(()),
+        Some(expected) => Err(CodeLimitsError::StackHeightMismatch {
+            pc: code.code[position].1,
+            expected,
+            actual: height,
+        }),
+    }
+}
+
+fn next(code: &Code, position: usize) -> Result<usize> {
+    match position + 1 < code.code.len() {
+        true => Ok(position + 1),
+        false => Err(CodeLimitsError::FallsOffEnd(code.code[position].1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute::Attribute;
+    use crate::method::{MethodAccessFlags, MethodDescriptor};
+    use crate::predefined_attributes::ExceptionHandler;
+
+    fn method(
+        flags: u16,
+        descriptor: &str,
+        code: Vec<(Instruction, u32)>,
+        exception_table: Vec<ExceptionHandler>,
+    ) -> Method {
+        let code =
+            Code { max_stack: 0, max_locals: 0, code, exception_table, attributes: Vec::new() };
+        Method {
+            flags: MethodAccessFlags::new(flags),
+            name: "m".to_string(),
+            type_descriptor: MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+                .unwrap(),
+            attributes: vec![Attribute::Code(code)],
+        }
+    }
+
+    fn compute(method: &Method) -> Result<CodeLimits> {
+        CodeLimits::compute(&ConstantPool::default(), method)
+    }
+
+    #[test]
+    fn test_parameters_and_wide_values() {
+        let instance = method(
+            0,
+            "(JI)J",
+            vec![
+                (Instruction::Lload_1, 0),
+                (Instruction::Dup_2, 1),
+                (Instruction::Ladd, 2),
+                (Instruction::Lreturn, 3),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(compute(&instance), Ok(CodeLimits { max_stack: 4, max_locals: 4 }));
+
+        let store = method(
+            0x0008,
+            "()V",
+            vec![(Instruction::Dconst_0, 0), (Instruction::Dstore(5), 1), (Instruction::Return, 3)],
+            Vec::new(),
+        );
+        assert_eq!(compute(&store), Ok(CodeLimits { max_stack: 2, max_locals: 7 }));
+    }
+
+    #[test]
+    fn test_exception_handlers() {
+        // The handler starts with the exception alone on the stack, whatever the height in the
+        // protected range.
+        let method = method(
+            0x0008,
+            "()V",
+            vec![
+                (Instruction::Iconst_0, 0),
+                (Instruction::Iconst_1, 1),
+                (Instruction::Iconst_2, 2),
+                (Instruction::Pop2, 3),
+                (Instruction::Pop, 4),
+                (Instruction::Return, 5),
+                (Instruction::Aconst_null, 6),
+                (Instruction::Dup, 7),
+                (Instruction::Pop2, 8),
+                (Instruction::Return, 9),
+            ],
+            vec![ExceptionHandler::new(0, 5, 6, 0)],
+        );
+        assert_eq!(compute(&method).unwrap().max_stack, 3);
+    }
+
+    #[test]
+    fn test_subroutines() {
+        let method = method(
+            0x0008,
+            "()V",
+            vec![
+                (Instruction::Iconst_0, 0),
+                (Instruction::Jsr(5), 1),
+                (Instruction::Pop, 4),
+                (Instruction::Return, 5),
+                (Instruction::Astore_0, 6),
+                (Instruction::Ret(0), 7),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(compute(&method), Ok(CodeLimits { max_stack: 2, max_locals: 1 }));
+    }
+
+    #[test]
+    fn test_inconsistent_code() {
+        let mismatch = method(
+            0x0008,
+            "(I)V",
+            vec![
+                (Instruction::Iload_0, 0),
+                (Instruction::Iconst_1, 1),
+                (Instruction::Swap, 2),
+                (Instruction::Ifeq(4), 3),
+                (Instruction::Iconst_2, 6),
+                (Instruction::Pop, 7),
+                (Instruction::Return, 8),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(
+            compute(&mismatch),
+            Err(CodeLimitsError::StackHeightMismatch { pc: 7, expected: 1, actual: 2 })
+        );
+
+        let underflow = method(
+            0x0008,
+            "()V",
+            vec![(Instruction::Pop, 0), (Instruction::Return, 1)],
+            Vec::new(),
+        );
+        assert_eq!(compute(&underflow), Err(CodeLimitsError::StackUnderflow(0)));
+
+        let falls_off = method(0x0008, "()V", vec![(Instruction::Nop, 0)], Vec::new());
+        assert_eq!(compute(&falls_off), Err(CodeLimitsError::FallsOffEnd(0)));
+
+        let bad_target = method(
+            0x0008,
+            "()V",
+            vec![(Instruction::Goto(2), 0), (Instruction::Return, 3)],
+            Vec::new(),
+        );
+        assert_eq!(compute(&bad_target), Err(CodeLimitsError::InvalidTarget(0)));
+    }
+}
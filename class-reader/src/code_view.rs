@@ -0,0 +1,286 @@
+//! Random access to the instructions of a [`Code`] attribute by pc, and the debug information
+//! attached to them: source lines from the `LineNumberTable`, and the names and types of local
+//! variables from the `LocalVariableTable` and `LocalVariableTypeTable`.
+
+use crate::attribute::Attribute;
+use crate::constant_pool::ConstantPool;
+use crate::instruction::Instruction;
+use crate::predefined_attributes::{Code, LocalVariable, LocalVariableType};
+
+/// A view of a [`Code`] attribute indexed by pc. Building it takes time linear in the length
+/// of the code; instruction lookups by pc take constant time and line lookups logarithmic time
+/// in the size of the `LineNumberTable`.
+#[derive(Debug, Clone)]
+pub struct CodeView<'a> {
+    code: &'a Code,
+    /// The position in `code.code` of the instruction starting at each pc.
+    positions: Vec<Option<usize>>,
+    /// `(start_pc, line)` pairs sorted by pc.
+    lines: Vec<(u32, u16)>,
+    local_variables: Vec<&'a LocalVariable>,
+    local_variable_types: Vec<&'a LocalVariableType>,
+}
+
+/// A local variable with its name and types resolved against the constant pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalVariableInfo<'a> {
+    pub slot: u16,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    /// The generic type from the `LocalVariableTypeTable`, for variables whose type uses type
+    /// variables or parameterized types.
+    pub signature: Option<&'a str>,
+    /// The first pc at which the variable has a value.
+    pub start_pc: u32,
+    /// The pc just past the range in which the variable has a value.
+    pub end_pc: u32,
+}
+
+impl<'a> CodeView<'a> {
+    pub fn new(code: &'a Code) -> Self {
+        let mut positions = vec![None; code_length(code) as usize];
+        for (position, (_, pc)) in code.code.iter().enumerate() {
+            positions[*pc as usize] = Some(position);
+        }
+
+        let mut lines = Vec::new();
+        let mut local_variables = Vec::new();
+        let mut local_variable_types = Vec::new();
+        for attribute in &code.attributes {
+            match attribute {
+                Attribute::LineNumberTable(table) => lines.extend(
+                    table
+                        .line_number_table
+                        .iter()
+                        .map(|line| (line.start_pc as u32, line.line_number)),
+                ),
+                Attribute::LocalVariableTable(table) => {
+                    local_variables.extend(&table.local_variable_table)
+                }
+                Attribute::LocalVariableTypeTable(table) => {
+                    local_variable_types.extend(&table.local_variable_type_table)
+                }
+                _ => {}
+            }
+        }
+        lines.sort();
+
+        CodeView { code, positions, lines, local_variables, local_variable_types }
+    }
+
+    pub fn code(&self) -> &'a Code {
+        self.code
+    }
+
+    /// The number of bytes the instructions are encoded in, the pc just past the last one.
+    pub fn code_length(&self) -> u32 {
+        self.positions.len() as u32
+    }
+
+    /// The position in [`Code::code`] of the instruction starting at `pc`, `None` if no
+    /// instruction starts there.
+    pub fn position(&self, pc: u32) -> Option<usize> {
+        self.positions.get(pc as usize).copied().flatten()
+    }
+
+    /// The instruction starting at `pc`.
+    pub fn instruction_at(&self, pc: u32) -> Option<&'a Instruction> {
+        self.position(pc).map(|position| &self.code.code[position].0)
+    }
+
+    /// The pc of the instruction following the one at `pc`, `None` if `pc` is not the start
+    /// of an instruction or is the last one.
+    pub fn next_pc(&self, pc: u32) -> Option<u32> {
+        let position = self.position(pc)?;
+        self.code.code.get(position + 1).map(|(_, pc)| *pc)
+    }
+
+    /// The pc of the instruction preceding the one at `pc`, `None` if `pc` is not the start
+    /// of an instruction or is the first one.
+    pub fn previous_pc(&self, pc: u32) -> Option<u32> {
+        let position = self.position(pc)?.checked_sub(1)?;
+        Some(self.code.code[position].1)
+    }
+
+    /// The source line the instruction at `pc` was compiled from: the line of the last
+    /// `LineNumberTable` entry starting at or before it.
+    pub fn line_at(&self, pc: u32) -> Option<u16> {
+        match self.lines.partition_point(|(start_pc, _)| *start_pc <= pc) {
+            0 => None,
+            index => Some(self.lines[index - 1].1),
+        }
+    }
+
+    /// The `LocalVariableTable` entry for `slot` whose range covers `pc`.
+    pub fn local_variable(&self, slot: u16, pc: u32) -> Option<&'a LocalVariable> {
+        self.local_variables.iter().copied().find(|variable| {
+            variable.index == slot && covers(variable.start_pc, variable.length, pc)
+        })
+    }
+
+    /// The `LocalVariableTypeTable` entry for `slot` whose range covers `pc`.
+    pub fn local_variable_type(&self, slot: u16, pc: u32) -> Option<&'a LocalVariableType> {
+        self.local_variable_types.iter().copied().find(|variable| {
+            variable.index == slot && covers(variable.start_pc, variable.length, pc)
+        })
+    }
+
+    /// The name and types of the local variable in `slot` at `pc`, resolved against
+    /// `constant_pool`. `None` without a `LocalVariableTable` entry covering `pc` or if the
+    /// entry refers to invalid constants.
+    pub fn local_variable_info(
+        &self,
+        constant_pool: &'a ConstantPool,
+        slot: u16,
+        pc: u32,
+    ) -> Option<LocalVariableInfo<'a>> {
+        let variable = self.local_variable(slot, pc)?;
+        let signature = self
+            .local_variable_type(slot, pc)
+            .filter(|variable_type| variable_type.start_pc == variable.start_pc)
+            .and_then(|variable_type| constant_pool.utf8(variable_type.signature_index as usize));
+        Some(LocalVariableInfo {
+            slot,
+            name: constant_pool.utf8(variable.name_index as usize)?,
+            descriptor: constant_pool.utf8(variable.descriptor_index as usize)?,
+            signature,
+            start_pc: variable.start_pc as u32,
+            end_pc: variable.start_pc as u32 + variable.length as u32,
+        })
+    }
+
+    /// The local variables with a value at `pc`, ordered by slot.
+    pub fn local_variables_at(
+        &self,
+        constant_pool: &'a ConstantPool,
+        pc: u32,
+    ) -> Vec<LocalVariableInfo<'a>> {
+        let mut slots = self
+            .local_variables
+            .iter()
+            .filter(|variable| covers(variable.start_pc, variable.length, pc))
+            .map(|variable| variable.index)
+            .collect::<Vec<_>>();
+        slots.sort_unstable();
+        slots.dedup();
+        slots
+            .into_iter()
+            .filter_map(|slot| self.local_variable_info(constant_pool, slot, pc))
+            .collect()
+    }
+}
+
+fn covers(start_pc: u16, length: u16, pc: u32) -> bool {
+    (start_pc as u32..start_pc as u32 + length as u32).contains(&pc)
+}
+
+fn code_length(code: &Code) -> u32 {
+    code.code.last().map_or(0, |(instruction, pc)| pc + instruction.length(*pc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant_pool::Constant;
+    use crate::predefined_attributes::{
+        LineNumber, LineNumberTable, LocalVariableTable, LocalVariableTypeTable,
+    };
+
+    fn code() -> Code {
+        Code {
+            max_stack: 1,
+            max_locals: 2,
+            code: vec![
+                (Instruction::Aconst_null, 0),
+                (Instruction::Astore_1, 1),
+                (Instruction::Aload_1, 2),
+                (Instruction::Ifnull(5), 3),
+                (Instruction::Sipush(300), 6),
+                (Instruction::Pop, 9),
+                (Instruction::Return, 10),
+            ],
+            exception_table: Vec::new(),
+            attributes: vec![
+                Attribute::LineNumberTable(LineNumberTable::new(vec![
+                    LineNumber { start_pc: 2, line_number: 11 },
+                    LineNumber { start_pc: 0, line_number: 10 },
+                    LineNumber { start_pc: 6, line_number: 12 },
+                ])),
+                Attribute::LocalVariableTable(LocalVariableTable::new(vec![
+                    LocalVariable::new(0, 11, 1, 2, 0),
+                    LocalVariable::new(2, 9, 3, 4, 1),
+                ])),
+                Attribute::LocalVariableTypeTable(LocalVariableTypeTable::new(vec![
+                    LocalVariableType::new(2, 9, 3, 5, 1),
+                ])),
+            ],
+        }
+    }
+
+    fn constant_pool() -> ConstantPool {
+        let mut constant_pool = ConstantPool::default();
+        for name in ["args", "[Ljava/lang/String;", "list", "Ljava/util/List;"] {
+            constant_pool.add(Constant::Utf8(name.to_string()));
+        }
+        constant_pool.add(Constant::Utf8("Ljava/util/List<Ljava/lang/String;>;".to_string()));
+        constant_pool
+    }
+
+    #[test]
+    fn test_pc_lookups() {
+        let code = code();
+        let view = CodeView::new(&code);
+        assert_eq!(view.code_length(), 11);
+        assert_eq!(view.instruction_at(6), Some(&Instruction::Sipush(300)));
+        assert_eq!(view.instruction_at(7), None);
+        assert_eq!(view.position(9), Some(5));
+        assert_eq!(view.next_pc(3), Some(6));
+        assert_eq!(view.next_pc(10), None);
+        assert_eq!(view.previous_pc(6), Some(3));
+        assert_eq!(view.previous_pc(0), None);
+        assert_eq!(view.previous_pc(4), None);
+        assert_eq!(view.instruction_at(40), None);
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let code = code();
+        let view = CodeView::new(&code);
+        assert_eq!(
+            [0, 1, 2, 3, 6, 10].map(|pc| view.line_at(pc)),
+            [10, 10, 11, 11, 12, 12].map(Some)
+        );
+        let empty = Code { attributes: Vec::new(), ..code };
+        assert_eq!(CodeView::new(&empty).line_at(3), None);
+    }
+
+    #[test]
+    fn test_local_variables() {
+        let code = code();
+        let constant_pool = constant_pool();
+        let view = CodeView::new(&code);
+        assert_eq!(view.local_variable_info(&constant_pool, 1, 1), None);
+        let list = view.local_variable_info(&constant_pool, 1, 6).unwrap();
+        assert_eq!(
+            list,
+            LocalVariableInfo {
+                slot: 1,
+                name: "list",
+                descriptor: "Ljava/util/List;",
+                signature: Some("Ljava/util/List<Ljava/lang/String;>;"),
+                start_pc: 2,
+                end_pc: 11,
+            }
+        );
+        let names = |pc| {
+            view.local_variables_at(&constant_pool, pc)
+                .iter()
+                .map(|variable| variable.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(0), ["args"]);
+        assert_eq!(names(10), ["args", "list"]);
+        assert!(names(11).is_empty());
+        assert_eq!(view.local_variable_info(&constant_pool, 0, 3).unwrap().signature, None);
+    }
+}
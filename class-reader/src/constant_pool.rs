@@ -11,12 +11,13 @@ pub enum ConstantPoolError {
 }
 
 #[derive(Debug, Clone, PartialEq, NamedVariant)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Constant {
     Utf8(String),
     Integer(i32),
-    Float(f32),
+    Float(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::f32_value"))] f32),
     Long(i64),
-    Double(f64),
+    Double(#[cfg_attr(feature = "serde", serde(with = "crate::serde_support::f64_value"))] f64),
     ClassIndex(u16),
     StringIndex(u16),
     FieldRef(u16, u16),
@@ -52,10 +53,102 @@ impl ConstantPool {
     }
 
     pub fn get(&self, index: usize) -> Result<&Constant, ConstantPoolError> {
-        match self.constants.get(index - 1) {
+        match index.checked_sub(1).and_then(|index| self.constants.get(index)) {
             Some(Constant::Unsuable) => Err(ConstantPoolError::UnsuableConstant(index)),
             Some(constant) => Ok(constant),
             None => Err(ConstantPoolError::IndexOutOfBounds(index)),
         }
     }
+
+    /// The string of the `Utf8` entry at `index`.
+    pub fn utf8(&self, index: usize) -> Option<&str> {
+        match self.get(index) {
+            Ok(Constant::Utf8(string)) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The internal name of the `Class` entry at `index`.
+    pub fn class_name(&self, index: usize) -> Option<&str> {
+        match self.get(index) {
+            Ok(Constant::ClassIndex(name_index)) => self.utf8(*name_index as usize),
+            _ => None,
+        }
+    }
+
+    /// The name and descriptor of the `NameAndType` entry at `index`.
+    pub fn name_and_type(&self, index: usize) -> Option<(&str, &str)> {
+        match self.get(index) {
+            Ok(Constant::NameAndType(name_index, descriptor_index)) => {
+                Some((self.utf8(*name_index as usize)?, self.utf8(*descriptor_index as usize)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// The owner, name and descriptor of the field, method or interface method reference at
+    /// `index`.
+    pub fn member_ref(&self, index: usize) -> Option<(&str, &str, &str)> {
+        match self.get(index) {
+            Ok(
+                Constant::FieldRef(class_index, name_and_type_index)
+                | Constant::MethodRef(class_index, name_and_type_index)
+                | Constant::InterfaceMethodRef(class_index, name_and_type_index),
+            ) => {
+                let (name, descriptor) = self.name_and_type(*name_and_type_index as usize)?;
+                Some((self.class_name(*class_index as usize)?, name, descriptor))
+            }
+            _ => None,
+        }
+    }
+
+    /// Renders the entry at `index` with the indices it refers to resolved, in the style of
+    /// `javap`: `java/io/PrintStream.println:(I)V` for a method reference or
+    /// `#0:run:()Ljava/lang/Runnable;` for an invokedynamic call site. Literal constants and
+    /// invalid indices have no description.
+    pub fn describe(&self, index: usize) -> Option<String> {
+        match self.get(index).ok()? {
+            Constant::ClassIndex(name_index)
+            | Constant::StringIndex(name_index)
+            | Constant::MethodType(name_index)
+            | Constant::Module(name_index)
+            | Constant::Package(name_index) => self.utf8(*name_index as usize).map(str::to_string),
+            Constant::FieldRef(..) | Constant::MethodRef(..) | Constant::InterfaceMethodRef(..) => {
+                let (owner, name, descriptor) = self.member_ref(index)?;
+                Some(format!("{}.{}:{}", owner, name, descriptor))
+            }
+            Constant::NameAndType(..) => {
+                let (name, descriptor) = self.name_and_type(index)?;
+                Some(format!("{}:{}", name, descriptor))
+            }
+            Constant::MethodHandle(kind, reference_index) => Some(format!(
+                "{} {}",
+                reference_kind_name(*kind)?,
+                self.describe(*reference_index as usize)?
+            )),
+            Constant::Dynamic(bootstrap_index, name_and_type_index)
+            | Constant::InvokeDynamic(bootstrap_index, name_and_type_index) => {
+                let (name, descriptor) = self.name_and_type(*name_and_type_index as usize)?;
+                Some(format!("#{}:{}:{}", bootstrap_index, name, descriptor))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The `REF_` name of a method handle reference kind.
+pub fn reference_kind_name(kind: u8) -> Option<&'static str> {
+    let name = match kind {
+        1 => "REF_getField",
+        2 => "REF_getStatic",
+        3 => "REF_putField",
+        4 => "REF_putStatic",
+        5 => "REF_invokeVirtual",
+        6 => "REF_invokeStatic",
+        7 => "REF_invokeSpecial",
+        8 => "REF_newInvokeSpecial",
+        9 => "REF_invokeInterface",
+        _ => return None,
+    };
+    Some(name)
 }
@@ -0,0 +1,558 @@
+//! Rewriting of the constant pool references of a class: compaction of its constant pool, and
+//! in-place access to the raw bodies of the predefined attributes the reader does not decode.
+//!
+//! Compaction visits every reference of a class twice: first marking the entries in use,
+//! including those the writer looks up by value, then renumbering them.
+
+use std::collections::HashMap;
+
+use crate::attribute::{Attribute, UserDefinedAttribute};
+use crate::class_file::ClassFile;
+use crate::class_file_writer::is_same_constant;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::instruction::Instruction;
+use crate::predefined_attributes::{StackMapFrame, VerificationTypeInfo};
+
+/// Drops the constant pool entries of `class` that nothing refers to. Returns whether any
+/// were dropped, `None` if the class has attributes of unknown layout or invalid indices.
+pub(crate) fn compact_constant_pool(class: &mut ClassFile) -> Option<bool> {
+    let constant_pool = std::mem::take(&mut class.constant_pool);
+    let result = compact(class, &constant_pool);
+    if result != Some(true) {
+        class.constant_pool = constant_pool;
+    }
+    result
+}
+
+fn compact(class: &mut ClassFile, constant_pool: &ConstantPool) -> Option<bool> {
+    let mut marker = Marker::new(constant_pool);
+    if !visit_class(class, constant_pool, &mut marker) || !marker.valid {
+        return None;
+    }
+    let live = marker.propagate()?;
+
+    let mut indices = vec![0; constant_pool.constants.len() + 1];
+    let mut next = 1;
+    for (position, constant) in constant_pool.constants.iter().enumerate() {
+        if live[position + 1] {
+            indices[position + 1] = next;
+            next += match constant {
+                Constant::Long(_) | Constant::Double(_) => 2,
+                _ => 1,
+            };
+        }
+    }
+    if next as usize == constant_pool.constants.len() + 1 {
+        return Some(false);
+    }
+    let mut renumberer = Renumberer { indices };
+    let mut compacted = ConstantPool::default();
+    for (position, constant) in constant_pool.constants.iter().enumerate() {
+        if live[position + 1] {
+            let mut constant = constant.clone();
+            map_constant(&mut constant, &mut |index| renumberer.index(index));
+            compacted.add(constant);
+        }
+    }
+    visit_class(class, constant_pool, &mut renumberer);
+    class.constant_pool = compacted;
+    Some(true)
+}
+
+/// Receives the constant pool references of a class, by index or by the strings that
+/// [`ClassFileWriter`](crate::class_file_writer::ClassFileWriter) looks up, and returns what indices are to become.
+trait IndexVisitor {
+    fn index(&mut self, index: u16) -> u16;
+
+    /// A `Utf8` entry the writer looks up by its string.
+    fn utf8(&mut self, _string: &str) {}
+
+    /// A `Class` entry the writer looks up by its name.
+    fn class_name(&mut self, _name: &str) {}
+
+    /// A constant the writer finds or adds by value.
+    fn constant(&mut self, constant: &mut Constant);
+}
+
+/// Marks the entries a class refers to.
+struct Marker<'a> {
+    constant_pool: &'a ConstantPool,
+    live: Vec<bool>,
+    utf8_indices: HashMap<&'a str, u16>,
+    class_indices: HashMap<&'a str, u16>,
+    valid: bool,
+}
+
+impl<'a> Marker<'a> {
+    fn new(constant_pool: &'a ConstantPool) -> Self {
+        // The first entry of each string and class, which is what the writer uses.
+        let mut utf8_indices = HashMap::new();
+        let mut class_indices = HashMap::new();
+        for (position, constant) in constant_pool.constants.iter().enumerate() {
+            let index = position as u16 + 1;
+            match constant {
+                Constant::Utf8(string) => {
+                    utf8_indices.entry(string.as_str()).or_insert(index);
+                }
+                Constant::ClassIndex(name_index) => {
+                    if let Some(name) = constant_pool.utf8(*name_index as usize) {
+                        class_indices.entry(name).or_insert(index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Marker {
+            constant_pool,
+            live: vec![false; constant_pool.constants.len() + 1],
+            utf8_indices,
+            class_indices,
+            valid: true,
+        }
+    }
+
+    /// Marks the entry at `index`, returning whether it was not marked yet.
+    fn mark(&mut self, index: u16) -> bool {
+        match self.constant_pool.get(index as usize) {
+            Ok(_) => !std::mem::replace(&mut self.live[index as usize], true),
+            Err(_) => {
+                self.valid = false;
+                false
+            }
+        }
+    }
+
+    /// Marks the entries the marked ones refer to, `None` if one refers to an invalid index.
+    fn propagate(mut self) -> Option<Vec<bool>> {
+        let mut worklist =
+            (1..self.live.len()).filter(|&index| self.live[index]).collect::<Vec<_>>();
+        while let Some(index) = worklist.pop() {
+            let mut constant = self.constant_pool.constants[index - 1].clone();
+            let mut references = Vec::new();
+            map_constant(&mut constant, &mut |reference| {
+                references.push(reference);
+                reference
+            });
+            for reference in references {
+                if self.mark(reference) {
+                    worklist.push(reference as usize);
+                }
+            }
+        }
+        self.valid.then_some(self.live)
+    }
+}
+
+impl IndexVisitor for Marker<'_> {
+    fn index(&mut self, index: u16) -> u16 {
+        if index != 0 {
+            self.mark(index);
+        }
+        index
+    }
+
+    fn utf8(&mut self, string: &str) {
+        if let Some(&index) = self.utf8_indices.get(string) {
+            self.live[index as usize] = true;
+        }
+    }
+
+    fn class_name(&mut self, name: &str) {
+        match self.class_indices.get(name) {
+            Some(&index) => self.live[index as usize] = true,
+            None => self.utf8(name),
+        }
+    }
+
+    fn constant(&mut self, constant: &mut Constant) {
+        if let Constant::Utf8(string) = constant {
+            return self.utf8(string);
+        }
+        let existing =
+            self.constant_pool.constants.iter().position(|other| is_same_constant(other, constant));
+        match existing {
+            Some(position) => self.live[position + 1] = true,
+            None => map_constant(constant, &mut |index| {
+                if index != 0 {
+                    self.mark(index);
+                }
+                index
+            }),
+        }
+    }
+}
+
+/// Rewrites indices to those of the compacted constant pool.
+struct Renumberer {
+    indices: Vec<u16>,
+}
+
+impl IndexVisitor for Renumberer {
+    fn index(&mut self, index: u16) -> u16 {
+        self.indices[index as usize]
+    }
+
+    fn constant(&mut self, constant: &mut Constant) {
+        map_constant(constant, &mut |index| self.indices[index as usize]);
+    }
+}
+
+/// Applies `f` to the indices of other entries an entry refers to.
+fn map_constant(constant: &mut Constant, f: &mut impl FnMut(u16) -> u16) {
+    match constant {
+        Constant::ClassIndex(index)
+        | Constant::StringIndex(index)
+        | Constant::MethodType(index)
+        | Constant::Module(index)
+        | Constant::Package(index)
+        | Constant::MethodHandle(_, index)
+        | Constant::Dynamic(_, index)
+        | Constant::InvokeDynamic(_, index) => *index = f(*index),
+        Constant::FieldRef(first, second)
+        | Constant::MethodRef(first, second)
+        | Constant::InterfaceMethodRef(first, second)
+        | Constant::NameAndType(first, second) => {
+            *first = f(*first);
+            *second = f(*second);
+        }
+        _ => {}
+    }
+}
+
+/// Visits the references of `class`, whose entries are in `constant_pool`. Returns false if
+/// an attribute has an unknown layout, leaving its references unvisited.
+fn visit_class(
+    class: &mut ClassFile,
+    constant_pool: &ConstantPool,
+    visitor: &mut impl IndexVisitor,
+) -> bool {
+    visitor.class_name(&class.this_class);
+    if let Some(super_class) = &class.super_class {
+        visitor.class_name(super_class);
+    }
+    for interface in &class.interfaces {
+        visitor.class_name(interface);
+    }
+    let mut known = true;
+    for field in &mut class.fields {
+        visitor.utf8(&field.name);
+        visitor.utf8(&field.type_descriptor.to_string());
+        known &= visit_attributes(&mut field.attributes, constant_pool, visitor);
+    }
+    for method in &mut class.methods {
+        visitor.utf8(&method.name);
+        visitor.utf8(&method.type_descriptor.to_string());
+        known &= visit_attributes(&mut method.attributes, constant_pool, visitor);
+    }
+    known & visit_attributes(&mut class.attributes, constant_pool, visitor)
+}
+
+fn visit_attributes(
+    attributes: &mut [Attribute],
+    constant_pool: &ConstantPool,
+    visitor: &mut impl IndexVisitor,
+) -> bool {
+    let mut known = true;
+    for attribute in attributes {
+        visitor.utf8(attribute.name());
+        match attribute {
+            Attribute::ConstantValue(constant_value) => visitor.constant(&mut constant_value.value),
+            Attribute::Code(code) => {
+                for (instruction, _) in &mut code.code {
+                    visit_instruction(instruction, visitor);
+                }
+                for handler in &mut code.exception_table {
+                    handler.catch_type = visitor.index(handler.catch_type);
+                }
+                known &= visit_attributes(&mut code.attributes, constant_pool, visitor);
+            }
+            Attribute::StackMapTable(table) => {
+                for frame in &mut table.frames {
+                    let types = match frame {
+                        StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                        | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                            vec![stack]
+                        }
+                        StackMapFrame::AppendFrame { locals, .. } => locals.iter_mut().collect(),
+                        StackMapFrame::FullFrame { locals, stack, .. } => {
+                            locals.iter_mut().chain(stack).collect()
+                        }
+                        _ => Vec::new(),
+                    };
+                    for info in types {
+                        if let VerificationTypeInfo::Object { constant } = info {
+                            visitor.constant(constant);
+                        }
+                    }
+                }
+            }
+            Attribute::LocalVariableTable(table) => {
+                for variable in &mut table.local_variable_table {
+                    variable.name_index = visitor.index(variable.name_index);
+                    variable.descriptor_index = visitor.index(variable.descriptor_index);
+                }
+            }
+            Attribute::LocalVariableTypeTable(table) => {
+                for variable in &mut table.local_variable_type_table {
+                    variable.name_index = visitor.index(variable.name_index);
+                    variable.signature_index = visitor.index(variable.signature_index);
+                }
+            }
+            Attribute::NestHost(nest_host) => visitor.class_name(&nest_host.name),
+            Attribute::NestMembers(nest_members) => {
+                nest_members.names.iter().for_each(|name| visitor.class_name(name))
+            }
+            Attribute::PermittedSubclasses(permitted_subclasses) => {
+                permitted_subclasses.names.iter().for_each(|name| visitor.class_name(name))
+            }
+            Attribute::SourceFile(source_file) => visitor.utf8(&source_file.file_name),
+            Attribute::BootstrapMethods(bootstrap_methods) => {
+                for method in &mut bootstrap_methods.bootstrap_methods {
+                    method.bootstrap_method_ref = visitor.index(method.bootstrap_method_ref);
+                    for argument in &mut method.bootstrap_arguments {
+                        *argument = visitor.index(*argument);
+                    }
+                }
+            }
+            Attribute::UserDefined(user_defined) => {
+                let mut info = user_defined.info().to_vec();
+                let mut raw = RawAttribute { info: &mut info, position: 0 };
+                match raw.visit(user_defined.name(), constant_pool, visitor) {
+                    Some(()) => {
+                        let name = user_defined.name().to_string();
+                        *user_defined = UserDefinedAttribute::new(name, &info);
+                    }
+                    None => known = false,
+                }
+            }
+            Attribute::LineNumberTable(_) => {}
+        }
+    }
+    known
+}
+
+fn visit_instruction(instruction: &mut Instruction, visitor: &mut impl IndexVisitor) {
+    match instruction {
+        // Indices only decrease, so those of `ldc` still fit in a byte.
+        Instruction::Ldc(index) => *index = visitor.index(*index as u16) as u8,
+        Instruction::Ldc_w(index)
+        | Instruction::Ldc2_w(index)
+        | Instruction::Getstatic(index)
+        | Instruction::Putstatic(index)
+        | Instruction::Getfield(index)
+        | Instruction::Putfield(index)
+        | Instruction::Invokevirtual(index)
+        | Instruction::Invokespecial(index)
+        | Instruction::Invokestatic(index)
+        | Instruction::Invokeinterface(index, _)
+        | Instruction::Invokedynamic(index)
+        | Instruction::New(index)
+        | Instruction::Anewarray(index)
+        | Instruction::Checkcast(index)
+        | Instruction::Instanceof(index)
+        | Instruction::Multianewarray(index, _) => *index = visitor.index(*index),
+        _ => {}
+    }
+}
+
+/// The body of a predefined attribute the reader does not decode, whose indices are visited
+/// and rewritten in place.
+pub(crate) struct RawAttribute<'b> {
+    pub(crate) info: &'b mut [u8],
+    pub(crate) position: usize,
+}
+
+impl RawAttribute<'_> {
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        let value = *self.info.get(self.position)?;
+        self.position += 1;
+        Some(value)
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        let bytes = self.info.get(self.position..self.position + 2)?;
+        self.position += 2;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        Some((self.u16()? as u32) << 16 | self.u16()? as u32)
+    }
+
+    pub(crate) fn skip(&mut self, length: usize) -> Option<()> {
+        let position = self.position.checked_add(length)?;
+        (position <= self.info.len()).then(|| self.position = position)
+    }
+
+    /// Overwrites the `u16` just read with `value`.
+    pub(crate) fn replace(&mut self, value: u16) {
+        self.write_u16(self.position - 2, value);
+    }
+
+    pub(crate) fn write_u16(&mut self, position: usize, value: u16) {
+        self.info[position..position + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    fn index(&mut self, visitor: &mut impl IndexVisitor) -> Option<()> {
+        let index = visitor.index(self.u16()?);
+        self.replace(index);
+        Some(())
+    }
+
+    /// A `u16` count followed by that many indices.
+    fn indices(&mut self, visitor: &mut impl IndexVisitor) -> Option<()> {
+        for _ in 0..self.u16()? {
+            self.index(visitor)?;
+        }
+        Some(())
+    }
+
+    /// Visits the body of the attribute `name`, `None` if its layout is unknown or it does not
+    /// match it.
+    fn visit(
+        &mut self,
+        name: &str,
+        constant_pool: &ConstantPool,
+        visitor: &mut impl IndexVisitor,
+    ) -> Option<()> {
+        match name {
+            "Synthetic" | "Deprecated" | "SourceDebugExtension" => {
+                self.position = self.info.len();
+            }
+            "Signature" | "ModuleMainClass" => self.index(visitor)?,
+            "Exceptions" | "ModulePackages" => self.indices(visitor)?,
+            "InnerClasses" => {
+                for _ in 0..self.u16()? {
+                    self.index(visitor)?;
+                    self.index(visitor)?;
+                    self.index(visitor)?;
+                    self.skip(2)?;
+                }
+            }
+            "EnclosingMethod" => {
+                self.index(visitor)?;
+                self.index(visitor)?;
+            }
+            "MethodParameters" => {
+                for _ in 0..self.u8()? {
+                    self.index(visitor)?;
+                    self.skip(2)?;
+                }
+            }
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                for _ in 0..self.u16()? {
+                    self.annotation(visitor)?;
+                }
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                for _ in 0..self.u8()? {
+                    for _ in 0..self.u16()? {
+                        self.annotation(visitor)?;
+                    }
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                for _ in 0..self.u16()? {
+                    self.type_annotation(visitor)?;
+                }
+            }
+            "AnnotationDefault" => self.element_value(visitor)?,
+            "Module" => {
+                self.index(visitor)?;
+                self.skip(2)?;
+                self.index(visitor)?;
+                for _ in 0..self.u16()? {
+                    self.index(visitor)?;
+                    self.skip(2)?;
+                    self.index(visitor)?;
+                }
+                // Exports, then opens.
+                for _ in 0..2 {
+                    for _ in 0..self.u16()? {
+                        self.index(visitor)?;
+                        self.skip(2)?;
+                        self.indices(visitor)?;
+                    }
+                }
+                self.indices(visitor)?;
+                for _ in 0..self.u16()? {
+                    self.index(visitor)?;
+                    self.indices(visitor)?;
+                }
+            }
+            "Record" => {
+                for _ in 0..self.u16()? {
+                    self.index(visitor)?;
+                    self.index(visitor)?;
+                    for _ in 0..self.u16()? {
+                        let name_index = self.u16()?;
+                        let name = constant_pool.utf8(name_index as usize)?;
+                        self.position -= 2;
+                        self.index(visitor)?;
+                        let length = self.u32()? as usize;
+                        let end = self.position.checked_add(length)?;
+                        let mut nested = RawAttribute {
+                            info: self.info.get_mut(self.position..end)?,
+                            position: 0,
+                        };
+                        nested.visit(name, constant_pool, visitor)?;
+                        self.position = end;
+                    }
+                }
+            }
+            _ => return None,
+        }
+        (self.position == self.info.len()).then_some(())
+    }
+
+    fn annotation(&mut self, visitor: &mut impl IndexVisitor) -> Option<()> {
+        self.index(visitor)?;
+        for _ in 0..self.u16()? {
+            self.index(visitor)?;
+            self.element_value(visitor)?;
+        }
+        Some(())
+    }
+
+    fn element_value(&mut self, visitor: &mut impl IndexVisitor) -> Option<()> {
+        match self.u8()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' | b'c' => {
+                self.index(visitor)
+            }
+            b'e' => {
+                self.index(visitor)?;
+                self.index(visitor)
+            }
+            b'@' => self.annotation(visitor),
+            b'[' => {
+                for _ in 0..self.u16()? {
+                    self.element_value(visitor)?;
+                }
+                Some(())
+            }
+            _ => None,
+        }
+    }
+
+    fn type_annotation(&mut self, visitor: &mut impl IndexVisitor) -> Option<()> {
+        self.skip_type_annotation_target()?;
+        self.annotation(visitor)
+    }
+
+    /// Skips the target and the type path of a `type_annotation`, which hold no indices.
+    pub(crate) fn skip_type_annotation_target(&mut self) -> Option<()> {
+        match self.u8()? {
+            0x00 | 0x01 | 0x16 => self.skip(1)?,
+            0x10..=0x12 | 0x17 | 0x42..=0x46 => self.skip(2)?,
+            0x13..=0x15 => {}
+            0x40 | 0x41 => {
+                let length = self.u16()? as usize;
+                self.skip(length * 6)?;
+            }
+            0x47..=0x4B => self.skip(3)?,
+            _ => return None,
+        }
+        let path_length = self.u8()? as usize;
+        self.skip(path_length * 2)
+    }
+}
@@ -0,0 +1,548 @@
+//! Control-flow graphs of `Code` attributes.
+//!
+//! Blocks are split at branch targets, after branches, and at the bounds of exception
+//! handler ranges, so that every instruction of a block is covered by the same handlers.
+//! Subroutines are handled by adding a [`EdgeKind::Ret`] edge from each `ret` to the
+//! instruction after every `jsr` that calls its subroutine.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::code_view::CodeView;
+use crate::instruction::{Instruction, WideInstruction};
+use crate::predefined_attributes::Code;
+
+type Result<T> = std::result::Result<T, ControlFlowError>;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum ControlFlowError {
+    #[error("Method has no code")]
+    #[non_exhaustive]
+    EmptyCode,
+    #[error("Instruction at pc {pc} branches to {target}, which is not an instruction")]
+    #[non_exhaustive]
+    InvalidBranchTarget { pc: u32, target: i64 },
+    #[error("Instruction at pc {0} falls off the end of the code")]
+    #[non_exhaustive]
+    FallsOffEnd(u32),
+    #[error("Exception handler [{start_pc}, {end_pc}) -> {handler_pc} is not at instructions")]
+    #[non_exhaustive]
+    InvalidExceptionHandler { start_pc: u16, end_pc: u16, handler_pc: u16 },
+    #[error("ret at pc {0} is not part of a subroutine")]
+    #[non_exhaustive]
+    UnmatchedRet(u32),
+}
+
+pub type BlockId = usize;
+
+/// A maximal run of instructions that is only entered at its first instruction and only
+/// left after its last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub id: BlockId,
+    /// The indices of the block's instructions in [`Code::code`].
+    pub instructions: Range<usize>,
+    pub start_pc: u32,
+    /// The pc of the last instruction of the block.
+    pub last_pc: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// To the next block, when the last instruction does not transfer control or is a
+    /// conditional branch that is not taken.
+    FallThrough,
+    /// A `goto` or a taken conditional branch.
+    Jump,
+    /// The case of a `tableswitch` or `lookupswitch` with the given key.
+    Switch(i32),
+    SwitchDefault,
+    /// From a `jsr` to the start of its subroutine.
+    Jsr,
+    /// From a `ret` to the instruction after a `jsr` that called the subroutine.
+    Ret,
+    /// From a block covered by an exception handler to the handler, with the handler's
+    /// `catch_type` constant pool index, 0 for handlers that catch everything.
+    Exception {
+        catch_type: u16,
+    },
+}
+
+impl EdgeKind {
+    pub fn is_exceptional(&self) -> bool {
+        matches!(self, EdgeKind::Exception { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    pub from: BlockId,
+    pub to: BlockId,
+    pub kind: EdgeKind,
+}
+
+/// The basic blocks of a method and the edges between them. Block 0 is the entry block and
+/// blocks are numbered in pc order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+    /// Edges ordered by source block, normal edges before exceptional ones.
+    pub edges: Vec<Edge>,
+    successors: Vec<Vec<BlockId>>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl ControlFlowGraph {
+    pub fn new(code: &Code) -> Result<Self> {
+        Builder::new(code)?.build()
+    }
+
+    pub fn entry(&self) -> BlockId {
+        0
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// The block containing the instruction at `pc`.
+    pub fn block_at(&self, pc: u32) -> Option<BlockId> {
+        let index = self.blocks.partition_point(|block| block.start_pc <= pc).checked_sub(1)?;
+        (pc <= self.blocks[index].last_pc).then_some(index)
+    }
+
+    /// The distinct successors of `block`, in the order of its edges.
+    pub fn successors(&self, block: BlockId) -> &[BlockId] {
+        &self.successors[block]
+    }
+
+    /// The distinct predecessors of `block`, in block order.
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block]
+    }
+
+    pub fn edges_from(&self, block: BlockId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == block)
+    }
+
+    pub fn edges_to(&self, block: BlockId) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.to == block)
+    }
+
+    /// The blocks reachable from the entry, each before its successors except along back
+    /// edges. Exception handlers are reached through their exceptional edges.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::with_capacity(self.blocks.len());
+        let mut stack = vec![(self.entry(), 0)];
+        visited[self.entry()] = true;
+        while let Some((block, next)) = stack.pop() {
+            match self.successors[block].get(next) {
+                Some(&successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    /// Graphviz source with the instructions of each block and the source lines they were
+    /// compiled from, according to the `LineNumberTable` of `code`. Exceptional edges are
+    /// dashed and subroutine returns dotted.
+    pub fn to_dot(&self, code: &Code) -> String {
+        let view = CodeView::new(code);
+
+        let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        for block in &self.blocks {
+            let mut label = format!("B{}", block.id);
+            let mut block_lines = Vec::new();
+            for (_, pc) in &code.code[block.instructions.clone()] {
+                if let Some(line) = view.line_at(*pc).filter(|line| !block_lines.contains(line)) {
+                    block_lines.push(line);
+                }
+            }
+            if !block_lines.is_empty() {
+                let block_lines = block_lines.iter().map(u16::to_string).collect::<Vec<_>>();
+                write!(label, " (line {})", block_lines.join(", ")).unwrap();
+            }
+            label.push_str("\\l");
+            for (instruction, pc) in &code.code[block.instructions.clone()] {
+                write!(label, "{}: {}\\l", pc, escape(&format!("{:?}", instruction))).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.id, label).unwrap();
+        }
+        for edge in &self.edges {
+            let attributes = match edge.kind {
+                EdgeKind::FallThrough | EdgeKind::Jump => String::new(),
+                EdgeKind::Switch(key) => format!(" [label=\"{}\"]", key),
+                EdgeKind::SwitchDefault => " [label=\"default\"]".to_string(),
+                EdgeKind::Jsr => " [label=\"jsr\"]".to_string(),
+                EdgeKind::Ret => " [label=\"ret\", style=dotted]".to_string(),
+                EdgeKind::Exception { catch_type: 0 } => {
+                    " [label=\"any\", style=dashed]".to_string()
+                }
+                EdgeKind::Exception { catch_type } => {
+                    format!(" [label=\"#{}\", style=dashed]", catch_type)
+                }
+            };
+            writeln!(dot, "    b{} -> b{}{};", edge.from, edge.to, attributes).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The control transfer made by the last instruction of a block.
+enum Transfer {
+    /// Continues with the next instruction.
+    Next,
+    /// Leaves the method, by returning or throwing.
+    Exit,
+    Goto(i64),
+    Branch(i64),
+    Switch {
+        default: i64,
+        cases: Vec<(i32, i64)>,
+    },
+    Jsr(i64),
+    Ret,
+}
+
+fn transfer(instruction: &Instruction, pc: u32) -> Transfer {
+    let target = |offset: i32| pc as i64 + offset as i64;
+    match instruction {
+        Instruction::Tableswitch { default, low, high, offsets } => Transfer::Switch {
+            default: target(*default),
+            cases: (*low..=*high)
+                .zip(offsets)
+                .map(|(key, offset)| (key, target(*offset)))
+                .collect(),
+        },
+        Instruction::Lookupswitch { default, pairs } => Transfer::Switch {
+            default: target(*default),
+            cases: pairs.iter().map(|(key, offset)| (*key, target(*offset))).collect(),
+        },
+        Instruction::Ret(_) | Instruction::Wide(WideInstruction::Ret(_)) => Transfer::Ret,
+        _ => match instruction.branch_offset() {
+            Some(offset) if instruction.is_conditional() => Transfer::Branch(target(offset)),
+            Some(offset) if matches!(instruction, Instruction::Jsr(_) | Instruction::Jsr_w(_)) => {
+                Transfer::Jsr(target(offset))
+            }
+            Some(offset) => Transfer::Goto(target(offset)),
+            None if instruction.is_terminator() => Transfer::Exit,
+            None => Transfer::Next,
+        },
+    }
+}
+
+/// A `jsr` in block `caller` to the subroutine starting at block `subroutine`, which returns
+/// to block `site`.
+struct ReturnSite {
+    caller: BlockId,
+    subroutine: BlockId,
+    site: BlockId,
+}
+
+struct Builder<'a> {
+    code: &'a Code,
+    pcs: Vec<u32>,
+    transfers: Vec<Transfer>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(code: &'a Code) -> Result<Self> {
+        if code.code.is_empty() {
+            return Err(ControlFlowError::EmptyCode);
+        }
+        Ok(Builder {
+            code,
+            pcs: code.code.iter().map(|(_, pc)| *pc).collect(),
+            transfers: code
+                .code
+                .iter()
+                .map(|(instruction, pc)| transfer(instruction, *pc))
+                .collect(),
+        })
+    }
+
+    /// The index of the instruction at `target`, for a branch of the instruction at `index`.
+    fn target_index(&self, index: usize, target: i64) -> Result<usize> {
+        u32::try_from(target)
+            .ok()
+            .and_then(|target| self.pcs.binary_search(&target).ok())
+            .ok_or(ControlFlowError::InvalidBranchTarget { pc: self.pcs[index], target })
+    }
+
+    /// The index of the instruction after the one at `index`.
+    fn next_index(&self, index: usize) -> Result<usize> {
+        match index + 1 < self.pcs.len() {
+            true => Ok(index + 1),
+            false => Err(ControlFlowError::FallsOffEnd(self.pcs[index])),
+        }
+    }
+
+    /// The instruction range of an exception handler, where an end past the last instruction
+    /// is the end of the code, and the index of the handler.
+    fn handler_indices(&self, handler_index: usize) -> Result<(Range<usize>, usize)> {
+        let handler = &self.code.exception_table[handler_index];
+        let error = ControlFlowError::InvalidExceptionHandler {
+            start_pc: handler.start_pc,
+            end_pc: handler.end_pc,
+            handler_pc: handler.handler_pc,
+        };
+        let index = |pc: u16| self.pcs.binary_search(&(pc as u32));
+        let start = index(handler.start_pc).map_err(|_| error.clone())?;
+        let end = match index(handler.end_pc) {
+            Ok(end) => end,
+            Err(end) if end == self.pcs.len() => end,
+            Err(_) => return Err(error),
+        };
+        let handler_start = index(handler.handler_pc).map_err(|_| error.clone())?;
+        if start >= end {
+            return Err(error);
+        }
+        Ok((start..end, handler_start))
+    }
+
+    fn build(self) -> Result<ControlFlowGraph> {
+        let count = self.pcs.len();
+        let mut leaders = BTreeSet::from([0]);
+        for (index, transfer) in self.transfers.iter().enumerate() {
+            let targets = match transfer {
+                Transfer::Next => continue,
+                Transfer::Exit | Transfer::Ret => Vec::new(),
+                Transfer::Goto(target) | Transfer::Branch(target) | Transfer::Jsr(target) => {
+                    vec![*target]
+                }
+                Transfer::Switch { default, cases } => {
+                    cases.iter().map(|(_, target)| *target).chain([*default]).collect()
+                }
+            };
+            for target in targets {
+                leaders.insert(self.target_index(index, target)?);
+            }
+            leaders.insert(index + 1);
+        }
+        let handlers = (0..self.code.exception_table.len())
+            .map(|handler| self.handler_indices(handler))
+            .collect::<Result<Vec<_>>>()?;
+        for (range, handler) in &handlers {
+            leaders.extend([range.start, range.end, *handler]);
+        }
+        leaders.retain(|&leader| leader < count);
+
+        let starts = leaders.into_iter().collect::<Vec<_>>();
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(id, &start)| {
+                let end = starts.get(id + 1).copied().unwrap_or(count);
+                BasicBlock {
+                    id,
+                    instructions: start..end,
+                    start_pc: self.pcs[start],
+                    last_pc: self.pcs[end - 1],
+                }
+            })
+            .collect::<Vec<_>>();
+        let block_of = |index: usize| starts.partition_point(|&start| start <= index) - 1;
+
+        let mut edges = Vec::new();
+        let mut return_sites = Vec::<ReturnSite>::new();
+        for block in &blocks {
+            let last = block.instructions.end - 1;
+            let mut edge =
+                |to: usize, kind| edges.push(Edge { from: block.id, to: block_of(to), kind });
+            match &self.transfers[last] {
+                Transfer::Next => edge(self.next_index(last)?, EdgeKind::FallThrough),
+                Transfer::Exit | Transfer::Ret => {}
+                Transfer::Goto(target) => edge(self.target_index(last, *target)?, EdgeKind::Jump),
+                Transfer::Branch(target) => {
+                    edge(self.target_index(last, *target)?, EdgeKind::Jump);
+                    edge(self.next_index(last)?, EdgeKind::FallThrough);
+                }
+                Transfer::Switch { default, cases } => {
+                    for (key, target) in cases {
+                        edge(self.target_index(last, *target)?, EdgeKind::Switch(*key));
+                    }
+                    edge(self.target_index(last, *default)?, EdgeKind::SwitchDefault);
+                }
+                Transfer::Jsr(target) => {
+                    let subroutine = self.target_index(last, *target)?;
+                    edge(subroutine, EdgeKind::Jsr);
+                    return_sites.push(ReturnSite {
+                        caller: block.id,
+                        subroutine: block_of(subroutine),
+                        site: block_of(self.next_index(last)?),
+                    });
+                }
+            }
+        }
+        for block in &blocks {
+            for ((range, handler), entry) in handlers.iter().zip(&self.code.exception_table) {
+                if range.contains(&block.instructions.start) {
+                    let kind = EdgeKind::Exception { catch_type: entry.catch_type };
+                    edges.push(Edge { from: block.id, to: block_of(*handler), kind });
+                }
+            }
+        }
+        edges.extend(self.ret_edges(&blocks, &edges, &return_sites)?);
+        edges.sort_by_key(|edge| (edge.from, edge.kind.is_exceptional()));
+
+        let mut successors = vec![Vec::new(); blocks.len()];
+        let mut predecessors = vec![Vec::new(); blocks.len()];
+        for edge in &edges {
+            if !successors[edge.from].contains(&edge.to) {
+                successors[edge.from].push(edge.to);
+            }
+            predecessors[edge.to].push(edge.from);
+        }
+        for predecessors in &mut predecessors {
+            predecessors.sort();
+            predecessors.dedup();
+        }
+        Ok(ControlFlowGraph { blocks, edges, successors, predecessors })
+    }
+
+    /// Edges from every `ret` to the return sites of the subroutine it belongs to. The blocks
+    /// of a subroutine are the ones reachable from its entry without following exceptional
+    /// edges, stepping over the calls of nested subroutines.
+    fn ret_edges(
+        &self,
+        blocks: &[BasicBlock],
+        edges: &[Edge],
+        return_sites: &[ReturnSite],
+    ) -> Result<Vec<Edge>> {
+        let subroutines = return_sites.iter().map(|call| call.subroutine).collect::<BTreeSet<_>>();
+        let mut ret_edges = Vec::new();
+        let mut matched = BTreeSet::new();
+        for &entry in &subroutines {
+            let mut visited = BTreeSet::from([entry]);
+            let mut work = vec![entry];
+            while let Some(block) = work.pop() {
+                let last = blocks[block].instructions.end - 1;
+                if matches!(self.transfers[last], Transfer::Ret) {
+                    matched.insert(block);
+                    for call in return_sites.iter().filter(|call| call.subroutine == entry) {
+                        ret_edges.push(Edge { from: block, to: call.site, kind: EdgeKind::Ret });
+                    }
+                }
+                let successors = edges
+                    .iter()
+                    .filter(|edge| edge.from == block && !edge.kind.is_exceptional())
+                    .map(|edge| match edge.kind {
+                        EdgeKind::Jsr => {
+                            return_sites.iter().find(|call| call.caller == block).unwrap().site
+                        }
+                        _ => edge.to,
+                    })
+                    .collect::<Vec<_>>();
+                for successor in successors {
+                    if visited.insert(successor) {
+                        work.push(successor);
+                    }
+                }
+            }
+        }
+        for block in blocks {
+            let last = block.instructions.end - 1;
+            if matches!(self.transfers[last], Transfer::Ret) && !matched.contains(&block.id) {
+                return Err(ControlFlowError::UnmatchedRet(self.pcs[last]));
+            }
+        }
+        Ok(ret_edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predefined_attributes::ExceptionHandler;
+
+    fn code(instructions: Vec<(Instruction, u32)>, handlers: Vec<ExceptionHandler>) -> Code {
+        Code {
+            max_stack: 2,
+            max_locals: 2,
+            code: instructions,
+            exception_table: handlers,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn edges(graph: &ControlFlowGraph) -> Vec<(BlockId, BlockId, EdgeKind)> {
+        graph.edges.iter().map(|edge| (edge.from, edge.to, edge.kind)).collect()
+    }
+
+    #[test]
+    fn test_subroutines() {
+        // try { i = 1; } finally { i = 2; } in the style of javac before 1.4.2.
+        let code = code(
+            vec![
+                (Instruction::Iconst_1, 0),
+                (Instruction::Istore_0, 1),
+                (Instruction::Jsr(10), 2),
+                (Instruction::Return, 5),
+                (Instruction::Astore_1, 6),
+                (Instruction::Jsr(5), 7),
+                (Instruction::Aload_1, 10),
+                (Instruction::Athrow, 11),
+                (Instruction::Astore_1, 12),
+                (Instruction::Iconst_2, 13),
+                (Instruction::Istore_0, 14),
+                (Instruction::Ret(1), 15),
+            ],
+            vec![ExceptionHandler::new(0, 2, 6, 0)],
+        );
+        let graph = ControlFlowGraph::new(&code).unwrap();
+
+        let starts = graph.blocks.iter().map(|block| block.start_pc).collect::<Vec<_>>();
+        assert_eq!(starts, [0, 2, 5, 6, 10, 12]);
+        assert_eq!(
+            edges(&graph),
+            [
+                (0, 1, EdgeKind::FallThrough),
+                (0, 3, EdgeKind::Exception { catch_type: 0 }),
+                (1, 5, EdgeKind::Jsr),
+                (3, 5, EdgeKind::Jsr),
+                (5, 2, EdgeKind::Ret),
+                (5, 4, EdgeKind::Ret),
+            ]
+        );
+        assert_eq!(graph.predecessors(5), [1, 3]);
+        assert_eq!(graph.reverse_postorder(), [0, 3, 1, 5, 4, 2]);
+        assert_eq!(graph.block_at(14), Some(5));
+        assert_eq!(graph.block_at(16), None);
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let falls_off = code(vec![(Instruction::Iconst_1, 0), (Instruction::Pop, 1)], Vec::new());
+        assert_eq!(ControlFlowGraph::new(&falls_off), Err(ControlFlowError::FallsOffEnd(1)));
+
+        let into_operand = code(vec![(Instruction::Goto(1), 0)], Vec::new());
+        assert_eq!(
+            ControlFlowGraph::new(&into_operand),
+            Err(ControlFlowError::InvalidBranchTarget { pc: 0, target: 1 })
+        );
+
+        let stray_ret = code(vec![(Instruction::Ret(0), 0)], Vec::new());
+        assert_eq!(ControlFlowGraph::new(&stray_ret), Err(ControlFlowError::UnmatchedRet(0)));
+    }
+}
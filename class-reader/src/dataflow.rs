@@ -0,0 +1,1186 @@
+//! Dataflow analysis of method bytecode.
+//!
+//! Two engines run over the [`ControlFlowGraph`] of a method:
+//!
+//! - [`solve`] computes a fixed point of a [`DataflowAnalysis`], a forward or backward
+//!   analysis over a lattice of the caller's choosing. [`Liveness`] and
+//!   [`ReachingDefinitions`] are built on it.
+//! - [`analyze_frames`] interprets the method abstractly over frames of local variables and
+//!   operand stack slots, as the verifier does, with the values of each slot taken from a
+//!   [`ValueInterpreter`]. It models the stack effect of every instruction, including long
+//!   and double values that take two slots, and applies the method's `StackMapTable` frames
+//!   at the blocks they describe. [`ConstantPropagation`] and [`Nullness`] are interpreters.
+//!
+//! Both engines flow the state before every instruction of a block covered by an exception
+//! handler into the handler, since any of them may throw.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display, Formatter};
+
+use thiserror::Error;
+
+use crate::class_file::ClassFile;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::control_flow::{BlockId, ControlFlowError, ControlFlowGraph, EdgeKind};
+use crate::field::{BaseType, FieldType};
+use crate::instruction::{Effect, Instruction, WideInstruction};
+use crate::method::Method;
+use crate::predefined_attributes::Code;
+use crate::stack_map::{StackMap, StackMapError, VerificationType};
+
+type Result<T> = std::result::Result<T, DataflowError>;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum DataflowError {
+    #[error("Method {0} has no code")]
+    #[non_exhaustive]
+    MissingCode(String),
+    #[error("{0}")]
+    #[non_exhaustive]
+    ControlFlow(#[from] ControlFlowError),
+    #[error("Operand stack underflow at pc {0}")]
+    #[non_exhaustive]
+    StackUnderflow(u32),
+    #[error("Operand stack overflow at pc {0}")]
+    #[non_exhaustive]
+    StackOverflow(u32),
+    #[error("Instruction at pc {0} splits a long or double value")]
+    #[non_exhaustive]
+    CategoryMismatch(u32),
+    #[error("Local variable {local} is out of range at pc {pc}")]
+    #[non_exhaustive]
+    InvalidLocal { pc: u32, local: u16 },
+    #[error("Invalid constant pool reference at pc {0}")]
+    #[non_exhaustive]
+    InvalidConstant(u32),
+    #[error("Stack heights differ where control flow merges at pc {0}")]
+    #[non_exhaustive]
+    StackHeightMismatch(u32),
+    #[error("StackMapTable frame at pc {0} does not match the code")]
+    #[non_exhaustive]
+    InvalidStackMapFrame(u32),
+    #[error("{0}")]
+    #[non_exhaustive]
+    StackMap(#[from] StackMapError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// A dataflow problem over a lattice of states, solved by [`solve`].
+pub trait DataflowAnalysis {
+    type State: Clone + PartialEq + Debug;
+
+    fn direction(&self) -> Direction;
+
+    /// The state on method entry for forward analyses, and after the instructions that leave
+    /// the method for backward ones.
+    fn boundary(&self) -> Self::State;
+
+    /// The least element of the lattice, which every other block starts from.
+    fn bottom(&self) -> Self::State;
+
+    /// Joins `other` into `state`, returning whether `state` changed.
+    fn join(&self, state: &mut Self::State, other: &Self::State) -> bool;
+
+    /// Applies `instruction` to `state`, turning the state before it into the state after it
+    /// for forward analyses and the other way round for backward ones.
+    fn transfer(&self, state: &mut Self::State, instruction: &Instruction, pc: u32);
+}
+
+/// The fixed point of a [`DataflowAnalysis`]. States are given in program order whatever
+/// the direction of the analysis: the entry state of a block is the state before its first
+/// instruction.
+#[derive(Debug)]
+pub struct Solution<'a, A: DataflowAnalysis> {
+    analysis: A,
+    code: &'a Code,
+    graph: &'a ControlFlowGraph,
+    entries: Vec<A::State>,
+    exits: Vec<A::State>,
+}
+
+impl<A: DataflowAnalysis> Solution<'_, A> {
+    pub fn analysis(&self) -> &A {
+        &self.analysis
+    }
+
+    pub fn entry(&self, block: BlockId) -> &A::State {
+        &self.entries[block]
+    }
+
+    pub fn exit(&self, block: BlockId) -> &A::State {
+        &self.exits[block]
+    }
+
+    /// The state before the instruction at `pc`.
+    pub fn before(&self, pc: u32) -> Option<A::State> {
+        self.at(pc).map(|(before, _)| before)
+    }
+
+    /// The state after the instruction at `pc`.
+    pub fn after(&self, pc: u32) -> Option<A::State> {
+        self.at(pc).map(|(_, after)| after)
+    }
+
+    /// Replays the transfer functions of the block containing `pc` up to its instruction.
+    fn at(&self, pc: u32) -> Option<(A::State, A::State)> {
+        let block = self.graph.block_at(pc)?;
+        let instructions = &self.code.code[self.graph.blocks[block].instructions.clone()];
+        match self.analysis.direction() {
+            Direction::Forward => {
+                let mut state = self.entries[block].clone();
+                for (instruction, instruction_pc) in instructions {
+                    let before = state.clone();
+                    self.analysis.transfer(&mut state, instruction, *instruction_pc);
+                    if *instruction_pc == pc {
+                        return Some((before, state));
+                    }
+                }
+            }
+            Direction::Backward => {
+                let handlers = exception_handlers(self.graph, block);
+                let mut state = self.exits[block].clone();
+                for (instruction, instruction_pc) in instructions.iter().rev() {
+                    let after = state.clone();
+                    self.analysis.transfer(&mut state, instruction, *instruction_pc);
+                    for &(handler, _) in &handlers {
+                        self.analysis.join(&mut state, &self.entries[handler]);
+                    }
+                    if *instruction_pc == pc {
+                        return Some((state, after));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Solves `analysis` over the blocks of `graph`, the control-flow graph of `code`, with a
+/// worklist.
+pub fn solve<'a, A: DataflowAnalysis>(
+    analysis: A,
+    code: &'a Code,
+    graph: &'a ControlFlowGraph,
+) -> Solution<'a, A> {
+    let count = graph.len();
+    let mut entries = vec![analysis.bottom(); count];
+    let mut exits = vec![analysis.bottom(); count];
+    let normal_successors = (0..count)
+        .map(|block| {
+            let mut successors = graph
+                .edges_from(block)
+                .filter(|edge| !edge.kind.is_exceptional())
+                .map(|edge| edge.to)
+                .collect::<Vec<_>>();
+            successors.dedup();
+            successors
+        })
+        .collect::<Vec<_>>();
+    let handlers = (0..count).map(|block| exception_handlers(graph, block)).collect::<Vec<_>>();
+    let instructions = |block: BlockId| &code.code[graph.blocks[block].instructions.clone()];
+
+    let mut order = graph.reverse_postorder();
+    if analysis.direction() == Direction::Backward {
+        // Blocks that cannot be reached still contribute to the blocks they flow into.
+        let reachable = order.iter().copied().collect::<BTreeSet<_>>();
+        order.extend((0..count).filter(|block| !reachable.contains(block)));
+        order.reverse();
+    }
+    let mut queued = vec![false; count];
+    let mut worklist = std::collections::VecDeque::new();
+    for &block in &order {
+        queued[block] = true;
+        worklist.push_back(block);
+    }
+    if let Some(entry) = entries.get_mut(graph.entry()) {
+        if analysis.direction() == Direction::Forward {
+            *entry = analysis.boundary();
+        }
+    }
+
+    while let Some(block) = worklist.pop_front() {
+        queued[block] = false;
+        let mut changed = Vec::new();
+        match analysis.direction() {
+            Direction::Forward => {
+                let mut state = entries[block].clone();
+                for (instruction, pc) in instructions(block) {
+                    for &(handler, _) in &handlers[block] {
+                        if analysis.join(&mut entries[handler], &state) {
+                            changed.push(handler);
+                        }
+                    }
+                    analysis.transfer(&mut state, instruction, *pc);
+                }
+                for &successor in &normal_successors[block] {
+                    if analysis.join(&mut entries[successor], &state) {
+                        changed.push(successor);
+                    }
+                }
+                exits[block] = state;
+            }
+            Direction::Backward => {
+                let mut state = match normal_successors[block].is_empty() {
+                    true => analysis.boundary(),
+                    false => analysis.bottom(),
+                };
+                for &successor in &normal_successors[block] {
+                    analysis.join(&mut state, &entries[successor]);
+                }
+                exits[block] = state.clone();
+                for (instruction, pc) in instructions(block).iter().rev() {
+                    analysis.transfer(&mut state, instruction, *pc);
+                    for &(handler, _) in &handlers[block] {
+                        analysis.join(&mut state, &entries[handler]);
+                    }
+                }
+                if state != entries[block] {
+                    entries[block] = state;
+                    changed.extend(graph.predecessors(block));
+                }
+            }
+        }
+        for block in changed {
+            if !queued[block] {
+                queued[block] = true;
+                worklist.push_back(block);
+            }
+        }
+    }
+    Solution { analysis, code, graph, entries, exits }
+}
+
+/// The handlers covering `block`, with their catch types.
+fn exception_handlers(graph: &ControlFlowGraph, block: BlockId) -> Vec<(BlockId, u16)> {
+    graph
+        .edges_from(block)
+        .filter_map(|edge| match edge.kind {
+            EdgeKind::Exception { catch_type } => Some((edge.to, catch_type)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// The local variable an instruction reads or writes, with the number of slots it takes.
+pub(crate) fn local_access(instruction: &Instruction) -> Option<(u16, u16, Access)> {
+    use Access::{Read, ReadWrite, Write};
+    Some(match instruction {
+        Instruction::Iload(local) | Instruction::Fload(local) | Instruction::Aload(local) => {
+            (*local as u16, 1, Read)
+        }
+        Instruction::Lload(local) | Instruction::Dload(local) => (*local as u16, 2, Read),
+        Instruction::Istore(local) | Instruction::Fstore(local) | Instruction::Astore(local) => {
+            (*local as u16, 1, Write)
+        }
+        Instruction::Lstore(local) | Instruction::Dstore(local) => (*local as u16, 2, Write),
+        Instruction::Iinc(local, _) => (*local as u16, 1, ReadWrite),
+        Instruction::Ret(local) => (*local as u16, 1, Read),
+        Instruction::Iload_0 | Instruction::Fload_0 | Instruction::Aload_0 => (0, 1, Read),
+        Instruction::Iload_1 | Instruction::Fload_1 | Instruction::Aload_1 => (1, 1, Read),
+        Instruction::Iload_2 | Instruction::Fload_2 | Instruction::Aload_2 => (2, 1, Read),
+        Instruction::Iload_3 | Instruction::Fload_3 | Instruction::Aload_3 => (3, 1, Read),
+        Instruction::Lload_0 | Instruction::Dload_0 => (0, 2, Read),
+        Instruction::Lload_1 | Instruction::Dload_1 => (1, 2, Read),
+        Instruction::Lload_2 | Instruction::Dload_2 => (2, 2, Read),
+        Instruction::Lload_3 | Instruction::Dload_3 => (3, 2, Read),
+        Instruction::Istore_0 | Instruction::Fstore_0 | Instruction::Astore_0 => (0, 1, Write),
+        Instruction::Istore_1 | Instruction::Fstore_1 | Instruction::Astore_1 => (1, 1, Write),
+        Instruction::Istore_2 | Instruction::Fstore_2 | Instruction::Astore_2 => (2, 1, Write),
+        Instruction::Istore_3 | Instruction::Fstore_3 | Instruction::Astore_3 => (3, 1, Write),
+        Instruction::Lstore_0 | Instruction::Dstore_0 => (0, 2, Write),
+        Instruction::Lstore_1 | Instruction::Dstore_1 => (1, 2, Write),
+        Instruction::Lstore_2 | Instruction::Dstore_2 => (2, 2, Write),
+        Instruction::Lstore_3 | Instruction::Dstore_3 => (3, 2, Write),
+        Instruction::Wide(wide) => match wide {
+            WideInstruction::Iload(local)
+            | WideInstruction::Fload(local)
+            | WideInstruction::Aload(local)
+            | WideInstruction::Ret(local) => (*local, 1, Read),
+            WideInstruction::Lload(local) | WideInstruction::Dload(local) => (*local, 2, Read),
+            WideInstruction::Istore(local)
+            | WideInstruction::Fstore(local)
+            | WideInstruction::Astore(local) => (*local, 1, Write),
+            WideInstruction::Lstore(local) | WideInstruction::Dstore(local) => (*local, 2, Write),
+            WideInstruction::Iinc(local, _) => (*local, 1, ReadWrite),
+        },
+        _ => return None,
+    })
+}
+
+/// Live local variable slots, a backward analysis: a slot is live before an instruction if
+/// some path from it reads the slot before writing it. Long and double variables take two
+/// slots.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Liveness;
+
+impl DataflowAnalysis for Liveness {
+    type State = BTreeSet<u16>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> Self::State {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> Self::State {
+        BTreeSet::new()
+    }
+
+    fn join(&self, state: &mut Self::State, other: &Self::State) -> bool {
+        let before = state.len();
+        state.extend(other);
+        state.len() != before
+    }
+
+    fn transfer(&self, state: &mut Self::State, instruction: &Instruction, _pc: u32) {
+        if let Some((local, size, access)) = local_access(instruction) {
+            let slots = local..local + size;
+            match access {
+                Access::Write => slots.for_each(|slot| {
+                    state.remove(&slot);
+                }),
+                Access::Read | Access::ReadWrite => state.extend(slots),
+            }
+        }
+    }
+}
+
+/// An assignment of a local variable slot: a store or `iinc` at `pc`, or the value the slot
+/// holds on method entry when `pc` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    pub local: u16,
+    pub pc: Option<u32>,
+}
+
+/// The definitions of local variable slots that reach each instruction, a forward analysis.
+#[derive(Debug, Clone)]
+pub struct ReachingDefinitions {
+    parameter_slots: u16,
+}
+
+impl ReachingDefinitions {
+    pub fn new(method: &Method) -> Self {
+        let this = !method.flags.is_static() as u16;
+        let parameters = method.type_descriptor.parameters().iter().map(slot_count).sum::<u16>();
+        ReachingDefinitions { parameter_slots: this + parameters }
+    }
+}
+
+impl DataflowAnalysis for ReachingDefinitions {
+    type State = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::State {
+        (0..self.parameter_slots).map(|local| Definition { local, pc: None }).collect()
+    }
+
+    fn bottom(&self) -> Self::State {
+        BTreeSet::new()
+    }
+
+    fn join(&self, state: &mut Self::State, other: &Self::State) -> bool {
+        let before = state.len();
+        state.extend(other);
+        state.len() != before
+    }
+
+    fn transfer(&self, state: &mut Self::State, instruction: &Instruction, pc: u32) {
+        if let Some((local, size, Access::Write | Access::ReadWrite)) = local_access(instruction) {
+            let slots = local..local + size;
+            state.retain(|definition| !slots.contains(&definition.local));
+            state.extend(slots.map(|local| Definition { local, pc: Some(pc) }));
+        }
+    }
+}
+
+fn slot_count(field_type: &FieldType) -> u16 {
+    ValueType::of(field_type).size() as u16
+}
+
+/// The verification type of a slot, as far as the engine tracks it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// An unusable slot: uninitialized, the second half of a long or double, or where
+    /// different types merge.
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Reference,
+    /// The address pushed by `jsr`.
+    ReturnAddress,
+}
+
+impl ValueType {
+    /// The number of slots a value of this type takes: 2 for long and double values.
+    pub fn size(self) -> usize {
+        match self {
+            ValueType::Long | ValueType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn of(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Base(BaseType::Long) => ValueType::Long,
+            FieldType::Base(BaseType::Double) => ValueType::Double,
+            FieldType::Base(BaseType::Float) => ValueType::Float,
+            FieldType::Base(_) => ValueType::Int,
+            FieldType::Object(_) | FieldType::Array(_) => ValueType::Reference,
+        }
+    }
+
+    fn of_verification_type(info: &VerificationType) -> Self {
+        match info {
+            VerificationType::Top => ValueType::Top,
+            VerificationType::Integer => ValueType::Int,
+            VerificationType::Float => ValueType::Float,
+            VerificationType::Long => ValueType::Long,
+            VerificationType::Double => ValueType::Double,
+            VerificationType::Null
+            | VerificationType::UninitializedThis
+            | VerificationType::Object(_)
+            | VerificationType::Uninitialized { .. } => ValueType::Reference,
+        }
+    }
+}
+
+impl Display for ValueType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = match self {
+            ValueType::Top => "top",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Long => "long",
+            ValueType::Double => "double",
+            ValueType::Reference => "reference",
+            ValueType::ReturnAddress => "returnAddress",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot<V> {
+    pub value_type: ValueType,
+    pub value: V,
+}
+
+/// The local variables and operand stack before an instruction. A long or double value takes
+/// one stack entry but two locals, the second of which is [`ValueType::Top`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame<V> {
+    pub locals: Vec<Slot<V>>,
+    /// The operand stack, from the bottom to the top.
+    pub stack: Vec<Slot<V>>,
+}
+
+impl<V> Frame<V> {
+    pub fn local(&self, index: u16) -> Option<&V> {
+        self.locals.get(index as usize).map(|slot| &slot.value)
+    }
+
+    /// The stack entry `depth` entries below the top, 0 being the top.
+    pub fn peek(&self, depth: usize) -> Option<&V> {
+        self.stack.iter().rev().nth(depth).map(|slot| &slot.value)
+    }
+}
+
+/// What an instruction is being interpreted.
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    pub instruction: &'a Instruction,
+    pub pc: u32,
+    pub constant_pool: &'a ConstantPool,
+}
+
+/// The values of the slots of a [`Frame`], and how instructions compute them. The engine takes
+/// care of the types and categories of slots; interpreters only see values.
+pub trait ValueInterpreter {
+    type Value: Clone + PartialEq + Debug;
+
+    /// A value nothing is known about, also used for unusable slots.
+    fn unknown(&mut self) -> Self::Value;
+
+    /// The value of `this` or of a parameter on method entry.
+    fn parameter(&mut self, local: u16, value_type: ValueType, is_this: bool) -> Self::Value {
+        let _ = (local, value_type, is_this);
+        self.unknown()
+    }
+
+    /// The exception on the stack on entry of a handler, with the class the handler catches,
+    /// `None` for handlers that catch everything.
+    fn exception(&mut self, catch_type: Option<&str>) -> Self::Value {
+        let _ = catch_type;
+        self.unknown()
+    }
+
+    /// What the verification type of a `StackMapTable` frame says about a value, e.g. that
+    /// it is null. The result is joined into the computed value; `None` leaves it as is.
+    fn declared(&mut self, info: &VerificationType) -> Option<Self::Value> {
+        let _ = info;
+        None
+    }
+
+    /// The value pushed by an instruction, given the values it pops from the bottom to the
+    /// top of the stack. Called for every instruction that pushes a value except loads and
+    /// stack manipulation, and for `iinc` with the incremented local.
+    fn operation(
+        &mut self,
+        context: &Context,
+        operands: &[&Self::Value],
+        result: ValueType,
+    ) -> Self::Value;
+
+    /// A value copied by a load, a store or a `dup` instruction.
+    fn copy(&mut self, context: &Context, value: &Self::Value) -> Self::Value {
+        let _ = context;
+        value.clone()
+    }
+
+    /// The least upper bound of two values meeting where control flow merges.
+    fn join(&mut self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// The frames before the instructions of a method, computed by [`analyze_frames`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frames<V> {
+    pcs: Vec<u32>,
+    frames: Vec<Option<Frame<V>>>,
+}
+
+impl<V> Frames<V> {
+    /// The frame before the instruction at `pc`, `None` for unreachable instructions.
+    pub fn before(&self, pc: u32) -> Option<&Frame<V>> {
+        let index = self.pcs.binary_search(&pc).ok()?;
+        self.frames[index].as_ref()
+    }
+
+    /// The pcs of the instructions with their frames, in program order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Option<&Frame<V>>)> {
+        self.pcs.iter().copied().zip(self.frames.iter().map(Option::as_ref))
+    }
+}
+
+/// Interprets the code of `method` with `interpreter` until the frames before every reachable
+/// instruction are stable.
+pub fn analyze_frames<I: ValueInterpreter>(
+    class: &ClassFile,
+    method: &Method,
+    interpreter: &mut I,
+) -> Result<Frames<I::Value>> {
+    let code = method.code().ok_or_else(|| DataflowError::MissingCode(method.name.clone()))?;
+    let graph = ControlFlowGraph::new(code)?;
+    let mut analyzer = FrameAnalyzer {
+        interpreter,
+        constant_pool: &class.constant_pool,
+        code,
+        max_locals: code.max_locals as usize,
+        stack_map: StackMap::new(class, method)?,
+    };
+    analyzer.run(method, &graph)
+}
+
+struct FrameAnalyzer<'a, I: ValueInterpreter> {
+    interpreter: &'a mut I,
+    constant_pool: &'a ConstantPool,
+    code: &'a Code,
+    max_locals: usize,
+    stack_map: StackMap,
+}
+
+impl<I: ValueInterpreter> FrameAnalyzer<'_, I> {
+    fn run(&mut self, method: &Method, graph: &ControlFlowGraph) -> Result<Frames<I::Value>> {
+        let mut entries = vec![None; graph.len()];
+        entries[graph.entry()] = Some(self.initial_frame(method)?);
+        let mut frames = vec![None; self.code.code.len()];
+        let mut queued = vec![false; graph.len()];
+        let mut worklist = vec![graph.entry()];
+        queued[graph.entry()] = true;
+
+        while let Some(block) = worklist.pop() {
+            queued[block] = false;
+            let Some(mut frame) = entries[block].clone() else {
+                continue;
+            };
+            let handlers = exception_handlers(graph, block);
+            let mut changed = Vec::new();
+            for index in graph.blocks[block].instructions.clone() {
+                frames[index] = Some(frame.clone());
+                for &(handler, catch_type) in &handlers {
+                    let catch_type = match catch_type {
+                        0 => None,
+                        catch_type => Some(
+                            self.constant_pool
+                                .class_name(catch_type as usize)
+                                .ok_or(DataflowError::InvalidConstant(self.code.code[index].1))?,
+                        ),
+                    };
+                    let exception = Slot {
+                        value_type: ValueType::Reference,
+                        value: self.interpreter.exception(catch_type),
+                    };
+                    let handler_frame =
+                        Frame { locals: frame.locals.clone(), stack: vec![exception] };
+                    if self.merge_into(&mut entries[handler], handler_frame, graph, handler)? {
+                        changed.push(handler);
+                    }
+                }
+                self.execute(&mut frame, index)?;
+            }
+            let mut successors = graph
+                .edges_from(block)
+                .filter(|edge| !edge.kind.is_exceptional())
+                .map(|edge| edge.to)
+                .collect::<Vec<_>>();
+            successors.dedup();
+            for successor in successors {
+                if self.merge_into(&mut entries[successor], frame.clone(), graph, successor)? {
+                    changed.push(successor);
+                }
+            }
+            for block in changed {
+                if !queued[block] {
+                    queued[block] = true;
+                    worklist.push(block);
+                }
+            }
+        }
+        Ok(Frames { pcs: self.code.code.iter().map(|(_, pc)| *pc).collect(), frames })
+    }
+
+    fn top(&mut self) -> Slot<I::Value> {
+        Slot { value_type: ValueType::Top, value: self.interpreter.unknown() }
+    }
+
+    fn initial_frame(&mut self, method: &Method) -> Result<Frame<I::Value>> {
+        let mut locals = Vec::with_capacity(self.max_locals);
+        if !method.flags.is_static() {
+            let value = self.interpreter.parameter(0, ValueType::Reference, true);
+            locals.push(Slot { value_type: ValueType::Reference, value });
+        }
+        for parameter in method.type_descriptor.parameters() {
+            let value_type = ValueType::of(parameter);
+            let value = self.interpreter.parameter(locals.len() as u16, value_type, false);
+            locals.push(Slot { value_type, value });
+            if value_type.size() == 2 {
+                locals.push(self.top());
+            }
+        }
+        if locals.len() > self.max_locals {
+            let local = self.max_locals as u16;
+            return Err(DataflowError::InvalidLocal { pc: 0, local });
+        }
+        while locals.len() < self.max_locals {
+            locals.push(self.top());
+        }
+        Ok(Frame { locals, stack: Vec::new() })
+    }
+
+    /// Merges `frame` into the entry frame of `block`, then applies the block's stack map
+    /// frame. Returns whether the entry frame changed.
+    fn merge_into(
+        &mut self,
+        entry: &mut Option<Frame<I::Value>>,
+        frame: Frame<I::Value>,
+        graph: &ControlFlowGraph,
+        block: BlockId,
+    ) -> Result<bool> {
+        let pc = graph.blocks[block].start_pc;
+        let previous = entry.take();
+        let mut merged = match &previous {
+            None => frame,
+            Some(previous) => {
+                if previous.stack.len() != frame.stack.len() {
+                    return Err(DataflowError::StackHeightMismatch(pc));
+                }
+                let mut merged = previous.clone();
+                let slots = merged.locals.iter_mut().zip(&frame.locals);
+                for (slot, other) in slots.chain(merged.stack.iter_mut().zip(&frame.stack)) {
+                    *slot = self.join_slots(slot, other);
+                }
+                merged
+            }
+        };
+        self.apply_stack_map(&mut merged, pc)?;
+        let changed = previous.as_ref() != Some(&merged);
+        *entry = Some(merged);
+        Ok(changed)
+    }
+
+    fn join_slots(&mut self, a: &Slot<I::Value>, b: &Slot<I::Value>) -> Slot<I::Value> {
+        if a.value_type != b.value_type {
+            return self.top();
+        }
+        Slot { value_type: a.value_type, value: self.interpreter.join(&a.value, &b.value) }
+    }
+
+    fn apply_stack_map(&mut self, frame: &mut Frame<I::Value>, pc: u32) -> Result<()> {
+        let Some(declared) = self.stack_map.frame_at(pc) else {
+            return Ok(());
+        };
+        if declared.stack.len() != frame.stack.len() || declared.locals.len() > frame.locals.len() {
+            return Err(DataflowError::InvalidStackMapFrame(pc));
+        }
+        let declared_locals = declared.locals.iter().map(Some).chain(std::iter::repeat(None));
+        for (slot, info) in frame.locals.iter_mut().zip(declared_locals) {
+            match info {
+                None | Some(VerificationType::Top) => {
+                    if slot.value_type != ValueType::Top {
+                        *slot =
+                            Slot { value_type: ValueType::Top, value: self.interpreter.unknown() };
+                    }
+                }
+                Some(info) => declare(self.interpreter, slot, info),
+            }
+        }
+        for (slot, info) in frame.stack.iter_mut().zip(&declared.stack) {
+            declare(self.interpreter, slot, info);
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, frame: &mut Frame<I::Value>, index: usize) -> Result<()> {
+        let (instruction, pc) = &self.code.code[index];
+        let pc = *pc;
+        let context = Context { instruction, pc, constant_pool: self.constant_pool };
+        match instruction.effect(self.constant_pool).ok_or(DataflowError::InvalidConstant(pc))? {
+            Effect::Load(local, value_type) => {
+                let slot = frame
+                    .locals
+                    .get(local as usize)
+                    .ok_or(DataflowError::InvalidLocal { pc, local })?;
+                let value = self.interpreter.copy(&context, &slot.value);
+                frame.stack.push(Slot { value_type, value });
+            }
+            Effect::Store(local, value_type) => {
+                let slot = pop(frame, pc)?;
+                let value = self.interpreter.copy(&context, &slot.value);
+                self.set_local(frame, local, Slot { value_type, value }, pc)?;
+            }
+            Effect::Increment(local) => {
+                let slot = frame
+                    .locals
+                    .get(local as usize)
+                    .ok_or(DataflowError::InvalidLocal { pc, local })?;
+                let value = self.interpreter.operation(&context, &[&slot.value], ValueType::Int);
+                self.set_local(frame, local, Slot { value_type: ValueType::Int, value }, pc)?;
+            }
+            Effect::Pop(words) => {
+                pop_words(frame, words, pc)?;
+            }
+            Effect::Dup { top, under } => {
+                let top = pop_words(frame, top, pc)?;
+                let under = pop_words(frame, under, pc)?;
+                for slot in &top {
+                    let value = self.interpreter.copy(&context, &slot.value);
+                    frame.stack.push(Slot { value_type: slot.value_type, value });
+                }
+                frame.stack.extend(under);
+                frame.stack.extend(top);
+            }
+            Effect::Swap => {
+                let mut slots = pop_words(frame, 2, pc)?;
+                if slots.len() != 2 {
+                    return Err(DataflowError::CategoryMismatch(pc));
+                }
+                slots.reverse();
+                frame.stack.extend(slots);
+            }
+            Effect::Operation { operands, result } => {
+                let popped = pop_words(frame, operands, pc)?;
+                if let Some(value_type) = result {
+                    let values = popped.iter().map(|slot| &slot.value).collect::<Vec<_>>();
+                    let value = self.interpreter.operation(&context, &values, value_type);
+                    frame.stack.push(Slot { value_type, value });
+                }
+            }
+        }
+        let words = frame.stack.iter().map(|slot| slot.value_type.size()).sum::<usize>();
+        if words > self.code.max_stack as usize {
+            return Err(DataflowError::StackOverflow(pc));
+        }
+        Ok(())
+    }
+
+    fn set_local(
+        &mut self,
+        frame: &mut Frame<I::Value>,
+        local: u16,
+        slot: Slot<I::Value>,
+        pc: u32,
+    ) -> Result<()> {
+        let index = local as usize;
+        if index + slot.value_type.size() > frame.locals.len() {
+            return Err(DataflowError::InvalidLocal { pc, local });
+        }
+        // Overwriting the second half of a long or double invalidates it.
+        if index > 0 && frame.locals[index - 1].value_type.size() == 2 {
+            frame.locals[index - 1] = self.top();
+        }
+        if slot.value_type.size() == 2 {
+            frame.locals[index + 1] = self.top();
+        }
+        frame.locals[index] = slot;
+        Ok(())
+    }
+}
+
+fn declare<I: ValueInterpreter>(
+    interpreter: &mut I,
+    slot: &mut Slot<I::Value>,
+    info: &VerificationType,
+) {
+    slot.value_type = ValueType::of_verification_type(info);
+    if let Some(value) = interpreter.declared(info) {
+        slot.value = interpreter.join(&slot.value, &value);
+    }
+}
+
+fn pop<V>(frame: &mut Frame<V>, pc: u32) -> Result<Slot<V>> {
+    frame.stack.pop().ok_or(DataflowError::StackUnderflow(pc))
+}
+
+/// Pops the entries taking the top `words` slots of the stack, failing if that would split a
+/// long or double value. Returns them from the bottom to the top.
+fn pop_words<V>(frame: &mut Frame<V>, words: usize, pc: u32) -> Result<Vec<Slot<V>>> {
+    let mut popped = Vec::new();
+    let mut taken = 0;
+    while taken < words {
+        let slot = pop(frame, pc)?;
+        taken += slot.value_type.size();
+        popped.push(slot);
+    }
+    if taken != words {
+        return Err(DataflowError::CategoryMismatch(pc));
+    }
+    popped.reverse();
+    Ok(popped)
+}
+
+/// A constant value, or [`Const::Unknown`] for values that are not constant. Floating-point
+/// values are kept as their bits so that NaN constants compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Const {
+    Int(i32),
+    Long(i64),
+    Float(u32),
+    Double(u64),
+    Null,
+    String(String),
+    Unknown,
+}
+
+/// Constant propagation: which slots hold the same constant on every path. Arithmetic on
+/// constants is folded with the JVM's semantics, except operations that would throw.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantPropagation;
+
+impl ValueInterpreter for ConstantPropagation {
+    type Value = Const;
+
+    fn unknown(&mut self) -> Const {
+        Const::Unknown
+    }
+
+    fn operation(&mut self, context: &Context, operands: &[&Const], _result: ValueType) -> Const {
+        fold(context, operands).unwrap_or(Const::Unknown)
+    }
+
+    fn join(&mut self, a: &Const, b: &Const) -> Const {
+        match a == b {
+            true => a.clone(),
+            false => Const::Unknown,
+        }
+    }
+}
+
+pub(crate) fn fold(context: &Context, operands: &[&Const]) -> Option<Const> {
+    use Const::{Double, Float, Int, Long};
+    let float = |bits: &u32| f32::from_bits(*bits);
+    let double = |bits: &u64| f64::from_bits(*bits);
+    let compare = |ordering: Option<std::cmp::Ordering>, nan: i32| {
+        Int(ordering.map_or(nan, |ordering| ordering as i32))
+    };
+    Some(match (context.instruction, operands) {
+        (Instruction::Aconst_null, []) => Const::Null,
+        (Instruction::Iconst_m1, []) => Int(-1),
+        (Instruction::Iconst_0, []) => Int(0),
+        (Instruction::Iconst_1, []) => Int(1),
+        (Instruction::Iconst_2, []) => Int(2),
+        (Instruction::Iconst_3, []) => Int(3),
+        (Instruction::Iconst_4, []) => Int(4),
+        (Instruction::Iconst_5, []) => Int(5),
+        (Instruction::Bipush(value), []) => Int(*value as i8 as i32),
+        (Instruction::Sipush(value), []) => Int(*value as i32),
+        (Instruction::Lconst_0, []) => Long(0),
+        (Instruction::Lconst_1, []) => Long(1),
+        (Instruction::Fconst_0, []) => Float(0f32.to_bits()),
+        (Instruction::Fconst_1, []) => Float(1f32.to_bits()),
+        (Instruction::Fconst_2, []) => Float(2f32.to_bits()),
+        (Instruction::Dconst_0, []) => Double(0f64.to_bits()),
+        (Instruction::Dconst_1, []) => Double(1f64.to_bits()),
+        (Instruction::Ldc(index), []) => constant(context.constant_pool, *index as u16)?,
+        (Instruction::Ldc_w(index) | Instruction::Ldc2_w(index), []) => {
+            constant(context.constant_pool, *index)?
+        }
+        (Instruction::Iinc(_, increment), [Int(value)]) => {
+            Int(value.wrapping_add(*increment as i32))
+        }
+        (Instruction::Wide(WideInstruction::Iinc(_, increment)), [Int(value)]) => {
+            Int(value.wrapping_add(*increment as i32))
+        }
+        (Instruction::Iadd, [Int(a), Int(b)]) => Int(a.wrapping_add(*b)),
+        (Instruction::Isub, [Int(a), Int(b)]) => Int(a.wrapping_sub(*b)),
+        (Instruction::Imul, [Int(a), Int(b)]) => Int(a.wrapping_mul(*b)),
+        (Instruction::Idiv, [Int(a), Int(b)]) if *b != 0 => Int(a.wrapping_div(*b)),
+        (Instruction::Irem, [Int(a), Int(b)]) if *b != 0 => Int(a.wrapping_rem(*b)),
+        (Instruction::Iand, [Int(a), Int(b)]) => Int(a & b),
+        (Instruction::Ior, [Int(a), Int(b)]) => Int(a | b),
+        (Instruction::Ixor, [Int(a), Int(b)]) => Int(a ^ b),
+        (Instruction::Ishl, [Int(a), Int(b)]) => Int(a.wrapping_shl(*b as u32)),
+        (Instruction::Ishr, [Int(a), Int(b)]) => Int(a.wrapping_shr(*b as u32)),
+        (Instruction::Iushr, [Int(a), Int(b)]) => Int((*a as u32).wrapping_shr(*b as u32) as i32),
+        (Instruction::Ineg, [Int(a)]) => Int(a.wrapping_neg()),
+        (Instruction::Ladd, [Long(a), Long(b)]) => Long(a.wrapping_add(*b)),
+        (Instruction::Lsub, [Long(a), Long(b)]) => Long(a.wrapping_sub(*b)),
+        (Instruction::Lmul, [Long(a), Long(b)]) => Long(a.wrapping_mul(*b)),
+        (Instruction::Ldiv, [Long(a), Long(b)]) if *b != 0 => Long(a.wrapping_div(*b)),
+        (Instruction::Lrem, [Long(a), Long(b)]) if *b != 0 => Long(a.wrapping_rem(*b)),
+        (Instruction::Land, [Long(a), Long(b)]) => Long(a & b),
+        (Instruction::Lor, [Long(a), Long(b)]) => Long(a | b),
+        (Instruction::Lxor, [Long(a), Long(b)]) => Long(a ^ b),
+        (Instruction::Lshl, [Long(a), Int(b)]) => Long(a.wrapping_shl(*b as u32)),
+        (Instruction::Lshr, [Long(a), Int(b)]) => Long(a.wrapping_shr(*b as u32)),
+        (Instruction::Lushr, [Long(a), Int(b)]) => Long((*a as u64).wrapping_shr(*b as u32) as i64),
+        (Instruction::Lneg, [Long(a)]) => Long(a.wrapping_neg()),
+        (Instruction::Lcmp, [Long(a), Long(b)]) => Int(a.cmp(b) as i32),
+        (Instruction::Fadd, [Float(a), Float(b)]) => Float((float(a) + float(b)).to_bits()),
+        (Instruction::Fsub, [Float(a), Float(b)]) => Float((float(a) - float(b)).to_bits()),
+        (Instruction::Fmul, [Float(a), Float(b)]) => Float((float(a) * float(b)).to_bits()),
+        (Instruction::Fdiv, [Float(a), Float(b)]) => Float((float(a) / float(b)).to_bits()),
+        (Instruction::Frem, [Float(a), Float(b)]) => Float((float(a) % float(b)).to_bits()),
+        (Instruction::Fneg, [Float(a)]) => Float((-float(a)).to_bits()),
+        (Instruction::Fcmpl, [Float(a), Float(b)]) => compare(float(a).partial_cmp(&float(b)), -1),
+        (Instruction::Fcmpg, [Float(a), Float(b)]) => compare(float(a).partial_cmp(&float(b)), 1),
+        (Instruction::Dadd, [Double(a), Double(b)]) => Double((double(a) + double(b)).to_bits()),
+        (Instruction::Dsub, [Double(a), Double(b)]) => Double((double(a) - double(b)).to_bits()),
+        (Instruction::Dmul, [Double(a), Double(b)]) => Double((double(a) * double(b)).to_bits()),
+        (Instruction::Ddiv, [Double(a), Double(b)]) => Double((double(a) / double(b)).to_bits()),
+        (Instruction::Drem, [Double(a), Double(b)]) => Double((double(a) % double(b)).to_bits()),
+        (Instruction::Dneg, [Double(a)]) => Double((-double(a)).to_bits()),
+        (Instruction::Dcmpl, [Double(a), Double(b)]) => {
+            compare(double(a).partial_cmp(&double(b)), -1)
+        }
+        (Instruction::Dcmpg, [Double(a), Double(b)]) => {
+            compare(double(a).partial_cmp(&double(b)), 1)
+        }
+        (Instruction::I2l, [Int(a)]) => Long(*a as i64),
+        (Instruction::I2f, [Int(a)]) => Float((*a as f32).to_bits()),
+        (Instruction::I2d, [Int(a)]) => Double((*a as f64).to_bits()),
+        (Instruction::I2b, [Int(a)]) => Int(*a as i8 as i32),
+        (Instruction::I2c, [Int(a)]) => Int(*a as u16 as i32),
+        (Instruction::I2s, [Int(a)]) => Int(*a as i16 as i32),
+        (Instruction::L2i, [Long(a)]) => Int(*a as i32),
+        (Instruction::L2f, [Long(a)]) => Float((*a as f32).to_bits()),
+        (Instruction::L2d, [Long(a)]) => Double((*a as f64).to_bits()),
+        // Rust's saturating float to integer casts match the JVM's, NaN included.
+        (Instruction::F2i, [Float(a)]) => Int(float(a) as i32),
+        (Instruction::F2l, [Float(a)]) => Long(float(a) as i64),
+        (Instruction::F2d, [Float(a)]) => Double((float(a) as f64).to_bits()),
+        (Instruction::D2i, [Double(a)]) => Int(double(a) as i32),
+        (Instruction::D2l, [Double(a)]) => Long(double(a) as i64),
+        (Instruction::D2f, [Double(a)]) => Float((double(a) as f32).to_bits()),
+        (Instruction::Checkcast(_), [Const::Null]) => Const::Null,
+        (Instruction::Instanceof(_), [Const::Null]) => Int(0),
+        _ => return None,
+    })
+}
+
+fn constant(constant_pool: &ConstantPool, index: u16) -> Option<Const> {
+    Some(match constant_pool.get(index as usize).ok()? {
+        Constant::Integer(value) => Const::Int(*value),
+        Constant::Long(value) => Const::Long(*value),
+        Constant::Float(value) => Const::Float(value.to_bits()),
+        Constant::Double(value) => Const::Double(value.to_bits()),
+        Constant::StringIndex(index) => Const::String(constant_pool.utf8(*index as usize)?.into()),
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Nullability {
+    Null,
+    NonNull,
+    /// Possibly null, or not a reference.
+    Unknown,
+}
+
+/// Nullness of references: `null` constants, new objects, constants, `this` and caught
+/// exceptions are known; everything read from fields, arrays, parameters or method results
+/// is not.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Nullness;
+
+impl ValueInterpreter for Nullness {
+    type Value = Nullability;
+
+    fn unknown(&mut self) -> Nullability {
+        Nullability::Unknown
+    }
+
+    fn parameter(&mut self, _local: u16, _value_type: ValueType, is_this: bool) -> Nullability {
+        match is_this {
+            true => Nullability::NonNull,
+            false => Nullability::Unknown,
+        }
+    }
+
+    fn exception(&mut self, _catch_type: Option<&str>) -> Nullability {
+        Nullability::NonNull
+    }
+
+    fn declared(&mut self, info: &VerificationType) -> Option<Nullability> {
+        match info {
+            VerificationType::Null => Some(Nullability::Null),
+            VerificationType::UninitializedThis | VerificationType::Uninitialized { .. } => {
+                Some(Nullability::NonNull)
+            }
+            _ => None,
+        }
+    }
+
+    fn operation(
+        &mut self,
+        context: &Context,
+        operands: &[&Nullability],
+        result: ValueType,
+    ) -> Nullability {
+        if result != ValueType::Reference {
+            return Nullability::Unknown;
+        }
+        match context.instruction {
+            Instruction::Aconst_null => Nullability::Null,
+            Instruction::New(_)
+            | Instruction::Newarray(_)
+            | Instruction::Anewarray(_)
+            | Instruction::Multianewarray(..)
+            | Instruction::Ldc(_)
+            | Instruction::Ldc_w(_) => Nullability::NonNull,
+            Instruction::Checkcast(_) => *operands[0],
+            _ => Nullability::Unknown,
+        }
+    }
+
+    fn join(&mut self, a: &Nullability, b: &Nullability) -> Nullability {
+        match a == b {
+            true => *a,
+            false => Nullability::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flag::ClassFileAccessFlags;
+    use crate::attribute::Attribute;
+    use crate::class_file_version::ClassFileVersion;
+    use crate::method::{MethodAccessFlags, MethodDescriptor};
+
+    fn static_method(descriptor: &str, code: Vec<(Instruction, u32)>) -> (ClassFile, Method) {
+        let code = Code {
+            max_stack: 4,
+            max_locals: 2,
+            code,
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let method = Method {
+            flags: MethodAccessFlags::new(0x0008),
+            name: "m".to_string(),
+            type_descriptor: MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+                .unwrap(),
+            attributes: vec![Attribute::Code(code)],
+        };
+        let class = ClassFile {
+            version: ClassFileVersion::default(),
+            constant_pool: ConstantPool::default(),
+            flags: ClassFileAccessFlags::new(0x0021),
+            this_class: "T".to_string(),
+            super_class: Some("java/lang/Object".to_string()),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        (class, method)
+    }
+
+    #[test]
+    fn test_category_two_values() {
+        let (class, method) = static_method(
+            "()I",
+            vec![
+                (Instruction::Lconst_1, 0),
+                (Instruction::Dup_2, 1),
+                (Instruction::Ladd, 2),
+                (Instruction::Dup_2, 3),
+                (Instruction::Lstore_0, 4),
+                (Instruction::L2i, 5),
+                (Instruction::Iconst_3, 6),
+                (Instruction::Swap, 7),
+                (Instruction::Pop, 8),
+                (Instruction::Ireturn, 9),
+            ],
+        );
+        let frames = analyze_frames(&class, &method, &mut ConstantPropagation).unwrap();
+
+        let frame = frames.before(2).unwrap();
+        assert_eq!(frame.stack.len(), 2);
+        assert_eq!(frame.peek(1), Some(&Const::Long(1)));
+        let frame = frames.before(5).unwrap();
+        assert_eq!(frame.locals[0], Slot { value_type: ValueType::Long, value: Const::Long(2) });
+        assert_eq!(frame.locals[1].value_type, ValueType::Top);
+        assert_eq!(frames.before(8).unwrap().peek(0), Some(&Const::Int(2)));
+        assert_eq!(frames.before(9).unwrap().peek(0), Some(&Const::Int(3)));
+    }
+
+    #[test]
+    fn test_invalid_stack_operations() {
+        let (class, method) = static_method(
+            "()V",
+            vec![(Instruction::Lconst_0, 0), (Instruction::Pop, 1), (Instruction::Return, 2)],
+        );
+        assert_eq!(
+            analyze_frames(&class, &method, &mut Nullness),
+            Err(DataflowError::CategoryMismatch(1))
+        );
+
+        let (class, method) =
+            static_method("()V", vec![(Instruction::Iadd, 0), (Instruction::Return, 1)]);
+        assert_eq!(
+            analyze_frames(&class, &method, &mut Nullness),
+            Err(DataflowError::StackUnderflow(0))
+        );
+    }
+}
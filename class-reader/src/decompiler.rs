@@ -0,0 +1,4324 @@
+//! Decompilation of methods into Java-like source.
+//!
+//! Each basic block is translated by simulating the operand stack: instructions build
+//! expression trees, and statements are emitted when a value is stored, a method is called
+//! for its side effects, or the block ends. Values left on the stack at a block boundary are
+//! assigned to synthetic `stackN` variables, which are inlined back into their single use
+//! where that keeps Java's evaluation order. Blocks that only test a condition are merged
+//! into `&&` and `||` conditions.
+//!
+//! Control flow is recovered from the dominator tree, the post-dominator tree and the natural
+//! loops of the block graph: loops become `while`, `do`-`while` or labeled `while (true)`
+//! statements, two-way branches become `if`/`else` around their immediate post-dominator,
+//! `tableswitch` and `lookupswitch` become `switch`, and exception table entries become
+//! `try`/`catch`, with `finally` recognized from javac's catch-all handlers that rethrow.
+//! Whatever cannot be structured falls back to labels and `goto`, so the output is meant for
+//! reading and is not always valid Java.
+//!
+//! Variable names come from the `LocalVariableTable` and generic types from `Signature` and
+//! `LocalVariableTypeTable` attributes. Lambdas, method references and string concatenation
+//! are recovered from the `LambdaMetafactory` and `StringConcatFactory` bootstrap methods of
+//! `invokedynamic`. Subroutines (`jsr` and `ret`) are not supported.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+
+use thiserror::Error;
+
+use crate::access_flag::{AccessFlags, ClassFlag};
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::control_flow::{BasicBlock, ControlFlowError, ControlFlowGraph};
+use crate::dominators::{DominatorTree, reverse_postorder};
+use crate::field::{self, BaseType, Field, FieldType};
+use crate::instruction::{Instruction, WideInstruction};
+use crate::loops::LoopNest;
+use crate::method::{Method, MethodDescriptor, MethodFlag, ReturnDescriptor};
+use crate::predefined_attributes::{BootstrapMethod, Code};
+
+type Result<T> = std::result::Result<T, DecompilerError>;
+
+const METHOD_MODIFIERS: [(MethodFlag, &str); 9] = [
+    (MethodFlag::Public, "public"),
+    (MethodFlag::Private, "private"),
+    (MethodFlag::Protected, "protected"),
+    (MethodFlag::Abstract, "abstract"),
+    (MethodFlag::Static, "static"),
+    (MethodFlag::Final, "final"),
+    (MethodFlag::Synchronized, "synchronized"),
+    (MethodFlag::Native, "native"),
+    (MethodFlag::Strict, "strictfp"),
+];
+
+const FIELD_MODIFIERS: [(field::AccessFlag, &str); 7] = [
+    (field::AccessFlag::Public, "public"),
+    (field::AccessFlag::Private, "private"),
+    (field::AccessFlag::Protected, "protected"),
+    (field::AccessFlag::Static, "static"),
+    (field::AccessFlag::Final, "final"),
+    (field::AccessFlag::Transient, "transient"),
+    (field::AccessFlag::Volatile, "volatile"),
+];
+
+/// How many lambdas deep bodies are decompiled inline before falling back to method references.
+const MAX_LAMBDA_DEPTH: usize = 8;
+
+const LAMBDA: u8 = 0;
+const ASSIGNMENT: u8 = 1;
+const TERNARY: u8 = 2;
+const OR: u8 = 3;
+const AND: u8 = 4;
+const BIT_OR: u8 = 5;
+const BIT_XOR: u8 = 6;
+const BIT_AND: u8 = 7;
+const EQUALITY: u8 = 8;
+const RELATIONAL: u8 = 9;
+const SHIFT: u8 = 10;
+const ADDITIVE: u8 = 11;
+const MULTIPLICATIVE: u8 = 12;
+const UNARY: u8 = 13;
+const POSTFIX: u8 = 14;
+const PRIMARY: u8 = 15;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum DecompilerError {
+    #[error("Method {0} has no code")]
+    #[non_exhaustive]
+    MissingCode(String),
+    #[error("{0}")]
+    #[non_exhaustive]
+    ControlFlow(#[from] ControlFlowError),
+    #[error("Subroutine instruction at pc {0} is not supported")]
+    #[non_exhaustive]
+    Subroutine(u32),
+    #[error("Instruction at pc {0} pops from an empty operand stack")]
+    #[non_exhaustive]
+    StackUnderflow(u32),
+    #[error("Instruction at pc {0} splits a long or double value")]
+    #[non_exhaustive]
+    CategoryMismatch(u32),
+    #[error("Instruction at pc {0} refers to an invalid constant")]
+    #[non_exhaustive]
+    InvalidConstant(u32),
+    #[error("Operand stacks of different heights reach pc {0}")]
+    #[non_exhaustive]
+    StackHeightMismatch(u32),
+}
+
+/// Decompiles `class` into a Java-like class declaration. Synthetic members are left out, and
+/// methods that cannot be decompiled keep their declaration with the error in their body.
+pub fn decompile(class: &ClassFile) -> String {
+    let context = ClassContext::new(class);
+    let mut source = String::new();
+    if !context.names.package.is_empty() {
+        writeln!(source, "package {};\n", context.names.package.replace('/', ".")).unwrap();
+    }
+    writeln!(source, "{} {{", context.class_header()).unwrap();
+    let mut members = Vec::new();
+    let fields = class
+        .fields
+        .iter()
+        .filter(|field| !field.flags.contains(field::AccessFlag::Synthetic))
+        .map(|field| format!("    {};\n", context.field(field)))
+        .collect::<String>();
+    if !fields.is_empty() {
+        members.push(fields);
+    }
+    for method in
+        class.methods.iter().filter(|method| !method.flags.contains(MethodFlag::Synthetic))
+    {
+        members.push(context.method(method, 1));
+    }
+    source.push_str(&members.join("\n"));
+    source.push_str("}\n");
+    source
+}
+
+/// Decompiles `method` of `class` into a Java-like method declaration.
+pub fn decompile_method(class: &ClassFile, method: &Method) -> Result<String> {
+    let context = ClassContext::new(class);
+    let header = context.method_header(method);
+    if method.code().is_none() {
+        return Ok(format!("{};\n", header));
+    }
+    let body = context.method_body(method, 1)?;
+    Ok(format!("{} {{\n{}}}\n", header, body))
+}
+
+/// A local variable as it appears in the output. Variables are identified by their name.
+#[derive(Debug, Clone)]
+struct Variable {
+    name: String,
+    ty: String,
+    /// Whether the variable was introduced by the decompiler rather than the source.
+    synthetic: bool,
+}
+
+impl PartialEq for Variable {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+/// What a field access, method call or method reference applies to.
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Static(String),
+    Instance(Box<Expression>),
+    Super,
+    /// No qualifier, as in `this(...)` and `super(...)` constructor calls.
+    Implicit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Literal(String),
+    Variable(Variable),
+    /// The exception on the stack at the start of a handler.
+    Caught,
+    /// The result of `new` before its constructor has been called.
+    Uninitialized {
+        class: String,
+        pc: u32,
+    },
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    /// `lcmp`, `fcmp<op>` and `dcmp<op>`, named by the boxed type whose `compare` matches them.
+    Compare(&'static str, Box<Expression>, Box<Expression>),
+    Cast(String, Box<Expression>),
+    InstanceOf(Box<Expression>, String),
+    Field(Target, String),
+    ArrayElement(Box<Expression>, Box<Expression>),
+    ArrayLength(Box<Expression>),
+    Invoke {
+        target: Target,
+        owner: String,
+        name: String,
+        descriptor: String,
+        arguments: Vec<Expression>,
+    },
+    New {
+        class: String,
+        arguments: Vec<Expression>,
+    },
+    NewArray {
+        element: String,
+        dimensions: Vec<Expression>,
+        /// Trailing dimensions that are not allocated, as in `new int[3][]`.
+        extra: usize,
+        pc: u32,
+        /// The elements stored right after the allocation, printed as an initializer.
+        elements: Option<Vec<Expression>>,
+    },
+    Ternary(Box<Expression>, Box<Expression>, Box<Expression>),
+    PostIncrement(Variable, i32),
+    /// An assignment whose value is used, as in `(line = reader.readLine()) != null`.
+    Assignment(Variable, Box<Expression>),
+    Concat(Vec<Expression>),
+    Lambda {
+        parameters: Vec<String>,
+        body: Vec<Statement>,
+    },
+    MethodReference(Target, String),
+    /// An `int` used where a `boolean` is expected.
+    Boolean(Box<Expression>),
+    /// An `invokedynamic` call site or dynamic constant without a known bootstrap pattern.
+    Dynamic {
+        name: String,
+        arguments: Vec<Expression>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Catch {
+    types: Vec<String>,
+    name: String,
+    body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    /// The start of the block at a pc, kept in the output only where a `goto` targets it.
+    Label(u32),
+    Expression(Expression),
+    Assign(Expression, Expression),
+    Increment(Variable, i32),
+    Return(Option<Expression>),
+    Throw(Expression),
+    If {
+        condition: Expression,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    While {
+        label: Option<String>,
+        condition: Expression,
+        body: Vec<Statement>,
+    },
+    DoWhile {
+        label: Option<String>,
+        body: Vec<Statement>,
+        condition: Expression,
+    },
+    Switch {
+        label: Option<String>,
+        value: Expression,
+        /// The keys of each case group, `None` standing for `default`.
+        cases: Vec<(Vec<Option<i32>>, Vec<Statement>)>,
+    },
+    Try {
+        body: Vec<Statement>,
+        catches: Vec<Catch>,
+        finally: Option<Vec<Statement>>,
+    },
+    Break(Option<String>),
+    Continue(Option<String>),
+    Goto(u32),
+    /// `monitorenter` and `monitorexit` left over from `synchronized` blocks.
+    Monitor(&'static str, Expression),
+    Synchronized {
+        lock: Expression,
+        body: Vec<Statement>,
+    },
+}
+
+/// Renders internal class names and signatures as they are written in source, leaving out
+/// `java.lang` and the package of the class being decompiled.
+struct TypeNames {
+    package: String,
+}
+
+/// The parts of a method `Signature` attribute, rendered.
+struct MethodSignature {
+    type_parameters: String,
+    parameters: Vec<String>,
+    exceptions: Vec<String>,
+}
+
+impl TypeNames {
+    fn new(class: &str) -> Self {
+        let package = class.rfind('/').map_or("", |end| &class[..end]).to_string();
+        TypeNames { package }
+    }
+
+    fn class(&self, internal: &str) -> String {
+        if internal.starts_with('[') {
+            return FieldType::try_from(&mut internal.chars().peekable()).map_or_else(
+                |_| internal.replace('/', "."),
+                |field_type| self.field_type(&field_type),
+            );
+        }
+        let (package, simple) = internal.rsplit_once('/').unwrap_or(("", internal));
+        if package == "java/lang" || package == self.package {
+            simple.replace('$', ".")
+        } else {
+            internal.replace(['/', '$'], ".")
+        }
+    }
+
+    fn field_type(&self, field_type: &FieldType) -> String {
+        match field_type {
+            FieldType::Base(base) => base_name(*base).to_string(),
+            FieldType::Object(class) => self.class(class),
+            FieldType::Array(element) => format!("{}[]", self.field_type(element)),
+        }
+    }
+
+    fn return_type(&self, return_type: &ReturnDescriptor) -> String {
+        match return_type {
+            ReturnDescriptor::FieldType(field_type) => self.field_type(field_type),
+            ReturnDescriptor::VoidDescriptor => "void".to_string(),
+        }
+    }
+
+    /// Renders a `JavaTypeSignature` or `V` from the front of `chars`.
+    fn type_signature(&self, chars: &mut Peekable<Chars>) -> Option<String> {
+        Some(match chars.next()? {
+            'B' => "byte".to_string(),
+            'C' => "char".to_string(),
+            'D' => "double".to_string(),
+            'F' => "float".to_string(),
+            'I' => "int".to_string(),
+            'J' => "long".to_string(),
+            'S' => "short".to_string(),
+            'Z' => "boolean".to_string(),
+            'V' => "void".to_string(),
+            '[' => format!("{}[]", self.type_signature(chars)?),
+            'T' => {
+                let name = chars.by_ref().take_while(|&char| char != ';').collect::<String>();
+                if name.is_empty() {
+                    return None;
+                }
+                name
+            }
+            'L' => {
+                let mut rendered = String::new();
+                let mut internal = String::new();
+                loop {
+                    match chars.next()? {
+                        ';' => break,
+                        '<' => {
+                            rendered.push_str(&self.class(&internal));
+                            internal.clear();
+                            rendered.push_str(&self.type_arguments(chars)?);
+                        }
+                        '.' => {
+                            rendered.push_str(&self.class(&internal));
+                            internal.clear();
+                            rendered.push('.');
+                        }
+                        char => internal.push(char),
+                    }
+                }
+                if !internal.is_empty() {
+                    if rendered.ends_with('.') {
+                        rendered.push_str(&internal);
+                    } else {
+                        rendered.push_str(&self.class(&internal));
+                    }
+                }
+                rendered
+            }
+            _ => return None,
+        })
+    }
+
+    /// Renders `TypeArguments` up to and including the closing `>`, the `<` already consumed.
+    fn type_arguments(&self, chars: &mut Peekable<Chars>) -> Option<String> {
+        let mut arguments = Vec::new();
+        loop {
+            match chars.peek()? {
+                '>' => {
+                    chars.next();
+                    return Some(format!("<{}>", arguments.join(", ")));
+                }
+                '*' => {
+                    chars.next();
+                    arguments.push("?".to_string());
+                }
+                '+' => {
+                    chars.next();
+                    arguments.push(format!("? extends {}", self.type_signature(chars)?));
+                }
+                '-' => {
+                    chars.next();
+                    arguments.push(format!("? super {}", self.type_signature(chars)?));
+                }
+                _ => arguments.push(self.type_signature(chars)?),
+            }
+        }
+    }
+
+    /// Renders optional `TypeParameters` from the front of `chars`, dropping `Object` bounds.
+    fn type_parameters(&self, chars: &mut Peekable<Chars>) -> Option<String> {
+        if chars.peek() != Some(&'<') {
+            return Some(String::new());
+        }
+        chars.next();
+        let mut parameters = Vec::new();
+        while chars.peek()? != &'>' {
+            let name = chars.by_ref().take_while(|&char| char != ':').collect::<String>();
+            let mut bounds = Vec::new();
+            loop {
+                if chars.peek()? == &':' {
+                    chars.next();
+                }
+                let bound = self.type_signature(chars)?;
+                if bound != "Object" {
+                    bounds.push(bound);
+                }
+                if chars.peek()? != &':' {
+                    break;
+                }
+            }
+            if bounds.is_empty() {
+                parameters.push(name);
+            } else {
+                parameters.push(format!("{} extends {}", name, bounds.join(" & ")));
+            }
+        }
+        chars.next();
+        Some(format!("<{}>", parameters.join(", ")))
+    }
+
+    fn method_signature(&self, signature: &str) -> Option<MethodSignature> {
+        let mut chars = signature.chars().peekable();
+        let type_parameters = self.type_parameters(&mut chars)?;
+        if chars.next()? != '(' {
+            return None;
+        }
+        let mut parameters = Vec::new();
+        while chars.peek()? != &')' {
+            parameters.push(self.type_signature(&mut chars)?);
+        }
+        chars.next();
+        self.type_signature(&mut chars)?;
+        let mut exceptions = Vec::new();
+        while chars.next() == Some('^') {
+            exceptions.push(self.type_signature(&mut chars)?);
+        }
+        Some(MethodSignature { type_parameters, parameters, exceptions })
+    }
+
+    /// Renders a class `Signature` into its type parameters, superclass and interfaces.
+    fn class_signature(&self, signature: &str) -> Option<(String, Option<String>, Vec<String>)> {
+        let mut chars = signature.chars().peekable();
+        let type_parameters = self.type_parameters(&mut chars)?;
+        let superclass = self.type_signature(&mut chars)?;
+        let mut interfaces = Vec::new();
+        while chars.peek().is_some() {
+            interfaces.push(self.type_signature(&mut chars)?);
+        }
+        Some((type_parameters, Some(superclass), interfaces))
+    }
+}
+
+/// The `Signature` attribute among `attributes`.
+fn signature<'a>(attributes: &[Attribute], pool: &'a ConstantPool) -> Option<&'a str> {
+    attributes.iter().find_map(|attribute| match attribute {
+        Attribute::UserDefined(attribute) if attribute.name() == "Signature" => {
+            let info = attribute.info();
+            (info.len() == 2).then(|| pool.utf8(u16::from_be_bytes([info[0], info[1]]) as usize))?
+        }
+        _ => None,
+    })
+}
+
+/// The classes named by the `Exceptions` attribute among `attributes`.
+fn exceptions<'a>(attributes: &[Attribute], pool: &'a ConstantPool) -> Vec<&'a str> {
+    let Some(info) = attributes.iter().find_map(|attribute| match attribute {
+        Attribute::UserDefined(attribute) if attribute.name() == "Exceptions" => {
+            Some(attribute.info())
+        }
+        _ => None,
+    }) else {
+        return Vec::new();
+    };
+    info.get(2..)
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .filter_map(|index| pool.class_name(u16::from_be_bytes([index[0], index[1]]) as usize))
+        .collect()
+}
+
+/// A `LocalVariableTable` entry with its rendered type.
+struct LocalEntry {
+    start: u32,
+    end: u32,
+    slot: u16,
+    name: String,
+    ty: String,
+    field_type: FieldType,
+}
+
+fn local_entries(code: &Code, pool: &ConstantPool, names: &TypeNames) -> Vec<LocalEntry> {
+    let mut generic = HashMap::new();
+    for attribute in &code.attributes {
+        if let Attribute::LocalVariableTypeTable(table) = attribute {
+            for entry in &table.local_variable_type_table {
+                let signature = pool.utf8(entry.signature_index as usize);
+                if let Some(ty) =
+                    signature.and_then(|s| names.type_signature(&mut s.chars().peekable()))
+                {
+                    generic.insert((entry.start_pc, entry.index), ty);
+                }
+            }
+        }
+    }
+    let mut entries = Vec::new();
+    for attribute in &code.attributes {
+        if let Attribute::LocalVariableTable(table) = attribute {
+            for entry in &table.local_variable_table {
+                let name = pool.utf8(entry.name_index as usize);
+                let descriptor = pool.utf8(entry.descriptor_index as usize);
+                let (Some(name), Some(descriptor)) = (name, descriptor) else { continue };
+                let Ok(field_type) = FieldType::try_from(&mut descriptor.chars().peekable()) else {
+                    continue;
+                };
+                let ty = generic
+                    .remove(&(entry.start_pc, entry.index))
+                    .unwrap_or_else(|| names.field_type(&field_type));
+                entries.push(LocalEntry {
+                    start: entry.start_pc as u32,
+                    end: entry.start_pc as u32 + entry.length as u32,
+                    slot: entry.index,
+                    name: name.to_string(),
+                    ty,
+                    field_type,
+                });
+            }
+        }
+    }
+    entries
+}
+
+struct Parameter {
+    slot: u16,
+    variable: Variable,
+    field_type: FieldType,
+}
+
+struct ClassContext<'a> {
+    class: &'a ClassFile,
+    names: TypeNames,
+    bootstrap_methods: &'a [BootstrapMethod],
+}
+
+impl<'a> ClassContext<'a> {
+    fn new(class: &'a ClassFile) -> Self {
+        let bootstrap_methods = class
+            .attributes
+            .iter()
+            .find_map(|attribute| match attribute {
+                Attribute::BootstrapMethods(methods) => Some(methods.bootstrap_methods.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        ClassContext { class, names: TypeNames::new(&class.this_class), bootstrap_methods }
+    }
+
+    fn pool(&self) -> &'a ConstantPool {
+        &self.class.constant_pool
+    }
+
+    fn simple_name(&self) -> String {
+        let name = &self.class.this_class;
+        name.rsplit(['/', '$']).next().unwrap_or(name).to_string()
+    }
+
+    fn class_header(&self) -> String {
+        let flags = &self.class.flags;
+        let mut words = Vec::new();
+        if flags.contains(ClassFlag::Public) {
+            words.push("public");
+        }
+        let interface = flags.contains(ClassFlag::Interface);
+        let kind = if flags.contains(ClassFlag::Annotation) {
+            "@interface"
+        } else if interface {
+            "interface"
+        } else if flags.contains(ClassFlag::Enum) {
+            "enum"
+        } else {
+            if flags.contains(ClassFlag::Abstract) {
+                words.push("abstract");
+            }
+            if flags.contains(ClassFlag::Final) {
+                words.push("final");
+            }
+            "class"
+        };
+        words.push(kind);
+        let signature = signature(&self.class.attributes, self.pool())
+            .and_then(|signature| self.names.class_signature(signature));
+        let (type_parameters, superclass, interfaces) = signature.unwrap_or_else(|| {
+            (
+                String::new(),
+                self.class.super_class.as_deref().map(|name| self.names.class(name)),
+                self.class.interfaces.iter().map(|name| self.names.class(name)).collect(),
+            )
+        });
+        let mut header = format!("{} {}{}", words.join(" "), self.simple_name(), type_parameters);
+        let superclass = superclass
+            .filter(|name| !interface && !flags.contains(ClassFlag::Enum) && name != "Object");
+        if let Some(superclass) = superclass {
+            write!(header, " extends {}", superclass).unwrap();
+        }
+        let interfaces = interfaces
+            .into_iter()
+            .filter(|name| name != "java.lang.annotation.Annotation")
+            .collect::<Vec<_>>();
+        if !interfaces.is_empty() {
+            let keyword = if interface { "extends" } else { "implements" };
+            write!(header, " {} {}", keyword, interfaces.join(", ")).unwrap();
+        }
+        header
+    }
+
+    fn field(&self, field: &Field) -> String {
+        let mut words = modifiers(&field.flags, &FIELD_MODIFIERS);
+        let ty = signature(&field.attributes, self.pool())
+            .and_then(|signature| self.names.type_signature(&mut signature.chars().peekable()))
+            .unwrap_or_else(|| self.names.field_type(&field.type_descriptor));
+        let value = field.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::ConstantValue(constant) => {
+                constant_literal(&constant.value, self.pool(), &self.names)
+            }
+            _ => None,
+        });
+        let initializer = value.map(|(value, _)| {
+            format!(
+                " = {}",
+                Printer::new(0, HashSet::new()).expression(&coerce(value, &ty), ASSIGNMENT)
+            )
+        });
+        words.push(ty);
+        words.push(field.name.clone());
+        format!("{}{}", words.join(" "), initializer.unwrap_or_default())
+    }
+
+    fn method(&self, method: &Method, indent: usize) -> String {
+        let padding = "    ".repeat(indent);
+        let header = self.method_header(method);
+        if method.code().is_none() {
+            return format!("{}{};\n", padding, header);
+        }
+        match self.method_body(method, indent + 1) {
+            Ok(body) => format!("{}{} {{\n{}{}}}\n", padding, header, body, padding),
+            Err(error) => {
+                format!(
+                    "{0}{1} {{\n{0}    // Could not decompile: {2}\n{0}}}\n",
+                    padding, header, error
+                )
+            }
+        }
+    }
+
+    fn method_header(&self, method: &Method) -> String {
+        if method.name == "<clinit>" {
+            return "static".to_string();
+        }
+        let flags = &method.flags;
+        let mut words = modifiers(flags, &METHOD_MODIFIERS);
+        let interface = self.class.flags.contains(ClassFlag::Interface);
+        if interface
+            && method.code().is_some()
+            && !flags.is_static()
+            && !flags.contains(MethodFlag::Private)
+        {
+            words.push("default".to_string());
+        }
+        let signature = signature(&method.attributes, self.pool())
+            .and_then(|signature| self.names.method_signature(signature));
+        if let Some(signature) =
+            signature.as_ref().filter(|signature| !signature.type_parameters.is_empty())
+        {
+            words.push(signature.type_parameters.clone());
+        }
+        if method.name == "<init>" {
+            words.push(self.simple_name());
+        } else {
+            let return_type = signature
+                .as_ref()
+                .and_then(|_| signature_return_type(method, self))
+                .unwrap_or_else(|| self.names.return_type(method.type_descriptor.return_type()));
+            words.push(format!("{} {}", return_type, method.name));
+        }
+        let locals = method
+            .code()
+            .map(|code| local_entries(code, self.pool(), &self.names))
+            .unwrap_or_default();
+        let parameters = self
+            .parameters(method, &locals)
+            .iter()
+            .map(|parameter| format!("{} {}", parameter.variable.ty, parameter.variable.name))
+            .collect::<Vec<_>>();
+        let mut thrown = signature.map(|signature| signature.exceptions).unwrap_or_default();
+        if thrown.is_empty() {
+            thrown = exceptions(&method.attributes, self.pool())
+                .into_iter()
+                .map(|name| self.names.class(name))
+                .collect();
+        }
+        let mut header = format!("{}({})", words.join(" "), parameters.join(", "));
+        if !thrown.is_empty() {
+            write!(header, " throws {}", thrown.join(", ")).unwrap();
+        }
+        header
+    }
+
+    fn parameters(&self, method: &Method, locals: &[LocalEntry]) -> Vec<Parameter> {
+        let descriptor_types = method.type_descriptor.parameters();
+        let signature_types = signature(&method.attributes, self.pool())
+            .and_then(|signature| self.names.method_signature(signature))
+            .map(|signature| signature.parameters)
+            .filter(|types| types.len() == descriptor_types.len());
+        let varargs = method.flags.contains(MethodFlag::Varargs);
+        let mut slot = if method.flags.is_static() { 0 } else { 1 };
+        let mut parameters = Vec::new();
+        for (index, field_type) in descriptor_types.iter().enumerate() {
+            let name = locals
+                .iter()
+                .find(|local| local.slot == slot && local.start == 0)
+                .map_or_else(|| format!("arg{}", index), |local| local.name.clone());
+            let mut ty = signature_types
+                .as_ref()
+                .map_or_else(|| self.names.field_type(field_type), |types| types[index].clone());
+            if varargs && index + 1 == descriptor_types.len() && ty.ends_with("[]") {
+                ty.truncate(ty.len() - 2);
+                ty.push_str("...");
+            }
+            let variable = Variable { name, ty, synthetic: false };
+            parameters.push(Parameter { slot, variable, field_type: field_type.clone() });
+            slot += size(field_type) as u16;
+        }
+        parameters
+    }
+
+    fn method_body(&self, method: &Method, indent: usize) -> Result<String> {
+        let (mut statements, parameters) = self.statements(method, &HashMap::new(), 0)?;
+        if method.name == "<init>" {
+            if let Some(Statement::Expression(Expression::Invoke {
+                target: Target::Implicit,
+                name,
+                arguments,
+                ..
+            })) = statements.first()
+            {
+                if name == "super" && arguments.is_empty() {
+                    statements.remove(0);
+                }
+            }
+        }
+        let mut declared =
+            parameters.into_iter().map(|parameter| parameter.variable.name).collect::<HashSet<_>>();
+        declared.insert("this".to_string());
+        let mut printer = Printer::new(indent, declared);
+        printer.statements(&statements);
+        Ok(printer.output)
+    }
+
+    /// Decompiles the body of `method`, naming the local variables in `renames` after the
+    /// variables a lambda captures.
+    fn statements(
+        &self,
+        method: &Method,
+        renames: &HashMap<u16, Variable>,
+        depth: usize,
+    ) -> Result<(Vec<Statement>, Vec<Parameter>)> {
+        let code =
+            method.code().ok_or_else(|| DecompilerError::MissingCode(method.name.clone()))?;
+        if let Some((_, pc)) = code.code.iter().find(|(instruction, _)| is_subroutine(instruction))
+        {
+            return Err(DecompilerError::Subroutine(*pc));
+        }
+        let graph = ControlFlowGraph::new(code)?;
+        let context = MethodContext::new(self, method, code, renames, depth);
+        let mut nodes = Translator::new(&context).translate(&graph)?;
+        let handlers = context.handlers(&graph);
+        merge_conditions(&mut nodes, &handlers);
+        let mut statements = Structurer::new(nodes, handlers).structure();
+        let void = matches!(method.type_descriptor.return_type(), ReturnDescriptor::VoidDescriptor);
+        post_process(&mut statements, void);
+        Ok((statements, context.parameters))
+    }
+}
+
+fn signature_return_type(method: &Method, context: &ClassContext) -> Option<String> {
+    let signature = signature(&method.attributes, context.pool())?;
+    let mut chars = signature.chars().peekable();
+    context.names.type_parameters(&mut chars)?;
+    chars.by_ref().find(|&char| char == '(')?;
+    while chars.peek()? != &')' {
+        context.names.type_signature(&mut chars)?;
+    }
+    chars.next();
+    context.names.type_signature(&mut chars)
+}
+
+fn modifiers<F: AccessFlags>(flags: &F, table: &[(F::Flag, &str)]) -> Vec<String> {
+    table
+        .iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, word)| word.to_string())
+        .collect()
+}
+
+/// An exception table entry with its handler block.
+struct Handler {
+    start: u32,
+    end: u32,
+    node: usize,
+    catch_type: Option<String>,
+}
+
+impl Handler {
+    fn covers(&self, pc: u32) -> bool {
+        self.start <= pc && pc < self.end
+    }
+}
+
+struct MethodContext<'a> {
+    class: &'a ClassContext<'a>,
+    method: &'a Method,
+    code: &'a Code,
+    locals: Vec<LocalEntry>,
+    this: Option<Variable>,
+    parameters: Vec<Parameter>,
+    renames: &'a HashMap<u16, Variable>,
+    depth: usize,
+}
+
+impl<'a> MethodContext<'a> {
+    fn new(
+        class: &'a ClassContext<'a>,
+        method: &'a Method,
+        code: &'a Code,
+        renames: &'a HashMap<u16, Variable>,
+        depth: usize,
+    ) -> Self {
+        let locals = local_entries(code, class.pool(), &class.names);
+        let parameters = class.parameters(method, &locals);
+        let this = (!method.flags.is_static()).then(|| Variable {
+            name: "this".to_string(),
+            ty: class.simple_name(),
+            synthetic: false,
+        });
+        MethodContext { class, method, code, locals, this, parameters, renames, depth }
+    }
+
+    /// The source variable in `slot` at `pc`, if it is known.
+    fn variable(&self, slot: u16, pc: u32) -> Option<(Variable, FieldType)> {
+        let parameter = self.parameters.iter().find(|parameter| parameter.slot == slot);
+        if let Some(variable) = self.renames.get(&slot) {
+            let field_type =
+                parameter.map_or_else(object, |parameter| parameter.field_type.clone());
+            return Some((variable.clone(), field_type));
+        }
+        let local = self
+            .locals
+            .iter()
+            .find(|local| local.slot == slot && local.start <= pc && pc < local.end);
+        if let Some(local) = local {
+            let variable =
+                Variable { name: local.name.clone(), ty: local.ty.clone(), synthetic: false };
+            return Some((variable, local.field_type.clone()));
+        }
+        if let (0, Some(this)) = (slot, &self.this) {
+            return Some((this.clone(), FieldType::Object(self.class.class.this_class.clone())));
+        }
+        parameter.map(|parameter| (parameter.variable.clone(), parameter.field_type.clone()))
+    }
+
+    fn handlers(&self, graph: &ControlFlowGraph) -> Vec<Handler> {
+        self.code
+            .exception_table
+            .iter()
+            .filter_map(|handler| {
+                let catch_type = (handler.catch_type != 0).then(|| {
+                    let name = self.class.pool().class_name(handler.catch_type as usize);
+                    self.class.names.class(name.unwrap_or("java/lang/Throwable"))
+                });
+                Some(Handler {
+                    start: handler.start_pc as u32,
+                    end: handler.end_pc as u32,
+                    node: graph.block_at(handler.handler_pc as u32)?,
+                    catch_type,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A translated basic block: its statements and how control leaves it.
+#[derive(Debug, Clone)]
+struct Node {
+    pc: u32,
+    statements: Vec<Statement>,
+    exit: Exit,
+    /// The height of the operand stack on entry.
+    height: usize,
+}
+
+#[derive(Debug, Clone)]
+enum Exit {
+    Goto(usize),
+    If { condition: Expression, target: usize, next: usize },
+    Switch { value: Expression, cases: Vec<(i32, usize)>, default: usize },
+    Return(Option<Expression>),
+    Throw(Expression),
+}
+
+impl Exit {
+    fn successors(&self) -> Vec<usize> {
+        let mut successors = match self {
+            Exit::Goto(target) => vec![*target],
+            Exit::If { target, next, .. } => vec![*next, *target],
+            Exit::Switch { cases, default, .. } => {
+                cases.iter().map(|(_, target)| *target).chain([*default]).collect()
+            }
+            Exit::Return(_) | Exit::Throw(_) => Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        successors.retain(|successor| seen.insert(*successor));
+        successors
+    }
+}
+
+type StackValue = (Expression, FieldType);
+
+enum Step {
+    Next,
+    /// The next instruction was translated along with this one.
+    SkipNext,
+    Exit(Exit),
+}
+
+/// Translates the basic blocks of a method into statements by simulating the operand stack.
+struct Translator<'a> {
+    context: &'a MethodContext<'a>,
+    statements: Vec<Statement>,
+    stack: Vec<StackValue>,
+    /// The type last stored into each slot without a source variable.
+    slot_types: HashMap<u16, FieldType>,
+    temporaries: usize,
+    stack_variables: usize,
+}
+
+impl<'a> Translator<'a> {
+    fn new(context: &'a MethodContext<'a>) -> Self {
+        Translator {
+            context,
+            statements: Vec::new(),
+            stack: Vec::new(),
+            slot_types: HashMap::new(),
+            temporaries: 0,
+            stack_variables: 0,
+        }
+    }
+
+    fn names(&self) -> &'a TypeNames {
+        &self.context.class.names
+    }
+
+    fn pool(&self) -> &'a ConstantPool {
+        self.context.class.pool()
+    }
+
+    fn translate(mut self, graph: &ControlFlowGraph) -> Result<Vec<Option<Node>>> {
+        let mut nodes = (0..graph.len()).map(|_| None).collect::<Vec<Option<Node>>>();
+        let mut entries = vec![None; graph.len()];
+        for handler in &self.context.code.exception_table {
+            let Some(block) = graph.block_at(handler.handler_pc as u32) else { continue };
+            let catch_type = self
+                .pool()
+                .class_name(handler.catch_type as usize)
+                .unwrap_or("java/lang/Throwable");
+            entries[block].get_or_insert_with(|| {
+                vec![(Expression::Caught, FieldType::Object(catch_type.to_string()))]
+            });
+        }
+        for block in graph.reverse_postorder() {
+            let basic_block = &graph.blocks[block];
+            self.stack = entries[block].clone().unwrap_or_default();
+            let height = self.stack.len();
+            let exit = self.block(basic_block, graph)?;
+            self.leave(&exit, &mut entries, graph)?;
+            let statements = std::mem::take(&mut self.statements);
+            nodes[block] = Some(Node { pc: basic_block.start_pc, statements, exit, height });
+        }
+        Ok(nodes)
+    }
+
+    fn block(&mut self, block: &BasicBlock, graph: &ControlFlowGraph) -> Result<Exit> {
+        let mut index = block.instructions.start;
+        while index < block.instructions.end {
+            let lookahead = index + 1 < block.instructions.end;
+            match self.instruction(index, lookahead, graph)? {
+                Step::Next => index += 1,
+                Step::SkipNext => index += 2,
+                Step::Exit(exit) => return Ok(exit),
+            }
+        }
+        Ok(Exit::Goto(block.id + 1))
+    }
+
+    /// Hands the values left on the stack to the successors in variables shared by all the
+    /// edges into each successor.
+    fn leave(
+        &mut self,
+        exit: &Exit,
+        entries: &mut [Option<Vec<StackValue>>],
+        graph: &ControlFlowGraph,
+    ) -> Result<()> {
+        let values = std::mem::take(&mut self.stack);
+        let mut current =
+            values.iter().map(|(expression, _)| expression.clone()).collect::<Vec<_>>();
+        for successor in exit.successors() {
+            let slots = match &entries[successor] {
+                Some(slots) if slots.len() == values.len() => slots.clone(),
+                Some(_) => {
+                    return Err(DecompilerError::StackHeightMismatch(
+                        graph.blocks[successor].start_pc,
+                    ));
+                }
+                None => {
+                    let mut slots = Vec::new();
+                    for (expression, field_type) in &values {
+                        let slot = match expression {
+                            Expression::Uninitialized { .. } => expression.clone(),
+                            _ => Expression::Variable(self.stack_variable(field_type)),
+                        };
+                        slots.push((slot, field_type.clone()));
+                    }
+                    entries[successor] = Some(slots.clone());
+                    slots
+                }
+            };
+            for (value, (slot, _)) in current.iter_mut().zip(slots) {
+                if matches!(slot, Expression::Variable(_)) && *value != slot {
+                    let value = std::mem::replace(value, slot.clone());
+                    self.statements.push(Statement::Assign(slot, value));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn stack_variable(&mut self, field_type: &FieldType) -> Variable {
+        self.stack_variables += 1;
+        let ty = self.names().field_type(field_type);
+        Variable { name: format!("stack{}", self.stack_variables), ty, synthetic: true }
+    }
+
+    fn temporary(&mut self, field_type: &FieldType) -> Variable {
+        self.temporaries += 1;
+        let ty = self.names().field_type(field_type);
+        Variable { name: format!("tmp{}", self.temporaries), ty, synthetic: true }
+    }
+
+    fn push(&mut self, expression: Expression, field_type: FieldType) {
+        self.stack.push((expression, field_type));
+    }
+
+    fn pop(&mut self, pc: u32) -> Result<StackValue> {
+        self.stack.pop().ok_or(DecompilerError::StackUnderflow(pc))
+    }
+
+    /// Pops the values making up the top `words` words of the stack, bottom first.
+    fn take(&mut self, words: usize, pc: u32) -> Result<Vec<StackValue>> {
+        let mut values = Vec::new();
+        let mut taken = 0;
+        while taken < words {
+            let value = self.pop(pc)?;
+            taken += size(&value.1);
+            values.push(value);
+        }
+        if taken != words {
+            return Err(DecompilerError::CategoryMismatch(pc));
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    /// Assigns the stack values matching `predicate` to temporaries, bottom first, so that
+    /// statements emitted next do not change the order in which they are evaluated.
+    fn spill(&mut self, predicate: impl Fn(&Expression) -> bool) {
+        for index in 0..self.stack.len() {
+            let (expression, field_type) = &self.stack[index];
+            if matches!(expression, Expression::Variable(_) | Expression::Uninitialized { .. })
+                || !predicate(expression)
+            {
+                continue;
+            }
+            let (expression, field_type) = (expression.clone(), field_type.clone());
+            let temporary = Expression::Variable(self.temporary(&field_type));
+            for (value, _) in &mut self.stack[index..] {
+                if *value == expression {
+                    *value = temporary.clone();
+                }
+            }
+            self.statements.push(Statement::Assign(temporary, expression));
+        }
+    }
+
+    fn emit(&mut self, statement: Statement) {
+        self.spill(|expression| !is_pure(expression));
+        self.statements.push(statement);
+    }
+
+    fn instruction(
+        &mut self,
+        index: usize,
+        lookahead: bool,
+        graph: &ControlFlowGraph,
+    ) -> Result<Step> {
+        use Instruction::*;
+        let code = &self.context.code.code;
+        let (instruction, pc) = &code[index];
+        let pc = *pc;
+        let after = code.get(index + 1).map_or(pc + 1, |(_, pc)| *pc);
+        let block_at =
+            |offset: i64| graph.block_at((pc as i64 + offset) as u32).unwrap_or_default();
+        if let Some((store, slot, kind)) = local_access(instruction) {
+            if store {
+                let (value, field_type) = self.pop(pc)?;
+                self.store(slot, pc, after, value, field_type);
+            } else {
+                let (variable, field_type) = self.variable(slot, pc, kind);
+                self.push(Expression::Variable(variable), field_type);
+            }
+            return Ok(Step::Next);
+        }
+        if let Some((store, element)) = array_access(instruction) {
+            if store {
+                self.array_store(pc)?;
+            } else {
+                let (index, _) = self.pop(pc)?;
+                let (array, array_type) = self.pop(pc)?;
+                let field_type = match (array_type, element) {
+                    (FieldType::Array(element), _) => *element,
+                    (_, Some(base)) => FieldType::Base(base),
+                    (_, None) => object(),
+                };
+                self.push(Expression::ArrayElement(Box::new(array), Box::new(index)), field_type);
+            }
+            return Ok(Step::Next);
+        }
+        if let Some(operator) = binary_operator(instruction) {
+            let (right, _) = self.pop(pc)?;
+            let (left, field_type) = self.pop(pc)?;
+            self.push(Expression::Binary(operator, Box::new(left), Box::new(right)), field_type);
+            return Ok(Step::Next);
+        }
+        if let Some(base) = conversion(instruction) {
+            let (value, _) = self.pop(pc)?;
+            self.push(
+                Expression::Cast(base_name(base).to_string(), Box::new(value)),
+                FieldType::Base(base),
+            );
+            return Ok(Step::Next);
+        }
+        if let Some((operands, operator, zero, offset)) = branch(instruction) {
+            let condition = self.condition(operands, operator, zero, pc)?;
+            let (target, next) = (block_at(offset as i16 as i64), block_at((after - pc) as i64));
+            return Ok(Step::Exit(Exit::If { condition, target, next }));
+        }
+        match instruction {
+            Aconst_null => self.push(literal("null"), object()),
+            Iconst_m1 => self.push(literal("-1"), int()),
+            Iconst_0 => self.push(literal("0"), int()),
+            Iconst_1 => self.push(literal("1"), int()),
+            Iconst_2 => self.push(literal("2"), int()),
+            Iconst_3 => self.push(literal("3"), int()),
+            Iconst_4 => self.push(literal("4"), int()),
+            Iconst_5 => self.push(literal("5"), int()),
+            Lconst_0 => self.push(literal("0L"), FieldType::Base(BaseType::Long)),
+            Lconst_1 => self.push(literal("1L"), FieldType::Base(BaseType::Long)),
+            Fconst_0 => self.push(literal("0.0F"), FieldType::Base(BaseType::Float)),
+            Fconst_1 => self.push(literal("1.0F"), FieldType::Base(BaseType::Float)),
+            Fconst_2 => self.push(literal("2.0F"), FieldType::Base(BaseType::Float)),
+            Dconst_0 => self.push(literal("0.0"), FieldType::Base(BaseType::Double)),
+            Dconst_1 => self.push(literal("1.0"), FieldType::Base(BaseType::Double)),
+            Bipush(value) => self.push(literal((*value as i8).to_string()), int()),
+            Sipush(value) => self.push(literal(value.to_string()), int()),
+            Ldc(index) => self.constant(*index as usize, pc)?,
+            Ldc_w(index) | Ldc2_w(index) => self.constant(*index as usize, pc)?,
+            Iinc(slot, delta) => self.increment(*slot as u16, *delta as i32, pc),
+            Wide(WideInstruction::Iinc(slot, delta)) => self.increment(*slot, *delta as i32, pc),
+            Ineg | Lneg | Fneg | Dneg => {
+                let (value, field_type) = self.pop(pc)?;
+                self.push(Expression::Unary("-", Box::new(value)), field_type);
+            }
+            Lcmp | Fcmpl | Fcmpg | Dcmpl | Dcmpg => {
+                let kind = match instruction {
+                    Lcmp => "Long",
+                    Fcmpl | Fcmpg => "Float",
+                    _ => "Double",
+                };
+                let (right, _) = self.pop(pc)?;
+                let (left, _) = self.pop(pc)?;
+                self.push(Expression::Compare(kind, Box::new(left), Box::new(right)), int());
+            }
+            Getfield(index) | Getstatic(index) | Putfield(index) | Putstatic(index) => {
+                self.field(instruction, *index, pc)?
+            }
+            Invokevirtual(index)
+            | Invokespecial(index)
+            | Invokestatic(index)
+            | Invokeinterface(index, _) => self.invoke(instruction, *index, pc)?,
+            Invokedynamic(index) => self.invoke_dynamic(*index, pc)?,
+            New(index) => {
+                let class = self
+                    .pool()
+                    .class_name(*index as usize)
+                    .ok_or(DecompilerError::InvalidConstant(pc))?;
+                self.push(
+                    Expression::Uninitialized { class: class.to_string(), pc },
+                    FieldType::Object(class.to_string()),
+                );
+            }
+            Newarray(kind) => {
+                let base = match kind {
+                    4 => BaseType::Boolean,
+                    5 => BaseType::Char,
+                    6 => BaseType::Float,
+                    7 => BaseType::Double,
+                    8 => BaseType::Byte,
+                    9 => BaseType::Short,
+                    10 => BaseType::Int,
+                    11 => BaseType::Long,
+                    _ => return Err(DecompilerError::InvalidConstant(pc)),
+                };
+                let (length, _) = self.pop(pc)?;
+                let element = base_name(base).to_string();
+                let array = Expression::NewArray {
+                    element,
+                    dimensions: vec![length],
+                    extra: 0,
+                    pc,
+                    elements: None,
+                };
+                self.push(array, FieldType::Array(Box::new(FieldType::Base(base))));
+            }
+            Anewarray(index) => {
+                let element = self.class_type(*index, pc)?;
+                let array_type = FieldType::Array(Box::new(element));
+                self.new_array(array_type, 1, pc)?;
+            }
+            Multianewarray(index, dimensions) => {
+                let array_type = self.class_type(*index, pc)?;
+                self.new_array(array_type, *dimensions as usize, pc)?;
+            }
+            Arraylength => {
+                let (array, _) = self.pop(pc)?;
+                self.push(Expression::ArrayLength(Box::new(array)), int());
+            }
+            Checkcast(index) => {
+                let field_type = self.class_type(*index, pc)?;
+                let (value, _) = self.pop(pc)?;
+                self.push(
+                    Expression::Cast(self.names().field_type(&field_type), Box::new(value)),
+                    field_type,
+                );
+            }
+            Instanceof(index) => {
+                let field_type = self.class_type(*index, pc)?;
+                let (value, _) = self.pop(pc)?;
+                let ty = self.names().field_type(&field_type);
+                self.push(
+                    Expression::InstanceOf(Box::new(value), ty),
+                    FieldType::Base(BaseType::Boolean),
+                );
+            }
+            Athrow => return Ok(Step::Exit(Exit::Throw(self.pop(pc)?.0))),
+            Ireturn | Lreturn | Freturn | Dreturn | Areturn => {
+                let (value, _) = self.pop(pc)?;
+                let ty =
+                    self.names().return_type(self.context.method.type_descriptor.return_type());
+                return Ok(Step::Exit(Exit::Return(Some(coerce(value, &ty)))));
+            }
+            Return => return Ok(Step::Exit(Exit::Return(None))),
+            Monitorenter | Monitorexit => {
+                let (value, _) = self.pop(pc)?;
+                let kind = if matches!(instruction, Monitorenter) {
+                    "monitorenter"
+                } else {
+                    "monitorexit"
+                };
+                self.emit(Statement::Monitor(kind, value));
+            }
+            Goto(offset) => return Ok(Step::Exit(Exit::Goto(block_at(*offset as i16 as i64)))),
+            Goto_w(offset) => return Ok(Step::Exit(Exit::Goto(block_at(*offset as i64)))),
+            Tableswitch { default, low, offsets, .. } => {
+                let (value, _) = self.pop(pc)?;
+                let cases = offsets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, offset)| (low + index as i32, block_at(*offset as i64)))
+                    .collect();
+                return Ok(Step::Exit(Exit::Switch {
+                    value,
+                    cases,
+                    default: block_at(*default as i64),
+                }));
+            }
+            Lookupswitch { default, pairs } => {
+                let (value, _) = self.pop(pc)?;
+                let cases =
+                    pairs.iter().map(|(key, offset)| (*key, block_at(*offset as i64))).collect();
+                return Ok(Step::Exit(Exit::Switch {
+                    value,
+                    cases,
+                    default: block_at(*default as i64),
+                }));
+            }
+            Pop => self.discard(1, pc)?,
+            Pop2 => self.discard(2, pc)?,
+            Dup | Dup_x1 | Dup_x2 | Dup_2 | Dup2_x1 | Dup2_x2 => {
+                return self.duplicate(index, lookahead);
+            }
+            Swap => {
+                if self.stack.iter().rev().take(2).all(|(expression, _)| !is_pure(expression)) {
+                    self.spill(|expression| !is_pure(expression));
+                }
+                let top = self.pop(pc)?;
+                let under = self.pop(pc)?;
+                self.stack.push(top);
+                self.stack.push(under);
+            }
+            Jsr(_) | Jsr_w(_) | Ret(_) | Wide(WideInstruction::Ret(_)) => {
+                return Err(DecompilerError::Subroutine(pc));
+            }
+            // Loads, stores, array accesses, arithmetic, conversions and branches are handled
+            // above, and `nop` has no effect.
+            _ => {}
+        }
+        Ok(Step::Next)
+    }
+
+    /// The variable loaded from `slot`, falling back to a synthetic `varN` named by the slot.
+    fn variable(&mut self, slot: u16, pc: u32, kind: char) -> (Variable, FieldType) {
+        if let Some(found) = self.context.variable(slot, pc) {
+            return found;
+        }
+        let field_type = self
+            .slot_types
+            .get(&slot)
+            .filter(|field_type| kind_matches(field_type, kind))
+            .cloned()
+            .unwrap_or_else(|| kind_type(kind));
+        (self.fallback(slot, &field_type), field_type)
+    }
+
+    fn fallback(&self, slot: u16, field_type: &FieldType) -> Variable {
+        Variable {
+            name: format!("var{}", slot),
+            ty: self.names().field_type(field_type),
+            synthetic: false,
+        }
+    }
+
+    fn store(&mut self, slot: u16, pc: u32, after: u32, value: Expression, field_type: FieldType) {
+        let (variable, value) = self.assign(slot, pc, after, value, field_type);
+        self.statements.push(Statement::Assign(Expression::Variable(variable), value));
+    }
+
+    /// The variable a store to `slot` assigns and the value it is assigned, after spilling
+    /// the stack values the assignment would change.
+    fn assign(
+        &mut self,
+        slot: u16,
+        pc: u32,
+        after: u32,
+        value: Expression,
+        field_type: FieldType,
+    ) -> (Variable, Expression) {
+        let found = self.context.variable(slot, after).or_else(|| self.context.variable(slot, pc));
+        let variable = match found {
+            Some((variable, _)) => variable,
+            None => self.fallback(slot, &field_type),
+        };
+        self.slot_types.insert(slot, field_type);
+        let value = coerce(value, &variable.ty);
+        let impure = !is_pure(&value);
+        self.spill(|expression| {
+            (impure && !is_pure(expression)) || references(expression, &variable)
+        });
+        (variable, value)
+    }
+
+    fn increment(&mut self, slot: u16, delta: i32, pc: u32) {
+        let (variable, _) = self.variable(slot, pc, 'I');
+        self.spill(|expression| {
+            references(expression, &variable)
+                && *expression != Expression::Variable(variable.clone())
+        });
+        if let (1 | -1, Some((Expression::Variable(top), field_type))) = (delta, self.stack.last())
+        {
+            if *top == variable {
+                let field_type = field_type.clone();
+                self.stack.pop();
+                self.spill(|expression| references(expression, &variable));
+                self.push(Expression::PostIncrement(variable, delta), field_type);
+                return;
+            }
+        }
+        self.spill(|expression| references(expression, &variable));
+        self.statements.push(Statement::Increment(variable, delta));
+    }
+
+    fn constant(&mut self, index: usize, pc: u32) -> Result<()> {
+        let pool = self.pool();
+        let constant = pool.get(index).map_err(|_| DecompilerError::InvalidConstant(pc))?;
+        let (expression, field_type) = match constant {
+            Constant::ClassIndex(_) => {
+                let class = self.class_type(index as u16, pc)?;
+                let literal = literal(format!("{}.class", self.names().field_type(&class)));
+                (literal, FieldType::Object("java/lang/Class".to_string()))
+            }
+            Constant::MethodType(_) | Constant::MethodHandle(..) | Constant::Dynamic(..) => {
+                let description = pool.describe(index).unwrap_or_default();
+                let arguments = vec![literal(java_string(&description))];
+                (Expression::Dynamic { name: "ldc".to_string(), arguments }, object())
+            }
+            constant => constant_literal(constant, pool, self.names())
+                .ok_or(DecompilerError::InvalidConstant(pc))?,
+        };
+        self.push(expression, field_type);
+        Ok(())
+    }
+
+    /// The type named by a class constant, which is an array descriptor for array classes.
+    fn class_type(&self, index: u16, pc: u32) -> Result<FieldType> {
+        let name =
+            self.pool().class_name(index as usize).ok_or(DecompilerError::InvalidConstant(pc))?;
+        if name.starts_with('[') {
+            FieldType::try_from(&mut name.chars().peekable())
+                .map_err(|_| DecompilerError::InvalidConstant(pc))
+        } else {
+            Ok(FieldType::Object(name.to_string()))
+        }
+    }
+
+    fn member(&self, index: u16, pc: u32) -> Result<(String, String, String)> {
+        let (owner, name, descriptor) =
+            self.pool().member_ref(index as usize).ok_or(DecompilerError::InvalidConstant(pc))?;
+        Ok((owner.to_string(), name.to_string(), descriptor.to_string()))
+    }
+
+    fn new_array(&mut self, array_type: FieldType, count: usize, pc: u32) -> Result<()> {
+        let mut dimensions = (0..count)
+            .map(|_| self.pop(pc).map(|(length, _)| length))
+            .collect::<Result<Vec<_>>>()?;
+        dimensions.reverse();
+        let mut element = &array_type;
+        let mut depth = 0usize;
+        while let FieldType::Array(inner) = element {
+            element = inner;
+            depth += 1;
+        }
+        let element = self.names().field_type(element);
+        let extra = depth.saturating_sub(count);
+        self.push(
+            Expression::NewArray { element, dimensions, extra, pc, elements: None },
+            array_type,
+        );
+        Ok(())
+    }
+
+    fn field(&mut self, instruction: &Instruction, index: u16, pc: u32) -> Result<()> {
+        use Instruction::*;
+        let (owner, name, descriptor) = self.member(index, pc)?;
+        let field_type = FieldType::try_from(&mut descriptor.chars().peekable())
+            .map_err(|_| DecompilerError::InvalidConstant(pc))?;
+        let value = match instruction {
+            Putfield(_) | Putstatic(_) => Some(self.pop(pc)?.0),
+            _ => None,
+        };
+        let target = match instruction {
+            Getstatic(_) | Putstatic(_) => Target::Static(self.names().class(&owner)),
+            _ => Target::Instance(Box::new(self.pop(pc)?.0)),
+        };
+        match value {
+            None => self.push(Expression::Field(target, name), field_type),
+            Some(value) => {
+                let value = coerce(value, &self.names().field_type(&field_type));
+                self.emit(Statement::Assign(Expression::Field(target, name), value));
+            }
+        }
+        Ok(())
+    }
+
+    fn arguments(&mut self, descriptor: &MethodDescriptor, pc: u32) -> Result<Vec<Expression>> {
+        let mut arguments = Vec::new();
+        for parameter in descriptor.parameters().iter().rev() {
+            let (value, _) = self.pop(pc)?;
+            arguments.push(coerce(value, &self.names().field_type(parameter)));
+        }
+        arguments.reverse();
+        Ok(arguments)
+    }
+
+    fn push_result(&mut self, expression: Expression, descriptor: &MethodDescriptor) {
+        match descriptor.return_type() {
+            ReturnDescriptor::VoidDescriptor => self.emit(Statement::Expression(expression)),
+            ReturnDescriptor::FieldType(field_type) => self.push(expression, field_type.clone()),
+        }
+    }
+
+    fn invoke(&mut self, instruction: &Instruction, index: u16, pc: u32) -> Result<()> {
+        let (owner, name, descriptor) = self.member(index, pc)?;
+        let method_descriptor = MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+            .map_err(|_| DecompilerError::InvalidConstant(pc))?;
+        let arguments = self.arguments(&method_descriptor, pc)?;
+        let target = match instruction {
+            Instruction::Invokestatic(_) if is_boxing(&owner, &name, &descriptor) => {
+                self.push_result(
+                    arguments.into_iter().next().unwrap_or_else(|| literal("null")),
+                    &method_descriptor,
+                );
+                return Ok(());
+            }
+            Instruction::Invokestatic(_) => Target::Static(self.names().class(&owner)),
+            _ => {
+                let (receiver, _) = self.pop(pc)?;
+                if name == "<init>" {
+                    self.construct(receiver, owner, descriptor, arguments);
+                    return Ok(());
+                }
+                if name == "toString" && arguments.is_empty() {
+                    if let Some(concat) = builder_concat(&receiver) {
+                        self.push(concat, string());
+                        return Ok(());
+                    }
+                }
+                if is_unboxing(&owner, &name, &descriptor) {
+                    self.push_result(receiver, &method_descriptor);
+                    return Ok(());
+                }
+                let this_class = &self.context.class.class.this_class;
+                if matches!(instruction, Instruction::Invokespecial(_))
+                    && is_this(&receiver)
+                    && owner != *this_class
+                {
+                    Target::Super
+                } else {
+                    Target::Instance(Box::new(receiver))
+                }
+            }
+        };
+        let expression = Expression::Invoke { target, owner, name, descriptor, arguments };
+        self.push_result(expression, &method_descriptor);
+        Ok(())
+    }
+
+    fn construct(
+        &mut self,
+        receiver: Expression,
+        owner: String,
+        descriptor: String,
+        arguments: Vec<Expression>,
+    ) {
+        if let Expression::Uninitialized { class, .. } = &receiver {
+            let created = Expression::New { class: self.names().class(class), arguments };
+            let field_type = FieldType::Object(class.clone());
+            let mut used = false;
+            for value in &mut self.stack {
+                if value.0 == receiver {
+                    *value = (created.clone(), field_type.clone());
+                    used = true;
+                }
+            }
+            if !used {
+                self.emit(Statement::Expression(created));
+            }
+        } else if is_this(&receiver) {
+            let this_class = &self.context.class.class.this_class;
+            let name = if owner == *this_class { "this" } else { "super" }.to_string();
+            self.emit(Statement::Expression(Expression::Invoke {
+                target: Target::Implicit,
+                owner,
+                name,
+                descriptor,
+                arguments,
+            }));
+        } else {
+            let target = Target::Instance(Box::new(receiver));
+            let name = "<init>".to_string();
+            self.emit(Statement::Expression(Expression::Invoke {
+                target,
+                owner,
+                name,
+                descriptor,
+                arguments,
+            }));
+        }
+    }
+
+    fn invoke_dynamic(&mut self, index: u16, pc: u32) -> Result<()> {
+        let pool = self.pool();
+        let Ok(Constant::InvokeDynamic(bootstrap, name_and_type)) = pool.get(index as usize) else {
+            return Err(DecompilerError::InvalidConstant(pc));
+        };
+        let (name, descriptor) = pool
+            .name_and_type(*name_and_type as usize)
+            .ok_or(DecompilerError::InvalidConstant(pc))?;
+        let method_descriptor = MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+            .map_err(|_| DecompilerError::InvalidConstant(pc))?;
+        let arguments = self.arguments(&method_descriptor, pc)?;
+        let strings =
+            method_descriptor.parameters().iter().map(|parameter| *parameter == string()).collect();
+        let expression = self
+            .call_site(*bootstrap, &arguments, strings)
+            .unwrap_or_else(|| Expression::Dynamic { name: name.to_string(), arguments });
+        self.push_result(expression, &method_descriptor);
+        Ok(())
+    }
+
+    /// Recognizes the call sites of string concatenation, lambdas and method references.
+    fn call_site(
+        &self,
+        bootstrap: u16,
+        arguments: &[Expression],
+        strings: Vec<bool>,
+    ) -> Option<Expression> {
+        let pool = self.pool();
+        let method = self.context.class.bootstrap_methods.get(bootstrap as usize)?;
+        let Ok(Constant::MethodHandle(_, reference)) =
+            pool.get(method.bootstrap_method_ref as usize)
+        else {
+            return None;
+        };
+        match pool.member_ref(*reference as usize)? {
+            ("java/lang/invoke/StringConcatFactory", "makeConcatWithConstants", _) => {
+                self.concat_with_constants(method, arguments, strings)
+            }
+            ("java/lang/invoke/StringConcatFactory", "makeConcat", _) => {
+                Some(concat(arguments.iter().cloned().zip(strings).collect()))
+            }
+            ("java/lang/invoke/LambdaMetafactory", "metafactory" | "altMetafactory", _) => {
+                self.lambda(method, arguments)
+            }
+            _ => None,
+        }
+    }
+
+    /// Splices the arguments and constants of a call site into its recipe, where `\u{1}`
+    /// stands for the next argument and `\u{2}` for the next constant.
+    fn concat_with_constants(
+        &self,
+        method: &BootstrapMethod,
+        arguments: &[Expression],
+        strings: Vec<bool>,
+    ) -> Option<Expression> {
+        let pool = self.pool();
+        let Ok(Constant::StringIndex(recipe)) =
+            pool.get(*method.bootstrap_arguments.first()? as usize)
+        else {
+            return None;
+        };
+        let mut arguments = arguments.iter().cloned().zip(strings);
+        let mut constants = method.bootstrap_arguments[1..].iter();
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        for char in pool.utf8(*recipe as usize)?.chars() {
+            match char {
+                '\u{1}' | '\u{2}' => {
+                    if !text.is_empty() {
+                        parts.push((literal(java_string(&text)), true));
+                        text.clear();
+                    }
+                    let part = if char == '\u{1}' {
+                        arguments.next()?
+                    } else {
+                        let constant = pool.get(*constants.next()? as usize).ok()?;
+                        let (expression, field_type) =
+                            constant_literal(constant, pool, self.names())?;
+                        (expression, field_type == string())
+                    };
+                    parts.push(part);
+                }
+                char => text.push(char),
+            }
+        }
+        if !text.is_empty() {
+            parts.push((literal(java_string(&text)), true));
+        }
+        Some(concat(parts))
+    }
+
+    /// Recovers a lambda from its synthetic implementation method in this class, or a method
+    /// reference to any other implementation.
+    fn lambda(&self, method: &BootstrapMethod, captured: &[Expression]) -> Option<Expression> {
+        let pool = self.pool();
+        let Ok(Constant::MethodHandle(kind, reference)) =
+            pool.get(*method.bootstrap_arguments.get(1)? as usize)
+        else {
+            return None;
+        };
+        let (owner, name, descriptor) = pool.member_ref(*reference as usize)?;
+        let class = self.context.class;
+        if owner == class.class.this_class && name.starts_with("lambda$") {
+            if let Some(lambda) = self.lambda_body(name, descriptor, captured) {
+                return Some(lambda);
+            }
+        }
+        const REF_INVOKE_VIRTUAL: u8 = 5;
+        const REF_INVOKE_SPECIAL: u8 = 7;
+        const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+        const REF_INVOKE_INTERFACE: u8 = 9;
+        let bound = matches!(*kind, REF_INVOKE_VIRTUAL | REF_INVOKE_SPECIAL | REF_INVOKE_INTERFACE);
+        let target = match captured.first() {
+            Some(receiver) if bound => Target::Instance(Box::new(receiver.clone())),
+            _ => Target::Static(self.names().class(owner)),
+        };
+        let name = if *kind == REF_NEW_INVOKE_SPECIAL { "new" } else { name };
+        Some(Expression::MethodReference(target, name.to_string()))
+    }
+
+    fn lambda_body(
+        &self,
+        name: &str,
+        descriptor: &str,
+        captured: &[Expression],
+    ) -> Option<Expression> {
+        let class = self.context.class;
+        if self.context.depth >= MAX_LAMBDA_DEPTH {
+            return None;
+        }
+        let method = class.class.methods.iter().find(|method| {
+            method.name == name && method.type_descriptor.to_string() == descriptor
+        })?;
+        let instance = !method.flags.is_static();
+        let captured = if instance { captured.get(1..)? } else { captured };
+        let types = method.type_descriptor.parameters();
+        if captured.len() > types.len() {
+            return None;
+        }
+        let mut renames = HashMap::new();
+        let mut slot = if instance { 1 } else { 0 };
+        for (value, field_type) in captured.iter().zip(types) {
+            if let Expression::Variable(variable) = value {
+                renames.insert(slot, variable.clone());
+            }
+            slot += size(field_type) as u16;
+        }
+        let (body, parameters) = class.statements(method, &renames, self.context.depth + 1).ok()?;
+        let parameters = parameters[captured.len()..]
+            .iter()
+            .map(|parameter| parameter.variable.name.clone())
+            .collect();
+        Some(Expression::Lambda { parameters, body })
+    }
+
+    fn array_store(&mut self, pc: u32) -> Result<()> {
+        let (value, _) = self.pop(pc)?;
+        let (index, _) = self.pop(pc)?;
+        let (mut array, array_type) = self.pop(pc)?;
+        let value = match &array_type {
+            FieldType::Array(element) => coerce(value, &self.names().field_type(element)),
+            _ => value,
+        };
+        let created = match &array {
+            Expression::NewArray { pc, dimensions, elements, .. } => {
+                Some((*pc, dimensions.len(), elements.as_ref().map_or(0, Vec::len)))
+            }
+            _ => None,
+        };
+        if let Some((created, dimensions, position)) = created {
+            let shared = self.stack.iter().any(|(expression, _)| same_array(expression, created));
+            if shared && dimensions == 1 && index == literal(position.to_string()) {
+                for (expression, _) in &mut self.stack {
+                    if let Expression::NewArray { pc, elements, .. } = expression {
+                        if *pc == created {
+                            elements.get_or_insert_with(Vec::new).push(value.clone());
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            if shared {
+                let temporary = Expression::Variable(self.temporary(&array_type));
+                for (expression, _) in &mut self.stack {
+                    if same_array(expression, created) {
+                        *expression = temporary.clone();
+                    }
+                }
+                self.emit(Statement::Assign(temporary.clone(), array));
+                array = temporary;
+            }
+        }
+        self.emit(Statement::Assign(
+            Expression::ArrayElement(Box::new(array), Box::new(index)),
+            value,
+        ));
+        Ok(())
+    }
+
+    fn condition(
+        &mut self,
+        operands: usize,
+        operator: &'static str,
+        zero: &'static str,
+        pc: u32,
+    ) -> Result<Expression> {
+        let char = FieldType::Base(BaseType::Char);
+        let boolean = FieldType::Base(BaseType::Boolean);
+        if operands == 2 {
+            let (mut right, right_type) = self.pop(pc)?;
+            let (mut left, left_type) = self.pop(pc)?;
+            if right_type == char {
+                left = coerce(left, "char");
+            }
+            if left_type == char {
+                right = coerce(right, "char");
+            }
+            return Ok(Expression::Binary(operator, Box::new(left), Box::new(right)));
+        }
+        let (value, field_type) = self.pop(pc)?;
+        Ok(match value {
+            Expression::Compare(_, left, right) if zero == "0" => {
+                Expression::Binary(operator, left, right)
+            }
+            value if zero == "0" && field_type == boolean && operator == "==" => negate(value),
+            value if zero == "0" && field_type == boolean && operator == "!=" => value,
+            value => {
+                let zero =
+                    if field_type == char { coerce(literal(zero), "char") } else { literal(zero) };
+                Expression::Binary(operator, Box::new(value), Box::new(zero))
+            }
+        })
+    }
+
+    fn discard(&mut self, words: usize, pc: u32) -> Result<()> {
+        for (value, _) in self.take(words, pc)? {
+            if !is_pure(&value) {
+                self.emit(Statement::Expression(value));
+            }
+        }
+        Ok(())
+    }
+
+    fn duplicate(&mut self, index: usize, lookahead: bool) -> Result<Step> {
+        use Instruction::*;
+        let code = &self.context.code.code;
+        let (instruction, pc) = &code[index];
+        let pc = *pc;
+        let (top, under) = match instruction {
+            Dup => (1, 0),
+            Dup_x1 => (1, 1),
+            Dup_x2 => (1, 2),
+            Dup_2 => (2, 0),
+            Dup2_x1 => (2, 1),
+            _ => (2, 2),
+        };
+        // The value of an assignment used further, as in `(line = reader.readLine()) != null`.
+        if let (0, true, Some((value, field_type))) = (under, lookahead, self.stack.last()) {
+            let (next, store_pc) = &code[index + 1];
+            if let Some((true, slot, _)) = local_access(next) {
+                if !is_trivial(value) && size(field_type) == top {
+                    let (value, field_type) = self.pop(pc)?;
+                    let after = code.get(index + 2).map_or(store_pc + 1, |(_, pc)| *pc);
+                    let (variable, value) =
+                        self.assign(slot, *store_pc, after, value, field_type.clone());
+                    self.push(Expression::Assignment(variable, Box::new(value)), field_type);
+                    return Ok(Step::SkipNext);
+                }
+            }
+        }
+        let duplicable = |expression: &Expression| {
+            is_pure(expression) || matches!(expression, Expression::NewArray { .. })
+        };
+        if self.stack.iter().rev().take(top).any(|(expression, _)| !duplicable(expression)) {
+            self.spill(|expression| !duplicable(expression));
+        }
+        let values = self.take(top, pc)?;
+        let below = self.take(under, pc)?;
+        self.stack.extend(values.iter().cloned());
+        self.stack.extend(below);
+        self.stack.extend(values);
+        Ok(Step::Next)
+    }
+}
+
+fn is_subroutine(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Jsr(_)
+            | Instruction::Jsr_w(_)
+            | Instruction::Ret(_)
+            | Instruction::Wide(WideInstruction::Ret(_))
+    )
+}
+
+/// Whether the instruction stores (`true`) or loads a local, its slot and its type letter.
+fn local_access(instruction: &Instruction) -> Option<(bool, u16, char)> {
+    use Instruction::*;
+    Some(match instruction {
+        Iload(slot) => (false, *slot as u16, 'I'),
+        Lload(slot) => (false, *slot as u16, 'J'),
+        Fload(slot) => (false, *slot as u16, 'F'),
+        Dload(slot) => (false, *slot as u16, 'D'),
+        Aload(slot) => (false, *slot as u16, 'A'),
+        Istore(slot) => (true, *slot as u16, 'I'),
+        Lstore(slot) => (true, *slot as u16, 'J'),
+        Fstore(slot) => (true, *slot as u16, 'F'),
+        Dstore(slot) => (true, *slot as u16, 'D'),
+        Astore(slot) => (true, *slot as u16, 'A'),
+        Iload_0 => (false, 0, 'I'),
+        Iload_1 => (false, 1, 'I'),
+        Iload_2 => (false, 2, 'I'),
+        Iload_3 => (false, 3, 'I'),
+        Lload_0 => (false, 0, 'J'),
+        Lload_1 => (false, 1, 'J'),
+        Lload_2 => (false, 2, 'J'),
+        Lload_3 => (false, 3, 'J'),
+        Fload_0 => (false, 0, 'F'),
+        Fload_1 => (false, 1, 'F'),
+        Fload_2 => (false, 2, 'F'),
+        Fload_3 => (false, 3, 'F'),
+        Dload_0 => (false, 0, 'D'),
+        Dload_1 => (false, 1, 'D'),
+        Dload_2 => (false, 2, 'D'),
+        Dload_3 => (false, 3, 'D'),
+        Aload_0 => (false, 0, 'A'),
+        Aload_1 => (false, 1, 'A'),
+        Aload_2 => (false, 2, 'A'),
+        Aload_3 => (false, 3, 'A'),
+        Istore_0 => (true, 0, 'I'),
+        Istore_1 => (true, 1, 'I'),
+        Istore_2 => (true, 2, 'I'),
+        Istore_3 => (true, 3, 'I'),
+        Lstore_0 => (true, 0, 'J'),
+        Lstore_1 => (true, 1, 'J'),
+        Lstore_2 => (true, 2, 'J'),
+        Lstore_3 => (true, 3, 'J'),
+        Fstore_0 => (true, 0, 'F'),
+        Fstore_1 => (true, 1, 'F'),
+        Fstore_2 => (true, 2, 'F'),
+        Fstore_3 => (true, 3, 'F'),
+        Dstore_0 => (true, 0, 'D'),
+        Dstore_1 => (true, 1, 'D'),
+        Dstore_2 => (true, 2, 'D'),
+        Dstore_3 => (true, 3, 'D'),
+        Astore_0 => (true, 0, 'A'),
+        Astore_1 => (true, 1, 'A'),
+        Astore_2 => (true, 2, 'A'),
+        Astore_3 => (true, 3, 'A'),
+        Wide(WideInstruction::Iload(slot)) => (false, *slot, 'I'),
+        Wide(WideInstruction::Lload(slot)) => (false, *slot, 'J'),
+        Wide(WideInstruction::Fload(slot)) => (false, *slot, 'F'),
+        Wide(WideInstruction::Dload(slot)) => (false, *slot, 'D'),
+        Wide(WideInstruction::Aload(slot)) => (false, *slot, 'A'),
+        Wide(WideInstruction::Istore(slot)) => (true, *slot, 'I'),
+        Wide(WideInstruction::Lstore(slot)) => (true, *slot, 'J'),
+        Wide(WideInstruction::Fstore(slot)) => (true, *slot, 'F'),
+        Wide(WideInstruction::Dstore(slot)) => (true, *slot, 'D'),
+        Wide(WideInstruction::Astore(slot)) => (true, *slot, 'A'),
+        _ => return None,
+    })
+}
+
+/// Whether the instruction stores (`true`) or loads an array element, and the element type
+/// when the instruction implies it.
+fn array_access(instruction: &Instruction) -> Option<(bool, Option<BaseType>)> {
+    use Instruction::*;
+    Some(match instruction {
+        Iaload => (false, Some(BaseType::Int)),
+        Laload => (false, Some(BaseType::Long)),
+        Faload => (false, Some(BaseType::Float)),
+        Daload => (false, Some(BaseType::Double)),
+        Aaload => (false, None),
+        Baload => (false, Some(BaseType::Byte)),
+        Caload => (false, Some(BaseType::Char)),
+        Saload => (false, Some(BaseType::Short)),
+        Iastore | Lastore | Fastore | Dastore | Aastore | Bastore | Castore | Sastore => {
+            (true, None)
+        }
+        _ => return None,
+    })
+}
+
+fn binary_operator(instruction: &Instruction) -> Option<&'static str> {
+    use Instruction::*;
+    Some(match instruction {
+        Iadd | Ladd | Fadd | Dadd => "+",
+        Isub | Lsub | Fsub | Dsub => "-",
+        Imul | Lmul | Fmul | Dmul => "*",
+        Idiv | Ldiv | Fdiv | Ddiv => "/",
+        Irem | Lrem | Frem | Drem => "%",
+        Ishl | Lshl => "<<",
+        Ishr | Lshr => ">>",
+        Iushr | Lushr => ">>>",
+        Iand | Land => "&",
+        Ior | Lor => "|",
+        Ixor | Lxor => "^",
+        _ => return None,
+    })
+}
+
+fn conversion(instruction: &Instruction) -> Option<BaseType> {
+    use Instruction::*;
+    Some(match instruction {
+        L2i | F2i | D2i => BaseType::Int,
+        I2l | F2l | D2l => BaseType::Long,
+        I2f | L2f | D2f => BaseType::Float,
+        I2d | L2d | F2d => BaseType::Double,
+        I2b => BaseType::Byte,
+        I2c => BaseType::Char,
+        I2s => BaseType::Short,
+        _ => return None,
+    })
+}
+
+/// The operand count, operator, implicit right operand and offset of a conditional branch.
+fn branch(instruction: &Instruction) -> Option<(usize, &'static str, &'static str, u16)> {
+    use Instruction::*;
+    Some(match instruction {
+        Ifeq(offset) => (1, "==", "0", *offset),
+        Ifne(offset) => (1, "!=", "0", *offset),
+        Iflt(offset) => (1, "<", "0", *offset),
+        Ifge(offset) => (1, ">=", "0", *offset),
+        Ifgt(offset) => (1, ">", "0", *offset),
+        Ifle(offset) => (1, "<=", "0", *offset),
+        Ifnull(offset) => (1, "==", "null", *offset),
+        Ifnonnull(offset) => (1, "!=", "null", *offset),
+        If_icmpeq(offset) | If_acmpeq(offset) => (2, "==", "", *offset),
+        If_icmpne(offset) | If_acmpne(offset) => (2, "!=", "", *offset),
+        If_icmplt(offset) => (2, "<", "", *offset),
+        If_icmpge(offset) => (2, ">=", "", *offset),
+        If_icmpgt(offset) => (2, ">", "", *offset),
+        If_icmple(offset) => (2, "<=", "", *offset),
+        _ => return None,
+    })
+}
+
+fn base_name(base: BaseType) -> &'static str {
+    match base {
+        BaseType::Byte => "byte",
+        BaseType::Char => "char",
+        BaseType::Double => "double",
+        BaseType::Float => "float",
+        BaseType::Int => "int",
+        BaseType::Long => "long",
+        BaseType::Short => "short",
+        BaseType::Boolean => "boolean",
+    }
+}
+
+/// The number of stack words or local slots a value of the type takes.
+fn size(field_type: &FieldType) -> usize {
+    match field_type {
+        FieldType::Base(BaseType::Long | BaseType::Double) => 2,
+        _ => 1,
+    }
+}
+
+fn kind_matches(field_type: &FieldType, kind: char) -> bool {
+    matches!(
+        (kind, field_type),
+        (
+            'I',
+            FieldType::Base(
+                BaseType::Int
+                    | BaseType::Boolean
+                    | BaseType::Byte
+                    | BaseType::Char
+                    | BaseType::Short
+            )
+        ) | ('J', FieldType::Base(BaseType::Long))
+            | ('F', FieldType::Base(BaseType::Float))
+            | ('D', FieldType::Base(BaseType::Double))
+            | ('A', FieldType::Object(_) | FieldType::Array(_))
+    )
+}
+
+fn kind_type(kind: char) -> FieldType {
+    match kind {
+        'J' => FieldType::Base(BaseType::Long),
+        'F' => FieldType::Base(BaseType::Float),
+        'D' => FieldType::Base(BaseType::Double),
+        'A' => object(),
+        _ => int(),
+    }
+}
+
+fn int() -> FieldType {
+    FieldType::Base(BaseType::Int)
+}
+
+fn object() -> FieldType {
+    FieldType::Object("java/lang/Object".to_string())
+}
+
+fn string() -> FieldType {
+    FieldType::Object("java/lang/String".to_string())
+}
+
+fn literal(text: impl Into<String>) -> Expression {
+    Expression::Literal(text.into())
+}
+
+/// The literal for a loadable constant, with its type.
+fn constant_literal(
+    constant: &Constant,
+    pool: &ConstantPool,
+    names: &TypeNames,
+) -> Option<StackValue> {
+    Some(match constant {
+        Constant::Integer(value) => (literal(value.to_string()), int()),
+        Constant::Float(value) => {
+            (literal(float_literal(*value as f64, "F", "Float")), FieldType::Base(BaseType::Float))
+        }
+        Constant::Long(value) => (literal(format!("{}L", value)), FieldType::Base(BaseType::Long)),
+        Constant::Double(value) => {
+            (literal(float_literal(*value, "", "Double")), FieldType::Base(BaseType::Double))
+        }
+        Constant::StringIndex(index) => {
+            (literal(java_string(pool.utf8(*index as usize)?)), string())
+        }
+        Constant::ClassIndex(index) => {
+            let class = names.class(pool.utf8(*index as usize)?);
+            (literal(format!("{}.class", class)), FieldType::Object("java/lang/Class".to_string()))
+        }
+        _ => return None,
+    })
+}
+
+fn float_literal(value: f64, suffix: &str, class: &str) -> String {
+    if value.is_nan() {
+        format!("{}.NaN", class)
+    } else if value.is_infinite() {
+        let sign = if value > 0.0 { "POSITIVE" } else { "NEGATIVE" };
+        format!("{}.{}_INFINITY", class, sign)
+    } else if suffix == "F" {
+        format!("{:?}F", value as f32)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+fn java_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for char in text.chars() {
+        escape(char, '"', &mut quoted);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn escape(char: char, quote: char, output: &mut String) {
+    match char {
+        '\n' => output.push_str("\\n"),
+        '\t' => output.push_str("\\t"),
+        '\r' => output.push_str("\\r"),
+        '\u{8}' => output.push_str("\\b"),
+        '\u{c}' => output.push_str("\\f"),
+        '\\' => output.push_str("\\\\"),
+        char if char == quote => {
+            output.push('\\');
+            output.push(char);
+        }
+        char if char.is_control() => write!(output, "\\u{:04x}", char as u32).unwrap(),
+        char => output.push(char),
+    }
+}
+
+/// Rewrites an `int` value for a context expecting `ty`, turning literals into `boolean` and
+/// `char` literals.
+fn coerce(expression: Expression, ty: &str) -> Expression {
+    match (ty, expression) {
+        ("boolean", Expression::Literal(text)) => match text.as_str() {
+            "0" => literal("false"),
+            "1" => literal("true"),
+            _ => Expression::Literal(text),
+        },
+        ("boolean", expression @ Expression::Boolean(_)) => expression,
+        ("boolean", expression) => Expression::Boolean(Box::new(expression)),
+        ("char", Expression::Literal(text)) => {
+            match text.parse::<u32>().ok().and_then(char::from_u32) {
+                Some(char) => {
+                    let mut quoted = String::from("'");
+                    escape(char, '\'', &mut quoted);
+                    quoted.push('\'');
+                    Expression::Literal(quoted)
+                }
+                None => Expression::Literal(text),
+            }
+        }
+        (_, expression) => expression,
+    }
+}
+
+fn is_this(expression: &Expression) -> bool {
+    matches!(expression, Expression::Variable(variable) if variable.name == "this")
+}
+
+fn same_array(expression: &Expression, created: u32) -> bool {
+    matches!(expression, Expression::NewArray { pc, .. } if *pc == created)
+}
+
+const BOX_CLASSES: [&str; 8] = [
+    "java/lang/Boolean",
+    "java/lang/Byte",
+    "java/lang/Character",
+    "java/lang/Short",
+    "java/lang/Integer",
+    "java/lang/Long",
+    "java/lang/Float",
+    "java/lang/Double",
+];
+
+/// Whether the call is the `valueOf` javac emits to box a primitive value.
+fn is_boxing(owner: &str, name: &str, descriptor: &str) -> bool {
+    let primitive = descriptor.len() > 3
+        && descriptor.as_bytes()[2] == b')'
+        && !descriptor[1..2].starts_with('L');
+    BOX_CLASSES.contains(&owner) && name == "valueOf" && primitive
+}
+
+/// Whether the call is the `xxxValue` javac emits to unbox a wrapper.
+fn is_unboxing(owner: &str, name: &str, descriptor: &str) -> bool {
+    BOX_CLASSES.contains(&owner)
+        && name.ends_with("Value")
+        && descriptor.len() == 3
+        && descriptor.starts_with("()")
+}
+
+fn is_builder(class: &str) -> bool {
+    class == "java/lang/StringBuilder" || class == "java/lang/StringBuffer"
+}
+
+/// Folds a `StringBuilder` append chain ending in `toString()` into a concatenation.
+fn builder_concat(receiver: &Expression) -> Option<Expression> {
+    let mut parts = Vec::new();
+    let mut current = receiver;
+    loop {
+        match current {
+            Expression::Invoke {
+                target: Target::Instance(inner),
+                owner,
+                name,
+                descriptor,
+                arguments,
+            } if is_builder(owner) && name == "append" && arguments.len() == 1 => {
+                parts.push((arguments[0].clone(), descriptor.starts_with("(Ljava/lang/String;)")));
+                current = inner;
+            }
+            Expression::New { class, arguments }
+                if class == "StringBuilder" || class == "StringBuffer" =>
+            {
+                match arguments.as_slice() {
+                    [] => {}
+                    [first @ Expression::Literal(text)] if text.starts_with('"') => {
+                        parts.push((first.clone(), true))
+                    }
+                    [first @ Expression::Invoke { name, .. }] if name == "valueOf" => {
+                        parts.push((first.clone(), true))
+                    }
+                    _ => return None,
+                }
+                break;
+            }
+            _ => return None,
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    parts.reverse();
+    Some(concat(parts))
+}
+
+/// Concatenates the parts, each flagged with whether it is a `String`, starting with `""`
+/// when neither of the first two parts is a `String`.
+fn concat(parts: Vec<(Expression, bool)>) -> Expression {
+    let mut expressions = Vec::new();
+    if !parts.iter().take(2).any(|(_, string)| *string) {
+        expressions.push(literal("\"\""));
+    }
+    expressions.extend(parts.into_iter().map(|(expression, _)| expression));
+    if expressions.len() == 1 {
+        return expressions.remove(0);
+    }
+    Expression::Concat(expressions)
+}
+
+fn target_expression(target: &Target) -> Option<&Expression> {
+    match target {
+        Target::Instance(expression) => Some(expression),
+        _ => None,
+    }
+}
+
+fn target_expression_mut(target: &mut Target) -> Option<&mut Expression> {
+    match target {
+        Target::Instance(expression) => Some(expression),
+        _ => None,
+    }
+}
+
+/// The direct subexpressions in evaluation order, not looking into lambda bodies.
+fn children(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Unary(_, operand)
+        | Expression::Cast(_, operand)
+        | Expression::InstanceOf(operand, _)
+        | Expression::ArrayLength(operand)
+        | Expression::Boolean(operand)
+        | Expression::Assignment(_, operand) => vec![operand],
+        Expression::Binary(_, left, right)
+        | Expression::Compare(_, left, right)
+        | Expression::ArrayElement(left, right) => {
+            vec![left, right]
+        }
+        Expression::Ternary(condition, yes, no) => vec![condition, yes, no],
+        Expression::Field(target, _) | Expression::MethodReference(target, _) => {
+            target_expression(target).into_iter().collect()
+        }
+        Expression::Invoke { target, arguments, .. } => {
+            target_expression(target).into_iter().chain(arguments).collect()
+        }
+        Expression::New { arguments, .. }
+        | Expression::Dynamic { arguments, .. }
+        | Expression::Concat(arguments) => arguments.iter().collect(),
+        Expression::NewArray { dimensions, elements, .. } => {
+            dimensions.iter().chain(elements.iter().flatten()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn children_mut(expression: &mut Expression) -> Vec<&mut Expression> {
+    match expression {
+        Expression::Unary(_, operand)
+        | Expression::Cast(_, operand)
+        | Expression::InstanceOf(operand, _)
+        | Expression::ArrayLength(operand)
+        | Expression::Boolean(operand)
+        | Expression::Assignment(_, operand) => vec![operand],
+        Expression::Binary(_, left, right)
+        | Expression::Compare(_, left, right)
+        | Expression::ArrayElement(left, right) => {
+            vec![left, right]
+        }
+        Expression::Ternary(condition, yes, no) => vec![condition, yes, no],
+        Expression::Field(target, _) | Expression::MethodReference(target, _) => {
+            target_expression_mut(target).into_iter().collect()
+        }
+        Expression::Invoke { target, arguments, .. } => {
+            target_expression_mut(target).into_iter().chain(arguments).collect()
+        }
+        Expression::New { arguments, .. }
+        | Expression::Dynamic { arguments, .. }
+        | Expression::Concat(arguments) => arguments.iter_mut().collect(),
+        Expression::NewArray { dimensions, elements, .. } => {
+            dimensions.iter_mut().chain(elements.iter_mut().flatten()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether evaluating the expression itself, not counting its subexpressions, can have side
+/// effects or observe them.
+fn node_is_pure(expression: &Expression) -> bool {
+    !matches!(
+        expression,
+        Expression::Invoke { .. }
+            | Expression::New { .. }
+            | Expression::NewArray { .. }
+            | Expression::Field(..)
+            | Expression::ArrayElement(..)
+            | Expression::ArrayLength(..)
+            | Expression::PostIncrement(..)
+            | Expression::Assignment(..)
+            | Expression::Dynamic { .. }
+    )
+}
+
+fn is_pure(expression: &Expression) -> bool {
+    node_is_pure(expression) && children(expression).into_iter().all(is_pure)
+}
+
+fn is_trivial(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Literal(_)
+            | Expression::Variable(_)
+            | Expression::Caught
+            | Expression::Uninitialized { .. }
+    )
+}
+
+/// The top-level expressions of a statement, not looking into nested statements.
+fn expressions(statement: &Statement) -> Vec<&Expression> {
+    match statement {
+        Statement::Expression(expression)
+        | Statement::Return(Some(expression))
+        | Statement::Throw(expression)
+        | Statement::Monitor(_, expression) => vec![expression],
+        Statement::Assign(target, value) => vec![target, value],
+        Statement::If { condition, .. }
+        | Statement::While { condition, .. }
+        | Statement::DoWhile { condition, .. } => {
+            vec![condition]
+        }
+        Statement::Switch { value, .. } | Statement::Synchronized { lock: value, .. } => {
+            vec![value]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The statement lists nested in a statement.
+fn blocks(statement: &Statement) -> Vec<&Vec<Statement>> {
+    match statement {
+        Statement::If { then, otherwise, .. } => vec![then, otherwise],
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::Synchronized { body, .. } => vec![body],
+        Statement::Switch { cases, .. } => cases.iter().map(|(_, body)| body).collect(),
+        Statement::Try { body, catches, finally } => {
+            let catches = catches.iter().map(|catch| &catch.body);
+            std::iter::once(body).chain(catches).chain(finally).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn blocks_mut(statement: &mut Statement) -> Vec<&mut Vec<Statement>> {
+    match statement {
+        Statement::If { then, otherwise, .. } => vec![then, otherwise],
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::Synchronized { body, .. } => vec![body],
+        Statement::Switch { cases, .. } => cases.iter_mut().map(|(_, body)| body).collect(),
+        Statement::Try { body, catches, finally } => {
+            let catches = catches.iter_mut().map(|catch| &mut catch.body);
+            std::iter::once(body).chain(catches).chain(finally).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `predicate` holds for the expression or any expression within it, lambda bodies
+/// included.
+fn any_in(expression: &Expression, predicate: &dyn Fn(&Expression) -> bool) -> bool {
+    predicate(expression)
+        || children(expression).into_iter().any(|child| any_in(child, predicate))
+        || matches!(expression, Expression::Lambda { body, .. } if any_in_statements(body, predicate))
+}
+
+fn any_in_statements(statements: &[Statement], predicate: &dyn Fn(&Expression) -> bool) -> bool {
+    statements.iter().any(|statement| {
+        expressions(statement).into_iter().any(|expression| any_in(expression, predicate))
+            || blocks(statement).into_iter().any(|block| any_in_statements(block, predicate))
+    })
+}
+
+fn references(expression: &Expression, variable: &Variable) -> bool {
+    any_in(
+        expression,
+        &|expression| matches!(expression, Expression::Variable(found) | Expression::PostIncrement(found, _) | Expression::Assignment(found, _) if found == variable),
+    )
+}
+
+fn negate(condition: Expression) -> Expression {
+    match condition {
+        Expression::Unary("!", operand) => *operand,
+        Expression::Binary(operator, left, right) => {
+            let negated = match operator {
+                "==" => "!=",
+                "!=" => "==",
+                "<" => ">=",
+                ">=" => "<",
+                ">" => "<=",
+                "<=" => ">",
+                "&&" => return or(negate(*left), negate(*right)),
+                "||" => return and(negate(*left), negate(*right)),
+                _ => {
+                    return Expression::Unary(
+                        "!",
+                        Box::new(Expression::Binary(operator, left, right)),
+                    );
+                }
+            };
+            Expression::Binary(negated, left, right)
+        }
+        Expression::Literal(text) if text == "true" => literal("false"),
+        Expression::Literal(text) if text == "false" => literal("true"),
+        condition => Expression::Unary("!", Box::new(condition)),
+    }
+}
+
+fn and(left: Expression, right: Expression) -> Expression {
+    Expression::Binary("&&", Box::new(left), Box::new(right))
+}
+
+fn or(left: Expression, right: Expression) -> Expression {
+    Expression::Binary("||", Box::new(left), Box::new(right))
+}
+
+/// Merges blocks that only test a condition into the conditional block that is their single
+/// predecessor, turning the branches of `&&` and `||` back into one condition.
+fn merge_conditions(nodes: &mut [Option<Node>], handlers: &[Handler]) {
+    loop {
+        let mut predecessors = vec![0; nodes.len()];
+        for node in nodes.iter().flatten() {
+            for successor in node.exit.successors() {
+                predecessors[successor] += 1;
+            }
+        }
+        for handler in handlers {
+            predecessors[handler.node] += 2;
+        }
+        let covering =
+            |pc: u32| handlers.iter().map(|handler| handler.covers(pc)).collect::<Vec<_>>();
+        let mut merged = None;
+        'search: for first in 0..nodes.len() {
+            let Some(Node { pc, exit: Exit::If { condition, target, next }, .. }) = &nodes[first]
+            else {
+                continue;
+            };
+            let (t1, f1) = (*target, *next);
+            for second in [f1, t1] {
+                if second == first || second == 0 || t1 == f1 || predecessors[second] != 1 {
+                    continue;
+                }
+                let Some(Node { pc: second_pc, statements, exit, height }) = &nodes[second] else {
+                    continue;
+                };
+                let Exit::If { condition: inner, target: t2, next: f2 } = exit else { continue };
+                let (t2, f2) = (*t2, *f2);
+                if !statements.is_empty()
+                    || *height != 0
+                    || t2 == second
+                    || f2 == second
+                    || covering(*pc) != covering(*second_pc)
+                {
+                    continue;
+                }
+                let (c1, c2) = (condition.clone(), inner.clone());
+                let combined = if second == f1 && t1 == t2 {
+                    or(c1, c2)
+                } else if second == f1 && t1 == f2 {
+                    and(negate(c1), c2)
+                } else if second == t1 && f1 == f2 {
+                    and(c1, c2)
+                } else if second == t1 && f1 == t2 {
+                    or(negate(c1), c2)
+                } else {
+                    continue;
+                };
+                merged =
+                    Some((first, second, Exit::If { condition: combined, target: t2, next: f2 }));
+                break 'search;
+            }
+        }
+        let Some((first, second, exit)) = merged else { return };
+        if let Some(node) = &mut nodes[first] {
+            node.exit = exit;
+        }
+        nodes[second] = None;
+    }
+}
+
+/// A statement that `break` or `continue` can leave: a loop, or a switch without a header.
+struct Breakable {
+    header: Option<usize>,
+    exit: Option<usize>,
+    label: String,
+    labeled: bool,
+}
+
+/// Rebuilds structured statements from the translated blocks.
+struct Structurer {
+    nodes: Vec<Option<Node>>,
+    pcs: Vec<u32>,
+    handlers: Vec<Handler>,
+    consumed: Vec<bool>,
+    dominators: DominatorTree,
+    post_dominators: DominatorTree,
+    /// The position of each block in reverse postorder.
+    order: Vec<usize>,
+    loops: LoopNest,
+    emitted: Vec<bool>,
+    /// Indices of the loops being structured, innermost last.
+    active: Vec<usize>,
+    breakables: Vec<Breakable>,
+    /// Blocks targeted by a `goto`, emitted after the structured code if nothing else does.
+    pending: Vec<usize>,
+}
+
+impl Structurer {
+    fn new(nodes: Vec<Option<Node>>, handlers: Vec<Handler>) -> Self {
+        let normal = nodes
+            .iter()
+            .map(|node| node.as_ref().map_or_else(Vec::new, |node| node.exit.successors()))
+            .collect::<Vec<_>>();
+        let mut all = normal.clone();
+        for (successors, node) in all.iter_mut().zip(&nodes) {
+            let Some(node) = node else { continue };
+            for handler in handlers.iter().filter(|handler| handler.covers(node.pc)) {
+                // Handlers that protect themselves would otherwise form loops.
+                let protects_itself =
+                    nodes[handler.node].as_ref().is_some_and(|entry| handler.covers(entry.pc));
+                if !protects_itself && !successors.contains(&handler.node) {
+                    successors.push(handler.node);
+                }
+            }
+        }
+        let dominators = DominatorTree::from_successors(all.clone(), 0);
+        let loops = LoopNest::from_successors(&all, &dominators);
+        // Branches that end in `throw` should not keep the other branches from joining, so
+        // blocks that throw get a self-loop and drop out of the post-dominator tree, unless
+        // the method never returns.
+        let returns = nodes.iter().flatten().any(|node| matches!(node.exit, Exit::Return(_)));
+        let mut exits = normal.clone();
+        for (index, node) in nodes.iter().enumerate() {
+            if let (true, Some(Node { exit: Exit::Throw(_), .. })) = (returns, node) {
+                exits[index] = vec![index];
+            }
+        }
+        let post_dominators = DominatorTree::post_dominators_from_successors(&exits);
+        let pcs = nodes.iter().map(|node| node.as_ref().map_or(0, |node| node.pc)).collect();
+        let mut order = vec![usize::MAX; nodes.len()];
+        for (index, node) in reverse_postorder(&normal, 0).into_iter().enumerate() {
+            order[node] = index;
+        }
+        Structurer {
+            emitted: vec![false; nodes.len()],
+            consumed: vec![false; handlers.len()],
+            nodes,
+            pcs,
+            handlers,
+            dominators,
+            post_dominators,
+            order,
+            loops,
+            active: Vec::new(),
+            breakables: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn structure(mut self) -> Vec<Statement> {
+        let mut statements = self.sequence(0, None);
+        while let Some(node) = self
+            .pending
+            .iter()
+            .copied()
+            .find(|&node| !self.emitted[node] && self.nodes[node].is_some())
+        {
+            statements.extend(self.sequence(node, None));
+        }
+        statements
+    }
+
+    fn sequence(&mut self, node: usize, follow: Option<usize>) -> Vec<Statement> {
+        self.sequence_from(node, follow, false)
+    }
+
+    /// Structures the code from `node` until control reaches `follow` or leaves the enclosing
+    /// constructs. `entering` skips the checks for jumps and `follow` at the first node, for
+    /// the headers of the loops and the starts of the `try` blocks being built.
+    fn sequence_from(
+        &mut self,
+        mut node: usize,
+        follow: Option<usize>,
+        mut entering: bool,
+    ) -> Vec<Statement> {
+        let mut statements = Vec::new();
+        loop {
+            if !entering {
+                if Some(node) == follow {
+                    return statements;
+                }
+                if let Some(jump) = self.jump(node) {
+                    statements.push(jump);
+                    return statements;
+                }
+            }
+            entering = false;
+            if self.emitted[node] || self.nodes[node].is_none() {
+                statements.push(self.goto(node));
+                return statements;
+            }
+            let found = self.loop_at(node);
+            let range = self.try_range(node);
+            let next = match (found, range) {
+                (Some(index), Some(range)) if !self.covers_loop(index, range) => {
+                    self.structure_loop(index, &mut statements)
+                }
+                (_, Some(range)) => self.structure_try(node, range, &mut statements),
+                (Some(index), None) => self.structure_loop(index, &mut statements),
+                (None, None) => self.structure_node(node, follow, &mut statements),
+            };
+            match next {
+                Some(next) => node = next,
+                None => return statements,
+            }
+        }
+    }
+
+    fn covers_loop(&self, index: usize, (start, end): (u32, u32)) -> bool {
+        self.loops.loops[index]
+            .blocks
+            .iter()
+            .all(|&block| start <= self.pcs[block] && self.pcs[block] < end)
+    }
+
+    fn goto(&mut self, node: usize) -> Statement {
+        self.pending.push(node);
+        Statement::Goto(self.pcs[node])
+    }
+
+    /// The `break` or `continue` that reaches `node` from within the enclosing constructs.
+    fn jump(&mut self, node: usize) -> Option<Statement> {
+        let innermost = self.breakables.len().checked_sub(1)?;
+        let innermost_loop =
+            self.breakables.iter().rposition(|breakable| breakable.header.is_some());
+        for index in (0..self.breakables.len()).rev() {
+            let breakable = &mut self.breakables[index];
+            if breakable.header == Some(node) {
+                let labeled = Some(index) != innermost_loop;
+                breakable.labeled |= labeled;
+                return Some(Statement::Continue(labeled.then(|| breakable.label.clone())));
+            }
+            if breakable.exit == Some(node) {
+                let labeled = index != innermost;
+                breakable.labeled |= labeled;
+                return Some(Statement::Break(labeled.then(|| breakable.label.clone())));
+            }
+        }
+        None
+    }
+
+    fn pop_label(&mut self) -> Option<String> {
+        self.breakables.pop().filter(|breakable| breakable.labeled).map(|breakable| breakable.label)
+    }
+
+    fn loop_at(&self, node: usize) -> Option<usize> {
+        self.loops
+            .loops
+            .iter()
+            .position(|found| found.header == node)
+            .filter(|index| !self.active.contains(index))
+    }
+
+    fn successors(&self, node: usize) -> Vec<usize> {
+        self.nodes[node].as_ref().map_or_else(Vec::new, |node| node.exit.successors())
+    }
+
+    /// Where control goes after the loop: the header's successor outside the loop, or else the
+    /// last block that the loop exits to.
+    fn loop_exit(&self, index: usize) -> Option<usize> {
+        let found = &self.loops.loops[index];
+        let outside = |node: &usize| !found.blocks.contains(node);
+        if let Some(exit) = self.successors(found.header).into_iter().find(outside) {
+            return Some(exit);
+        }
+        found.blocks.iter().flat_map(|&block| self.successors(block)).filter(outside).max()
+    }
+
+    fn structure_loop(&mut self, index: usize, statements: &mut Vec<Statement>) -> Option<usize> {
+        let header = self.loops.loops[index].header;
+        let exit = self.loop_exit(index);
+        let label = format!("loop{}", self.pcs[header]);
+        self.active.push(index);
+        self.breakables.push(Breakable { header: Some(header), exit, label, labeled: false });
+        let body = self.sequence_from(header, None, true);
+        let label = self.pop_label();
+        self.active.pop();
+        statements.push(Statement::While { label, condition: literal("true"), body });
+        exit
+    }
+
+    /// The block where the branches from `node` meet again, if that is inside the innermost
+    /// loop being structured.
+    fn join(&self, node: usize) -> Option<usize> {
+        let body = self.active.last().map(|&index| &self.loops.loops[index]);
+        let join = self.post_dominators.immediate_dominator(node);
+        if let Some(join) = join.filter(|join| body.is_none_or(|found| found.blocks.contains(join)))
+        {
+            return Some(join);
+        }
+        // Branches that return, throw or leave the loop do not keep the others from meeting,
+        // so fall back to the first block that all branches can reach.
+        let reach = |start: usize| {
+            let mut seen = BTreeSet::new();
+            let mut stack = vec![start];
+            while let Some(current) = stack.pop() {
+                let inside = body
+                    .is_none_or(|found| found.blocks.contains(&current) && found.header != current);
+                if current != node && inside && seen.insert(current) {
+                    stack.extend(self.successors(current));
+                }
+            }
+            seen
+        };
+        let mut branches = self.successors(node).into_iter();
+        let mut common = reach(branches.next()?);
+        for branch in branches {
+            let reached = reach(branch);
+            common.retain(|candidate| reached.contains(candidate));
+        }
+        common.into_iter().min_by_key(|&candidate| self.order[candidate])
+    }
+
+    fn structure_node(
+        &mut self,
+        node: usize,
+        follow: Option<usize>,
+        statements: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        self.emitted[node] = true;
+        let current = self.nodes[node].as_mut()?;
+        statements.push(Statement::Label(current.pc));
+        statements.append(&mut current.statements);
+        match current.exit.clone() {
+            Exit::Goto(target) => Some(target),
+            Exit::Return(value) => {
+                statements.push(Statement::Return(value));
+                None
+            }
+            Exit::Throw(value) => {
+                statements.push(Statement::Throw(value));
+                None
+            }
+            Exit::If { condition, target, next } => {
+                self.structure_if(node, condition, target, next, follow, statements)
+            }
+            Exit::Switch { value, cases, default } => {
+                self.structure_switch(node, value, cases, default, statements)
+            }
+        }
+    }
+
+    fn structure_if(
+        &mut self,
+        node: usize,
+        condition: Expression,
+        target: usize,
+        next: usize,
+        follow: Option<usize>,
+        statements: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        if target == next {
+            return Some(target);
+        }
+        let join = self.join(node);
+        if join == Some(target) {
+            let then = self.sequence(next, join);
+            statements.push(Statement::If {
+                condition: negate(condition),
+                then,
+                otherwise: Vec::new(),
+            });
+            return Some(target);
+        }
+        if join == Some(next) {
+            let then = self.sequence(target, join);
+            statements.push(Statement::If { condition, then, otherwise: Vec::new() });
+            return Some(next);
+        }
+        if let Some(jump) = self.jump(target) {
+            statements.push(Statement::If { condition, then: vec![jump], otherwise: Vec::new() });
+            return Some(next);
+        }
+        if let Some(jump) = self.jump(next) {
+            statements.push(Statement::If {
+                condition: negate(condition),
+                then: vec![jump],
+                otherwise: Vec::new(),
+            });
+            return Some(target);
+        }
+        if let Some(join) = join {
+            let then = self.sequence(next, Some(join));
+            let otherwise = self.sequence(target, Some(join));
+            statements.push(Statement::If { condition: negate(condition), then, otherwise });
+            return Some(join);
+        }
+        // Without a join, as when a branch returns, the branch target still ends the
+        // statement if control also reaches it from outside the fall-through branch.
+        let loop_body = self.active.last().map(|&index| &self.loops.loops[index].blocks);
+        let region = |entry: usize, other: usize| {
+            self.dominators.dominates(node, entry)
+                && !self.dominators.dominates(entry, other)
+                && loop_body.is_none_or(|blocks| blocks.contains(&other))
+        };
+        if !self.emitted[target] && region(next, target) {
+            let then = self.sequence(next, Some(target));
+            statements.push(Statement::If {
+                condition: negate(condition),
+                then,
+                otherwise: Vec::new(),
+            });
+            return Some(target);
+        }
+        let then = self.sequence(next, follow);
+        if !falls_through(&then) {
+            statements.push(Statement::If {
+                condition: negate(condition),
+                then,
+                otherwise: Vec::new(),
+            });
+            return Some(target);
+        }
+        let otherwise = self.sequence(target, follow);
+        statements.push(Statement::If { condition: negate(condition), then, otherwise });
+        None
+    }
+
+    fn structure_switch(
+        &mut self,
+        node: usize,
+        value: Expression,
+        cases: Vec<(i32, usize)>,
+        default: usize,
+        statements: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        let join = self.join(node);
+        let mut groups = BTreeMap::<u32, (usize, Vec<Option<i32>>)>::new();
+        for (key, target) in cases.into_iter().filter(|&(_, target)| target != default) {
+            groups
+                .entry(self.pcs[target])
+                .or_insert_with(|| (target, Vec::new()))
+                .1
+                .push(Some(key));
+        }
+        if Some(default) != join {
+            groups.entry(self.pcs[default]).or_insert_with(|| (default, Vec::new())).1.push(None);
+        }
+        let groups = groups.into_values().collect::<Vec<_>>();
+        let label = format!("switch{}", self.pcs[node]);
+        self.breakables.push(Breakable { header: None, exit: join, label, labeled: false });
+        let mut switch_cases = Vec::new();
+        for (index, (target, keys)) in groups.iter().enumerate() {
+            let follow = groups.get(index + 1).map(|(next, _)| *next).or(join);
+            let body = if Some(*target) == join {
+                vec![Statement::Break(None)]
+            } else {
+                self.sequence(*target, follow)
+            };
+            switch_cases.push((keys.clone(), body));
+        }
+        let label = self.pop_label();
+        statements.push(Statement::Switch { label, value, cases: switch_cases });
+        join
+    }
+
+    /// The widest range of unconsumed exception table entries protecting `node`.
+    fn try_range(&self, node: usize) -> Option<(u32, u32)> {
+        let pc = self.pcs[node];
+        self.handlers
+            .iter()
+            .zip(&self.consumed)
+            .filter(|(handler, consumed)| !**consumed && handler.covers(pc))
+            .map(|(handler, _)| (handler.start, handler.end))
+            .max_by_key(|&(start, end)| (end - start, Reverse(start)))
+    }
+
+    fn structure_try(
+        &mut self,
+        node: usize,
+        (start, end): (u32, u32),
+        statements: &mut Vec<Statement>,
+    ) -> Option<usize> {
+        let mut group = (0..self.handlers.len())
+            .filter(|&index| {
+                let handler = &self.handlers[index];
+                !self.consumed[index] && handler.start == start && handler.end == end
+            })
+            .collect::<Vec<_>>();
+        let mut handler_nodes = Vec::new();
+        for &index in &group {
+            if !handler_nodes.contains(&self.handlers[index].node) {
+                handler_nodes.push(self.handlers[index].node);
+            }
+        }
+        // javac splits the range of a try statement around the code it inlines before jumps
+        // out of it, leaving several entries for the same handlers.
+        let (mut start, mut end) = (start, end);
+        for (index, handler) in self.handlers.iter().enumerate() {
+            let split = handler_nodes.contains(&handler.node)
+                && !handler_nodes.iter().any(|&node| handler.covers(self.pcs[node]));
+            if split && !self.consumed[index] && !group.contains(&index) {
+                group.push(index);
+                start = start.min(handler.start);
+                end = end.max(handler.end);
+            }
+        }
+        for &index in &group {
+            self.consumed[index] = true;
+        }
+        let inside = |pc: u32| start <= pc && pc < end;
+        // The try statement ends where its normal exits and its catch blocks meet.
+        let mut exits = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if self.nodes[index].is_none() || !inside(self.pcs[index]) {
+                continue;
+            }
+            let successors = node.as_ref().map_or_else(Vec::new, |node| node.exit.successors());
+            for successor in successors {
+                if !inside(self.pcs[successor]) && !exits.contains(&successor) {
+                    exits.push(successor);
+                }
+            }
+        }
+        for &index in &group {
+            let handler = &self.handlers[index];
+            if handler.catch_type.is_some() && !exits.contains(&handler.node) {
+                exits.push(handler.node);
+            }
+        }
+        let follow = self.common_post_dominator(&exits, |node| {
+            !inside(self.pcs[node]) && !handler_nodes.contains(&node)
+        });
+        // javac protects the handlers of `synchronized` blocks with themselves.
+        for (handler, consumed) in self.handlers.iter().zip(&mut self.consumed) {
+            if handler_nodes.contains(&handler.node) && handler.covers(self.pcs[handler.node]) {
+                *consumed = true;
+            }
+        }
+        let body = self.sequence_from(node, follow, true);
+        let mut finally = None;
+        let mut prepared = HashMap::new();
+        for &handler_node in &handler_nodes {
+            let catch_all = group.iter().all(|&index| {
+                self.handlers[index].node != handler_node
+                    || self.handlers[index].catch_type.is_none()
+            });
+            if !catch_all || self.emitted[handler_node] || finally.is_some() {
+                continue;
+            }
+            let handled = self.sequence(handler_node, None);
+            match finally_body(&handled) {
+                Some(body) => {
+                    for (index, handler) in self.handlers.iter().enumerate() {
+                        if handler.node == handler_node {
+                            self.consumed[index] = true;
+                        }
+                    }
+                    finally = Some(body);
+                }
+                None => {
+                    prepared.insert(handler_node, handled);
+                }
+            }
+        }
+        let mut catches = Vec::new();
+        for &handler_node in &handler_nodes {
+            let mut body = match prepared.remove(&handler_node) {
+                Some(body) => body,
+                None if finally.is_some()
+                    && self.emitted[handler_node]
+                    && self.is_finally(handler_node, &group) =>
+                {
+                    continue;
+                }
+                None if self.emitted[handler_node] => vec![self.goto(handler_node)],
+                None => self.sequence(handler_node, follow),
+            };
+            let mut types = Vec::new();
+            for &index in &group {
+                let handler = &self.handlers[index];
+                let ty = handler.catch_type.clone().unwrap_or_else(|| "Throwable".to_string());
+                if handler.node == handler_node && !types.contains(&ty) {
+                    types.push(ty);
+                }
+            }
+            let name = catch_variable(&mut body);
+            catches.push(Catch { types, name, body });
+        }
+        let mut body = body;
+        if let Some(finally) = &finally {
+            let copy = without_labels(finally);
+            strip_copies(&mut body, &copy, true);
+            for catch in &mut catches {
+                strip_copies(&mut catch.body, &copy, true);
+            }
+        }
+        statements.push(Statement::Try { body, catches, finally });
+        follow
+    }
+
+    /// Whether `handler_node` is the catch-all handler of the group turned into `finally`.
+    fn is_finally(&self, handler_node: usize, group: &[usize]) -> bool {
+        group.iter().any(|&index| {
+            self.handlers[index].node == handler_node && self.handlers[index].catch_type.is_none()
+        })
+    }
+
+    /// The nearest block post-dominating all of `nodes` that `allowed` accepts.
+    fn common_post_dominator(
+        &self,
+        nodes: &[usize],
+        allowed: impl Fn(usize) -> bool,
+    ) -> Option<usize> {
+        let nodes = nodes
+            .iter()
+            .copied()
+            .filter(|&node| self.post_dominators.is_reachable(node))
+            .collect::<Vec<_>>();
+        let (&first, rest) = nodes.split_first()?;
+        let mut candidate = Some(first);
+        while let Some(current) = candidate {
+            if allowed(current)
+                && rest.iter().all(|&node| self.post_dominators.dominates(current, node))
+            {
+                return Some(current);
+            }
+            candidate = self.post_dominators.immediate_dominator(current);
+        }
+        None
+    }
+}
+
+/// The body of a javac `finally` handler, which stores the exception, runs the `finally`
+/// code and rethrows it.
+fn finally_body(statements: &[Statement]) -> Option<Vec<Statement>> {
+    let first =
+        statements.iter().position(|statement| !matches!(statement, Statement::Label(_)))?;
+    let last =
+        statements.iter().rposition(|statement| !matches!(statement, Statement::Label(_)))?;
+    let (
+        Statement::Assign(Expression::Variable(stored), Expression::Caught),
+        Statement::Throw(Expression::Variable(thrown)),
+    ) = (&statements[first], &statements[last])
+    else {
+        return None;
+    };
+    (stored == thrown && first < last).then(|| statements[first + 1..last].to_vec())
+}
+
+/// Takes the name of the caught exception from the store that starts a handler.
+fn catch_variable(statements: &mut Vec<Statement>) -> String {
+    let first = statements.iter().position(|statement| !matches!(statement, Statement::Label(_)));
+    if let Some(first) = first {
+        if let Statement::Assign(Expression::Variable(variable), Expression::Caught) =
+            &statements[first]
+        {
+            let name = variable.name.clone();
+            statements.remove(first);
+            return name;
+        }
+    }
+    "exception".to_string()
+}
+
+fn without_labels(statements: &[Statement]) -> Vec<Statement> {
+    let mut statements = statements.to_vec();
+    remove_labels(&mut statements, &BTreeSet::new());
+    statements
+}
+
+/// Removes the copies of `finally` code javac inlines before each jump out of a `try` block
+/// and at its end.
+fn strip_copies(statements: &mut Vec<Statement>, copy: &[Statement], at_end: bool) {
+    if copy.is_empty() {
+        return;
+    }
+    for statement in statements.iter_mut() {
+        for block in blocks_mut(statement) {
+            strip_copies(block, copy, false);
+        }
+    }
+    let mut positions = statements
+        .iter()
+        .enumerate()
+        .filter(|(_, statement)| {
+            matches!(
+                statement,
+                Statement::Return(_)
+                    | Statement::Break(_)
+                    | Statement::Continue(_)
+                    | Statement::Goto(_)
+            )
+        })
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    if at_end {
+        positions.push(statements.len());
+    }
+    let mut limit = statements.len() + 1;
+    for &position in positions.iter().rev() {
+        if position >= limit {
+            continue;
+        }
+        if let Some(range) = preceding_copy(statements, position, copy) {
+            limit = range.start;
+            statements.drain(range);
+        }
+    }
+}
+
+fn preceding_copy(
+    statements: &[Statement],
+    end: usize,
+    copy: &[Statement],
+) -> Option<Range<usize>> {
+    let mut start = end;
+    let mut count = 0;
+    while count < copy.len() {
+        start = start.checked_sub(1)?;
+        if !matches!(statements[start], Statement::Label(_)) {
+            count += 1;
+        }
+    }
+    (without_labels(&statements[start..end]) == copy).then_some(start..end)
+}
+
+/// Whether control can reach the end of the statements.
+fn falls_through(statements: &[Statement]) -> bool {
+    match statements.iter().rev().find(|statement| !matches!(statement, Statement::Label(_))) {
+        None => true,
+        Some(
+            Statement::Return(_)
+            | Statement::Throw(_)
+            | Statement::Break(_)
+            | Statement::Continue(_)
+            | Statement::Goto(_),
+        ) => false,
+        Some(Statement::If { then, otherwise, .. }) => {
+            otherwise.is_empty() || falls_through(then) || falls_through(otherwise)
+        }
+        Some(Statement::While { label, condition: Expression::Literal(text), body })
+            if text == "true" =>
+        {
+            breaks(body, label, false)
+        }
+        Some(Statement::Try { body, catches, finally }) => {
+            finally.as_deref().is_none_or(falls_through)
+                && (falls_through(body) || catches.iter().any(|catch| falls_through(&catch.body)))
+        }
+        Some(Statement::Synchronized { body, .. }) => falls_through(body),
+        Some(_) => true,
+    }
+}
+
+/// Whether the statements break out of the loop or switch labeled `label` that directly
+/// contains them, `nested` being set inside inner loops and switches.
+fn breaks(statements: &[Statement], label: &Option<String>, nested: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Break(None) => !nested,
+        Statement::Break(Some(target)) => label.as_ref() == Some(target),
+        Statement::While { body, .. } | Statement::DoWhile { body, .. } => {
+            breaks(body, label, true)
+        }
+        Statement::Switch { cases, .. } => cases.iter().any(|(_, body)| breaks(body, label, true)),
+        statement => blocks(statement).into_iter().any(|block| breaks(block, label, nested)),
+    })
+}
+
+/// Whether the statements continue the loop labeled `label` that directly contains them,
+/// `nested` being set inside inner loops.
+fn continues(statements: &[Statement], label: &Option<String>, nested: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Continue(None) => !nested,
+        Statement::Continue(Some(target)) => label.as_ref() == Some(target),
+        Statement::While { body, .. } | Statement::DoWhile { body, .. } => {
+            continues(body, label, true)
+        }
+        statement => blocks(statement).into_iter().any(|block| continues(block, label, nested)),
+    })
+}
+
+fn post_process(statements: &mut Vec<Statement>, void: bool) {
+    let mut targets = BTreeSet::new();
+    goto_targets(statements, &mut targets);
+    remove_labels(statements, &targets);
+    synchronize(statements);
+    simplify(statements);
+    let mut counts = HashMap::new();
+    count_variables(statements, &mut counts);
+    inline(statements, &counts);
+    return_in_blocks(statements, &counts);
+    simplify(statements);
+    if void && matches!(statements.last(), Some(Statement::Return(None))) {
+        statements.pop();
+    }
+}
+
+/// Folds `monitorenter` followed by a `try` whose `finally` is the matching `monitorexit`
+/// into a `synchronized` statement. javac stores the lock into a local variable as it enters
+/// the monitor, and exits the monitor through it before each jump out of the block.
+fn synchronize(statements: &mut Vec<Statement>) {
+    for statement in statements.iter_mut() {
+        for block in blocks_mut(statement) {
+            synchronize(block);
+        }
+    }
+    let mut index = 0;
+    while index + 1 < statements.len() {
+        let (
+            Statement::Monitor("monitorenter", entered),
+            Statement::Try { catches, finally: Some(finally), .. },
+        ) = (&statements[index], &statements[index + 1])
+        else {
+            index += 1;
+            continue;
+        };
+        let ([exit @ Statement::Monitor("monitorexit", exited)], true) =
+            (finally.as_slice(), catches.is_empty())
+        else {
+            index += 1;
+            continue;
+        };
+        let previous = index.checked_sub(1).map(|previous| &statements[previous]);
+        // The lock is stored either by an assignment within `monitorenter` or by the
+        // statement before it, which leaves the lock or the variable to `monitorenter`.
+        let (lock, stored) = match (entered, exited, previous) {
+            (Expression::Assignment(variable, value), Expression::Variable(used), _)
+                if variable == used =>
+            {
+                (Some(&**value), false)
+            }
+            (
+                entered,
+                Expression::Variable(used),
+                Some(Statement::Assign(Expression::Variable(variable), value)),
+            ) if variable == used && (entered == exited || entered == value) => (Some(value), true),
+            (entered, exited, _) if entered == exited => (None, false),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let (lock, entered, exit) = (lock.cloned(), entered.clone(), exit.clone());
+        let Statement::Try { mut body, .. } = statements.remove(index + 1) else { unreachable!() };
+        remove_all(&mut body, &exit);
+        let hidden = match &exit {
+            Statement::Monitor(_, Expression::Variable(used)) => {
+                !any_in_statements(&body, &|expression| references(expression, used))
+            }
+            _ => false,
+        };
+        match lock.filter(|_| hidden) {
+            Some(lock) if stored => {
+                statements.remove(index);
+                index -= 1;
+                statements[index] = Statement::Synchronized { lock, body };
+            }
+            Some(lock) => statements[index] = Statement::Synchronized { lock, body },
+            None => statements[index] = Statement::Synchronized { lock: entered, body },
+        }
+        index += 1;
+    }
+}
+
+/// Removes `removed` from the statements and the blocks nested in them.
+fn remove_all(statements: &mut Vec<Statement>, removed: &Statement) {
+    statements.retain(|statement| statement != removed);
+    for statement in statements {
+        for block in blocks_mut(statement) {
+            remove_all(block, removed);
+        }
+    }
+}
+
+/// Moves a return after a `try` or `synchronized` block into it when the block ends by
+/// assigning the returned variable, which nothing else uses. javac returns from these blocks
+/// by saving the value, running the inlined `finally` code and returning after the block.
+fn return_in_blocks(statements: &mut Vec<Statement>, counts: &HashMap<String, (usize, usize)>) {
+    for statement in statements.iter_mut() {
+        for block in blocks_mut(statement) {
+            return_in_blocks(block, counts);
+        }
+    }
+    let mut index = 0;
+    while index + 1 < statements.len() {
+        let (statement, rest) = statements[index..].split_first_mut().unwrap();
+        let (body, copy) = match statement {
+            Statement::Try { body, catches, finally }
+                if !catches.iter().any(|catch| falls_through(&catch.body)) =>
+            {
+                (body, finally.as_deref().map_or_else(Vec::new, without_labels))
+            }
+            Statement::Synchronized { body, .. } => (body, Vec::new()),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let returned = matches!(
+            (rest.get(copy.len()), body.last()),
+            (
+                Some(Statement::Return(Some(Expression::Variable(returned)))),
+                Some(Statement::Assign(Expression::Variable(assigned), _)),
+            ) if returned == assigned
+                && counts.get(&returned.name) == Some(&(1, 1))
+                && rest[..copy.len()] == copy[..]
+        );
+        if returned {
+            let Some(Statement::Assign(_, value)) = body.pop() else { unreachable!() };
+            body.push(Statement::Return(Some(value)));
+            statements.drain(index + 1..index + 2 + copy.len());
+        }
+        index += 1;
+    }
+}
+
+fn goto_targets(statements: &[Statement], targets: &mut BTreeSet<u32>) {
+    for statement in statements {
+        if let Statement::Goto(pc) = statement {
+            targets.insert(*pc);
+        }
+        for block in blocks(statement) {
+            goto_targets(block, targets);
+        }
+    }
+}
+
+/// Removes the labels that no `goto` in `targets` jumps to.
+fn remove_labels(statements: &mut Vec<Statement>, targets: &BTreeSet<u32>) {
+    statements
+        .retain(|statement| !matches!(statement, Statement::Label(pc) if !targets.contains(pc)));
+    for statement in statements {
+        for block in blocks_mut(statement) {
+            remove_labels(block, targets);
+        }
+    }
+}
+
+fn simplify(statements: &mut Vec<Statement>) {
+    let mut index = 0;
+    while index < statements.len() {
+        for block in blocks_mut(&mut statements[index]) {
+            simplify(block);
+        }
+        let taken = std::mem::replace(&mut statements[index], Statement::Break(None));
+        statements[index] = simplified(taken);
+        // An else branch after a branch that does not complete follows the if statement.
+        if let Statement::If { then, otherwise, .. } = &mut statements[index] {
+            if !otherwise.is_empty() && !falls_through(then) {
+                let rest = std::mem::take(otherwise);
+                statements.splice(index + 1..index + 1, rest);
+            }
+        }
+        index += 1;
+    }
+}
+
+fn simplified(statement: Statement) -> Statement {
+    match statement {
+        Statement::If { condition, then, otherwise }
+            if then.is_empty() && !otherwise.is_empty() =>
+        {
+            simplified(Statement::If {
+                condition: negate(condition),
+                then: otherwise,
+                otherwise: then,
+            })
+        }
+        Statement::If { condition, then, otherwise } => {
+            match (then.as_slice(), otherwise.as_slice()) {
+                (
+                    [Statement::Assign(Expression::Variable(variable), yes)],
+                    [Statement::Assign(Expression::Variable(other), no)],
+                ) if variable == other && variable.synthetic => Statement::Assign(
+                    Expression::Variable(variable.clone()),
+                    Expression::Ternary(
+                        Box::new(condition),
+                        Box::new(yes.clone()),
+                        Box::new(no.clone()),
+                    ),
+                ),
+                _ => Statement::If { condition, then, otherwise },
+            }
+        }
+        Statement::While { label, condition: Expression::Literal(text), body }
+            if text == "true" =>
+        {
+            loop_shape(label, body)
+        }
+        Statement::Expression(Expression::Assignment(variable, value)) => {
+            Statement::Assign(Expression::Variable(variable), *value)
+        }
+        statement => statement,
+    }
+}
+
+/// Turns a `while (true)` loop that tests its condition first into `while`, or last into
+/// `do`-`while`.
+fn loop_shape(label: Option<String>, mut body: Vec<Statement>) -> Statement {
+    let own_break = |statement: &Statement| match statement {
+        Statement::Break(target) => target.is_none() || *target == label,
+        _ => false,
+    };
+    let own_continue = |statement: &Statement| match statement {
+        Statement::Continue(target) => target.is_none() || *target == label,
+        _ => false,
+    };
+    if let Some(Statement::If { condition, then, otherwise }) = body.first() {
+        if otherwise.is_empty() && then.len() == 1 && own_break(&then[0]) {
+            let condition = negate(condition.clone());
+            body.remove(0);
+            if body.last().is_some_and(own_continue) {
+                body.pop();
+            }
+            return Statement::While { label: used_label(label, &body), condition, body };
+        }
+    }
+    if let [.., Statement::If { condition, then, otherwise }, last] = body.as_slice() {
+        let condition = match then.as_slice() {
+            [jump] if otherwise.is_empty() && own_continue(jump) && own_break(last) => {
+                Some(condition.clone())
+            }
+            [jump] if otherwise.is_empty() && own_break(jump) && own_continue(last) => {
+                Some(negate(condition.clone()))
+            }
+            _ => None,
+        };
+        let rest = &body[..body.len() - 2];
+        if let Some(condition) = condition.filter(|_| !continues(rest, &label, false)) {
+            body.truncate(body.len() - 2);
+            return Statement::DoWhile { label: used_label(label, &body), body, condition };
+        }
+    }
+    if body.last().is_some_and(own_continue) {
+        body.pop();
+    }
+    Statement::While { label: used_label(label, &body), condition: literal("true"), body }
+}
+
+fn used_label(label: Option<String>, body: &[Statement]) -> Option<String> {
+    label.filter(|label| {
+        let label = Some(label.clone());
+        breaks(body, &label, true) || continues(body, &label, true)
+    })
+}
+
+/// Counts the assignments and uses of each variable by name.
+fn count_variables(statements: &[Statement], counts: &mut HashMap<String, (usize, usize)>) {
+    for statement in statements {
+        match statement {
+            Statement::Assign(Expression::Variable(variable), value) => {
+                counts.entry(variable.name.clone()).or_default().0 += 1;
+                count_uses(value, counts);
+            }
+            Statement::Increment(variable, _) => {
+                let count = counts.entry(variable.name.clone()).or_default();
+                count.0 += 1;
+                count.1 += 1;
+            }
+            statement => {
+                for expression in expressions(statement) {
+                    count_uses(expression, counts);
+                }
+            }
+        }
+        for block in blocks(statement) {
+            count_variables(block, counts);
+        }
+    }
+}
+
+fn count_uses(expression: &Expression, counts: &mut HashMap<String, (usize, usize)>) {
+    match expression {
+        Expression::Variable(variable) => counts.entry(variable.name.clone()).or_default().1 += 1,
+        Expression::PostIncrement(variable, _) => {
+            let count = counts.entry(variable.name.clone()).or_default();
+            count.0 += 1;
+            count.1 += 1;
+        }
+        Expression::Assignment(variable, _) => {
+            counts.entry(variable.name.clone()).or_default().0 += 1
+        }
+        Expression::Lambda { body, .. } => count_variables(body, counts),
+        _ => {}
+    }
+    for child in children(expression) {
+        count_uses(child, counts);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Walk {
+    /// The variable was replaced by its value.
+    Done,
+    /// The variable was not found.
+    Missing,
+    /// Something evaluated before the variable prevents moving its value there.
+    Blocked,
+}
+
+/// Inlines synthetic variables assigned once and used once, in the statement right after the
+/// assignment, where nothing evaluated before the use could observe the move.
+fn inline(statements: &mut Vec<Statement>, counts: &HashMap<String, (usize, usize)>) {
+    for statement in statements.iter_mut() {
+        for block in blocks_mut(statement) {
+            inline(block, counts);
+        }
+    }
+    let mut index = 0;
+    while index + 1 < statements.len() {
+        let candidate = match &statements[index] {
+            Statement::Assign(Expression::Variable(variable), value)
+                if variable.synthetic && counts.get(&variable.name) == Some(&(1, 1)) =>
+            {
+                Some((variable.clone(), value.clone()))
+            }
+            _ => None,
+        };
+        if let Some((variable, value)) = candidate {
+            let pure = is_pure(&value);
+            let mut value = Some(value);
+            if substitute_statement(&mut statements[index + 1], &variable, &mut value, pure)
+                == Walk::Done
+            {
+                statements.remove(index);
+                index = index.saturating_sub(1);
+                continue;
+            }
+        }
+        index += 1;
+    }
+}
+
+fn substitute_statement(
+    statement: &mut Statement,
+    variable: &Variable,
+    value: &mut Option<Expression>,
+    pure: bool,
+) -> Walk {
+    match statement {
+        Statement::Expression(expression)
+        | Statement::Return(Some(expression))
+        | Statement::Throw(expression)
+        | Statement::Monitor(_, expression)
+        | Statement::If { condition: expression, .. }
+        | Statement::Switch { value: expression, .. }
+        | Statement::Synchronized { lock: expression, .. } => {
+            substitute(expression, variable, value, pure)
+        }
+        Statement::Assign(target, expression) => {
+            let walk = match target {
+                Expression::Field(Target::Instance(object), _) => {
+                    substitute(object, variable, value, pure)
+                }
+                Expression::ArrayElement(array, index) => {
+                    match substitute(array, variable, value, pure) {
+                        Walk::Missing => substitute(index, variable, value, pure),
+                        walk => walk,
+                    }
+                }
+                _ => Walk::Missing,
+            };
+            match walk {
+                Walk::Missing => substitute(expression, variable, value, pure),
+                walk => walk,
+            }
+        }
+        _ => Walk::Blocked,
+    }
+}
+
+fn substitute(
+    expression: &mut Expression,
+    variable: &Variable,
+    value: &mut Option<Expression>,
+    pure: bool,
+) -> Walk {
+    if matches!(expression, Expression::Variable(found) if found == variable) {
+        if let Some(value) = value.take() {
+            *expression = value;
+        }
+        return Walk::Done;
+    }
+    let conditional = match expression {
+        Expression::Ternary(condition, yes, no) => Some((condition, vec![&**yes, &**no])),
+        Expression::Binary("&&" | "||", left, right) => Some((left, vec![&**right])),
+        _ => None,
+    };
+    if let Some((condition, branches)) = conditional {
+        let blocked = branches
+            .iter()
+            .any(|branch| references(branch, variable) || (!pure && !is_pure(branch)));
+        return match substitute(condition, variable, value, pure) {
+            Walk::Missing if blocked => Walk::Blocked,
+            walk => walk,
+        };
+    }
+    if let Expression::Lambda { .. } = expression {
+        return if references(expression, variable) { Walk::Blocked } else { Walk::Missing };
+    }
+    for child in children_mut(expression) {
+        match substitute(child, variable, value, pure) {
+            Walk::Missing => {}
+            walk => return walk,
+        }
+    }
+    let barrier = if pure {
+        matches!(expression, Expression::PostIncrement(..) | Expression::Assignment(..))
+    } else {
+        !node_is_pure(expression)
+    };
+    if barrier { Walk::Blocked } else { Walk::Missing }
+}
+
+fn binary_precedence(operator: &str) -> u8 {
+    match operator {
+        "||" => OR,
+        "&&" => AND,
+        "|" => BIT_OR,
+        "^" => BIT_XOR,
+        "&" => BIT_AND,
+        "==" | "!=" => EQUALITY,
+        "<" | ">" | "<=" | ">=" => RELATIONAL,
+        "<<" | ">>" | ">>>" => SHIFT,
+        "+" | "-" => ADDITIVE,
+        _ => MULTIPLICATIVE,
+    }
+}
+
+/// The default value printed for the elements an array initializer leaves out.
+fn default_value(element: &str, extra: usize) -> String {
+    match element {
+        _ if extra > 0 => "null",
+        "boolean" => "false",
+        "char" => "'\\u0000'",
+        "long" => "0L",
+        "float" => "0.0F",
+        "double" => "0.0",
+        "byte" | "short" | "int" => "0",
+        _ => "null",
+    }
+    .to_string()
+}
+
+struct Printer {
+    output: String,
+    indent: usize,
+    /// The variables declared so far, whose assignments print without a type.
+    declared: HashSet<String>,
+}
+
+impl Printer {
+    fn new(indent: usize, declared: HashSet<String>) -> Self {
+        Printer { output: String::new(), indent, declared }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str("    ");
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    /// Prints the statements of a nested scope, forgetting the variables declared in it.
+    fn block(&mut self, statements: &[Statement]) {
+        let declared = self.declared.clone();
+        self.indent += 1;
+        self.statements(statements);
+        self.indent -= 1;
+        self.declared = declared;
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for (index, statement) in statements.iter().enumerate() {
+            self.hoist(statement, &statements[index + 1..]);
+            self.statement(statement);
+        }
+    }
+
+    /// Declares ahead of `statement` the variables first assigned in its nested scopes that
+    /// the statements after it still use.
+    fn hoist(&mut self, statement: &Statement, rest: &[Statement]) {
+        let mut assigned = Vec::new();
+        for expression in expressions(statement) {
+            embedded_assignments(expression, &mut assigned);
+        }
+        for variable in assigned {
+            if self.declared.insert(variable.name.clone()) {
+                self.line(&format!("{} {};", variable.ty, variable.name));
+            }
+        }
+        let mut assigned = Vec::new();
+        for block in blocks(statement) {
+            assigned_variables(block, &mut assigned);
+        }
+        for variable in assigned {
+            if !self.declared.contains(&variable.name)
+                && any_in_statements(
+                    rest,
+                    &|expression| matches!(expression, Expression::Variable(found) | Expression::PostIncrement(found, _) if *found == variable),
+                )
+            {
+                self.declared.insert(variable.name.clone());
+                self.line(&format!("{} {};", variable.ty, variable.name));
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Label(pc) => self.line(&format!("L{}:", pc)),
+            Statement::Expression(expression) => {
+                let text = self.expression(expression, LAMBDA);
+                self.line(&format!("{};", text));
+            }
+            Statement::Assign(target, value) => {
+                let text = self.assignment(target, value);
+                self.line(&format!("{};", text));
+            }
+            Statement::Increment(variable, delta) => {
+                let text = match delta {
+                    1 => format!("{}++;", variable.name),
+                    -1 => format!("{}--;", variable.name),
+                    delta if *delta < 0 => format!("{} -= {};", variable.name, -delta),
+                    delta => format!("{} += {};", variable.name, delta),
+                };
+                self.line(&text);
+            }
+            Statement::Return(None) => self.line("return;"),
+            Statement::Return(Some(value)) => {
+                let text = self.expression(value, LAMBDA);
+                self.line(&format!("return {};", text));
+            }
+            Statement::Throw(value) => {
+                let text = self.expression(value, LAMBDA);
+                self.line(&format!("throw {};", text));
+            }
+            Statement::If { condition, then, otherwise } => {
+                self.if_statement(condition, then, otherwise, "")
+            }
+            Statement::While { label, condition, body } => {
+                let text = format!(
+                    "{}while ({}) {{",
+                    label_prefix(label),
+                    self.expression(condition, LAMBDA)
+                );
+                self.line(&text);
+                self.block(body);
+                self.line("}");
+            }
+            Statement::DoWhile { label, body, condition } => {
+                self.line(&format!("{}do {{", label_prefix(label)));
+                self.block(body);
+                let text = format!("}} while ({});", self.expression(condition, LAMBDA));
+                self.line(&text);
+            }
+            Statement::Switch { label, value, cases } => {
+                let text = format!(
+                    "{}switch ({}) {{",
+                    label_prefix(label),
+                    self.expression(value, LAMBDA)
+                );
+                self.line(&text);
+                // The cases of a switch share one scope.
+                let declared = self.declared.clone();
+                self.indent += 1;
+                for (keys, body) in cases {
+                    for key in keys {
+                        match key {
+                            Some(key) => self.line(&format!("case {}:", key)),
+                            None => self.line("default:"),
+                        }
+                    }
+                    self.indent += 1;
+                    self.statements(body);
+                    self.indent -= 1;
+                }
+                self.indent -= 1;
+                self.declared = declared;
+                self.line("}");
+            }
+            Statement::Try { body, catches, finally } => {
+                self.line("try {");
+                self.block(body);
+                for catch in catches {
+                    let inserted = self.declared.insert(catch.name.clone());
+                    self.line(&format!("}} catch ({} {}) {{", catch.types.join(" | "), catch.name));
+                    self.block(&catch.body);
+                    if inserted {
+                        self.declared.remove(&catch.name);
+                    }
+                }
+                if let Some(finally) = finally {
+                    self.line("} finally {");
+                    self.block(finally);
+                }
+                self.line("}");
+            }
+            Statement::Break(None) => self.line("break;"),
+            Statement::Break(Some(label)) => self.line(&format!("break {};", label)),
+            Statement::Continue(None) => self.line("continue;"),
+            Statement::Continue(Some(label)) => self.line(&format!("continue {};", label)),
+            Statement::Goto(pc) => self.line(&format!("goto L{};", pc)),
+            Statement::Synchronized { lock, body } => {
+                let text = format!("synchronized ({}) {{", self.expression(lock, LAMBDA));
+                self.line(&text);
+                self.block(body);
+                self.line("}");
+            }
+            Statement::Monitor(kind, value) => {
+                let text = self.expression(value, LAMBDA);
+                self.line(&format!("{}({});", kind, text));
+            }
+        }
+    }
+
+    fn if_statement(
+        &mut self,
+        condition: &Expression,
+        then: &[Statement],
+        otherwise: &[Statement],
+        prefix: &str,
+    ) {
+        let text = format!("{}if ({}) {{", prefix, self.expression(condition, LAMBDA));
+        self.line(&text);
+        self.block(then);
+        match otherwise {
+            [] => self.line("}"),
+            [Statement::If { condition, then, otherwise }] => {
+                self.if_statement(condition, then, otherwise, "} else ")
+            }
+            _ => {
+                self.line("} else {");
+                self.block(otherwise);
+                self.line("}");
+            }
+        }
+    }
+
+    fn assignment(&mut self, target: &Expression, value: &Expression) -> String {
+        if let Expression::Variable(variable) = target {
+            if self.declared.insert(variable.name.clone()) {
+                let value = self.expression(value, ASSIGNMENT);
+                return format!("{} {} = {}", variable.ty, variable.name, value);
+            }
+        }
+        let target_text = self.expression(target, PRIMARY);
+        if let Expression::Binary(operator, left, right) = value {
+            if **left == *target
+                && !matches!(binary_precedence(operator), OR | AND | EQUALITY | RELATIONAL)
+            {
+                if matches!(&**right, Expression::Literal(one) if one == "1")
+                    && matches!(*operator, "+" | "-")
+                {
+                    return format!("{}{}{}", target_text, operator, operator);
+                }
+                return format!(
+                    "{} {}= {}",
+                    target_text,
+                    operator,
+                    self.expression(right, ASSIGNMENT)
+                );
+            }
+        }
+        format!("{} = {}", target_text, self.expression(value, ASSIGNMENT))
+    }
+
+    /// Prints the expression, in parentheses if it binds less tightly than `minimum`.
+    fn expression(&self, expression: &Expression, minimum: u8) -> String {
+        let (text, precedence) = self.expression_text(expression);
+        if precedence < minimum { format!("({})", text) } else { text }
+    }
+
+    fn arguments(&self, arguments: &[Expression]) -> String {
+        arguments
+            .iter()
+            .map(|argument| self.expression(argument, ASSIGNMENT))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn target(&self, target: &Target) -> String {
+        match target {
+            Target::Static(class) => class.clone(),
+            Target::Instance(expression) => self.expression(expression, PRIMARY),
+            Target::Super => "super".to_string(),
+            Target::Implicit => String::new(),
+        }
+    }
+
+    fn expression_text(&self, expression: &Expression) -> (String, u8) {
+        match expression {
+            Expression::Literal(text) => {
+                (text.clone(), if text.starts_with('-') { UNARY } else { PRIMARY })
+            }
+            Expression::Variable(variable) => (variable.name.clone(), PRIMARY),
+            Expression::Caught => ("exception".to_string(), PRIMARY),
+            Expression::Uninitialized { class, .. } => {
+                (format!("new {}", class.replace('/', ".")), PRIMARY)
+            }
+            Expression::Unary(operator, operand) => {
+                let text = self.expression(operand, UNARY);
+                if text.starts_with(operator) {
+                    (format!("{}({})", operator, text), UNARY)
+                } else {
+                    (format!("{}{}", operator, text), UNARY)
+                }
+            }
+            Expression::Binary(operator, left, right) => {
+                let precedence = binary_precedence(operator);
+                let left = self.expression(left, precedence);
+                let right = self.expression(right, precedence + 1);
+                (format!("{} {} {}", left, operator, right), precedence)
+            }
+            Expression::Compare(kind, left, right) => {
+                let (left, right) =
+                    (self.expression(left, ASSIGNMENT), self.expression(right, ASSIGNMENT));
+                (format!("{}.compare({}, {})", kind, left, right), PRIMARY)
+            }
+            Expression::Cast(ty, operand) => {
+                (format!("({}) {}", ty, self.expression(operand, UNARY)), UNARY)
+            }
+            Expression::InstanceOf(operand, ty) => {
+                (format!("{} instanceof {}", self.expression(operand, RELATIONAL), ty), RELATIONAL)
+            }
+            Expression::Field(target, name) => {
+                (format!("{}.{}", self.target(target), name), PRIMARY)
+            }
+            Expression::ArrayElement(array, index) => {
+                let array = match &**array {
+                    Expression::NewArray { elements: None, .. } => {
+                        format!("({})", self.expression(array, LAMBDA))
+                    }
+                    array => self.expression(array, PRIMARY),
+                };
+                (format!("{}[{}]", array, self.expression(index, LAMBDA)), PRIMARY)
+            }
+            Expression::ArrayLength(array) => {
+                (format!("{}.length", self.expression(array, PRIMARY)), PRIMARY)
+            }
+            Expression::Invoke { target: Target::Implicit, name, arguments, .. } => {
+                (format!("{}({})", name, self.arguments(arguments)), PRIMARY)
+            }
+            Expression::Invoke { target, name, arguments, .. } => (
+                format!("{}.{}({})", self.target(target), name, self.arguments(arguments)),
+                PRIMARY,
+            ),
+            Expression::New { class, arguments } => {
+                (format!("new {}({})", class, self.arguments(arguments)), PRIMARY)
+            }
+            Expression::NewArray {
+                element, dimensions, extra, elements: Some(elements), ..
+            } if dimensions.len() == 1 => {
+                let mut values = elements
+                    .iter()
+                    .map(|element| self.expression(element, ASSIGNMENT))
+                    .collect::<Vec<_>>();
+                let length = match &dimensions[0] {
+                    Expression::Literal(length) => length.parse::<usize>().unwrap_or_default(),
+                    _ => 0,
+                };
+                while values.len() < length {
+                    values.push(default_value(element, *extra));
+                }
+                (
+                    format!("new {}[]{} {{{}}}", element, "[]".repeat(*extra), values.join(", ")),
+                    PRIMARY,
+                )
+            }
+            Expression::NewArray { element, dimensions, extra, .. } => {
+                let dimensions = dimensions
+                    .iter()
+                    .map(|dimension| format!("[{}]", self.expression(dimension, LAMBDA)))
+                    .collect::<String>();
+                (format!("new {}{}{}", element, dimensions, "[]".repeat(*extra)), PRIMARY)
+            }
+            Expression::Ternary(condition, yes, no) => {
+                let condition = self.expression(condition, OR);
+                let (yes, no) = (self.expression(yes, TERNARY + 1), self.expression(no, TERNARY));
+                (format!("{} ? {} : {}", condition, yes, no), TERNARY)
+            }
+            Expression::PostIncrement(variable, delta) => {
+                let operator = if *delta > 0 { "++" } else { "--" };
+                (format!("{}{}", variable.name, operator), POSTFIX)
+            }
+            Expression::Assignment(variable, value) => {
+                (format!("{} = {}", variable.name, self.expression(value, ASSIGNMENT)), ASSIGNMENT)
+            }
+            Expression::Concat(parts) => {
+                let parts = parts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, part)| {
+                        self.expression(part, if index == 0 { ADDITIVE } else { ADDITIVE + 1 })
+                    })
+                    .collect::<Vec<_>>();
+                (parts.join(" + "), ADDITIVE)
+            }
+            Expression::Lambda { parameters, body } => (self.lambda(parameters, body), LAMBDA),
+            Expression::MethodReference(target, name) => {
+                (format!("{}::{}", self.target(target), name), PRIMARY)
+            }
+            Expression::Boolean(value) => self.boolean(value),
+            Expression::Dynamic { name, arguments } => {
+                (format!("{}({})", name, self.arguments(arguments)), PRIMARY)
+            }
+        }
+    }
+
+    fn boolean(&self, value: &Expression) -> (String, u8) {
+        let is = |expression: &Expression, text: &str| matches!(expression, Expression::Literal(found) if found == text);
+        match value {
+            Expression::Literal(text) if text == "0" => ("false".to_string(), PRIMARY),
+            Expression::Literal(text) if text == "1" => ("true".to_string(), PRIMARY),
+            Expression::Ternary(condition, yes, no) if is(yes, "1") && is(no, "0") => {
+                self.expression_text(condition)
+            }
+            Expression::Ternary(condition, yes, no) if is(yes, "0") && is(no, "1") => {
+                self.expression_text(&negate((**condition).clone()))
+            }
+            Expression::Ternary(condition, yes, no) => self.expression_text(&Expression::Ternary(
+                condition.clone(),
+                Box::new(coerce((**yes).clone(), "boolean")),
+                Box::new(coerce((**no).clone(), "boolean")),
+            )),
+            value => self.expression_text(value),
+        }
+    }
+
+    fn lambda(&self, parameters: &[String], body: &[Statement]) -> String {
+        let head = match parameters {
+            [single] => single.clone(),
+            parameters => format!("({})", parameters.join(", ")),
+        };
+        match body {
+            [Statement::Return(Some(value))] | [Statement::Expression(value)] => {
+                format!("{} -> {}", head, self.expression(value, LAMBDA))
+            }
+            body => {
+                let mut declared = self.declared.clone();
+                declared.extend(parameters.iter().cloned());
+                let mut printer = Printer::new(self.indent + 1, declared);
+                printer.statements(body);
+                format!("{} -> {{\n{}{}}}", head, printer.output, "    ".repeat(self.indent))
+            }
+        }
+    }
+}
+
+/// Collects the variables assigned in the statements and their nested scopes, in order.
+fn assigned_variables(statements: &[Statement], assigned: &mut Vec<Variable>) {
+    for statement in statements {
+        if let Statement::Assign(Expression::Variable(variable), _) = statement {
+            if !assigned.contains(variable) {
+                assigned.push(variable.clone());
+            }
+        }
+        for block in blocks(statement) {
+            assigned_variables(block, assigned);
+        }
+    }
+}
+
+/// Collects the variables assigned by assignment expressions, which cannot declare them.
+fn embedded_assignments(expression: &Expression, assigned: &mut Vec<Variable>) {
+    if let Expression::Assignment(variable, _) = expression {
+        if !assigned.contains(variable) {
+            assigned.push(variable.clone());
+        }
+    }
+    for child in children(expression) {
+        embedded_assignments(child, assigned);
+    }
+}
+
+fn label_prefix(label: &Option<String>) -> String {
+    label.as_ref().map_or_else(String::new, |label| format!("{}: ", label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flag::ClassFileAccessFlags;
+    use crate::class_file_version::ClassFileVersion;
+    use crate::method::MethodAccessFlags;
+
+    fn static_method(descriptor: &str, code: Vec<(Instruction, u32)>) -> (ClassFile, Method) {
+        let code = Code {
+            max_stack: 4,
+            max_locals: 2,
+            code,
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let method = Method {
+            flags: MethodAccessFlags::new(0x0008),
+            name: "m".to_string(),
+            type_descriptor: MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+                .unwrap(),
+            attributes: vec![Attribute::Code(code)],
+        };
+        let class = ClassFile {
+            version: ClassFileVersion::default(),
+            constant_pool: ConstantPool::default(),
+            flags: ClassFileAccessFlags::new(0x0021),
+            this_class: "p/T".to_string(),
+            super_class: Some("java/lang/Object".to_string()),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        (class, method)
+    }
+
+    fn variable(name: &str) -> Expression {
+        Expression::Variable(Variable {
+            name: name.to_string(),
+            ty: "int".to_string(),
+            synthetic: false,
+        })
+    }
+
+    fn binary(operator: &'static str, left: Expression, right: Expression) -> Expression {
+        Expression::Binary(operator, Box::new(left), Box::new(right))
+    }
+
+    #[test]
+    fn test_signatures() {
+        let names = TypeNames::new("p/Owner");
+        let signature = names
+            .method_signature(
+                "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>\
+                 (Ljava/util/Map<TK;+TV;>;[Lp/Owner$Inner;Ljava/util/List<*>;)TV;^TE;",
+            )
+            .unwrap();
+        assert_eq!(signature.type_parameters, "<K, V extends Comparable<? super V>>");
+        assert_eq!(
+            signature.parameters,
+            ["java.util.Map<K, ? extends V>", "Owner.Inner[]", "java.util.List<?>"]
+        );
+        assert_eq!(signature.exceptions, ["E"]);
+
+        let (type_parameters, superclass, interfaces) = names
+            .class_signature(
+                "<T:Ljava/lang/Number;>Lp/Base<TT;>.Node<TT;>;Ljava/lang/Iterable<TT;>;",
+            )
+            .unwrap();
+        assert_eq!(type_parameters, "<T extends Number>");
+        assert_eq!(superclass.as_deref(), Some("Base<T>.Node<T>"));
+        assert_eq!(interfaces, ["Iterable<T>"]);
+        assert!(names.method_signature("(Ljava/util/List<").is_none());
+    }
+
+    #[test]
+    fn test_loop_without_debug_info() {
+        let (class, method) = static_method(
+            "(I)I",
+            vec![
+                (Instruction::Iconst_0, 0),
+                (Instruction::Istore_1, 1),
+                (Instruction::Iload_0, 2),
+                (Instruction::Ifle(13), 3),
+                (Instruction::Iload_1, 6),
+                (Instruction::Iload_0, 7),
+                (Instruction::Iadd, 8),
+                (Instruction::Istore_1, 9),
+                (Instruction::Iinc(0, -1), 10),
+                (Instruction::Goto(-11i16 as u16), 13),
+                (Instruction::Iload_1, 16),
+                (Instruction::Ireturn, 17),
+            ],
+        );
+        assert_eq!(
+            decompile_method(&class, &method).unwrap(),
+            "static int m(int arg0) {\n    int var1 = 0;\n    while (arg0 > 0) {\n        \
+             var1 += arg0;\n        arg0--;\n    }\n    return var1;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_subroutines_are_rejected() {
+        let (class, method) = static_method(
+            "()V",
+            vec![
+                (Instruction::Jsr(4), 0),
+                (Instruction::Return, 3),
+                (Instruction::Astore_0, 4),
+                (Instruction::Ret(0), 5),
+            ],
+        );
+        assert_eq!(decompile_method(&class, &method), Err(DecompilerError::Subroutine(0)));
+    }
+
+    #[test]
+    fn test_negate_and_precedence() {
+        let condition = binary(
+            "&&",
+            binary("<", variable("a"), variable("b")),
+            Expression::Unary("!", Box::new(variable("c"))),
+        );
+        let printer = Printer::new(0, HashSet::new());
+        assert_eq!(printer.expression(&negate(condition), LAMBDA), "a >= b || c");
+
+        let sum = binary("+", variable("a"), variable("b"));
+        let product = binary("*", sum, variable("c"));
+        assert_eq!(printer.expression(&product, LAMBDA), "(a + b) * c");
+        let difference = binary("-", variable("a"), binary("-", variable("b"), variable("c")));
+        assert_eq!(printer.expression(&difference, LAMBDA), "a - (b - c)");
+    }
+}
@@ -0,0 +1,799 @@
+//! Static dependency analysis in the style of `jdeps`.
+//!
+//! Every type a class refers to is collected from its `Class` constants, the descriptors of
+//! its members and of the members it references, its generic signatures, its annotations
+//! and its exception tables and `throws` clauses. The dependencies can then be aggregated
+//! by class, package or archive, searched for cycles, and checked against
+//! [`DependencyRules`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter, Write};
+use std::iter::{Peekable, from_fn};
+use std::str::Chars;
+
+use thiserror::Error;
+
+use crate::attribute::Attribute;
+use crate::byte_reader::{ByteReader, ReadError};
+use crate::class_file::ClassFile;
+use crate::constant_pool::{Constant, ConstantPool};
+
+#[derive(Debug, Error)]
+pub enum RulesError {
+    #[error("Line {line}: {message}")]
+    #[non_exhaustive]
+    Syntax { line: usize, message: String },
+}
+
+type Result<T> = std::result::Result<T, RulesError>;
+
+/// The archive of classes that were referenced but not analysed.
+pub const NOT_FOUND: &str = "not found";
+
+/// The package name used for classes in the unnamed package.
+pub const UNNAMED_PACKAGE: &str = "<unnamed>";
+
+const ANNOTATION_ATTRIBUTES: [&str; 7] = [
+    "RuntimeVisibleAnnotations",
+    "RuntimeInvisibleAnnotations",
+    "RuntimeVisibleParameterAnnotations",
+    "RuntimeInvisibleParameterAnnotations",
+    "RuntimeVisibleTypeAnnotations",
+    "RuntimeInvisibleTypeAnnotations",
+    "AnnotationDefault",
+];
+
+/// Where in a class file a reference to another type was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ReferenceKind {
+    /// A `Class` constant, such as a supertype, an instantiated type or a member's owner.
+    Constant,
+    /// The descriptor of a declared member, or of a referenced member or method type.
+    Descriptor,
+    /// A generic `Signature` attribute.
+    Signature,
+    /// An annotation type, or a class or enum type used as an annotation element.
+    Annotation,
+    /// A caught exception type or a type declared in a `throws` clause.
+    Exception,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Granularity {
+    #[default]
+    Class,
+    Package,
+    /// The jar, module or directory a class was read from.
+    Archive,
+}
+
+/// The types referenced by one class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassDependencies {
+    pub name: String,
+    pub archive: String,
+    /// Referenced types by internal name, with the places they were referenced from. Array
+    /// types are reduced to their element types and the class itself is left out.
+    pub dependencies: BTreeMap<String, BTreeSet<ReferenceKind>>,
+}
+
+impl ClassDependencies {
+    pub fn new(class: &ClassFile, archive: &str) -> Self {
+        let mut collector = Collector {
+            constant_pool: &class.constant_pool,
+            this_class: &class.this_class,
+            dependencies: BTreeMap::new(),
+        };
+        collector.constant_pool();
+        for field in &class.fields {
+            collector.descriptor(&field.type_descriptor.to_string(), ReferenceKind::Descriptor);
+            collector.attributes(&field.attributes);
+        }
+        for method in &class.methods {
+            let descriptor = &method.type_descriptor;
+            for parameter in descriptor.parameters() {
+                collector.descriptor(&parameter.to_string(), ReferenceKind::Descriptor);
+            }
+            collector.descriptor(&descriptor.return_type().to_string(), ReferenceKind::Descriptor);
+            collector.attributes(&method.attributes);
+        }
+        collector.attributes(&class.attributes);
+        ClassDependencies {
+            name: class.this_class.clone(),
+            archive: archive.to_string(),
+            dependencies: collector.dependencies,
+        }
+    }
+}
+
+/// Collects the dependencies of a set of classes.
+///
+/// ```ignore
+/// let mut analyzer = DependencyAnalyzer::new();
+/// analyzer.exclude("java/");
+/// for class in &classes {
+///     analyzer.add_class(class, "app.jar");
+/// }
+/// let report = analyzer.analyze();
+/// println!("{}", report.graph(Granularity::Package));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DependencyAnalyzer {
+    classes: BTreeMap<String, ClassDependencies>,
+    excluded: Vec<String>,
+}
+
+impl DependencyAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out referenced types whose internal name starts with `prefix`, e.g. `java/` to
+    /// hide dependencies on the platform.
+    pub fn exclude(&mut self, prefix: &str) -> &mut Self {
+        self.excluded.push(prefix.to_string());
+        self
+    }
+
+    /// Adds a class read from `archive`. When the same class is added twice, the first one
+    /// is kept, as on a class path.
+    pub fn add_class(&mut self, class: &ClassFile, archive: &str) -> &mut Self {
+        if !self.classes.contains_key(&class.this_class) {
+            let dependencies = ClassDependencies::new(class, archive);
+            self.classes.insert(class.this_class.clone(), dependencies);
+        }
+        self
+    }
+
+    pub fn analyze(&self) -> DependencyReport {
+        let classes = self
+            .classes
+            .values()
+            .map(|class| {
+                let mut class = class.clone();
+                class.dependencies.retain(|name, _| {
+                    !self.excluded.iter().any(|prefix| name.starts_with(prefix.as_str()))
+                });
+                class
+            })
+            .collect();
+        DependencyReport { classes }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DependencyReport {
+    /// The analysed classes ordered by name.
+    pub classes: Vec<ClassDependencies>,
+}
+
+impl DependencyReport {
+    pub fn class(&self, name: &str) -> Option<&ClassDependencies> {
+        self.classes
+            .binary_search_by(|class| class.name.as_str().cmp(name))
+            .ok()
+            .map(|index| &self.classes[index])
+    }
+
+    /// The archive `class` was read from, [`NOT_FOUND`] if it was not analysed.
+    pub fn archive_of(&self, class: &str) -> &str {
+        self.class(class).map_or(NOT_FOUND, |class| class.archive.as_str())
+    }
+
+    /// The dependencies aggregated at `granularity`. Dependencies within a package or archive
+    /// are left out of the aggregated graphs.
+    pub fn graph(&self, granularity: Granularity) -> DependencyGraph {
+        let node = |class: &str| match granularity {
+            Granularity::Class => class.to_string(),
+            Granularity::Package => package_of(class).to_string(),
+            Granularity::Archive => self.archive_of(class).to_string(),
+        };
+        let mut graph = DependencyGraph { granularity, ..DependencyGraph::default() };
+        for class in &self.classes {
+            let from = node(&class.name);
+            graph.nodes.insert(from.clone());
+            for dependency in class.dependencies.keys() {
+                let to = node(dependency);
+                graph.nodes.insert(to.clone());
+                if to != from {
+                    graph.edges.entry(from.clone()).or_default().insert(to);
+                }
+            }
+        }
+        graph
+    }
+
+    /// The groups of packages that depend on each other.
+    pub fn package_cycles(&self) -> Vec<Vec<String>> {
+        self.graph(Granularity::Package).cycles()
+    }
+}
+
+/// The package of a class in internal form, [`UNNAMED_PACKAGE`] for the unnamed package.
+pub fn package_of(class: &str) -> &str {
+    class.rsplit_once('/').map_or(UNNAMED_PACKAGE, |(package, _)| package)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DependencyGraph {
+    pub granularity: Granularity,
+    /// Every node, including the ones that are only depended upon.
+    pub nodes: BTreeSet<String>,
+    pub edges: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    pub fn dependencies(&self, node: &str) -> Vec<&str> {
+        self.edges.get(node).map_or_else(Vec::new, |to| to.iter().map(String::as_str).collect())
+    }
+
+    /// The strongly connected components of more than one node, each sorted, found with
+    /// Tarjan's algorithm.
+    pub fn cycles(&self) -> Vec<Vec<String>> {
+        let nodes = self.nodes.iter().collect::<Vec<_>>();
+        let index_of = |name: &String| nodes.binary_search(&name).unwrap();
+        let successors = nodes
+            .iter()
+            .map(|node| match self.edges.get(*node) {
+                Some(to) => to.iter().map(index_of).collect(),
+                None => Vec::new(),
+            })
+            .collect::<Vec<Vec<usize>>>();
+
+        let mut tarjan = Tarjan {
+            successors: &successors,
+            index: vec![None; nodes.len()],
+            low_link: vec![0; nodes.len()],
+            on_stack: vec![false; nodes.len()],
+            stack: Vec::new(),
+            next_index: 0,
+            components: Vec::new(),
+        };
+        for node in 0..nodes.len() {
+            if tarjan.index[node].is_none() {
+                tarjan.connect(node);
+            }
+        }
+        let mut cycles = tarjan
+            .components
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut names =
+                    component.into_iter().map(|node| nodes[node].clone()).collect::<Vec<_>>();
+                names.sort();
+                names
+            })
+            .collect::<Vec<_>>();
+        cycles.sort();
+        cycles
+    }
+
+    /// Graphviz source with one node per class, package or archive.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            writeln!(dot, "    {:?};", node).unwrap();
+        }
+        for (from, to) in &self.edges {
+            for to in to {
+                writeln!(dot, "    {:?} -> {:?};", from, to).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Display for DependencyGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (from, to) in &self.edges {
+            for to in to {
+                writeln!(f, "{} -> {}", from, to)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Tarjan<'a> {
+    successors: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low_link: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next_index: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    /// Iterative so that deep package chains cannot overflow the call stack.
+    fn connect(&mut self, root: usize) {
+        let mut work = vec![(root, 0)];
+        self.visit(root);
+        while let Some((node, next)) = work.pop() {
+            if let Some(&successor) = self.successors[node].get(next) {
+                work.push((node, next + 1));
+                match self.index[successor] {
+                    None => {
+                        self.visit(successor);
+                        work.push((successor, 0));
+                    }
+                    Some(index) if self.on_stack[successor] => {
+                        self.low_link[node] = self.low_link[node].min(index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            if let Some(&(parent, _)) = work.last() {
+                self.low_link[parent] = self.low_link[parent].min(self.low_link[node]);
+            }
+            if Some(self.low_link[node]) == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next_index);
+        self.low_link[node] = self.next_index;
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+    }
+}
+
+/// Dependencies that are not allowed, read from a rules file such as
+///
+/// ```text
+/// # The domain must not know about the web layer...
+/// deny com.example.domain.** -> com.example.web.**
+/// # ...except for its data transfer objects.
+/// allow com.example.domain.** -> com.example.web.dto.*
+/// ```
+///
+/// A class dependency is a violation when it matches a `deny` rule and no `allow` rule.
+/// Patterns match class names written with dots or slashes: `*` matches within one package
+/// level and `**` matches across levels, so `a.b.*` matches the classes of package `a.b` and
+/// `a.b.**` also matches those of its subpackages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    from: String,
+    to: String,
+    text: String,
+}
+
+impl DependencyRules {
+    pub fn parse(rules: &str) -> Result<Self> {
+        let mut parsed = Vec::new();
+        for (number, line) in rules.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let syntax_error =
+                |message: &str| RulesError::Syntax { line: number + 1, message: message.into() };
+            let (directive, rule) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let allow = match directive {
+                "allow" => true,
+                "deny" => false,
+                _ => return Err(syntax_error("expected `allow` or `deny`")),
+            };
+            let (from, to) = rule.split_once("->").ok_or_else(|| syntax_error("expected `->`"))?;
+            let (from, to) = (from.trim(), to.trim());
+            let is_pattern =
+                |pattern: &str| !pattern.is_empty() && !pattern.contains(char::is_whitespace);
+            if !is_pattern(from) || !is_pattern(to) {
+                return Err(syntax_error("expected one class pattern on each side of `->`"));
+            }
+            parsed.push(Rule {
+                allow,
+                from: from.replace('.', "/"),
+                to: to.replace('.', "/"),
+                text: line.to_string(),
+            });
+        }
+        Ok(DependencyRules { rules: parsed })
+    }
+
+    /// The class dependencies of `report` that the rules deny, in class order.
+    pub fn check(&self, report: &DependencyReport) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for class in &report.classes {
+            for (dependency, kinds) in &class.dependencies {
+                let matching = |rule: &&Rule| {
+                    matches_pattern(&rule.from, &class.name)
+                        && matches_pattern(&rule.to, dependency)
+                };
+                if self.rules.iter().filter(|rule| rule.allow).any(|rule| matching(&rule)) {
+                    continue;
+                }
+                if let Some(rule) = self.rules.iter().filter(|rule| !rule.allow).find(matching) {
+                    violations.push(Violation {
+                        rule: rule.text.clone(),
+                        from: class.name.clone(),
+                        to: dependency.clone(),
+                        kinds: kinds.clone(),
+                    });
+                }
+            }
+        }
+        violations
+    }
+}
+
+/// A class dependency denied by a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violation {
+    /// The line of the rules file that denies the dependency.
+    pub rule: String,
+    pub from: String,
+    pub to: String,
+    pub kinds: BTreeSet<ReferenceKind>,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} violates `{}`", self.from, self.to, self.rule)
+    }
+}
+
+/// Glob matching of an internal class name, where `*` stops at `/` and `**` does not.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        return (0..=name.len())
+            .any(|skip| name.is_char_boundary(skip) && matches_pattern(rest, &name[skip..]));
+    }
+    if let Some(rest) = pattern.strip_prefix('*') {
+        let segment = name.find('/').unwrap_or(name.len());
+        return (0..=segment)
+            .any(|skip| name.is_char_boundary(skip) && matches_pattern(rest, &name[skip..]));
+    }
+    match (pattern.chars().next(), name.chars().next()) {
+        (None, None) => true,
+        (Some(expected), Some(actual)) if expected == actual => {
+            matches_pattern(&pattern[expected.len_utf8()..], &name[actual.len_utf8()..])
+        }
+        _ => false,
+    }
+}
+
+struct Collector<'a> {
+    constant_pool: &'a ConstantPool,
+    this_class: &'a str,
+    dependencies: BTreeMap<String, BTreeSet<ReferenceKind>>,
+}
+
+impl Collector<'_> {
+    fn add(&mut self, name: &str, kind: ReferenceKind) {
+        if name != self.this_class && !name.is_empty() {
+            self.dependencies.entry(name.to_string()).or_default().insert(kind);
+        }
+    }
+
+    /// Adds a `Class` constant, which names an array type by its descriptor.
+    fn class(&mut self, name: &str, kind: ReferenceKind) {
+        match name.starts_with('[') {
+            true => self.descriptor(name, kind),
+            false => self.add(name, kind),
+        }
+    }
+
+    /// Adds the class types of a field or method descriptor.
+    fn descriptor(&mut self, descriptor: &str, kind: ReferenceKind) {
+        let mut chars = descriptor.chars();
+        while let Some(char) = chars.next() {
+            if char == 'L' {
+                let name = chars.by_ref().take_while(|&char| char != ';').collect::<String>();
+                self.add(&name, kind);
+            }
+        }
+    }
+
+    fn constant_pool(&mut self) {
+        let constant_pool = self.constant_pool;
+        for (index, constant) in constant_pool.constants.iter().enumerate() {
+            match constant {
+                Constant::ClassIndex(_) => {
+                    if let Some(name) = constant_pool.class_name(index + 1) {
+                        self.class(name, ReferenceKind::Constant);
+                    }
+                }
+                Constant::NameAndType(..) => {
+                    if let Some((_, descriptor)) = constant_pool.name_and_type(index + 1) {
+                        self.descriptor(descriptor, ReferenceKind::Descriptor);
+                    }
+                }
+                Constant::MethodType(descriptor_index) => {
+                    if let Some(descriptor) = constant_pool.utf8(*descriptor_index as usize) {
+                        self.descriptor(descriptor, ReferenceKind::Descriptor);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        for attribute in attributes {
+            match attribute {
+                Attribute::Code(code) => {
+                    for handler in &code.exception_table {
+                        if let Some(name) =
+                            self.constant_pool.class_name(handler.catch_type as usize)
+                        {
+                            self.class(name, ReferenceKind::Exception);
+                        }
+                    }
+                    self.attributes(&code.attributes);
+                }
+                Attribute::UserDefined(attribute) => {
+                    let mut reader = ByteReader::new(attribute.info());
+                    // Malformed attributes contribute the types read before the error.
+                    let _ = match attribute.name() {
+                        "Signature" => self.signature(&mut reader),
+                        "Exceptions" => self.exceptions(&mut reader),
+                        name if ANNOTATION_ATTRIBUTES.contains(&name) => {
+                            self.annotation_attribute(name, &mut reader)
+                        }
+                        _ => Ok(()),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn signature(&mut self, reader: &mut ByteReader) -> std::result::Result<(), ReadError> {
+        let signature_index = reader.read_u16()?;
+        if let Some(signature) = self.constant_pool.utf8(signature_index as usize) {
+            for name in signature_types(signature) {
+                self.add(&name, ReferenceKind::Signature);
+            }
+        }
+        Ok(())
+    }
+
+    fn exceptions(&mut self, reader: &mut ByteReader) -> std::result::Result<(), ReadError> {
+        for _ in 0..reader.read_u16()? {
+            if let Some(name) = self.constant_pool.class_name(reader.read_u16()? as usize) {
+                self.class(name, ReferenceKind::Exception);
+            }
+        }
+        Ok(())
+    }
+
+    fn annotation_attribute(
+        &mut self,
+        name: &str,
+        reader: &mut ByteReader,
+    ) -> std::result::Result<(), ReadError> {
+        match name {
+            "AnnotationDefault" => self.element_value(reader),
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                for _ in 0..reader.read_u8()? {
+                    for _ in 0..reader.read_u16()? {
+                        self.annotation(reader)?;
+                    }
+                }
+                Ok(())
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                for _ in 0..reader.read_u16()? {
+                    skip_type_annotation_target(reader)?;
+                    self.annotation(reader)?;
+                }
+                Ok(())
+            }
+            _ => {
+                for _ in 0..reader.read_u16()? {
+                    self.annotation(reader)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn annotation(&mut self, reader: &mut ByteReader) -> std::result::Result<(), ReadError> {
+        self.utf8_descriptor(reader.read_u16()?);
+        for _ in 0..reader.read_u16()? {
+            reader.read_u16()?;
+            self.element_value(reader)?;
+        }
+        Ok(())
+    }
+
+    fn element_value(&mut self, reader: &mut ByteReader) -> std::result::Result<(), ReadError> {
+        match reader.read_u8()? {
+            b'e' => {
+                self.utf8_descriptor(reader.read_u16()?);
+                reader.read_u16()?;
+            }
+            b'c' => self.utf8_descriptor(reader.read_u16()?),
+            b'@' => self.annotation(reader)?,
+            b'[' => {
+                for _ in 0..reader.read_u16()? {
+                    self.element_value(reader)?;
+                }
+            }
+            _ => {
+                reader.read_u16()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn utf8_descriptor(&mut self, index: u16) {
+        if let Some(descriptor) = self.constant_pool.utf8(index as usize) {
+            self.descriptor(descriptor, ReferenceKind::Annotation);
+        }
+    }
+}
+
+/// Skips the `target_type`, `target_info` and `type_path` that precede the annotation of a
+/// `type_annotation` structure.
+fn skip_type_annotation_target(reader: &mut ByteReader) -> std::result::Result<(), ReadError> {
+    let target_info_length = match reader.read_u8()? {
+        0x00 | 0x01 | 0x16 => 1,
+        0x10 | 0x11 | 0x12 | 0x17 | 0x42..=0x46 => 2,
+        0x13..=0x15 => 0,
+        0x40 | 0x41 => 6 * reader.read_u16()? as usize,
+        _ => 3,
+    };
+    reader.read_bytes(target_info_length)?;
+    let path_length = reader.read_u8()?;
+    reader.read_bytes(2 * path_length as usize)?;
+    Ok(())
+}
+
+/// The class types of a class, method or field signature, with inner classes named by their
+/// binary names, e.g. `java/util/Map$Entry` for `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+fn signature_types(signature: &str) -> Vec<String> {
+    let mut chars = signature.chars().peekable();
+    let mut types = Vec::new();
+    if chars.next_if_eq(&'<').is_some() {
+        // Type parameters: an identifier followed by an optional class bound and any number
+        // of interface bounds, each introduced by a colon.
+        while chars.next_if(|&char| char != '>').is_some() {
+            from_fn(|| chars.next_if(|&char| char != ':')).for_each(drop);
+            while chars.next_if_eq(&':').is_some() {
+                if matches!(chars.peek(), Some('L' | 'T' | '[')) {
+                    reference_type(&mut chars, &mut types);
+                }
+            }
+        }
+        chars.next();
+    }
+    while let Some(&char) = chars.peek() {
+        match char {
+            'L' | 'T' | '[' => reference_type(&mut chars, &mut types),
+            _ => {
+                chars.next();
+            }
+        }
+    }
+    types
+}
+
+fn reference_type(chars: &mut Peekable<Chars>, types: &mut Vec<String>) {
+    let identifier = |chars: &mut Peekable<Chars>| {
+        from_fn(|| chars.next_if(|char| !matches!(char, '<' | '.' | ';'))).collect::<String>()
+    };
+    match chars.next() {
+        Some('L') => {
+            let mut name = identifier(chars);
+            loop {
+                match chars.next() {
+                    Some('<') => type_arguments(chars, types),
+                    Some('.') => {
+                        name.push('$');
+                        name.push_str(&identifier(chars));
+                    }
+                    _ => break,
+                }
+            }
+            types.push(name);
+        }
+        Some('T') => {
+            from_fn(|| chars.next_if(|&char| char != ';')).for_each(drop);
+            chars.next();
+        }
+        Some('[') => match chars.peek() {
+            Some('L' | 'T' | '[') => reference_type(chars, types),
+            _ => {
+                chars.next();
+            }
+        },
+        _ => {}
+    }
+}
+
+fn type_arguments(chars: &mut Peekable<Chars>, types: &mut Vec<String>) {
+    while let Some(&char) = chars.peek() {
+        match char {
+            '>' => {
+                chars.next();
+                return;
+            }
+            '*' | '+' | '-' => {
+                chars.next();
+            }
+            _ => reference_type(chars, types),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_types() {
+        assert_eq!(
+            signature_types(
+                "<K:Ljava/lang/Object;LT::Ljava/lang/Comparable<-TLT;>;>Ljava/util/AbstractMap<TK;[TLT;>;Ljava/io/Serializable;"
+            ),
+            vec![
+                "java/lang/Object",
+                "java/lang/Comparable",
+                "java/util/AbstractMap",
+                "java/io/Serializable"
+            ]
+        );
+        assert_eq!(
+            signature_types(
+                "<T:Ljava/lang/Exception;>(Ljava/util/Map<TT;*>.Entry<[I+Ljava/lang/Number;>;)V^TT;^Ljava/io/IOException;"
+            ),
+            vec![
+                "java/lang/Exception",
+                "java/lang/Number",
+                "java/util/Map$Entry",
+                "java/io/IOException"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("com/example/**", "com/example/a/b/C"));
+        assert!(matches_pattern("com/example/*", "com/example/C$Inner"));
+        assert!(!matches_pattern("com/example/*", "com/example/a/C"));
+        assert!(matches_pattern("**/internal/**", "com/example/internal/C"));
+        assert!(matches_pattern("com/example/*Impl", "com/example/ServiceImpl"));
+        assert!(!matches_pattern("com/example/*Impl", "com/example/Service"));
+    }
+
+    #[test]
+    fn test_cycles() {
+        let mut graph = DependencyGraph::default();
+        for (from, to) in [("a", "b"), ("b", "c"), ("c", "a"), ("c", "d"), ("d", "e"), ("e", "d")] {
+            graph.nodes.insert(from.to_string());
+            graph.nodes.insert(to.to_string());
+            graph.edges.entry(from.to_string()).or_default().insert(to.to_string());
+        }
+        assert_eq!(graph.cycles(), vec![vec!["a", "b", "c"], vec!["d", "e"]]);
+    }
+}
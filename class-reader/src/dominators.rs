@@ -0,0 +1,261 @@
+//! Dominator and post-dominator trees of [`ControlFlowGraph`]s, and dominance frontiers.
+//!
+//! Trees are computed with the iterative algorithm of Cooper, Harvey and Kennedy, "A Simple,
+//! Fast Dominance Algorithm". Post-dominators are computed on the reversed graph from a
+//! virtual exit that follows every block without successors, so a block whose immediate
+//! post-dominator is that exit has none.
+
+use std::collections::BTreeSet;
+
+use crate::control_flow::{BlockId, ControlFlowGraph};
+
+/// The dominator or post-dominator tree of a control-flow graph. Blocks that are not
+/// reachable from the entry, or for post-dominators cannot reach an exit, are not part of
+/// the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DominatorTree {
+    post_dominators: bool,
+    immediate_dominators: Vec<Option<BlockId>>,
+    reachable: Vec<bool>,
+    children: Vec<Vec<BlockId>>,
+    roots: Vec<BlockId>,
+    /// Entry and exit times of a depth-first walk of the tree, for constant time ancestor
+    /// queries.
+    preorder: Vec<usize>,
+    postorder: Vec<usize>,
+}
+
+impl DominatorTree {
+    pub fn new(graph: &ControlFlowGraph) -> Self {
+        let successors = (0..graph.len()).map(|block| graph.successors(block).to_vec()).collect();
+        Self::compute(successors, graph.entry(), false)
+    }
+
+    /// The dominator tree of the graph whose nodes have the given successors, rooted at
+    /// `entry`.
+    pub(crate) fn from_successors(successors: Vec<Vec<usize>>, entry: usize) -> Self {
+        Self::compute(successors, entry, false)
+    }
+
+    pub fn post_dominators(graph: &ControlFlowGraph) -> Self {
+        let successors =
+            (0..graph.len()).map(|block| graph.successors(block).to_vec()).collect::<Vec<_>>();
+        Self::post_dominators_from_successors(&successors)
+    }
+
+    /// The post-dominator tree of the graph whose nodes have the given distinct successors.
+    pub(crate) fn post_dominators_from_successors(successors: &[Vec<usize>]) -> Self {
+        let exit = successors.len();
+        let mut reversed = vec![Vec::new(); exit + 1];
+        for (node, targets) in successors.iter().enumerate() {
+            for &target in targets {
+                reversed[target].push(node);
+            }
+            if targets.is_empty() {
+                reversed[exit].push(node);
+            }
+        }
+        Self::compute(reversed, exit, true)
+    }
+
+    /// Computes the tree of the graph given by `successors` rooted at `root`. The root of a
+    /// post-dominator tree is the virtual exit, which is left out of the result.
+    fn compute(successors: Vec<Vec<usize>>, root: usize, post_dominators: bool) -> Self {
+        let count = successors.len();
+        let mut predecessors = vec![Vec::new(); count];
+        for (node, successors) in successors.iter().enumerate() {
+            for &successor in successors {
+                predecessors[successor].push(node);
+            }
+        }
+        let order = reverse_postorder(&successors, root);
+        let mut order_index = vec![usize::MAX; count];
+        for (index, &node) in order.iter().enumerate() {
+            order_index[node] = index;
+        }
+
+        let mut idoms = vec![None; count];
+        idoms[root] = Some(root);
+        let intersect = |idoms: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while order_index[a] > order_index[b] {
+                    a = idoms[a].unwrap();
+                }
+                while order_index[b] > order_index[a] {
+                    b = idoms[b].unwrap();
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in &order[1..] {
+                let mut processed = predecessors[node].iter().filter(|&&p| idoms[p].is_some());
+                let first = *processed.next().unwrap();
+                let idom = processed.fold(first, |idom, &p| intersect(&idoms, p, idom));
+                if idoms[node] != Some(idom) {
+                    idoms[node] = Some(idom);
+                    changed = true;
+                }
+            }
+        }
+
+        let blocks = if post_dominators { count - 1 } else { count };
+        let reachable = (0..blocks).map(|block| idoms[block].is_some()).collect::<Vec<_>>();
+        let immediate_dominators = (0..blocks)
+            .map(|block| {
+                // The entry is its own immediate dominator and the exit is not a block.
+                idoms[block].filter(|&idom| idom != block && !(post_dominators && idom == root))
+            })
+            .collect::<Vec<_>>();
+        let mut children = vec![Vec::new(); blocks];
+        let mut roots = Vec::new();
+        for &node in &order {
+            if node == root && post_dominators {
+                continue;
+            }
+            match immediate_dominators[node] {
+                Some(idom) => children[idom].push(node),
+                None => roots.push(node),
+            }
+        }
+
+        roots.sort();
+        let mut tree = DominatorTree {
+            post_dominators,
+            immediate_dominators,
+            reachable,
+            children,
+            roots,
+            preorder: vec![0; blocks],
+            postorder: vec![0; blocks],
+        };
+        tree.number();
+        tree
+    }
+
+    fn number(&mut self) {
+        let mut clock = 0;
+        let mut stack = self.roots.iter().rev().map(|&root| (root, false)).collect::<Vec<_>>();
+        while let Some((block, finished)) = stack.pop() {
+            clock += 1;
+            if finished {
+                self.postorder[block] = clock;
+                continue;
+            }
+            self.preorder[block] = clock;
+            stack.push((block, true));
+            stack.extend(self.children[block].iter().rev().map(|&child| (child, false)));
+        }
+    }
+
+    pub fn is_post_dominator_tree(&self) -> bool {
+        self.post_dominators
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.reachable[block]
+    }
+
+    /// The closest strict dominator of `block`. `None` for the entry block, unreachable
+    /// blocks and, in a post-dominator tree, blocks only post-dominated by the exit.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.immediate_dominators[block]
+    }
+
+    /// The blocks immediately dominated by `block`, in reverse postorder.
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block]
+    }
+
+    /// The blocks without an immediate dominator in block order: the entry block, or for
+    /// post-dominators the blocks immediately post-dominated by the exit.
+    pub fn roots(&self) -> &[BlockId] {
+        &self.roots
+    }
+
+    /// Whether every path from the entry to `b` goes through `a`, or for post-dominators
+    /// every path from `b` to the exit. Every block dominates itself.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        self.reachable[a]
+            && self.reachable[b]
+            && self.preorder[a] <= self.preorder[b]
+            && self.postorder[b] <= self.postorder[a]
+    }
+
+    pub fn strictly_dominates(&self, a: BlockId, b: BlockId) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// The dominators of `block` from the closest to the root of its tree, without `block`.
+    pub fn dominators(&self, block: BlockId) -> Vec<BlockId> {
+        let mut dominators = Vec::new();
+        let mut current = self.immediate_dominators[block];
+        while let Some(dominator) = current {
+            dominators.push(dominator);
+            current = self.immediate_dominators[dominator];
+        }
+        dominators
+    }
+
+    /// The dominance frontier of every block: the blocks where its dominance ends, which are
+    /// where SSA form places phi functions for the variables it assigns. The frontiers of a
+    /// post-dominator tree give control dependences: a block is control dependent on the
+    /// blocks of its post-dominance frontier.
+    pub fn dominance_frontiers(&self, graph: &ControlFlowGraph) -> Vec<BTreeSet<BlockId>> {
+        self.frontiers(|block| match self.post_dominators {
+            true => graph.successors(block),
+            false => graph.predecessors(block),
+        })
+    }
+
+    /// The dominance frontiers given the predecessors of each block in the direction of the
+    /// tree.
+    pub(crate) fn frontiers<'a>(
+        &self,
+        predecessors: impl Fn(BlockId) -> &'a [BlockId],
+    ) -> Vec<BTreeSet<BlockId>> {
+        let mut frontiers = vec![BTreeSet::new(); self.reachable.len()];
+        for block in (0..frontiers.len()).filter(|&block| self.reachable[block]) {
+            let predecessors =
+                predecessors(block).iter().filter(|&&p| self.reachable[p]).collect::<Vec<_>>();
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for &predecessor in predecessors {
+                let mut runner = Some(predecessor);
+                while let Some(current) = runner {
+                    if runner == self.immediate_dominators[block] {
+                        break;
+                    }
+                    frontiers[current].insert(block);
+                    runner = self.immediate_dominators[current];
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// Reverse postorder of the nodes reachable from `root`.
+pub(crate) fn reverse_postorder(successors: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut visited = vec![false; successors.len()];
+    let mut postorder = Vec::new();
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while let Some((node, next)) = stack.pop() {
+        match successors[node].get(next) {
+            Some(&successor) => {
+                stack.push((node, next + 1));
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            }
+            None => postorder.push(node),
+        }
+    }
+    postorder.reverse();
+    postorder
+}
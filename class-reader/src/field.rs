@@ -1,6 +1,8 @@
+use std::fmt::Display;
 use std::iter::{Peekable, from_fn};
 use std::str::Chars;
 
+use crate::access_flag::{AccessFlags, Flag};
 use crate::attribute::Attribute;
 
 #[derive(Debug, thiserror::Error)]
@@ -17,11 +19,12 @@ pub enum FieldError {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Field {
-    flags: FieldAccessFlags,
-    name: String,
-    type_descriptor: FieldType,
-    attributes: Vec<Attribute>,
+    pub flags: FieldAccessFlags,
+    pub name: String,
+    pub type_descriptor: FieldType,
+    pub attributes: Vec<Attribute>,
 }
 
 impl Field {
@@ -34,7 +37,7 @@ impl Field {
         Field { flags, name, type_descriptor, attributes }
     }
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccessFlag {
     Public,
     Private,
@@ -47,7 +50,7 @@ pub enum AccessFlag {
     Enum,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FieldAccessFlags {
     flags: Vec<AccessFlag>,
 }
@@ -94,9 +97,52 @@ impl FieldAccessFlags {
 
         FieldAccessFlags { flags }
     }
+
+    pub fn is_static(&self) -> bool {
+        self.contains(AccessFlag::Static)
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl AccessFlags for FieldAccessFlags {
+    type Flag = AccessFlag;
+
+    fn flags(&self) -> &[AccessFlag] {
+        &self.flags
+    }
+}
+
+impl Flag for AccessFlag {
+    fn mask(&self) -> u16 {
+        match self {
+            AccessFlag::Public => 0x0001,
+            AccessFlag::Private => 0x0002,
+            AccessFlag::Protected => 0x0004,
+            AccessFlag::Static => 0x0008,
+            AccessFlag::Final => 0x0010,
+            AccessFlag::Volatile => 0x0040,
+            AccessFlag::Transient => 0x0080,
+            AccessFlag::Synthetic => 0x1000,
+            AccessFlag::Enum => 0x4000,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            AccessFlag::Public => "public",
+            AccessFlag::Private => "private",
+            AccessFlag::Protected => "protected",
+            AccessFlag::Static => "static",
+            AccessFlag::Final => "final",
+            AccessFlag::Volatile => "volatile",
+            AccessFlag::Transient => "transient",
+            AccessFlag::Synthetic => "synthetic",
+            AccessFlag::Enum => "enum",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BaseType {
     Byte,
     Char,
@@ -141,6 +187,40 @@ impl FieldType {
             _ => Err(FieldError::InvalidDescriptor),
         }
     }
+
+    /// The number of local variable or operand stack slots a value of this type takes: 2 for
+    /// long and double values.
+    pub fn slots(&self) -> u16 {
+        match self {
+            FieldType::Base(BaseType::Long | BaseType::Double) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl BaseType {
+    pub fn descriptor(&self) -> char {
+        match self {
+            BaseType::Byte => 'B',
+            BaseType::Char => 'C',
+            BaseType::Double => 'D',
+            BaseType::Float => 'F',
+            BaseType::Int => 'I',
+            BaseType::Long => 'J',
+            BaseType::Short => 'S',
+            BaseType::Boolean => 'Z',
+        }
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Base(base_type) => write!(f, "{}", base_type.descriptor()),
+            FieldType::Object(class_name) => write!(f, "L{};", class_name),
+            FieldType::Array(element_type) => write!(f, "[{}", element_type),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +252,11 @@ mod tests {
             ))))))
         );
     }
+
+    #[test]
+    fn descriptor_display_round_trip() {
+        let descriptor = "[[Ljava/lang/String;";
+        let field_type = FieldType::try_from(&mut descriptor.chars().peekable()).unwrap();
+        assert_eq!(field_type.to_string(), descriptor);
+    }
 }
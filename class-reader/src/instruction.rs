@@ -1,5 +1,11 @@
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::dataflow::ValueType;
+use crate::field::FieldType;
+use crate::method::{MethodDescriptor, ReturnDescriptor};
+
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     Aaload,
     Aastore,
@@ -86,7 +92,7 @@ pub enum Instruction {
     Getfield(u16),
     Getstatic(u16),
     Goto(u16),
-    Goto_w(u16, u16),
+    Goto_w(i32),
     I2b,
     I2c,
     I2d,
@@ -149,7 +155,7 @@ pub enum Instruction {
     Iushr,
     Ixor,
     Jsr(u16),
-    Jsr_w(u16, u16),
+    Jsr_w(i32),
     L2d,
     L2f,
     L2i,
@@ -171,7 +177,7 @@ pub enum Instruction {
     Lload_3,
     Lmul,
     Lneg,
-    Lookupswitch,
+    Lookupswitch { default: i32, pairs: Vec<(i32, i32)> },
     Lor,
     Lrem,
     Lreturn,
@@ -189,7 +195,7 @@ pub enum Instruction {
     Monitorexit,
     Multianewarray(u16, u8),
     New(u16),
-    Newarray,
+    Newarray(u8),
     Nop,
     Pop,
     Pop2,
@@ -201,6 +207,1224 @@ pub enum Instruction {
     Sastore,
     Sipush(i16),
     Swap,
-    Tableswitch,
-    Wide,
+    Tableswitch { default: i32, low: i32, high: i32, offsets: Vec<i32> },
+    Wide(WideInstruction),
+}
+
+/// Local variable instructions in their `wide` form, with 16-bit indices.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WideInstruction {
+    Aload(u16),
+    Astore(u16),
+    Dload(u16),
+    Dstore(u16),
+    Fload(u16),
+    Fstore(u16),
+    Iinc(u16, i16),
+    Iload(u16),
+    Istore(u16),
+    Lload(u16),
+    Lstore(u16),
+    Ret(u16),
+}
+
+/// The number of operand stack slots an instruction pops and pushes; long and double values
+/// take two slots each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackEffect {
+    pub pops: u16,
+    pub pushes: u16,
+}
+
+impl StackEffect {
+    fn new(pops: u16, pushes: u16) -> Self {
+        StackEffect { pops, pushes }
+    }
+
+    /// The change in stack depth, `pushes - pops`.
+    pub fn delta(&self) -> i32 {
+        self.pushes as i32 - self.pops as i32
+    }
+}
+
+/// How an instruction moves values between the local variables and the operand stack, with
+/// the types of the values it pushes. Sizes are in slots.
+pub(crate) enum Effect {
+    Load(u16, ValueType),
+    Store(u16, ValueType),
+    Increment(u16),
+    Pop(usize),
+    /// Duplicates the top `top` slots below the `under` slots beneath them.
+    Dup {
+        top: usize,
+        under: usize,
+    },
+    Swap,
+    /// Pops the values taking the top `operands` slots and pushes a value of type `result`,
+    /// if any.
+    Operation {
+        operands: usize,
+        result: Option<ValueType>,
+    },
+}
+
+impl Effect {
+    fn stack_effect(&self) -> StackEffect {
+        let (pops, pushes) = match *self {
+            Effect::Load(_, value_type) => (0, value_type.size()),
+            Effect::Store(_, value_type) => (value_type.size(), 0),
+            Effect::Increment(_) => (0, 0),
+            Effect::Pop(words) => (words, 0),
+            Effect::Dup { top, under } => (top + under, 2 * top + under),
+            Effect::Swap => (2, 2),
+            Effect::Operation { operands, result } => (operands, result.map_or(0, ValueType::size)),
+        };
+        StackEffect::new(pops as u16, pushes as u16)
+    }
+}
+
+impl Instruction {
+    /// The opcode byte the instruction is encoded with; `0xc4` for every wide instruction.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => 0x00,
+            Instruction::Aconst_null => 0x01,
+            Instruction::Iconst_m1 => 0x02,
+            Instruction::Iconst_0 => 0x03,
+            Instruction::Iconst_1 => 0x04,
+            Instruction::Iconst_2 => 0x05,
+            Instruction::Iconst_3 => 0x06,
+            Instruction::Iconst_4 => 0x07,
+            Instruction::Iconst_5 => 0x08,
+            Instruction::Lconst_0 => 0x09,
+            Instruction::Lconst_1 => 0x0a,
+            Instruction::Fconst_0 => 0x0b,
+            Instruction::Fconst_1 => 0x0c,
+            Instruction::Fconst_2 => 0x0d,
+            Instruction::Dconst_0 => 0x0e,
+            Instruction::Dconst_1 => 0x0f,
+            Instruction::Bipush(..) => 0x10,
+            Instruction::Sipush(..) => 0x11,
+            Instruction::Ldc(..) => 0x12,
+            Instruction::Ldc_w(..) => 0x13,
+            Instruction::Ldc2_w(..) => 0x14,
+            Instruction::Iload(..) => 0x15,
+            Instruction::Lload(..) => 0x16,
+            Instruction::Fload(..) => 0x17,
+            Instruction::Dload(..) => 0x18,
+            Instruction::Aload(..) => 0x19,
+            Instruction::Iload_0 => 0x1a,
+            Instruction::Iload_1 => 0x1b,
+            Instruction::Iload_2 => 0x1c,
+            Instruction::Iload_3 => 0x1d,
+            Instruction::Lload_0 => 0x1e,
+            Instruction::Lload_1 => 0x1f,
+            Instruction::Lload_2 => 0x20,
+            Instruction::Lload_3 => 0x21,
+            Instruction::Fload_0 => 0x22,
+            Instruction::Fload_1 => 0x23,
+            Instruction::Fload_2 => 0x24,
+            Instruction::Fload_3 => 0x25,
+            Instruction::Dload_0 => 0x26,
+            Instruction::Dload_1 => 0x27,
+            Instruction::Dload_2 => 0x28,
+            Instruction::Dload_3 => 0x29,
+            Instruction::Aload_0 => 0x2a,
+            Instruction::Aload_1 => 0x2b,
+            Instruction::Aload_2 => 0x2c,
+            Instruction::Aload_3 => 0x2d,
+            Instruction::Iaload => 0x2e,
+            Instruction::Laload => 0x2f,
+            Instruction::Faload => 0x30,
+            Instruction::Daload => 0x31,
+            Instruction::Aaload => 0x32,
+            Instruction::Baload => 0x33,
+            Instruction::Caload => 0x34,
+            Instruction::Saload => 0x35,
+            Instruction::Istore(..) => 0x36,
+            Instruction::Lstore(..) => 0x37,
+            Instruction::Fstore(..) => 0x38,
+            Instruction::Dstore(..) => 0x39,
+            Instruction::Astore(..) => 0x3a,
+            Instruction::Istore_0 => 0x3b,
+            Instruction::Istore_1 => 0x3c,
+            Instruction::Istore_2 => 0x3d,
+            Instruction::Istore_3 => 0x3e,
+            Instruction::Lstore_0 => 0x3f,
+            Instruction::Lstore_1 => 0x40,
+            Instruction::Lstore_2 => 0x41,
+            Instruction::Lstore_3 => 0x42,
+            Instruction::Fstore_0 => 0x43,
+            Instruction::Fstore_1 => 0x44,
+            Instruction::Fstore_2 => 0x45,
+            Instruction::Fstore_3 => 0x46,
+            Instruction::Dstore_0 => 0x47,
+            Instruction::Dstore_1 => 0x48,
+            Instruction::Dstore_2 => 0x49,
+            Instruction::Dstore_3 => 0x4a,
+            Instruction::Astore_0 => 0x4b,
+            Instruction::Astore_1 => 0x4c,
+            Instruction::Astore_2 => 0x4d,
+            Instruction::Astore_3 => 0x4e,
+            Instruction::Iastore => 0x4f,
+            Instruction::Lastore => 0x50,
+            Instruction::Fastore => 0x51,
+            Instruction::Dastore => 0x52,
+            Instruction::Aastore => 0x53,
+            Instruction::Bastore => 0x54,
+            Instruction::Castore => 0x55,
+            Instruction::Sastore => 0x56,
+            Instruction::Pop => 0x57,
+            Instruction::Pop2 => 0x58,
+            Instruction::Dup => 0x59,
+            Instruction::Dup_x1 => 0x5a,
+            Instruction::Dup_x2 => 0x5b,
+            Instruction::Dup_2 => 0x5c,
+            Instruction::Dup2_x1 => 0x5d,
+            Instruction::Dup2_x2 => 0x5e,
+            Instruction::Swap => 0x5f,
+            Instruction::Iadd => 0x60,
+            Instruction::Ladd => 0x61,
+            Instruction::Fadd => 0x62,
+            Instruction::Dadd => 0x63,
+            Instruction::Isub => 0x64,
+            Instruction::Lsub => 0x65,
+            Instruction::Fsub => 0x66,
+            Instruction::Dsub => 0x67,
+            Instruction::Imul => 0x68,
+            Instruction::Lmul => 0x69,
+            Instruction::Fmul => 0x6a,
+            Instruction::Dmul => 0x6b,
+            Instruction::Idiv => 0x6c,
+            Instruction::Ldiv => 0x6d,
+            Instruction::Fdiv => 0x6e,
+            Instruction::Ddiv => 0x6f,
+            Instruction::Irem => 0x70,
+            Instruction::Lrem => 0x71,
+            Instruction::Frem => 0x72,
+            Instruction::Drem => 0x73,
+            Instruction::Ineg => 0x74,
+            Instruction::Lneg => 0x75,
+            Instruction::Fneg => 0x76,
+            Instruction::Dneg => 0x77,
+            Instruction::Ishl => 0x78,
+            Instruction::Lshl => 0x79,
+            Instruction::Ishr => 0x7a,
+            Instruction::Lshr => 0x7b,
+            Instruction::Iushr => 0x7c,
+            Instruction::Lushr => 0x7d,
+            Instruction::Iand => 0x7e,
+            Instruction::Land => 0x7f,
+            Instruction::Ior => 0x80,
+            Instruction::Lor => 0x81,
+            Instruction::Ixor => 0x82,
+            Instruction::Lxor => 0x83,
+            Instruction::Iinc(..) => 0x84,
+            Instruction::I2l => 0x85,
+            Instruction::I2f => 0x86,
+            Instruction::I2d => 0x87,
+            Instruction::L2i => 0x88,
+            Instruction::L2f => 0x89,
+            Instruction::L2d => 0x8a,
+            Instruction::F2i => 0x8b,
+            Instruction::F2l => 0x8c,
+            Instruction::F2d => 0x8d,
+            Instruction::D2i => 0x8e,
+            Instruction::D2l => 0x8f,
+            Instruction::D2f => 0x90,
+            Instruction::I2b => 0x91,
+            Instruction::I2c => 0x92,
+            Instruction::I2s => 0x93,
+            Instruction::Lcmp => 0x94,
+            Instruction::Fcmpl => 0x95,
+            Instruction::Fcmpg => 0x96,
+            Instruction::Dcmpl => 0x97,
+            Instruction::Dcmpg => 0x98,
+            Instruction::Ifeq(..) => 0x99,
+            Instruction::Ifne(..) => 0x9a,
+            Instruction::Iflt(..) => 0x9b,
+            Instruction::Ifge(..) => 0x9c,
+            Instruction::Ifgt(..) => 0x9d,
+            Instruction::Ifle(..) => 0x9e,
+            Instruction::If_icmpeq(..) => 0x9f,
+            Instruction::If_icmpne(..) => 0xa0,
+            Instruction::If_icmplt(..) => 0xa1,
+            Instruction::If_icmpge(..) => 0xa2,
+            Instruction::If_icmpgt(..) => 0xa3,
+            Instruction::If_icmple(..) => 0xa4,
+            Instruction::If_acmpeq(..) => 0xa5,
+            Instruction::If_acmpne(..) => 0xa6,
+            Instruction::Goto(..) => 0xa7,
+            Instruction::Jsr(..) => 0xa8,
+            Instruction::Ret(..) => 0xa9,
+            Instruction::Tableswitch { .. } => 0xaa,
+            Instruction::Lookupswitch { .. } => 0xab,
+            Instruction::Ireturn => 0xac,
+            Instruction::Lreturn => 0xad,
+            Instruction::Freturn => 0xae,
+            Instruction::Dreturn => 0xaf,
+            Instruction::Areturn => 0xb0,
+            Instruction::Return => 0xb1,
+            Instruction::Getstatic(..) => 0xb2,
+            Instruction::Putstatic(..) => 0xb3,
+            Instruction::Getfield(..) => 0xb4,
+            Instruction::Putfield(..) => 0xb5,
+            Instruction::Invokevirtual(..) => 0xb6,
+            Instruction::Invokespecial(..) => 0xb7,
+            Instruction::Invokestatic(..) => 0xb8,
+            Instruction::Invokeinterface(..) => 0xb9,
+            Instruction::Invokedynamic(..) => 0xba,
+            Instruction::New(..) => 0xbb,
+            Instruction::Newarray(..) => 0xbc,
+            Instruction::Anewarray(..) => 0xbd,
+            Instruction::Arraylength => 0xbe,
+            Instruction::Athrow => 0xbf,
+            Instruction::Checkcast(..) => 0xc0,
+            Instruction::Instanceof(..) => 0xc1,
+            Instruction::Monitorenter => 0xc2,
+            Instruction::Monitorexit => 0xc3,
+            Instruction::Wide(..) => 0xc4,
+            Instruction::Multianewarray(..) => 0xc5,
+            Instruction::Ifnull(..) => 0xc6,
+            Instruction::Ifnonnull(..) => 0xc7,
+            Instruction::Goto_w(..) => 0xc8,
+            Instruction::Jsr_w(..) => 0xc9,
+        }
+    }
+
+    /// The name of the instruction as written in the JVM specification, such as `iload_0` or
+    /// `invokevirtual`.
+    pub fn mnemonic(&self) -> &'static str {
+        MNEMONICS[self.opcode() as usize]
+    }
+
+    /// The number of bytes the instruction is encoded in when it starts at `pc`, which
+    /// determines the padding of `tableswitch` and `lookupswitch`.
+    pub fn length(&self, pc: u32) -> u32 {
+        let padding = 3 - pc % 4;
+        match self {
+            Instruction::Aload(_)
+            | Instruction::Astore(_)
+            | Instruction::Bipush(_)
+            | Instruction::Dload(_)
+            | Instruction::Dstore(_)
+            | Instruction::Fload(_)
+            | Instruction::Fstore(_)
+            | Instruction::Iload(_)
+            | Instruction::Istore(_)
+            | Instruction::Ldc(_)
+            | Instruction::Lload(_)
+            | Instruction::Lstore(_)
+            | Instruction::Newarray(_)
+            | Instruction::Ret(_) => 2,
+            Instruction::Anewarray(_)
+            | Instruction::Checkcast(_)
+            | Instruction::Getfield(_)
+            | Instruction::Getstatic(_)
+            | Instruction::Goto(_)
+            | Instruction::If_acmpeq(_)
+            | Instruction::If_acmpne(_)
+            | Instruction::If_icmpeq(_)
+            | Instruction::If_icmpne(_)
+            | Instruction::If_icmplt(_)
+            | Instruction::If_icmpge(_)
+            | Instruction::If_icmpgt(_)
+            | Instruction::If_icmple(_)
+            | Instruction::Ifeq(_)
+            | Instruction::Ifne(_)
+            | Instruction::Iflt(_)
+            | Instruction::Ifge(_)
+            | Instruction::Ifgt(_)
+            | Instruction::Ifle(_)
+            | Instruction::Ifnonnull(_)
+            | Instruction::Ifnull(_)
+            | Instruction::Iinc(..)
+            | Instruction::Instanceof(_)
+            | Instruction::Invokespecial(_)
+            | Instruction::Invokestatic(_)
+            | Instruction::Invokevirtual(_)
+            | Instruction::Jsr(_)
+            | Instruction::Ldc_w(_)
+            | Instruction::Ldc2_w(_)
+            | Instruction::New(_)
+            | Instruction::Putfield(_)
+            | Instruction::Putstatic(_)
+            | Instruction::Sipush(_) => 3,
+            Instruction::Multianewarray(..) => 4,
+            Instruction::Goto_w(_)
+            | Instruction::Invokedynamic(_)
+            | Instruction::Invokeinterface(..)
+            | Instruction::Jsr_w(_) => 5,
+            Instruction::Tableswitch { offsets, .. } => 1 + padding + 12 + 4 * offsets.len() as u32,
+            Instruction::Lookupswitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u32,
+            Instruction::Wide(WideInstruction::Iinc(..)) => 6,
+            Instruction::Wide(_) => 4,
+            _ => 1,
+        }
+    }
+
+    /// The operand stack slots the instruction pops and pushes. Invocations, field accesses
+    /// and `ldc` depend on the descriptors of the constants they refer to, and have no effect
+    /// if those are invalid. A `return` or `athrow` only counts the value it takes, not the
+    /// rest of the stack it discards.
+    pub fn stack_effect(&self, constant_pool: &ConstantPool) -> Option<StackEffect> {
+        self.effect(constant_pool).map(|effect| effect.stack_effect())
+    }
+
+    /// How the instruction moves values between the local variables and the operand stack,
+    /// `None` if a constant it refers to is invalid.
+    pub(crate) fn effect(&self, constant_pool: &ConstantPool) -> Option<Effect> {
+        use ValueType::{Double, Float, Int, Long, Reference, ReturnAddress};
+        let operation =
+            |operands: usize, result: Option<ValueType>| Effect::Operation { operands, result };
+        let field_type = |index: u16| {
+            let (_, _, descriptor) = constant_pool.member_ref(index as usize)?;
+            FieldType::try_from(&mut descriptor.chars().peekable())
+                .ok()
+                .map(|field| ValueType::of(&field))
+        };
+        let invocation = |descriptor: &str, receiver: usize| {
+            let descriptor = MethodDescriptor::try_from(&mut descriptor.chars().peekable()).ok()?;
+            let result = match descriptor.return_type() {
+                ReturnDescriptor::FieldType(field_type) => Some(ValueType::of(field_type)),
+                ReturnDescriptor::VoidDescriptor => None,
+            };
+            let arguments = descriptor.parameters().iter().map(FieldType::slots).sum::<u16>();
+            Some(operation(receiver + arguments as usize, result))
+        };
+        let constant = |index: u16| {
+            Some(match constant_pool.get(index as usize).ok()? {
+                Constant::Integer(_) => Int,
+                Constant::Float(_) => Float,
+                Constant::Long(_) => Long,
+                Constant::Double(_) => Double,
+                Constant::Dynamic(_, name_and_type) => {
+                    let (_, descriptor) = constant_pool.name_and_type(*name_and_type as usize)?;
+                    let field = FieldType::try_from(&mut descriptor.chars().peekable()).ok()?;
+                    ValueType::of(&field)
+                }
+                _ => Reference,
+            })
+        };
+        Some(match self {
+            Instruction::Aload(local) => Effect::Load(*local as u16, Reference),
+            Instruction::Aload_0 => Effect::Load(0, Reference),
+            Instruction::Aload_1 => Effect::Load(1, Reference),
+            Instruction::Aload_2 => Effect::Load(2, Reference),
+            Instruction::Aload_3 => Effect::Load(3, Reference),
+            Instruction::Iload(local) => Effect::Load(*local as u16, Int),
+            Instruction::Iload_0 => Effect::Load(0, Int),
+            Instruction::Iload_1 => Effect::Load(1, Int),
+            Instruction::Iload_2 => Effect::Load(2, Int),
+            Instruction::Iload_3 => Effect::Load(3, Int),
+            Instruction::Fload(local) => Effect::Load(*local as u16, Float),
+            Instruction::Fload_0 => Effect::Load(0, Float),
+            Instruction::Fload_1 => Effect::Load(1, Float),
+            Instruction::Fload_2 => Effect::Load(2, Float),
+            Instruction::Fload_3 => Effect::Load(3, Float),
+            Instruction::Lload(local) => Effect::Load(*local as u16, Long),
+            Instruction::Lload_0 => Effect::Load(0, Long),
+            Instruction::Lload_1 => Effect::Load(1, Long),
+            Instruction::Lload_2 => Effect::Load(2, Long),
+            Instruction::Lload_3 => Effect::Load(3, Long),
+            Instruction::Dload(local) => Effect::Load(*local as u16, Double),
+            Instruction::Dload_0 => Effect::Load(0, Double),
+            Instruction::Dload_1 => Effect::Load(1, Double),
+            Instruction::Dload_2 => Effect::Load(2, Double),
+            Instruction::Dload_3 => Effect::Load(3, Double),
+            Instruction::Astore(local) => Effect::Store(*local as u16, Reference),
+            Instruction::Astore_0 => Effect::Store(0, Reference),
+            Instruction::Astore_1 => Effect::Store(1, Reference),
+            Instruction::Astore_2 => Effect::Store(2, Reference),
+            Instruction::Astore_3 => Effect::Store(3, Reference),
+            Instruction::Istore(local) => Effect::Store(*local as u16, Int),
+            Instruction::Istore_0 => Effect::Store(0, Int),
+            Instruction::Istore_1 => Effect::Store(1, Int),
+            Instruction::Istore_2 => Effect::Store(2, Int),
+            Instruction::Istore_3 => Effect::Store(3, Int),
+            Instruction::Fstore(local) => Effect::Store(*local as u16, Float),
+            Instruction::Fstore_0 => Effect::Store(0, Float),
+            Instruction::Fstore_1 => Effect::Store(1, Float),
+            Instruction::Fstore_2 => Effect::Store(2, Float),
+            Instruction::Fstore_3 => Effect::Store(3, Float),
+            Instruction::Lstore(local) => Effect::Store(*local as u16, Long),
+            Instruction::Lstore_0 => Effect::Store(0, Long),
+            Instruction::Lstore_1 => Effect::Store(1, Long),
+            Instruction::Lstore_2 => Effect::Store(2, Long),
+            Instruction::Lstore_3 => Effect::Store(3, Long),
+            Instruction::Dstore(local) => Effect::Store(*local as u16, Double),
+            Instruction::Dstore_0 => Effect::Store(0, Double),
+            Instruction::Dstore_1 => Effect::Store(1, Double),
+            Instruction::Dstore_2 => Effect::Store(2, Double),
+            Instruction::Dstore_3 => Effect::Store(3, Double),
+            Instruction::Iinc(local, _) => Effect::Increment(*local as u16),
+            Instruction::Wide(wide) => match wide {
+                WideInstruction::Aload(local) => Effect::Load(*local, Reference),
+                WideInstruction::Iload(local) => Effect::Load(*local, Int),
+                WideInstruction::Fload(local) => Effect::Load(*local, Float),
+                WideInstruction::Lload(local) => Effect::Load(*local, Long),
+                WideInstruction::Dload(local) => Effect::Load(*local, Double),
+                WideInstruction::Astore(local) => Effect::Store(*local, Reference),
+                WideInstruction::Istore(local) => Effect::Store(*local, Int),
+                WideInstruction::Fstore(local) => Effect::Store(*local, Float),
+                WideInstruction::Lstore(local) => Effect::Store(*local, Long),
+                WideInstruction::Dstore(local) => Effect::Store(*local, Double),
+                WideInstruction::Iinc(local, _) => Effect::Increment(*local),
+                WideInstruction::Ret(_) => operation(0, None),
+            },
+            Instruction::Pop => Effect::Pop(1),
+            Instruction::Pop2 => Effect::Pop(2),
+            Instruction::Dup => Effect::Dup { top: 1, under: 0 },
+            Instruction::Dup_x1 => Effect::Dup { top: 1, under: 1 },
+            Instruction::Dup_x2 => Effect::Dup { top: 1, under: 2 },
+            Instruction::Dup_2 => Effect::Dup { top: 2, under: 0 },
+            Instruction::Dup2_x1 => Effect::Dup { top: 2, under: 1 },
+            Instruction::Dup2_x2 => Effect::Dup { top: 2, under: 2 },
+            Instruction::Swap => Effect::Swap,
+            Instruction::Aconst_null | Instruction::New(_) => operation(0, Some(Reference)),
+            Instruction::Iconst_m1
+            | Instruction::Iconst_0
+            | Instruction::Iconst_1
+            | Instruction::Iconst_2
+            | Instruction::Iconst_3
+            | Instruction::Iconst_4
+            | Instruction::Iconst_5
+            | Instruction::Bipush(_)
+            | Instruction::Sipush(_) => operation(0, Some(Int)),
+            Instruction::Lconst_0 | Instruction::Lconst_1 => operation(0, Some(Long)),
+            Instruction::Fconst_0 | Instruction::Fconst_1 | Instruction::Fconst_2 => {
+                operation(0, Some(Float))
+            }
+            Instruction::Dconst_0 | Instruction::Dconst_1 => operation(0, Some(Double)),
+            Instruction::Ldc(index) => operation(0, Some(constant(*index as u16)?)),
+            Instruction::Ldc_w(index) | Instruction::Ldc2_w(index) => {
+                operation(0, Some(constant(*index)?))
+            }
+            Instruction::Iaload
+            | Instruction::Baload
+            | Instruction::Caload
+            | Instruction::Saload => operation(2, Some(Int)),
+            Instruction::Laload => operation(2, Some(Long)),
+            Instruction::Faload => operation(2, Some(Float)),
+            Instruction::Daload => operation(2, Some(Double)),
+            Instruction::Aaload => operation(2, Some(Reference)),
+            Instruction::Iastore
+            | Instruction::Fastore
+            | Instruction::Aastore
+            | Instruction::Bastore
+            | Instruction::Castore
+            | Instruction::Sastore => operation(3, None),
+            Instruction::Lastore | Instruction::Dastore => operation(4, None),
+            Instruction::Iadd
+            | Instruction::Isub
+            | Instruction::Imul
+            | Instruction::Idiv
+            | Instruction::Irem
+            | Instruction::Ishl
+            | Instruction::Ishr
+            | Instruction::Iushr
+            | Instruction::Iand
+            | Instruction::Ior
+            | Instruction::Ixor
+            | Instruction::Fcmpl
+            | Instruction::Fcmpg => operation(2, Some(Int)),
+            Instruction::Lcmp | Instruction::Dcmpl | Instruction::Dcmpg => operation(4, Some(Int)),
+            Instruction::Ladd
+            | Instruction::Lsub
+            | Instruction::Lmul
+            | Instruction::Ldiv
+            | Instruction::Lrem
+            | Instruction::Land
+            | Instruction::Lor
+            | Instruction::Lxor => operation(4, Some(Long)),
+            Instruction::Lshl | Instruction::Lshr | Instruction::Lushr => operation(3, Some(Long)),
+            Instruction::Fadd
+            | Instruction::Fsub
+            | Instruction::Fmul
+            | Instruction::Fdiv
+            | Instruction::Frem => operation(2, Some(Float)),
+            Instruction::Dadd
+            | Instruction::Dsub
+            | Instruction::Dmul
+            | Instruction::Ddiv
+            | Instruction::Drem => operation(4, Some(Double)),
+            Instruction::Ineg
+            | Instruction::F2i
+            | Instruction::I2b
+            | Instruction::I2c
+            | Instruction::I2s
+            | Instruction::Arraylength
+            | Instruction::Instanceof(_) => operation(1, Some(Int)),
+            Instruction::L2i | Instruction::D2i => operation(2, Some(Int)),
+            Instruction::I2l | Instruction::F2l => operation(1, Some(Long)),
+            Instruction::Lneg | Instruction::D2l => operation(2, Some(Long)),
+            Instruction::Fneg | Instruction::I2f => operation(1, Some(Float)),
+            Instruction::L2f | Instruction::D2f => operation(2, Some(Float)),
+            Instruction::I2d | Instruction::F2d => operation(1, Some(Double)),
+            Instruction::Dneg | Instruction::L2d => operation(2, Some(Double)),
+            Instruction::Newarray(_) | Instruction::Anewarray(_) | Instruction::Checkcast(_) => {
+                operation(1, Some(Reference))
+            }
+            Instruction::Multianewarray(_, dimensions) => {
+                operation(*dimensions as usize, Some(Reference))
+            }
+            Instruction::Ifeq(_)
+            | Instruction::Ifne(_)
+            | Instruction::Iflt(_)
+            | Instruction::Ifge(_)
+            | Instruction::Ifgt(_)
+            | Instruction::Ifle(_)
+            | Instruction::Ifnull(_)
+            | Instruction::Ifnonnull(_)
+            | Instruction::Tableswitch { .. }
+            | Instruction::Lookupswitch { .. }
+            | Instruction::Ireturn
+            | Instruction::Freturn
+            | Instruction::Areturn
+            | Instruction::Athrow
+            | Instruction::Monitorenter
+            | Instruction::Monitorexit => operation(1, None),
+            Instruction::Lreturn
+            | Instruction::Dreturn
+            | Instruction::If_icmpeq(_)
+            | Instruction::If_icmpne(_)
+            | Instruction::If_icmplt(_)
+            | Instruction::If_icmpge(_)
+            | Instruction::If_icmpgt(_)
+            | Instruction::If_icmple(_)
+            | Instruction::If_acmpeq(_)
+            | Instruction::If_acmpne(_) => operation(2, None),
+            Instruction::Goto(_)
+            | Instruction::Goto_w(_)
+            | Instruction::Return
+            | Instruction::Ret(_)
+            | Instruction::Nop => operation(0, None),
+            Instruction::Jsr(_) | Instruction::Jsr_w(_) => operation(0, Some(ReturnAddress)),
+            Instruction::Getstatic(index) => operation(0, Some(field_type(*index)?)),
+            Instruction::Getfield(index) => operation(1, Some(field_type(*index)?)),
+            Instruction::Putstatic(index) => operation(field_type(*index)?.size(), None),
+            Instruction::Putfield(index) => operation(1 + field_type(*index)?.size(), None),
+            Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokeinterface(index, _) => {
+                invocation(constant_pool.member_ref(*index as usize)?.2, 1)?
+            }
+            Instruction::Invokestatic(index) => {
+                invocation(constant_pool.member_ref(*index as usize)?.2, 0)?
+            }
+            Instruction::Invokedynamic(index) => {
+                let Constant::InvokeDynamic(_, name_and_type) =
+                    constant_pool.get(*index as usize).ok()?
+                else {
+                    return None;
+                };
+                invocation(constant_pool.name_and_type(*name_and_type as usize)?.1, 0)?
+            }
+        })
+    }
+
+    /// The signed offset from the instruction to the target of a conditional branch, `goto`
+    /// or `jsr`, in either width. Switches have several targets and no single offset.
+    pub fn branch_offset(&self) -> Option<i32> {
+        match self {
+            Instruction::If_acmpeq(offset)
+            | Instruction::If_acmpne(offset)
+            | Instruction::If_icmpeq(offset)
+            | Instruction::If_icmpne(offset)
+            | Instruction::If_icmplt(offset)
+            | Instruction::If_icmpge(offset)
+            | Instruction::If_icmpgt(offset)
+            | Instruction::If_icmple(offset)
+            | Instruction::Ifeq(offset)
+            | Instruction::Ifne(offset)
+            | Instruction::Iflt(offset)
+            | Instruction::Ifge(offset)
+            | Instruction::Ifgt(offset)
+            | Instruction::Ifle(offset)
+            | Instruction::Ifnonnull(offset)
+            | Instruction::Ifnull(offset)
+            | Instruction::Goto(offset)
+            | Instruction::Jsr(offset) => Some(*offset as i16 as i32),
+            Instruction::Goto_w(offset) | Instruction::Jsr_w(offset) => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Whether the instruction transfers control to an offset it encodes: a conditional
+    /// branch, `goto`, `jsr` or a switch.
+    pub fn is_branch(&self) -> bool {
+        self.branch_offset().is_some()
+            || matches!(self, Instruction::Tableswitch { .. } | Instruction::Lookupswitch { .. })
+    }
+
+    /// Whether the instruction is one of the `if` instructions, which branch or continue with
+    /// the next instruction.
+    pub fn is_conditional(&self) -> bool {
+        self.branch_offset().is_some()
+            && !matches!(
+                self,
+                Instruction::Goto(_)
+                    | Instruction::Goto_w(_)
+                    | Instruction::Jsr(_)
+                    | Instruction::Jsr_w(_)
+            )
+    }
+
+    /// Whether control never continues with the next instruction: unconditional jumps,
+    /// switches, returns, `athrow` and `ret`. A `jsr` is not a terminator, as the subroutine
+    /// returns to the instruction after it.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Goto(_)
+                | Instruction::Goto_w(_)
+                | Instruction::Tableswitch { .. }
+                | Instruction::Lookupswitch { .. }
+                | Instruction::Ireturn
+                | Instruction::Lreturn
+                | Instruction::Freturn
+                | Instruction::Dreturn
+                | Instruction::Areturn
+                | Instruction::Return
+                | Instruction::Athrow
+                | Instruction::Ret(_)
+                | Instruction::Wide(WideInstruction::Ret(_))
+        )
+    }
+
+    /// Whether the instruction may throw an exception. Constants, local variables, stack
+    /// manipulation, arithmetic other than integer division and branches cannot.
+    pub fn may_throw(&self) -> bool {
+        !matches!(
+            self,
+            Instruction::Nop
+                | Instruction::Aconst_null
+                | Instruction::Iconst_m1
+                | Instruction::Iconst_0
+                | Instruction::Iconst_1
+                | Instruction::Iconst_2
+                | Instruction::Iconst_3
+                | Instruction::Iconst_4
+                | Instruction::Iconst_5
+                | Instruction::Lconst_0
+                | Instruction::Lconst_1
+                | Instruction::Fconst_0
+                | Instruction::Fconst_1
+                | Instruction::Fconst_2
+                | Instruction::Dconst_0
+                | Instruction::Dconst_1
+                | Instruction::Bipush(_)
+                | Instruction::Sipush(_)
+                | Instruction::Iload(_)
+                | Instruction::Lload(_)
+                | Instruction::Fload(_)
+                | Instruction::Dload(_)
+                | Instruction::Aload(_)
+                | Instruction::Iload_0
+                | Instruction::Iload_1
+                | Instruction::Iload_2
+                | Instruction::Iload_3
+                | Instruction::Lload_0
+                | Instruction::Lload_1
+                | Instruction::Lload_2
+                | Instruction::Lload_3
+                | Instruction::Fload_0
+                | Instruction::Fload_1
+                | Instruction::Fload_2
+                | Instruction::Fload_3
+                | Instruction::Dload_0
+                | Instruction::Dload_1
+                | Instruction::Dload_2
+                | Instruction::Dload_3
+                | Instruction::Aload_0
+                | Instruction::Aload_1
+                | Instruction::Aload_2
+                | Instruction::Aload_3
+                | Instruction::Istore(_)
+                | Instruction::Lstore(_)
+                | Instruction::Fstore(_)
+                | Instruction::Dstore(_)
+                | Instruction::Astore(_)
+                | Instruction::Istore_0
+                | Instruction::Istore_1
+                | Instruction::Istore_2
+                | Instruction::Istore_3
+                | Instruction::Lstore_0
+                | Instruction::Lstore_1
+                | Instruction::Lstore_2
+                | Instruction::Lstore_3
+                | Instruction::Fstore_0
+                | Instruction::Fstore_1
+                | Instruction::Fstore_2
+                | Instruction::Fstore_3
+                | Instruction::Dstore_0
+                | Instruction::Dstore_1
+                | Instruction::Dstore_2
+                | Instruction::Dstore_3
+                | Instruction::Astore_0
+                | Instruction::Astore_1
+                | Instruction::Astore_2
+                | Instruction::Astore_3
+                | Instruction::Pop
+                | Instruction::Pop2
+                | Instruction::Dup
+                | Instruction::Dup_x1
+                | Instruction::Dup_x2
+                | Instruction::Dup_2
+                | Instruction::Dup2_x1
+                | Instruction::Dup2_x2
+                | Instruction::Swap
+                | Instruction::Iadd
+                | Instruction::Ladd
+                | Instruction::Fadd
+                | Instruction::Dadd
+                | Instruction::Isub
+                | Instruction::Lsub
+                | Instruction::Fsub
+                | Instruction::Dsub
+                | Instruction::Imul
+                | Instruction::Lmul
+                | Instruction::Fmul
+                | Instruction::Dmul
+                | Instruction::Fdiv
+                | Instruction::Ddiv
+                | Instruction::Frem
+                | Instruction::Drem
+                | Instruction::Ineg
+                | Instruction::Lneg
+                | Instruction::Fneg
+                | Instruction::Dneg
+                | Instruction::Ishl
+                | Instruction::Lshl
+                | Instruction::Ishr
+                | Instruction::Lshr
+                | Instruction::Iushr
+                | Instruction::Lushr
+                | Instruction::Iand
+                | Instruction::Land
+                | Instruction::Ior
+                | Instruction::Lor
+                | Instruction::Ixor
+                | Instruction::Lxor
+                | Instruction::Iinc(..)
+                | Instruction::I2l
+                | Instruction::I2f
+                | Instruction::I2d
+                | Instruction::L2i
+                | Instruction::L2f
+                | Instruction::L2d
+                | Instruction::F2i
+                | Instruction::F2l
+                | Instruction::F2d
+                | Instruction::D2i
+                | Instruction::D2l
+                | Instruction::D2f
+                | Instruction::I2b
+                | Instruction::I2c
+                | Instruction::I2s
+                | Instruction::Lcmp
+                | Instruction::Fcmpl
+                | Instruction::Fcmpg
+                | Instruction::Dcmpl
+                | Instruction::Dcmpg
+                | Instruction::Ifeq(_)
+                | Instruction::Ifne(_)
+                | Instruction::Iflt(_)
+                | Instruction::Ifge(_)
+                | Instruction::Ifgt(_)
+                | Instruction::Ifle(_)
+                | Instruction::If_icmpeq(_)
+                | Instruction::If_icmpne(_)
+                | Instruction::If_icmplt(_)
+                | Instruction::If_icmpge(_)
+                | Instruction::If_icmpgt(_)
+                | Instruction::If_icmple(_)
+                | Instruction::If_acmpeq(_)
+                | Instruction::If_acmpne(_)
+                | Instruction::Ifnull(_)
+                | Instruction::Ifnonnull(_)
+                | Instruction::Goto(_)
+                | Instruction::Goto_w(_)
+                | Instruction::Tableswitch { .. }
+                | Instruction::Lookupswitch { .. }
+                | Instruction::Wide(_)
+        )
+    }
+
+    /// Whether the instruction invokes a method or a call site.
+    pub fn is_invoke(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Invokevirtual(_)
+                | Instruction::Invokespecial(_)
+                | Instruction::Invokestatic(_)
+                | Instruction::Invokeinterface(..)
+                | Instruction::Invokedynamic(_)
+        )
+    }
+
+    /// Whether the instruction reads or writes a static or instance field.
+    pub fn is_field_access(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Getfield(_)
+                | Instruction::Getstatic(_)
+                | Instruction::Putfield(_)
+                | Instruction::Putstatic(_)
+        )
+    }
+
+    /// Whether the instruction allocates an object or an array.
+    pub fn is_allocation(&self) -> bool {
+        matches!(
+            self,
+            Instruction::New(_)
+                | Instruction::Newarray(_)
+                | Instruction::Anewarray(_)
+                | Instruction::Multianewarray(..)
+        )
+    }
+}
+
+impl WideInstruction {
+    /// The opcode of the instruction widened by the `wide` prefix.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            WideInstruction::Iload(_) => 0x15,
+            WideInstruction::Lload(_) => 0x16,
+            WideInstruction::Fload(_) => 0x17,
+            WideInstruction::Dload(_) => 0x18,
+            WideInstruction::Aload(_) => 0x19,
+            WideInstruction::Istore(_) => 0x36,
+            WideInstruction::Lstore(_) => 0x37,
+            WideInstruction::Fstore(_) => 0x38,
+            WideInstruction::Dstore(_) => 0x39,
+            WideInstruction::Astore(_) => 0x3a,
+            WideInstruction::Iinc(..) => 0x84,
+            WideInstruction::Ret(_) => 0xa9,
+        }
+    }
+
+    /// The mnemonic of the instruction widened by the `wide` prefix.
+    pub fn mnemonic(&self) -> &'static str {
+        MNEMONICS[self.opcode() as usize]
+    }
+}
+
+/// Mnemonics indexed by opcode.
+const MNEMONICS: [&str; 202] = [
+    "nop",
+    "aconst_null",
+    "iconst_m1",
+    "iconst_0",
+    "iconst_1",
+    "iconst_2",
+    "iconst_3",
+    "iconst_4",
+    "iconst_5",
+    "lconst_0",
+    "lconst_1",
+    "fconst_0",
+    "fconst_1",
+    "fconst_2",
+    "dconst_0",
+    "dconst_1",
+    "bipush",
+    "sipush",
+    "ldc",
+    "ldc_w",
+    "ldc2_w",
+    "iload",
+    "lload",
+    "fload",
+    "dload",
+    "aload",
+    "iload_0",
+    "iload_1",
+    "iload_2",
+    "iload_3",
+    "lload_0",
+    "lload_1",
+    "lload_2",
+    "lload_3",
+    "fload_0",
+    "fload_1",
+    "fload_2",
+    "fload_3",
+    "dload_0",
+    "dload_1",
+    "dload_2",
+    "dload_3",
+    "aload_0",
+    "aload_1",
+    "aload_2",
+    "aload_3",
+    "iaload",
+    "laload",
+    "faload",
+    "daload",
+    "aaload",
+    "baload",
+    "caload",
+    "saload",
+    "istore",
+    "lstore",
+    "fstore",
+    "dstore",
+    "astore",
+    "istore_0",
+    "istore_1",
+    "istore_2",
+    "istore_3",
+    "lstore_0",
+    "lstore_1",
+    "lstore_2",
+    "lstore_3",
+    "fstore_0",
+    "fstore_1",
+    "fstore_2",
+    "fstore_3",
+    "dstore_0",
+    "dstore_1",
+    "dstore_2",
+    "dstore_3",
+    "astore_0",
+    "astore_1",
+    "astore_2",
+    "astore_3",
+    "iastore",
+    "lastore",
+    "fastore",
+    "dastore",
+    "aastore",
+    "bastore",
+    "castore",
+    "sastore",
+    "pop",
+    "pop2",
+    "dup",
+    "dup_x1",
+    "dup_x2",
+    "dup2",
+    "dup2_x1",
+    "dup2_x2",
+    "swap",
+    "iadd",
+    "ladd",
+    "fadd",
+    "dadd",
+    "isub",
+    "lsub",
+    "fsub",
+    "dsub",
+    "imul",
+    "lmul",
+    "fmul",
+    "dmul",
+    "idiv",
+    "ldiv",
+    "fdiv",
+    "ddiv",
+    "irem",
+    "lrem",
+    "frem",
+    "drem",
+    "ineg",
+    "lneg",
+    "fneg",
+    "dneg",
+    "ishl",
+    "lshl",
+    "ishr",
+    "lshr",
+    "iushr",
+    "lushr",
+    "iand",
+    "land",
+    "ior",
+    "lor",
+    "ixor",
+    "lxor",
+    "iinc",
+    "i2l",
+    "i2f",
+    "i2d",
+    "l2i",
+    "l2f",
+    "l2d",
+    "f2i",
+    "f2l",
+    "f2d",
+    "d2i",
+    "d2l",
+    "d2f",
+    "i2b",
+    "i2c",
+    "i2s",
+    "lcmp",
+    "fcmpl",
+    "fcmpg",
+    "dcmpl",
+    "dcmpg",
+    "ifeq",
+    "ifne",
+    "iflt",
+    "ifge",
+    "ifgt",
+    "ifle",
+    "if_icmpeq",
+    "if_icmpne",
+    "if_icmplt",
+    "if_icmpge",
+    "if_icmpgt",
+    "if_icmple",
+    "if_acmpeq",
+    "if_acmpne",
+    "goto",
+    "jsr",
+    "ret",
+    "tableswitch",
+    "lookupswitch",
+    "ireturn",
+    "lreturn",
+    "freturn",
+    "dreturn",
+    "areturn",
+    "return",
+    "getstatic",
+    "putstatic",
+    "getfield",
+    "putfield",
+    "invokevirtual",
+    "invokespecial",
+    "invokestatic",
+    "invokeinterface",
+    "invokedynamic",
+    "new",
+    "newarray",
+    "anewarray",
+    "arraylength",
+    "athrow",
+    "checkcast",
+    "instanceof",
+    "monitorenter",
+    "monitorexit",
+    "wide",
+    "multianewarray",
+    "ifnull",
+    "ifnonnull",
+    "goto_w",
+    "jsr_w",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::byte_writer::ByteWriter;
+    use crate::class_file_writer::write_instruction;
+
+    fn constant_pool() -> ConstantPool {
+        let mut constant_pool = ConstantPool::default();
+        for constant in [
+            Constant::Utf8("p/T".to_string()),
+            Constant::ClassIndex(1),
+            Constant::Utf8("f".to_string()),
+            Constant::Utf8("J".to_string()),
+            Constant::NameAndType(3, 4),
+            Constant::FieldRef(2, 5),
+            Constant::Utf8("m".to_string()),
+            Constant::Utf8("(IJ)D".to_string()),
+            Constant::NameAndType(7, 8),
+            Constant::MethodRef(2, 9),
+            Constant::Long(5),
+            Constant::InvokeDynamic(0, 9),
+            Constant::Integer(1),
+        ] {
+            constant_pool.add(constant);
+        }
+        constant_pool
+    }
+
+    #[test]
+    fn test_encoding_matches_writer() {
+        let instructions = [
+            Instruction::Nop,
+            Instruction::Iload(4),
+            Instruction::Sipush(-300),
+            Instruction::Ifnull(8),
+            Instruction::Invokeinterface(10, 4),
+            Instruction::Multianewarray(2, 3),
+            Instruction::Goto_w(-20),
+            Instruction::Tableswitch { default: 8, low: 1, high: 2, offsets: vec![4, 6] },
+            Instruction::Lookupswitch { default: 8, pairs: vec![(-1, 4)] },
+            Instruction::Wide(WideInstruction::Iinc(300, -2)),
+            Instruction::Wide(WideInstruction::Astore(300)),
+        ];
+        for instruction in &instructions {
+            for pc in 0..4 {
+                let mut code = ByteWriter::new();
+                (0..pc).for_each(|_| code.write_u8(0));
+                write_instruction(&mut code, instruction);
+                let bytes = code.into_bytes();
+                assert_eq!(bytes[pc as usize], instruction.opcode(), "{:?}", instruction);
+                assert_eq!(bytes.len() as u32 - pc, instruction.length(pc), "{:?}", instruction);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mnemonics() {
+        assert_eq!(Instruction::Nop.mnemonic(), "nop");
+        assert_eq!(Instruction::Dup_2.mnemonic(), "dup2");
+        assert_eq!(Instruction::Iload_0.mnemonic(), "iload_0");
+        assert_eq!(Instruction::If_acmpne(3).mnemonic(), "if_acmpne");
+        assert_eq!(Instruction::Jsr_w(5).mnemonic(), "jsr_w");
+        assert_eq!(Instruction::Wide(WideInstruction::Iinc(1, 1)).mnemonic(), "wide");
+        assert_eq!(WideInstruction::Iinc(1, 1).mnemonic(), "iinc");
+        assert_eq!(WideInstruction::Ret(1).mnemonic(), "ret");
+    }
+
+    #[test]
+    fn test_stack_effects() {
+        let constant_pool = constant_pool();
+        let effect = |instruction: Instruction| {
+            instruction.stack_effect(&constant_pool).map(|effect| (effect.pops, effect.pushes))
+        };
+        assert_eq!(effect(Instruction::Dup2_x1), Some((3, 5)));
+        assert_eq!(effect(Instruction::Lshl), Some((3, 2)));
+        assert_eq!(effect(Instruction::Lastore), Some((4, 0)));
+        assert_eq!(effect(Instruction::Getfield(6)), Some((1, 2)));
+        assert_eq!(effect(Instruction::Putstatic(6)), Some((2, 0)));
+        assert_eq!(effect(Instruction::Invokevirtual(10)), Some((4, 2)));
+        assert_eq!(effect(Instruction::Invokestatic(10)), Some((3, 2)));
+        assert_eq!(effect(Instruction::Invokedynamic(13)), Some((3, 2)));
+        assert_eq!(effect(Instruction::Ldc2_w(11)), Some((0, 2)));
+        assert_eq!(effect(Instruction::Ldc(14)), Some((0, 1)));
+        assert_eq!(effect(Instruction::Multianewarray(2, 3)), Some((3, 1)));
+        assert_eq!(effect(Instruction::Getfield(10)), None);
+        assert_eq!(effect(Instruction::Ldc(40)), None);
+        assert_eq!(Instruction::Lcmp.stack_effect(&constant_pool).unwrap().delta(), -3);
+    }
+
+    #[test]
+    fn test_classifications() {
+        let goto = Instruction::Goto(-6i16 as u16);
+        assert_eq!(goto.branch_offset(), Some(-6));
+        assert!(goto.is_branch() && goto.is_terminator() && !goto.is_conditional());
+        let branch = Instruction::Ifeq(8);
+        assert!(branch.is_branch() && branch.is_conditional() && !branch.is_terminator());
+        let jsr = Instruction::Jsr_w(100);
+        assert!(jsr.is_branch() && !jsr.is_conditional() && !jsr.is_terminator());
+        let switch = Instruction::Lookupswitch { default: 8, pairs: Vec::new() };
+        assert!(switch.is_branch() && switch.is_terminator() && switch.branch_offset().is_none());
+        assert!(Instruction::Athrow.is_terminator() && Instruction::Athrow.may_throw());
+        assert!(!Instruction::Iadd.may_throw() && Instruction::Idiv.may_throw());
+        assert!(Instruction::Invokedynamic(1).is_invoke());
+        assert!(Instruction::Putstatic(1).is_field_access());
+        assert!(
+            Instruction::Newarray(10).is_allocation() && !Instruction::Checkcast(1).is_allocation()
+        );
+    }
 }
@@ -0,0 +1,354 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::Path;
+
+use thiserror::Error;
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+
+type Result<T> = std::result::Result<T, JarError>;
+
+const MANIFEST_NAME: &str = "META-INF/MANIFEST.MF";
+const VERSIONS_PREFIX: &str = "META-INF/versions/";
+/// Versioned entries are only honoured from Java 9 on, the release that introduced them.
+const FIRST_VERSIONED_RELEASE: u16 = 9;
+
+#[derive(Debug, Error)]
+pub enum JarError {
+    #[error("Invalid archive: {0}")]
+    #[non_exhaustive]
+    Zip(#[from] ZipError),
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Invalid class {0}: {1}")]
+    #[non_exhaustive]
+    Class(String, ContextualError),
+    #[error("Invalid manifest line {0}: {1}")]
+    #[non_exhaustive]
+    InvalidManifest(usize, String),
+    #[error("Invalid jmod header {0:02x?}")]
+    #[non_exhaustive]
+    InvalidJmodHeader([u8; 4]),
+    #[error("Entry {0} not found")]
+    #[non_exhaustive]
+    EntryNotFound(String),
+}
+
+/// The main section of `META-INF/MANIFEST.MF`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    attributes: Vec<(String, String)>,
+}
+
+impl Manifest {
+    /// Parses a manifest, joining continuation lines and stopping at the first blank line, which
+    /// ends the main section.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                break;
+            }
+            if let Some(continuation) = line.strip_prefix(' ') {
+                match attributes.last_mut() {
+                    Some((_, value)) => value.push_str(continuation),
+                    None => return Err(JarError::InvalidManifest(number + 1, line.to_string())),
+                }
+                continue;
+            }
+            match line.split_once(": ") {
+                Some((name, value)) if !name.is_empty() => {
+                    attributes.push((name.to_string(), value.to_string()))
+                }
+                _ => return Err(JarError::InvalidManifest(number + 1, line.to_string())),
+            }
+        }
+        Ok(Manifest { attributes })
+    }
+
+    /// Looks up a main attribute; attribute names are case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn main_class(&self) -> Option<&str> {
+        self.get("Main-Class")
+    }
+
+    /// The relative URLs of the `Class-Path` attribute.
+    pub fn class_path(&self) -> Vec<&str> {
+        self.get("Class-Path").map(|value| value.split_whitespace().collect()).unwrap_or_default()
+    }
+
+    pub fn is_multi_release(&self) -> bool {
+        self.get("Multi-Release").is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+    }
+}
+
+/// A jar archive whose class entries are indexed by binary name.
+///
+/// For multi-release jars opened with [`JarFile::with_release`], the index points at the
+/// highest `META-INF/versions/N/` variant with `N` not above the release, falling back to the
+/// base entry.
+pub struct JarFile<R> {
+    archive: ZipArchive<R>,
+    manifest: Option<Manifest>,
+    release: Option<u16>,
+    root: &'static str,
+    classes: HashMap<String, usize>,
+    nested_jars: Vec<String>,
+}
+
+impl JarFile<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        JarFile::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> JarFile<R> {
+    /// Opens a jar, ignoring versioned entries.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::index(ZipArchive::new(reader)?, None, "")
+    }
+
+    /// Opens a jar as seen by a runtime of the given feature release, e.g. `17`.
+    pub fn with_release(reader: R, release: u16) -> Result<Self> {
+        Self::index(ZipArchive::new(reader)?, Some(release), "")
+    }
+
+    /// Indexes the archive, taking class names relative to the `root` directory.
+    pub(crate) fn index(
+        mut archive: ZipArchive<R>,
+        release: Option<u16>,
+        root: &'static str,
+    ) -> Result<Self> {
+        let manifest = match archive.index_for_name(MANIFEST_NAME) {
+            Some(index) => {
+                let mut text = String::new();
+                archive.by_index(index)?.read_to_string(&mut text)?;
+                Some(Manifest::parse(&text)?)
+            }
+            None => None,
+        };
+        let release = release.filter(|_| manifest.as_ref().is_some_and(Manifest::is_multi_release));
+
+        let mut classes = HashMap::new();
+        let mut selected_versions = HashMap::new();
+        let mut nested_jars = Vec::new();
+        for index in 0..archive.len() {
+            let name = match archive.name_for_index(index) {
+                Some(name) => name?,
+                None => continue,
+            };
+            if name.ends_with(".jar") {
+                nested_jars.push(name.to_string());
+                continue;
+            }
+            let Some(path) = name.strip_prefix(root).and_then(|path| path.strip_suffix(".class"))
+            else {
+                continue;
+            };
+            let (version, class_name) = match path.strip_prefix(VERSIONS_PREFIX) {
+                Some(versioned) => match versioned.split_once('/') {
+                    Some((version, class_name)) => match version.parse::<u16>() {
+                        Ok(version)
+                            if version >= FIRST_VERSIONED_RELEASE
+                                && release.is_some_and(|release| version <= release) =>
+                        {
+                            (version, class_name)
+                        }
+                        _ => continue,
+                    },
+                    None => continue,
+                },
+                None if path.starts_with("META-INF/") => continue,
+                None => (0, path),
+            };
+            let selected = selected_versions.entry(class_name.to_string()).or_insert(version);
+            if version >= *selected {
+                *selected = version;
+                classes.insert(class_name.to_string(), index);
+            }
+        }
+        nested_jars.sort_unstable();
+
+        Ok(JarFile { archive, manifest, release, root, classes, nested_jars })
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// The release versioned entries are resolved for, `None` unless the jar is multi-release.
+    pub fn release(&self) -> Option<u16> {
+        self.release
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Internal names (`java/lang/Object`) of all classes, in sorted order.
+    pub fn class_names(&self) -> Vec<&str> {
+        let mut names = self.classes.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn contains(&self, binary_name: &str) -> bool {
+        self.classes.contains_key(internal_name(binary_name).as_ref())
+    }
+
+    /// The bytes of a class, looked up by binary (`a.b.C`) or internal (`a/b/C`) name.
+    pub fn class_bytes(&mut self, binary_name: &str) -> Result<Option<Vec<u8>>> {
+        match self.classes.get(internal_name(binary_name).as_ref()) {
+            Some(&index) => Ok(Some(self.read_entry(index)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_class(&mut self, binary_name: &str) -> Result<Option<ClassFile>> {
+        match self.class_bytes(binary_name)? {
+            Some(bytes) => ClassFileReader::read_class(&bytes)
+                .map(Some)
+                .map_err(|err| JarError::Class(binary_name.to_string(), err)),
+            None => Ok(None),
+        }
+    }
+
+    /// The bytes of any entry, e.g. `META-INF/services/java.sql.Driver`, preferring the versioned
+    /// variant for multi-release jars.
+    pub fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        let root = self.root;
+        let versioned = self.release.into_iter().flat_map(|release| {
+            (FIRST_VERSIONED_RELEASE..=release)
+                .rev()
+                .map(move |version| format!("{}{}{}/{}", root, VERSIONS_PREFIX, version, path))
+        });
+        let candidates = versioned.chain(std::iter::once(format!("{}{}", root, path)));
+        for candidate in candidates.collect::<Vec<_>>() {
+            if let Some(index) = self.archive.index_for_name(&candidate) {
+                return Ok(Some(self.read_entry(index)?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parses every class of the jar, in the order of [`JarFile::class_names`].
+    pub fn classes(&mut self) -> JarClasses<'_, R> {
+        let mut names = self.classes.keys().cloned().collect::<Vec<_>>();
+        names.sort_unstable_by(|a, b| b.cmp(a));
+        JarClasses { jar: self, pending: names }
+    }
+
+    /// Names of the jars stored inside this one, as in the fat-jar layout.
+    pub fn nested_jars(&self) -> &[String] {
+        &self.nested_jars
+    }
+
+    /// Opens a jar stored inside this one, resolved for the same release.
+    pub fn open_nested(&mut self, name: &str) -> Result<JarFile<Cursor<Vec<u8>>>> {
+        let index = self
+            .archive
+            .index_for_name(name)
+            .ok_or_else(|| JarError::EntryNotFound(name.to_string()))?;
+        let reader = Cursor::new(self.read_entry(index)?);
+        match self.release {
+            Some(release) => JarFile::with_release(reader, release),
+            None => JarFile::new(reader),
+        }
+    }
+
+    fn read_entry(&mut self, index: usize) -> Result<Vec<u8>> {
+        let mut entry = self.archive.by_index(index)?;
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Iterator over the parsed classes of a jar, see [`JarFile::classes`].
+pub struct JarClasses<'j, R> {
+    jar: &'j mut JarFile<R>,
+    pending: Vec<String>,
+}
+
+impl<R: Read + Seek> Iterator for JarClasses<'_, R> {
+    type Item = (String, Result<ClassFile>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = self.pending.pop()?;
+        let result = self.jar.read_class(&name).and_then(|class| {
+            class.ok_or_else(|| JarError::EntryNotFound(format!("{}.class", name)))
+        });
+        Some((name, result))
+    }
+}
+
+pub(crate) fn internal_name(binary_name: &str) -> Cow<'_, str> {
+    if binary_name.contains('.') {
+        binary_name.replace('.', "/").into()
+    } else {
+        binary_name.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+    use zip::{ZipArchive, ZipWriter};
+
+    use super::{JarFile, Manifest};
+
+    #[test]
+    fn test_manifest_parse() {
+        let manifest = Manifest::parse(
+            "Manifest-Version: 1.0\r\nMain-Class: com.example.Ma\r\n in\r\nclass-path: a.jar  \
+             lib/b.jar\r\nMulti-Release: true\r\n\r\nName: com/example/\r\nSealed: true\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(manifest.main_class(), Some("com.example.Main"));
+        assert_eq!(manifest.class_path(), ["a.jar", "lib/b.jar"]);
+        assert!(manifest.is_multi_release());
+        assert_eq!(manifest.get("Sealed"), None);
+        assert!(Manifest::parse(" dangling").is_err());
+    }
+
+    #[test]
+    fn test_versioned_resource_under_root() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in [
+            ("META-INF/MANIFEST.MF", "Multi-Release: true\r\n"),
+            ("META-INF/versions/11/data.txt", "outside the root"),
+            ("classes/data.txt", "base"),
+            ("classes/META-INF/versions/11/data.txt", "11"),
+        ] {
+            writer.start_file(name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        let archive = ZipArchive::new(writer.finish().unwrap()).unwrap();
+        let mut jar = JarFile::index(archive, Some(17), "classes/").unwrap();
+
+        assert_eq!(jar.resource("data.txt").unwrap(), Some(b"11".to_vec()));
+    }
+}
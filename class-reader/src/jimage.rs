@@ -0,0 +1,492 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use flate2::read::ZlibDecoder;
+use memmap2::Mmap;
+use thiserror::Error;
+
+use crate::byte_reader::{ByteReader, ReadError};
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+use crate::jar_file::internal_name;
+
+type Result<T> = std::result::Result<T, JImageError>;
+
+const IMAGE_MAGIC: u32 = 0xCAFEDADA;
+const HEADER_SIZE: usize = 7 * 4;
+const MAJOR_VERSION: u16 = 1;
+
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: u8 = 1;
+const ATTRIBUTE_PARENT: u8 = 2;
+const ATTRIBUTE_BASE: u8 = 3;
+const ATTRIBUTE_EXTENSION: u8 = 4;
+const ATTRIBUTE_OFFSET: u8 = 5;
+const ATTRIBUTE_COMPRESSED: u8 = 6;
+const ATTRIBUTE_UNCOMPRESSED: u8 = 7;
+
+const HASH_MULTIPLIER: u32 = 0x01000193;
+
+const COMPRESSED_MAGIC: u32 = 0xCAFEFAFA;
+const COMPRESSED_HEADER_SIZE: usize = 4 + 8 + 8 + 4 + 4 + 1;
+
+const EXTERNALIZED_STRING: u8 = 23;
+const EXTERNALIZED_STRING_DESCRIPTOR: u8 = 25;
+
+#[derive(Debug, Error)]
+pub enum JImageError {
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Invalid jimage magic {0:#010x}")]
+    #[non_exhaustive]
+    InvalidMagic(u32),
+    #[error("Unsupported jimage version {0}.{1}")]
+    #[non_exhaustive]
+    UnsupportedVersion(u16, u16),
+    #[error("Image is truncated: {0} ends past the end of the file")]
+    #[non_exhaustive]
+    Truncated(&'static str),
+    #[error("Invalid location attribute kind {0}")]
+    #[non_exhaustive]
+    InvalidAttributeKind(u8),
+    #[error("Unknown decompressor {0}")]
+    #[non_exhaustive]
+    UnknownDecompressor(String),
+    #[error("Invalid constant pool tag {0} in shared string resource")]
+    #[non_exhaustive]
+    InvalidConstantTag(u8),
+    #[error("Error encountered during reading: {0}")]
+    #[non_exhaustive]
+    ReadError(#[from] ReadError),
+    #[error("Invalid class {0}: {1}")]
+    #[non_exhaustive]
+    Class(String, ContextualError),
+}
+
+/// A resource entry of a jimage, named `/module/parent/base.extension`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageLocation {
+    pub module: String,
+    pub parent: String,
+    pub base: String,
+    pub extension: String,
+    pub offset: u64,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl ImageLocation {
+    pub fn full_name(&self) -> String {
+        let mut name = String::new();
+        if !self.module.is_empty() {
+            name.push('/');
+            name.push_str(&self.module);
+            name.push('/');
+        }
+        if !self.parent.is_empty() {
+            name.push_str(&self.parent);
+            name.push('/');
+        }
+        name.push_str(&self.base);
+        if !self.extension.is_empty() {
+            name.push('.');
+            name.push_str(&self.extension);
+        }
+        name
+    }
+
+    /// The internal class name (`java/lang/Object`) if this is a class entry.
+    pub fn class_name(&self) -> Option<String> {
+        if self.extension != "class" || self.module == "modules" || self.module == "packages" {
+            return None;
+        }
+        match self.parent.is_empty() {
+            true => Some(self.base.clone()),
+            false => Some(format!("{}/{}", self.parent, self.base)),
+        }
+    }
+}
+
+/// A JDK runtime image (`lib/modules`), memory-mapped and indexed by class name.
+///
+/// The file is laid out as a header, a perfect hash redirect table, the location offsets,
+/// the location attributes, a string table and finally the resources. Index structures use the
+/// byte order of the platform that wrote the image, which is detected from the magic.
+pub struct JImage {
+    data: Mmap,
+    big_endian: bool,
+    version: (u16, u16),
+    table_length: usize,
+    redirect_start: usize,
+    offsets_start: usize,
+    locations_start: usize,
+    strings_start: usize,
+    index_size: usize,
+    classes: HashMap<String, usize>,
+    modules: BTreeMap<String, Vec<String>>,
+}
+
+impl JImage {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: runtime images are not modified while a JDK is installed.
+        let data = unsafe { Mmap::map(&file)? };
+        Self::new(data)
+    }
+
+    fn new(data: Mmap) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(JImageError::Truncated("header"));
+        }
+        let magic = u32::from_le_bytes(data[..4].try_into().unwrap());
+        let big_endian = match magic {
+            IMAGE_MAGIC => false,
+            _ if magic.swap_bytes() == IMAGE_MAGIC => true,
+            _ => return Err(JImageError::InvalidMagic(magic)),
+        };
+
+        let mut image = JImage {
+            data,
+            big_endian,
+            version: (0, 0),
+            table_length: 0,
+            redirect_start: HEADER_SIZE,
+            offsets_start: 0,
+            locations_start: 0,
+            strings_start: 0,
+            index_size: 0,
+            classes: HashMap::new(),
+            modules: BTreeMap::new(),
+        };
+        let version = image.u32_at(4)?;
+        image.version = ((version >> 16) as u16, version as u16);
+        if image.version.0 != MAJOR_VERSION {
+            return Err(JImageError::UnsupportedVersion(image.version.0, image.version.1));
+        }
+        image.table_length = image.u32_at(16)? as usize;
+        let locations_size = image.u32_at(20)? as usize;
+        let strings_size = image.u32_at(24)? as usize;
+        image.offsets_start = image.redirect_start + image.table_length * 4;
+        image.locations_start = image.offsets_start + image.table_length * 4;
+        image.strings_start = image.locations_start + locations_size;
+        image.index_size = image.strings_start + strings_size;
+        if image.index_size > image.data.len() {
+            return Err(JImageError::Truncated("index"));
+        }
+
+        for slot in 0..image.table_length {
+            let location = image.slot_location(slot)?;
+            let Some(class_name) = location.class_name() else {
+                continue;
+            };
+            let classes = image.modules.entry(location.module).or_default();
+            if class_name != "module-info" {
+                image.classes.insert(class_name.clone(), slot);
+                classes.push(class_name);
+            }
+        }
+        image.modules.values_mut().for_each(|classes| classes.sort_unstable());
+
+        Ok(image)
+    }
+
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// Every location of the image, in table order.
+    pub fn locations(&self) -> impl Iterator<Item = Result<ImageLocation>> + '_ {
+        (0..self.table_length).map(|slot| self.slot_location(slot))
+    }
+
+    /// Looks up a resource by its full name, e.g. `/java.base/java/lang/Object.class`.
+    pub fn find(&self, full_name: &str) -> Result<Option<ImageLocation>> {
+        if self.table_length == 0 {
+            return Ok(None);
+        }
+        let length = self.table_length as u32;
+        let redirect = self.u32_at(
+            self.redirect_start + (hash(full_name, HASH_MULTIPLIER) % length) as usize * 4,
+        )? as i32;
+        let slot = match redirect {
+            0 => return Ok(None),
+            redirect if redirect < 0 => (-redirect - 1) as u32,
+            seed => hash(full_name, seed as u32) % length,
+        };
+        if slot >= length {
+            return Ok(None);
+        }
+        let location = self.slot_location(slot as usize)?;
+        Ok((location.full_name() == full_name).then_some(location))
+    }
+
+    /// Looks up a resource by its path inside a module, e.g. `java/lang/Object.class`, trying
+    /// every module in name order.
+    pub fn find_resource(&self, path: &str) -> Result<Option<ImageLocation>> {
+        for module in self.modules.keys() {
+            if let Some(location) = self.find(&format!("/{}/{}", module, path))? {
+                return Ok(Some(location));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Names of the modules that contain classes.
+    pub fn modules(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
+    }
+
+    /// Internal names of the classes of a module, in sorted order. Every module has a
+    /// `module-info`, which is not listed: [`JImage::find`] looks it up by its full name, e.g.
+    /// `/java.base/module-info.class`.
+    pub fn class_names(&self, module: &str) -> &[String] {
+        self.modules.get(module).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The module defining a class, looked up by binary (`a.b.C`) or internal (`a/b/C`) name.
+    pub fn module_of(&self, binary_name: &str) -> Result<Option<String>> {
+        match self.classes.get(internal_name(binary_name).as_ref()) {
+            Some(&slot) => Ok(Some(self.slot_location(slot)?.module)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn class_bytes(&self, binary_name: &str) -> Result<Option<Vec<u8>>> {
+        match self.classes.get(internal_name(binary_name).as_ref()) {
+            Some(&slot) => Ok(Some(self.resource(&self.slot_location(slot)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn read_class(&self, binary_name: &str) -> Result<Option<ClassFile>> {
+        match self.class_bytes(binary_name)? {
+            Some(bytes) => ClassFileReader::read_class(&bytes)
+                .map(Some)
+                .map_err(|err| JImageError::Class(binary_name.to_string(), err)),
+            None => Ok(None),
+        }
+    }
+
+    /// The uncompressed content of a resource.
+    pub fn resource(&self, location: &ImageLocation) -> Result<Vec<u8>> {
+        let size = match location.compressed_size {
+            0 => location.uncompressed_size,
+            compressed_size => compressed_size,
+        };
+        let start = self.index_size + location.offset as usize;
+        let content = self
+            .data
+            .get(start..start + size as usize)
+            .ok_or(JImageError::Truncated("resource"))?
+            .to_vec();
+        match location.compressed_size {
+            0 => Ok(content),
+            _ => self.decompress(content),
+        }
+    }
+
+    /// Undoes the stack of compressions jlink applied, outermost first.
+    fn decompress(&self, mut content: Vec<u8>) -> Result<Vec<u8>> {
+        while content.len() >= COMPRESSED_HEADER_SIZE
+            && self.u32_in(&content, 0) == COMPRESSED_MAGIC
+        {
+            let compressed_size = self.u64_in(&content, 4) as usize;
+            let uncompressed_size = self.u64_in(&content, 12) as usize;
+            let decompressor = self.string(self.u32_in(&content, 20))?;
+            let payload = content
+                .get(COMPRESSED_HEADER_SIZE..COMPRESSED_HEADER_SIZE + compressed_size)
+                .ok_or(JImageError::Truncated("compressed resource"))?;
+            content = match decompressor.as_ref() {
+                "zip" => {
+                    let mut inflated = Vec::with_capacity(uncompressed_size);
+                    ZlibDecoder::new(payload).read_to_end(&mut inflated)?;
+                    inflated
+                }
+                "compact-cp" => self.expand_shared_strings(payload, uncompressed_size)?,
+                other => return Err(JImageError::UnknownDecompressor(other.to_string())),
+            };
+        }
+        Ok(content)
+    }
+
+    /// Restores a class whose constant pool strings were moved into the image string table.
+    fn expand_shared_strings(&self, data: &[u8], uncompressed_size: usize) -> Result<Vec<u8>> {
+        let mut reader = ByteReader::new(data);
+        let mut out = Vec::with_capacity(uncompressed_size);
+        out.extend_from_slice(reader.read_bytes(8)?);
+        let count = reader.read_u16()?;
+        out.extend_from_slice(&count.to_be_bytes());
+
+        let mut index = 1;
+        while index < count {
+            let tag = reader.read_u8()?;
+            let utf8 = match tag {
+                1 => {
+                    let length = reader.read_u16()?;
+                    Cow::Borrowed(reader.read_bytes(length as usize)?)
+                }
+                EXTERNALIZED_STRING => {
+                    Cow::Borrowed(self.raw_string(read_compressed_int(&mut reader)?)?)
+                }
+                EXTERNALIZED_STRING_DESCRIPTOR => {
+                    Cow::Owned(self.reconstruct_descriptor(&mut reader)?)
+                }
+                _ => {
+                    let size = match tag {
+                        3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+                        5 | 6 => 8,
+                        7 | 8 | 16 | 19 | 20 => 2,
+                        15 => 3,
+                        _ => return Err(JImageError::InvalidConstantTag(tag)),
+                    };
+                    out.push(tag);
+                    out.extend_from_slice(reader.read_bytes(size)?);
+                    index += if tag == 5 || tag == 6 { 2 } else { 1 };
+                    continue;
+                }
+            };
+            out.push(1);
+            out.extend_from_slice(&(utf8.len() as u16).to_be_bytes());
+            out.extend_from_slice(&utf8);
+            index += 1;
+        }
+        out.extend_from_slice(&data[reader.position()..]);
+        Ok(out)
+    }
+
+    /// Rebuilds a descriptor whose class names were split into shared package and class strings.
+    fn reconstruct_descriptor(&self, reader: &mut ByteReader) -> Result<Vec<u8>> {
+        let descriptor = self.raw_string(read_compressed_int(reader)?)?;
+        let indexes_length = read_compressed_int(reader)?;
+        let mut indexes = ByteReader::new(reader.read_bytes(indexes_length as usize)?);
+
+        let mut out = Vec::with_capacity(descriptor.len() * 2);
+        for &byte in descriptor {
+            out.push(byte);
+            if byte == b'L' {
+                let package = self.raw_string(read_compressed_int(&mut indexes)?)?;
+                if !package.is_empty() {
+                    out.extend_from_slice(package);
+                    out.push(b'/');
+                }
+                out.extend_from_slice(self.raw_string(read_compressed_int(&mut indexes)?)?);
+            }
+        }
+        Ok(out)
+    }
+
+    fn slot_location(&self, slot: usize) -> Result<ImageLocation> {
+        self.location_at(self.u32_at(self.offsets_start + slot * 4)?)
+    }
+
+    /// Decodes the attribute stream at `offset` in the location table. Each attribute starts
+    /// with a byte holding the kind in its high five bits and the value length minus one in its
+    /// low three bits, followed by the big-endian value.
+    fn location_at(&self, offset: u32) -> Result<ImageLocation> {
+        let mut position = self.locations_start + offset as usize;
+        let mut attributes = [0u64; 8];
+        loop {
+            let byte = *self.data.get(position).ok_or(JImageError::Truncated("locations"))?;
+            let kind = byte >> 3;
+            if kind == ATTRIBUTE_END {
+                break;
+            }
+            if kind > ATTRIBUTE_UNCOMPRESSED {
+                return Err(JImageError::InvalidAttributeKind(kind));
+            }
+            let length = (byte & 0x7) as usize + 1;
+            let value = self
+                .data
+                .get(position + 1..position + 1 + length)
+                .ok_or(JImageError::Truncated("locations"))?;
+            attributes[kind as usize] = value.iter().fold(0, |acc, &b| (acc << 8) | b as u64);
+            position += 1 + length;
+        }
+
+        let string = |kind: u8| self.string(attributes[kind as usize] as u32).map(Cow::into_owned);
+        Ok(ImageLocation {
+            module: string(ATTRIBUTE_MODULE)?,
+            parent: string(ATTRIBUTE_PARENT)?,
+            base: string(ATTRIBUTE_BASE)?,
+            extension: string(ATTRIBUTE_EXTENSION)?,
+            offset: attributes[ATTRIBUTE_OFFSET as usize],
+            compressed_size: attributes[ATTRIBUTE_COMPRESSED as usize],
+            uncompressed_size: attributes[ATTRIBUTE_UNCOMPRESSED as usize],
+        })
+    }
+
+    /// The modified UTF-8 bytes of the nul-terminated string at `offset` in the string table.
+    fn raw_string(&self, offset: u32) -> Result<&[u8]> {
+        let strings = &self.data[self.strings_start..self.index_size];
+        let start = strings.get(offset as usize..).ok_or(JImageError::Truncated("strings"))?;
+        let end = start.iter().position(|&b| b == 0).ok_or(JImageError::Truncated("strings"))?;
+        Ok(&start[..end])
+    }
+
+    fn string(&self, offset: u32) -> Result<Cow<'_, str>> {
+        let bytes = self.raw_string(offset)?;
+        Ok(cesu8::from_java_cesu8(bytes).map_err(ReadError::from)?)
+    }
+
+    fn u32_at(&self, position: usize) -> Result<u32> {
+        let bytes = self.data.get(position..position + 4).ok_or(JImageError::Truncated("index"))?;
+        Ok(self.u32_in(bytes, 0))
+    }
+
+    fn u32_in(&self, bytes: &[u8], position: usize) -> u32 {
+        let bytes = bytes[position..position + 4].try_into().unwrap();
+        match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    }
+
+    fn u64_in(&self, bytes: &[u8], position: usize) -> u64 {
+        let bytes = bytes[position..position + 8].try_into().unwrap();
+        match self.big_endian {
+            true => u64::from_be_bytes(bytes),
+            false => u64::from_le_bytes(bytes),
+        }
+    }
+}
+
+/// The string hash of the image's perfect hash table: FNV-1 over the UTF-8 bytes, where the
+/// multiplier doubles as the initial seed.
+fn hash(name: &str, seed: u32) -> u32 {
+    name.bytes().fold(seed, |seed, byte| seed.wrapping_mul(HASH_MULTIPLIER) ^ byte as u32)
+        & 0x7FFFFFFF
+}
+
+/// Reads an integer written by jlink's index compression: a header byte with the high bit set
+/// carries the total length in bits 5-6 and the top value bits in bits 0-4; otherwise the value
+/// is a plain four-byte big-endian integer.
+fn read_compressed_int(reader: &mut ByteReader) -> Result<u32> {
+    let header = reader.read_u8()?;
+    let (length, mut value) = match header & 0x80 {
+        0 => (4, header as u32),
+        _ => (((header >> 5) & 0x3) as usize, (header & 0x1F) as u32),
+    };
+    for _ in 1..length {
+        value = (value << 8) | reader.read_u8()? as u32;
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ByteReader, read_compressed_int};
+
+    #[test]
+    fn test_read_compressed_int() {
+        let bytes = [0xA5, 0xC1, 0x02, 0x00, 0x00, 0x01, 0x00];
+        let mut reader = ByteReader::new(&bytes);
+
+        assert_eq!(read_compressed_int(&mut reader).unwrap(), 5);
+        assert_eq!(read_compressed_int(&mut reader).unwrap(), 0x0102);
+        assert_eq!(read_compressed_int(&mut reader).unwrap(), 0x100);
+    }
+}
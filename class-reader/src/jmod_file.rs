@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::class_file::ClassFile;
+use crate::jar_file::{JarClasses, JarError, JarFile};
+
+type Result<T> = std::result::Result<T, JarError>;
+
+/// `JM` followed by format version 1.0.
+const JMOD_MAGIC: [u8; 4] = [0x4A, 0x4D, 0x01, 0x00];
+const CLASSES_ROOT: &str = "classes/";
+
+/// A `.jmod` file: a four byte header followed by a zip archive whose classes live under
+/// `classes/`, next to native libraries, launchers and configuration files.
+pub struct JmodFile<R> {
+    jar: JarFile<R>,
+    module_name: Option<String>,
+}
+
+impl JmodFile<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        JmodFile::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> JmodFile<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != JMOD_MAGIC {
+            return Err(JarError::InvalidJmodHeader(magic));
+        }
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut jar = JarFile::index(ZipArchive::new(reader)?, None, CLASSES_ROOT)?;
+        let module_name = match jar.read_class("module-info")? {
+            Some(module_info) => module_info.module_name().map(str::to_string),
+            None => None,
+        };
+        Ok(JmodFile { jar, module_name })
+    }
+
+    /// The module name declared by `classes/module-info.class`.
+    pub fn module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.jar.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jar.is_empty()
+    }
+
+    /// Internal names of all classes, including `module-info`, in sorted order.
+    pub fn class_names(&self) -> Vec<&str> {
+        self.jar.class_names()
+    }
+
+    pub fn contains(&self, binary_name: &str) -> bool {
+        self.jar.contains(binary_name)
+    }
+
+    pub fn class_bytes(&mut self, binary_name: &str) -> Result<Option<Vec<u8>>> {
+        self.jar.class_bytes(binary_name)
+    }
+
+    pub fn read_class(&mut self, binary_name: &str) -> Result<Option<ClassFile>> {
+        self.jar.read_class(binary_name)
+    }
+
+    /// The bytes of a resource below `classes/`.
+    pub fn resource(&mut self, path: &str) -> Result<Option<Vec<u8>>> {
+        self.jar.resource(path)
+    }
+
+    pub fn classes(&mut self) -> JarClasses<'_, R> {
+        self.jar.classes()
+    }
+}
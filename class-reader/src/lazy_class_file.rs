@@ -0,0 +1,411 @@
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use crate::access_flag::ClassFileAccessFlags;
+use crate::attribute::Attribute;
+use crate::byte_reader::ByteReader;
+use crate::class_file_reader::{
+    AttributeLocation, ClassFileReader, ClassReaderError, ContextualError,
+};
+use crate::class_file_version::ClassFileVersion;
+use crate::constant_pool::{Constant, ConstantPool, ConstantPoolError};
+use crate::field::{Field, FieldAccessFlags, FieldType};
+use crate::method::{Method, MethodAccessFlags, MethodDescriptor};
+use crate::predefined_attributes::Code;
+
+type Result<T> = std::result::Result<T, ClassReaderError>;
+
+/// Decoding a single constant never consults the pool it belongs to.
+static EMPTY_CONSTANT_POOL: ConstantPool = ConstantPool { constants: Vec::new() };
+
+/// A class file that borrows its input and decodes members and attributes only when asked.
+///
+/// Parsing records the offsets of constant pool entries, members and attributes without
+/// allocating strings, so scanning many classes for a single method stays cheap.
+#[derive(Debug)]
+pub struct LazyClassFile<'a> {
+    version: ClassFileVersion,
+    constant_pool: LazyConstantPool<'a>,
+    flags: u16,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<MemberInfo<'a>>,
+    methods: Vec<MemberInfo<'a>>,
+    attributes: Vec<AttributeInfo<'a>>,
+}
+
+impl<'a> LazyClassFile<'a> {
+    pub fn parse(data: &'a [u8]) -> std::result::Result<Self, ContextualError> {
+        let mut reader = ByteReader::new(data);
+        Self::index(data, &mut reader).map_err(|err| ContextualError::new(err, reader.snippet()))
+    }
+
+    fn index(data: &'a [u8], reader: &mut ByteReader<'a>) -> Result<Self> {
+        let magic_number = reader.read_u32()?;
+        if magic_number != 0xCAFEBABE {
+            return Err(ClassReaderError::InvalidMagicNumber(magic_number));
+        }
+        let minor_version = reader.read_u16()?;
+        let major_version = reader.read_u16()?;
+        let version = ClassFileVersion::from(major_version, minor_version)?;
+
+        let constant_pool = LazyConstantPool::index(data, reader)?;
+
+        let flags = reader.read_u16()?;
+        let (this_class, super_class) = reader.read_pair_u16()?;
+        let interfaces_count = reader.read_u16()?;
+        let mut interfaces = Vec::with_capacity(interfaces_count as usize);
+        for _ in 0..interfaces_count {
+            interfaces.push(reader.read_u16()?);
+        }
+
+        let fields = Self::index_members(reader)?;
+        let methods = Self::index_members(reader)?;
+        let attributes = Self::index_attributes(reader)?;
+
+        Ok(LazyClassFile {
+            version,
+            constant_pool,
+            flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+
+    fn index_members(reader: &mut ByteReader<'a>) -> Result<Vec<MemberInfo<'a>>> {
+        let members_count = reader.read_u16()?;
+        let mut members = Vec::with_capacity(members_count as usize);
+        for _ in 0..members_count {
+            let (flags, name_index) = reader.read_pair_u16()?;
+            let descriptor_index = reader.read_u16()?;
+            let attributes = Self::index_attributes(reader)?;
+            members.push(MemberInfo { flags, name_index, descriptor_index, attributes });
+        }
+        Ok(members)
+    }
+
+    fn index_attributes(reader: &mut ByteReader<'a>) -> Result<Vec<AttributeInfo<'a>>> {
+        let attributes_count = reader.read_u16()?;
+        let mut attributes = Vec::with_capacity(attributes_count as usize);
+        for _ in 0..attributes_count {
+            let name_index = reader.read_u16()?;
+            let mut length_reader = *reader;
+            let length = length_reader.read_u32()?;
+            let data = reader.read_bytes(4 + length as usize)?;
+            attributes.push(AttributeInfo { name_index, data });
+        }
+        Ok(attributes)
+    }
+
+    pub fn version(&self) -> ClassFileVersion {
+        self.version
+    }
+
+    pub fn flags(&self) -> ClassFileAccessFlags {
+        ClassFileAccessFlags::new(self.flags)
+    }
+
+    pub fn constant_pool(&self) -> &LazyConstantPool<'a> {
+        &self.constant_pool
+    }
+
+    pub fn this_class(&self) -> Result<Cow<'a, str>> {
+        self.constant_pool.class_name(self.this_class)
+    }
+
+    pub fn super_class(&self) -> Result<Option<Cow<'a, str>>> {
+        match self.super_class {
+            0 => Ok(None),
+            index => self.constant_pool.class_name(index).map(Some),
+        }
+    }
+
+    pub fn interfaces(&self) -> impl Iterator<Item = Result<Cow<'a, str>>> + '_ {
+        self.interfaces.iter().map(|&index| self.constant_pool.class_name(index))
+    }
+
+    pub fn fields(&self) -> impl ExactSizeIterator<Item = LazyField<'_, 'a>> {
+        self.fields.iter().map(|member| LazyField { class: self, member })
+    }
+
+    pub fn methods(&self) -> impl ExactSizeIterator<Item = LazyMethod<'_, 'a>> {
+        self.methods.iter().map(|member| LazyMethod { class: self, member })
+    }
+
+    /// Finds a method by name and, optionally, by descriptor without decoding any other method.
+    pub fn method(
+        &self,
+        name: &str,
+        descriptor: Option<&str>,
+    ) -> Result<Option<LazyMethod<'_, 'a>>> {
+        for method in self.methods() {
+            if method.name()? != name {
+                continue;
+            }
+            if descriptor
+                .is_none_or(|descriptor| method.descriptor().is_ok_and(|d| d == descriptor))
+            {
+                return Ok(Some(method));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn attributes(&self) -> impl ExactSizeIterator<Item = LazyAttribute<'_, 'a>> {
+        self.attributes.iter().map(|attribute| LazyAttribute {
+            class: self,
+            attribute,
+            owner: AttributeOwner::Class,
+        })
+    }
+}
+
+/// Constant pool that knows where each entry starts and decodes entries on request.
+#[derive(Debug)]
+pub struct LazyConstantPool<'a> {
+    data: &'a [u8],
+    offsets: Vec<Option<usize>>,
+    materialized: OnceLock<ConstantPool>,
+}
+
+impl<'a> LazyConstantPool<'a> {
+    fn index(data: &'a [u8], reader: &mut ByteReader<'a>) -> Result<Self> {
+        let constant_pool_count = reader.read_u16()?;
+        let mut offsets = Vec::with_capacity(constant_pool_count as usize);
+        while offsets.len() + 1 < constant_pool_count as usize {
+            offsets.push(Some(reader.position()));
+            let tag = reader.read_u8()?;
+            let size = match tag {
+                1 => reader.read_u16()? as usize,
+                3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+                5 | 6 => 8,
+                7 | 8 | 16 | 19 | 20 => 2,
+                15 => 3,
+                _ => return Err(ClassReaderError::TagNotSupported(tag)),
+            };
+            reader.read_bytes(size)?;
+            if matches!(tag, 5 | 6) {
+                offsets.push(None);
+            }
+        }
+        Ok(LazyConstantPool { data, offsets, materialized: OnceLock::new() })
+    }
+
+    /// Number of constant pool slots, including the unusable ones following longs and doubles.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    fn offset(&self, index: u16) -> std::result::Result<usize, ConstantPoolError> {
+        match self.offsets.get((index as usize).wrapping_sub(1)) {
+            Some(Some(offset)) => Ok(*offset),
+            Some(None) => Err(ConstantPoolError::UnsuableConstant(index as usize)),
+            None => Err(ConstantPoolError::IndexOutOfBounds(index as usize)),
+        }
+    }
+
+    /// Decodes the constant at `index`, allocating only for `Utf8` entries.
+    pub fn get(&self, index: u16) -> Result<Constant> {
+        let offset = self.offset(index)?;
+        let mut reader =
+            ClassFileReader::with_constant_pool(&self.data[offset..], &EMPTY_CONSTANT_POOL);
+        reader.read_constant()
+    }
+
+    /// Returns the string at `index`, borrowed from the input unless it needs transcoding.
+    pub fn utf8(&self, index: u16) -> Result<Cow<'a, str>> {
+        let offset = self.offset(index)?;
+        let mut reader = ByteReader::new(&self.data[offset..]);
+        if reader.read_u8()? != 1 {
+            return Err(ClassReaderError::UnexpectedConstant {
+                expected: "Utf8".to_string(),
+                actual: self.get(index)?.name(),
+            });
+        }
+        let length = reader.read_u16()?;
+        Ok(reader.read_utf8(length as u32)?)
+    }
+
+    pub fn class_name(&self, index: u16) -> Result<Cow<'a, str>> {
+        match self.get(index)? {
+            Constant::ClassIndex(name_index) => self.utf8(name_index),
+            constant => Err(ClassReaderError::UnexpectedConstant {
+                expected: "ClassIndex".to_string(),
+                actual: constant.name(),
+            }),
+        }
+    }
+
+    /// Decodes the whole pool once. Decoding attributes does not need it, as they decode only
+    /// the constants they refer to.
+    pub fn materialize(&self) -> Result<&ConstantPool> {
+        if let Some(constant_pool) = self.materialized.get() {
+            return Ok(constant_pool);
+        }
+        let mut constant_pool = ConstantPool::default();
+        for index in 1..=self.offsets.len() as u16 {
+            if self.offsets[index as usize - 1].is_some() {
+                constant_pool.add(self.get(index)?);
+            }
+        }
+        Ok(self.materialized.get_or_init(|| constant_pool))
+    }
+}
+
+#[derive(Debug)]
+struct MemberInfo<'a> {
+    flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<AttributeInfo<'a>>,
+}
+
+#[derive(Debug)]
+struct AttributeInfo<'a> {
+    name_index: u16,
+    /// Attribute length followed by the attribute body.
+    data: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy)]
+enum AttributeOwner {
+    Class,
+    Field { descriptor_index: u16 },
+    Method,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LazyField<'c, 'a> {
+    class: &'c LazyClassFile<'a>,
+    member: &'c MemberInfo<'a>,
+}
+
+impl<'c, 'a> LazyField<'c, 'a> {
+    pub fn flags(&self) -> FieldAccessFlags {
+        FieldAccessFlags::new(self.member.flags)
+    }
+
+    pub fn name(&self) -> Result<Cow<'a, str>> {
+        self.class.constant_pool.utf8(self.member.name_index)
+    }
+
+    pub fn descriptor(&self) -> Result<Cow<'a, str>> {
+        self.class.constant_pool.utf8(self.member.descriptor_index)
+    }
+
+    pub fn field_type(&self) -> Result<FieldType> {
+        Ok(FieldType::try_from(&mut self.descriptor()?.chars().peekable())?)
+    }
+
+    pub fn attributes(&self) -> impl ExactSizeIterator<Item = LazyAttribute<'c, 'a>> {
+        let (class, descriptor_index) = (self.class, self.member.descriptor_index);
+        self.member.attributes.iter().map(move |attribute| LazyAttribute {
+            class,
+            attribute,
+            owner: AttributeOwner::Field { descriptor_index },
+        })
+    }
+
+    /// Decodes the field and all of its attributes.
+    pub fn to_field(&self) -> Result<Field> {
+        let attributes = self.attributes().map(|a| a.decode()).collect::<Result<Vec<_>>>()?;
+        Ok(Field::new(self.flags(), self.name()?.into_owned(), self.field_type()?, attributes))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LazyMethod<'c, 'a> {
+    class: &'c LazyClassFile<'a>,
+    member: &'c MemberInfo<'a>,
+}
+
+impl<'c, 'a> LazyMethod<'c, 'a> {
+    pub fn flags(&self) -> MethodAccessFlags {
+        MethodAccessFlags::new(self.member.flags)
+    }
+
+    pub fn name(&self) -> Result<Cow<'a, str>> {
+        self.class.constant_pool.utf8(self.member.name_index)
+    }
+
+    pub fn descriptor(&self) -> Result<Cow<'a, str>> {
+        self.class.constant_pool.utf8(self.member.descriptor_index)
+    }
+
+    pub fn method_descriptor(&self) -> Result<MethodDescriptor> {
+        Ok(MethodDescriptor::try_from(&mut self.descriptor()?.chars().peekable())?)
+    }
+
+    pub fn attributes(&self) -> impl ExactSizeIterator<Item = LazyAttribute<'c, 'a>> {
+        let class = self.class;
+        self.member.attributes.iter().map(move |attribute| LazyAttribute {
+            class,
+            attribute,
+            owner: AttributeOwner::Method,
+        })
+    }
+
+    /// Decodes the method body, or returns `None` for abstract and native methods.
+    pub fn code(&self) -> Result<Option<Code>> {
+        for attribute in self.attributes() {
+            if attribute.name()? == "Code" {
+                return match attribute.decode()? {
+                    Attribute::Code(code) => Ok(Some(code)),
+                    _ => unreachable!("Code attribute of a method decodes to Code"),
+                };
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decodes the method and all of its attributes.
+    pub fn to_method(&self) -> Result<Method> {
+        let attributes = self.attributes().map(|a| a.decode()).collect::<Result<Vec<_>>>()?;
+        Ok(Method {
+            flags: self.flags(),
+            name: self.name()?.into_owned(),
+            type_descriptor: self.method_descriptor()?,
+            attributes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LazyAttribute<'c, 'a> {
+    class: &'c LazyClassFile<'a>,
+    attribute: &'c AttributeInfo<'a>,
+    owner: AttributeOwner,
+}
+
+impl<'a> LazyAttribute<'_, 'a> {
+    pub fn name(&self) -> Result<Cow<'a, str>> {
+        self.class.constant_pool.utf8(self.attribute.name_index)
+    }
+
+    /// Raw attribute body, without the name index and length.
+    pub fn info(&self) -> &'a [u8] {
+        &self.attribute.data[4..]
+    }
+
+    pub fn decode(&self) -> Result<Attribute> {
+        let location = match self.owner {
+            AttributeOwner::Class => AttributeLocation::Class,
+            AttributeOwner::Field { descriptor_index } => {
+                let descriptor = self.class.constant_pool.utf8(descriptor_index)?;
+                AttributeLocation::Field(FieldType::try_from(&mut descriptor.chars().peekable())?)
+            }
+            AttributeOwner::Method => AttributeLocation::Method,
+        };
+        ClassFileReader::with_lazy_constant_pool(self.attribute.data, &self.class.constant_pool)
+            .read_attribute(self.attribute.name_index, &location)
+    }
+}
@@ -11,3 +11,33 @@ pub mod attribute;
 pub mod predefined_attributes;
 pub mod method;
 pub mod instruction;
+pub mod code_limits;
+pub mod code_view;
+pub mod lazy_class_file;
+pub mod byte_writer;
+pub mod class_visitor;
+pub mod class_file_writer;
+pub mod class_file_io;
+pub mod jar_file;
+pub mod jimage;
+pub mod jmod_file;
+pub mod classpath;
+pub mod bulk_reader;
+#[cfg(feature = "serde")]
+pub mod serde_support;
+pub mod class_diff;
+pub mod binary_compat;
+pub mod class_hierarchy;
+pub mod call_graph;
+pub mod dependency_analysis;
+pub mod control_flow;
+pub mod dominators;
+pub mod loops;
+pub mod stack_map;
+pub mod dataflow;
+pub mod ssa;
+pub mod optimizer;
+mod constant_pool_rewriting;
+pub mod shrinker;
+pub mod remapper;
+pub mod decompiler;
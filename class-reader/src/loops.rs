@@ -0,0 +1,183 @@
+//! Natural loops of [`ControlFlowGraph`]s and the detection of irreducible control flow.
+//!
+//! A back edge is an edge whose target dominates its source. The natural loop of a header
+//! is the header together with every block that reaches one of its back edges without going
+//! through the header; loops that share a header are merged. A cycle entered at more than
+//! one block has no header, which makes the graph irreducible: its retreating edges are
+//! reported instead, and its blocks are not part of any loop.
+
+use std::collections::BTreeSet;
+
+use crate::control_flow::{BlockId, ControlFlowGraph};
+use crate::dominators::{DominatorTree, reverse_postorder};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    pub header: BlockId,
+    /// The sources of the back edges to the header.
+    pub latches: Vec<BlockId>,
+    /// The blocks of the loop, including the header and the blocks of nested loops.
+    pub blocks: BTreeSet<BlockId>,
+    /// The index of the innermost enclosing loop.
+    pub parent: Option<usize>,
+    /// 1 for outermost loops.
+    pub depth: usize,
+}
+
+/// The natural loops of a method, outer loops before the loops they contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopNest {
+    pub loops: Vec<Loop>,
+    /// Edges `(from, to)` that go back to a block of the depth-first path leading to `from`
+    /// without `to` dominating `from`: each enters a cycle that has more than one entry.
+    pub irreducible_edges: Vec<(BlockId, BlockId)>,
+    innermost: Vec<Option<usize>>,
+}
+
+impl LoopNest {
+    /// Finds the loops of `graph` given its dominator tree, not its post-dominator tree.
+    pub fn new(graph: &ControlFlowGraph, dominators: &DominatorTree) -> Self {
+        let successors =
+            (0..graph.len()).map(|block| graph.successors(block).to_vec()).collect::<Vec<_>>();
+        Self::from_successors(&successors, dominators)
+    }
+
+    /// The loops of the graph whose nodes have the given distinct successors, entered at
+    /// node 0.
+    pub(crate) fn from_successors(successors: &[Vec<BlockId>], dominators: &DominatorTree) -> Self {
+        let mut predecessors = vec![Vec::new(); successors.len()];
+        for (node, targets) in successors.iter().enumerate() {
+            for &target in targets {
+                predecessors[target].push(node);
+            }
+        }
+        let order = reverse_postorder(successors, 0);
+        let mut loops = Vec::new();
+        for &header in &order {
+            let latches = predecessors[header]
+                .iter()
+                .copied()
+                .filter(|&latch| dominators.dominates(header, latch))
+                .collect::<Vec<_>>();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut blocks = BTreeSet::from([header]);
+            let mut work = latches.clone();
+            while let Some(block) = work.pop() {
+                if dominators.is_reachable(block) && blocks.insert(block) {
+                    work.extend(&predecessors[block]);
+                }
+            }
+            loops.push(Loop { header, latches, blocks, parent: None, depth: 1 });
+        }
+
+        // Headers dominate the blocks of their loops, so an enclosing loop comes earlier in
+        // reverse postorder and the innermost one is the last to contain the header.
+        for index in 0..loops.len() {
+            let header = loops[index].header;
+            if let Some(parent) =
+                (0..index).rev().find(|&outer| loops[outer].blocks.contains(&header))
+            {
+                loops[index].parent = Some(parent);
+                loops[index].depth = loops[parent].depth + 1;
+            }
+        }
+        let mut innermost = vec![None; successors.len()];
+        for (index, found) in loops.iter().enumerate() {
+            for &block in &found.blocks {
+                innermost[block] = Some(index);
+            }
+        }
+
+        let irreducible_edges = retreating_edges(successors)
+            .into_iter()
+            .filter(|&(from, to)| !dominators.dominates(to, from))
+            .collect();
+        LoopNest { loops, irreducible_edges, innermost }
+    }
+
+    pub fn is_reducible(&self) -> bool {
+        self.irreducible_edges.is_empty()
+    }
+
+    /// The index of the innermost loop containing `block`.
+    pub fn innermost_loop(&self, block: BlockId) -> Option<usize> {
+        self.innermost[block]
+    }
+
+    /// The number of loops containing `block`.
+    pub fn loop_depth(&self, block: BlockId) -> usize {
+        self.innermost[block].map_or(0, |index| self.loops[index].depth)
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.loops.iter().map(|found| found.depth).max().unwrap_or(0)
+    }
+
+    pub fn is_loop_header(&self, block: BlockId) -> bool {
+        self.loops.iter().any(|found| found.header == block)
+    }
+}
+
+/// The edges to a node on the depth-first path from node 0 to their source.
+fn retreating_edges(successors: &[Vec<BlockId>]) -> Vec<(BlockId, BlockId)> {
+    let mut visited = vec![false; successors.len()];
+    let mut on_path = vec![false; successors.len()];
+    let mut edges = Vec::new();
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    on_path[0] = true;
+    while let Some((block, next)) = stack.pop() {
+        let Some(&successor) = successors[block].get(next) else {
+            on_path[block] = false;
+            continue;
+        };
+        stack.push((block, next + 1));
+        if on_path[successor] {
+            edges.push((block, successor));
+        } else if !visited[successor] {
+            visited[successor] = true;
+            on_path[successor] = true;
+            stack.push((successor, 0));
+        }
+    }
+    edges.sort();
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Instruction;
+    use crate::predefined_attributes::Code;
+
+    #[test]
+    fn test_irreducible_loop() {
+        // A cycle between the blocks at 4 and 8, entered at both from the block at 0.
+        let code = Code {
+            max_stack: 1,
+            max_locals: 1,
+            code: vec![
+                (Instruction::Iload_0, 0),
+                (Instruction::Ifeq(7), 1),
+                (Instruction::Iload_0, 4),
+                (Instruction::Ifeq(7), 5),
+                (Instruction::Nop, 8),
+                (Instruction::Goto(-5i16 as u16), 9),
+                (Instruction::Return, 12),
+            ],
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let graph = ControlFlowGraph::new(&code).unwrap();
+        let dominators = DominatorTree::new(&graph);
+        let loops = LoopNest::new(&graph, &dominators);
+
+        assert_eq!(dominators.immediate_dominator(1), Some(0));
+        assert_eq!(dominators.immediate_dominator(2), Some(0));
+        assert!(loops.loops.is_empty());
+        assert!(!loops.is_reducible());
+        assert_eq!(loops.irreducible_edges, [(1, 2)]);
+    }
+}
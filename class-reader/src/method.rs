@@ -1,8 +1,11 @@
+use std::fmt::Display;
 use std::iter::Peekable;
 use std::str::Chars;
 
+use crate::access_flag::{AccessFlags, Flag};
 use crate::attribute::Attribute;
 use crate::field::{FieldError, FieldType};
+use crate::predefined_attributes::Code;
 
 type Result<T> = std::result::Result<T, MethodParsingError>;
 
@@ -20,6 +23,7 @@ pub enum MethodParsingError {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Method {
     pub flags: MethodAccessFlags,
     pub name: String,
@@ -27,7 +31,17 @@ pub struct Method {
     pub attributes: Vec<Attribute>,
 }
 
-#[derive(Debug, Clone)]
+impl Method {
+    /// The `Code` attribute, absent for abstract and native methods.
+    pub fn code(&self) -> Option<&Code> {
+        self.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MethodFlag {
     Public,
     Private,
@@ -43,7 +57,7 @@ pub enum MethodFlag {
     Synthetic,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MethodAccessFlags {
     flags: Vec<MethodFlag>,
 }
@@ -102,6 +116,54 @@ impl MethodAccessFlags {
 
         MethodAccessFlags { flags }
     }
+
+    pub fn is_static(&self) -> bool {
+        self.contains(MethodFlag::Static)
+    }
+}
+
+impl AccessFlags for MethodAccessFlags {
+    type Flag = MethodFlag;
+
+    fn flags(&self) -> &[MethodFlag] {
+        &self.flags
+    }
+}
+
+impl Flag for MethodFlag {
+    fn mask(&self) -> u16 {
+        match self {
+            MethodFlag::Public => 0x0001,
+            MethodFlag::Private => 0x0002,
+            MethodFlag::Protected => 0x0004,
+            MethodFlag::Static => 0x0008,
+            MethodFlag::Final => 0x0010,
+            MethodFlag::Synchronized => 0x0020,
+            MethodFlag::Bridge => 0x0040,
+            MethodFlag::Varargs => 0x0080,
+            MethodFlag::Native => 0x0100,
+            MethodFlag::Abstract => 0x0400,
+            MethodFlag::Strict => 0x0800,
+            MethodFlag::Synthetic => 0x1000,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MethodFlag::Public => "public",
+            MethodFlag::Private => "private",
+            MethodFlag::Protected => "protected",
+            MethodFlag::Static => "static",
+            MethodFlag::Final => "final",
+            MethodFlag::Synchronized => "synchronized",
+            MethodFlag::Bridge => "bridge",
+            MethodFlag::Varargs => "varargs",
+            MethodFlag::Native => "native",
+            MethodFlag::Abstract => "abstract",
+            MethodFlag::Strict => "strict",
+            MethodFlag::Synthetic => "synthetic",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -111,6 +173,18 @@ pub struct ParameterDescriptor(Vec<FieldType>);
 pub struct MethodDescriptor(ParameterDescriptor, ReturnDescriptor);
 
 impl MethodDescriptor {
+    pub fn new(parameters: Vec<FieldType>, return_type: ReturnDescriptor) -> Self {
+        MethodDescriptor(ParameterDescriptor(parameters), return_type)
+    }
+
+    pub fn parameters(&self) -> &[FieldType] {
+        &self.0.0
+    }
+
+    pub fn return_type(&self) -> &ReturnDescriptor {
+        &self.1
+    }
+
     pub fn try_from(chars: &mut Peekable<Chars>) -> Result<MethodDescriptor> {
         if chars.next() != Some('(') {
             return Err(MethodParsingError::NoOpeningBracket);
@@ -148,6 +222,25 @@ pub enum ReturnDescriptor {
 #[derive(Debug)]
 pub struct VoidDescriptor;
 
+impl Display for ReturnDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReturnDescriptor::FieldType(field_type) => write!(f, "{}", field_type),
+            ReturnDescriptor::VoidDescriptor => write!(f, "V"),
+        }
+    }
+}
+
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for parameter in self.parameters() {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, "){}", self.return_type())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +296,13 @@ mod tests {
         assert_eq!(result.unwrap(), expected);
     }
 
+    #[test]
+    fn descriptor_display_round_trip() {
+        let descriptor = "(IDLjava/lang/Thread;[J)Ljava/lang/Object;";
+        let result = MethodDescriptor::try_from(&mut descriptor.chars().peekable()).unwrap();
+        assert_eq!(result.to_string(), descriptor);
+    }
+
     #[test]
     fn invalid_descriptor_missing_opening_bracket() {
         let descriptor = "IDLjava/lang/Thread;)Ljava/lang/Object;";
@@ -0,0 +1,1593 @@
+//! Optimization of method bytecode without leaving the stack-based form.
+//!
+//! The [`Optimizer`] runs a pipeline of passes over the instructions of a method until none
+//! of them finds anything left to do:
+//!
+//! - constant folding replaces arithmetic, comparisons and conversions whose operands are
+//!   pushed as constants right before them by a push of the result;
+//! - branch simplification decides conditional branches and switches on constants, removes
+//!   branches to the next instruction and turns a conditional branch over a `goto` into the
+//!   opposite branch;
+//! - jump threading sends branches to a `goto` straight to its target, and replaces a `goto`
+//!   to a return by the return;
+//! - dead store removal discards values stored to local variables that are never read
+//!   again, then removes values pushed only to be popped;
+//! - unreachable code removal, which always runs since the other passes leave such code
+//!   behind and the verifier rejects it without `StackMapTable` frames.
+//!
+//! Finally loads, stores and constants are given their shortest encodings.
+//!
+//! The passes edit instructions in place: an instruction is replaced by another or removed,
+//! never moved, and a branch to a removed instruction continues at the next remaining one.
+//! The reassembled code keeps its exception table, `LineNumberTable`, `LocalVariableTable`,
+//! `LocalVariableTypeTable` and `StackMapTable` consistent with the new pcs. Stores to
+//! variables described by the `LocalVariableTable` are kept for debuggers. Methods with
+//! subroutines (`jsr` and `ret`) or with code attributes the reader does not decode are left
+//! as they are.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use thiserror::Error;
+
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::code_limits::{CodeLimits, CodeLimitsError};
+use crate::code_view::CodeView;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::dataflow::{
+    Access, Const, Context, DataflowAnalysis, Liveness, ValueType, fold, local_access,
+};
+use crate::field::{BaseType, FieldType};
+use crate::instruction::{Instruction, WideInstruction};
+use crate::method::Method;
+use crate::predefined_attributes::{
+    Code, ExceptionHandler, LineNumber, LineNumberTable, LocalVariable, LocalVariableTable,
+    LocalVariableType, LocalVariableTypeTable, StackMapFrame, StackMapTable, VerificationTypeInfo,
+};
+use crate::ssa::{load, store};
+
+type Result<T> = std::result::Result<T, OptimizerError>;
+
+/// The passes stop after this many rounds even if the last one changed something.
+const MAX_ROUNDS: usize = 16;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum OptimizerError {
+    #[error("{attribute} refers to pc {pc}, which is not the start of an instruction")]
+    #[non_exhaustive]
+    InvalidOffset { attribute: &'static str, pc: u32 },
+    #[error("StackMapTable frame at pc {0} chops more locals than the previous frame has")]
+    #[non_exhaustive]
+    InvalidChop(u32),
+    #[error("Branch at pc {0} of the optimized code is out of range")]
+    #[non_exhaustive]
+    BranchOutOfRange(u32),
+    #[error("{0}")]
+    #[non_exhaustive]
+    CodeLimits(#[from] CodeLimitsError),
+}
+
+/// A pipeline of bytecode optimizations, all enabled by default.
+#[derive(Debug, Clone)]
+pub struct Optimizer {
+    fold_constants: bool,
+    simplify_branches: bool,
+    thread_jumps: bool,
+    remove_dead_stores: bool,
+    shorten_encodings: bool,
+}
+
+impl Default for Optimizer {
+    fn default() -> Self {
+        Optimizer {
+            fold_constants: true,
+            simplify_branches: true,
+            thread_jumps: true,
+            remove_dead_stores: true,
+            shorten_encodings: true,
+        }
+    }
+}
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Optimizer::default()
+    }
+
+    pub fn fold_constants(&mut self, enabled: bool) -> &mut Self {
+        self.fold_constants = enabled;
+        self
+    }
+
+    pub fn simplify_branches(&mut self, enabled: bool) -> &mut Self {
+        self.simplify_branches = enabled;
+        self
+    }
+
+    pub fn thread_jumps(&mut self, enabled: bool) -> &mut Self {
+        self.thread_jumps = enabled;
+        self
+    }
+
+    pub fn remove_dead_stores(&mut self, enabled: bool) -> &mut Self {
+        self.remove_dead_stores = enabled;
+        self
+    }
+
+    pub fn shorten_encodings(&mut self, enabled: bool) -> &mut Self {
+        self.shorten_encodings = enabled;
+        self
+    }
+
+    /// Optimizes every method of `class` that has code.
+    pub fn optimize(&self, class: &mut ClassFile) -> Result<()> {
+        for method in &mut class.methods {
+            self.optimize_method(&mut class.constant_pool, &class.this_class, method)?;
+        }
+        Ok(())
+    }
+
+    /// Optimizes the code of `method`, a method of `this_class`, adding the constants that
+    /// folded values need to `constant_pool`. Returns whether the code changed; `max_stack`
+    /// is recomputed when it did.
+    pub fn optimize_method(
+        &self,
+        constant_pool: &mut ConstantPool,
+        this_class: &str,
+        method: &mut Method,
+    ) -> Result<bool> {
+        let Some(code) = method.code() else {
+            return Ok(false);
+        };
+        let unsupported = code.code.iter().any(|(instruction, _)| {
+            matches!(
+                instruction,
+                Instruction::Jsr(_)
+                    | Instruction::Jsr_w(_)
+                    | Instruction::Ret(_)
+                    | Instruction::Wide(WideInstruction::Ret(_))
+            )
+        });
+        let undecoded =
+            code.attributes.iter().any(|attribute| matches!(attribute, Attribute::UserDefined(_)));
+        if unsupported || undecoded || code.code.is_empty() {
+            return Ok(false);
+        }
+
+        let mut editor = Editor::new(code)?;
+        for _ in 0..MAX_ROUNDS {
+            let edits = editor.edits;
+            if self.fold_constants {
+                editor.fold_constants(constant_pool);
+            }
+            if self.simplify_branches {
+                editor.simplify_branches(constant_pool);
+            }
+            if self.thread_jumps {
+                editor.thread_jumps();
+            }
+            if self.remove_dead_stores {
+                editor.remove_dead_stores();
+                editor.remove_unused_pushes(constant_pool);
+            }
+            editor.remove_unreachable_code();
+            if editor.edits == edits {
+                break;
+            }
+        }
+        if self.shorten_encodings {
+            editor.shorten_encodings(constant_pool);
+        }
+        if editor.edits == 0 {
+            return Ok(false);
+        }
+        let has_frames = code
+            .attributes
+            .iter()
+            .any(|attribute| matches!(attribute, Attribute::StackMapTable(_)));
+        let initial = match has_frames {
+            true => initial_locals(constant_pool, this_class, method),
+            false => Vec::new(),
+        };
+        let optimized = editor.assemble(&initial, self.shorten_encodings)?;
+
+        for attribute in &mut method.attributes {
+            if let Attribute::Code(code) = attribute {
+                *code = optimized;
+                break;
+            }
+        }
+        let limits = CodeLimits::compute(constant_pool, method)?;
+        for attribute in &mut method.attributes {
+            if let Attribute::Code(code) = attribute {
+                code.max_stack = limits.max_stack;
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// The instructions of a method being optimized, by their position in the original code.
+struct Editor<'a> {
+    code: &'a Code,
+    view: CodeView<'a>,
+    /// The instructions, `None` where removed.
+    instructions: Vec<Option<Instruction>>,
+    /// The positions each branch goes to, the default first for switches.
+    targets: Vec<Vec<usize>>,
+    /// The exception table with positions: the start, the end, which may be one past the last
+    /// instruction, and the handler.
+    handlers: Vec<(usize, usize, usize)>,
+    /// The instructions removed because they cannot be reached, whose frames go with them.
+    unreachable: Vec<bool>,
+    /// The slots of removed stores, which frames no longer describe where they are dead.
+    dead_slots: BTreeSet<u16>,
+    /// The number of changes made so far.
+    edits: usize,
+}
+
+impl<'a> Editor<'a> {
+    fn new(code: &'a Code) -> Result<Self> {
+        let view = CodeView::new(code);
+        let position = |pc: i64, attribute| {
+            u32::try_from(pc)
+                .ok()
+                .and_then(|pc| view.position(pc))
+                .ok_or(OptimizerError::InvalidOffset { attribute, pc: pc as u32 })
+        };
+        let mut targets = Vec::with_capacity(code.code.len());
+        for (instruction, pc) in &code.code {
+            let offsets = match instruction {
+                Instruction::Tableswitch { default, offsets, .. } => {
+                    std::iter::once(*default).chain(offsets.iter().copied()).collect()
+                }
+                Instruction::Lookupswitch { default, pairs } => std::iter::once(*default)
+                    .chain(pairs.iter().map(|(_, offset)| *offset))
+                    .collect(),
+                _ => instruction.branch_offset().into_iter().collect::<Vec<_>>(),
+            };
+            let positions = offsets
+                .into_iter()
+                .map(|offset| position(*pc as i64 + offset as i64, "Branch"))
+                .collect::<Result<Vec<_>>>()?;
+            targets.push(positions);
+        }
+        let end = |pc: u16| match pc as u32 == view.code_length() {
+            true => Ok(code.code.len()),
+            false => position(pc as i64, "Exception table"),
+        };
+        let handlers = code
+            .exception_table
+            .iter()
+            .map(|entry| {
+                Ok((
+                    position(entry.start_pc as i64, "Exception table")?,
+                    end(entry.end_pc)?,
+                    position(entry.handler_pc as i64, "Exception table")?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Editor {
+            code,
+            view,
+            instructions: code
+                .code
+                .iter()
+                .map(|(instruction, _)| Some(instruction.clone()))
+                .collect(),
+            targets,
+            handlers,
+            unreachable: vec![false; code.code.len()],
+            dead_slots: BTreeSet::new(),
+            edits: 0,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    fn instruction(&self, position: usize) -> Option<&Instruction> {
+        self.instructions[position].as_ref()
+    }
+
+    /// Replaces the instruction at `position`, forgetting its targets unless the replacement
+    /// is a branch too.
+    fn replace(&mut self, position: usize, instruction: Instruction) {
+        if instruction.branch_offset().is_none() && !is_switch(&instruction) {
+            self.targets[position].clear();
+        }
+        self.instructions[position] = Some(instruction);
+        self.edits += 1;
+    }
+
+    fn remove(&mut self, position: usize) {
+        self.instructions[position] = None;
+        self.targets[position].clear();
+        self.edits += 1;
+    }
+
+    fn retarget(&mut self, position: usize, targets: Vec<usize>) {
+        self.targets[position] = targets;
+        self.edits += 1;
+    }
+
+    /// The first remaining instruction at or after `position`, where control continues when
+    /// it reaches `position`. `None` past the last instruction.
+    fn resolve(&self, position: usize) -> Option<usize> {
+        (position..self.len()).find(|&position| self.instructions[position].is_some())
+    }
+
+    /// The remaining instruction before `position`.
+    fn previous(&self, position: usize) -> Option<usize> {
+        (0..position).rev().find(|&position| self.instructions[position].is_some())
+    }
+
+    /// The instructions control continues at after the one at `position`, without exception
+    /// handlers.
+    fn successors(&self, position: usize) -> Vec<usize> {
+        let Some(instruction) = self.instruction(position) else {
+            return Vec::new();
+        };
+        let mut successors = self.targets[position]
+            .iter()
+            .filter_map(|&target| self.resolve(target))
+            .collect::<Vec<_>>();
+        if !instruction.is_terminator() {
+            successors.extend(self.resolve(position + 1));
+        }
+        successors
+    }
+
+    /// The handlers of the exception table entries covering `position`.
+    fn handlers_at(&self, position: usize) -> Vec<usize> {
+        self.handlers
+            .iter()
+            .filter(|(start, end, _)| (*start..*end).contains(&position))
+            .filter_map(|(_, _, handler)| self.resolve(*handler))
+            .collect()
+    }
+
+    /// Which instructions control can reach other than from the instruction before them: the
+    /// targets of branches and exception handlers.
+    fn leaders(&self) -> Vec<bool> {
+        let mut leaders = vec![false; self.len()];
+        for position in 0..self.len() {
+            if self.instructions[position].is_some() {
+                for &target in &self.targets[position] {
+                    if let Some(target) = self.resolve(target) {
+                        leaders[target] = true;
+                    }
+                }
+            }
+        }
+        for (_, _, handler) in &self.handlers {
+            if let Some(handler) = self.resolve(*handler) {
+                leaders[handler] = true;
+            }
+        }
+        leaders
+    }
+
+    /// Recomputes `leaders` if the code changed since it was computed at `edits`, as removing
+    /// an instruction sends the branches to it to the next one.
+    fn refresh_leaders(&self, leaders: &mut Vec<bool>, edits: &mut usize) {
+        if *edits != self.edits {
+            *leaders = self.leaders();
+            *edits = self.edits;
+        }
+    }
+
+    /// The constants pushed by the instructions right before `position` for it to pop,
+    /// `slots` slots in all, with the positions of the pushes. `None` unless every operand is
+    /// such a constant and control can only reach `position` and the pushes after the first
+    /// one from the instruction before them.
+    fn constant_operands(
+        &self,
+        constant_pool: &ConstantPool,
+        leaders: &[bool],
+        position: usize,
+        slots: u16,
+    ) -> Option<(Vec<Const>, Vec<usize>)> {
+        let mut operands = Vec::new();
+        let mut positions = Vec::new();
+        let mut current = position;
+        let mut count = 0;
+        while count < slots {
+            if leaders[current] {
+                return None;
+            }
+            let previous = self.previous(current)?;
+            let value = pushed_constant(constant_pool, self.instruction(previous)?)?;
+            count += const_size(&value);
+            operands.insert(0, value);
+            positions.insert(0, previous);
+            current = previous;
+        }
+        (count == slots).then_some((operands, positions))
+    }
+
+    fn fold_constants(&mut self, constant_pool: &mut ConstantPool) {
+        let (mut leaders, mut edits) = (self.leaders(), self.edits);
+        for position in 0..self.len() {
+            self.refresh_leaders(&mut leaders, &mut edits);
+            let Some(instruction) = self.instruction(position).cloned() else {
+                continue;
+            };
+            if local_access(&instruction).is_some() {
+                continue;
+            }
+            let Some(effect) = instruction.stack_effect(constant_pool) else {
+                continue;
+            };
+            if effect.pops == 0 || effect.pushes == 0 {
+                continue;
+            }
+            let Some((operands, positions)) =
+                self.constant_operands(constant_pool, &leaders, position, effect.pops)
+            else {
+                continue;
+            };
+            let context = Context {
+                instruction: &instruction,
+                pc: self.code.code[position].1,
+                constant_pool,
+            };
+            let Some(result) = fold(&context, &operands.iter().collect::<Vec<_>>()) else {
+                continue;
+            };
+            let Some(push) = push_constant(constant_pool, &result) else {
+                continue;
+            };
+            // The result takes the place of the first push, which control may reach from
+            // elsewhere.
+            self.replace(positions[0], push);
+            for &push in &positions[1..] {
+                self.remove(push);
+            }
+            self.remove(position);
+        }
+    }
+
+    fn simplify_branches(&mut self, constant_pool: &ConstantPool) {
+        let (mut leaders, mut edits) = (self.leaders(), self.edits);
+        for position in 0..self.len() {
+            self.refresh_leaders(&mut leaders, &mut edits);
+            let Some(instruction) = self.instruction(position).cloned() else {
+                continue;
+            };
+            let is_goto = matches!(instruction, Instruction::Goto(_) | Instruction::Goto_w(_));
+            if !is_goto && !instruction.is_conditional() && !is_switch(&instruction) {
+                continue;
+            }
+            let next = self.resolve(position + 1);
+
+            // A branch to the next instruction only pops its operands.
+            let targets = &self.targets[position];
+            if next.is_some() && targets.iter().all(|&target| self.resolve(target) == next) {
+                match operand_count(&instruction) {
+                    0 => self.remove(position),
+                    1 => self.replace(position, Instruction::Pop),
+                    _ => self.replace(position, Instruction::Pop2),
+                }
+                continue;
+            }
+            if is_goto {
+                continue;
+            }
+
+            // A branch on constants goes one way.
+            let operands = operand_count(&instruction) as u16;
+            if let Some((values, pushes)) =
+                self.constant_operands(constant_pool, &leaders, position, operands)
+            {
+                let taken = match is_switch(&instruction) {
+                    true => switch_target(&instruction, &values),
+                    false => branch_taken(&instruction, &values).map(|taken| match taken {
+                        true => Some(0),
+                        false => None,
+                    }),
+                };
+                if let Some(taken) = taken {
+                    for push in pushes {
+                        self.remove(push);
+                    }
+                    match taken {
+                        Some(index) => {
+                            self.retarget(position, vec![self.targets[position][index]]);
+                            self.replace(position, Instruction::Goto(0));
+                        }
+                        None => self.remove(position),
+                    }
+                    continue;
+                }
+            }
+
+            // `if<cond> L; goto M; L:` becomes `if<!cond> M`.
+            let Some(next) = next else {
+                continue;
+            };
+            if !instruction.is_conditional()
+                || leaders[next]
+                || !matches!(
+                    self.instruction(next),
+                    Some(Instruction::Goto(_) | Instruction::Goto_w(_))
+                )
+            {
+                continue;
+            }
+            let over = self.resolve(next + 1);
+            if over.is_some() && self.resolve(self.targets[position][0]) == over {
+                self.retarget(position, self.targets[next].clone());
+                self.replace(position, negate(&instruction));
+                self.remove(next);
+            }
+        }
+    }
+
+    fn thread_jumps(&mut self) {
+        for position in 0..self.len() {
+            if self.instructions[position].is_none() {
+                continue;
+            }
+            for index in 0..self.targets[position].len() {
+                let Some(original) = self.resolve(self.targets[position][index]) else {
+                    continue;
+                };
+                // Follow chains of gotos, giving up on loops of them.
+                let mut target = original;
+                for _ in 0..self.len() {
+                    match self.instruction(target) {
+                        Some(Instruction::Goto(_) | Instruction::Goto_w(_)) => {
+                            match self.resolve(self.targets[target][0]) {
+                                Some(next) if next != target => target = next,
+                                _ => break,
+                            }
+                        }
+                        _ => break,
+                    }
+                }
+                if target != original {
+                    self.targets[position][index] = target;
+                    self.edits += 1;
+                }
+            }
+
+            // A goto to a return returns right away, if the same handlers cover both.
+            if !matches!(
+                self.instruction(position),
+                Some(Instruction::Goto(_) | Instruction::Goto_w(_))
+            ) {
+                continue;
+            }
+            let Some(target) = self.resolve(self.targets[position][0]) else {
+                continue;
+            };
+            let returns = matches!(
+                self.instruction(target),
+                Some(
+                    Instruction::Return
+                        | Instruction::Ireturn
+                        | Instruction::Lreturn
+                        | Instruction::Freturn
+                        | Instruction::Dreturn
+                        | Instruction::Areturn
+                        | Instruction::Athrow
+                )
+            );
+            if returns && self.handlers_at(position) == self.handlers_at(target) {
+                let instruction = self.instruction(target).cloned().expect("the target remains");
+                self.replace(position, instruction);
+            }
+        }
+    }
+
+    /// The live local variable slots before each instruction, with the handlers covering an
+    /// instruction as its successors.
+    fn liveness(&self) -> Vec<BTreeSet<u16>> {
+        let mut before = vec![BTreeSet::new(); self.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for position in (0..self.len()).rev() {
+                let Some(instruction) = self.instruction(position) else {
+                    continue;
+                };
+                let mut state = self.live_after(&before, position);
+                Liveness.transfer(&mut state, instruction, 0);
+                for handler in self.handlers_at(position) {
+                    state.extend(&before[handler]);
+                }
+                if state != before[position] {
+                    before[position] = state;
+                    changed = true;
+                }
+            }
+        }
+        before
+    }
+
+    fn live_after(&self, before: &[BTreeSet<u16>], position: usize) -> BTreeSet<u16> {
+        let mut state = BTreeSet::new();
+        for successor in self.successors(position) {
+            state.extend(&before[successor]);
+        }
+        state
+    }
+
+    fn remove_dead_stores(&mut self) {
+        let live = self.liveness();
+        for position in 0..self.len() {
+            let Some(instruction) = self.instruction(position) else {
+                continue;
+            };
+            let Some((local, size, access)) = local_access(instruction) else {
+                continue;
+            };
+            if access == Access::Read {
+                continue;
+            }
+            let slots = local..local + size;
+            let live_after = self.live_after(&live, position);
+            if slots.clone().any(|slot| live_after.contains(&slot)) {
+                continue;
+            }
+            let (original, pc) = &self.code.code[position];
+            if self.view.local_variable(local, pc + original.length(*pc)).is_some() {
+                continue;
+            }
+            match (access, size) {
+                (Access::ReadWrite, _) => self.remove(position),
+                _ => self.replace(position, pop(size)),
+            }
+            self.dead_slots.extend(slots);
+        }
+    }
+
+    /// Removes pops of values pushed by the instruction right before them without side
+    /// effects, or pops the operands of arithmetic whose result is dropped instead.
+    fn remove_unused_pushes(&mut self, constant_pool: &ConstantPool) {
+        let (mut leaders, mut edits) = (self.leaders(), self.edits);
+        for position in 0..self.len() {
+            self.refresh_leaders(&mut leaders, &mut edits);
+            let slots = match self.instruction(position) {
+                Some(Instruction::Pop) => 1,
+                Some(Instruction::Pop2) => 2,
+                _ => continue,
+            };
+            if leaders[position] {
+                continue;
+            }
+            let Some(previous) = self.previous(position) else {
+                continue;
+            };
+            let instruction = self.instruction(previous).expect("previous instructions remain");
+            let pushed = match instruction {
+                Instruction::Dup => Some(1),
+                _ => match local_access(instruction) {
+                    Some((_, size, Access::Read)) => Some(size),
+                    Some(_) => None,
+                    None => {
+                        pushed_constant(constant_pool, instruction).map(|value| const_size(&value))
+                    }
+                },
+            };
+            if pushed == Some(slots) {
+                self.remove(previous);
+                self.remove(position);
+                continue;
+            }
+            // A value computed without side effects is dropped by dropping its operands.
+            let pushes = instruction.stack_effect(constant_pool).map(|effect| effect.pushes);
+            match (pure_operands(instruction), pushes == Some(slots)) {
+                (Some([operand]), true) => {
+                    let operand = pop(*operand);
+                    self.remove(previous);
+                    self.replace(position, operand);
+                }
+                (Some([first, second]), true) => {
+                    let (first, second) = (pop(*first), pop(*second));
+                    self.replace(previous, second);
+                    self.replace(position, first);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn remove_unreachable_code(&mut self) {
+        let mut reachable = vec![false; self.len()];
+        let mut worklist = self.resolve(0).into_iter().collect::<Vec<_>>();
+        while let Some(position) = worklist.pop() {
+            if std::mem::replace(&mut reachable[position], true) {
+                continue;
+            }
+            worklist.extend(self.successors(position));
+            worklist.extend(self.handlers_at(position));
+        }
+        for (position, reachable) in reachable.into_iter().enumerate() {
+            if self.instructions[position].is_some() && !reachable {
+                self.remove(position);
+                self.unreachable[position] = true;
+            }
+        }
+    }
+
+    fn shorten_encodings(&mut self, constant_pool: &mut ConstantPool) {
+        for position in 0..self.len() {
+            let Some(instruction) = self.instruction(position) else {
+                continue;
+            };
+            let constant = pushed_constant(constant_pool, instruction)
+                .filter(|value| !matches!(value, Const::String(_)));
+            let shorter = match (constant, instruction) {
+                (Some(value), _) => push_constant(constant_pool, &value),
+                (None, Instruction::Ldc_w(index)) => {
+                    u8::try_from(*index).ok().map(Instruction::Ldc)
+                }
+                (None, Instruction::Wide(WideInstruction::Iinc(local, increment))) => {
+                    match (u8::try_from(*local), i8::try_from(*increment)) {
+                        (Ok(local), Ok(increment)) => Some(Instruction::Iinc(local, increment)),
+                        _ => None,
+                    }
+                }
+                (None, instruction) => match local_access(instruction) {
+                    Some((local, _, Access::Read)) => Some(load(local_type(instruction), local)),
+                    Some((local, _, Access::Write)) => Some(store(local_type(instruction), local)),
+                    _ => None,
+                },
+            };
+            let pc = self.code.code[position].1;
+            if let Some(shorter) = shorter {
+                let instruction = self.instruction(position).expect("the instruction remains");
+                if shorter.length(pc) < instruction.length(pc) {
+                    self.replace(position, shorter);
+                }
+            }
+        }
+    }
+
+    /// Lays out the remaining instructions and maps the pcs of the exception table and of
+    /// the attributes to theirs. `initial` are the locals on method entry, which the first
+    /// `StackMapTable` frame is relative to.
+    fn assemble(&self, initial: &[VerificationTypeInfo], shorten: bool) -> Result<Code> {
+        let positions = (0..self.len())
+            .filter(|&position| self.instructions[position].is_some())
+            .collect::<Vec<_>>();
+        let mut indices = vec![usize::MAX; self.len()];
+        for (index, &position) in positions.iter().enumerate() {
+            indices[position] = index;
+        }
+        let mut instructions = positions
+            .iter()
+            .map(|&position| self.instruction(position).cloned().expect("positions remain"))
+            .collect::<Vec<_>>();
+
+        // Gotos start short when encodings are shortened and are widened until every one
+        // reaches its target.
+        let mut wide = instructions
+            .iter()
+            .map(|instruction| !shorten && matches!(instruction, Instruction::Goto_w(_)))
+            .collect::<Vec<_>>();
+        let target = |position: usize, index: usize| {
+            self.resolve(self.targets[position][index]).expect("branches target instructions")
+        };
+        let pcs = loop {
+            let mut pcs = Vec::with_capacity(instructions.len() + 1);
+            let mut pc = 0;
+            for (index, instruction) in instructions.iter_mut().enumerate() {
+                if matches!(instruction, Instruction::Goto(_) | Instruction::Goto_w(_)) {
+                    *instruction = match wide[index] {
+                        true => Instruction::Goto_w(0),
+                        false => Instruction::Goto(0),
+                    };
+                }
+                pcs.push(pc);
+                pc += instruction.length(pc);
+            }
+            pcs.push(pc);
+            let mut widened = false;
+            for (index, instruction) in instructions.iter().enumerate() {
+                if let Instruction::Goto(_) = instruction {
+                    let offset =
+                        pcs[indices[target(positions[index], 0)]] as i64 - pcs[index] as i64;
+                    if i16::try_from(offset).is_err() {
+                        wide[index] = true;
+                        widened = true;
+                    }
+                }
+            }
+            if !widened {
+                break pcs;
+            }
+        };
+        let code_length = *pcs.last().expect("pcs end with the code length");
+        if code_length > u16::MAX as u32 {
+            return Err(OptimizerError::BranchOutOfRange(code_length));
+        }
+        // The new pc of where control continues at `position`.
+        let new_pc = |position: usize| match self.resolve(position) {
+            Some(position) => pcs[indices[position]],
+            None => code_length,
+        };
+
+        for (index, instruction) in instructions.iter_mut().enumerate() {
+            let position = positions[index];
+            let pc = pcs[index];
+            let offsets = (0..self.targets[position].len())
+                .map(|target_index| new_pc(target(position, target_index)) as i32 - pc as i32)
+                .collect::<Vec<_>>();
+            let short = |offset: i32| {
+                i16::try_from(offset)
+                    .map(|offset| offset as u16)
+                    .map_err(|_| OptimizerError::BranchOutOfRange(pc))
+            };
+            match instruction {
+                Instruction::Goto(offset)
+                | Instruction::Ifeq(offset)
+                | Instruction::Ifne(offset)
+                | Instruction::Iflt(offset)
+                | Instruction::Ifge(offset)
+                | Instruction::Ifgt(offset)
+                | Instruction::Ifle(offset)
+                | Instruction::If_icmpeq(offset)
+                | Instruction::If_icmpne(offset)
+                | Instruction::If_icmplt(offset)
+                | Instruction::If_icmpge(offset)
+                | Instruction::If_icmpgt(offset)
+                | Instruction::If_icmple(offset)
+                | Instruction::If_acmpeq(offset)
+                | Instruction::If_acmpne(offset)
+                | Instruction::Ifnull(offset)
+                | Instruction::Ifnonnull(offset) => *offset = short(offsets[0])?,
+                Instruction::Goto_w(offset) => *offset = offsets[0],
+                Instruction::Tableswitch { default, offsets: cases, .. } => {
+                    *default = offsets[0];
+                    cases.copy_from_slice(&offsets[1..]);
+                }
+                Instruction::Lookupswitch { default, pairs } => {
+                    *default = offsets[0];
+                    for (pair, offset) in pairs.iter_mut().zip(&offsets[1..]) {
+                        pair.1 = *offset;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut exception_table = Vec::new();
+        for ((start, end, handler), entry) in self.handlers.iter().zip(&self.code.exception_table) {
+            let (start_pc, end_pc) = (new_pc(*start), new_pc(*end));
+            if start_pc < end_pc && self.resolve(*handler).is_some() {
+                exception_table.push(ExceptionHandler {
+                    start_pc: start_pc as u16,
+                    end_pc: end_pc as u16,
+                    handler_pc: new_pc(*handler) as u16,
+                    catch_type: entry.catch_type,
+                });
+            }
+        }
+
+        let mut attributes = Vec::with_capacity(self.code.attributes.len());
+        for attribute in &self.code.attributes {
+            attributes.push(match attribute {
+                Attribute::LineNumberTable(table) => {
+                    Attribute::LineNumberTable(self.map_line_numbers(table, &new_pc)?)
+                }
+                Attribute::LocalVariableTable(table) => {
+                    let variables = table.local_variable_table.iter().map(|variable| {
+                        let range = self.map_range(variable.start_pc, variable.length, &new_pc)?;
+                        Ok(range.map(|(start_pc, length)| LocalVariable {
+                            start_pc,
+                            length,
+                            ..variable.clone()
+                        }))
+                    });
+                    let variables = variables.collect::<Result<Vec<_>>>()?.into_iter().flatten();
+                    Attribute::LocalVariableTable(LocalVariableTable::new(variables.collect()))
+                }
+                Attribute::LocalVariableTypeTable(table) => {
+                    let variables = table.local_variable_type_table.iter().map(|variable| {
+                        let range = self.map_range(variable.start_pc, variable.length, &new_pc)?;
+                        Ok(range.map(|(start_pc, length)| LocalVariableType {
+                            start_pc,
+                            length,
+                            ..variable.clone()
+                        }))
+                    });
+                    let variables = variables.collect::<Result<Vec<_>>>()?.into_iter().flatten();
+                    Attribute::LocalVariableTypeTable(LocalVariableTypeTable::new(
+                        variables.collect(),
+                    ))
+                }
+                Attribute::StackMapTable(table) => {
+                    Attribute::StackMapTable(self.map_frames(table, initial, &new_pc)?)
+                }
+                attribute => attribute.clone(),
+            });
+        }
+
+        Ok(Code {
+            max_stack: self.code.max_stack,
+            max_locals: self.code.max_locals,
+            code: instructions.into_iter().zip(pcs).collect(),
+            exception_table,
+            attributes,
+        })
+    }
+
+    /// The position of the instruction at `pc`, or one past the last for the code length.
+    fn position_or_end(&self, pc: u32, attribute: &'static str) -> Result<usize> {
+        match pc == self.view.code_length() {
+            true => Ok(self.len()),
+            false => self.view.position(pc).ok_or(OptimizerError::InvalidOffset { attribute, pc }),
+        }
+    }
+
+    /// Entries whose instructions were removed move to the next remaining one, where an
+    /// entry of a later line takes precedence.
+    fn map_line_numbers(
+        &self,
+        table: &LineNumberTable,
+        new_pc: &impl Fn(usize) -> u32,
+    ) -> Result<LineNumberTable> {
+        let mut entries = table.line_number_table.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.start_pc);
+        let mut lines = BTreeMap::new();
+        for entry in entries {
+            let position = self.position_or_end(entry.start_pc as u32, "LineNumberTable")?;
+            if self.resolve(position).is_some() {
+                lines.insert(new_pc(position) as u16, entry.line_number);
+            }
+        }
+        let lines =
+            lines.into_iter().map(|(start_pc, line_number)| LineNumber { start_pc, line_number });
+        Ok(LineNumberTable::new(lines.collect()))
+    }
+
+    /// The new start pc and length of a range of instructions, `None` if all were removed.
+    fn map_range(
+        &self,
+        start_pc: u16,
+        length: u16,
+        new_pc: &impl Fn(usize) -> u32,
+    ) -> Result<Option<(u16, u16)>> {
+        let start = self.position_or_end(start_pc as u32, "LocalVariableTable")?;
+        let end = self.position_or_end(start_pc as u32 + length as u32, "LocalVariableTable")?;
+        let (start_pc, end_pc) = (new_pc(start), new_pc(end));
+        Ok((start_pc < end_pc).then_some((start_pc as u16, (end_pc - start_pc) as u16)))
+    }
+
+    /// Moves the frames to the new pcs and encodes them again. Frames of unreachable
+    /// instructions are dropped, those of other removed instructions move to the next
+    /// remaining one unless it has its own, and slots of removed stores become `Top` where
+    /// they are dead.
+    fn map_frames(
+        &self,
+        table: &StackMapTable,
+        initial: &[VerificationTypeInfo],
+        new_pc: &impl Fn(usize) -> u32,
+    ) -> Result<StackMapTable> {
+        let live = match self.dead_slots.is_empty() {
+            true => Vec::new(),
+            false => self.liveness(),
+        };
+        let mut frames = BTreeMap::new();
+        for (position, locals, stack) in self.expand_frames(table, initial)? {
+            let Some(target) = self.resolve(position).filter(|_| !self.unreachable[position])
+            else {
+                continue;
+            };
+            let remap = |info: VerificationTypeInfo| match info {
+                VerificationTypeInfo::Uninitialized { offset } => {
+                    let position = self.position_or_end(offset as u32, "StackMapTable")?;
+                    Ok(VerificationTypeInfo::Uninitialized { offset: new_pc(position) as u16 })
+                }
+                info => Ok(info),
+            };
+            let mut locals = locals.into_iter().map(remap).collect::<Result<Vec<_>>>()?;
+            let stack = stack.into_iter().map(remap).collect::<Result<Vec<_>>>()?;
+            if !live.is_empty() {
+                locals = self.forget_dead_slots(locals, &live[target]);
+            }
+            while locals.last() == Some(&VerificationTypeInfo::Top) {
+                locals.pop();
+            }
+            frames.insert(new_pc(target), (locals, stack));
+        }
+
+        let mut encoded = Vec::with_capacity(frames.len());
+        let mut previous_locals = initial.to_vec();
+        let mut previous_pc = None;
+        for (pc, (locals, stack)) in frames {
+            let offset_delta = match previous_pc {
+                None => pc as u16,
+                Some(previous) => (pc - previous - 1) as u16,
+            };
+            encoded.push(encode_frame(&previous_locals, &locals, &stack, offset_delta));
+            previous_locals = locals;
+            previous_pc = Some(pc);
+        }
+        Ok(StackMapTable::new(encoded))
+    }
+
+    /// The frames of `table` at the positions of their instructions, with every local and
+    /// stack value listed, long and double values taking one entry.
+    #[allow(clippy::type_complexity)]
+    fn expand_frames(
+        &self,
+        table: &StackMapTable,
+        initial: &[VerificationTypeInfo],
+    ) -> Result<Vec<(usize, Vec<VerificationTypeInfo>, Vec<VerificationTypeInfo>)>> {
+        let mut frames = Vec::with_capacity(table.frames.len());
+        let mut locals = initial.to_vec();
+        let mut previous = None::<u32>;
+        for frame in &table.frames {
+            let offset_delta = match frame {
+                StackMapFrame::SameFrame { frame_type } => *frame_type as u16,
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, .. } => {
+                    *frame_type as u16 - 64
+                }
+                StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, .. }
+                | StackMapFrame::ChopFrame { offset_delta, .. }
+                | StackMapFrame::SameFrameExtended { offset_delta, .. }
+                | StackMapFrame::AppendFrame { offset_delta, .. }
+                | StackMapFrame::FullFrame { offset_delta, .. } => *offset_delta,
+            };
+            let pc = match previous {
+                None => offset_delta as u32,
+                Some(previous) => previous + offset_delta as u32 + 1,
+            };
+            previous = Some(pc);
+            let stack = match frame {
+                StackMapFrame::SameFrame { .. } | StackMapFrame::SameFrameExtended { .. } => {
+                    Vec::new()
+                }
+                StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                    vec![stack.clone()]
+                }
+                StackMapFrame::ChopFrame { frame_type, .. } => {
+                    let chopped = 251 - *frame_type as usize;
+                    let remaining =
+                        locals.len().checked_sub(chopped).ok_or(OptimizerError::InvalidChop(pc))?;
+                    locals.truncate(remaining);
+                    Vec::new()
+                }
+                StackMapFrame::AppendFrame { locals: appended, .. } => {
+                    locals.extend(appended.iter().cloned());
+                    Vec::new()
+                }
+                StackMapFrame::FullFrame { locals: full, stack, .. } => {
+                    locals = full.clone();
+                    stack.clone()
+                }
+            };
+            let position = self
+                .view
+                .position(pc)
+                .ok_or(OptimizerError::InvalidOffset { attribute: "StackMapTable", pc })?;
+            frames.push((position, locals.clone(), stack));
+        }
+        Ok(frames)
+    }
+
+    /// Replaces the types of the dead slots of removed stores by `Top`.
+    fn forget_dead_slots(
+        &self,
+        locals: Vec<VerificationTypeInfo>,
+        live: &BTreeSet<u16>,
+    ) -> Vec<VerificationTypeInfo> {
+        let dead = |slot: usize| {
+            let slot = slot as u16;
+            self.dead_slots.contains(&slot) && !live.contains(&slot)
+        };
+        let mut forgotten = Vec::with_capacity(locals.len());
+        let mut slot = 0;
+        for info in locals {
+            let size = match info {
+                VerificationTypeInfo::Long | VerificationTypeInfo::Double => 2,
+                _ => 1,
+            };
+            match (0..size).any(|offset| dead(slot + offset)) {
+                true => forgotten.extend(std::iter::repeat_n(VerificationTypeInfo::Top, size)),
+                false => forgotten.push(info),
+            }
+            slot += size;
+        }
+        forgotten
+    }
+}
+
+/// The most compact encoding of a frame at `offset_delta` after the previous one.
+fn encode_frame(
+    previous: &[VerificationTypeInfo],
+    locals: &[VerificationTypeInfo],
+    stack: &[VerificationTypeInfo],
+    offset_delta: u16,
+) -> StackMapFrame {
+    let same_locals = locals == previous;
+    match stack {
+        [] if same_locals => match offset_delta {
+            0..=63 => StackMapFrame::SameFrame { frame_type: offset_delta as u8 },
+            _ => StackMapFrame::SameFrameExtended { frame_type: 251, offset_delta },
+        },
+        [stack] if same_locals => match offset_delta {
+            0..=63 => StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 64 + offset_delta as u8,
+                stack: stack.clone(),
+            },
+            _ => StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type: 247,
+                offset_delta,
+                stack: stack.clone(),
+            },
+        },
+        [] if locals.len() > previous.len()
+            && locals.len() - previous.len() <= 3
+            && locals.starts_with(previous) =>
+        {
+            StackMapFrame::AppendFrame {
+                frame_type: 251 + (locals.len() - previous.len()) as u8,
+                offset_delta,
+                locals: locals[previous.len()..].to_vec(),
+            }
+        }
+        [] if previous.len() > locals.len()
+            && previous.len() - locals.len() <= 3
+            && previous.starts_with(locals) =>
+        {
+            StackMapFrame::ChopFrame {
+                frame_type: 251 - (previous.len() - locals.len()) as u8,
+                offset_delta,
+            }
+        }
+        _ => StackMapFrame::FullFrame {
+            frame_type: 255,
+            offset_delta,
+            locals: locals.to_vec(),
+            stack: stack.to_vec(),
+        },
+    }
+}
+
+/// The locals of the implicit frame on entry of `method`, one entry per value.
+fn initial_locals(
+    constant_pool: &mut ConstantPool,
+    this_class: &str,
+    method: &Method,
+) -> Vec<VerificationTypeInfo> {
+    let mut object = |name: String| VerificationTypeInfo::Object {
+        constant: Constant::ClassIndex(utf8_index(constant_pool, name)),
+    };
+    let mut locals = Vec::new();
+    if !method.flags.is_static() {
+        locals.push(match method.name.as_str() {
+            "<init>" if this_class != "java/lang/Object" => VerificationTypeInfo::UninitializedThis,
+            _ => object(this_class.to_string()),
+        });
+    }
+    for parameter in method.type_descriptor.parameters() {
+        locals.push(match parameter {
+            FieldType::Base(BaseType::Long) => VerificationTypeInfo::Long,
+            FieldType::Base(BaseType::Double) => VerificationTypeInfo::Double,
+            FieldType::Base(BaseType::Float) => VerificationTypeInfo::Float,
+            FieldType::Base(_) => VerificationTypeInfo::Integer,
+            FieldType::Object(name) => object(name.clone()),
+            array => object(array.to_string()),
+        });
+    }
+    locals
+}
+
+fn utf8_index(constant_pool: &mut ConstantPool, string: String) -> u16 {
+    let position = constant_pool
+        .constants
+        .iter()
+        .position(|constant| matches!(constant, Constant::Utf8(utf8) if *utf8 == string));
+    match position {
+        Some(position) => position as u16 + 1,
+        None => {
+            constant_pool.add(Constant::Utf8(string));
+            constant_pool.constants.len() as u16
+        }
+    }
+}
+
+/// The constant an instruction pushes without popping anything.
+fn pushed_constant(constant_pool: &ConstantPool, instruction: &Instruction) -> Option<Const> {
+    let context = Context { instruction, pc: 0, constant_pool };
+    match instruction.stack_effect(constant_pool)?.pops {
+        0 => fold(&context, &[]),
+        _ => None,
+    }
+}
+
+fn const_size(value: &Const) -> u16 {
+    match value {
+        Const::Long(_) | Const::Double(_) => 2,
+        _ => 1,
+    }
+}
+
+/// The shortest instruction pushing `value`, adding the constant to `constant_pool` if it
+/// needs to be loaded from there and is not yet. `None` for strings and if the constant
+/// pool is full.
+fn push_constant(constant_pool: &mut ConstantPool, value: &Const) -> Option<Instruction> {
+    let loaded = match *value {
+        Const::Null => return Some(Instruction::Aconst_null),
+        Const::Int(-1) => return Some(Instruction::Iconst_m1),
+        Const::Int(0) => return Some(Instruction::Iconst_0),
+        Const::Int(1) => return Some(Instruction::Iconst_1),
+        Const::Int(2) => return Some(Instruction::Iconst_2),
+        Const::Int(3) => return Some(Instruction::Iconst_3),
+        Const::Int(4) => return Some(Instruction::Iconst_4),
+        Const::Int(5) => return Some(Instruction::Iconst_5),
+        Const::Int(value) if i8::try_from(value).is_ok() => {
+            return Some(Instruction::Bipush(value as i8 as u8));
+        }
+        Const::Int(value) if i16::try_from(value).is_ok() => {
+            return Some(Instruction::Sipush(value as i16));
+        }
+        Const::Long(0) => return Some(Instruction::Lconst_0),
+        Const::Long(1) => return Some(Instruction::Lconst_1),
+        Const::Float(bits) if bits == 0f32.to_bits() => return Some(Instruction::Fconst_0),
+        Const::Float(bits) if bits == 1f32.to_bits() => return Some(Instruction::Fconst_1),
+        Const::Float(bits) if bits == 2f32.to_bits() => return Some(Instruction::Fconst_2),
+        Const::Double(bits) if bits == 0f64.to_bits() => return Some(Instruction::Dconst_0),
+        Const::Double(bits) if bits == 1f64.to_bits() => return Some(Instruction::Dconst_1),
+        Const::Int(value) => Constant::Integer(value),
+        Const::Long(value) => Constant::Long(value),
+        Const::Float(bits) => Constant::Float(f32::from_bits(bits)),
+        Const::Double(bits) => Constant::Double(f64::from_bits(bits)),
+        Const::String(_) | Const::Unknown => return None,
+    };
+    let index = constant_index(constant_pool, loaded.clone())?;
+    Some(match loaded {
+        Constant::Long(_) | Constant::Double(_) => Instruction::Ldc2_w(index),
+        _ => match u8::try_from(index) {
+            Ok(index) => Instruction::Ldc(index),
+            Err(_) => Instruction::Ldc_w(index),
+        },
+    })
+}
+
+/// The index of a numeric constant in `constant_pool`, adding it if it is not there.
+/// Floating-point constants are compared by their bits.
+fn constant_index(constant_pool: &mut ConstantPool, constant: Constant) -> Option<u16> {
+    let same = |existing: &Constant| match (existing, &constant) {
+        (Constant::Float(a), Constant::Float(b)) => a.to_bits() == b.to_bits(),
+        (Constant::Double(a), Constant::Double(b)) => a.to_bits() == b.to_bits(),
+        (existing, constant) => existing == constant,
+    };
+    if let Some(position) = constant_pool.constants.iter().position(same) {
+        return Some(position as u16 + 1);
+    }
+    // Indices go up to 65535, and long and double constants take two.
+    let index = constant_pool.constants.len() + 1;
+    let size = match constant {
+        Constant::Long(_) | Constant::Double(_) => 2,
+        _ => 1,
+    };
+    if index + size > u16::MAX as usize + 1 {
+        return None;
+    }
+    constant_pool.add(constant);
+    Some(index as u16)
+}
+
+/// The type of the local variable a load or store accesses.
+fn local_type(instruction: &Instruction) -> ValueType {
+    let mnemonic = match instruction {
+        Instruction::Wide(wide) => wide.mnemonic(),
+        instruction => instruction.mnemonic(),
+    };
+    match mnemonic.as_bytes()[0] {
+        b'i' => ValueType::Int,
+        b'l' => ValueType::Long,
+        b'f' => ValueType::Float,
+        b'd' => ValueType::Double,
+        _ => ValueType::Reference,
+    }
+}
+
+/// The sizes of the operands, deepest first, of an instruction that computes its result from
+/// them alone and cannot throw.
+fn pure_operands(instruction: &Instruction) -> Option<&'static [u16]> {
+    match instruction {
+        Instruction::Iadd
+        | Instruction::Isub
+        | Instruction::Imul
+        | Instruction::Iand
+        | Instruction::Ior
+        | Instruction::Ixor
+        | Instruction::Ishl
+        | Instruction::Ishr
+        | Instruction::Iushr
+        | Instruction::Fadd
+        | Instruction::Fsub
+        | Instruction::Fmul
+        | Instruction::Fdiv
+        | Instruction::Frem
+        | Instruction::Fcmpl
+        | Instruction::Fcmpg => Some(&[1, 1]),
+        Instruction::Ladd
+        | Instruction::Lsub
+        | Instruction::Lmul
+        | Instruction::Land
+        | Instruction::Lor
+        | Instruction::Lxor
+        | Instruction::Lcmp
+        | Instruction::Dadd
+        | Instruction::Dsub
+        | Instruction::Dmul
+        | Instruction::Ddiv
+        | Instruction::Drem
+        | Instruction::Dcmpl
+        | Instruction::Dcmpg => Some(&[2, 2]),
+        Instruction::Lshl | Instruction::Lshr | Instruction::Lushr => Some(&[2, 1]),
+        Instruction::Ineg
+        | Instruction::Fneg
+        | Instruction::I2b
+        | Instruction::I2c
+        | Instruction::I2s
+        | Instruction::I2l
+        | Instruction::I2f
+        | Instruction::I2d
+        | Instruction::F2i
+        | Instruction::F2l
+        | Instruction::F2d => Some(&[1]),
+        Instruction::Lneg
+        | Instruction::Dneg
+        | Instruction::L2i
+        | Instruction::L2f
+        | Instruction::L2d
+        | Instruction::D2i
+        | Instruction::D2l
+        | Instruction::D2f => Some(&[2]),
+        _ => None,
+    }
+}
+
+fn pop(size: u16) -> Instruction {
+    match size {
+        2 => Instruction::Pop2,
+        _ => Instruction::Pop,
+    }
+}
+
+fn is_switch(instruction: &Instruction) -> bool {
+    matches!(instruction, Instruction::Tableswitch { .. } | Instruction::Lookupswitch { .. })
+}
+
+/// The number of values a branch pops.
+fn operand_count(instruction: &Instruction) -> usize {
+    match instruction {
+        Instruction::Goto(_) | Instruction::Goto_w(_) => 0,
+        Instruction::If_icmpeq(_)
+        | Instruction::If_icmpne(_)
+        | Instruction::If_icmplt(_)
+        | Instruction::If_icmpge(_)
+        | Instruction::If_icmpgt(_)
+        | Instruction::If_icmple(_)
+        | Instruction::If_acmpeq(_)
+        | Instruction::If_acmpne(_) => 2,
+        _ => 1,
+    }
+}
+
+/// Whether a conditional branch on constant operands is taken, `None` if that cannot be
+/// told from them.
+fn branch_taken(instruction: &Instruction, operands: &[Const]) -> Option<bool> {
+    use Const::{Int, Null};
+    let reference = |value: &Const| match value {
+        Null => Some(true),
+        Const::String(_) => Some(false),
+        _ => None,
+    };
+    Some(match (instruction, operands) {
+        (Instruction::Ifeq(_), [Int(a)]) => *a == 0,
+        (Instruction::Ifne(_), [Int(a)]) => *a != 0,
+        (Instruction::Iflt(_), [Int(a)]) => *a < 0,
+        (Instruction::Ifge(_), [Int(a)]) => *a >= 0,
+        (Instruction::Ifgt(_), [Int(a)]) => *a > 0,
+        (Instruction::Ifle(_), [Int(a)]) => *a <= 0,
+        (Instruction::If_icmpeq(_), [Int(a), Int(b)]) => a == b,
+        (Instruction::If_icmpne(_), [Int(a), Int(b)]) => a != b,
+        (Instruction::If_icmplt(_), [Int(a), Int(b)]) => a < b,
+        (Instruction::If_icmpge(_), [Int(a), Int(b)]) => a >= b,
+        (Instruction::If_icmpgt(_), [Int(a), Int(b)]) => a > b,
+        (Instruction::If_icmple(_), [Int(a), Int(b)]) => a <= b,
+        (Instruction::If_acmpeq(_), [Null, Null]) => true,
+        (Instruction::If_acmpne(_), [Null, Null]) => false,
+        (Instruction::Ifnull(_), [value]) => reference(value)?,
+        (Instruction::Ifnonnull(_), [value]) => !reference(value)?,
+        _ => return None,
+    })
+}
+
+/// The index of the target a switch on a constant key goes to, the default being 0.
+fn switch_target(instruction: &Instruction, operands: &[Const]) -> Option<Option<usize>> {
+    let [Const::Int(key)] = operands else {
+        return None;
+    };
+    Some(Some(match instruction {
+        Instruction::Tableswitch { low, high, .. } if (*low..=*high).contains(key) => {
+            (*key as i64 - *low as i64) as usize + 1
+        }
+        Instruction::Lookupswitch { pairs, .. } => {
+            pairs.iter().position(|(case, _)| case == key).map_or(0, |index| index + 1)
+        }
+        _ => 0,
+    }))
+}
+
+/// The conditional branch taken exactly when `instruction` is not.
+pub(crate) fn negate(instruction: &Instruction) -> Instruction {
+    match *instruction {
+        Instruction::Ifeq(offset) => Instruction::Ifne(offset),
+        Instruction::Ifne(offset) => Instruction::Ifeq(offset),
+        Instruction::Iflt(offset) => Instruction::Ifge(offset),
+        Instruction::Ifge(offset) => Instruction::Iflt(offset),
+        Instruction::Ifgt(offset) => Instruction::Ifle(offset),
+        Instruction::Ifle(offset) => Instruction::Ifgt(offset),
+        Instruction::If_icmpeq(offset) => Instruction::If_icmpne(offset),
+        Instruction::If_icmpne(offset) => Instruction::If_icmpeq(offset),
+        Instruction::If_icmplt(offset) => Instruction::If_icmpge(offset),
+        Instruction::If_icmpge(offset) => Instruction::If_icmplt(offset),
+        Instruction::If_icmpgt(offset) => Instruction::If_icmple(offset),
+        Instruction::If_icmple(offset) => Instruction::If_icmpgt(offset),
+        Instruction::If_acmpeq(offset) => Instruction::If_acmpne(offset),
+        Instruction::If_acmpne(offset) => Instruction::If_acmpeq(offset),
+        Instruction::Ifnull(offset) => Instruction::Ifnonnull(offset),
+        Instruction::Ifnonnull(offset) => Instruction::Ifnull(offset),
+        _ => unreachable!("only conditional branches are negated"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method::{MethodAccessFlags, MethodDescriptor};
+
+    fn method(descriptor: &str, code: Vec<(Instruction, u32)>) -> Method {
+        let code = Code {
+            max_stack: 4,
+            max_locals: 4,
+            code,
+            exception_table: Vec::new(),
+            attributes: Vec::new(),
+        };
+        Method {
+            flags: MethodAccessFlags::new(0x0008),
+            name: "m".to_string(),
+            type_descriptor: MethodDescriptor::try_from(&mut descriptor.chars().peekable())
+                .unwrap(),
+            attributes: vec![Attribute::Code(code)],
+        }
+    }
+
+    fn optimize(method: &mut Method) -> Vec<Instruction> {
+        let mut constant_pool = ConstantPool::default();
+        Optimizer::new().optimize_method(&mut constant_pool, "C", method).unwrap();
+        method.code().unwrap().code.iter().map(|(instruction, _)| instruction.clone()).collect()
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let mut method = method(
+            "()I",
+            vec![
+                (Instruction::Iconst_2, 0),
+                (Instruction::Sipush(300), 1),
+                (Instruction::Imul, 4),
+                (Instruction::Ireturn, 5),
+            ],
+        );
+        assert_eq!(optimize(&mut method), [Instruction::Sipush(600), Instruction::Ireturn]);
+        assert_eq!(method.code().unwrap().max_stack, 1);
+    }
+
+    #[test]
+    fn test_dead_stores_and_unused_values() {
+        let mut method = method(
+            "(JI)J",
+            vec![
+                (Instruction::Lload_0, 0),
+                (Instruction::Iload_2, 1),
+                (Instruction::Lshl, 2),
+                (Instruction::Lstore(3), 3),
+                (Instruction::Lload_0, 5),
+                (Instruction::Lreturn, 6),
+            ],
+        );
+        assert_eq!(optimize(&mut method), [Instruction::Lload_0, Instruction::Lreturn]);
+        assert_eq!(method.code().unwrap().max_stack, 2);
+
+        let mut unchanged = method.clone();
+        let mut constant_pool = ConstantPool::default();
+        assert!(
+            !Optimizer::new().optimize_method(&mut constant_pool, "C", &mut unchanged).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_branches_and_tables() {
+        let mut method = method(
+            "(I)I",
+            vec![
+                (Instruction::Iconst_0, 0),
+                (Instruction::Ifeq(5), 1),
+                (Instruction::Iconst_1, 4),
+                (Instruction::Ireturn, 5),
+                (Instruction::Iload_0, 6),
+                (Instruction::Iconst_2, 7),
+                (Instruction::Idiv, 8),
+                (Instruction::Ireturn, 9),
+                (Instruction::Astore_1, 10),
+                (Instruction::Iconst_m1, 11),
+                (Instruction::Ireturn, 12),
+            ],
+        );
+        if let Some(Attribute::Code(code)) = method.attributes.first_mut() {
+            code.exception_table.push(ExceptionHandler::new(6, 10, 10, 0));
+            code.attributes.push(Attribute::LineNumberTable(LineNumberTable::new(vec![
+                LineNumber { start_pc: 0, line_number: 10 },
+                LineNumber { start_pc: 6, line_number: 11 },
+                LineNumber { start_pc: 10, line_number: 12 },
+            ])));
+        }
+        assert_eq!(
+            optimize(&mut method),
+            [
+                Instruction::Iload_0,
+                Instruction::Iconst_2,
+                Instruction::Idiv,
+                Instruction::Ireturn,
+                Instruction::Pop,
+                Instruction::Iconst_m1,
+                Instruction::Ireturn,
+            ]
+        );
+        let code = method.code().unwrap();
+        assert_eq!(code.exception_table, [ExceptionHandler::new(0, 4, 4, 0)]);
+        let view = CodeView::new(code);
+        assert_eq!([0, 3, 4, 6].map(|pc| view.line_at(pc)), [11, 11, 12, 12].map(Some));
+    }
+
+    #[test]
+    fn test_thread_jumps() {
+        let mut method = method(
+            "(I)V",
+            vec![
+                (Instruction::Iload_0, 0),
+                (Instruction::Ifeq(7), 1),
+                (Instruction::Iinc(0, 1), 4),
+                (Instruction::Goto(-7i16 as u16), 7),
+                (Instruction::Goto(-8i16 as u16), 8),
+            ],
+        );
+        let mut disabled = method.clone();
+        let mut constant_pool = ConstantPool::default();
+        Optimizer::new()
+            .thread_jumps(false)
+            .remove_dead_stores(false)
+            .optimize_method(&mut constant_pool, "C", &mut disabled)
+            .unwrap();
+        assert_eq!(disabled.code().unwrap().code.len(), 5);
+
+        Optimizer::new()
+            .remove_dead_stores(false)
+            .optimize_method(&mut constant_pool, "C", &mut method)
+            .unwrap();
+        assert_eq!(
+            method.code().unwrap().code,
+            [
+                (Instruction::Iload_0, 0),
+                (Instruction::Ifeq(-1i16 as u16), 1),
+                (Instruction::Iinc(0, 1), 4),
+                (Instruction::Goto(-7i16 as u16), 7),
+            ]
+        );
+    }
+}
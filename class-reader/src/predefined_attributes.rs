@@ -5,8 +5,9 @@ use crate::constant_pool::Constant;
 use crate::instruction::Instruction;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstantValue {
-    value: Constant,
+    pub value: Constant,
 }
 
 impl ConstantValue {
@@ -16,20 +17,23 @@ impl ConstantValue {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Code {
     pub max_stack: u16,
     pub max_locals: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::instructions"))]
     pub code: Vec<(Instruction, u32)>,
     pub exception_table: Vec<ExceptionHandler>,
     pub attributes: Vec<Attribute>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExceptionHandler {
-    start_pc: u16,
-    end_pc: u16,
-    handler_pc: u16,
-    catch_type: u16,
+    pub start_pc: u16,
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    pub catch_type: u16,
 }
 
 impl ExceptionHandler {
@@ -39,6 +43,7 @@ impl ExceptionHandler {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineNumberTable {
     pub line_number_table: Vec<LineNumber>,
 }
@@ -50,14 +55,16 @@ impl LineNumberTable {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LineNumber {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableTable {
-    local_variable_table: Vec<LocalVariable>,
+    pub local_variable_table: Vec<LocalVariable>,
 }
 
 impl LocalVariableTable {
@@ -67,12 +74,13 @@ impl LocalVariableTable {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariable {
-    start_pc: u16,
-    length: u16,
-    name_index: u16,
-    descriptor_index: u16,
-    index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub index: u16,
 }
 
 impl LocalVariable {
@@ -88,8 +96,9 @@ impl LocalVariable {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableTypeTable {
-    local_variable_type_table: Vec<LocalVariableType>,
+    pub local_variable_type_table: Vec<LocalVariableType>,
 }
 
 impl LocalVariableTypeTable {
@@ -99,12 +108,13 @@ impl LocalVariableTypeTable {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableType {
-    start_pc: u16,
-    length: u16,
-    name_index: u16,
-    signature_index: u16,
-    index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+    pub name_index: u16,
+    pub signature_index: u16,
+    pub index: u16,
 }
 
 impl LocalVariableType {
@@ -120,8 +130,9 @@ impl LocalVariableType {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StackMapTable {
-    frames: Vec<StackMapFrame>,
+    pub frames: Vec<StackMapFrame>,
 }
 
 impl StackMapTable {
@@ -131,6 +142,7 @@ impl StackMapTable {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StackMapFrame {
     SameFrame {
         frame_type: u8, /* 0-63 */
@@ -166,6 +178,7 @@ pub enum StackMapFrame {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootstrapMethods {
     pub bootstrap_methods: Vec<BootstrapMethod>,
 }
@@ -177,27 +190,32 @@ impl BootstrapMethods {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BootstrapMethod {
     pub bootstrap_method_ref: u16,
     pub bootstrap_arguments: Vec<u16>,
 }
 
 #[derive(Debug, Clone, From, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NestHost {
     pub name: String,
 }
 
 #[derive(Debug, Clone, From, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NestMembers {
     pub names: Vec<String>,
 }
 
 #[derive(Debug, Clone, From, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PetrmittedSubclasses {
     pub names: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VerificationTypeInfo {
     Top,
     Integer,
@@ -211,6 +229,7 @@ pub enum VerificationTypeInfo {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SourceFile {
     pub file_name: String,
 }
@@ -0,0 +1,1688 @@
+//! Renaming of classes, fields and methods after a mapping file, to deobfuscate a jar or the
+//! stack traces it produces.
+//!
+//! [`Mappings`] are read from ProGuard `mapping.txt` files, whose original → obfuscated
+//! direction is inverted so that they deobfuscate, or from Tiny v2 files between any two of
+//! their namespaces. A [`Remapper`] applies them to classes: the constant pool, member names
+//! and descriptors, signatures, `InnerClasses`, `EnclosingMethod`, `NestHost`, `NestMembers`,
+//! `PermittedSubclasses`, `Record`, annotations and the arguments of bootstrap methods.
+//!
+//! Member names are looked up through the hierarchy of the classes added to the remapper, so
+//! references through a subclass find the mapping of the declaring class and a method takes
+//! the name its mapping gives to any method it overrides or is overridden by. Classes outside
+//! that hierarchy, such as those of the JDK, are only looked up by their own mappings.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::io::{Read, Seek, Write};
+use std::iter::once;
+
+use thiserror::Error;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::attribute::{Attribute, UserDefinedAttribute};
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+use crate::class_file_writer::{ClassFileWriter, ClassWriterError};
+use crate::class_hierarchy::ClassHierarchy;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::constant_pool_rewriting::{RawAttribute, compact_constant_pool};
+use crate::field::FieldType;
+use crate::method::MethodDescriptor;
+use crate::predefined_attributes::{BootstrapMethods, StackMapFrame, VerificationTypeInfo};
+
+type Result<T> = std::result::Result<T, RemapperError>;
+
+/// The owner, name and descriptor of a field or method.
+type MemberKey = (String, String, String);
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+const OBJECT_METHODS: &str = "java/lang/runtime/ObjectMethods";
+const REF_GET_FIELD: u8 = 1;
+
+#[derive(Debug, Error)]
+pub enum RemapperError {
+    #[error("Invalid mapping at line {0}: {1}")]
+    #[non_exhaustive]
+    InvalidMapping(usize, String),
+    #[error("Namespace {0} is not declared by the mappings")]
+    #[non_exhaustive]
+    UnknownNamespace(String),
+    #[error("Constant pool of {0} overflows")]
+    #[non_exhaustive]
+    ConstantPoolOverflow(String),
+    #[error("{0}")]
+    #[non_exhaustive]
+    Read(#[from] ContextualError),
+    #[error("{0}")]
+    #[non_exhaustive]
+    Write(#[from] ClassWriterError),
+    #[error("Invalid archive: {0}")]
+    #[non_exhaustive]
+    Zip(#[from] ZipError),
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Invalid entry {0}: {1}")]
+    #[non_exhaustive]
+    Entry(String, Box<RemapperError>),
+}
+
+/// Renamings of classes and members, from a source to a target namespace. Classes use
+/// internal names and members are keyed by their descriptor in the source namespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mappings {
+    classes: HashMap<String, String>,
+    fields: HashMap<MemberKey, String>,
+    methods: HashMap<MemberKey, String>,
+    /// The line ranges of the methods of each class, from ProGuard mappings.
+    frames: HashMap<String, Vec<FrameMapping>>,
+    /// The original source file of classes, from ProGuard mappings.
+    source_files: HashMap<String, String>,
+}
+
+/// A method of a ProGuard mapping, or a method inlined into it, as it shows in stack traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FrameMapping {
+    name: String,
+    /// The class declaring the original method, another one for some inlined methods.
+    class: String,
+    method: String,
+    lines: Option<(u32, u32)>,
+    original_lines: Option<(u32, u32)>,
+}
+
+/// A member line of a ProGuard mapping, before class names are resolved.
+struct ProguardMember {
+    class: String,
+    java_type: String,
+    name: String,
+    /// The parameter types of methods, `None` for fields.
+    parameters: Option<Vec<String>>,
+    obfuscated: String,
+    lines: Option<(u32, u32)>,
+    original_lines: Option<(u32, u32)>,
+}
+
+impl Mappings {
+    pub fn new() -> Self {
+        Mappings::default()
+    }
+
+    /// Reads a ProGuard or R8 `mapping.txt`, giving mappings from the obfuscated names to the
+    /// original ones.
+    pub fn from_proguard(text: &str) -> Result<Self> {
+        let mut classes = Vec::<(String, String)>::new();
+        let mut members = Vec::new();
+        let mut source_files = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: &str| RemapperError::InvalidMapping(number + 1, message.into());
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(comment) = trimmed.strip_prefix('#') {
+                // R8 records the source file as `# {"id":"sourceFile","fileName":"A.java"}`.
+                if let (Some((class, _)), true) = (classes.last(), comment.contains("sourceFile")) {
+                    if let Some(file_name) = json_string(comment, "fileName") {
+                        source_files.insert(class.clone(), file_name.to_string());
+                    }
+                }
+                continue;
+            }
+            let (left, obfuscated) =
+                trimmed.rsplit_once(" -> ").ok_or_else(|| invalid("expected ` -> `"))?;
+            if !line.starts_with(char::is_whitespace) {
+                let obfuscated =
+                    obfuscated.strip_suffix(':').ok_or_else(|| invalid("expected `:`"))?;
+                classes.push((internal_name(left), internal_name(obfuscated)));
+                continue;
+            }
+            let (class, _) = classes.last().ok_or_else(|| invalid("member outside a class"))?;
+            let member = parse_proguard_member(left)
+                .ok_or_else(|| invalid("expected `[a:b:]type name[(types)][:c[:d]]`"))?;
+            let (java_type, name, parameters, lines, original_lines) = member;
+            members.push(ProguardMember {
+                class: class.clone(),
+                java_type: java_type.to_string(),
+                name: name.to_string(),
+                parameters: parameters.map(|types| types.into_iter().map(String::from).collect()),
+                obfuscated: obfuscated.to_string(),
+                lines,
+                original_lines,
+            });
+        }
+
+        let obfuscated_names: HashMap<_, _> = classes.iter().cloned().collect();
+        let mut mappings = Mappings::new();
+        for (original, obfuscated) in &classes {
+            mappings.classes.insert(obfuscated.clone(), original.clone());
+            if let Some(file_name) = source_files.get(original) {
+                mappings.source_files.insert(original.clone(), file_name.clone());
+            }
+        }
+        let descriptor = |java_type: &str| java_type_descriptor(java_type, &obfuscated_names);
+        for (position, member) in members.iter().enumerate() {
+            let owner = obfuscated_names[&member.class].clone();
+            let Some(parameters) = &member.parameters else {
+                let key = (owner, member.obfuscated.clone(), descriptor(&member.java_type));
+                mappings.fields.entry(key).or_insert_with(|| member.name.clone());
+                continue;
+            };
+            // A method qualified with its class was inlined from that class.
+            let (class, method) = match member.name.rsplit_once('.') {
+                Some((class, method)) => (internal_name(class), method),
+                None => (member.class.clone(), member.name.as_str()),
+            };
+            mappings.frames.entry(owner.clone()).or_default().push(FrameMapping {
+                name: member.obfuscated.clone(),
+                class,
+                method: method.to_string(),
+                lines: member.lines,
+                original_lines: member.original_lines,
+            });
+            // Methods inlined into another one precede it with the same obfuscated range.
+            let inlined = member.lines.is_some()
+                && members.get(position + 1).is_some_and(|next| {
+                    next.class == member.class
+                        && next.obfuscated == member.obfuscated
+                        && next.lines == member.lines
+                });
+            if inlined || member.name.contains('.') {
+                continue;
+            }
+            let parameters: String =
+                parameters.iter().map(|java_type| descriptor(java_type)).collect();
+            let method_descriptor = format!("({}){}", parameters, descriptor(&member.java_type));
+            let key = (owner, member.obfuscated.clone(), method_descriptor);
+            mappings.methods.entry(key).or_insert_with(|| member.name.clone());
+        }
+        Ok(mappings)
+    }
+
+    /// Reads a Tiny v2 file, giving mappings from the namespace `source` to `target`.
+    pub fn from_tiny(text: &str, source: &str, target: &str) -> Result<Self> {
+        let mut lines = text.lines().enumerate();
+        let header: Vec<&str> =
+            lines.next().map(|(_, line)| line.split('\t').collect()).unwrap_or_default();
+        if header.len() < 4 || header[0] != "tiny" || header[1] != "2" {
+            return Err(RemapperError::InvalidMapping(1, "expected a Tiny v2 header".into()));
+        }
+        let namespaces = &header[3..];
+        let namespace = |name: &str| {
+            namespaces
+                .iter()
+                .position(|namespace| *namespace == name)
+                .ok_or_else(|| RemapperError::UnknownNamespace(name.to_string()))
+        };
+        let (source, target) = (namespace(source)?, namespace(target)?);
+
+        // Descriptors are given in the first namespace, so classes are all read first.
+        let mut escaped = false;
+        let mut classes: Vec<Vec<String>> = Vec::new();
+        let mut members = Vec::new();
+        for (number, line) in lines {
+            let depth = line.len() - line.trim_start_matches('\t').len();
+            let columns: Vec<&str> = line[depth..].split('\t').collect();
+            let names = |columns: &[&str]| {
+                if columns.len() < namespaces.len() {
+                    return Err(RemapperError::InvalidMapping(number + 1, "missing names".into()));
+                }
+                let names: Vec<String> = columns[..namespaces.len()]
+                    .iter()
+                    .map(|name| if escaped { unescape(name) } else { name.to_string() })
+                    .collect();
+                // An empty name stands for the name in the first namespace.
+                Ok(names
+                    .iter()
+                    .map(|name| if name.is_empty() { &names[0] } else { name })
+                    .cloned()
+                    .collect::<Vec<_>>())
+            };
+            match (depth, columns[0]) {
+                (0, "c") => classes.push(names(&columns[1..])?),
+                (1, "escaped-names") if classes.is_empty() => escaped = true,
+                (1, kind @ ("f" | "m")) if !classes.is_empty() && columns.len() > 1 => {
+                    let descriptor = columns[1].to_string();
+                    members.push((
+                        classes.len() - 1,
+                        kind == "f",
+                        descriptor,
+                        names(&columns[2..])?,
+                    ));
+                }
+                (0, _) if !line.is_empty() => {
+                    return Err(RemapperError::InvalidMapping(
+                        number + 1,
+                        "expected a class".into(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let mut mappings = Mappings::new();
+        let mut source_names = Mappings::new();
+        for names in &classes {
+            source_names.classes.insert(names[0].clone(), names[source].clone());
+            mappings.classes.insert(names[source].clone(), names[target].clone());
+        }
+        for (class, is_field, descriptor, names) in members {
+            let key = (
+                classes[class][source].clone(),
+                names[source].clone(),
+                source_names.map_descriptor(&descriptor),
+            );
+            match is_field {
+                true => mappings.fields.insert(key, names[target].clone()),
+                false => mappings.methods.insert(key, names[target].clone()),
+            };
+        }
+        Ok(mappings)
+    }
+
+    /// The mappings from the target namespace back to the source one. Line ranges and source
+    /// files are left out.
+    pub fn reversed(&self) -> Mappings {
+        let mut reversed = Mappings::new();
+        for (source, target) in &self.classes {
+            reversed.classes.insert(target.clone(), source.clone());
+        }
+        let reverse = |members: &HashMap<MemberKey, String>| {
+            members
+                .iter()
+                .map(|((owner, name, descriptor), target)| {
+                    let key =
+                        (self.map_class(owner), target.clone(), self.map_descriptor(descriptor));
+                    (key, name.clone())
+                })
+                .collect()
+        };
+        reversed.fields = reverse(&self.fields);
+        reversed.methods = reverse(&self.methods);
+        reversed
+    }
+
+    pub fn add_class(&mut self, source: &str, target: &str) -> &mut Self {
+        self.classes.insert(source.to_string(), target.to_string());
+        self
+    }
+
+    pub fn add_field(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        target: &str,
+    ) -> &mut Self {
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        self.fields.insert(key, target.to_string());
+        self
+    }
+
+    pub fn add_method(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+        target: &str,
+    ) -> &mut Self {
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        self.methods.insert(key, target.to_string());
+        self
+    }
+
+    pub fn class(&self, name: &str) -> Option<&str> {
+        self.classes.get(name).map(String::as_str)
+    }
+
+    pub fn field(&self, owner: &str, name: &str, descriptor: &str) -> Option<&str> {
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        self.fields.get(&key).map(String::as_str)
+    }
+
+    pub fn method(&self, owner: &str, name: &str, descriptor: &str) -> Option<&str> {
+        let key = (owner.to_string(), name.to_string(), descriptor.to_string());
+        self.methods.get(&key).map(String::as_str)
+    }
+
+    /// The number of class, field and method mappings.
+    pub fn len(&self) -> usize {
+        self.classes.len() + self.fields.len() + self.methods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The target name of the class or array type `name`. Nested classes without a mapping of
+    /// their own follow their outer class: `a$1` becomes `com/example/Outer$1`.
+    pub fn map_class(&self, name: &str) -> String {
+        if name.starts_with('[') {
+            return self.map_descriptor(name);
+        }
+        if let Some(target) = self.classes.get(name) {
+            return target.clone();
+        }
+        if let Some((outer, nested)) = name.rsplit_once('$') {
+            if !outer.is_empty() && !nested.is_empty() {
+                let mapped = self.map_class(outer);
+                if mapped != outer {
+                    return format!("{}${}", mapped, nested);
+                }
+            }
+        }
+        name.to_string()
+    }
+
+    /// Maps the classes of a field or method descriptor.
+    pub fn map_descriptor(&self, descriptor: &str) -> String {
+        let mut mapped = String::with_capacity(descriptor.len());
+        let mut rest = descriptor;
+        while let Some(start) = rest.find('L') {
+            mapped.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find(';') else {
+                break;
+            };
+            mapped.push_str(&self.map_class(&rest[..end]));
+            rest = &rest[end..];
+        }
+        mapped.push_str(rest);
+        mapped
+    }
+
+    /// Maps the classes of a class, method or field signature, leaving it unchanged if it is
+    /// malformed.
+    pub fn map_signature(&self, signature: &str) -> String {
+        let mut remapper =
+            SignatureRemapper { mappings: self, signature, position: 0, output: String::new() };
+        match remapper.signature() {
+            Some(()) => remapper.output,
+            None => signature.to_string(),
+        }
+    }
+
+    /// Deobfuscates a stack trace with the line ranges of ProGuard mappings: frames of mapped
+    /// classes get the original class, method, source file and line, expanding inlined methods
+    /// into one frame each, and other mentions of mapped classes get their original name.
+    /// Frames that match several methods list their names separated by `|`.
+    pub fn retrace(&self, trace: &str) -> String {
+        let mut retraced: Vec<String> = trace
+            .lines()
+            .map(|line| self.retrace_frame(line).unwrap_or_else(|| self.retrace_names(line)))
+            .collect();
+        if trace.ends_with('\n') {
+            retraced.push(String::new());
+        }
+        retraced.join("\n")
+    }
+
+    fn retrace_frame(&self, line: &str) -> Option<String> {
+        let at = line.find("at ")?;
+        if !line[..at].trim().is_empty() {
+            return None;
+        }
+        let (prefix, rest) = line.split_at(at + 3);
+        let open = rest.find('(')?;
+        let close = rest.rfind(')')?;
+        // A module or class loader may precede the class, as in `app//a.b.c(...)`.
+        let (module, qualified) =
+            rest[..open].split_at(rest[..open].rfind('/').map_or(0, |i| i + 1));
+        let (class, method) = qualified.rsplit_once('.')?;
+        let location = &rest[open + 1..close];
+        let class = internal_name(class);
+        let line_number = location.rsplit_once(':').and_then(|(_, number)| number.parse().ok());
+
+        let candidates: Vec<&FrameMapping> = self
+            .frames
+            .get(&class)
+            .into_iter()
+            .flatten()
+            .filter(|frame| frame.name == method)
+            .collect();
+        let original_class = self.map_class(&class);
+        if original_class == class {
+            return None;
+        }
+        let frame = |class: &str, method: &str, line: Option<u32>| {
+            let location = retrace_location(self.source_files.get(class), class, location, line);
+            format!(
+                "{}{}{}.{}({}){}",
+                prefix,
+                module,
+                class.replace('/', "."),
+                method,
+                location,
+                &rest[close + 1..]
+            )
+        };
+
+        if let Some(number) = line_number {
+            let inlined: Vec<String> = candidates
+                .iter()
+                .filter(|frame| {
+                    frame.lines.is_some_and(|(start, end)| (start..=end).contains(&number))
+                })
+                .map(|mapping| {
+                    let (start, _) = mapping.lines.unwrap_or_default();
+                    let original = match mapping.original_lines {
+                        Some((original, end)) if end > original => original + (number - start),
+                        Some((original, _)) => original,
+                        None => number,
+                    };
+                    frame(&mapping.class, &mapping.method, Some(original))
+                })
+                .collect();
+            if !inlined.is_empty() {
+                return Some(inlined.join("\n"));
+            }
+        }
+        let mut methods: Vec<&str> = Vec::new();
+        for candidate in &candidates {
+            if !methods.contains(&candidate.method.as_str()) {
+                methods.push(&candidate.method);
+            }
+        }
+        let method = if methods.is_empty() { method.to_string() } else { methods.join("|") };
+        Some(frame(&original_class, &method, line_number))
+    }
+
+    /// Replaces the qualified names of mapped classes in `line`.
+    fn retrace_names(&self, line: &str) -> String {
+        let mut retraced = String::with_capacity(line.len());
+        let mut rest = line;
+        while !rest.is_empty() {
+            let is_name = |c: char| c.is_alphanumeric() || matches!(c, '_' | '$' | '.');
+            let start = rest.find(is_name).unwrap_or(rest.len());
+            retraced.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+            let token = rest[..end].trim_end_matches('.');
+            let class = internal_name(token);
+            let original = self.map_class(&class);
+            match original != class && token.contains(['.', '$']) {
+                true => retraced.push_str(&original.replace('/', ".")),
+                false => retraced.push_str(token),
+            }
+            rest = &rest[token.len()..];
+            if token.is_empty() {
+                retraced.push_str(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+        retraced
+    }
+}
+
+/// Applies [`Mappings`] to classes, looking members up through the hierarchy of the classes
+/// added with [`add_classes`](Self::add_classes).
+#[derive(Debug, Clone, Default)]
+pub struct Remapper {
+    mappings: Mappings,
+    hierarchy: ClassHierarchy,
+    /// The names and descriptors of the fields each class of the hierarchy declares.
+    fields: HashMap<String, HashSet<(String, String)>>,
+    /// The names methods without a mapping take from another method of their override family.
+    inherited: HashMap<MemberKey, String>,
+    /// The target names of annotation elements, which annotations give without descriptor.
+    elements: HashMap<(String, String), String>,
+}
+
+impl Remapper {
+    pub fn new(mappings: Mappings) -> Self {
+        let mut elements = HashMap::new();
+        for ((owner, name, descriptor), target) in &mappings.methods {
+            if descriptor.starts_with("()") {
+                elements.insert((owner.clone(), name.clone()), target.clone());
+            }
+        }
+        Remapper { mappings, elements, ..Remapper::default() }
+    }
+
+    pub fn mappings(&self) -> &Mappings {
+        &self.mappings
+    }
+
+    /// Adds classes to the hierarchy members are looked up in, usually all the classes that
+    /// are remapped together.
+    pub fn add_classes<'a, I: IntoIterator<Item = &'a ClassFile>>(
+        &mut self,
+        classes: I,
+    ) -> &mut Self {
+        for class in classes {
+            self.hierarchy.add_class(class);
+            let fields = class
+                .fields
+                .iter()
+                .map(|field| (field.name.clone(), field.type_descriptor.to_string()));
+            self.fields.entry(class.this_class.clone()).or_default().extend(fields);
+        }
+        self.inherited = self.inherit();
+        self
+    }
+
+    pub fn map_class(&self, name: &str) -> String {
+        self.mappings.map_class(name)
+    }
+
+    pub fn map_descriptor(&self, descriptor: &str) -> String {
+        self.mappings.map_descriptor(descriptor)
+    }
+
+    pub fn map_signature(&self, signature: &str) -> String {
+        self.mappings.map_signature(signature)
+    }
+
+    /// The target name of the field `name` referred to through `owner`.
+    pub fn map_field(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        for class in once(owner).chain(self.ancestors(owner)) {
+            if let Some(target) = self.mappings.field(class, name, descriptor) {
+                return target.to_string();
+            }
+            let declared = self
+                .fields
+                .get(class)
+                .is_some_and(|fields| fields.contains(&(name.to_string(), descriptor.to_string())));
+            if declared {
+                break;
+            }
+        }
+        name.to_string()
+    }
+
+    /// The target name of the method `name` referred to through `owner`. Constructors and
+    /// static initializers keep their names.
+    pub fn map_method(&self, owner: &str, name: &str, descriptor: &str) -> String {
+        if name.starts_with('<') {
+            return name.to_string();
+        }
+        for class in once(owner).chain(self.ancestors(owner)) {
+            let key = (class.to_string(), name.to_string(), descriptor.to_string());
+            if let Some(target) =
+                self.mappings.methods.get(&key).or_else(|| self.inherited.get(&key))
+            {
+                return target.clone();
+            }
+            if self.hierarchy.get(class).is_some_and(|node| node.method(name, descriptor).is_some())
+            {
+                break;
+            }
+        }
+        name.to_string()
+    }
+
+    /// Remaps `class` in place. Its constant pool is compacted afterwards, unless it has
+    /// attributes of unknown layout.
+    pub fn remap(&self, class: &mut ClassFile) -> Result<()> {
+        let original = class.constant_pool.clone();
+        let this_class = class.this_class.clone();
+        let bootstrap_methods = class.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::BootstrapMethods(bootstrap_methods) => Some(bootstrap_methods.clone()),
+            _ => None,
+        });
+        let mut editor = ConstantPoolEditor::new(&mut class.constant_pool);
+        self.remap_constants(&original, bootstrap_methods.as_ref(), &mut editor);
+
+        let context = Context { constant_pool: &original, this_class: &this_class };
+        class.this_class = self.map_class(&class.this_class);
+        class.super_class = class.super_class.as_deref().map(|name| self.map_class(name));
+        for interface in &mut class.interfaces {
+            *interface = self.map_class(interface);
+        }
+        for field in &mut class.fields {
+            let descriptor = field.type_descriptor.to_string();
+            field.name = self.map_field(&this_class, &field.name, &descriptor);
+            if let Ok(mapped) =
+                FieldType::try_from(&mut self.map_descriptor(&descriptor).chars().peekable())
+            {
+                field.type_descriptor = mapped;
+            }
+            self.remap_attributes(&mut field.attributes, &context, &mut editor);
+        }
+        for method in &mut class.methods {
+            let descriptor = method.type_descriptor.to_string();
+            method.name = self.map_method(&this_class, &method.name, &descriptor);
+            let mapped = self.map_descriptor(&descriptor);
+            if let Ok(mapped) = MethodDescriptor::try_from(&mut mapped.chars().peekable()) {
+                method.type_descriptor = mapped;
+            }
+            self.remap_attributes(&mut method.attributes, &context, &mut editor);
+        }
+        self.remap_attributes(&mut class.attributes, &context, &mut editor);
+        if editor.overflow {
+            return Err(RemapperError::ConstantPoolOverflow(this_class));
+        }
+        compact_constant_pool(class);
+        Ok(())
+    }
+
+    /// Remaps an encoded class, returning the encoding of the remapped class.
+    pub fn remap_bytes(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut class = ClassFileReader::read_class(data)?;
+        self.remap(&mut class)?;
+        Ok(ClassFileWriter::write(&class)?)
+    }
+
+    /// Writes `input` to `output` with its classes remapped and renamed, adding them to the
+    /// hierarchy first. Other entries are copied, except for the `Main-Class` of the manifest,
+    /// which is remapped too.
+    pub fn remap_jar<R: Read + Seek, W: Write + Seek>(&self, input: R, output: W) -> Result<()> {
+        let mut archive = ZipArchive::new(input)?;
+        let mut classes = BTreeMap::new();
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let name = entry.name()?.to_string();
+            if entry.is_dir() || !name.ends_with(".class") {
+                continue;
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let class = ClassFileReader::read_class(&data)
+                .map_err(|error| RemapperError::Entry(name, Box::new(error.into())))?;
+            classes.insert(index, class);
+        }
+        let mut remapper = self.clone();
+        remapper.add_classes(classes.values());
+
+        let mut writer = ZipWriter::new(output);
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let name = entry.name()?.to_string();
+            let data = match classes.remove(&index) {
+                Some(mut class) => {
+                    remapper
+                        .remap(&mut class)
+                        .map_err(|error| RemapperError::Entry(name.clone(), Box::new(error)))?;
+                    let data = ClassFileWriter::write(&class).map_err(|error| {
+                        RemapperError::Entry(name.clone(), Box::new(error.into()))
+                    })?;
+                    // Classes of multi-release jars stay under their version directory.
+                    let version = name
+                        .strip_prefix("META-INF/versions/")
+                        .and_then(|rest| rest.split_once('/'))
+                        .map(|(version, _)| format!("META-INF/versions/{}/", version));
+                    (format!("{}{}.class", version.unwrap_or_default(), class.this_class), data)
+                }
+                None if name == "META-INF/MANIFEST.MF" => {
+                    let mut manifest = String::new();
+                    entry.read_to_string(&mut manifest)?;
+                    (name, remapper.remap_manifest(&manifest).into_bytes())
+                }
+                None => {
+                    writer.raw_copy_file(entry)?;
+                    continue;
+                }
+            };
+            let mut options = SimpleFileOptions::default().compression_method(entry.compression());
+            if let Some(time) = entry.last_modified() {
+                options = options.last_modified_time(time);
+            }
+            if let Some(mode) = entry.unix_mode() {
+                options = options.unix_permissions(mode);
+            }
+            writer.start_file(data.0, options)?;
+            writer.write_all(&data.1)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Remaps the `Main-Class` of a manifest, joining its continuation lines.
+    fn remap_manifest(&self, manifest: &str) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut main_class = None;
+        for line in manifest.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+            if let (Some(continuation), Some(position)) = (content.strip_prefix(' '), main_class) {
+                let main_line: &mut String = &mut lines[position];
+                main_line.push_str(continuation);
+                continue;
+            }
+            main_class = None;
+            if content.starts_with("Main-Class:") {
+                main_class = Some(lines.len());
+                lines.push(content.to_string());
+            } else {
+                lines.push(line.to_string());
+            }
+        }
+        let mut remapped = String::new();
+        for line in lines {
+            match line.strip_prefix("Main-Class:") {
+                Some(name) => {
+                    let mapped = self.map_class(&internal_name(name.trim()));
+                    remapped.push_str(&format!("Main-Class: {}\r\n", mapped.replace('/', ".")));
+                }
+                None => remapped.push_str(&line),
+            }
+        }
+        remapped
+    }
+
+    /// The supertypes of `name` in the hierarchy, nearest first.
+    fn ancestors<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        let mut ancestors = Vec::new();
+        let mut pending = VecDeque::from([name]);
+        while let Some(class) = pending.pop_front() {
+            for supertype in
+                self.hierarchy.get(class).into_iter().flat_map(|node| node.direct_supertypes())
+            {
+                if !ancestors.contains(&supertype) {
+                    ancestors.push(supertype);
+                    pending.push_back(supertype);
+                }
+            }
+        }
+        ancestors
+    }
+
+    fn declares_virtual(&self, class: &str, name: &str, descriptor: &str) -> bool {
+        self.hierarchy
+            .get(class)
+            .and_then(|node| node.method(name, descriptor))
+            .is_some_and(|method| method.is_virtual() && !name.starts_with('<'))
+    }
+
+    /// Spreads the mapping of each method of the hierarchy to the methods it overrides or that
+    /// override it, transitively, which have no mapping of their own. The first mapping in
+    /// key order wins when a family has conflicting ones.
+    fn inherit(&self) -> HashMap<MemberKey, String> {
+        let mut methods: Vec<_> = self.mappings.methods.iter().collect();
+        methods.sort();
+        let mut inherited = HashMap::new();
+        for ((owner, name, descriptor), target) in methods {
+            if !self.declares_virtual(owner, name, descriptor) {
+                continue;
+            }
+            let mut family = HashSet::from([owner.as_str()]);
+            let mut pending = vec![owner.as_str()];
+            while let Some(class) = pending.pop() {
+                for related in
+                    self.ancestors(class).into_iter().chain(self.hierarchy.subtypes(class))
+                {
+                    if self.declares_virtual(related, name, descriptor) && family.insert(related) {
+                        pending.push(related);
+                    }
+                }
+            }
+            for class in family {
+                let key = (class.to_string(), name.clone(), descriptor.clone());
+                if !self.mappings.methods.contains_key(&key) {
+                    inherited.entry(key).or_insert_with(|| target.clone());
+                }
+            }
+        }
+        inherited
+    }
+
+    /// Rewrites the entries that name classes or members in place, so that instructions and
+    /// attributes keep referring to them by the same index, with new `Utf8` and `NameAndType`
+    /// entries as those may be shared.
+    fn remap_constants(
+        &self,
+        original: &ConstantPool,
+        bootstrap_methods: Option<&BootstrapMethods>,
+        editor: &mut ConstantPoolEditor,
+    ) {
+        for (position, constant) in original.constants.iter().enumerate() {
+            let index = position + 1;
+            let remapped = match constant {
+                Constant::ClassIndex(_) => {
+                    let Some(name) = original.class_name(index) else {
+                        continue;
+                    };
+                    let mapped = self.map_class(name);
+                    if mapped == name {
+                        continue;
+                    }
+                    Constant::ClassIndex(editor.utf8(&mapped))
+                }
+                Constant::FieldRef(class_index, _)
+                | Constant::MethodRef(class_index, _)
+                | Constant::InterfaceMethodRef(class_index, _) => {
+                    let Some((owner, name, descriptor)) = original.member_ref(index) else {
+                        continue;
+                    };
+                    let mapped_name = match constant {
+                        Constant::FieldRef(..) => self.map_field(owner, name, descriptor),
+                        _ => self.map_method(owner, name, descriptor),
+                    };
+                    let mapped_descriptor = self.map_descriptor(descriptor);
+                    if mapped_name == name && mapped_descriptor == descriptor {
+                        continue;
+                    }
+                    let name_and_type = editor.name_and_type(&mapped_name, &mapped_descriptor);
+                    match constant {
+                        Constant::FieldRef(..) => Constant::FieldRef(*class_index, name_and_type),
+                        Constant::MethodRef(..) => Constant::MethodRef(*class_index, name_and_type),
+                        _ => Constant::InterfaceMethodRef(*class_index, name_and_type),
+                    }
+                }
+                Constant::MethodType(descriptor_index) => {
+                    let Some(descriptor) = original.utf8(*descriptor_index as usize) else {
+                        continue;
+                    };
+                    let mapped = self.map_descriptor(descriptor);
+                    if mapped == descriptor {
+                        continue;
+                    }
+                    Constant::MethodType(editor.utf8(&mapped))
+                }
+                Constant::InvokeDynamic(bootstrap_index, name_and_type_index)
+                | Constant::Dynamic(bootstrap_index, name_and_type_index) => {
+                    let Some((name, descriptor)) =
+                        original.name_and_type(*name_and_type_index as usize)
+                    else {
+                        continue;
+                    };
+                    let mapped_name = match constant {
+                        Constant::InvokeDynamic(..) => self
+                            .lambda_method_name(
+                                original,
+                                bootstrap_methods,
+                                *bootstrap_index,
+                                name,
+                                descriptor,
+                            )
+                            .unwrap_or_else(|| name.to_string()),
+                        _ => name.to_string(),
+                    };
+                    let mapped_descriptor = self.map_descriptor(descriptor);
+                    if mapped_name == name && mapped_descriptor == descriptor {
+                        continue;
+                    }
+                    let name_and_type = editor.name_and_type(&mapped_name, &mapped_descriptor);
+                    match constant {
+                        Constant::InvokeDynamic(..) => {
+                            Constant::InvokeDynamic(*bootstrap_index, name_and_type)
+                        }
+                        _ => Constant::Dynamic(*bootstrap_index, name_and_type),
+                    }
+                }
+                _ => continue,
+            };
+            editor.constant_pool.constants[position] = remapped;
+        }
+    }
+
+    /// The name of the interface method a `LambdaMetafactory` call site implements, which
+    /// follows the renaming of that method.
+    fn lambda_method_name(
+        &self,
+        constant_pool: &ConstantPool,
+        bootstrap_methods: Option<&BootstrapMethods>,
+        bootstrap_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Option<String> {
+        let method = bootstrap_methods?.bootstrap_methods.get(bootstrap_index as usize)?;
+        let Ok(Constant::MethodHandle(_, reference)) =
+            constant_pool.get(method.bootstrap_method_ref as usize)
+        else {
+            return None;
+        };
+        let (owner, _, _) = constant_pool.member_ref(*reference as usize)?;
+        if owner != LAMBDA_METAFACTORY {
+            return None;
+        }
+        let Ok(Constant::MethodType(method_type)) =
+            constant_pool.get(*method.bootstrap_arguments.first()? as usize)
+        else {
+            return None;
+        };
+        let interface_descriptor = constant_pool.utf8(*method_type as usize)?;
+        let interface = descriptor.rsplit_once(')')?.1.strip_prefix('L')?.strip_suffix(';')?;
+        Some(self.map_method(interface, name, interface_descriptor))
+    }
+
+    fn remap_attributes(
+        &self,
+        attributes: &mut [Attribute],
+        context: &Context,
+        editor: &mut ConstantPoolEditor,
+    ) {
+        let constant_pool = context.constant_pool;
+        for attribute in attributes {
+            match attribute {
+                Attribute::Code(code) => {
+                    self.remap_attributes(&mut code.attributes, context, editor)
+                }
+                Attribute::StackMapTable(table) => {
+                    for frame in &mut table.frames {
+                        let types = match frame {
+                            StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                            | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                                vec![stack]
+                            }
+                            StackMapFrame::AppendFrame { locals, .. } => {
+                                locals.iter_mut().collect()
+                            }
+                            StackMapFrame::FullFrame { locals, stack, .. } => {
+                                locals.iter_mut().chain(stack).collect()
+                            }
+                            _ => Vec::new(),
+                        };
+                        for info in types {
+                            if let VerificationTypeInfo::Object {
+                                constant: Constant::ClassIndex(name_index),
+                            } = info
+                            {
+                                if let Some(name) = constant_pool.utf8(*name_index as usize) {
+                                    *name_index = editor.utf8(&self.map_class(name));
+                                }
+                            }
+                        }
+                    }
+                }
+                Attribute::LocalVariableTable(table) => {
+                    for variable in &mut table.local_variable_table {
+                        if let Some(descriptor) =
+                            constant_pool.utf8(variable.descriptor_index as usize)
+                        {
+                            variable.descriptor_index =
+                                editor.utf8(&self.map_descriptor(descriptor));
+                        }
+                    }
+                }
+                Attribute::LocalVariableTypeTable(table) => {
+                    for variable in &mut table.local_variable_type_table {
+                        if let Some(signature) =
+                            constant_pool.utf8(variable.signature_index as usize)
+                        {
+                            variable.signature_index = editor.utf8(&self.map_signature(signature));
+                        }
+                    }
+                }
+                Attribute::NestHost(nest_host) => nest_host.name = self.map_class(&nest_host.name),
+                Attribute::NestMembers(nest_members) => {
+                    nest_members.names.iter_mut().for_each(|name| *name = self.map_class(name))
+                }
+                Attribute::PermittedSubclasses(permitted_subclasses) => permitted_subclasses
+                    .names
+                    .iter_mut()
+                    .for_each(|name| *name = self.map_class(name)),
+                Attribute::BootstrapMethods(bootstrap_methods) => {
+                    for method in &mut bootstrap_methods.bootstrap_methods {
+                        self.remap_record_names(
+                            &mut method.bootstrap_arguments,
+                            method.bootstrap_method_ref,
+                            context,
+                            editor,
+                        );
+                    }
+                }
+                Attribute::UserDefined(user_defined) => {
+                    let mut info = user_defined.info().to_vec();
+                    let mut raw = RawAttribute { info: &mut info, position: 0 };
+                    if self.remap_raw(user_defined.name(), &mut raw, context, editor).is_some() {
+                        let name = user_defined.name().to_string();
+                        *user_defined = UserDefinedAttribute::new(name, &info);
+                    }
+                }
+                Attribute::ConstantValue(_)
+                | Attribute::LineNumberTable(_)
+                | Attribute::SourceFile(_) => {}
+            }
+        }
+    }
+
+    /// Replaces the field names `ObjectMethods` receives for the `toString` of a record, which
+    /// it pairs with the getters passed as the following arguments.
+    fn remap_record_names(
+        &self,
+        arguments: &mut [u16],
+        bootstrap_method_ref: u16,
+        context: &Context,
+        editor: &mut ConstantPoolEditor,
+    ) -> Option<()> {
+        let constant_pool = context.constant_pool;
+        let Ok(Constant::MethodHandle(_, reference)) =
+            constant_pool.get(bootstrap_method_ref as usize)
+        else {
+            return None;
+        };
+        if constant_pool.member_ref(*reference as usize)?.0 != OBJECT_METHODS || arguments.len() < 2
+        {
+            return None;
+        }
+        let mut names = Vec::new();
+        for &argument in &arguments[2..] {
+            let Ok(Constant::MethodHandle(REF_GET_FIELD, field)) =
+                constant_pool.get(argument as usize)
+            else {
+                return None;
+            };
+            let (owner, name, descriptor) = constant_pool.member_ref(*field as usize)?;
+            names.push(self.map_field(owner, name, descriptor));
+        }
+        let Ok(Constant::StringIndex(string_index)) = constant_pool.get(arguments[1] as usize)
+        else {
+            return None;
+        };
+        let joined = names.join(";");
+        if constant_pool.utf8(*string_index as usize)? != joined {
+            let utf8 = editor.utf8(&joined);
+            arguments[1] = editor.add(Constant::StringIndex(utf8));
+        }
+        Some(())
+    }
+
+    /// Remaps a predefined attribute the reader does not decode. Returns `None` for malformed
+    /// attributes, which are kept as they are.
+    fn remap_raw(
+        &self,
+        name: &str,
+        raw: &mut RawAttribute,
+        context: &Context,
+        editor: &mut ConstantPoolEditor,
+    ) -> Option<()> {
+        let constant_pool = context.constant_pool;
+        match name {
+            "Signature" => {
+                let signature = constant_pool.utf8(raw.u16()? as usize)?;
+                raw.replace(editor.utf8(&self.map_signature(signature)));
+            }
+            "InnerClasses" => {
+                for _ in 0..raw.u16()? {
+                    let inner = constant_pool.class_name(raw.u16()? as usize);
+                    let outer = constant_pool.class_name(raw.u16()? as usize);
+                    let simple_name = constant_pool.utf8(raw.u16()? as usize);
+                    raw.skip(2)?;
+                    let (Some(inner), Some(simple_name)) = (inner, simple_name) else {
+                        continue;
+                    };
+                    let mapped = self.map_class(inner);
+                    let outer = outer.map(|outer| self.map_class(outer));
+                    let mapped_simple_name = inner_simple_name(&mapped, outer.as_deref());
+                    if mapped != inner
+                        && !mapped_simple_name.is_empty()
+                        && mapped_simple_name != simple_name
+                    {
+                        raw.write_u16(raw.position - 4, editor.utf8(mapped_simple_name));
+                    }
+                }
+            }
+            "EnclosingMethod" => {
+                let owner = constant_pool.class_name(raw.u16()? as usize)?;
+                let method_index = raw.u16()?;
+                if method_index != 0 {
+                    let (name, descriptor) = constant_pool.name_and_type(method_index as usize)?;
+                    let mapped_name = self.map_method(owner, name, descriptor);
+                    let mapped_descriptor = self.map_descriptor(descriptor);
+                    raw.replace(editor.name_and_type(&mapped_name, &mapped_descriptor));
+                }
+            }
+            "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+                self.annotations(raw, constant_pool, editor)?
+            }
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                for _ in 0..raw.u8()? {
+                    self.annotations(raw, constant_pool, editor)?;
+                }
+            }
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                for _ in 0..raw.u16()? {
+                    raw.skip_type_annotation_target()?;
+                    self.annotation(raw, constant_pool, editor)?;
+                }
+            }
+            "AnnotationDefault" => self.element_value(raw, constant_pool, editor)?,
+            "Record" => {
+                for _ in 0..raw.u16()? {
+                    let name = constant_pool.utf8(raw.u16()? as usize)?;
+                    let descriptor = constant_pool.utf8(raw.u16()? as usize)?;
+                    raw.replace(editor.utf8(&self.map_descriptor(descriptor)));
+                    let mapped_name = self.map_field(context.this_class, name, descriptor);
+                    raw.write_u16(raw.position - 4, editor.utf8(&mapped_name));
+                    for _ in 0..raw.u16()? {
+                        let name = constant_pool.utf8(raw.u16()? as usize)?;
+                        let length = raw.u32()? as usize;
+                        let start = raw.position;
+                        raw.skip(length)?;
+                        let mut nested = RawAttribute {
+                            info: &mut raw.info[start..start + length],
+                            position: 0,
+                        };
+                        // Malformed nested attributes are kept like top-level ones.
+                        self.remap_raw(name, &mut nested, context, editor);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn annotations(
+        &self,
+        raw: &mut RawAttribute,
+        constant_pool: &ConstantPool,
+        editor: &mut ConstantPoolEditor,
+    ) -> Option<()> {
+        for _ in 0..raw.u16()? {
+            self.annotation(raw, constant_pool, editor)?;
+        }
+        Some(())
+    }
+
+    fn annotation(
+        &self,
+        raw: &mut RawAttribute,
+        constant_pool: &ConstantPool,
+        editor: &mut ConstantPoolEditor,
+    ) -> Option<()> {
+        let descriptor = constant_pool.utf8(raw.u16()? as usize)?;
+        raw.replace(editor.utf8(&self.map_descriptor(descriptor)));
+        let annotation_type = descriptor.strip_prefix('L')?.strip_suffix(';')?;
+        for _ in 0..raw.u16()? {
+            let name = constant_pool.utf8(raw.u16()? as usize)?;
+            if let Some(mapped) =
+                self.elements.get(&(annotation_type.to_string(), name.to_string()))
+            {
+                raw.replace(editor.utf8(mapped));
+            }
+            self.element_value(raw, constant_pool, editor)?;
+        }
+        Some(())
+    }
+
+    fn element_value(
+        &self,
+        raw: &mut RawAttribute,
+        constant_pool: &ConstantPool,
+        editor: &mut ConstantPoolEditor,
+    ) -> Option<()> {
+        match raw.u8()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => raw.skip(2)?,
+            b'e' => {
+                let descriptor = constant_pool.utf8(raw.u16()? as usize)?;
+                raw.replace(editor.utf8(&self.map_descriptor(descriptor)));
+                let name = constant_pool.utf8(raw.u16()? as usize)?;
+                let enum_type = descriptor.strip_prefix('L')?.strip_suffix(';')?;
+                raw.replace(editor.utf8(&self.map_field(enum_type, name, descriptor)));
+            }
+            b'c' => {
+                let descriptor = constant_pool.utf8(raw.u16()? as usize)?;
+                raw.replace(editor.utf8(&self.map_descriptor(descriptor)));
+            }
+            b'@' => self.annotation(raw, constant_pool, editor)?,
+            b'[' => {
+                for _ in 0..raw.u16()? {
+                    self.element_value(raw, constant_pool, editor)?;
+                }
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// What attributes are remapped against: the constant pool before remapping, which the
+/// attribute indices still refer to, and the name of the class before remapping.
+struct Context<'a> {
+    constant_pool: &'a ConstantPool,
+    this_class: &'a str,
+}
+
+/// Finds or adds the `Utf8` and `NameAndType` entries remapped names need, reusing the first
+/// existing entry like [`ClassFileWriter`] does.
+struct ConstantPoolEditor<'a> {
+    constant_pool: &'a mut ConstantPool,
+    utf8_indices: HashMap<String, u16>,
+    name_and_type_indices: HashMap<(u16, u16), u16>,
+    overflow: bool,
+}
+
+impl<'a> ConstantPoolEditor<'a> {
+    fn new(constant_pool: &'a mut ConstantPool) -> Self {
+        let mut utf8_indices = HashMap::new();
+        let mut name_and_type_indices = HashMap::new();
+        for (position, constant) in constant_pool.constants.iter().enumerate() {
+            let index = position as u16 + 1;
+            match constant {
+                Constant::Utf8(string) => {
+                    utf8_indices.entry(string.clone()).or_insert(index);
+                }
+                Constant::NameAndType(name, descriptor) => {
+                    name_and_type_indices.entry((*name, *descriptor)).or_insert(index);
+                }
+                _ => {}
+            }
+        }
+        ConstantPoolEditor { constant_pool, utf8_indices, name_and_type_indices, overflow: false }
+    }
+
+    fn add(&mut self, constant: Constant) -> u16 {
+        if self.constant_pool.constants.len() + 1 >= u16::MAX as usize {
+            self.overflow = true;
+            return 0;
+        }
+        self.constant_pool.add(constant);
+        self.constant_pool.constants.len() as u16
+    }
+
+    fn utf8(&mut self, string: &str) -> u16 {
+        if let Some(&index) = self.utf8_indices.get(string) {
+            return index;
+        }
+        let index = self.add(Constant::Utf8(string.to_string()));
+        self.utf8_indices.insert(string.to_string(), index);
+        index
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> u16 {
+        let key = (self.utf8(name), self.utf8(descriptor));
+        if let Some(&index) = self.name_and_type_indices.get(&key) {
+            return index;
+        }
+        let index = self.add(Constant::NameAndType(key.0, key.1));
+        self.name_and_type_indices.insert(key, index);
+        index
+    }
+}
+
+/// Rewrites the class names of a signature while checking it against the grammar of class,
+/// method and field signatures.
+struct SignatureRemapper<'a> {
+    mappings: &'a Mappings,
+    signature: &'a str,
+    position: usize,
+    output: String,
+}
+
+impl SignatureRemapper<'_> {
+    fn peek(&self) -> Option<char> {
+        self.signature[self.position..].chars().next()
+    }
+
+    /// Copies the next character to the output.
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        self.output.push(c);
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        (self.next()? == expected).then_some(())
+    }
+
+    /// Reads an identifier without copying it.
+    fn identifier(&mut self) -> Option<&str> {
+        let rest = &self.signature[self.position..];
+        let end = rest.find(['.', ';', '[', '/', '<', '>', ':']).unwrap_or(rest.len());
+        self.position += end;
+        (end > 0).then(|| &rest[..end])
+    }
+
+    fn signature(&mut self) -> Option<()> {
+        if self.peek() == Some('<') {
+            self.type_parameters()?;
+        }
+        if self.peek() == Some('(') {
+            self.next();
+            while self.peek()? != ')' {
+                self.java_type()?;
+            }
+            self.next();
+            match self.peek()? {
+                'V' => self.expect('V')?,
+                _ => self.java_type()?,
+            }
+            while self.peek() == Some('^') {
+                self.next();
+                self.reference_type()?;
+            }
+        } else {
+            // A field signature, or the superclass and interfaces of a class signature.
+            self.reference_type()?;
+            while self.position < self.signature.len() {
+                self.reference_type()?;
+            }
+        }
+        (self.position == self.signature.len()).then_some(())
+    }
+
+    fn type_parameters(&mut self) -> Option<()> {
+        self.expect('<')?;
+        while self.peek()? != '>' {
+            let name = self.identifier()?.to_string();
+            self.output.push_str(&name);
+            self.expect(':')?;
+            if matches!(self.peek()?, 'L' | 'T' | '[') {
+                self.reference_type()?;
+            }
+            while self.peek() == Some(':') {
+                self.next();
+                self.reference_type()?;
+            }
+        }
+        self.expect('>')
+    }
+
+    fn java_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' => self.next().map(drop),
+            _ => self.reference_type(),
+        }
+    }
+
+    fn reference_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            'L' => self.class_type(),
+            'T' => {
+                self.next();
+                let name = self.identifier()?.to_string();
+                self.output.push_str(&name);
+                self.expect(';')
+            }
+            '[' => {
+                self.next();
+                self.java_type()
+            }
+            _ => None,
+        }
+    }
+
+    /// A class type, whose nested classes are given by their simple names after a `.`.
+    fn class_type(&mut self) -> Option<()> {
+        self.expect('L')?;
+        let rest = &self.signature[self.position..];
+        let end = rest.find(['<', '.', ';'])?;
+        let mut name = rest[..end].to_string();
+        self.position += end;
+        let mut mapped = self.mappings.map_class(&name);
+        self.output.push_str(&mapped);
+        loop {
+            if self.peek()? == '<' {
+                self.type_arguments()?;
+            }
+            match self.next()? {
+                '.' => {
+                    name = format!("{}${}", name, self.identifier()?);
+                    let mapped_nested = self.mappings.map_class(&name);
+                    self.output.push_str(inner_simple_name(&mapped_nested, Some(&mapped)));
+                    mapped = mapped_nested;
+                }
+                ';' => return Some(()),
+                _ => return None,
+            }
+        }
+    }
+
+    fn type_arguments(&mut self) -> Option<()> {
+        self.expect('<')?;
+        while self.peek()? != '>' {
+            match self.peek()? {
+                '*' => self.expect('*')?,
+                '+' | '-' => {
+                    self.next();
+                    self.reference_type()?;
+                }
+                _ => self.reference_type()?,
+            }
+        }
+        self.expect('>')
+    }
+}
+
+/// The simple name of the nested class `name`: what follows its outer class, or its last
+/// `$`-separated part, without the digits that prefix local classes.
+fn inner_simple_name<'a>(name: &'a str, outer: Option<&str>) -> &'a str {
+    if let Some(simple_name) = outer.and_then(|outer| name.strip_prefix(outer)?.strip_prefix('$')) {
+        return simple_name;
+    }
+    let simple_name = name.rsplit(['$', '/']).next().unwrap_or(name);
+    match outer {
+        Some(_) => simple_name,
+        None => simple_name.trim_start_matches(|c: char| c.is_ascii_digit()),
+    }
+}
+
+fn internal_name(java_name: &str) -> String {
+    java_name.replace('.', "/")
+}
+
+/// The descriptor of a Java type such as `int[]` or `java.lang.String`, with class names
+/// mapped by `classes`.
+fn java_type_descriptor(java_type: &str, classes: &HashMap<String, String>) -> String {
+    let mut element = java_type;
+    let mut descriptor = String::new();
+    while let Some(component) = element.strip_suffix("[]") {
+        descriptor.push('[');
+        element = component;
+    }
+    match element {
+        "boolean" => descriptor.push('Z'),
+        "byte" => descriptor.push('B'),
+        "char" => descriptor.push('C'),
+        "short" => descriptor.push('S'),
+        "int" => descriptor.push('I'),
+        "long" => descriptor.push('J'),
+        "float" => descriptor.push('F'),
+        "double" => descriptor.push('D'),
+        "void" => descriptor.push('V'),
+        class => {
+            let name = internal_name(class);
+            descriptor.push_str(&format!("L{};", classes.get(&name).unwrap_or(&name)));
+        }
+    }
+    descriptor
+}
+
+/// The type, name, parameter types, line range and original line range of a ProGuard member
+/// line without its obfuscated name, such as `1:3:void run(int):10:12`.
+#[allow(clippy::type_complexity)]
+fn parse_proguard_member(
+    member: &str,
+) -> Option<(&str, &str, Option<Vec<&str>>, Option<(u32, u32)>, Option<(u32, u32)>)> {
+    let (signature, parameters, original) = match member.find('(') {
+        Some(open) => {
+            let close = open + member[open..].find(')')?;
+            let parameters = &member[open + 1..close];
+            let parameters =
+                parameters.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+            (&member[..open], Some(parameters), &member[close + 1..])
+        }
+        None => (member, None, ""),
+    };
+    let mut parts = signature.split(':');
+    let (lines, declaration) = match (parts.next()?, parts.next(), parts.next()) {
+        (start, Some(end), Some(declaration)) => {
+            (Some((start.parse().ok()?, end.parse().ok()?)), declaration)
+        }
+        (declaration, None, None) => (None, declaration),
+        _ => return None,
+    };
+    let (java_type, name) = declaration.trim().rsplit_once(' ')?;
+    let mut original = original.split(':').skip(1).map(str::parse::<u32>);
+    let original_lines = match (original.next(), original.next()) {
+        (Some(start), Some(end)) => Some((start.ok()?, end.ok()?)),
+        (Some(line), None) => line.ok().map(|line| (line, line)),
+        (None, _) => lines,
+    };
+    Some((java_type.trim(), name, parameters, lines, original_lines))
+}
+
+/// The string value of `key` in a flat JSON object.
+fn json_string<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let rest = &json[json.find(&format!("\"{}\"", key))? + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    Some(&rest[..rest.find('"')?])
+}
+
+fn unescape(name: &str) -> String {
+    let mut unescaped = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('t') => unescaped.push('\t'),
+            Some('0') => unescaped.push('\0'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// The location of a retraced frame: the original source file and line, or `location` if the
+/// frame had no source file.
+fn retrace_location(
+    source_file: Option<&String>,
+    class: &str,
+    location: &str,
+    line: Option<u32>,
+) -> String {
+    if location == "Native Method" || location == "Unknown Source" {
+        return location.to_string();
+    }
+    let file = source_file.cloned().unwrap_or_else(|| {
+        let simple_name = class.rsplit('/').next().unwrap_or(class);
+        format!("{}.java", simple_name.split('$').next().unwrap_or(simple_name))
+    });
+    match line {
+        Some(line) => format!("{}:{}", file, line),
+        None => file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGUARD: &str = "\
+# compiler: R8
+com.example.Main -> a.a:
+# {\"id\":\"sourceFile\",\"fileName\":\"Main.java\"}
+    int count -> a
+    com.example.Main$Inner inner -> b
+    1:1:void <init>():10:10 -> <init>
+    1:4:void helper(int):20:23 -> a
+    5:5:void inlined():30:30 -> b
+    5:5:void run(com.example.Main):40 -> b
+    6:8:void run(com.example.Main):41:43 -> b
+    void unused(java.lang.String[]) -> c
+com.example.Main$Inner -> a.b:
+    void call() -> a
+";
+
+    #[test]
+    fn test_proguard_mappings() {
+        let mappings = Mappings::from_proguard(PROGUARD).unwrap();
+
+        assert_eq!(mappings.class("a/a"), Some("com/example/Main"));
+        assert_eq!(mappings.class("a/b"), Some("com/example/Main$Inner"));
+        assert_eq!(mappings.field("a/a", "a", "I"), Some("count"));
+        assert_eq!(mappings.field("a/a", "b", "La/b;"), Some("inner"));
+        assert_eq!(mappings.method("a/a", "a", "(I)V"), Some("helper"));
+        assert_eq!(mappings.method("a/a", "b", "(La/a;)V"), Some("run"));
+        assert_eq!(mappings.method("a/a", "b", "()V"), None);
+        assert_eq!(mappings.method("a/a", "c", "([Ljava/lang/String;)V"), Some("unused"));
+        assert_eq!(mappings.method("a/b", "a", "()V"), Some("call"));
+
+        let reversed = mappings.reversed();
+        assert_eq!(reversed.class("com/example/Main"), Some("a/a"));
+        assert_eq!(reversed.method("com/example/Main", "run", "(Lcom/example/Main;)V"), Some("b"));
+
+        let error = Mappings::from_proguard("a.B -> c:\n    int -> d\n").unwrap_err();
+        assert!(matches!(error, RemapperError::InvalidMapping(2, _)), "{}", error);
+    }
+
+    #[test]
+    fn test_retrace() {
+        let mappings = Mappings::from_proguard(PROGUARD).unwrap();
+        let trace = "\
+Exception in thread \"main\" java.lang.IllegalStateException: a.b failed
+\tat a.a.b(SourceFile:5)
+\tat app//a.a.b(SourceFile:7)
+\tat a.a.c(SourceFile)
+\tat a.b.a(Unknown Source)
+\tat java.base/java.lang.Thread.run(Thread.java:1583)
+Caused by: a.b$1: a.a
+\t... 3 more
+";
+        let expected = "\
+Exception in thread \"main\" java.lang.IllegalStateException: com.example.Main$Inner failed
+\tat com.example.Main.inlined(Main.java:30)
+\tat com.example.Main.run(Main.java:40)
+\tat app//com.example.Main.run(Main.java:42)
+\tat com.example.Main.unused(Main.java)
+\tat com.example.Main$Inner.call(Unknown Source)
+\tat java.base/java.lang.Thread.run(Thread.java:1583)
+Caused by: com.example.Main$Inner$1: com.example.Main
+\t... 3 more
+";
+        assert_eq!(mappings.retrace(trace), expected);
+    }
+
+    #[test]
+    fn test_tiny_mappings() {
+        let tiny = "\
+tiny\t2\t0\tofficial\tintermediary\tnamed
+\tescaped-names
+c\ta\tclass_1\tcom/example/Main
+\tc\tThe main class.
+\tf\tLa;\tb\tfield_1\tself
+\tm\t(La;I)V\tc\tmethod_1\trun
+\t\tp\t1\t\t\tcount
+c\ta$b\tclass_1$class_2\t
+\tm\t()V\td\tmethod_2\tname\\twith\\ttabs
+";
+        let mappings = Mappings::from_tiny(tiny, "intermediary", "named").unwrap();
+        assert_eq!(mappings.class("class_1"), Some("com/example/Main"));
+        assert_eq!(mappings.class("class_1$class_2"), Some("a$b"));
+        assert_eq!(mappings.field("class_1", "field_1", "Lclass_1;"), Some("self"));
+        assert_eq!(mappings.method("class_1", "method_1", "(Lclass_1;I)V"), Some("run"));
+        assert_eq!(mappings.method("class_1$class_2", "method_2", "()V"), Some("name\twith\ttabs"));
+        assert_eq!(mappings.len(), 5);
+
+        let reversed = mappings.reversed();
+        assert_eq!(
+            reversed.method("com/example/Main", "run", "(Lcom/example/Main;I)V"),
+            Some("method_1")
+        );
+
+        let official = Mappings::from_tiny(tiny, "official", "named").unwrap();
+        assert_eq!(official.method("a", "c", "(La;I)V"), Some("run"));
+
+        let error = Mappings::from_tiny(tiny, "obfuscated", "named").unwrap_err();
+        assert!(matches!(error, RemapperError::UnknownNamespace(_)), "{}", error);
+        let error = Mappings::from_tiny("v1\tofficial\tnamed\n", "official", "named").unwrap_err();
+        assert!(matches!(error, RemapperError::InvalidMapping(1, _)), "{}", error);
+    }
+
+    #[test]
+    fn test_map_names() {
+        let mut mappings = Mappings::new();
+        mappings
+            .add_class("a", "com/Foo")
+            .add_class("a$b", "com/Foo$Bar")
+            .add_class("c", "com/Baz");
+
+        assert_eq!(mappings.map_class("a$1"), "com/Foo$1");
+        assert_eq!(mappings.map_class("[[La$b;"), "[[Lcom/Foo$Bar;");
+        assert_eq!(mappings.map_class("d"), "d");
+        assert_eq!(
+            mappings.map_descriptor("(ILa;[Lc;)La$b;"),
+            "(ILcom/Foo;[Lcom/Baz;)Lcom/Foo$Bar;"
+        );
+        assert_eq!(
+            mappings.map_signature("<T:La;>Ljava/lang/Object;Ljava/util/List<La<TT;>.b;>;"),
+            "<T:Lcom/Foo;>Ljava/lang/Object;Ljava/util/List<Lcom/Foo<TT;>.Bar;>;"
+        );
+        assert_eq!(
+            mappings.map_signature("<E:Ljava/lang/Exception;>(Ljava/util/Map<+Lc;*>;[TE;)Lc;^TE;"),
+            "<E:Ljava/lang/Exception;>(Ljava/util/Map<+Lcom/Baz;*>;[TE;)Lcom/Baz;^TE;"
+        );
+        assert_eq!(mappings.map_signature("<T::La;>Lc;"), "<T::Lcom/Foo;>Lcom/Baz;");
+        assert_eq!(mappings.map_signature("La<"), "La<");
+    }
+}
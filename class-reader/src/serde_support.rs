@@ -0,0 +1,423 @@
+//! Serde implementations for the types whose serialized form differs from their layout.
+//!
+//! The schema favours readability next to round-tripping: descriptors are strings, access
+//! flags carry their names next to the mask, and every constant pool entry and every
+//! instruction with a constant pool operand carries the symbol it resolves to next to its raw
+//! indices. Derived fields ignore those extras when deserializing, so only the raw values
+//! have to be present.
+//!
+//! Instructions resolve their operands against the constant pool of their class, which a
+//! [`ClassFile`] has at hand. A method or a `Code` attribute serialized on its own has no
+//! symbols unless it is wrapped in a [`WithPool`].
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::access_flag::{AccessFlags, ClassFileAccessFlags};
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::class_file_version::ClassFileVersion;
+use crate::constant_pool::{Constant, ConstantPool};
+use crate::field::{Field, FieldAccessFlags, FieldType};
+use crate::instruction::Instruction;
+use crate::method::{Method, MethodAccessFlags, MethodDescriptor};
+use crate::predefined_attributes::{Code, ExceptionHandler};
+
+/// A value serialized with the constant pool its instructions resolve their operands against,
+/// e.g. a method as it appears in its serialized class.
+#[derive(Debug, Clone, Copy)]
+pub struct WithPool<'a, T: ?Sized> {
+    pub constant_pool: &'a ConstantPool,
+    pub value: &'a T,
+}
+
+impl<'a, T: ?Sized> WithPool<'a, T> {
+    pub fn new(constant_pool: &'a ConstantPool, value: &'a T) -> Self {
+        WithPool { constant_pool, value }
+    }
+
+    fn with<U: ?Sized>(&self, value: &'a U) -> WithPool<'a, U> {
+        WithPool::new(self.constant_pool, value)
+    }
+}
+
+impl<'a, T> Serialize for WithPool<'a, [T]>
+where
+    WithPool<'a, T>: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.value.iter().map(|value| self.with(value)))
+    }
+}
+
+#[derive(Serialize)]
+struct MethodRef<'a> {
+    flags: &'a MethodAccessFlags,
+    name: &'a str,
+    type_descriptor: &'a MethodDescriptor,
+    attributes: WithPool<'a, [Attribute]>,
+}
+
+impl Serialize for WithPool<'_, Method> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Method { flags, name, type_descriptor, attributes } = self.value;
+        let attributes = self.with(&attributes[..]);
+        MethodRef { flags, name, type_descriptor, attributes }.serialize(serializer)
+    }
+}
+
+/// The index of `Attribute::Code` among the variants of the derived implementation.
+const CODE_VARIANT_INDEX: u32 = 1;
+
+impl Serialize for WithPool<'_, Attribute> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.value {
+            Attribute::Code(code) => serializer.serialize_newtype_variant(
+                "Attribute",
+                CODE_VARIANT_INDEX,
+                "Code",
+                &self.with(code),
+            ),
+            attribute => attribute.serialize(serializer),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CodeRef<'a> {
+    max_stack: u16,
+    max_locals: u16,
+    code: InstructionsRef<'a>,
+    exception_table: &'a [ExceptionHandler],
+    attributes: WithPool<'a, [Attribute]>,
+}
+
+impl Serialize for WithPool<'_, Code> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Code { max_stack, max_locals, code, exception_table, attributes } = self.value;
+        CodeRef {
+            max_stack: *max_stack,
+            max_locals: *max_locals,
+            code: InstructionsRef { constant_pool: Some(self.constant_pool), code },
+            exception_table,
+            attributes: self.with(&attributes[..]),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Serialize)]
+struct ClassFileRef<'a> {
+    version: &'a ClassFileVersion,
+    constant_pool: &'a ConstantPool,
+    flags: &'a ClassFileAccessFlags,
+    this_class: &'a str,
+    super_class: &'a Option<String>,
+    interfaces: &'a [String],
+    fields: &'a [Field],
+    methods: WithPool<'a, [Method]>,
+    attributes: &'a [Attribute],
+}
+
+/// Serialized field by field, with its constant pool at hand for the instructions.
+impl Serialize for ClassFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ClassFile {
+            version,
+            constant_pool,
+            flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        } = self;
+        let class_file = ClassFileRef {
+            version,
+            constant_pool,
+            flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods: WithPool::new(constant_pool, methods),
+            attributes,
+        };
+        class_file.serialize(serializer)
+    }
+}
+
+#[derive(Serialize)]
+struct ConstantEntryRef<'a> {
+    index: usize,
+    constant: &'a Constant,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ConstantEntry {
+    index: usize,
+    constant: Constant,
+}
+
+/// Serialized as the list of usable entries; the slot after a `Long` or `Double` is implied.
+impl Serialize for ConstantPool {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            self.constants
+                .iter()
+                .enumerate()
+                .filter(|(_, constant)| !matches!(constant, Constant::Unsuable))
+                .map(|(position, constant)| ConstantEntryRef {
+                    index: position + 1,
+                    constant,
+                    symbol: self.describe(position + 1),
+                }),
+        )
+    }
+}
+
+impl<'de> Deserialize<'de> for ConstantPool {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut constant_pool = ConstantPool::default();
+        for entry in Vec::<ConstantEntry>::deserialize(deserializer)? {
+            let expected = constant_pool.constants.len() + 1;
+            if entry.index != expected {
+                return Err(D::Error::custom(format!(
+                    "constant pool entry {} out of order, expected index {}",
+                    entry.index, expected
+                )));
+            }
+            if entry.constant == Constant::Unsuable {
+                return Err(D::Error::custom("unusable constant pool entries are implied"));
+            }
+            constant_pool.add(entry.constant);
+        }
+        Ok(constant_pool)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Version {
+    major: u16,
+    minor: u16,
+}
+
+impl Serialize for ClassFileVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Version { major: self.major(), minor: self.minor() }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ClassFileVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Version { major, minor } = Version::deserialize(deserializer)?;
+        ClassFileVersion::from(major, minor).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize)]
+struct FlagsRef {
+    mask: u16,
+    names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Flags {
+    mask: u16,
+}
+
+macro_rules! access_flags_serde {
+    ($flags:ty) => {
+        impl Serialize for $flags {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                FlagsRef { mask: self.mask(), names: self.names() }.serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $flags {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Ok(<$flags>::new(Flags::deserialize(deserializer)?.mask))
+            }
+        }
+    };
+}
+
+access_flags_serde!(ClassFileAccessFlags);
+access_flags_serde!(FieldAccessFlags);
+access_flags_serde!(MethodAccessFlags);
+
+/// Serialized as the descriptor string, e.g. `[Ljava/lang/String;`.
+impl Serialize for FieldType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for FieldType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let descriptor = String::deserialize(deserializer)?;
+        let mut chars = descriptor.chars().peekable();
+        let field_type = FieldType::try_from(&mut chars).map_err(D::Error::custom)?;
+        match chars.next() {
+            None => Ok(field_type),
+            Some(_) => Err(D::Error::custom(format!("trailing characters in {}", descriptor))),
+        }
+    }
+}
+
+/// Serialized as the descriptor string, e.g. `(I[J)V`.
+impl Serialize for MethodDescriptor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for MethodDescriptor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let descriptor = String::deserialize(deserializer)?;
+        let mut chars = descriptor.chars().peekable();
+        let method_descriptor = MethodDescriptor::try_from(&mut chars).map_err(D::Error::custom)?;
+        match chars.next() {
+            None => Ok(method_descriptor),
+            Some(_) => Err(D::Error::custom(format!("trailing characters in {}", descriptor))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FloatValue<T> {
+    Number(T),
+    Bits(String),
+}
+
+/// Finite floats as numbers; NaNs and infinities, which JSON cannot represent, as their bit
+/// pattern in hex so that NaN payloads survive a round trip.
+pub(crate) mod f32_value {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        match value.is_finite() {
+            true => FloatValue::Number(*value),
+            false => FloatValue::Bits(format!("{:#010x}", value.to_bits())),
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        match FloatValue::deserialize(deserializer)? {
+            FloatValue::Number(value) => Ok(value),
+            FloatValue::Bits(bits) => u32::from_str_radix(bits.trim_start_matches("0x"), 16)
+                .map(f32::from_bits)
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+pub(crate) mod f64_value {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match value.is_finite() {
+            true => FloatValue::Number(*value),
+            false => FloatValue::Bits(format!("{:#018x}", value.to_bits())),
+        }
+        .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        match FloatValue::deserialize(deserializer)? {
+            FloatValue::Number(value) => Ok(value),
+            FloatValue::Bits(bits) => u64::from_str_radix(bits.trim_start_matches("0x"), 16)
+                .map(f64::from_bits)
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct InstructionEntryRef<'a> {
+    pc: u32,
+    instruction: &'a Instruction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<String>,
+}
+
+struct InstructionsRef<'a> {
+    constant_pool: Option<&'a ConstantPool>,
+    code: &'a [(Instruction, u32)],
+}
+
+impl Serialize for InstructionsRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.code.iter().map(|(instruction, pc)| InstructionEntryRef {
+            pc: *pc,
+            instruction,
+            symbol:
+                self.constant_pool.and_then(|pool| instructions::operand_symbol(pool, instruction)),
+        }))
+    }
+}
+
+#[derive(Deserialize)]
+struct InstructionEntry {
+    pc: u32,
+    instruction: Instruction,
+}
+
+/// The instructions of a `Code` attribute, each with its pc and, when serialized with its
+/// constant pool, the symbol or literal each constant pool operand resolves to:
+/// `java/lang/String.length:()I` for a method reference, `42L` for a long loaded by `ldc2_w`.
+pub(crate) mod instructions {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        code: &[(Instruction, u32)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        InstructionsRef { constant_pool: None, code }.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(Instruction, u32)>, D::Error> {
+        let entries = Vec::<InstructionEntry>::deserialize(deserializer)?;
+        Ok(entries.into_iter().map(|entry| (entry.instruction, entry.pc)).collect())
+    }
+
+    pub(super) fn operand_symbol(
+        constant_pool: &ConstantPool,
+        instruction: &Instruction,
+    ) -> Option<String> {
+        let index = match instruction {
+            Instruction::Ldc(index) => *index as usize,
+            Instruction::Ldc_w(index)
+            | Instruction::Ldc2_w(index)
+            | Instruction::Getstatic(index)
+            | Instruction::Putstatic(index)
+            | Instruction::Getfield(index)
+            | Instruction::Putfield(index)
+            | Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index)
+            | Instruction::Invokeinterface(index, _)
+            | Instruction::Invokedynamic(index)
+            | Instruction::New(index)
+            | Instruction::Anewarray(index)
+            | Instruction::Checkcast(index)
+            | Instruction::Instanceof(index)
+            | Instruction::Multianewarray(index, _) => *index as usize,
+            _ => return None,
+        };
+        match constant_pool.get(index).ok()? {
+            Constant::Integer(value) => Some(value.to_string()),
+            Constant::Float(value) => Some(format!("{}f", value)),
+            Constant::Long(value) => Some(format!("{}L", value)),
+            Constant::Double(value) => Some(format!("{}d", value)),
+            _ => constant_pool.describe(index),
+        }
+    }
+}
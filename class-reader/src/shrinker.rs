@@ -0,0 +1,416 @@
+//! Shrinking of class files for deployment: stripping the attributes a program does not need
+//! to run, then dropping the constant pool entries nothing refers to anymore.
+//!
+//! The attributes that can be stripped are the debug information (`LineNumberTable`,
+//! `LocalVariableTable`, `LocalVariableTypeTable` and `SourceFile`), the annotations only
+//! visible to tools and attributes the JVM specification does not define. Constant pool
+//! compaction renumbers the entries that remain and rewrites every index that refers to them:
+//! in instructions, exception tables, attributes the reader decodes and the raw bodies of the
+//! other predefined attributes. A class keeping attributes of unknown layout keeps its whole
+//! constant pool, as their bodies may refer to any entry.
+//!
+//! Shrinking a jar rewrites its classes and copies the other entries unchanged, so the
+//! signatures of a signed jar no longer match.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::{Read, Seek, Write};
+
+use thiserror::Error;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::class_file_reader::{ClassFileReader, ContextualError};
+use crate::class_file_writer::{ClassFileWriter, ClassWriterError};
+use crate::constant_pool_rewriting::compact_constant_pool;
+
+type Result<T> = std::result::Result<T, ShrinkerError>;
+
+/// The attributes defined by the JVM specification, whose layout is known.
+const PREDEFINED_ATTRIBUTES: [&str; 30] = [
+    "ConstantValue",
+    "Code",
+    "StackMapTable",
+    "Exceptions",
+    "InnerClasses",
+    "EnclosingMethod",
+    "Synthetic",
+    "Signature",
+    "SourceFile",
+    "SourceDebugExtension",
+    "LineNumberTable",
+    "LocalVariableTable",
+    "LocalVariableTypeTable",
+    "Deprecated",
+    "RuntimeVisibleAnnotations",
+    "RuntimeInvisibleAnnotations",
+    "RuntimeVisibleParameterAnnotations",
+    "RuntimeInvisibleParameterAnnotations",
+    "RuntimeVisibleTypeAnnotations",
+    "RuntimeInvisibleTypeAnnotations",
+    "AnnotationDefault",
+    "BootstrapMethods",
+    "MethodParameters",
+    "Module",
+    "ModulePackages",
+    "ModuleMainClass",
+    "NestHost",
+    "NestMembers",
+    "Record",
+    "PermittedSubclasses",
+];
+
+const INVISIBLE_ANNOTATIONS: [&str; 3] = [
+    "RuntimeInvisibleAnnotations",
+    "RuntimeInvisibleParameterAnnotations",
+    "RuntimeInvisibleTypeAnnotations",
+];
+
+#[derive(Debug, Error)]
+pub enum ShrinkerError {
+    #[error("{0}")]
+    #[non_exhaustive]
+    Read(#[from] ContextualError),
+    #[error("{0}")]
+    #[non_exhaustive]
+    Write(#[from] ClassWriterError),
+    #[error("Invalid archive: {0}")]
+    #[non_exhaustive]
+    Zip(#[from] ZipError),
+    #[error("I/O error: {0}")]
+    #[non_exhaustive]
+    Io(#[from] std::io::Error),
+    #[error("Invalid entry {0}: {1}")]
+    #[non_exhaustive]
+    Entry(String, Box<ShrinkerError>),
+}
+
+/// What a [`Shrinker`] removes, and what the bytes it saves are reported under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, strum_macros::IntoStaticStr)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[strum(serialize_all = "snake_case")]
+pub enum ShrinkCategory {
+    LineNumberTable,
+    LocalVariableTable,
+    LocalVariableTypeTable,
+    SourceFile,
+    /// `RuntimeInvisibleAnnotations`, `RuntimeInvisibleParameterAnnotations` and
+    /// `RuntimeInvisibleTypeAnnotations`.
+    InvisibleAnnotations,
+    /// Attributes the JVM specification does not define, which the JVM ignores.
+    UnknownAttributes,
+    /// Constant pool entries nothing refers to, including those only the stripped attributes
+    /// referred to.
+    ConstantPool,
+}
+
+impl ShrinkCategory {
+    pub const ALL: [ShrinkCategory; 7] = [
+        ShrinkCategory::LineNumberTable,
+        ShrinkCategory::LocalVariableTable,
+        ShrinkCategory::LocalVariableTypeTable,
+        ShrinkCategory::SourceFile,
+        ShrinkCategory::InvisibleAnnotations,
+        ShrinkCategory::UnknownAttributes,
+        ShrinkCategory::ConstantPool,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
+    /// The category `attribute` is stripped with, `None` for attributes that are kept.
+    fn of(attribute: &Attribute) -> Option<Self> {
+        match attribute {
+            Attribute::LineNumberTable(_) => Some(ShrinkCategory::LineNumberTable),
+            Attribute::LocalVariableTable(_) => Some(ShrinkCategory::LocalVariableTable),
+            Attribute::LocalVariableTypeTable(_) => Some(ShrinkCategory::LocalVariableTypeTable),
+            Attribute::SourceFile(_) => Some(ShrinkCategory::SourceFile),
+            Attribute::UserDefined(attribute)
+                if INVISIBLE_ANNOTATIONS.contains(&attribute.name()) =>
+            {
+                Some(ShrinkCategory::InvisibleAnnotations)
+            }
+            Attribute::UserDefined(attribute)
+                if !PREDEFINED_ATTRIBUTES.contains(&attribute.name()) =>
+            {
+                Some(ShrinkCategory::UnknownAttributes)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for ShrinkCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// The sizes of shrunk classes, as encoded by [`ClassFileWriter`], and the bytes each
+/// category accounts for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShrinkReport {
+    pub classes: usize,
+    pub original_size: u64,
+    pub shrunk_size: u64,
+    pub saved: BTreeMap<ShrinkCategory, u64>,
+    /// The classes whose constant pool was kept whole because they keep attributes of unknown
+    /// layout.
+    pub uncompacted: Vec<String>,
+}
+
+impl ShrinkReport {
+    pub fn saved(&self, category: ShrinkCategory) -> u64 {
+        self.saved.get(&category).copied().unwrap_or(0)
+    }
+
+    pub fn total_saved(&self) -> u64 {
+        self.original_size.saturating_sub(self.shrunk_size)
+    }
+
+    /// Adds the classes of `other` to this report.
+    pub fn merge(&mut self, other: ShrinkReport) {
+        self.classes += other.classes;
+        self.original_size += other.original_size;
+        self.shrunk_size += other.shrunk_size;
+        for (category, saved) in other.saved {
+            *self.saved.entry(category).or_default() += saved;
+        }
+        self.uncompacted.extend(other.uncompacted);
+    }
+}
+
+impl Display for ShrinkReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} classes: {} -> {} bytes, {} saved",
+            self.classes,
+            self.original_size,
+            self.shrunk_size,
+            self.total_saved()
+        )?;
+        for (category, saved) in &self.saved {
+            writeln!(f, "  {:<26} {:>10}", category, saved)?;
+        }
+        for class in &self.uncompacted {
+            writeln!(f, "constant pool of {} kept: it has attributes of unknown layout", class)?;
+        }
+        Ok(())
+    }
+}
+
+/// Strips attributes from classes and compacts their constant pools; every category is
+/// stripped by default.
+#[derive(Debug, Clone)]
+pub struct Shrinker {
+    strip: Vec<ShrinkCategory>,
+}
+
+impl Default for Shrinker {
+    fn default() -> Self {
+        Shrinker { strip: ShrinkCategory::ALL.to_vec() }
+    }
+}
+
+impl Shrinker {
+    pub fn new() -> Self {
+        Shrinker::default()
+    }
+
+    /// Whether to strip the attributes of `category`, or for [`ShrinkCategory::ConstantPool`]
+    /// whether to compact the constant pool.
+    pub fn strip(&mut self, category: ShrinkCategory, enabled: bool) -> &mut Self {
+        self.strip.retain(|stripped| *stripped != category);
+        if enabled {
+            self.strip.push(category);
+            self.strip.sort();
+        }
+        self
+    }
+
+    /// Shrinks `class` in place.
+    pub fn shrink(&self, class: &mut ClassFile) -> Result<ShrinkReport> {
+        self.shrink_class(class).map(|(_, report)| report)
+    }
+
+    /// Shrinks an encoded class, returning the encoding of the shrunk class.
+    pub fn shrink_bytes(&self, data: &[u8]) -> Result<(Vec<u8>, ShrinkReport)> {
+        let mut class = ClassFileReader::read_class(data)?;
+        self.shrink_class(&mut class)
+    }
+
+    /// Writes `input` to `output` with its classes shrunk, keeping the order, compression
+    /// method and timestamps of the entries.
+    pub fn shrink_jar<R: Read + Seek, W: Write + Seek>(
+        &self,
+        input: R,
+        output: W,
+    ) -> Result<ShrinkReport> {
+        let mut archive = ZipArchive::new(input)?;
+        let mut writer = ZipWriter::new(output);
+        let mut report = ShrinkReport::default();
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let name = entry.name()?.to_string();
+            if entry.is_dir() || !name.ends_with(".class") {
+                writer.raw_copy_file(entry)?;
+                continue;
+            }
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let (shrunk, class_report) = self
+                .shrink_bytes(&data)
+                .map_err(|error| ShrinkerError::Entry(name.clone(), Box::new(error)))?;
+            let mut options = SimpleFileOptions::default().compression_method(entry.compression());
+            if let Some(time) = entry.last_modified() {
+                options = options.last_modified_time(time);
+            }
+            if let Some(mode) = entry.unix_mode() {
+                options = options.unix_permissions(mode);
+            }
+            writer.start_file(name, options)?;
+            writer.write_all(&shrunk)?;
+            report.merge(class_report);
+        }
+        writer.finish()?;
+        Ok(report)
+    }
+
+    /// Shrinks `class` one category at a time, measuring the encoded size after each.
+    fn shrink_class(&self, class: &mut ClassFile) -> Result<(Vec<u8>, ShrinkReport)> {
+        let mut bytes = ClassFileWriter::write(class)?;
+        let mut report = ShrinkReport {
+            classes: 1,
+            original_size: bytes.len() as u64,
+            ..ShrinkReport::default()
+        };
+        for &category in &self.strip {
+            let changed = match category {
+                ShrinkCategory::ConstantPool => match compact_constant_pool(class) {
+                    Some(changed) => changed,
+                    None => {
+                        report.uncompacted.push(class.this_class.clone());
+                        false
+                    }
+                },
+                _ => strip_attributes(class, category),
+            };
+            if changed {
+                let shrunk = ClassFileWriter::write(class)?;
+                let saved = (bytes.len() as u64).saturating_sub(shrunk.len() as u64);
+                report.saved.insert(category, saved);
+                bytes = shrunk;
+            }
+        }
+        report.shrunk_size = bytes.len() as u64;
+        Ok((bytes, report))
+    }
+}
+
+/// Removes the attributes of `category` from `class`, its members and their code. Returns
+/// whether there were any.
+fn strip_attributes(class: &mut ClassFile, category: ShrinkCategory) -> bool {
+    let mut removed = false;
+    let mut strip = |attributes: &mut Vec<Attribute>| {
+        let count = attributes.len();
+        attributes.retain(|attribute| ShrinkCategory::of(attribute) != Some(category));
+        removed |= attributes.len() != count;
+        for attribute in attributes {
+            if let Attribute::Code(code) = attribute {
+                let count = code.attributes.len();
+                code.attributes.retain(|attribute| ShrinkCategory::of(attribute) != Some(category));
+                removed |= code.attributes.len() != count;
+            }
+        }
+    };
+    strip(&mut class.attributes);
+    for field in &mut class.fields {
+        strip(&mut field.attributes);
+    }
+    for method in &mut class.methods {
+        strip(&mut method.attributes);
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute::UserDefinedAttribute;
+    use crate::constant_pool::{Constant, ConstantPool};
+
+    fn class() -> ClassFile {
+        let mut constant_pool = ConstantPool::default();
+        for constant in [
+            Constant::Utf8("unused".to_string()),
+            Constant::Utf8("C".to_string()),
+            Constant::ClassIndex(2),
+            Constant::Utf8("java/lang/Object".to_string()),
+            Constant::ClassIndex(4),
+            Constant::Long(5),
+            Constant::Utf8("Signature".to_string()),
+            Constant::Utf8("Ljava/lang/Object;".to_string()),
+            Constant::Utf8("Custom".to_string()),
+        ] {
+            constant_pool.add(constant);
+        }
+        ClassFile {
+            constant_pool,
+            this_class: "C".to_string(),
+            super_class: Some("java/lang/Object".to_string()),
+            attributes: vec![
+                Attribute::UserDefined(UserDefinedAttribute::new("Signature".to_string(), &[0, 9])),
+                Attribute::UserDefined(UserDefinedAttribute::new("Custom".to_string(), &[0, 1])),
+            ],
+            ..ClassFile::default()
+        }
+    }
+
+    fn signature(class: &ClassFile) -> Option<&str> {
+        class.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::UserDefined(attribute) if attribute.name() == "Signature" => {
+                let index = u16::from_be_bytes(attribute.info().try_into().ok()?);
+                class.constant_pool.utf8(index as usize)
+            }
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_compaction_rewrites_attribute_bodies() {
+        let mut class = class();
+        let report = Shrinker::new().shrink(&mut class).unwrap();
+        assert_eq!(class.attributes.len(), 1);
+        assert_eq!(signature(&class), Some("Ljava/lang/Object;"));
+        assert_eq!(class.constant_pool.constants.len(), 6);
+        assert!(!class.constant_pool.constants.contains(&Constant::Long(5)));
+        assert!(report.saved(ShrinkCategory::UnknownAttributes) > 0);
+        assert_eq!(report.saved(ShrinkCategory::ConstantPool), 9 + 9 + 9);
+        assert_eq!(report.total_saved(), report.saved.values().sum::<u64>());
+
+        let bytes = ClassFileWriter::write(&class).unwrap();
+        assert_eq!(bytes.len() as u64, report.shrunk_size);
+        assert_eq!(signature(&ClassFileReader::read_class(&bytes).unwrap()), signature(&class));
+    }
+
+    #[test]
+    fn test_unknown_attributes_keep_constant_pool() {
+        let mut class = class();
+        let report = Shrinker::new()
+            .strip(ShrinkCategory::UnknownAttributes, false)
+            .shrink(&mut class)
+            .unwrap();
+        assert_eq!(class.attributes.len(), 2);
+        assert_eq!(class.constant_pool.constants.len(), 10);
+        assert_eq!(report.uncompacted, ["C"]);
+        assert_eq!(report.total_saved(), 0);
+        assert!(report.to_string().contains("constant pool of C kept"));
+    }
+}
@@ -0,0 +1,1883 @@
+//! Static single assignment form of method code.
+//!
+//! [`SsaCode::lift`] turns the `Code` of a method into blocks of [`Statement`]s over typed
+//! [`Value`]s that are each assigned once. Local variables and operand stack slots are
+//! replaced by values: loads, stores and stack manipulation disappear, and where control flow
+//! merges different values of the same variable a [`Phi`] selects between them. Phis are
+//! placed at the iterated dominance frontiers of the assignments, then removed when unused or
+//! when they select a single value.
+//!
+//! Exception edges are explicit. Inside the range of an exception handler, every instruction
+//! that may throw is alone in its block, and the block lists the [`Handler`]s it continues at,
+//! so the phis of a handler receive the values of the variables where the exception is
+//! thrown. Methods with subroutines (`jsr` and `ret`) are not supported.
+//!
+//! [`SsaCode::lower`] turns the blocks back into instructions, with the phis replaced by copies
+//! on the edges leading to them. Values live at the same time get different local variables,
+//! and a value only used right after its definition stays on the operand stack.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use thiserror::Error;
+
+use crate::class_file::ClassFile;
+use crate::constant_pool::ConstantPool;
+use crate::control_flow::{BlockId, ControlFlowError, ControlFlowGraph};
+use crate::dataflow::{
+    self, Context, DataflowError, Frame, Frames, Slot, ValueInterpreter, ValueType,
+};
+use crate::dominators::DominatorTree;
+use crate::instruction::{Effect, Instruction, WideInstruction};
+use crate::method::Method;
+use crate::optimizer::negate;
+use crate::predefined_attributes::{Code, ExceptionHandler};
+
+type Result<T> = std::result::Result<T, SsaError>;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum SsaError {
+    #[error("Method {0} has no code")]
+    #[non_exhaustive]
+    MissingCode(String),
+    #[error("{0}")]
+    #[non_exhaustive]
+    Dataflow(#[from] DataflowError),
+    #[error("{0}")]
+    #[non_exhaustive]
+    ControlFlow(#[from] ControlFlowError),
+    #[error("Subroutine instruction at pc {0} is not supported")]
+    #[non_exhaustive]
+    Subroutine(u32),
+    #[error("Exception handler at pc {0} is also reached without an exception")]
+    #[non_exhaustive]
+    HandlerReachedNormally(u32),
+    #[error("Variable without a value is read at pc {0}")]
+    #[non_exhaustive]
+    UndefinedVariable(u32),
+    #[error("Lowered code needs more than 65535 local variables")]
+    #[non_exhaustive]
+    TooManyLocals,
+    #[error("Lowered code is larger than 65535 bytes")]
+    #[non_exhaustive]
+    CodeTooLarge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Value(pub u32);
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// `this` or a parameter on method entry, with its local variable slot.
+    Parameter(u16),
+    /// The exception caught by a handler, the first statement of its block.
+    CaughtException,
+    /// An instruction applied to the statement's operands instead of the operand stack.
+    /// Branch offsets are meaningless: control flow is in the [`Terminator`]s.
+    Instruction(Instruction),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub result: Option<Value>,
+    pub operation: Operation,
+    /// The values the instruction pops, from the bottom to the top of the stack.
+    pub operands: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    pub result: Value,
+    /// The value from each predecessor of the block.
+    pub arguments: Vec<(BlockId, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    GreaterOrEqual,
+    Greater,
+    LessOrEqual,
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let operator = match self {
+            Condition::Equal => "==",
+            Condition::NotEqual => "!=",
+            Condition::Less => "<",
+            Condition::GreaterOrEqual => ">=",
+            Condition::Greater => ">",
+            Condition::LessOrEqual => "<=",
+        };
+        f.write_str(operator)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+    Goto(BlockId),
+    /// Compares two ints or references, or one with 0 or `null`, and continues at `target`
+    /// if the condition holds.
+    If {
+        condition: Condition,
+        operands: Vec<Value>,
+        target: BlockId,
+        fallthrough: BlockId,
+    },
+    Switch {
+        operand: Value,
+        cases: Vec<(i32, BlockId)>,
+        default: BlockId,
+    },
+    Return(Option<Value>),
+    Throw(Value),
+}
+
+impl Terminator {
+    /// The blocks control continues at, without exception handlers.
+    pub fn successors(&self) -> Vec<BlockId> {
+        let mut successors = match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::If { target, fallthrough, .. } => vec![*target, *fallthrough],
+            Terminator::Switch { cases, default, .. } => {
+                cases.iter().map(|(_, target)| *target).chain([*default]).collect()
+            }
+            Terminator::Return(_) | Terminator::Throw(_) => Vec::new(),
+        };
+        let mut seen = BTreeSet::new();
+        successors.retain(|&block| seen.insert(block));
+        successors
+    }
+
+    pub fn operands(&self) -> Vec<Value> {
+        match self {
+            Terminator::If { operands, .. } => operands.clone(),
+            Terminator::Switch { operand, .. } | Terminator::Throw(operand) => vec![*operand],
+            Terminator::Return(operand) => operand.iter().copied().collect(),
+            Terminator::Goto(_) => Vec::new(),
+        }
+    }
+
+    fn operands_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Terminator::If { operands, .. } => operands.iter_mut().collect(),
+            Terminator::Switch { operand, .. } | Terminator::Throw(operand) => vec![operand],
+            Terminator::Return(operand) => operand.iter_mut().collect(),
+            Terminator::Goto(_) => Vec::new(),
+        }
+    }
+}
+
+/// An exception handler that a block's statement may continue at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handler {
+    /// The handler's `catch_type` constant pool index, 0 for handlers that catch everything.
+    pub catch_type: u16,
+    pub block: BlockId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub id: BlockId,
+    /// The pc of the instruction the block starts at, `None` for the entry block, which only
+    /// defines the parameters.
+    pub pc: Option<u32>,
+    pub phis: Vec<Phi>,
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+    /// The handlers of the statement that may throw, in the order they are tried. The phis
+    /// of a handler receive the values of the block's variables before that statement.
+    pub handlers: Vec<Handler>,
+}
+
+impl Block {
+    /// The successors of the block followed by its handlers.
+    pub fn successors(&self) -> Vec<BlockId> {
+        let mut successors = self.terminator.successors();
+        for handler in &self.handlers {
+            if !successors.contains(&handler.block) {
+                successors.push(handler.block);
+            }
+        }
+        successors
+    }
+}
+
+/// The SSA form of a method's code. Block 0 is the entry block, and every block is reachable
+/// from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsaCode {
+    pub blocks: Vec<Block>,
+    /// The type of each value.
+    pub value_types: Vec<ValueType>,
+}
+
+impl SsaCode {
+    pub fn lift(class: &ClassFile, method: &Method) -> Result<Self> {
+        let code = method.code().ok_or_else(|| SsaError::MissingCode(method.name.clone()))?;
+        if let Some((_, pc)) = code.code.iter().find(|(instruction, _)| {
+            matches!(
+                instruction,
+                Instruction::Jsr(_)
+                    | Instruction::Jsr_w(_)
+                    | Instruction::Ret(_)
+                    | Instruction::Wide(WideInstruction::Ret(_))
+            )
+        }) {
+            return Err(SsaError::Subroutine(*pc));
+        }
+        let graph = ControlFlowGraph::new(code)?;
+        let frames = dataflow::analyze_frames(class, method, &mut Types)?;
+        Lifter::new(class, method, code, &graph, &frames)?.lift()
+    }
+
+    pub fn value_type(&self, value: Value) -> ValueType {
+        self.value_types[value.0 as usize]
+    }
+
+    /// The distinct predecessors of each block, in block order.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for block in &self.blocks {
+            for successor in block.successors() {
+                predecessors[successor].push(block.id);
+            }
+        }
+        predecessors
+    }
+
+    /// Instructions computing the same results as the blocks. `this` and the parameters keep
+    /// their local variables, values used once by the next statement stay on the operand
+    /// stack, and the other values share local variables when they are not live at the same
+    /// time. Phis become copies on the edges to their blocks, through trampolines at the end
+    /// of the code for the edges of conditional branches and switches, and branches too far
+    /// for a 16-bit offset go through a `goto_w`. The instructions refer to the constant pool
+    /// of the class the code was lifted from, and the result has no attributes: in particular
+    /// a `StackMapTable` must be computed separately.
+    pub fn lower(&self) -> Result<Code> {
+        Lowerer::new(self)?.lower()
+    }
+}
+
+impl Display for SsaCode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let typed = |value: Value| format!("{}: {}", value, self.value_type(value));
+        let list =
+            |values: &[Value]| values.iter().map(Value::to_string).collect::<Vec<_>>().join(", ");
+        for block in &self.blocks {
+            write!(f, "b{}", block.id)?;
+            let mut notes = Vec::new();
+            if let Some(pc) = block.pc {
+                notes.push(format!("pc {}", pc));
+            }
+            if !block.handlers.is_empty() {
+                let handlers = block.handlers.iter().map(|handler| match handler.catch_type {
+                    0 => format!("b{} any", handler.block),
+                    catch_type => format!("b{} #{}", handler.block, catch_type),
+                });
+                notes.push(format!("handlers {}", handlers.collect::<Vec<_>>().join(", ")));
+            }
+            if !notes.is_empty() {
+                write!(f, " ({})", notes.join(", "))?;
+            }
+            writeln!(f, ":")?;
+            for phi in &block.phis {
+                let arguments = phi
+                    .arguments
+                    .iter()
+                    .map(|(block, value)| format!("b{}: {}", block, value))
+                    .collect::<Vec<_>>();
+                writeln!(f, "    {} = phi [{}]", typed(phi.result), arguments.join(", "))?;
+            }
+            for statement in &block.statements {
+                write!(f, "    ")?;
+                if let Some(result) = statement.result {
+                    write!(f, "{} = ", typed(result))?;
+                }
+                match &statement.operation {
+                    Operation::Parameter(local) => write!(f, "parameter {}", local)?,
+                    Operation::CaughtException => write!(f, "catch")?,
+                    Operation::Instruction(instruction) => write!(f, "{:?}", instruction)?,
+                }
+                if !statement.operands.is_empty() {
+                    write!(f, " {}", list(&statement.operands))?;
+                }
+                writeln!(f)?;
+            }
+            match &block.terminator {
+                Terminator::Goto(target) => writeln!(f, "    goto b{}", target)?,
+                Terminator::If { condition, operands, target, fallthrough } => {
+                    let right = match operands.get(1) {
+                        Some(right) => right.to_string(),
+                        None if self.value_type(operands[0]) == ValueType::Reference => {
+                            "null".to_string()
+                        }
+                        None => "0".to_string(),
+                    };
+                    writeln!(
+                        f,
+                        "    if {} {} {} goto b{} else b{}",
+                        operands[0], condition, right, target, fallthrough
+                    )?
+                }
+                Terminator::Switch { operand, cases, default } => {
+                    let cases = cases
+                        .iter()
+                        .map(|(key, target)| format!("{}: b{}", key, target))
+                        .collect::<Vec<_>>();
+                    writeln!(
+                        f,
+                        "    switch {} [{}] default b{}",
+                        operand,
+                        cases.join(", "),
+                        default
+                    )?
+                }
+                Terminator::Return(None) => writeln!(f, "    return")?,
+                Terminator::Return(Some(value)) => writeln!(f, "    return {}", value)?,
+                Terminator::Throw(value) => writeln!(f, "    throw {}", value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Only the types of the frames are needed.
+struct Types;
+
+impl ValueInterpreter for Types {
+    type Value = ();
+
+    fn unknown(&mut self) {}
+
+    fn operation(&mut self, _context: &Context, _operands: &[&()], _result: ValueType) {}
+
+    fn join(&mut self, _a: &(), _b: &()) {}
+}
+
+/// A local variable or operand stack slot before SSA renaming: locals are numbered first,
+/// then stack entries from the bottom, then a scratch variable.
+type Variable = usize;
+
+/// A step of a block before SSA renaming, with the pc it comes from. Values in statements
+/// and terminators are variable numbers until renamed.
+enum Step {
+    Statement {
+        result: Option<(Variable, ValueType)>,
+        statement: Statement,
+    },
+    /// Assigns the sources to the targets at once.
+    Copy {
+        targets: Vec<Variable>,
+        sources: Vec<Variable>,
+    },
+    /// Makes a variable unusable, like the second half of a long or double local.
+    Kill(Variable),
+}
+
+struct PendingBlock {
+    pc: Option<u32>,
+    /// The frame on entry of the block, `None` for the entry block.
+    frame: Option<Frame<()>>,
+    is_handler: bool,
+    steps: Vec<(Step, u32)>,
+    terminator: Terminator,
+    handlers: Vec<Handler>,
+}
+
+fn variable(value: Value) -> Variable {
+    value.0 as usize
+}
+
+fn placeholder(variable: Variable) -> Value {
+    Value(variable as u32)
+}
+
+/// The instructions of a block to create.
+struct Segment {
+    instructions: Range<usize>,
+    /// Whether the block starts an exception handler.
+    is_handler: bool,
+    /// The pcs and catch types of the handlers of its instruction, if it may throw.
+    handlers: Vec<(u32, u16)>,
+}
+
+struct Lifter<'a> {
+    constant_pool: &'a ConstantPool,
+    method: &'a Method,
+    code: &'a Code,
+    frames: &'a Frames<()>,
+    max_locals: usize,
+    scratch: Variable,
+    segments: Vec<Segment>,
+}
+
+impl<'a> Lifter<'a> {
+    fn new(
+        class: &'a ClassFile,
+        method: &'a Method,
+        code: &'a Code,
+        graph: &ControlFlowGraph,
+        frames: &'a Frames<()>,
+    ) -> Result<Self> {
+        let handler_pcs = code
+            .exception_table
+            .iter()
+            .map(|handler| handler.handler_pc as u32)
+            .collect::<Vec<_>>();
+        let mut segments = Vec::new();
+        for block in &graph.blocks {
+            if frames.before(block.start_pc).is_none() {
+                continue;
+            }
+            let is_handler = handler_pcs.contains(&block.start_pc);
+            if is_handler && graph.edges_to(block.id).any(|edge| !edge.kind.is_exceptional()) {
+                return Err(SsaError::HandlerReachedNormally(block.start_pc));
+            }
+            // Every instruction of a block is covered by the same handlers.
+            let handlers = code
+                .exception_table
+                .iter()
+                .filter(|handler| {
+                    (handler.start_pc as u32..handler.end_pc as u32).contains(&block.start_pc)
+                })
+                .map(|handler| (handler.handler_pc as u32, handler.catch_type))
+                .collect::<Vec<_>>();
+            let range = block.instructions.clone();
+            let mut segment = |instructions: Range<usize>, handlers: Vec<(u32, u16)>| {
+                let is_handler = is_handler && instructions.start == range.start;
+                segments.push(Segment { instructions, is_handler, handlers });
+            };
+            if handlers.is_empty() {
+                segment(range.clone(), Vec::new());
+                continue;
+            }
+            let mut start = range.start;
+            for index in range.clone() {
+                if code.code[index].0.may_throw() {
+                    if index > start {
+                        segment(start..index, Vec::new());
+                    }
+                    segment(index..index + 1, handlers.clone());
+                    start = index + 1;
+                }
+            }
+            if start < range.end {
+                segment(start..range.end, Vec::new());
+            }
+        }
+        let max_locals = code.max_locals as usize;
+        Ok(Lifter {
+            constant_pool: &class.constant_pool,
+            method,
+            code,
+            frames,
+            max_locals,
+            scratch: max_locals + code.max_stack as usize,
+            segments,
+        })
+    }
+
+    /// The block starting at the instruction at `pc`.
+    fn block_at(&self, pc: u32) -> BlockId {
+        let index = self.code.code.partition_point(|(_, instruction_pc)| *instruction_pc < pc);
+        1 + self.segments.partition_point(|segment| segment.instructions.start < index)
+    }
+
+    fn lift(self) -> Result<SsaCode> {
+        let mut blocks = vec![self.entry_block()];
+        for segment in &self.segments {
+            blocks.push(self.translate(segment)?);
+        }
+        let blocks = retain_reachable(blocks);
+        Renamer::new(self.scratch + 1, self.max_locals, &blocks).rename(blocks)
+    }
+
+    fn entry_block(&self) -> PendingBlock {
+        let mut steps = Vec::new();
+        let mut local = 0;
+        if !self.method.flags.is_static() {
+            steps.push(parameter(0, ValueType::Reference));
+            local = 1;
+        }
+        for parameter_type in self.method.type_descriptor.parameters() {
+            let value_type = ValueType::of(parameter_type);
+            steps.push(parameter(local, value_type));
+            local += value_type.size() as u16;
+        }
+        PendingBlock {
+            pc: None,
+            frame: None,
+            is_handler: false,
+            steps,
+            terminator: Terminator::Goto(1),
+            handlers: Vec::new(),
+        }
+    }
+
+    fn translate(&self, segment: &Segment) -> Result<PendingBlock> {
+        let range = segment.instructions.clone();
+        let start_pc = self.code.code[range.start].1;
+        let frame = self.frames.before(start_pc).cloned();
+        let mut steps = Vec::new();
+        if segment.is_handler {
+            let statement = Statement {
+                result: None,
+                operation: Operation::CaughtException,
+                operands: Vec::new(),
+            };
+            let result = Some((self.max_locals, ValueType::Reference));
+            steps.push((Step::Statement { result, statement }, start_pc));
+        }
+        let mut terminator = None;
+        for (instruction, pc) in &self.code.code[range.clone()] {
+            let frame = self.frames.before(*pc).ok_or(SsaError::UndefinedVariable(*pc))?;
+            terminator = self.translate_instruction(instruction, *pc, frame, &mut steps)?;
+        }
+        let terminator = match terminator {
+            Some(terminator) => terminator,
+            None if range.end < self.code.code.len() => {
+                Terminator::Goto(self.block_at(self.code.code[range.end].1))
+            }
+            None => {
+                return Err(ControlFlowError::FallsOffEnd(self.code.code[range.end - 1].1).into());
+            }
+        };
+        let handlers = segment
+            .handlers
+            .iter()
+            .map(|&(handler_pc, catch_type)| Handler {
+                catch_type,
+                block: self.block_at(handler_pc),
+            })
+            .collect();
+        let is_handler = segment.is_handler;
+        Ok(PendingBlock { pc: Some(start_pc), frame, is_handler, steps, terminator, handlers })
+    }
+
+    /// Appends the steps of an instruction, or returns the terminator it ends its block with.
+    fn translate_instruction(
+        &self,
+        instruction: &Instruction,
+        pc: u32,
+        frame: &Frame<()>,
+        steps: &mut Vec<(Step, u32)>,
+    ) -> Result<Option<Terminator>> {
+        let height = frame.stack.len();
+        let stack = |depth: usize| self.max_locals + depth;
+        let top = |count: usize| (height - count..height).map(|depth| placeholder(stack(depth)));
+        let target = |offset: i32| self.block_at((pc as i64 + offset as i64) as u32);
+        let branch = |condition, operands: usize, offset: u16| {
+            Some(Terminator::If {
+                condition,
+                operands: top(operands).collect(),
+                target: target(offset as i16 as i32),
+                fallthrough: 0,
+            })
+        };
+        let terminator = match instruction {
+            Instruction::Ifeq(offset) | Instruction::Ifnull(offset) => {
+                branch(Condition::Equal, 1, *offset)
+            }
+            Instruction::Ifne(offset) | Instruction::Ifnonnull(offset) => {
+                branch(Condition::NotEqual, 1, *offset)
+            }
+            Instruction::Iflt(offset) => branch(Condition::Less, 1, *offset),
+            Instruction::Ifge(offset) => branch(Condition::GreaterOrEqual, 1, *offset),
+            Instruction::Ifgt(offset) => branch(Condition::Greater, 1, *offset),
+            Instruction::Ifle(offset) => branch(Condition::LessOrEqual, 1, *offset),
+            Instruction::If_icmpeq(offset) | Instruction::If_acmpeq(offset) => {
+                branch(Condition::Equal, 2, *offset)
+            }
+            Instruction::If_icmpne(offset) | Instruction::If_acmpne(offset) => {
+                branch(Condition::NotEqual, 2, *offset)
+            }
+            Instruction::If_icmplt(offset) => branch(Condition::Less, 2, *offset),
+            Instruction::If_icmpge(offset) => branch(Condition::GreaterOrEqual, 2, *offset),
+            Instruction::If_icmpgt(offset) => branch(Condition::Greater, 2, *offset),
+            Instruction::If_icmple(offset) => branch(Condition::LessOrEqual, 2, *offset),
+            Instruction::Goto(offset) => Some(Terminator::Goto(target(*offset as i16 as i32))),
+            Instruction::Goto_w(offset) => Some(Terminator::Goto(target(*offset))),
+            Instruction::Tableswitch { default, low, high, offsets } => Some(Terminator::Switch {
+                operand: placeholder(stack(height - 1)),
+                cases: (*low..=*high)
+                    .zip(offsets)
+                    .map(|(key, offset)| (key, target(*offset)))
+                    .collect(),
+                default: target(*default),
+            }),
+            Instruction::Lookupswitch { default, pairs } => Some(Terminator::Switch {
+                operand: placeholder(stack(height - 1)),
+                cases: pairs.iter().map(|(key, offset)| (*key, target(*offset))).collect(),
+                default: target(*default),
+            }),
+            Instruction::Return => Some(Terminator::Return(None)),
+            Instruction::Ireturn
+            | Instruction::Lreturn
+            | Instruction::Freturn
+            | Instruction::Dreturn
+            | Instruction::Areturn => Some(Terminator::Return(top(1).next())),
+            Instruction::Athrow => Some(Terminator::Throw(placeholder(stack(height - 1)))),
+            _ => None,
+        };
+        if let Some(mut terminator) = terminator {
+            if let Terminator::If { fallthrough, .. } = &mut terminator {
+                let index = self.code.code.partition_point(|(_, next)| *next <= pc);
+                let next_pc = self.code.code.get(index).ok_or(ControlFlowError::FallsOffEnd(pc))?.1;
+                *fallthrough = self.block_at(next_pc);
+            }
+            return Ok(Some(terminator));
+        }
+
+        let effect =
+            instruction.effect(self.constant_pool).ok_or(DataflowError::InvalidConstant(pc))?;
+        let step = match effect {
+            Effect::Load(local, _) => {
+                Step::Copy { targets: vec![stack(height)], sources: vec![local as usize] }
+            }
+            Effect::Store(local, value_type) => {
+                let local = local as usize;
+                if value_type.size() == 2 {
+                    steps.push((Step::Kill(local + 1), pc));
+                }
+                Step::Copy { targets: vec![local], sources: vec![stack(height - 1)] }
+            }
+            Effect::Increment(local) => {
+                let (constant, local) = match instruction {
+                    Instruction::Iinc(_, increment) => {
+                        (Instruction::Bipush(*increment as u8), local)
+                    }
+                    Instruction::Wide(WideInstruction::Iinc(_, increment)) => {
+                        (Instruction::Sipush(*increment), local)
+                    }
+                    _ => unreachable!("only iinc increments"),
+                };
+                let statement = Statement {
+                    result: None,
+                    operation: Operation::Instruction(constant),
+                    operands: Vec::new(),
+                };
+                let result = Some((self.scratch, ValueType::Int));
+                steps.push((Step::Statement { result, statement }, pc));
+                let local = local as usize;
+                let statement = Statement {
+                    result: None,
+                    operation: Operation::Instruction(Instruction::Iadd),
+                    operands: vec![placeholder(local), placeholder(self.scratch)],
+                };
+                Step::Statement { result: Some((local, ValueType::Int)), statement }
+            }
+            Effect::Pop(_) => return Ok(None),
+            Effect::Dup { top: top_words, under: under_words } => {
+                let tops = entries(&frame.stack, top_words);
+                let unders = entries(&frame.stack[..height - tops], under_words);
+                let base = height - tops - unders;
+                let top_entries = (base + unders..height).map(stack);
+                let sources = top_entries.clone().chain((base..base + unders).map(stack));
+                Step::Copy {
+                    targets: (base..height + tops).map(stack).collect(),
+                    sources: sources.chain(top_entries).collect(),
+                }
+            }
+            Effect::Swap => Step::Copy {
+                targets: vec![stack(height - 2), stack(height - 1)],
+                sources: vec![stack(height - 1), stack(height - 2)],
+            },
+            Effect::Operation { operands: words, result } => {
+                let operands = entries(&frame.stack, words);
+                let statement = Statement {
+                    result: None,
+                    operation: Operation::Instruction(instruction.clone()),
+                    operands: top(operands).collect(),
+                };
+                let result = result.map(|value_type| (stack(height - operands), value_type));
+                Step::Statement { result, statement }
+            }
+        };
+        steps.push((step, pc));
+        Ok(None)
+    }
+}
+
+/// The number of entries at the top of `stack` that take its top `words` slots.
+fn entries(stack: &[Slot<()>], words: usize) -> usize {
+    let (mut count, mut taken) = (0, 0);
+    while taken < words {
+        count += 1;
+        taken += stack[stack.len() - count].value_type.size();
+    }
+    count
+}
+
+fn parameter(local: u16, value_type: ValueType) -> (Step, u32) {
+    let statement =
+        Statement { result: None, operation: Operation::Parameter(local), operands: Vec::new() };
+    (Step::Statement { result: Some((local as usize, value_type)), statement }, 0)
+}
+
+/// Removes the blocks that cannot be reached from the entry, like handlers whose range has
+/// no instruction that may throw, and renumbers the others.
+fn retain_reachable(blocks: Vec<PendingBlock>) -> Vec<PendingBlock> {
+    let successors = |block: &PendingBlock| {
+        let handlers = block.handlers.iter().map(|handler| handler.block);
+        block.terminator.successors().into_iter().chain(handlers).collect::<Vec<_>>()
+    };
+    let mut reachable = vec![false; blocks.len()];
+    let mut work = vec![0];
+    reachable[0] = true;
+    while let Some(block) = work.pop() {
+        for successor in successors(&blocks[block]) {
+            if !reachable[successor] {
+                reachable[successor] = true;
+                work.push(successor);
+            }
+        }
+    }
+    let mut ids = vec![0; blocks.len()];
+    let mut next = 0;
+    for (block, id) in ids.iter_mut().enumerate() {
+        if reachable[block] {
+            *id = next;
+            next += 1;
+        }
+    }
+    blocks
+        .into_iter()
+        .zip(reachable)
+        .filter(|(_, reachable)| *reachable)
+        .map(|(mut block, _)| {
+            match &mut block.terminator {
+                Terminator::Goto(target) => *target = ids[*target],
+                Terminator::If { target, fallthrough, .. } => {
+                    *target = ids[*target];
+                    *fallthrough = ids[*fallthrough];
+                }
+                Terminator::Switch { cases, default, .. } => {
+                    cases.iter_mut().for_each(|(_, target)| *target = ids[*target]);
+                    *default = ids[*default];
+                }
+                Terminator::Return(_) | Terminator::Throw(_) => {}
+            }
+            block.handlers.iter_mut().for_each(|handler| handler.block = ids[handler.block]);
+            block
+        })
+        .collect()
+}
+
+/// Places phis and renames variables to values.
+struct Renamer {
+    variables: usize,
+    successors: Vec<Vec<BlockId>>,
+    dominators: DominatorTree,
+    /// The variables that get a phi in each block, with their types.
+    phis: Vec<Vec<(Variable, ValueType)>>,
+    value_types: Vec<ValueType>,
+}
+
+impl Renamer {
+    fn new(variables: usize, max_locals: usize, blocks: &[PendingBlock]) -> Self {
+        let successors = blocks
+            .iter()
+            .map(|block| {
+                let mut successors = block.terminator.successors();
+                for handler in &block.handlers {
+                    if !successors.contains(&handler.block) {
+                        successors.push(handler.block);
+                    }
+                }
+                successors
+            })
+            .collect::<Vec<_>>();
+        let mut predecessors = vec![Vec::new(); blocks.len()];
+        for (block, successors) in successors.iter().enumerate() {
+            for &successor in successors {
+                predecessors[successor].push(block);
+            }
+        }
+        let dominators = DominatorTree::from_successors(successors.clone(), 0);
+        let frontiers = dominators.frontiers(|block| &predecessors[block]);
+
+        let mut definitions = vec![BTreeSet::new(); variables];
+        for (id, block) in blocks.iter().enumerate() {
+            for (step, _) in &block.steps {
+                match step {
+                    Step::Statement { result: Some((variable, _)), .. } | Step::Kill(variable) => {
+                        definitions[*variable].insert(id);
+                    }
+                    Step::Copy { targets, .. } => {
+                        targets.iter().for_each(|&variable| {
+                            definitions[variable].insert(id);
+                        });
+                    }
+                    Step::Statement { result: None, .. } => {}
+                }
+            }
+        }
+
+        // A variable only needs a phi where its slot is usable according to the frames.
+        let phi_type = |block: &PendingBlock, variable: Variable| {
+            let frame = block.frame.as_ref()?;
+            let slot = match variable < max_locals {
+                true => frame.locals.get(variable)?,
+                false if block.is_handler => return None,
+                false => frame.stack.get(variable - max_locals)?,
+            };
+            (slot.value_type != ValueType::Top).then_some(slot.value_type)
+        };
+        let mut phis = vec![Vec::new(); blocks.len()];
+        for (variable, definitions) in definitions.into_iter().enumerate() {
+            let mut placed = BTreeSet::new();
+            let mut work = definitions.iter().copied().collect::<Vec<_>>();
+            let mut queued = definitions;
+            while let Some(block) = work.pop() {
+                for &frontier in &frontiers[block] {
+                    if !placed.insert(frontier) {
+                        continue;
+                    }
+                    if let Some(value_type) = phi_type(&blocks[frontier], variable) {
+                        phis[frontier].push((variable, value_type));
+                    }
+                    if queued.insert(frontier) {
+                        work.push(frontier);
+                    }
+                }
+            }
+        }
+        Renamer { variables, successors, dominators, phis, value_types: Vec::new() }
+    }
+
+    fn new_value(&mut self, value_type: ValueType) -> Value {
+        self.value_types.push(value_type);
+        Value(self.value_types.len() as u32 - 1)
+    }
+
+    fn rename(mut self, pending: Vec<PendingBlock>) -> Result<SsaCode> {
+        let count = pending.len();
+        let mut phis = vec![Vec::new(); count];
+        let mut arguments = vec![Vec::new(); count];
+        for (block, block_phis) in self.phis.iter().enumerate() {
+            arguments[block] = vec![Vec::new(); block_phis.len()];
+        }
+        let mut blocks = pending.into_iter().map(Some).collect::<Vec<_>>();
+        let mut renamed = (0..count).map(|_| None).collect::<Vec<Option<Block>>>();
+
+        let mut work = vec![(0, vec![None; self.variables])];
+        while let Some((id, mut state)) = work.pop() {
+            let block = blocks[id].take().expect("blocks are renamed once");
+            for (variable, value_type) in self.phis[id].clone() {
+                let value = self.new_value(value_type);
+                state[variable] = Some(value);
+                phis[id].push(value);
+            }
+            let read = |state: &[Option<Value>], value: &mut Value, pc: u32| {
+                *value = state[variable(*value)].ok_or(SsaError::UndefinedVariable(pc))?;
+                Ok::<_, SsaError>(())
+            };
+            let mut statements = Vec::new();
+            for (step, pc) in block.steps {
+                match step {
+                    Step::Statement { result, mut statement } => {
+                        for operand in &mut statement.operands {
+                            read(&state, operand, pc)?;
+                        }
+                        if let Some((variable, value_type)) = result {
+                            let value = self.new_value(value_type);
+                            statement.result = Some(value);
+                            state[variable] = Some(value);
+                        }
+                        statements.push(statement);
+                    }
+                    Step::Copy { targets, sources } => {
+                        let values =
+                            sources.iter().map(|&source| state[source]).collect::<Vec<_>>();
+                        for (target, value) in targets.into_iter().zip(values) {
+                            state[target] = value;
+                        }
+                    }
+                    Step::Kill(variable) => state[variable] = None,
+                }
+            }
+            let mut terminator = block.terminator;
+            let pc = block.pc.unwrap_or(0);
+            for operand in terminator.operands_mut() {
+                read(&state, operand, pc)?;
+            }
+            for &successor in &self.successors[id] {
+                for (index, (variable, _)) in self.phis[successor].iter().enumerate() {
+                    let value = state[*variable].ok_or(SsaError::UndefinedVariable(pc))?;
+                    arguments[successor][index].push((id, value));
+                }
+            }
+            for &child in self.dominators.children(id).iter().rev() {
+                work.push((child, state.clone()));
+            }
+            renamed[id] = Some(Block {
+                id,
+                pc: block.pc,
+                phis: Vec::new(),
+                statements,
+                terminator,
+                handlers: block.handlers,
+            });
+        }
+
+        let mut blocks = renamed
+            .into_iter()
+            .map(|block| block.expect("blocks are reachable"))
+            .collect::<Vec<_>>();
+        for (block, (results, arguments)) in blocks.iter_mut().zip(phis.into_iter().zip(arguments))
+        {
+            block.phis = results
+                .into_iter()
+                .zip(arguments)
+                .map(|(result, mut arguments)| {
+                    arguments.sort();
+                    Phi { result, arguments }
+                })
+                .collect();
+        }
+        let mut code = SsaCode { blocks, value_types: self.value_types };
+        remove_phis(&mut code);
+        renumber(&mut code);
+        Ok(code)
+    }
+}
+
+/// Removes the phis whose result is never used, then those that select a single value other
+/// than their own result, replacing them with that value.
+fn remove_phis(code: &mut SsaCode) {
+    let mut used = vec![false; code.value_types.len()];
+    let mut work = Vec::new();
+    for block in &code.blocks {
+        let operands = block.statements.iter().flat_map(|statement| &statement.operands);
+        work.extend(operands.copied().chain(block.terminator.operands()));
+    }
+    let mut phi_arguments = vec![None; code.value_types.len()];
+    for phi in code.blocks.iter().flat_map(|block| &block.phis) {
+        phi_arguments[phi.result.0 as usize] = Some(&phi.arguments);
+    }
+    while let Some(value) = work.pop() {
+        if std::mem::replace(&mut used[value.0 as usize], true) {
+            continue;
+        }
+        if let Some(arguments) = phi_arguments[value.0 as usize] {
+            work.extend(arguments.iter().map(|(_, argument)| *argument));
+        }
+    }
+    for block in &mut code.blocks {
+        block.phis.retain(|phi| used[phi.result.0 as usize]);
+    }
+
+    let mut replacements = (0..code.value_types.len() as u32).map(Value).collect::<Vec<_>>();
+    let resolve = |replacements: &[Value], mut value: Value| {
+        while replacements[value.0 as usize] != value {
+            value = replacements[value.0 as usize];
+        }
+        value
+    };
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in &mut code.blocks {
+            block.phis.retain(|phi| {
+                let mut distinct = phi
+                    .arguments
+                    .iter()
+                    .map(|(_, argument)| resolve(&replacements, *argument))
+                    .filter(|&argument| argument != phi.result)
+                    .collect::<BTreeSet<_>>();
+                match (distinct.pop_first(), distinct.is_empty()) {
+                    (Some(single), true) => {
+                        replacements[phi.result.0 as usize] = single;
+                        changed = true;
+                        false
+                    }
+                    _ => true,
+                }
+            });
+        }
+    }
+    for_each_use(code, |value| *value = resolve(&replacements, *value));
+}
+
+fn for_each_use(code: &mut SsaCode, mut f: impl FnMut(&mut Value)) {
+    for block in &mut code.blocks {
+        for phi in &mut block.phis {
+            phi.arguments.iter_mut().for_each(|(_, argument)| f(argument));
+        }
+        for statement in &mut block.statements {
+            statement.operands.iter_mut().for_each(&mut f);
+        }
+        block.terminator.operands_mut().into_iter().for_each(&mut f);
+    }
+}
+
+/// Numbers the remaining values in the order of their definitions.
+fn renumber(code: &mut SsaCode) {
+    let mut numbers = vec![None; code.value_types.len()];
+    let mut value_types = Vec::new();
+    let mut define = |value: &mut Value| {
+        value_types.push(code.value_types[value.0 as usize]);
+        numbers[value.0 as usize] = Some(Value(value_types.len() as u32 - 1));
+        *value = Value(value_types.len() as u32 - 1);
+    };
+    for block in &mut code.blocks {
+        block.phis.iter_mut().for_each(|phi| define(&mut phi.result));
+        block
+            .statements
+            .iter_mut()
+            .filter_map(|statement| statement.result.as_mut())
+            .for_each(&mut define);
+    }
+    code.value_types = value_types;
+    for_each_use(code, |value| *value = numbers[value.0 as usize].expect("uses are defined"));
+}
+
+/// Where a branch of the lowered code goes: the start of a block or a trampoline.
+type Label = usize;
+
+/// Copies from the first value of each pair to the second.
+type Copies = Vec<(Value, Value)>;
+
+struct Lowerer<'a> {
+    ssa: &'a SsaCode,
+    /// The local variable of each value that is stored in one.
+    slots: Vec<Option<u16>>,
+    /// Whether each value stays on the operand stack until its only use, by the statement or
+    /// terminator right after its definition.
+    stacked: Vec<bool>,
+    /// The stacked value the previous statement left on the operand stack.
+    pending: Option<Value>,
+    max_locals: u16,
+    max_stack: usize,
+    instructions: Vec<Instruction>,
+    /// The instruction index of each label, blocks first.
+    labels: Vec<Option<usize>>,
+    /// The branches to patch, with the labels of their targets, the default first for
+    /// switches.
+    fixups: Vec<(usize, Vec<Label>)>,
+    /// Copies that only happen on an edge, with the label of the trampoline and the block
+    /// it continues at.
+    trampolines: Vec<(Label, Copies, BlockId)>,
+    /// The instruction ranges of the blocks with handlers, with their handlers.
+    handlers: Vec<(usize, usize, Vec<Handler>)>,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(ssa: &'a SsaCode) -> Result<Self> {
+        let mut uses = vec![0usize; ssa.value_types.len()];
+        for block in &ssa.blocks {
+            let phi_arguments = block.phis.iter().flat_map(|phi| &phi.arguments);
+            let operands =
+                block.statements.iter().flat_map(|statement| statement.operands.iter().copied());
+            let values = phi_arguments.map(|(_, value)| *value).chain(operands);
+            for value in values.chain(block.terminator.operands()) {
+                uses[value.0 as usize] += 1;
+            }
+        }
+        let stacked = stacked_values(ssa, &uses);
+        let parameters =
+            ssa.blocks[0].statements.iter().filter_map(|statement| match statement.operation {
+                Operation::Parameter(local) => Some((statement.result?, local)),
+                _ => None,
+            });
+        let mut slots = vec![None; ssa.value_types.len()];
+        let mut first = 0u32;
+        for (value, local) in parameters {
+            slots[value.0 as usize] = Some(local);
+            first = first.max(local as u32 + ssa.value_type(value).size() as u32);
+        }
+        // Phi results are the targets of copies even when they are not used.
+        let mut needs_local: Vec<bool> =
+            uses.iter().zip(&stacked).map(|(&uses, &stacked)| uses > 0 && !stacked).collect();
+        for block in &ssa.blocks {
+            block.phis.iter().for_each(|phi| needs_local[phi.result.0 as usize] = true);
+        }
+        for (index, slot) in slots.iter().enumerate() {
+            needs_local[index] &= slot.is_none();
+        }
+        let max_locals = allocate_locals(ssa, &needs_local, first, &mut slots)?;
+        Ok(Lowerer {
+            ssa,
+            slots,
+            stacked,
+            pending: None,
+            max_locals,
+            max_stack: 0,
+            instructions: Vec::new(),
+            labels: vec![None; ssa.blocks.len()],
+            fixups: Vec::new(),
+            trampolines: Vec::new(),
+            handlers: Vec::new(),
+        })
+    }
+
+    fn slot(&self, value: Value) -> u16 {
+        self.slots[value.0 as usize].expect("used values have a slot")
+    }
+
+    fn size(&self, value: Value) -> usize {
+        self.ssa.value_type(value).size()
+    }
+
+    /// Pushes the values, the first of which may already be on the stack.
+    fn load(&mut self, values: &[Value]) {
+        let words = values.iter().map(|&value| self.size(value)).sum::<usize>();
+        self.max_stack = self.max_stack.max(words);
+        let values = match self.pending.take() {
+            Some(pending) => {
+                debug_assert_eq!(values.first(), Some(&pending));
+                &values[1..]
+            }
+            None => values,
+        };
+        for &value in values {
+            let instruction = load(self.ssa.value_type(value), self.slot(value));
+            self.instructions.push(instruction);
+        }
+    }
+
+    /// Stores the value on top of the stack, or pops it if it is never used.
+    fn store(&mut self, value: Value) {
+        self.max_stack = self.max_stack.max(self.size(value));
+        let instruction = match self.slots[value.0 as usize] {
+            Some(slot) => store(self.ssa.value_type(value), slot),
+            None if self.size(value) == 2 => Instruction::Pop2,
+            None => Instruction::Pop,
+        };
+        self.instructions.push(instruction);
+    }
+
+    /// Copies the sources to the targets at once, through the operand stack.
+    fn copy(&mut self, copies: &[(Value, Value)]) {
+        let copies = copies
+            .iter()
+            .copied()
+            .filter(|&(source, target)| self.slot(source) != self.slot(target))
+            .collect::<Vec<_>>();
+        let sources = copies.iter().map(|(source, _)| *source).collect::<Vec<_>>();
+        self.load(&sources);
+        for &(_, target) in copies.iter().rev() {
+            self.store(target);
+        }
+    }
+
+    fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn branch(&mut self, instruction: Instruction, targets: Vec<Label>) {
+        self.fixups.push((self.instructions.len(), targets));
+        self.instructions.push(instruction);
+    }
+
+    /// The label to branch to for the edge from `from` to `to`, a trampoline when the edge
+    /// has copies.
+    fn edge_label(&mut self, from: BlockId, to: BlockId) -> Label {
+        let copies = phi_copies(self.ssa, from, to);
+        if copies.is_empty() {
+            return to;
+        }
+        let label = self.new_label();
+        self.trampolines.push((label, copies, to));
+        label
+    }
+
+    fn lower(mut self) -> Result<Code> {
+        for block in &self.ssa.blocks {
+            self.labels[block.id] = Some(self.instructions.len());
+            let mut try_start = None;
+            if !block.handlers.is_empty() {
+                for handler in &block.handlers {
+                    let copies = phi_copies(self.ssa, block.id, handler.block);
+                    self.copy(&copies);
+                }
+                try_start = Some(self.instructions.len());
+            }
+            for statement in &block.statements {
+                match &statement.operation {
+                    Operation::Parameter(_) => continue,
+                    Operation::CaughtException => self.max_stack = self.max_stack.max(1),
+                    Operation::Instruction(instruction) => {
+                        self.load(&statement.operands);
+                        self.instructions.push(instruction.clone());
+                    }
+                }
+                match statement.result {
+                    Some(result) if self.stacked[result.0 as usize] => {
+                        self.max_stack = self.max_stack.max(self.size(result));
+                        self.pending = Some(result);
+                    }
+                    Some(result) => self.store(result),
+                    None => {}
+                }
+            }
+            self.lower_terminator(block);
+            if let Some(start) = try_start {
+                self.handlers.push((start, self.instructions.len(), block.handlers.clone()));
+            }
+        }
+        for (label, copies, target) in std::mem::take(&mut self.trampolines) {
+            self.labels[label] = Some(self.instructions.len());
+            self.copy(&copies);
+            self.branch(Instruction::Goto(0), vec![target]);
+        }
+        self.assemble()
+    }
+
+    fn lower_terminator(&mut self, block: &Block) {
+        let next = block.id + 1;
+        match &block.terminator {
+            Terminator::Goto(target) => {
+                let copies = phi_copies(self.ssa, block.id, *target);
+                self.copy(&copies);
+                if *target != next {
+                    self.branch(Instruction::Goto(0), vec![*target]);
+                }
+            }
+            Terminator::If { condition, operands, target, fallthrough } => {
+                self.load(operands);
+                let label = self.edge_label(block.id, *target);
+                let instruction =
+                    branch(*condition, operands.len(), self.ssa.value_type(operands[0]));
+                self.branch(instruction, vec![label]);
+                let copies = phi_copies(self.ssa, block.id, *fallthrough);
+                self.copy(&copies);
+                if *fallthrough != next {
+                    self.branch(Instruction::Goto(0), vec![*fallthrough]);
+                }
+            }
+            Terminator::Switch { operand, cases, default } => {
+                self.load(&[*operand]);
+                let mut targets = vec![self.edge_label(block.id, *default)];
+                let mut labels = vec![(*default, targets[0])];
+                for (_, target) in cases {
+                    let label = match labels.iter().find(|(block, _)| block == target) {
+                        Some((_, label)) => *label,
+                        None => self.edge_label(block.id, *target),
+                    };
+                    labels.push((*target, label));
+                    targets.push(label);
+                }
+                let mut keys = cases.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+                let contiguous = keys.windows(2).all(|pair| pair[1] == pair[0].wrapping_add(1));
+                let instruction = match (keys.first(), keys.last()) {
+                    (Some(&low), Some(&high)) if contiguous => Instruction::Tableswitch {
+                        default: 0,
+                        low,
+                        high,
+                        offsets: vec![0; keys.len()],
+                    },
+                    _ => {
+                        // Lookup switches need their keys sorted.
+                        let mut order = (0..keys.len()).collect::<Vec<_>>();
+                        order.sort_by_key(|&index| keys[index]);
+                        let default = targets[0];
+                        targets = std::iter::once(default)
+                            .chain(order.iter().map(|&index| targets[index + 1]))
+                            .collect();
+                        keys.sort();
+                        Instruction::Lookupswitch {
+                            default: 0,
+                            pairs: keys.into_iter().map(|key| (key, 0)).collect(),
+                        }
+                    }
+                };
+                self.branch(instruction, targets);
+            }
+            Terminator::Return(value) => {
+                let instruction = match value {
+                    None => Instruction::Return,
+                    Some(value) => {
+                        self.load(&[*value]);
+                        match self.ssa.value_type(*value) {
+                            ValueType::Int => Instruction::Ireturn,
+                            ValueType::Long => Instruction::Lreturn,
+                            ValueType::Float => Instruction::Freturn,
+                            ValueType::Double => Instruction::Dreturn,
+                            _ => Instruction::Areturn,
+                        }
+                    }
+                };
+                self.instructions.push(instruction);
+            }
+            Terminator::Throw(value) => {
+                self.load(&[*value]);
+                self.instructions.push(Instruction::Athrow);
+            }
+        }
+    }
+
+    /// Computes the pcs of the instructions and patches the branches. Branches too far from
+    /// their targets for a 16-bit offset are relaxed until the pcs settle: a `goto` becomes a
+    /// `goto_w`, and a conditional branch skips a `goto_w` to its target on the opposite
+    /// condition.
+    fn assemble(self) -> Result<Code> {
+        let mut far = vec![false; self.instructions.len()];
+        let pcs = loop {
+            let mut pcs = Vec::with_capacity(self.instructions.len() + 1);
+            let mut pc = 0;
+            for (instruction, &far) in self.instructions.iter().zip(&far) {
+                pcs.push(pc);
+                pc += match (far, instruction) {
+                    (false, _) => instruction.length(pc),
+                    (true, Instruction::Goto(_)) => 5,
+                    (true, _) => 8,
+                };
+            }
+            pcs.push(pc);
+            if pc > u16::MAX as u32 {
+                return Err(SsaError::CodeTooLarge);
+            }
+            let mut relaxed = false;
+            for (index, targets) in &self.fixups {
+                let switch = matches!(
+                    self.instructions[*index],
+                    Instruction::Tableswitch { .. } | Instruction::Lookupswitch { .. }
+                );
+                let target = pcs[self.labels[targets[0]].expect("labels are placed")];
+                if !switch
+                    && !far[*index]
+                    && i16::try_from(target as i32 - pcs[*index] as i32).is_err()
+                {
+                    far[*index] = true;
+                    relaxed = true;
+                }
+            }
+            if !relaxed {
+                break pcs;
+            }
+        };
+        let label_pc = |label: Label| pcs[self.labels[label].expect("labels are placed")];
+
+        let mut instructions = self.instructions;
+        // The offset of the `goto_w` after each relaxed conditional branch.
+        let mut far_gotos = vec![None; instructions.len()];
+        for (index, targets) in self.fixups {
+            let pc = pcs[index];
+            let offsets = targets.iter().map(|&label| label_pc(label) as i32 - pc as i32);
+            let mut offsets = offsets.collect::<Vec<_>>().into_iter();
+            let mut next = || offsets.next().expect("a target per offset");
+            match (far[index], &mut instructions[index]) {
+                (_, Instruction::Tableswitch { default, offsets, .. }) => {
+                    *default = next();
+                    offsets.iter_mut().for_each(|offset| *offset = next());
+                }
+                (_, Instruction::Lookupswitch { default, pairs }) => {
+                    *default = next();
+                    pairs.iter_mut().for_each(|(_, offset)| *offset = next());
+                }
+                (true, instruction @ Instruction::Goto(_)) => {
+                    *instruction = Instruction::Goto_w(next());
+                }
+                (
+                    far,
+                    Instruction::Goto(offset)
+                    | Instruction::Ifeq(offset)
+                    | Instruction::Ifne(offset)
+                    | Instruction::Iflt(offset)
+                    | Instruction::Ifge(offset)
+                    | Instruction::Ifgt(offset)
+                    | Instruction::Ifle(offset)
+                    | Instruction::If_icmpeq(offset)
+                    | Instruction::If_icmpne(offset)
+                    | Instruction::If_icmplt(offset)
+                    | Instruction::If_icmpge(offset)
+                    | Instruction::If_icmpgt(offset)
+                    | Instruction::If_icmple(offset)
+                    | Instruction::If_acmpeq(offset)
+                    | Instruction::If_acmpne(offset)
+                    | Instruction::Ifnull(offset)
+                    | Instruction::Ifnonnull(offset),
+                ) => match far {
+                    true => {
+                        far_gotos[index] = Some(next() - 3);
+                        *offset = 8;
+                    }
+                    false => *offset = next() as i16 as u16,
+                },
+                _ => unreachable!("only branches are patched"),
+            }
+            if far_gotos[index].is_some() {
+                instructions[index] = negate(&instructions[index]);
+            }
+        }
+        let mut code = Vec::with_capacity(instructions.len());
+        for ((instruction, &pc), far_goto) in instructions.into_iter().zip(&pcs).zip(far_gotos) {
+            code.push((instruction, pc));
+            if let Some(offset) = far_goto {
+                code.push((Instruction::Goto_w(offset), pc + 3));
+            }
+        }
+
+        // Entries of consecutive blocks with the same handlers are merged.
+        let mut exception_table = Vec::<ExceptionHandler>::new();
+        let mut previous: Option<(&[Handler], usize)> = None;
+        for (start, end, handlers) in &self.handlers {
+            let (start_pc, end_pc) = (pcs[*start] as u16, pcs[*end] as u16);
+            if let Some((previous_handlers, first)) = previous {
+                if previous_handlers == handlers.as_slice()
+                    && exception_table[first].end_pc == start_pc
+                {
+                    exception_table[first..].iter_mut().for_each(|entry| entry.end_pc = end_pc);
+                    continue;
+                }
+            }
+            previous = Some((handlers, exception_table.len()));
+            exception_table.extend(handlers.iter().map(|handler| ExceptionHandler {
+                start_pc,
+                end_pc,
+                handler_pc: label_pc(handler.block) as u16,
+                catch_type: handler.catch_type,
+            }));
+        }
+
+        Ok(Code {
+            max_stack: self.max_stack as u16,
+            max_locals: self.max_locals,
+            code,
+            exception_table,
+            attributes: Vec::new(),
+        })
+    }
+}
+
+fn branch(condition: Condition, operands: usize, value_type: ValueType) -> Instruction {
+    match (operands, value_type == ValueType::Reference, condition) {
+        (1, true, Condition::Equal) => Instruction::Ifnull(0),
+        (1, true, _) => Instruction::Ifnonnull(0),
+        (1, false, Condition::Equal) => Instruction::Ifeq(0),
+        (1, false, Condition::NotEqual) => Instruction::Ifne(0),
+        (1, false, Condition::Less) => Instruction::Iflt(0),
+        (1, false, Condition::GreaterOrEqual) => Instruction::Ifge(0),
+        (1, false, Condition::Greater) => Instruction::Ifgt(0),
+        (1, false, Condition::LessOrEqual) => Instruction::Ifle(0),
+        (_, true, Condition::Equal) => Instruction::If_acmpeq(0),
+        (_, true, _) => Instruction::If_acmpne(0),
+        (_, false, Condition::Equal) => Instruction::If_icmpeq(0),
+        (_, false, Condition::NotEqual) => Instruction::If_icmpne(0),
+        (_, false, Condition::Less) => Instruction::If_icmplt(0),
+        (_, false, Condition::GreaterOrEqual) => Instruction::If_icmpge(0),
+        (_, false, Condition::Greater) => Instruction::If_icmpgt(0),
+        (_, false, Condition::LessOrEqual) => Instruction::If_icmple(0),
+    }
+}
+
+/// The copies for the phis of `to` on the edge from `from`.
+fn phi_copies(ssa: &SsaCode, from: BlockId, to: BlockId) -> Copies {
+    ssa.blocks[to]
+        .phis
+        .iter()
+        .filter_map(|phi| {
+            let (_, argument) = phi.arguments.iter().find(|(block, _)| *block == from)?;
+            Some((*argument, phi.result))
+        })
+        .collect()
+}
+
+/// The values used once, as the first operand of the statement or terminator right after
+/// their definition, which can stay on the operand stack in between.
+fn stacked_values(ssa: &SsaCode, uses: &[usize]) -> Vec<bool> {
+    let mut stacked = vec![false; uses.len()];
+    for block in &ssa.blocks {
+        // The copies of a goto load their sources themselves.
+        let terminator_operand = match &block.terminator {
+            Terminator::Goto(_) => None,
+            terminator => terminator.operands().first().copied(),
+        };
+        let next_operands = block
+            .statements
+            .iter()
+            .skip(1)
+            .map(|statement| statement.operands.first().copied())
+            .chain([terminator_operand]);
+        for (statement, next) in block.statements.iter().zip(next_operands) {
+            if let (Some(result), Some(next)) = (statement.result, next) {
+                let parameter = matches!(statement.operation, Operation::Parameter(_));
+                stacked[result.0 as usize] =
+                    result == next && uses[result.0 as usize] == 1 && !parameter;
+            }
+        }
+    }
+    stacked
+}
+
+/// The values that need a local variable and are live on entry to each block, without its
+/// phis.
+fn live_values(ssa: &SsaCode, needs_local: &[bool]) -> Vec<BTreeSet<Value>> {
+    let mut live_in = vec![BTreeSet::new(); ssa.blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in ssa.blocks.iter().rev() {
+            let mut live = BTreeSet::new();
+            for successor in block.terminator.successors() {
+                live.extend(live_in[successor].iter().copied());
+                live.extend(
+                    phi_copies(ssa, block.id, successor).into_iter().map(|(value, _)| value),
+                );
+            }
+            for handler in &block.handlers {
+                live.extend(live_in[handler.block].iter().copied());
+            }
+            live.extend(block.terminator.operands());
+            for statement in block.statements.iter().rev() {
+                if let Some(result) = statement.result {
+                    live.remove(&result);
+                }
+                live.extend(statement.operands.iter().copied());
+            }
+            // The phis of the handlers are copied before the statements.
+            for handler in &block.handlers {
+                let copies = phi_copies(ssa, block.id, handler.block);
+                live.extend(copies.into_iter().map(|(value, _)| value));
+            }
+            for phi in &block.phis {
+                live.remove(&phi.result);
+            }
+            live.retain(|value| needs_local[value.0 as usize]);
+            if live != live_in[block.id] {
+                live_in[block.id] = live;
+                changed = true;
+            }
+        }
+    }
+    live_in
+}
+
+/// Assigns the local variables from `first` on to the values that need one, and returns the
+/// number of local variables. Like in linear scan register allocation, each value is live
+/// over an interval of points in the order of the blocks, from the first to the last point
+/// where it is live, and values whose intervals do not overlap share local variables.
+fn allocate_locals(
+    ssa: &SsaCode,
+    needs_local: &[bool],
+    first: u32,
+    slots: &mut [Option<u16>],
+) -> Result<u16> {
+    let live_in = live_values(ssa, needs_local);
+    let mut intervals = vec![None::<(usize, usize)>; needs_local.len()];
+    let mut extend = |value: Value, point: usize| {
+        if needs_local[value.0 as usize] {
+            let interval = intervals[value.0 as usize].get_or_insert((point, point));
+            *interval = (interval.0.min(point), interval.1.max(point));
+        }
+    };
+    // A block's points are its start, where the copies to its handlers' phis happen, its
+    // statements, and its end, where the terminator and the copies to its successors' phis
+    // happen. Copies on trampolines only involve values that are live at the block's end and
+    // at the start of its successor.
+    let mut start = 0;
+    for block in &ssa.blocks {
+        let end = start + block.statements.len() + 1;
+        live_in[block.id].iter().for_each(|&value| extend(value, start));
+        block.phis.iter().for_each(|phi| extend(phi.result, start));
+        for handler in &block.handlers {
+            for (argument, result) in phi_copies(ssa, block.id, handler.block) {
+                extend(argument, start);
+                extend(result, start);
+                extend(result, end);
+            }
+            for &value in &live_in[handler.block] {
+                extend(value, start);
+                extend(value, end);
+            }
+        }
+        for (point, statement) in (start + 1..).zip(&block.statements) {
+            statement.operands.iter().for_each(|&value| extend(value, point));
+            statement.result.into_iter().for_each(|value| extend(value, point));
+        }
+        block.terminator.operands().into_iter().for_each(|value| extend(value, end));
+        for successor in block.terminator.successors() {
+            for (argument, result) in phi_copies(ssa, block.id, successor) {
+                extend(argument, end);
+                extend(result, end);
+            }
+            live_in[successor].iter().for_each(|&value| extend(value, end));
+        }
+        start = end + 1;
+    }
+
+    let mut values =
+        (0..intervals.len()).filter(|&index| intervals[index].is_some()).collect::<Vec<_>>();
+    values.sort_by_key(|&index| intervals[index].map(|(start, _)| start));
+    // The last point at which each local variable holds a value.
+    let mut taken_until = Vec::<Option<usize>>::new();
+    for index in values {
+        let (start, end) = intervals[index].expect("values with intervals");
+        let size = ssa.value_types[index].size();
+        let free = |local: usize| {
+            taken_until.get(local).is_none_or(|last| last.is_none_or(|last| last < start))
+        };
+        let local = (0..).find(|&local| (local..local + size).all(free)).expect("free locals");
+        if taken_until.len() < local + size {
+            taken_until.resize(local + size, None);
+        }
+        taken_until[local..local + size].fill(Some(end));
+        let slot = u16::try_from(first as usize + local).map_err(|_| SsaError::TooManyLocals)?;
+        slots[index] = Some(slot);
+    }
+    u16::try_from(first as usize + taken_until.len()).map_err(|_| SsaError::TooManyLocals)
+}
+
+type LocalInstructions = ([Instruction; 4], fn(u8) -> Instruction, fn(u16) -> WideInstruction);
+
+pub(crate) fn load(value_type: ValueType, slot: u16) -> Instruction {
+    let forms: LocalInstructions = match value_type {
+        ValueType::Int => (
+            [
+                Instruction::Iload_0,
+                Instruction::Iload_1,
+                Instruction::Iload_2,
+                Instruction::Iload_3,
+            ],
+            Instruction::Iload,
+            WideInstruction::Iload,
+        ),
+        ValueType::Long => (
+            [
+                Instruction::Lload_0,
+                Instruction::Lload_1,
+                Instruction::Lload_2,
+                Instruction::Lload_3,
+            ],
+            Instruction::Lload,
+            WideInstruction::Lload,
+        ),
+        ValueType::Float => (
+            [
+                Instruction::Fload_0,
+                Instruction::Fload_1,
+                Instruction::Fload_2,
+                Instruction::Fload_3,
+            ],
+            Instruction::Fload,
+            WideInstruction::Fload,
+        ),
+        ValueType::Double => (
+            [
+                Instruction::Dload_0,
+                Instruction::Dload_1,
+                Instruction::Dload_2,
+                Instruction::Dload_3,
+            ],
+            Instruction::Dload,
+            WideInstruction::Dload,
+        ),
+        _ => (
+            [
+                Instruction::Aload_0,
+                Instruction::Aload_1,
+                Instruction::Aload_2,
+                Instruction::Aload_3,
+            ],
+            Instruction::Aload,
+            WideInstruction::Aload,
+        ),
+    };
+    local_instruction(forms, slot)
+}
+
+pub(crate) fn store(value_type: ValueType, slot: u16) -> Instruction {
+    let forms: LocalInstructions = match value_type {
+        ValueType::Int => (
+            [
+                Instruction::Istore_0,
+                Instruction::Istore_1,
+                Instruction::Istore_2,
+                Instruction::Istore_3,
+            ],
+            Instruction::Istore,
+            WideInstruction::Istore,
+        ),
+        ValueType::Long => (
+            [
+                Instruction::Lstore_0,
+                Instruction::Lstore_1,
+                Instruction::Lstore_2,
+                Instruction::Lstore_3,
+            ],
+            Instruction::Lstore,
+            WideInstruction::Lstore,
+        ),
+        ValueType::Float => (
+            [
+                Instruction::Fstore_0,
+                Instruction::Fstore_1,
+                Instruction::Fstore_2,
+                Instruction::Fstore_3,
+            ],
+            Instruction::Fstore,
+            WideInstruction::Fstore,
+        ),
+        ValueType::Double => (
+            [
+                Instruction::Dstore_0,
+                Instruction::Dstore_1,
+                Instruction::Dstore_2,
+                Instruction::Dstore_3,
+            ],
+            Instruction::Dstore,
+            WideInstruction::Dstore,
+        ),
+        _ => (
+            [
+                Instruction::Astore_0,
+                Instruction::Astore_1,
+                Instruction::Astore_2,
+                Instruction::Astore_3,
+            ],
+            Instruction::Astore,
+            WideInstruction::Astore,
+        ),
+    };
+    local_instruction(forms, slot)
+}
+
+fn local_instruction((short, narrow, wide): LocalInstructions, slot: u16) -> Instruction {
+    match slot {
+        0..=3 => short[slot as usize].clone(),
+        4..=255 => narrow(slot as u8),
+        _ => Instruction::Wide(wide(slot)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lower_swapping_phis() {
+        // Two ints swapped on every iteration, whose phis must be copied at once.
+        let statement = |result, local| Statement {
+            result: Some(Value(result)),
+            operation: Operation::Parameter(local),
+            operands: Vec::new(),
+        };
+        let ssa = SsaCode {
+            blocks: vec![
+                Block {
+                    id: 0,
+                    pc: None,
+                    phis: Vec::new(),
+                    statements: vec![statement(0, 0), statement(1, 1)],
+                    terminator: Terminator::Goto(1),
+                    handlers: Vec::new(),
+                },
+                Block {
+                    id: 1,
+                    pc: Some(0),
+                    phis: vec![
+                        Phi { result: Value(2), arguments: vec![(0, Value(0)), (1, Value(3))] },
+                        Phi { result: Value(3), arguments: vec![(0, Value(1)), (1, Value(2))] },
+                    ],
+                    statements: Vec::new(),
+                    terminator: Terminator::If {
+                        condition: Condition::Less,
+                        operands: vec![Value(2), Value(3)],
+                        target: 1,
+                        fallthrough: 2,
+                    },
+                    handlers: Vec::new(),
+                },
+                Block {
+                    id: 2,
+                    pc: Some(5),
+                    phis: Vec::new(),
+                    statements: Vec::new(),
+                    terminator: Terminator::Return(Some(Value(2))),
+                    handlers: Vec::new(),
+                },
+            ],
+            value_types: vec![ValueType::Int; 4],
+        };
+        assert_eq!(ssa.predecessors(), [vec![], vec![0, 1], vec![1]]);
+        assert!(ssa.to_string().contains("    v2: int = phi [b0: v0, b1: v3]\n"));
+        assert!(ssa.to_string().contains("    if v2 < v3 goto b1 else b2\n"));
+
+        let code = ssa.lower().unwrap();
+        assert_eq!((code.max_stack, code.max_locals), (2, 4));
+        assert_eq!(
+            code.code,
+            [
+                (Instruction::Iload_0, 0),
+                (Instruction::Iload_1, 1),
+                (Instruction::Istore_3, 2),
+                (Instruction::Istore_2, 3),
+                (Instruction::Iload_2, 4),
+                (Instruction::Iload_3, 5),
+                (Instruction::If_icmplt(5), 6),
+                (Instruction::Iload_2, 9),
+                (Instruction::Ireturn, 10),
+                (Instruction::Iload_3, 11),
+                (Instruction::Iload_2, 12),
+                (Instruction::Istore_3, 13),
+                (Instruction::Istore_2, 14),
+                (Instruction::Goto(-11i16 as u16), 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lower_far_branches() {
+        // A loop whose body is too long for the offsets of its exit and back edge.
+        let block = |id, statements, terminator| Block {
+            id,
+            pc: Some(0),
+            phis: Vec::new(),
+            statements,
+            terminator,
+            handlers: Vec::new(),
+        };
+        let nop = Statement {
+            result: None,
+            operation: Operation::Instruction(Instruction::Nop),
+            operands: Vec::new(),
+        };
+        let parameter = Statement {
+            result: Some(Value(0)),
+            operation: Operation::Parameter(0),
+            operands: Vec::new(),
+        };
+        let ssa = SsaCode {
+            blocks: vec![
+                block(0, vec![parameter], Terminator::Goto(1)),
+                block(
+                    1,
+                    Vec::new(),
+                    Terminator::If {
+                        condition: Condition::Equal,
+                        operands: vec![Value(0)],
+                        target: 3,
+                        fallthrough: 2,
+                    },
+                ),
+                block(2, vec![nop; 40000], Terminator::Goto(1)),
+                block(3, Vec::new(), Terminator::Return(None)),
+            ],
+            value_types: vec![ValueType::Int],
+        };
+
+        let code = ssa.lower().unwrap();
+        assert_eq!(code.code.len(), 40005);
+        assert_eq!(
+            code.code[..3],
+            [(Instruction::Iload_0, 0), (Instruction::Ifne(8), 1), (Instruction::Goto_w(40010), 4)]
+        );
+        assert_eq!(
+            code.code[40003..],
+            [(Instruction::Goto_w(-40009), 40009), (Instruction::Return, 40014)]
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Expansion of `StackMapTable` attributes into frames with absolute pcs and every local
+//! variable slot spelled out.
+//!
+//! The attribute delta-encodes its frames: each gives its pc as an offset from the previous
+//! frame and its locals relative to the previous frame's, starting from the frame implied by
+//! the method descriptor. Expansion replays those deltas and resolves the constant pool
+//! references of the verification types.
+
+use std::fmt::{self, Display, Formatter};
+
+use thiserror::Error;
+
+use crate::attribute::Attribute;
+use crate::class_file::ClassFile;
+use crate::constant_pool::Constant;
+use crate::field::{BaseType, FieldType};
+use crate::instruction::Instruction;
+use crate::method::Method;
+use crate::predefined_attributes::{Code, StackMapFrame, VerificationTypeInfo};
+
+type Result<T> = std::result::Result<T, StackMapError>;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum StackMapError {
+    #[error("Method {0} has no code")]
+    #[non_exhaustive]
+    MissingCode(String),
+    #[error("StackMapTable frame at pc {0} refers to an invalid class constant")]
+    #[non_exhaustive]
+    InvalidConstant(u32),
+    #[error("StackMapTable frame at pc {pc} refers to pc {offset}, which is not a new instruction")]
+    #[non_exhaustive]
+    InvalidUninitialized { pc: u32, offset: u16 },
+    #[error("StackMapTable frame at pc {0} chops more locals than the previous frame has")]
+    #[non_exhaustive]
+    InvalidChop(u32),
+}
+
+/// A verification type with its constant pool references resolved.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VerificationType {
+    /// An unusable slot, also the second slot of long and double locals.
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in a constructor before the superclass constructor is called.
+    UninitializedThis,
+    /// A class or interface by internal name, or an array by descriptor.
+    Object(String),
+    /// An object created by the `new` instruction at `pc` whose constructor has not been
+    /// called yet.
+    Uninitialized {
+        pc: u32,
+        class: String,
+    },
+}
+
+impl VerificationType {
+    /// The verification type of a value of `field_type`.
+    pub fn of(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Base(BaseType::Long) => VerificationType::Long,
+            FieldType::Base(BaseType::Double) => VerificationType::Double,
+            FieldType::Base(BaseType::Float) => VerificationType::Float,
+            FieldType::Base(_) => VerificationType::Integer,
+            FieldType::Object(name) => VerificationType::Object(name.clone()),
+            array => VerificationType::Object(array.to_string()),
+        }
+    }
+
+    /// The number of slots a value of this type takes: 2 for long and double values.
+    pub fn size(&self) -> usize {
+        match self {
+            VerificationType::Long | VerificationType::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for VerificationType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            VerificationType::Top => write!(f, "top"),
+            VerificationType::Integer => write!(f, "int"),
+            VerificationType::Float => write!(f, "float"),
+            VerificationType::Long => write!(f, "long"),
+            VerificationType::Double => write!(f, "double"),
+            VerificationType::Null => write!(f, "null"),
+            VerificationType::UninitializedThis => write!(f, "uninitializedThis"),
+            VerificationType::Object(name) => write!(f, "{}", name),
+            VerificationType::Uninitialized { pc, class } => {
+                write!(f, "uninitialized({}) {}", pc, class)
+            }
+        }
+    }
+}
+
+/// A frame of a `StackMapTable` at its absolute pc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandedFrame {
+    pub pc: u32,
+    /// One type per local variable slot, with `Top` for the second slot of long and double
+    /// values. Slots past the end are unusable.
+    pub locals: Vec<VerificationType>,
+    /// One type per value on the operand stack, from the bottom.
+    pub stack: Vec<VerificationType>,
+}
+
+/// The expanded `StackMapTable` of a method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackMap {
+    /// The implicit frame at the start of the method, given by its descriptor.
+    pub initial: ExpandedFrame,
+    /// The frames of the attribute in pc order, empty if the method has none.
+    pub frames: Vec<ExpandedFrame>,
+}
+
+impl StackMap {
+    pub fn new(class: &ClassFile, method: &Method) -> Result<Self> {
+        let code = method.code().ok_or_else(|| StackMapError::MissingCode(method.name.clone()))?;
+
+        // Frames list locals one entry per value, so long and double take one entry.
+        let mut locals = Vec::new();
+        if !method.flags.is_static() {
+            locals.push(match method.name.as_str() {
+                "<init>" if class.this_class != "java/lang/Object" => {
+                    VerificationType::UninitializedThis
+                }
+                _ => VerificationType::Object(class.this_class.clone()),
+            });
+        }
+        locals.extend(method.type_descriptor.parameters().iter().map(VerificationType::of));
+        let initial = ExpandedFrame { pc: 0, locals: slots(&locals), stack: Vec::new() };
+
+        let Some(table) = code.attributes.iter().find_map(|attribute| match attribute {
+            Attribute::StackMapTable(table) => Some(table),
+            _ => None,
+        }) else {
+            return Ok(StackMap { initial, frames: Vec::new() });
+        };
+
+        let mut frames = Vec::with_capacity(table.frames.len());
+        let mut previous = None::<u32>;
+        for frame in &table.frames {
+            let offset_delta = match frame {
+                StackMapFrame::SameFrame { frame_type } => *frame_type as u16,
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, .. } => {
+                    *frame_type as u16 - 64
+                }
+                StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, .. }
+                | StackMapFrame::ChopFrame { offset_delta, .. }
+                | StackMapFrame::SameFrameExtended { offset_delta, .. }
+                | StackMapFrame::AppendFrame { offset_delta, .. }
+                | StackMapFrame::FullFrame { offset_delta, .. } => *offset_delta,
+            };
+            let pc = match previous {
+                None => offset_delta as u32,
+                Some(previous) => previous + offset_delta as u32 + 1,
+            };
+            previous = Some(pc);
+
+            let resolve_all = |types: &[VerificationTypeInfo]| {
+                types.iter().map(|info| resolve(class, code, info, pc)).collect::<Result<Vec<_>>>()
+            };
+            let stack = match frame {
+                StackMapFrame::SameFrame { .. } | StackMapFrame::SameFrameExtended { .. } => {
+                    Vec::new()
+                }
+                StackMapFrame::SameLocals1StackItemFrame { stack, .. }
+                | StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => {
+                    resolve_all(std::slice::from_ref(stack))?
+                }
+                StackMapFrame::ChopFrame { frame_type, .. } => {
+                    let chopped = 251 - *frame_type as usize;
+                    let remaining =
+                        locals.len().checked_sub(chopped).ok_or(StackMapError::InvalidChop(pc))?;
+                    locals.truncate(remaining);
+                    Vec::new()
+                }
+                StackMapFrame::AppendFrame { locals: appended, .. } => {
+                    locals.extend(resolve_all(appended)?);
+                    Vec::new()
+                }
+                StackMapFrame::FullFrame { locals: full, stack, .. } => {
+                    locals = resolve_all(full)?;
+                    resolve_all(stack)?
+                }
+            };
+            frames.push(ExpandedFrame { pc, locals: slots(&locals), stack });
+        }
+        Ok(StackMap { initial, frames })
+    }
+
+    /// The frame declared at `pc`, `None` if the attribute has no frame there.
+    pub fn frame_at(&self, pc: u32) -> Option<&ExpandedFrame> {
+        let index = self.frames.binary_search_by_key(&pc, |frame| frame.pc).ok()?;
+        Some(&self.frames[index])
+    }
+}
+
+/// Lays out `values` one slot at a time, with `Top` after each long and double.
+fn slots(values: &[VerificationType]) -> Vec<VerificationType> {
+    let mut slots = Vec::with_capacity(values.len());
+    for value in values {
+        slots.push(value.clone());
+        if value.size() == 2 {
+            slots.push(VerificationType::Top);
+        }
+    }
+    slots
+}
+
+fn resolve(
+    class: &ClassFile,
+    code: &Code,
+    info: &VerificationTypeInfo,
+    pc: u32,
+) -> Result<VerificationType> {
+    Ok(match info {
+        VerificationTypeInfo::Top => VerificationType::Top,
+        VerificationTypeInfo::Integer => VerificationType::Integer,
+        VerificationTypeInfo::Float => VerificationType::Float,
+        VerificationTypeInfo::Long => VerificationType::Long,
+        VerificationTypeInfo::Double => VerificationType::Double,
+        VerificationTypeInfo::Null => VerificationType::Null,
+        VerificationTypeInfo::UninitializedThis => VerificationType::UninitializedThis,
+        VerificationTypeInfo::Object { constant } => {
+            let name = match constant {
+                Constant::ClassIndex(name_index) => class.constant_pool.utf8(*name_index as usize),
+                _ => None,
+            };
+            VerificationType::Object(name.ok_or(StackMapError::InvalidConstant(pc))?.to_string())
+        }
+        VerificationTypeInfo::Uninitialized { offset } => {
+            let new_pc = *offset as u32;
+            let position = code.code.binary_search_by_key(&new_pc, |(_, pc)| *pc).ok();
+            let class_name = match position.map(|position| &code.code[position].0) {
+                Some(Instruction::New(index)) => class.constant_pool.class_name(*index as usize),
+                _ => None,
+            };
+            let class_name =
+                class_name.ok_or(StackMapError::InvalidUninitialized { pc, offset: *offset })?;
+            VerificationType::Uninitialized { pc: new_pc, class: class_name.to_string() }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access_flag::ClassFileAccessFlags;
+    use crate::class_file_version::ClassFileVersion;
+    use crate::constant_pool::ConstantPool;
+    use crate::method::{MethodAccessFlags, MethodDescriptor};
+    use crate::predefined_attributes::StackMapTable;
+
+    fn sample_method(flags: u16, name: &str, frames: Vec<StackMapFrame>) -> (ClassFile, Method) {
+        let code = Code {
+            max_stack: 2,
+            max_locals: 5,
+            code: vec![
+                (Instruction::New(2), 0),
+                (Instruction::Dup, 3),
+                (Instruction::Nop, 4),
+                (Instruction::Nop, 5),
+                (Instruction::Nop, 6),
+                (Instruction::Return, 7),
+            ],
+            exception_table: Vec::new(),
+            attributes: vec![Attribute::StackMapTable(StackMapTable::new(frames))],
+        };
+        let method = Method {
+            flags: MethodAccessFlags::new(flags),
+            name: name.to_string(),
+            type_descriptor: MethodDescriptor::try_from(
+                &mut "(JLjava/lang/String;)V".chars().peekable(),
+            )
+            .unwrap(),
+            attributes: vec![Attribute::Code(code)],
+        };
+        let mut constant_pool = ConstantPool::default();
+        constant_pool.add(Constant::Utf8("p/T".to_string()));
+        constant_pool.add(Constant::ClassIndex(1));
+        constant_pool.add(Constant::Utf8("[I".to_string()));
+        constant_pool.add(Constant::ClassIndex(3));
+        let class = ClassFile {
+            version: ClassFileVersion::default(),
+            constant_pool,
+            flags: ClassFileAccessFlags::new(0x0021),
+            this_class: "p/T".to_string(),
+            super_class: Some("java/lang/Object".to_string()),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+        };
+        (class, method)
+    }
+
+    fn object(name: &str) -> VerificationType {
+        VerificationType::Object(name.to_string())
+    }
+
+    #[test]
+    fn test_expand_frames() {
+        let (class, method) = sample_method(
+            0x0008,
+            "m",
+            vec![
+                StackMapFrame::SameLocals1StackItemFrame {
+                    frame_type: 68,
+                    stack: VerificationTypeInfo::Uninitialized { offset: 0 },
+                },
+                StackMapFrame::AppendFrame {
+                    frame_type: 252,
+                    offset_delta: 0,
+                    locals: vec![VerificationTypeInfo::Integer],
+                },
+                StackMapFrame::ChopFrame { frame_type: 249, offset_delta: 0 },
+                StackMapFrame::FullFrame {
+                    frame_type: 255,
+                    offset_delta: 0,
+                    locals: vec![
+                        VerificationTypeInfo::Object { constant: Constant::ClassIndex(3) },
+                        VerificationTypeInfo::Double,
+                    ],
+                    stack: Vec::new(),
+                },
+            ],
+        );
+        let stack_map = StackMap::new(&class, &method).unwrap();
+        let parameters =
+            [VerificationType::Long, VerificationType::Top, object("java/lang/String")];
+        assert_eq!(stack_map.initial.locals, parameters);
+
+        let frames = &stack_map.frames;
+        assert_eq!(frames.iter().map(|frame| frame.pc).collect::<Vec<_>>(), [4, 5, 6, 7]);
+        assert_eq!(frames[0].locals, parameters);
+        assert_eq!(
+            frames[0].stack,
+            [VerificationType::Uninitialized { pc: 0, class: "p/T".to_string() }]
+        );
+        assert_eq!(frames[1].locals.last(), Some(&VerificationType::Integer));
+        assert!(frames[1].stack.is_empty());
+        assert_eq!(frames[2].locals, [VerificationType::Long, VerificationType::Top]);
+        assert_eq!(
+            frames[3].locals,
+            [object("[I"), VerificationType::Double, VerificationType::Top]
+        );
+        assert_eq!(stack_map.frame_at(6), Some(&frames[2]));
+        assert_eq!(stack_map.frame_at(3), None);
+    }
+
+    #[test]
+    fn test_initial_frame_of_constructor() {
+        let (class, method) = sample_method(0, "<init>", Vec::new());
+        let stack_map = StackMap::new(&class, &method).unwrap();
+        assert_eq!(stack_map.initial.locals[0], VerificationType::UninitializedThis);
+        assert_eq!(stack_map.initial.locals.len(), 4);
+        assert!(stack_map.frames.is_empty());
+
+        let (class, method) = sample_method(0, "run", Vec::new());
+        let stack_map = StackMap::new(&class, &method).unwrap();
+        assert_eq!(stack_map.initial.locals[0], object("p/T"));
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let (class, method) = sample_method(
+            0x0008,
+            "m",
+            vec![StackMapFrame::ChopFrame { frame_type: 248, offset_delta: 4 }],
+        );
+        assert_eq!(StackMap::new(&class, &method), Err(StackMapError::InvalidChop(4)));
+
+        let (class, method) = sample_method(
+            0x0008,
+            "m",
+            vec![StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 68,
+                stack: VerificationTypeInfo::Uninitialized { offset: 3 },
+            }],
+        );
+        assert_eq!(
+            StackMap::new(&class, &method),
+            Err(StackMapError::InvalidUninitialized { pc: 4, offset: 3 })
+        );
+
+        let (class, method) = sample_method(
+            0x0008,
+            "m",
+            vec![StackMapFrame::SameLocals1StackItemFrame {
+                frame_type: 64,
+                stack: VerificationTypeInfo::Object { constant: Constant::ClassIndex(40) },
+            }],
+        );
+        assert_eq!(StackMap::new(&class, &method), Err(StackMapError::InvalidConstant(0)));
+    }
+}
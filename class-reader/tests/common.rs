@@ -1,9 +1,14 @@
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::str;
+use std::sync::Mutex;
+
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
 
 #[derive(Clone)]
 pub struct JavaCompilerOptions {
@@ -37,6 +42,12 @@ impl JavaCompilerOptions {
         self.add_flag("-d").add_flag(output_dir)
     }
 
+    /// The directory given with `-d`, where `javac` writes the classes.
+    #[allow(dead_code)]
+    pub fn output_dir(&self) -> Option<&str> {
+        self.flags.windows(2).rev().find(|pair| pair[0] == "-d").map(|pair| pair[1].as_str())
+    }
+
     #[allow(dead_code)]
     pub fn custom_flag(&mut self, flag: &str) -> &mut Self {
         self.add_flag(flag)
@@ -60,10 +71,7 @@ pub fn compile_java_file(path: &Path, options: &JavaCompilerOptions) -> io::Resu
 
     let status = command.status()?;
     if !status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Failed to compile file: {}", path.display()),
-        ));
+        return Err(io::Error::other(format!("Failed to compile file: {}", path.display())));
     }
 
     Ok(())
@@ -89,6 +97,53 @@ pub fn check_javac_version() -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// The classes compiled from the Java source at `path` with `options`, which must name an
+/// output directory of its own: it is emptied first, then read back whole. Each source is
+/// compiled once per test binary and set of options, after checking the `javac` version.
+#[allow(dead_code)]
+pub fn compiled_classes(path: &Path, options: &JavaCompilerOptions) -> &'static [ClassFile] {
+    type Compilation = (PathBuf, Vec<String>);
+    static CLASSES: Mutex<BTreeMap<Compilation, &'static [ClassFile]>> =
+        Mutex::new(BTreeMap::new());
+    let mut classes = CLASSES.lock().unwrap();
+    classes.entry((path.to_path_buf(), options.to_args())).or_insert_with(|| {
+        if let Err(e) = check_javac_version() {
+            panic!("{}", e);
+        }
+        let output_dir = options.output_dir().expect("javac options without -d");
+        let _ = fs::remove_dir_all(output_dir);
+        compile_java_file(path, options).unwrap();
+        let classes = ClassFileReader::read_directory(output_dir)
+            .map(|(path, result)| result.unwrap_or_else(|e| panic!("{}: {}", path.display(), e)))
+            .collect::<Vec<_>>();
+        Box::leak(classes.into_boxed_slice())
+    })
+}
+
+/// The top-level class compiled from the Java source at `path`, see [`compiled_classes`].
+#[allow(dead_code)]
+pub fn compiled_class(path: &Path, options: &JavaCompilerOptions) -> &'static ClassFile {
+    let name = path.file_stem().unwrap().to_str().unwrap();
+    let classes = compiled_classes(path, options);
+    classes.iter().find(|class| class.this_class == name).unwrap()
+}
+
+/// The class file of [`compiled_class`], as `javac` wrote it.
+#[allow(dead_code)]
+pub fn compiled_class_bytes(path: &Path, options: &JavaCompilerOptions) -> Vec<u8> {
+    let class = compiled_class(path, options);
+    let output_dir = Path::new(options.output_dir().unwrap());
+    fs::read(output_dir.join(format!("{}.class", class.this_class))).unwrap()
+}
+
+/// Runs `java` with `arguments`, checking that it succeeds, and returns what it printed.
+#[allow(dead_code)]
+pub fn run_java(arguments: &[&str]) -> String {
+    let output = Command::new("java").args(arguments).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
 pub fn read_class_file(path: &Path) -> io::Result<Vec<u8>> {
     let mut file = File::open(path)?;
     let mut contents = Vec::new();
@@ -124,3 +179,15 @@ impl CompileConfig {
         Ok(bytes)
     }
 }
+
+/// The home directory of the JDK providing `javac`, as reported by its `java.home` property.
+#[allow(dead_code)]
+pub fn java_home() -> Result<PathBuf, Box<dyn Error>> {
+    let output = Command::new("java").args(["-XshowSettings:properties", "-version"]).output()?;
+    let stderr = str::from_utf8(&output.stderr)?;
+    let java_home = stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("java.home = "))
+        .ok_or("Failed to find java.home")?;
+    Ok(PathBuf::from(java_home))
+}
@@ -0,0 +1,42 @@
+import java.util.function.Supplier;
+
+public class CallGraphSample {
+    interface Shape {
+        double area();
+    }
+
+    static class Circle implements Shape {
+        public double area() {
+            return 3.0;
+        }
+    }
+
+    static class Square implements Shape {
+        public double area() {
+            return 4.0;
+        }
+    }
+
+    static class Unused implements Shape {
+        public double area() {
+            return 0.0;
+        }
+    }
+
+    @Deprecated
+    static int legacy() {
+        return 1;
+    }
+
+    static double total(Shape shape) {
+        return shape.area();
+    }
+
+    public static void main(String[] args) {
+        Shape circle = new Circle();
+        Supplier<Shape> factory = Square::new;
+        Runnable task = () -> System.out.println(legacy());
+        task.run();
+        System.out.println(total(circle) + total(factory.get()));
+    }
+}
@@ -0,0 +1,46 @@
+public class CodeLimitsSample {
+    private long total;
+
+    public CodeLimitsSample(long total) {
+        this.total = total;
+    }
+
+    static int nested(int a, int b) {
+        return Math.max(a, Math.min(b, a + b * (a - b)));
+    }
+
+    long wide(long a, double b) {
+        long product = a * (long) b;
+        return total + product + (a ^ product);
+    }
+
+    static String guarded(String text) {
+        try {
+            return text.substring(Integer.parseInt(text.trim()));
+        } catch (NumberFormatException | IndexOutOfBoundsException e) {
+            return "invalid: " + e.getMessage();
+        } finally {
+            System.out.println(text);
+        }
+    }
+
+    static int branches(int kind, int[] values) {
+        int result = 0;
+        switch (kind) {
+            case 0 -> result = values.length;
+            case 1 -> {
+                for (int value : values) {
+                    result += value > 0 ? value : -value;
+                }
+            }
+            default -> result = kind < 0 ? -1 : values[kind % values.length];
+        }
+        return result;
+    }
+
+    static int[][] matrix(int size) {
+        int[][] matrix = new int[size][size];
+        matrix[0][0] = 1;
+        return matrix;
+    }
+}
@@ -0,0 +1,12 @@
+import java.util.ArrayList;
+import java.util.List;
+
+public class CodeViewSample {
+    static List<String> names(int count) {
+        List<String> names = new ArrayList<>();
+        for (int i = 0; i < count; i++) {
+            names.add("n" + i);
+        }
+        return names;
+    }
+}
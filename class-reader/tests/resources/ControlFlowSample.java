@@ -0,0 +1,58 @@
+public class ControlFlowSample {
+
+    static int loop(int count) {
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            sum += i;
+        }
+        return sum;
+    }
+
+    static int classify(int value) {
+        switch (value) {
+            case 1:
+                return 10;
+            case 2:
+                return 20;
+            case 3:
+                return 30;
+            default:
+                return -1;
+        }
+    }
+
+    static int parse(String text) {
+        try {
+            return Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            return -1;
+        } finally {
+            System.out.println("parsed");
+        }
+    }
+
+    static int nested(int[][] grid) {
+        int sum = 0;
+        for (int i = 0; i < grid.length; i++) {
+            for (int j = 0; j < grid[i].length; j++) {
+                if (grid[i][j] > 0) {
+                    sum += grid[i][j];
+                }
+            }
+        }
+        return sum;
+    }
+
+    static int classifyLargest(int value) {
+        switch (value) {
+            case Integer.MAX_VALUE - 2:
+                return 1;
+            case Integer.MAX_VALUE - 1:
+                return 2;
+            case Integer.MAX_VALUE:
+                return 3;
+            default:
+                return 0;
+        }
+    }
+}
@@ -0,0 +1,34 @@
+public class DataflowSample {
+
+    static int constants(boolean flag) {
+        int a = 6;
+        long b = 7L;
+        int c = a * (int) b;
+        int d = flag ? 1 : 2;
+        return c + d;
+    }
+
+    static String nullness(String text) {
+        String s = null;
+        Object o = new Object();
+        if (text != null) {
+            s = text;
+        }
+        return o.hashCode() > 0 ? s : null;
+    }
+
+    static int liveness(int x, int y) {
+        int unused = y * 2;
+        return x + 1;
+    }
+
+    static int guarded(String text) {
+        int result = 0;
+        try {
+            result = Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            e.printStackTrace();
+        }
+        return result;
+    }
+}
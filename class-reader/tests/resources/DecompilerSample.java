@@ -0,0 +1,172 @@
+import java.util.ArrayList;
+import java.util.List;
+import java.util.function.Function;
+import java.util.function.Supplier;
+
+public class DecompilerSample<T extends Comparable<T>> {
+    private final List<T> items = new ArrayList<>();
+    private int count;
+    static final String GREETING = "hello";
+
+    public DecompilerSample(int count) {
+        this.count = count;
+    }
+
+    static int max(int a, int b) {
+        if (a > b) {
+            return a;
+        } else {
+            return b;
+        }
+    }
+
+    static boolean inRange(int value, int low, int high) {
+        return value >= low && value <= high;
+    }
+
+    static String classify(int value) {
+        if (value < 0 || value > 100) {
+            return "out";
+        }
+        return "in";
+    }
+
+    static int sum(int[] values) {
+        int total = 0;
+        for (int i = 0; i < values.length; i++) {
+            total += values[i];
+        }
+        return total;
+    }
+
+    static int countDown(int n) {
+        int steps = 0;
+        while (n > 0) {
+            n--;
+            steps++;
+        }
+        return steps;
+    }
+
+    static int digits(int n) {
+        int result = 0;
+        do {
+            n /= 10;
+            result++;
+        } while (n != 0);
+        return result;
+    }
+
+    static int findPair(int[][] grid, int target) {
+        int found = -1;
+        outer:
+        for (int i = 0; i < grid.length; i++) {
+            for (int j = 0; j < grid[i].length; j++) {
+                if (grid[i][j] == target) {
+                    found = i;
+                    break outer;
+                }
+            }
+        }
+        return found;
+    }
+
+    static String name(int day) {
+        String result;
+        switch (day) {
+            case 1:
+                result = "Monday";
+                break;
+            case 2:
+                result = "Tuesday";
+                break;
+            case 6:
+            case 7:
+                result = "Weekend";
+                break;
+            default:
+                result = "Other";
+        }
+        return result;
+    }
+
+    static int parse(String text) {
+        try {
+            return Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            return -1;
+        }
+    }
+
+    int guarded(String text) {
+        try {
+            count = Integer.parseInt(text);
+        } catch (NumberFormatException | NullPointerException e) {
+            count = 0;
+        } finally {
+            count++;
+        }
+        return count;
+    }
+
+    static Function<Integer, Integer> adder(int amount) {
+        return x -> x + amount;
+    }
+
+    static Supplier<List<String>> factory() {
+        return ArrayList::new;
+    }
+
+    static String describe(String name, int age) {
+        return "Name: " + name + ", age: " + age;
+    }
+
+    void add(T item) {
+        items.add(item);
+    }
+
+    T largest() {
+        T best = null;
+        for (T item : items) {
+            if (best == null || item.compareTo(best) > 0) {
+                best = item;
+            }
+        }
+        return best;
+    }
+
+    static int sign(int value) {
+        return value < 0 ? -1 : 1;
+    }
+
+    static int[] primes() {
+        int[] values = {2, 3, 5, 7};
+        return values;
+    }
+
+    int next() {
+        return count++;
+    }
+
+    static int attempt(Supplier<Integer> task, List<String> log) {
+        try {
+            return task.get();
+        } finally {
+            log.add("done");
+        }
+    }
+
+    int locked(int delta) {
+        synchronized (items) {
+            count += delta;
+            return count;
+        }
+    }
+
+    static void shared(Object lock, List<String> log) {
+        synchronized (lock) {
+            log.add("entered");
+        }
+        log.add("left");
+    }
+}
@@ -0,0 +1,12 @@
+public class LongDoubleConstants {
+
+    private static final long LONG = 1L << 40;
+
+    private static final double DOUBLE = 0.1;
+
+    private static final String STRING = "after wide constants";
+
+    long sum(long value) {
+        return value + LONG;
+    }
+}
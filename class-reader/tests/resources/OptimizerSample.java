@@ -0,0 +1,117 @@
+import java.util.ArrayList;
+import java.util.List;
+
+public class OptimizerSample {
+    private int counter;
+
+    OptimizerSample(int start) {
+        int doubled = start * 2;
+        doubled = start + 1;
+        counter = doubled;
+    }
+
+    static int compute() {
+        return 5;
+    }
+
+    static int overwritten() {
+        int x = compute();
+        x = 6;
+        return x;
+    }
+
+    static long wide(long a) {
+        long unused = a * 2;
+        double scale = 1.5;
+        for (int i = 0; i < 3; i++) {
+            a += i;
+        }
+        return a;
+    }
+
+    static String nested(boolean a, boolean b) {
+        String result;
+        if (a) {
+            if (b) {
+                result = "both";
+            } else {
+                result = "a";
+            }
+        } else {
+            result = "none";
+        }
+        return result;
+    }
+
+    static int loops(int[] values) {
+        int sum = 0;
+        outer:
+        for (int i = 0; i < values.length; i++) {
+            for (int j = 0; j < i; j++) {
+                if (values[j] < 0) {
+                    continue outer;
+                }
+                if (values[j] > 100) {
+                    break outer;
+                }
+                sum += values[j];
+            }
+        }
+        return sum;
+    }
+
+    static String guarded(String text) {
+        int attempts = 0;
+        try {
+            attempts = text.length();
+            return text.substring(Integer.parseInt(text.trim()));
+        } catch (NumberFormatException e) {
+            return "invalid " + attempts;
+        } finally {
+            attempts = -1;
+            System.out.print("");
+        }
+    }
+
+    static String classify(int value) {
+        switch (value) {
+            case 1:
+                return "one";
+            case 2:
+            case 3:
+                return "few";
+            case 1000:
+                return "thousand";
+            default:
+                return "many";
+        }
+    }
+
+    static List<String> objects(int count) {
+        List<String> list = new ArrayList<>(count > 2 ? count : 2);
+        for (int i = 0; i < count; i++) {
+            Object unused = list.isEmpty() ? null : list.get(0);
+            list.add(new StringBuilder().append(i).toString());
+        }
+        return list;
+    }
+
+    int increment() {
+        int old = counter;
+        old++;
+        return ++counter;
+    }
+
+    public static void main(String[] args) {
+        System.out.println(overwritten());
+        System.out.println(wide(7));
+        System.out.println(nested(true, true) + nested(true, false) + nested(false, true));
+        System.out.println(loops(new int[] {1, 2, 3, -4, 5}) + " " + loops(new int[] {1, 200, 3}));
+        System.out.println(guarded("1abc") + guarded("x") + guarded("0"));
+        for (int value : new int[] {1, 2, 3, 4, 1000}) {
+            System.out.println(classify(value));
+        }
+        System.out.println(objects(4));
+        System.out.println(new OptimizerSample(3).increment());
+    }
+}
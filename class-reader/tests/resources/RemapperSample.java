@@ -0,0 +1,126 @@
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.reflect.Method;
+import java.util.Arrays;
+import java.util.List;
+import java.util.function.Supplier;
+
+public class RemapperSample {
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Tag {
+        String value();
+
+        Level level() default Level.LOW;
+
+        Class<?> type() default Object.class;
+    }
+
+    enum Level {
+        LOW,
+        HIGH
+    }
+
+    interface Named {
+        String label();
+    }
+
+    sealed interface Shape extends Named permits Circle, Square {
+        double area();
+
+        default String describe() {
+            return label() + " of area " + area();
+        }
+    }
+
+    @Tag(value = "round", level = Level.HIGH, type = Circle.class)
+    record Circle(double radius) implements Shape {
+        public double area() {
+            return Math.round(Math.PI * radius * radius);
+        }
+
+        public String label() {
+            return "circle";
+        }
+    }
+
+    static final class Square implements Shape {
+        static int created;
+        private final double side;
+
+        Square(double side) {
+            this.side = side;
+            created++;
+        }
+
+        public double area() {
+            return side * side;
+        }
+
+        public String label() {
+            return "square";
+        }
+    }
+
+    abstract static class Counter<T> {
+        protected int total;
+
+        abstract void accept(T value);
+
+        int total() {
+            return total;
+        }
+    }
+
+    static class LengthCounter extends Counter<String> {
+        @Override
+        void accept(String value) {
+            total += value.length();
+        }
+    }
+
+    private int secret = 42;
+
+    class Inner {
+        int reveal() {
+            return secret;
+        }
+    }
+
+    public static void main(String[] args) {
+        List<Shape> shapes = List.of(new Circle(2), new Square(3));
+        for (Shape shape : shapes) {
+            System.out.println(shape.describe());
+        }
+        System.out.println(new Circle(1));
+        Named named = () -> "lambda";
+        System.out.println(named.label());
+        Supplier<String> supplier = shapes.get(0)::label;
+        System.out.println(supplier.get());
+        Counter<String> counter = new LengthCounter();
+        for (String word : List.of("a", "bb", "ccc")) {
+            counter.accept(word);
+        }
+        System.out.println(counter.total());
+        System.out.println(Square.created);
+        System.out.println(new RemapperSample().new Inner().reveal());
+        Tag tag = Circle.class.getAnnotation(Tag.class);
+        System.out.println(tag.value() + " " + tag.level() + " " + tag.type().getSimpleName());
+        System.out.println(Level.values()[1]);
+        System.out.println(LengthCounter.class.getGenericSuperclass().getTypeName());
+        System.out.println(Circle.class.getRecordComponents()[0].getName());
+        for (Class<?> permitted : Shape.class.getPermittedSubclasses()) {
+            System.out.println(permitted.getSimpleName());
+        }
+        Class<?> enclosing = Inner.class.getEnclosingClass();
+        System.out.println(Inner.class.getSimpleName() + " in " + enclosing.getName());
+        Object anonymous = new Object() {
+            @Override
+            public String toString() {
+                return getClass().getEnclosingMethod().getName();
+            }
+        };
+        System.out.println(anonymous);
+        Method[] methods = Circle.class.getDeclaredMethods();
+        System.out.println(Arrays.stream(methods).map(Method::getName).sorted().toList());
+    }
+}
@@ -0,0 +1,26 @@
+import java.util.function.IntSupplier;
+
+public class SerdeSample implements Comparable<SerdeSample> {
+
+    private static final float NOT_A_NUMBER = Float.NaN;
+
+    private static final double NEGATIVE_INFINITY = Double.NEGATIVE_INFINITY;
+
+    private static final long BIG = 1L << 50;
+
+    private final String[] names = { "a", "b" };
+
+    public int compareTo(SerdeSample other) {
+        IntSupplier supplier = () -> names.length;
+        System.out.println("comparing " + supplier.getAsInt());
+        return Integer.compare(names.length, other.names.length);
+    }
+
+    Object describe(Object other) {
+        long stamp = System.nanoTime() + BIG;
+        if (other instanceof String) {
+            return ((String) other).concat("!");
+        }
+        return new StringBuilder("at ").append(stamp);
+    }
+}
@@ -0,0 +1,78 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.lang.reflect.Field;
+import java.lang.reflect.Method;
+import java.lang.reflect.RecordComponent;
+import java.util.ArrayList;
+import java.util.Arrays;
+import java.util.List;
+import java.util.function.Function;
+import java.util.function.Supplier;
+
+public class ShrinkerSample {
+    @Retention(RetentionPolicy.CLASS)
+    @interface Invisible {
+        String value();
+    }
+
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Visible {
+        String value() default "default";
+
+        int[] numbers() default {};
+    }
+
+    @Target(ElementType.TYPE_USE)
+    @interface Checked {}
+
+    record Point(@Visible("x") int x, @Invisible("y") int y) {}
+
+    enum Color {
+        RED,
+        GREEN
+    }
+
+    static final String GREETING = "hello";
+    static final long BIG = 1L << 40;
+
+    @Visible(value = "field", numbers = {1, 2})
+    @Invisible("field")
+    static List<String> names = new ArrayList<>();
+
+    class Inner {
+        int size() {
+            return names.size();
+        }
+    }
+
+    @Visible
+    @Invisible("method")
+    static <T extends Comparable<T>> T max(@Invisible("a") T a, @Checked T b)
+            throws IllegalStateException {
+        List<T> both = new ArrayList<>(List.of(a, b));
+        return both.get(0).compareTo(both.get(1)) >= 0 ? a : b;
+    }
+
+    public static void main(String[] args) throws Exception {
+        names.add(GREETING);
+        Function<String, Integer> length = String::length;
+        Supplier<String> supplier = () -> GREETING + BIG;
+        System.out.println(max(3, 7) + " " + length.apply(supplier.get()));
+        System.out.println(new ShrinkerSample().new Inner().size());
+        System.out.println(new Point(1, 2) + " " + Color.GREEN.ordinal());
+
+        Field field = ShrinkerSample.class.getDeclaredField("names");
+        Visible visible = field.getAnnotation(Visible.class);
+        System.out.println(field.getGenericType() + " " + Arrays.toString(visible.numbers()));
+        System.out.println(visible.value() + " " + field.getAnnotations().length);
+        Method method =
+                ShrinkerSample.class.getDeclaredMethod("max", Comparable.class, Comparable.class);
+        System.out.println(method.toGenericString() + " " + method.getAnnotation(Visible.class));
+        RecordComponent component = Point.class.getRecordComponents()[0];
+        System.out.println(component.getName() + " " + component.getAnnotation(Visible.class));
+        Class<?> outer = Inner.class.getEnclosingClass();
+        System.out.println(outer.getSimpleName() + "." + Inner.class.getSimpleName());
+    }
+}
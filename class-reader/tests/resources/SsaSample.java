@@ -0,0 +1,145 @@
+public class SsaSample {
+    private int counter;
+    private long total;
+
+    SsaSample(int start) {
+        counter = start;
+    }
+
+    static int fibonacci(int n) {
+        int a = 0;
+        int b = 1;
+        for (int i = 0; i < n; i++) {
+            int t = a + b;
+            a = b;
+            b = t;
+        }
+        return a;
+    }
+
+    static int swap(int x, int y, int n) {
+        while (n-- > 0) {
+            int t = x;
+            x = y;
+            y = t;
+        }
+        return x * 10 + y;
+    }
+
+    static String classify(int value) {
+        switch (value) {
+            case 1:
+                return "one";
+            case 2:
+                return "two";
+            case 3:
+                return "three";
+            default:
+                return "many";
+        }
+    }
+
+    static int sparse(int value) {
+        switch (value) {
+            case -100:
+                return 1;
+            case 7:
+                return 2;
+            case 1000:
+                return 3;
+            default:
+                return 0;
+        }
+    }
+
+    static int largest(int value) {
+        switch (value) {
+            case Integer.MAX_VALUE - 2:
+                return 1;
+            case Integer.MAX_VALUE - 1:
+                return 2;
+            case Integer.MAX_VALUE:
+                return 3;
+            default:
+                return 0;
+        }
+    }
+
+    static int parse(String text) {
+        int result = -1;
+        try {
+            result = Integer.parseInt(text);
+        } catch (NumberFormatException e) {
+            result = -2;
+        } finally {
+            result *= 2;
+        }
+        return result;
+    }
+
+    static double average(long[] values) {
+        long sum = 0;
+        for (long value : values) {
+            sum += value;
+        }
+        return values.length == 0 ? 0.0 : (double) sum / values.length;
+    }
+
+    static long increment(long[] values, int index) {
+        values[index] += 5;
+        values[index]++;
+        return values[index];
+    }
+
+    int next() {
+        return counter++;
+    }
+
+    long accumulate(long amount) {
+        return total += amount;
+    }
+
+    static int retry(int[] data, int index) {
+        int attempts = 0;
+        while (true) {
+            try {
+                attempts++;
+                return data[index] / (attempts - 1);
+            } catch (ArithmeticException e) {
+                if (attempts > 3) {
+                    return -attempts;
+                }
+            } catch (ArrayIndexOutOfBoundsException e) {
+                index = 0;
+            }
+        }
+    }
+
+    static String trim(String text) {
+        String s = text == null ? "none" : text;
+        return s.trim();
+    }
+
+    public static void main(String[] args) {
+        System.out.println(fibonacci(10));
+        System.out.println(swap(1, 2, 3));
+        System.out.println(classify(2));
+        System.out.println(classify(7));
+        System.out.println(sparse(1000));
+        System.out.println(sparse(-100));
+        System.out.println(largest(Integer.MAX_VALUE));
+        System.out.println(parse("21"));
+        System.out.println(parse("x"));
+        System.out.println(average(new long[] {1, 2, 4}));
+        System.out.println(increment(new long[] {1, 2}, 1));
+        SsaSample sample = new SsaSample(5);
+        sample.next();
+        System.out.println(sample.next());
+        System.out.println(sample.accumulate(3));
+        System.out.println(sample.accumulate(4));
+        System.out.println(retry(new int[] {8, 9}, 5));
+        System.out.println(retry(new int[] {8}, 0));
+        System.out.println(trim(null));
+        System.out.println(trim(" x "));
+    }
+}
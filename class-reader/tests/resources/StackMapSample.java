@@ -0,0 +1,19 @@
+public class StackMapSample {
+    private final String name;
+
+    public StackMapSample(boolean flag) {
+        this.name = flag ? "yes" : "no";
+    }
+
+    static Object wrap(boolean flag, long value) {
+        return new StringBuilder(flag ? "a" : "b");
+    }
+
+    static int sum(long start, int[] values) {
+        int total = 0;
+        for (int value : values) {
+            total += value;
+        }
+        return total + (int) start;
+    }
+}
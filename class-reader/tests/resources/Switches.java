@@ -0,0 +1,38 @@
+public class Switches {
+
+    static int dense(int value) {
+        switch (value) {
+            case 1:
+                return 10;
+            case 2:
+                return 20;
+            case 3:
+                return 30;
+            default:
+                return -1;
+        }
+    }
+
+    static int sparse(int value) {
+        switch (value) {
+            case -1000:
+                return 1;
+            case 7:
+                return 2;
+            case 1000000:
+                return 3;
+            default:
+                return 0;
+        }
+    }
+
+    static int loop(int count) {
+        int[] values = new int[count];
+        int sum = 0;
+        for (int i = 0; i < count; i++) {
+            values[i] = i;
+            sum += values[i];
+        }
+        return sum;
+    }
+}
@@ -0,0 +1,24 @@
+public class Api extends Base implements Runnable {
+    public static final int LIMIT = 20;
+
+    public long count;
+    private String name;
+
+    public Api() {}
+
+    public void run() {}
+
+    public long size() {
+        return count;
+    }
+
+    public void resize(long size) {}
+
+    public void helper() {}
+
+    public static void instanceMethod() {}
+
+    protected final void hook() {}
+
+    private void secret(int value) {}
+}
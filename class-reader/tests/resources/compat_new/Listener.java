@@ -0,0 +1,5 @@
+public interface Listener {
+    void onEvent();
+
+    void onClose();
+}
@@ -0,0 +1 @@
+public sealed interface Shape permits Circle, Triangle {}
@@ -0,0 +1,26 @@
+public class Api extends Base implements Runnable, java.io.Serializable {
+    public static final int LIMIT = 10;
+
+    public int count;
+    protected String name;
+
+    public Api() {}
+
+    public void run() {}
+
+    public int size() {
+        return count;
+    }
+
+    public void resize(int size) {}
+
+    public static void helper() {}
+
+    public void instanceMethod() {}
+
+    protected void hook() {}
+
+    public void moved() {}
+
+    private void secret() {}
+}
@@ -0,0 +1 @@
+public final class Circle implements Shape {}
@@ -0,0 +1 @@
+public final class Square implements Shape {}
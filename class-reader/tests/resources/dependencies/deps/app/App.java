@@ -0,0 +1,22 @@
+package deps.app;
+
+import deps.err.AppException;
+import deps.err.RetryException;
+import deps.meta.Marker;
+import deps.model.Item;
+import deps.model.Order;
+import deps.util.Strings;
+import java.util.List;
+
+@Marker(Item.class)
+public class App {
+    private List<Order> orders;
+
+    public static void main(String[] args) throws AppException {
+        try {
+            System.out.println(Strings.shout("hello"));
+        } catch (RetryException e) {
+            throw new AppException();
+        }
+    }
+}
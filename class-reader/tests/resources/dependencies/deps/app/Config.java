@@ -0,0 +1,7 @@
+package deps.app;
+
+public class Config {
+    public static String suffix() {
+        return "!";
+    }
+}
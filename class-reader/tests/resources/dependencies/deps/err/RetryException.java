@@ -0,0 +1,3 @@
+package deps.err;
+
+public class RetryException extends RuntimeException {}
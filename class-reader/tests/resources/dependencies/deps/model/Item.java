@@ -0,0 +1,3 @@
+package deps.model;
+
+public class Item {}
@@ -0,0 +1,9 @@
+package deps.util;
+
+import deps.app.Config;
+
+public class Strings {
+    public static String shout(String text) {
+        return text.toUpperCase() + Config.suffix();
+    }
+}
@@ -0,0 +1,18 @@
+public class DiffSample implements Runnable {
+    public static final String NAME = "new";
+
+    private int count;
+
+    public long added(long value) {
+        return Math.max(value, 17L) + Long.MIN_VALUE;
+    }
+
+    public void run() {
+        count++;
+        System.out.println("run " + count);
+    }
+
+    public final int compute(int x) {
+        return x * 3;
+    }
+}
@@ -0,0 +1,16 @@
+public class DiffSample implements Runnable {
+    public static final String NAME = "old";
+
+    private int count;
+
+    public void run() {
+        count++;
+        System.out.println("run " + count);
+    }
+
+    public int compute(int x) {
+        return x * 2;
+    }
+
+    public void removed() {}
+}
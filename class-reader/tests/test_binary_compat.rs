@@ -0,0 +1,103 @@
+use common::{JavaCompilerOptions, check_javac_version, compile_java_file};
+use rsjvm_class_reader::binary_compat::{
+    CompatibilityReport, FindingKind, Severity, check_compatibility,
+};
+use rsjvm_class_reader::bulk_reader::BulkReader;
+use rsjvm_class_reader::class_file::ClassFile;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[allow(dead_code)]
+mod common;
+
+/// Compiles every source of a version of the sample library into its own directory.
+fn compile_library(version: &str) -> Vec<ClassFile> {
+    let sources = Path::new("tests/resources").join(version);
+    let output_dir = format!("target/classes/{}", version);
+    let mut options = JavaCompilerOptions::new();
+    options
+        .custom_flag("-sourcepath")
+        .custom_flag(sources.to_str().unwrap())
+        .use_output_dir(&output_dir);
+    for entry in fs::read_dir(&sources).unwrap() {
+        compile_java_file(&entry.unwrap().path(), &options).unwrap();
+    }
+    let result = BulkReader::new().add_path(&output_dir).read();
+    assert!(result.failures.is_empty());
+    result.classes.into_iter().map(|parsed| parsed.class).collect()
+}
+
+/// Both versions, compiled once for all tests of this file.
+fn libraries() -> &'static (Vec<ClassFile>, Vec<ClassFile>) {
+    static LIBRARIES: OnceLock<(Vec<ClassFile>, Vec<ClassFile>)> = OnceLock::new();
+    LIBRARIES.get_or_init(|| {
+        if let Err(e) = check_javac_version() {
+            panic!("{}", e);
+        }
+        (compile_library("compat_old"), compile_library("compat_new"))
+    })
+}
+
+fn sample_report() -> CompatibilityReport {
+    let (old, new) = libraries();
+    check_compatibility(old, new)
+}
+
+#[test]
+fn test_binary_compatibility() {
+    let report = sample_report();
+    let findings = report
+        .findings
+        .iter()
+        .map(|finding| (finding.class.as_str(), finding.member.as_deref(), finding.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        findings,
+        [
+            ("Api", None, FindingKind::SuperinterfaceRemoved),
+            ("Api", Some("LIMIT:I"), FindingKind::ConstantValueChanged),
+            ("Api", Some("count:I"), FindingKind::FieldTypeChanged),
+            ("Api", Some("name:Ljava/lang/String;"), FindingKind::MemberAccessNarrowed),
+            ("Api", Some("size:()I"), FindingKind::ReturnTypeChanged),
+            ("Api", Some("resize:(I)V"), FindingKind::MethodDescriptorChanged),
+            ("Api", Some("helper:()V"), FindingKind::StaticChanged),
+            ("Api", Some("instanceMethod:()V"), FindingKind::StaticChanged),
+            ("Api", Some("hook:()V"), FindingKind::MethodMadeFinal),
+            ("Gone", None, FindingKind::ClassRemoved),
+            ("Listener", Some("onClose:()V"), FindingKind::AbstractMethodAdded),
+            ("Shape", None, FindingKind::PermittedSubclassRemoved),
+            ("Shape", None, FindingKind::PermittedSubclassAdded),
+            ("Square", None, FindingKind::SuperinterfaceRemoved),
+            ("Widget", None, FindingKind::ClassMadeFinal),
+        ]
+    );
+
+    assert_eq!(report.max_severity(), Some(Severity::Error));
+    assert_eq!(report.count(Severity::Warning), 3);
+    assert!(report.has_findings(Severity::Error));
+
+    let text = report.to_string();
+    assert!(text.contains("error: [superinterface_removed] Api: java/io/Serializable is no"));
+    assert!(text.contains("error: [return_type_changed] Api.size:()I: return type changed to J\n"));
+    assert!(text.ends_with("12 errors, 3 warnings, 0 infos\n"));
+}
+
+#[test]
+fn test_compatible_with_itself() {
+    let (old, _) = libraries();
+    let report = check_compatibility(old, old);
+    assert_eq!(report.findings, []);
+    assert_eq!(report.max_severity(), None);
+    assert!(!report.has_findings(Severity::Info));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_report_json() {
+    let value = serde_json::to_value(sample_report()).unwrap();
+    let finding = &value["findings"][4];
+    assert_eq!(finding["severity"], "error");
+    assert_eq!(finding["kind"], "return_type_changed");
+    assert_eq!(finding["member"], "size:()I");
+}
@@ -0,0 +1,80 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::num::NonZeroUsize;
+
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::bulk_reader::BulkReader;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+mod common;
+
+#[test]
+fn test_bulk_read() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let switches = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let constants = CompileConfig::new("LongDoubleConstants.java".to_string()).run().unwrap();
+    let mut truncated = switches.clone();
+    truncated.truncate(switches.len() / 2);
+
+    let directory = "target/test_bulk_read";
+    let _ = fs::remove_dir_all(directory);
+    fs::create_dir_all(format!("{}/nested", directory)).unwrap();
+    fs::write(format!("{}/Switches.class", directory), &switches).unwrap();
+    fs::write(format!("{}/nested/Truncated.class", directory), &truncated).unwrap();
+    fs::write(format!("{}/nested/notes.txt", directory), "not a class").unwrap();
+
+    let jar = "target/test_bulk_read.jar";
+    let mut zip = ZipWriter::new(File::create(jar).unwrap());
+    for (name, bytes) in [
+        ("a/LongDoubleConstants.class", constants.as_slice()),
+        ("a/BadMagic.class", &[0xCA, 0xFE, 0xBE, 0xEF]),
+        ("b/Switches.class", switches.as_slice()),
+    ] {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let result = BulkReader::new()
+        .add_path(directory)
+        .add_path(jar)
+        .add_path("target/test_bulk_read_missing.jar")
+        .threads(NonZeroUsize::new(2).unwrap())
+        .read();
+
+    let names = result.classes.iter().map(|parsed| parsed.class.this_class.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["Switches", "LongDoubleConstants", "Switches"]);
+    assert_eq!(result.classes[1].location.entry.as_deref(), Some("a/LongDoubleConstants"));
+
+    let stats = &result.stats;
+    assert_eq!(stats.classes, 3);
+    assert_eq!(stats.versions.values().sum::<usize>(), 3);
+    assert_eq!(stats.failure_count(), 3);
+    assert_eq!(stats.failures.get("Archive"), Some(&1));
+    assert_eq!(stats.failures.get("InvalidMagicNumber"), Some(&1));
+    assert_eq!(stats.failures.get("ReadError"), Some(&1));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bulk_read_skips_symlink_cycles() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let switches = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+
+    let directory = "target/test_bulk_read_cycle";
+    let _ = fs::remove_dir_all(directory);
+    fs::create_dir_all(format!("{}/nested", directory)).unwrap();
+    fs::write(format!("{}/nested/Switches.class", directory), &switches).unwrap();
+    std::os::unix::fs::symlink("..", format!("{}/nested/parent", directory)).unwrap();
+
+    let result = BulkReader::new().add_path(directory).read();
+    assert_eq!(result.stats.classes, 1);
+    assert_eq!(result.stats.failure_count(), 0);
+}
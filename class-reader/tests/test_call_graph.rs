@@ -0,0 +1,100 @@
+use common::{JavaCompilerOptions, compiled_classes};
+use rsjvm_class_reader::call_graph::{CallGraph, CallGraphBuilder, CallKind, MethodId, Precision};
+use rsjvm_class_reader::class_file::ClassFile;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_classes() -> &'static [ClassFile] {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/call_graph");
+    compiled_classes(Path::new("tests/resources/CallGraphSample.java"), &options)
+}
+
+fn main_method() -> MethodId {
+    MethodId::new("CallGraphSample", "main", "([Ljava/lang/String;)V")
+}
+
+fn build(precision: Precision) -> CallGraph {
+    CallGraphBuilder::new(sample_classes()).precision(precision).entry_point(main_method()).build()
+}
+
+fn area_targets(graph: &CallGraph) -> Vec<String> {
+    let total = MethodId::new("CallGraphSample", "total", "(LCallGraphSample$Shape;)D");
+    graph.callees(&total).into_iter().map(|edge| edge.callee.owner.clone()).collect()
+}
+
+#[test]
+fn test_cha_call_graph() {
+    let graph = build(Precision::Cha);
+
+    assert_eq!(
+        area_targets(&graph),
+        ["CallGraphSample$Circle", "CallGraphSample$Square", "CallGraphSample$Unused"]
+    );
+    assert!(graph.methods.contains(&MethodId::new("CallGraphSample$Unused", "area", "()D")));
+}
+
+#[test]
+fn test_rta_call_graph() {
+    let graph = build(Precision::Rta);
+
+    // `Unused` is never instantiated; `Square` only through a constructor reference.
+    assert_eq!(area_targets(&graph), ["CallGraphSample$Circle", "CallGraphSample$Square"]);
+    assert!(!graph.methods.contains(&MethodId::new("CallGraphSample$Unused", "area", "()D")));
+
+    let lambda = graph
+        .callees(&main_method())
+        .into_iter()
+        .find(|edge| edge.kind == CallKind::Lambda && edge.callee.name.starts_with("lambda$"))
+        .unwrap();
+    let legacy = MethodId::new("CallGraphSample", "legacy", "()I");
+    let callers = graph.callers(&legacy).into_iter().map(|edge| &edge.caller).collect::<Vec<_>>();
+    assert_eq!(callers, [&lambda.callee]);
+
+    let constructor = MethodId::new("CallGraphSample$Square", "<init>", "()V");
+    assert!(graph.callees(&main_method()).iter().any(|edge| edge.callee == constructor));
+
+    // Calls into classes that were not analysed keep their symbolic target.
+    let run = MethodId::new("java/lang/Runnable", "run", "()V");
+    assert_eq!(graph.callers(&run).len(), 1);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph calls {\n"));
+    assert!(dot.contains(&format!(
+        "{:?} -> {:?} [style=dashed];",
+        main_method().to_string(),
+        lambda.callee.to_string()
+    )));
+}
+
+#[test]
+fn test_whole_program_call_graph() {
+    let graph = CallGraphBuilder::new(sample_classes()).build();
+
+    // Without entry points every method with a body is analysed, including `Unused`.
+    let unused_area = MethodId::new("CallGraphSample$Unused", "area", "()D");
+    assert!(graph.methods.contains(&unused_area));
+    let bodies = sample_classes()
+        .iter()
+        .flat_map(|class| &class.methods)
+        .filter(|method| method.attributes.iter().any(|attribute| attribute.name() == "Code"))
+        .count();
+    assert_eq!(graph.methods.len(), bodies);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_call_graph_json() {
+    let graph = build(Precision::Rta);
+    let value = serde_json::to_value(&graph).unwrap();
+    let edge = value["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|edge| edge["callee"]["name"] == "legacy")
+        .unwrap();
+    assert_eq!(edge["kind"], "static");
+    assert_eq!(edge["callee"]["owner"], "CallGraphSample");
+}
@@ -0,0 +1,124 @@
+use common::{CompileConfig, JavaCompilerOptions, check_javac_version};
+use rsjvm_class_reader::class_diff::{ClassDiff, ClassDiffer, EditKind, MemberChange};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use std::sync::OnceLock;
+
+mod common;
+
+fn compile(version: &str) -> ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_g().use_output_dir(&format!("target/classes/{}", version));
+    let bytes = CompileConfig::new(format!("{}/DiffSample.java", version))
+        .with_options(options)
+        .run()
+        .unwrap();
+    ClassFileReader::read_class(&bytes).unwrap()
+}
+
+/// Both versions, compiled once for all tests of this file.
+fn sample_diff(ignore_debug_info: bool) -> ClassDiff {
+    static VERSIONS: OnceLock<(ClassFile, ClassFile)> = OnceLock::new();
+    let (old, new) = VERSIONS.get_or_init(|| {
+        if let Err(e) = check_javac_version() {
+            panic!("{}", e);
+        }
+        (compile("diff_old"), compile("diff_new"))
+    });
+    ClassDiffer::new().ignore_debug_info(ignore_debug_info).diff(old, new)
+}
+
+#[test]
+fn test_class_diff() {
+    let diff = sample_diff(true);
+
+    assert!(diff.version.is_none() && diff.flags.is_none() && diff.super_class.is_none());
+    assert!(diff.attributes.is_empty());
+
+    let field = diff.fields.iter().map(|field| (field.name.as_str(), &field.change));
+    let [(name, MemberChange::Changed(changes))] = field.collect::<Vec<_>>()[..] else {
+        panic!("unexpected field changes: {:?}", diff.fields);
+    };
+    assert_eq!(name, "NAME");
+    let constant_value = &changes.attributes[0];
+    assert_eq!(constant_value.name, "ConstantValue");
+    assert_eq!(constant_value.old.as_deref(), Some("\"old\""));
+    assert_eq!(constant_value.new.as_deref(), Some("\"new\""));
+
+    // `run` is unchanged even though the new constants shifted its pool references.
+    let methods = diff
+        .methods
+        .iter()
+        .map(|method| (method.name.as_str(), method.descriptor.as_str(), &method.change))
+        .collect::<Vec<_>>();
+    assert_eq!(methods.len(), 3);
+    assert_eq!(methods[0].0, "added");
+    assert_eq!(methods[0].2, &MemberChange::Added);
+    assert_eq!(methods[2].0, "removed");
+    assert_eq!(methods[2].2, &MemberChange::Removed);
+
+    let ("compute", "(I)I", MemberChange::Changed(compute)) = methods[1] else {
+        panic!("unexpected method change: {:?}", methods[1]);
+    };
+    let flags = compute.flags.as_ref().unwrap();
+    assert_eq!(flags.old, ["public"]);
+    assert_eq!(flags.new, ["public", "final"]);
+    let code = compute.code.as_ref().unwrap();
+    let edits = code
+        .instructions
+        .iter()
+        .map(|edit| (edit.kind, edit.index, edit.instruction.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(edits, [(EditKind::Removed, 1, "iconst_2"), (EditKind::Added, 1, "iconst_3")]);
+
+    let text = diff.to_string();
+    assert!(text.starts_with("~ class DiffSample\n"));
+    assert!(text.contains("    + method added:(J)J\n"));
+    assert!(text.contains("        - 1: iconst_2\n        + 1: iconst_3\n"));
+}
+
+#[test]
+fn test_class_diff_debug_info() {
+    let diff = sample_diff(false);
+
+    let run = diff.methods.iter().find(|method| method.name == "run").unwrap();
+    let MemberChange::Changed(changes) = &run.change else {
+        panic!("unexpected change of run: {:?}", run.change);
+    };
+    assert!(changes.code.is_none());
+    let names = changes.attributes.iter().map(|attribute| attribute.name.as_str());
+    assert_eq!(names.collect::<Vec<_>>(), ["LineNumberTable"]);
+}
+
+#[test]
+fn test_identical_classes() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+    let bytes = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+
+    let diff = ClassDiffer::new().diff_archives([&class_file], [&class_file]);
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string(), "");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_class_diff_json() {
+    let diff = sample_diff(true);
+    let value = serde_json::to_value(&diff).unwrap();
+
+    assert_eq!(value["name"], "DiffSample");
+    let compute = &value["methods"][1];
+    assert_eq!(compute["name"], "compute");
+    let instructions = &compute["change"]["changed"]["code"]["instructions"];
+    assert_eq!(
+        instructions[0],
+        serde_json::json!({"kind": "removed", "index": 1, "instruction": "iconst_2"})
+    );
+    assert_eq!(value["methods"][0]["change"], "added");
+
+    let round_trip: ClassDiff = serde_json::from_value(value).unwrap();
+    assert_eq!(round_trip, diff);
+}
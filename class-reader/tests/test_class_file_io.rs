@@ -0,0 +1,90 @@
+use std::fs::{self, File};
+use std::path::Path;
+
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::class_file_io::MappedClassFile;
+use rsjvm_class_reader::class_file_reader::{ClassFileReader, ClassReaderError};
+
+mod common;
+
+#[test]
+fn test_read_from_stream_and_mapping() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let path = Path::new("target/classes/Switches.class");
+
+    let from_stream = ClassFileReader::read_from(File::open(path).unwrap()).unwrap();
+    let from_path = ClassFileReader::read_path(path).unwrap();
+    assert_eq!(from_stream.this_class, "Switches");
+    assert_eq!(from_path.methods.len(), from_stream.methods.len());
+
+    let mapped = MappedClassFile::open(path).unwrap();
+    assert_eq!(mapped.bytes(), bytes.as_slice());
+    assert_eq!(mapped.lazy().unwrap().this_class().unwrap(), "Switches");
+
+    let too_large = ClassFileReader::read_from_with_limit(bytes.as_slice(), 16).unwrap_err();
+    assert!(matches!(too_large.error(), ClassReaderError::ClassFileTooLarge { .. }));
+
+    let missing = ClassFileReader::read_path("target/classes/Missing.class").unwrap_err();
+    assert!(matches!(missing.error(), ClassReaderError::Io { .. }));
+}
+
+#[test]
+fn test_read_directory() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("LongDoubleConstants.java".to_string()).run().unwrap();
+
+    let root = Path::new("target/test_read_directory");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("nested/deeper")).unwrap();
+    fs::write(root.join("nested/deeper/LongDoubleConstants.class"), &bytes).unwrap();
+    fs::write(root.join("nested/Broken.class"), [0xCA, 0xFE, 0xBA, 0xBE, 0x00]).unwrap();
+    fs::write(root.join("README.txt"), "not a class").unwrap();
+
+    let results = ClassFileReader::read_directory(root).collect::<Vec<_>>();
+    let paths =
+        results.iter().map(|(path, _)| path.strip_prefix(root).unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [Path::new("nested/Broken.class"), Path::new("nested/deeper/LongDoubleConstants.class")]
+    );
+
+    assert!(matches!(
+        results[0].1.as_ref().unwrap_err().error(),
+        ClassReaderError::ReadError { .. }
+    ));
+    assert_eq!(results[1].1.as_ref().unwrap().this_class, "LongDoubleConstants");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_read_directory_with_symlink_cycle() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("LongDoubleConstants.java".to_string()).run().unwrap();
+
+    let root = Path::new("target/test_read_directory_with_symlink_cycle");
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("nested")).unwrap();
+    fs::write(root.join("nested/LongDoubleConstants.class"), &bytes).unwrap();
+    std::os::unix::fs::symlink("..", root.join("nested/parent")).unwrap();
+    std::os::unix::fs::symlink("LongDoubleConstants.class", root.join("nested/Linked.class"))
+        .unwrap();
+
+    let results = ClassFileReader::read_directory(root).collect::<Vec<_>>();
+    let paths =
+        results.iter().map(|(path, _)| path.strip_prefix(root).unwrap()).collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [Path::new("nested/Linked.class"), Path::new("nested/LongDoubleConstants.class")]
+    );
+    assert!(results.iter().all(|(_, result)| result.is_ok()));
+}
@@ -0,0 +1,100 @@
+use common::{JavaCompilerOptions, check_javac_version, compile_java_file, java_home};
+use rsjvm_class_reader::class_hierarchy::ClassHierarchy;
+use rsjvm_class_reader::classpath::Classpath;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+#[test]
+fn test_jdk_hierarchy() {
+    let mut classpath = Classpath::new();
+    classpath.add_path(java_home().unwrap().join("lib/modules")).unwrap();
+    let mut hierarchy = ClassHierarchy::new();
+    for name in
+        ["java.util.ArrayList", "java.util.LinkedList", "java.lang.Integer", "java.lang.Long"]
+    {
+        hierarchy.add_with_supertypes(&mut classpath, name).unwrap();
+    }
+    assert!(hierarchy.missing_classes().is_empty());
+
+    assert_eq!(
+        hierarchy.superclasses("java/util/ArrayList").unwrap(),
+        ["java/util/AbstractList", "java/util/AbstractCollection", "java/lang/Object"]
+    );
+    let supertypes = hierarchy.supertypes("java/util/LinkedList").unwrap();
+    assert!(supertypes.contains("java/util/Deque") && supertypes.contains("java/lang/Iterable"));
+    assert!(hierarchy.is_subtype("java/util/ArrayList", "java/util/Collection").unwrap());
+    assert!(!hierarchy.is_subtype("java/util/ArrayList", "java/util/Deque").unwrap());
+
+    let lcs = |first, second| hierarchy.least_common_superclass(first, second).unwrap();
+    assert_eq!(lcs("java/util/ArrayList", "java/util/LinkedList"), "java/util/AbstractList");
+    assert_eq!(lcs("java/lang/Integer", "java/lang/Long"), "java/lang/Number");
+    assert_eq!(lcs("java/util/ArrayList", "java/lang/Integer"), "java/lang/Object");
+    assert_eq!(lcs("java/util/List", "java/util/ArrayList"), "java/util/List");
+    assert_eq!(lcs("java/util/List", "java/util/Deque"), "java/lang/Object");
+
+    let implementors = hierarchy.implementors("java/util/List");
+    assert!(implementors.contains("java/util/ArrayList"));
+    assert!(implementors.contains("java/util/LinkedList"));
+    assert!(!implementors.contains("java/util/List"));
+    assert!(hierarchy.subtypes("java/lang/Number").contains("java/lang/Integer"));
+
+    let consumer = "(Ljava/util/function/Consumer;)V";
+    let select = |class, name, descriptor| hierarchy.select_method(class, name, descriptor);
+    assert_eq!(
+        select("java/util/ArrayList", "forEach", consumer).unwrap(),
+        Some("java/util/ArrayList")
+    );
+    assert_eq!(
+        select("java/util/LinkedList", "forEach", consumer).unwrap(),
+        Some("java/lang/Iterable")
+    );
+    assert_eq!(
+        select("java/util/LinkedList", "size", "()I").unwrap(),
+        Some("java/util/LinkedList")
+    );
+    assert_eq!(
+        hierarchy.resolve_method("java/util/List", "forEach", consumer).unwrap(),
+        Some("java/lang/Iterable")
+    );
+
+    let overridden = hierarchy.overridden_methods("java/util/ArrayList", "size", "()I").unwrap();
+    assert!(overridden.contains(&"java/util/AbstractCollection"));
+    assert!(overridden.contains(&"java/util/List"));
+    let implementations = hierarchy.implementations("java/util/AbstractList", "size", "()I");
+    assert_eq!(implementations, ["java/util/ArrayList", "java/util/LinkedList"]);
+}
+
+#[test]
+fn test_classpath_hierarchy() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/hierarchy");
+    compile_java_file(Path::new("tests/resources/PermittedSubclasses.java"), &options).unwrap();
+
+    let mut classpath = Classpath::new();
+    classpath.add_path("target/classes/hierarchy").unwrap();
+    let hierarchy = ClassHierarchy::from_classpath(&mut classpath).unwrap();
+
+    assert_eq!(hierarchy.len(), 3);
+    assert_eq!(
+        hierarchy.direct_subtypes("PermittedSubclasses").collect::<Vec<_>>(),
+        ["Subclass", "Subclass2"]
+    );
+    assert_eq!(
+        hierarchy.least_common_superclass("Subclass", "Subclass2").unwrap(),
+        "PermittedSubclasses"
+    );
+    assert_eq!(
+        hierarchy.select_method("Subclass", "function", "()V").unwrap(),
+        Some("PermittedSubclasses")
+    );
+    assert_eq!(
+        hierarchy.superclasses("Subclass").unwrap(),
+        ["PermittedSubclasses", "java/lang/Object"]
+    );
+    assert!(hierarchy.supertypes("java/lang/String").is_err());
+}
@@ -0,0 +1,215 @@
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file_reader::{AttributeLocation, ClassFileReader};
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::class_visitor::{ClassVisitor, MethodHeader};
+use rsjvm_class_reader::instruction::Instruction;
+use rsjvm_class_reader::predefined_attributes::ExceptionHandler;
+
+mod common;
+
+/// Drops every method with the given name and forwards everything else.
+struct RemoveMethod {
+    name: &'static str,
+    removing: bool,
+    writer: ClassFileWriter,
+}
+
+impl ClassVisitor for RemoveMethod {
+    fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+        if self.removing { None } else { Some(&mut self.writer) }
+    }
+
+    fn visit_method(&mut self, header: MethodHeader) {
+        self.removing = header.name == self.name;
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_method(header);
+        }
+    }
+
+    fn visit_method_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_method_end();
+        }
+        self.removing = false;
+    }
+}
+
+/// Counts instructions without building a class file.
+#[derive(Default)]
+struct InstructionCounter {
+    instructions: usize,
+    handlers: usize,
+}
+
+impl ClassVisitor for InstructionCounter {
+    fn visit_instruction(&mut self, _instruction: Instruction, _pc: u32) {
+        self.instructions += 1;
+    }
+
+    fn visit_exception_handler(&mut self, _handler: ExceptionHandler) {
+        self.handlers += 1;
+    }
+}
+
+/// Records the method level events, leaving out the contents of the code.
+#[derive(Default)]
+struct MethodEvents {
+    events: Vec<String>,
+}
+
+impl ClassVisitor for MethodEvents {
+    fn visit_method(&mut self, header: MethodHeader) {
+        self.events.push(format!("method {}", header.name));
+    }
+
+    fn visit_code(&mut self, _max_stack: u16, _max_locals: u16) {
+        self.events.push("code".to_string());
+    }
+
+    fn visit_code_end(&mut self) {
+        self.events.push("code end".to_string());
+    }
+
+    fn visit_method_end(&mut self) {
+        self.events.push("method end".to_string());
+    }
+
+    fn visit_attribute(&mut self, location: &AttributeLocation, attribute: Attribute) {
+        if *location == AttributeLocation::Method {
+            self.events.push(attribute.name().to_string());
+        }
+    }
+}
+
+#[test]
+fn test_write_round_trip_is_byte_identical() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    for java_file in [
+        "IntListControlFlowSingleFunction.java",
+        "LongDoubleConstants.java",
+        "BootstrapMethods.java",
+        "PermittedSubclasses.java",
+    ] {
+        let bytes = CompileConfig::new(java_file.to_string()).run().unwrap();
+        let class_file = ClassFileReader::read_class(&bytes).unwrap();
+
+        let written = ClassFileWriter::write(&class_file).unwrap();
+        assert!(written == bytes, "{} does not round trip", java_file);
+
+        let mut writer = ClassFileWriter::new();
+        ClassFileReader::accept(&bytes, &mut writer).unwrap();
+        assert!(writer.finish().unwrap() == bytes, "{} does not stream", java_file);
+    }
+}
+
+#[test]
+fn test_switches_and_loops_are_decoded() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+
+    let instructions = |name: &str| {
+        let method = class_file.methods.iter().find(|m| m.name == name).unwrap();
+        match method.attributes.iter().find(|a| matches!(a, Attribute::Code(_))) {
+            Some(Attribute::Code(code)) => code.code.clone(),
+            _ => panic!("Code attribute not found in method '{}'", name),
+        }
+    };
+
+    let dense = instructions("dense");
+    assert!(dense.iter().any(|(instruction, _)| matches!(
+        instruction,
+        Instruction::Tableswitch { low: 1, high: 3, offsets, .. } if offsets.len() == 3
+    )));
+
+    let sparse = instructions("sparse");
+    let Some((Instruction::Lookupswitch { pairs, .. }, _)) = sparse
+        .iter()
+        .find(|(instruction, _)| matches!(instruction, Instruction::Lookupswitch { .. }))
+    else {
+        panic!("lookupswitch not found");
+    };
+    assert_eq!(pairs.iter().map(|(key, _)| *key).collect::<Vec<_>>(), [-1000, 7, 1000000]);
+
+    let loop_code = instructions("loop");
+    assert!(loop_code.iter().any(|(instruction, _)| *instruction == Instruction::Newarray(10)));
+    assert!(loop_code.iter().any(|(instruction, _)| matches!(instruction, Instruction::Goto(_))));
+    assert!(ClassFileWriter::write(&class_file).unwrap() == bytes);
+}
+
+#[test]
+fn test_filter_chain_removes_method() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("SumArithmeticExceptionCatch.java".to_string()).run().unwrap();
+
+    let mut filter = RemoveMethod { name: "main", removing: false, writer: ClassFileWriter::new() };
+    ClassFileReader::accept(&bytes, &mut filter).unwrap();
+    let filtered = ClassFileReader::read_class(&filter.writer.finish().unwrap()).unwrap();
+
+    let names = filtered.methods.iter().map(|m| m.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["<init>", "sum"]);
+
+    let mut counter = InstructionCounter::default();
+    ClassFileReader::accept(&bytes, &mut counter).unwrap();
+    let original = ClassFileReader::read_class(&bytes).unwrap();
+    let expected = original
+        .methods
+        .iter()
+        .flat_map(|m| &m.attributes)
+        .filter_map(|a| match a {
+            Attribute::Code(code) => Some(code.code.len()),
+            _ => None,
+        })
+        .sum::<usize>();
+    assert_eq!(counter.instructions, expected);
+    assert_eq!(counter.handlers, 1);
+    assert!(filtered.attributes.iter().any(|a| a.name() == "SourceFile"));
+    assert!(ClassFileWriter::write(&original).unwrap() == bytes);
+}
+
+#[test]
+fn test_method_attributes_arrive_in_file_order() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("ShrinkerSample.java".to_string()).run().unwrap();
+    let mut class_file = ClassFileReader::read_class(&bytes).unwrap();
+    let main = class_file.methods.iter_mut().find(|method| method.name == "main").unwrap();
+    assert_eq!(
+        main.attributes.iter().map(Attribute::name).collect::<Vec<_>>(),
+        ["Code", "Exceptions"]
+    );
+    // Store the code after the other attributes of the method.
+    main.attributes.rotate_left(1);
+    let bytes = ClassFileWriter::write(&class_file).unwrap();
+
+    let main_events = |events: Vec<String>| {
+        let start = events.iter().position(|event| event == "method main").unwrap();
+        let length = events[start..].iter().position(|event| event == "method end").unwrap();
+        events[start..=start + length].to_vec()
+    };
+    let expected = ["method main", "Exceptions", "code", "code end", "method end"];
+
+    let mut streamed = MethodEvents::default();
+    ClassFileReader::accept(&bytes, &mut streamed).unwrap();
+    assert_eq!(main_events(streamed.events), expected);
+
+    let mut accepted = MethodEvents::default();
+    ClassFileReader::read_class(&bytes).unwrap().accept(&mut accepted);
+    assert_eq!(main_events(accepted.events), expected);
+
+    let mut writer = ClassFileWriter::new();
+    ClassFileReader::accept(&bytes, &mut writer).unwrap();
+    assert!(writer.finish().unwrap() == bytes);
+}
@@ -0,0 +1,89 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::Arc;
+
+use common::{CompileConfig, check_javac_version, java_home};
+use rsjvm_class_reader::classpath::{ClassSource, Classpath, DirectorySource};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+mod common;
+
+#[test]
+fn test_classpath_resolution() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let switches = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let constants = CompileConfig::new("LongDoubleConstants.java".to_string()).run().unwrap();
+
+    let directory = "target/test_classpath";
+    let _ = fs::remove_dir_all(directory);
+    fs::create_dir_all(directory).unwrap();
+    fs::write(format!("{}/Switches.class", directory), &switches).unwrap();
+
+    let jar = "target/test_classpath.jar";
+    let mut zip = ZipWriter::new(File::create(jar).unwrap());
+    for (name, bytes) in [
+        ("Switches.class", constants.as_slice()),
+        ("LongDoubleConstants.class", constants.as_slice()),
+        ("META-INF/services/java.sql.Driver", b"com.example.Driver"),
+    ] {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let modules = java_home().unwrap().join("lib/modules");
+    let paths = std::env::join_paths([directory.as_ref(), jar.as_ref(), modules.as_path()]);
+    let mut classpath = Classpath::from_path_list(paths.unwrap()).unwrap();
+    assert_eq!(classpath.len(), 3);
+
+    let shadowed = classpath.load("Switches").unwrap().unwrap();
+    assert_eq!(shadowed.this_class, "Switches");
+    assert!(Arc::ptr_eq(&shadowed, &classpath.load("Switches").unwrap().unwrap()));
+    assert_eq!(
+        classpath.load("LongDoubleConstants").unwrap().unwrap().this_class,
+        "LongDoubleConstants"
+    );
+    assert_eq!(classpath.load("java.util.List").unwrap().unwrap().this_class, "java/util/List");
+    assert!(classpath.load("com/example/Missing").unwrap().is_none());
+
+    let driver = classpath.resource("META-INF/services/java.sql.Driver").unwrap().unwrap();
+    assert_eq!(driver, b"com.example.Driver");
+    assert!(classpath.resource("java/lang/Object.class").unwrap().is_some());
+
+    let names = classpath.class_names().unwrap();
+    assert!(names.contains("Switches") && names.contains("java/lang/Object"));
+}
+
+#[test]
+fn test_directory_resources_stay_inside_root() {
+    let directory = "target/test_classpath_root";
+    let _ = fs::remove_dir_all(directory);
+    fs::create_dir_all(format!("{}/inner/a", directory)).unwrap();
+    fs::write(format!("{}/inner/a/config.txt", directory), b"inside").unwrap();
+    fs::write(format!("{}/secret.txt", directory), b"outside").unwrap();
+
+    let mut source = DirectorySource::new(format!("{}/inner", directory));
+    assert_eq!(source.resource("a/config.txt").unwrap().unwrap(), b"inside");
+    assert!(source.resource("../secret.txt").unwrap().is_none());
+    assert!(source.resource("a/../../secret.txt").unwrap().is_none());
+    assert!(source.class_bytes("../secret").unwrap().is_none());
+    let absolute = fs::canonicalize(format!("{}/secret.txt", directory)).unwrap();
+    assert!(source.resource(absolute.to_str().unwrap()).unwrap().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_directory_class_names_skip_symlink_cycles() {
+    let directory = "target/test_classpath_cycle";
+    let _ = fs::remove_dir_all(directory);
+    fs::create_dir_all(format!("{}/a", directory)).unwrap();
+    fs::write(format!("{}/a/Named.class", directory), b"").unwrap();
+    std::os::unix::fs::symlink("..", format!("{}/a/parent", directory)).unwrap();
+
+    let mut source = DirectorySource::new(directory);
+    assert_eq!(source.class_names().unwrap(), ["a/Named"]);
+}
@@ -52,13 +52,11 @@ fn test_read_code_with_attrs() {
             (Instruction::Astore_1, 7),
             (Instruction::Aload_1, 8),
             (Instruction::Invokeinterface(10, 1), 9),
-            (Instruction::Nop, 13),
             (Instruction::Iflt(14), 14),
             (Instruction::Aload_1, 17),
             (Instruction::Iconst_1, 18),
             (Instruction::Invokestatic(16), 19),
             (Instruction::Invokeinterface(22, 2), 22),
-            (Instruction::Nop, 26),
             (Instruction::Pop, 27),
             (Instruction::Return, 28),
         ],
@@ -0,0 +1,62 @@
+use common::{JavaCompilerOptions, compiled_class_bytes};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::{ClassFileReader, ClassReaderError};
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::code_limits::CodeLimits;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_bytes() -> Vec<u8> {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/code_limits");
+    compiled_class_bytes(Path::new("tests/resources/CodeLimitsSample.java"), &options)
+}
+
+/// The sample with the `max_stack` or `max_locals` of `name` lowered by one.
+fn shrunk(name: &str, stack: bool) -> Vec<u8> {
+    let mut class = ClassFileReader::read_class(&sample_bytes()).unwrap();
+    let method = class.methods.iter_mut().find(|method| method.name == name).unwrap();
+    for attribute in &mut method.attributes {
+        if let Attribute::Code(code) = attribute {
+            match stack {
+                true => code.max_stack -= 1,
+                false => code.max_locals -= 1,
+            }
+        }
+    }
+    ClassFileWriter::write(&class).unwrap()
+}
+
+#[test]
+fn test_limits_match_javac() {
+    let class: ClassFile = ClassFileReader::read_class(&sample_bytes()).unwrap();
+    for method in &class.methods {
+        let code = method.code().unwrap();
+        let limits = CodeLimits::compute(&class.constant_pool, method).unwrap();
+        assert_eq!(limits.max_stack, code.max_stack, "max_stack of {}", method.name);
+        assert_eq!(limits.max_locals, code.max_locals, "max_locals of {}", method.name);
+        assert!(limits.fit(code));
+    }
+}
+
+#[test]
+fn test_checked_reader() {
+    assert!(ClassFileReader::read_class_checked(&sample_bytes()).is_ok());
+
+    let bytes = shrunk("wide", true);
+    assert!(ClassFileReader::read_class(&bytes).is_ok());
+    let err = ClassFileReader::read_class_checked(&bytes).unwrap_err();
+    assert!(matches!(
+        err.error(),
+        ClassReaderError::MaxStackTooSmall { method, declared: 5, required: 6, .. } if method == "wide"
+    ));
+
+    let err = ClassFileReader::read_class_checked(&shrunk("branches", false)).unwrap_err();
+    assert!(matches!(
+        err.error(),
+        ClassReaderError::MaxLocalsTooSmall { method, .. } if method == "branches"
+    ));
+}
@@ -0,0 +1,60 @@
+use common::{JavaCompilerOptions, check_javac_version, compile_java_file};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::code_view::CodeView;
+use rsjvm_class_reader::instruction::Instruction;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> ClassFile {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+    let mut options = JavaCompilerOptions::new();
+    options.use_g().use_output_dir("target/classes/code_view");
+    compile_java_file(Path::new("tests/resources/CodeViewSample.java"), &options).unwrap();
+    let bytes = fs::read("target/classes/code_view/CodeViewSample.class").unwrap();
+    ClassFileReader::read_class(&bytes).unwrap()
+}
+
+#[test]
+fn test_code_view() {
+    let class = sample_class();
+    let method = class.methods.iter().find(|method| method.name == "names").unwrap();
+    let code = method.code().unwrap();
+    let view = CodeView::new(code);
+
+    let (last, last_pc) = code.code.last().unwrap();
+    assert_eq!(view.code_length(), last_pc + last.length(*last_pc));
+    for (position, (instruction, pc)) in code.code.iter().enumerate() {
+        assert_eq!(view.position(*pc), Some(position));
+        assert_eq!(view.instruction_at(*pc), Some(instruction));
+        assert_eq!(view.next_pc(*pc), code.code.get(position + 1).map(|(_, pc)| *pc));
+    }
+
+    // `names.add(...)` is on line 8 of the source, the `return` on line 10.
+    let add = code
+        .code
+        .iter()
+        .find(|(instruction, _)| matches!(instruction, Instruction::Invokeinterface(..)))
+        .unwrap()
+        .1;
+    assert_eq!(view.line_at(add), Some(8));
+    assert_eq!(view.line_at(*last_pc), Some(10));
+
+    let locals = view.local_variables_at(&class.constant_pool, add);
+    let names = locals.iter().map(|variable| variable.name).collect::<Vec<_>>();
+    assert_eq!(names, ["count", "names", "i"]);
+    assert_eq!(locals[1].descriptor, "Ljava/util/List;");
+    assert_eq!(locals[1].signature, Some("Ljava/util/List<Ljava/lang/String;>;"));
+    assert_eq!(locals[2].signature, None);
+
+    let returned = view.local_variables_at(&class.constant_pool, *last_pc);
+    assert_eq!(
+        returned.iter().map(|variable| variable.name).collect::<Vec<_>>(),
+        ["count", "names"]
+    );
+}
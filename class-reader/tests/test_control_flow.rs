@@ -0,0 +1,116 @@
+use common::{JavaCompilerOptions, compiled_class};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::control_flow::{BlockId, ControlFlowGraph, EdgeKind};
+use rsjvm_class_reader::predefined_attributes::Code;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/control_flow");
+    compiled_class(Path::new("tests/resources/ControlFlowSample.java"), &options)
+}
+
+fn method_code(name: &str) -> &'static Code {
+    let method = sample_class().methods.iter().find(|method| method.name == name).unwrap();
+    method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        })
+        .unwrap()
+}
+
+fn edges(graph: &ControlFlowGraph) -> Vec<(BlockId, BlockId, EdgeKind)> {
+    graph.edges.iter().map(|edge| (edge.from, edge.to, edge.kind)).collect()
+}
+
+fn block_starts(graph: &ControlFlowGraph) -> Vec<u32> {
+    graph.blocks.iter().map(|block| block.start_pc).collect()
+}
+
+#[test]
+fn test_loop() {
+    let code = method_code("loop");
+    let graph = ControlFlowGraph::new(code).unwrap();
+
+    assert_eq!(block_starts(&graph), [0, 4, 9, 19]);
+    assert_eq!(
+        edges(&graph),
+        [
+            (0, 1, EdgeKind::FallThrough),
+            (1, 3, EdgeKind::Jump),
+            (1, 2, EdgeKind::FallThrough),
+            (2, 1, EdgeKind::Jump),
+        ]
+    );
+    assert_eq!(graph.predecessors(1), [0, 2]);
+    assert_eq!(graph.successors(1), [3, 2]);
+    assert_eq!(graph.reverse_postorder(), [0, 1, 2, 3]);
+
+    let dot = graph.to_dot(code);
+    assert!(dot.contains("    b2 [label=\"B2 (line 6, 5)\\l9: Iload_1\\l"), "{}", dot);
+    assert!(dot.contains("    b2 -> b1;\n"));
+}
+
+#[test]
+fn test_switch() {
+    let graph = ControlFlowGraph::new(method_code("classify")).unwrap();
+
+    assert_eq!(block_starts(&graph), [0, 28, 31, 34, 37]);
+    assert_eq!(
+        edges(&graph),
+        [
+            (0, 1, EdgeKind::Switch(1)),
+            (0, 2, EdgeKind::Switch(2)),
+            (0, 3, EdgeKind::Switch(3)),
+            (0, 4, EdgeKind::SwitchDefault),
+        ]
+    );
+    assert_eq!(graph.block_at(35), Some(3));
+}
+
+#[test]
+fn test_switch_up_to_max_key() {
+    let graph = ControlFlowGraph::new(method_code("classifyLargest")).unwrap();
+
+    let keys = graph.edges.iter().map(|edge| edge.kind).collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            EdgeKind::Switch(i32::MAX - 2),
+            EdgeKind::Switch(i32::MAX - 1),
+            EdgeKind::Switch(i32::MAX),
+            EdgeKind::SwitchDefault,
+        ]
+    );
+}
+
+#[test]
+fn test_exception_handlers() {
+    let code = method_code("parse");
+    let graph = ControlFlowGraph::new(code).unwrap();
+    let number_format_exception = code.exception_table[0].catch_type;
+
+    assert_eq!(block_starts(&graph), [0, 5, 15, 18, 28]);
+    assert_eq!(
+        edges(&graph),
+        [
+            (0, 1, EdgeKind::FallThrough),
+            (0, 2, EdgeKind::Exception { catch_type: number_format_exception }),
+            (0, 4, EdgeKind::Exception { catch_type: 0 }),
+            (2, 3, EdgeKind::FallThrough),
+            (2, 4, EdgeKind::Exception { catch_type: 0 }),
+        ]
+    );
+    assert_eq!(graph.predecessors(4), [0, 2]);
+    assert_eq!(graph.reverse_postorder(), [0, 2, 4, 3, 1]);
+
+    let dot = graph.to_dot(code);
+    assert!(dot.contains("    b0 -> b4 [label=\"any\", style=dashed];\n"));
+}
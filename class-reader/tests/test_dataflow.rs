@@ -0,0 +1,118 @@
+use common::{JavaCompilerOptions, compiled_class};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::control_flow::ControlFlowGraph;
+use rsjvm_class_reader::dataflow::{
+    Const, ConstantPropagation, Definition, Liveness, Nullability, Nullness, ReachingDefinitions,
+    ValueType, analyze_frames, solve,
+};
+use rsjvm_class_reader::instruction::Instruction;
+use rsjvm_class_reader::method::Method;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/dataflow");
+    compiled_class(Path::new("tests/resources/DataflowSample.java"), &options)
+}
+
+fn method(name: &str) -> &'static Method {
+    sample_class().methods.iter().find(|method| method.name == name).unwrap()
+}
+
+#[test]
+fn test_liveness() {
+    let code = method("liveness").code().unwrap();
+    let graph = ControlFlowGraph::new(code).unwrap();
+    let solution = solve(Liveness, code, &graph);
+
+    assert_eq!(solution.entry(graph.entry()), &BTreeSet::from([0, 1]));
+    // y is dead once multiplied, and the store of the product is never read.
+    assert_eq!(solution.before(1), Some(BTreeSet::from([0])));
+    assert_eq!(solution.after(3), Some(BTreeSet::from([0])));
+    assert_eq!(solution.after(7), Some(BTreeSet::new()));
+}
+
+#[test]
+fn test_reaching_definitions() {
+    let method = method("constants");
+    let code = method.code().unwrap();
+    let graph = ControlFlowGraph::new(code).unwrap();
+    let solution = solve(ReachingDefinitions::new(method), code, &graph);
+
+    let definitions = solution.before(24).unwrap();
+    assert_eq!(
+        definitions,
+        BTreeSet::from([
+            Definition { local: 0, pc: None },
+            Definition { local: 1, pc: Some(2) },
+            Definition { local: 2, pc: Some(6) },
+            Definition { local: 3, pc: Some(6) },
+            Definition { local: 4, pc: Some(11) },
+            Definition { local: 5, pc: Some(22) },
+        ])
+    );
+    assert_eq!(solution.before(0), Some(BTreeSet::from([Definition { local: 0, pc: None }])));
+}
+
+#[test]
+fn test_constant_propagation() {
+    let frames =
+        analyze_frames(sample_class(), method("constants"), &mut ConstantPropagation).unwrap();
+
+    let frame = frames.before(13).unwrap();
+    assert_eq!(frame.local(1), Some(&Const::Int(6)));
+    assert_eq!(frame.local(2), Some(&Const::Long(7)));
+    assert_eq!(frame.locals[2].value_type, ValueType::Long);
+    assert_eq!(frame.locals[3].value_type, ValueType::Top);
+    assert_eq!(frame.local(4), Some(&Const::Int(42)));
+    // The two arms of the conditional push different constants.
+    assert_eq!(frames.before(22).unwrap().peek(0), Some(&Const::Unknown));
+    assert_eq!(frames.before(22).unwrap().local(4), Some(&Const::Int(42)));
+    assert_eq!(frames.before(28).unwrap().peek(1), Some(&Const::Int(42)));
+}
+
+#[test]
+fn test_nullness() {
+    let method = method("nullness");
+    let frames = analyze_frames(sample_class(), method, &mut Nullness).unwrap();
+
+    let frame = frames.before(10).unwrap();
+    assert_eq!(frame.local(0), Some(&Nullability::Unknown));
+    assert_eq!(frame.local(1), Some(&Nullability::Null));
+    assert_eq!(frame.local(2), Some(&Nullability::NonNull));
+    // s is null or the parameter where the branch merges.
+    let frame = frames.before(16).unwrap();
+    assert_eq!(frame.local(1), Some(&Nullability::Unknown));
+    assert_eq!(frame.local(2), Some(&Nullability::NonNull));
+    let (instruction, pc) = method.code().unwrap().code.last().unwrap();
+    assert_eq!(instruction, &Instruction::Areturn);
+    assert_eq!(frames.before(*pc).unwrap().peek(0), Some(&Nullability::Unknown));
+    assert_eq!(frames.before(27).unwrap().stack.len(), 0);
+}
+
+#[test]
+fn test_exception_handlers() {
+    let method = method("guarded");
+    let code = method.code().unwrap();
+    let graph = ControlFlowGraph::new(code).unwrap();
+
+    // The handler reads the initial result, so the store in the try block does not kill it.
+    let liveness = solve(Liveness, code, &graph);
+    assert_eq!(liveness.before(2), Some(BTreeSet::from([0, 1])));
+    assert_eq!(liveness.before(10), Some(BTreeSet::from([1])));
+
+    let definitions = solve(ReachingDefinitions::new(method), code, &graph);
+    let result = definitions.before(15).unwrap().into_iter().filter(|d| d.local == 1);
+    assert_eq!(result.map(|d| d.pc).collect::<Vec<_>>(), [Some(1), Some(6)]);
+
+    let frames = analyze_frames(sample_class(), method, &mut ConstantPropagation).unwrap();
+    assert_eq!(frames.before(10).unwrap().local(1), Some(&Const::Int(0)));
+    assert_eq!(frames.before(15).unwrap().local(1), Some(&Const::Unknown));
+    let frames = analyze_frames(sample_class(), method, &mut Nullness).unwrap();
+    assert_eq!(frames.before(10).unwrap().peek(0), Some(&Nullability::NonNull));
+    assert_eq!(frames.before(11).unwrap().local(2), Some(&Nullability::NonNull));
+}
@@ -0,0 +1,155 @@
+use common::{JavaCompilerOptions, compiled_class};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::decompiler::{decompile, decompile_method};
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_g().use_output_dir("target/classes/decompiler");
+    compiled_class(Path::new("tests/resources/DecompilerSample.java"), &options)
+}
+
+fn method(name: &str) -> String {
+    let class = sample_class();
+    let method = class.methods.iter().find(|method| method.name == name).unwrap();
+    decompile_method(class, method).unwrap()
+}
+
+fn assert_contains(source: &str, expected: &[&str]) {
+    for line in expected {
+        assert!(source.contains(line), "missing `{}` in:\n{}", line, source);
+    }
+}
+
+#[test]
+fn test_class_declaration() {
+    let source = decompile(sample_class());
+    assert_contains(
+        &source,
+        &[
+            "public class DecompilerSample<T extends Comparable<T>> {",
+            "    private final java.util.List<T> items;",
+            "    static final String GREETING = \"hello\";",
+            "    public DecompilerSample(int count) {",
+        ],
+    );
+    assert!(!source.contains("lambda$"));
+    assert!(!source.contains("Could not decompile"));
+    assert!(!source.contains("goto"));
+}
+
+#[test]
+fn test_conditions() {
+    assert_eq!(
+        method("max"),
+        "static int max(int a, int b) {\n    if (a > b) {\n        return a;\n    }\n    return b;\n}\n"
+    );
+    assert_contains(&method("inRange"), &["return value >= low && value <= high;"]);
+    assert_contains(&method("classify"), &["if (value < 0 || value > 100) {", "return \"out\";"]);
+    assert_contains(&method("sign"), &["return value < 0 ? -1 : 1;"]);
+}
+
+#[test]
+fn test_loops() {
+    assert_contains(
+        &method("sum"),
+        &["int total = 0;", "while (i < values.length) {", "total += values[i];", "i++;"],
+    );
+    assert_contains(&method("countDown"), &["while (n > 0) {", "n--;", "steps++;"]);
+    assert_contains(&method("digits"), &["do {", "n /= 10;", "} while (n != 0);"]);
+    assert_contains(
+        &method("findPair"),
+        &["loop4: while (i < grid.length) {", "if (grid[i][j] == target) {", "break loop4;"],
+    );
+    assert_contains(
+        &method("largest"),
+        &["T best = null;", "if (best == null || item.compareTo(best) > 0) {", "return best;"],
+    );
+}
+
+#[test]
+fn test_switch() {
+    let source = method("name");
+    assert_contains(
+        &source,
+        &[
+            "String result;",
+            "switch (day) {",
+            "case 1:",
+            "result = \"Monday\";",
+            "case 6:\n        case 7:\n            result = \"Weekend\";",
+            "default:",
+            "return result;",
+        ],
+    );
+    assert!(!source.contains("case 3:"));
+}
+
+#[test]
+fn test_exceptions() {
+    assert_contains(
+        &method("parse"),
+        &[
+            "try {",
+            "return Integer.parseInt(text);",
+            "} catch (NumberFormatException e) {",
+            "return -1;",
+        ],
+    );
+    let source = method("guarded");
+    assert_contains(
+        &source,
+        &[
+            "this.count = Integer.parseInt(text);",
+            "} catch (NumberFormatException | NullPointerException e) {",
+            "this.count = 0;",
+            "} finally {",
+            "this.count++;",
+        ],
+    );
+    assert_eq!(source.matches("this.count++;").count(), 1);
+}
+
+#[test]
+fn test_returns_through_finally() {
+    let source = method("attempt");
+    assert_contains(&source, &["try {", "return (Integer) task.get();", "} finally {"]);
+    assert_eq!(source.matches("log.add(\"done\");").count(), 1, "{}", source);
+}
+
+#[test]
+fn test_synchronized() {
+    let source = method("locked");
+    assert_contains(
+        &source,
+        &["synchronized (this.items) {", "this.count += delta;", "return this.count;"],
+    );
+    assert_contains(&method("shared"), &["synchronized (lock) {", "log.add(\"entered\");"]);
+    for name in ["locked", "shared"] {
+        let source = method(name);
+        assert!(!source.contains("monitor") && !source.contains("try"), "{}", source);
+    }
+}
+
+#[test]
+fn test_invokedynamic() {
+    assert_contains(
+        &method("adder"),
+        &["Function<Integer, Integer> adder(int amount)", "return x -> x + amount;"],
+    );
+    assert_contains(&method("factory"), &["return java.util.ArrayList::new;"]);
+    assert_contains(&method("describe"), &["return \"Name: \" + name + \", age: \" + age;"]);
+}
+
+#[test]
+fn test_arrays_and_fields() {
+    assert_contains(&method("primes"), &["int[] values = new int[] {2, 3, 5, 7};"]);
+    assert_contains(&method("add"), &["void add(T item) {", "this.items.add(item);"]);
+    assert_contains(
+        &method("<init>"),
+        &["this.items = new java.util.ArrayList();", "this.count = count;"],
+    );
+}
@@ -0,0 +1,124 @@
+use common::{JavaCompilerOptions, compiled_classes};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::dependency_analysis::{
+    DependencyAnalyzer, DependencyReport, DependencyRules, Granularity, ReferenceKind, RulesError,
+};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_classes() -> &'static [ClassFile] {
+    let mut options = JavaCompilerOptions::new();
+    options
+        .custom_flag("-sourcepath")
+        .custom_flag("tests/resources/dependencies")
+        .use_output_dir("target/classes/dependencies");
+    compiled_classes(Path::new("tests/resources/dependencies/deps/app/App.java"), &options)
+}
+
+/// The classes of `deps/app` are in `app.jar`, the others in `lib.jar`.
+fn sample_report() -> DependencyReport {
+    let mut analyzer = DependencyAnalyzer::new();
+    analyzer.exclude("java/");
+    for class in sample_classes() {
+        let archive = match class.this_class.starts_with("deps/app/") {
+            true => "app.jar",
+            false => "lib.jar",
+        };
+        analyzer.add_class(class, archive);
+    }
+    analyzer.analyze()
+}
+
+#[test]
+fn test_class_dependencies() {
+    let report = sample_report();
+    let app = report.class("deps/app/App").unwrap();
+    let kinds = |name: &str| app.dependencies[name].iter().copied().collect::<Vec<_>>();
+
+    assert_eq!(
+        app.dependencies.keys().map(String::as_str).collect::<Vec<_>>(),
+        [
+            "deps/err/AppException",
+            "deps/err/RetryException",
+            "deps/meta/Marker",
+            "deps/model/Item",
+            "deps/model/Order",
+            "deps/util/Strings",
+        ]
+    );
+    assert_eq!(kinds("deps/err/AppException"), [ReferenceKind::Constant, ReferenceKind::Exception]);
+    assert_eq!(
+        kinds("deps/err/RetryException"),
+        [ReferenceKind::Constant, ReferenceKind::Exception]
+    );
+    assert_eq!(kinds("deps/meta/Marker"), [ReferenceKind::Annotation]);
+    assert_eq!(kinds("deps/model/Item"), [ReferenceKind::Annotation]);
+    assert_eq!(kinds("deps/model/Order"), [ReferenceKind::Signature]);
+    assert_eq!(kinds("deps/util/Strings"), [ReferenceKind::Constant]);
+    assert_eq!(report.archive_of("deps/util/Strings"), "lib.jar");
+    assert_eq!(report.archive_of("java/lang/String"), "not found");
+}
+
+#[test]
+fn test_package_graph_and_cycles() {
+    let report = sample_report();
+    let graph = report.graph(Granularity::Package);
+
+    assert_eq!(
+        graph.dependencies("deps/app"),
+        ["deps/err", "deps/meta", "deps/model", "deps/util"]
+    );
+    assert_eq!(graph.dependencies("deps/util"), ["deps/app"]);
+    assert_eq!(report.package_cycles(), [["deps/app", "deps/util"]]);
+    assert!(graph.to_string().contains("deps/util -> deps/app\n"));
+    assert!(graph.to_dot().contains("    \"deps/app\" -> \"deps/util\";\n"));
+
+    let archives = report.graph(Granularity::Archive);
+    assert_eq!(archives.nodes, BTreeSet::from(["app.jar".to_string(), "lib.jar".to_string()]));
+    assert_eq!(archives.cycles(), [["app.jar", "lib.jar"]]);
+}
+
+#[test]
+fn test_dependency_rules() {
+    let rules = DependencyRules::parse(
+        "# Utilities must not depend on the application...\n\
+         deny deps.util.** -> deps.app.**\n\
+         deny deps/app/* -> deps/model/*\n\
+         \n\
+         # ...but annotations may refer to the model.\n\
+         allow deps.app.App -> deps.model.Item\n",
+    )
+    .unwrap();
+    let violations = rules.check(&sample_report());
+
+    assert_eq!(
+        violations.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "deps/app/App -> deps/model/Order violates `deny deps/app/* -> deps/model/*`",
+            "deps/util/Strings -> deps/app/Config violates `deny deps.util.** -> deps.app.**`",
+        ]
+    );
+
+    let error = DependencyRules::parse("deny a.**\n").unwrap_err();
+    assert!(matches!(error, RulesError::Syntax { line: 1, .. }));
+    let error = DependencyRules::parse("\nforbid a -> b\n").unwrap_err();
+    assert!(matches!(error, RulesError::Syntax { line: 2, .. }));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_dependency_report_json() {
+    let report = sample_report();
+    let value = serde_json::to_value(&report).unwrap();
+    let app = value["classes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|class| class["name"] == "deps/app/App")
+        .unwrap();
+    assert_eq!(app["archive"], "app.jar");
+    assert_eq!(app["dependencies"]["deps/model/Order"], serde_json::json!(["signature"]));
+}
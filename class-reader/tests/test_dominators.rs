@@ -0,0 +1,101 @@
+use common::{JavaCompilerOptions, compiled_class};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::control_flow::ControlFlowGraph;
+use rsjvm_class_reader::dominators::DominatorTree;
+use rsjvm_class_reader::loops::LoopNest;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/dominators");
+    compiled_class(Path::new("tests/resources/ControlFlowSample.java"), &options)
+}
+
+fn method_graph(name: &str) -> ControlFlowGraph {
+    let method = sample_class().methods.iter().find(|method| method.name == name).unwrap();
+    let code = method
+        .attributes
+        .iter()
+        .find_map(|attribute| match attribute {
+            Attribute::Code(code) => Some(code),
+            _ => None,
+        })
+        .unwrap();
+    ControlFlowGraph::new(code).unwrap()
+}
+
+fn immediate_dominators(tree: &DominatorTree, blocks: usize) -> Vec<Option<usize>> {
+    (0..blocks).map(|block| tree.immediate_dominator(block)).collect()
+}
+
+#[test]
+fn test_dominators() {
+    // Blocks of `nested`: 0 entry, 1 outer condition, 2 inner init, 3 inner condition,
+    // 4 if, 5 sum, 6 inner increment, 7 outer increment, 8 return.
+    let graph = method_graph("nested");
+    let dominators = DominatorTree::new(&graph);
+
+    assert_eq!(
+        immediate_dominators(&dominators, graph.len()),
+        [None, Some(0), Some(1), Some(2), Some(3), Some(4), Some(4), Some(3), Some(1)]
+    );
+    assert!(dominators.dominates(1, 6));
+    assert!(!dominators.dominates(5, 6));
+    assert!(!dominators.strictly_dominates(3, 3));
+    assert_eq!(dominators.dominators(5), [4, 3, 2, 1, 0]);
+    assert_eq!(dominators.children(1), [2, 8]);
+
+    let frontiers = dominators.dominance_frontiers(&graph);
+    let expected: [&[usize]; 9] = [&[], &[1], &[1], &[1, 3], &[3], &[6], &[3], &[1], &[]];
+    assert_eq!(frontiers, expected.map(|blocks| blocks.iter().copied().collect::<BTreeSet<_>>()));
+}
+
+#[test]
+fn test_post_dominators() {
+    let graph = method_graph("nested");
+    let post_dominators = DominatorTree::post_dominators(&graph);
+
+    assert_eq!(
+        immediate_dominators(&post_dominators, graph.len()),
+        [Some(1), Some(8), Some(3), Some(7), Some(6), Some(6), Some(3), Some(1), None]
+    );
+    assert_eq!(post_dominators.roots(), [8]);
+    assert!(post_dominators.dominates(1, 5));
+
+    // The increment of `sum` is control dependent on the `if`.
+    let frontiers = post_dominators.dominance_frontiers(&graph);
+    assert_eq!(frontiers[5], BTreeSet::from([4]));
+
+    let switch = method_graph("classify");
+    let post_dominators = DominatorTree::post_dominators(&switch);
+    assert_eq!(post_dominators.immediate_dominator(0), None);
+    assert_eq!(post_dominators.roots(), [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_loop_nest() {
+    let graph = method_graph("nested");
+    let loops = LoopNest::new(&graph, &DominatorTree::new(&graph));
+
+    assert!(loops.is_reducible());
+    let found = loops
+        .loops
+        .iter()
+        .map(|found| (found.header, found.latches.clone(), found.parent, found.depth))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [(1, vec![7], None, 1), (3, vec![6], Some(0), 2)]);
+    assert_eq!(loops.loops[1].blocks, BTreeSet::from([3, 4, 5, 6]));
+    assert_eq!(loops.loop_depth(5), 2);
+    assert_eq!(loops.loop_depth(7), 1);
+    assert_eq!(loops.loop_depth(8), 0);
+    assert_eq!(loops.innermost_loop(2), Some(0));
+    assert_eq!(loops.max_depth(), 2);
+
+    let straight = method_graph("classify");
+    assert!(LoopNest::new(&straight, &DominatorTree::new(&straight)).loops.is_empty());
+}
@@ -0,0 +1,49 @@
+use common::{JavaCompilerOptions, check_javac_version, compile_java_file};
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use std::collections::BTreeSet;
+use std::fs;
+
+#[allow(dead_code)]
+mod common;
+
+#[test]
+fn test_lengths_and_stack_effects_of_compiled_fixtures() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+    let output_dir = "target/classes/instruction";
+    let _ = fs::remove_dir_all(output_dir);
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir(output_dir);
+    let mut sources = fs::read_dir("tests/resources")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("java".as_ref()))
+        .collect::<Vec<_>>();
+    sources.sort();
+    for source in &sources {
+        compile_java_file(source, &options).unwrap();
+    }
+
+    let mut mnemonics = BTreeSet::new();
+    for entry in fs::read_dir(output_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let class = ClassFileReader::read_class(&fs::read(&path).unwrap()).unwrap();
+        for method in &class.methods {
+            let Some(code) = method.code() else {
+                continue;
+            };
+            let location = |pc: u32| format!("{}.{} at pc {}", class.this_class, method.name, pc);
+            for pair in code.code.windows(2) {
+                let ((instruction, pc), (_, next_pc)) = (&pair[0], &pair[1]);
+                assert_eq!(next_pc - pc, instruction.length(*pc), "{}", location(*pc));
+            }
+            for (instruction, pc) in &code.code {
+                let effect = instruction.stack_effect(&class.constant_pool);
+                assert!(effect.is_some(), "{:?} in {}", instruction, location(*pc));
+                mnemonics.insert(instruction.mnemonic());
+            }
+        }
+    }
+    assert!(sources.len() > 10 && mnemonics.len() > 100, "{:?}", mnemonics);
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{Cursor, Write};
+
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::jar_file::JarFile;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+mod common;
+
+fn write_jar<W: Write + std::io::Seek>(writer: W, entries: &[(&str, &[u8])]) -> W {
+    let mut zip = ZipWriter::new(writer);
+    for (name, bytes) in entries {
+        zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(bytes).unwrap();
+    }
+    zip.finish().unwrap()
+}
+
+#[test]
+fn test_multi_release_and_nested_jars() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let switches = CompileConfig::new("Switches.java".to_string()).run().unwrap();
+    let constants = CompileConfig::new("LongDoubleConstants.java".to_string()).run().unwrap();
+
+    let inner = write_jar(
+        Cursor::new(Vec::new()),
+        &[("com/example/LongDoubleConstants.class", &constants)],
+    )
+    .into_inner();
+    let manifest = b"Manifest-Version: 1.0\r\nMain-Class: Switches\r\nMulti-Release: true\r\n\r\n";
+    let path = "target/test_multi_release.jar";
+    write_jar(
+        File::create(path).unwrap(),
+        &[
+            ("META-INF/MANIFEST.MF", manifest),
+            ("META-INF/versions/11/Switches.class", &constants),
+            ("Switches.class", &switches),
+            ("META-INF/versions/21/Switches.class", b"not a class"),
+            ("BOOT-INF/lib/inner.jar", &inner),
+            ("README.txt", b"not a class"),
+        ],
+    );
+
+    let mut jar = JarFile::open(path).unwrap();
+    assert_eq!(jar.manifest().unwrap().main_class(), Some("Switches"));
+    assert_eq!(jar.release(), None);
+    assert_eq!(jar.class_names(), ["Switches"]);
+    assert_eq!(jar.read_class("Switches").unwrap().unwrap().this_class, "Switches");
+    assert!(jar.read_class("Missing").unwrap().is_none());
+
+    let mut jar = JarFile::with_release(File::open(path).unwrap(), 17).unwrap();
+    assert_eq!(jar.release(), Some(17));
+    assert_eq!(jar.read_class("Switches").unwrap().unwrap().this_class, "LongDoubleConstants");
+
+    let mut jar = JarFile::with_release(File::open(path).unwrap(), 21).unwrap();
+    assert!(jar.read_class("Switches").is_err());
+
+    assert_eq!(jar.nested_jars(), ["BOOT-INF/lib/inner.jar"]);
+    let mut nested = jar.open_nested("BOOT-INF/lib/inner.jar").unwrap();
+    assert!(nested.contains("com.example.LongDoubleConstants"));
+    let classes = nested.classes().collect::<Vec<_>>();
+    assert_eq!(classes.len(), 1);
+    assert_eq!(classes[0].0, "com/example/LongDoubleConstants");
+    assert_eq!(classes[0].1.as_ref().unwrap().this_class, "LongDoubleConstants");
+}
@@ -0,0 +1,79 @@
+use std::process::Command;
+
+use common::java_home;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::jimage::JImage;
+use rsjvm_class_reader::jmod_file::JmodFile;
+
+#[allow(dead_code)]
+mod common;
+
+#[test]
+fn test_runtime_image() {
+    let image = JImage::open(java_home().unwrap().join("lib/modules")).unwrap();
+
+    assert_eq!(image.version().0, 1);
+    assert!(image.modules().any(|module| module == "java.base"));
+    assert!(image.class_names("java.base").iter().any(|name| name == "java/lang/Object"));
+    for name in image.class_names("java.base") {
+        assert_eq!(image.module_of(name).unwrap().as_deref(), Some("java.base"), "{}", name);
+    }
+    assert_eq!(image.module_of("java.sql.Connection").unwrap().as_deref(), Some("java.sql"));
+
+    let location = image.find("/java.base/java/lang/Object.class").unwrap().unwrap();
+    assert_eq!(location.class_name().as_deref(), Some("java/lang/Object"));
+    assert!(image.find("/java.base/java/lang/Missing.class").unwrap().is_none());
+
+    let object = image.read_class("java.lang.Object").unwrap().unwrap();
+    assert_eq!(object.this_class, "java/lang/Object");
+    assert_eq!(object.super_class, None);
+    assert!(image.read_class("java/lang/Missing").unwrap().is_none());
+
+    let module_info = image.find("/java.base/module-info.class").unwrap().unwrap();
+    let module_info = image.resource(&module_info).unwrap();
+    let module_info = ClassFileReader::read_class(&module_info);
+    assert_eq!(module_info.unwrap().module_name(), Some("java.base"));
+}
+
+#[test]
+fn test_compressed_runtime_images() {
+    let java_home = java_home().unwrap();
+    let image = JImage::open(java_home.join("lib/modules")).unwrap();
+    let expected = image.class_bytes("java/lang/String").unwrap().unwrap();
+
+    for level in ["1", "2"] {
+        let output = format!("target/jlink-compress-{}", level);
+        let _ = std::fs::remove_dir_all(&output);
+        let status = Command::new(java_home.join("bin/jlink"))
+            .args(["--add-modules", "java.base", "--compress", level, "--output", &output])
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let compressed = JImage::open(format!("{}/lib/modules", output)).unwrap();
+        let location = compressed.find("/java.base/java/lang/String.class").unwrap().unwrap();
+        assert_ne!(location.compressed_size, 0);
+        assert_eq!(compressed.class_bytes("java.lang.String").unwrap().unwrap(), expected);
+        for name in compressed.class_names("java.base").iter().take(200) {
+            compressed.read_class(name).unwrap().unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_jmod() {
+    let java_home = java_home().unwrap();
+    let mut jmod = JmodFile::open(java_home.join("jmods/java.sql.jmod")).unwrap();
+
+    assert_eq!(jmod.module_name(), Some("java.sql"));
+    assert!(jmod.contains("java.sql.Connection"));
+    let image = JImage::open(java_home.join("lib/modules")).unwrap();
+    assert_eq!(
+        jmod.class_bytes("java/sql/Connection").unwrap(),
+        image.class_bytes("java/sql/Connection").unwrap()
+    );
+
+    let classes = jmod.classes().collect::<Vec<_>>();
+    assert_eq!(classes.len(), jmod.len());
+    assert!(classes.iter().all(|(_, class)| class.is_ok()));
+}
@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::lazy_class_file::LazyClassFile;
+
+mod common;
+
+#[test]
+fn test_lazy_method_code_matches_eager() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let config = CompileConfig::new("IntListControlFlowSingleFunction.java".to_string());
+    let bytes = config.run().unwrap();
+
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+    let lazy = LazyClassFile::parse(&bytes).unwrap();
+
+    assert!(matches!(
+        lazy.this_class().unwrap(),
+        Cow::Borrowed("IntListControlFlowSingleFunction")
+    ));
+    assert_eq!(lazy.super_class().unwrap().as_deref(), Some("java/lang/Object"));
+    assert_eq!(lazy.methods().len(), class_file.methods.len());
+
+    let expected_code = class_file
+        .methods
+        .iter()
+        .find(|m| m.name == "function")
+        .and_then(|m| m.attributes.iter().find(|a| matches!(a, Attribute::Code(_))))
+        .expect("Code attribute not found in method 'function'");
+
+    let method =
+        lazy.method("function", Some("()V")).unwrap().expect("Method 'function' not found");
+    let code = method.code().unwrap().expect("Lazy method has no code");
+
+    assert_eq!(&Attribute::Code(code), expected_code);
+    assert!(lazy.method("function", Some("(I)V")).unwrap().is_none());
+}
+
+#[test]
+fn test_lazy_constant_pool_with_wide_constants() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let config = CompileConfig::new("LongDoubleConstants.java".to_string());
+    let bytes = config.run().unwrap();
+
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+    let lazy = LazyClassFile::parse(&bytes).unwrap();
+
+    assert_eq!(lazy.constant_pool().len(), class_file.constant_pool.constants.len());
+    assert_eq!(
+        lazy.constant_pool().materialize().unwrap().constants,
+        class_file.constant_pool.constants
+    );
+
+    let field_names = lazy.fields().map(|f| f.name().unwrap()).collect::<Vec<_>>();
+    assert_eq!(field_names, ["LONG", "DOUBLE", "STRING"]);
+
+    for field in lazy.fields() {
+        let attributes = field.attributes().map(|a| a.decode().unwrap()).collect::<Vec<_>>();
+        assert!(matches!(attributes.as_slice(), [Attribute::ConstantValue(_)]));
+    }
+}
+
+#[test]
+fn test_lazy_attributes_decode_only_referenced_constants() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let config = CompileConfig::new("IntListControlFlowSingleFunction.java".to_string());
+    let mut bytes = config.run().unwrap();
+
+    // Only the SourceFile attribute refers to the name of the source file.
+    let source_file = b"IntListControlFlowSingleFunction.java";
+    let offset = bytes.windows(source_file.len()).position(|window| window == source_file).unwrap();
+    bytes[offset] = 0xFF;
+    assert!(ClassFileReader::read_class(&bytes).is_err());
+
+    let lazy = LazyClassFile::parse(&bytes).unwrap();
+    assert!(lazy.constant_pool().materialize().is_err());
+    let method = lazy.method("function", Some("()V")).unwrap().unwrap();
+    assert!(method.code().unwrap().is_some());
+    assert!(method.to_method().is_ok());
+}
@@ -0,0 +1,106 @@
+use common::{JavaCompilerOptions, compiled_class, run_java};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::code_view::CodeView;
+use rsjvm_class_reader::instruction::Instruction;
+use rsjvm_class_reader::optimizer::Optimizer;
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+/// Compiles the sample into `directory`, with local variable tables if `debug` is set.
+fn compile(directory: &str, debug: bool) -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    if debug {
+        options.use_g();
+    }
+    options.use_output_dir(directory);
+    compiled_class(Path::new("tests/resources/OptimizerSample.java"), &options)
+}
+
+fn code_length(class: &ClassFile) -> u32 {
+    class
+        .methods
+        .iter()
+        .filter_map(|method| method.code())
+        .map(|code| CodeView::new(code).code_length())
+        .sum()
+}
+
+fn optimize_and_run(directory: &str, debug: bool) -> ClassFile {
+    let original = compile(directory, debug);
+    let mut optimized = original.clone();
+    Optimizer::new().optimize(&mut optimized).unwrap();
+    assert!(code_length(&optimized) < code_length(original));
+
+    // The written class is checked by the verifier against its rewritten StackMapTable.
+    let optimized_directory = format!("{}_optimized", directory);
+    fs::create_dir_all(&optimized_directory).unwrap();
+    let bytes = ClassFileWriter::write(&optimized).unwrap();
+    fs::write(format!("{}/OptimizerSample.class", optimized_directory), &bytes).unwrap();
+    assert_eq!(
+        run_java(&["-cp", &optimized_directory, "OptimizerSample"]),
+        run_java(&["-cp", directory, "OptimizerSample"])
+    );
+
+    let reread = ClassFileReader::read_class_checked(&bytes).unwrap();
+    for method in &reread.methods {
+        let code = method.code().unwrap();
+        let view = CodeView::new(code);
+        for entry in &code.exception_table {
+            assert!(view.instruction_at(entry.handler_pc as u32).is_some());
+        }
+        assert_eq!(view.line_at(0).is_some(), !code.code.is_empty());
+    }
+    optimized
+}
+
+fn instructions<'a>(class: &'a ClassFile, name: &str) -> Vec<&'a Instruction> {
+    let method = class.methods.iter().find(|method| method.name == name).unwrap();
+    method.code().unwrap().code.iter().map(|(instruction, _)| instruction).collect()
+}
+
+#[test]
+fn test_optimized_code_runs() {
+    let optimized = optimize_and_run("target/classes/optimizer", false);
+
+    // The first value of x is computed for the call alone.
+    let overwritten = instructions(&optimized, "overwritten");
+    assert!(matches!(overwritten[0], Instruction::Invokestatic(_)));
+    assert_eq!(
+        overwritten[1..],
+        [
+            &Instruction::Pop,
+            &Instruction::Bipush(6),
+            &Instruction::Istore_0,
+            &Instruction::Iload_0,
+            &Instruction::Ireturn
+        ]
+    );
+    // Unused long and double locals disappear, and frames no longer declare them.
+    let wide = instructions(&optimized, "wide");
+    assert!(!wide.contains(&&Instruction::Lmul));
+    assert!(!wide.contains(&&Instruction::Ldc2_w(0)));
+
+    // No branch lands on a goto.
+    let method = optimized.methods.iter().find(|method| method.name == "loops").unwrap();
+    let code = method.code().unwrap();
+    let view = CodeView::new(code);
+    for (instruction, pc) in &code.code {
+        if let Some(offset) = instruction.branch_offset() {
+            let target = view.instruction_at((*pc as i32 + offset) as u32).unwrap();
+            assert!(!matches!(target, Instruction::Goto(_)), "{:?} at {}", instruction, pc);
+        }
+    }
+}
+
+#[test]
+fn test_debug_info_is_kept() {
+    let optimized = optimize_and_run("target/classes/optimizer_debug", true);
+
+    // Stores to named variables stay for debuggers.
+    assert!(instructions(&optimized, "overwritten").contains(&&Instruction::Istore_0));
+}
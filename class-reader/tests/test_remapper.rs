@@ -0,0 +1,200 @@
+use common::{JavaCompilerOptions, compiled_classes, run_java};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::jar_file::JarFile;
+use rsjvm_class_reader::remapper::{Mappings, Remapper};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+#[allow(dead_code)]
+mod common;
+
+const ORIGINAL_OUTPUT: &str = "\
+circle of area 13.0
+square of area 9.0
+Circle[radius=1.0]
+lambda
+circle
+6
+1
+42
+round HIGH Circle
+HIGH
+RemapperSample$Counter<java.lang.String>
+radius
+Circle
+Square
+Inner in RemapperSample
+main
+[area, equals, hashCode, label, radius, toString]
+";
+
+const RENAMED_OUTPUT: &str = "\
+circle of area 13.0
+square of area 9.0
+Disk[size=1.0]
+lambda
+circle
+6
+1
+42
+round HIGH Disk
+HIGH
+renamed.Main$Tally<java.lang.String>
+size
+Disk
+Square
+Inner in renamed.Main
+main
+[equals, hashCode, size, surface, title, toString]
+";
+
+/// Renames the sample into the package `renamed`, moving the record out of its outer class.
+/// Only the declaring methods are mapped; their overrides follow through the hierarchy, and
+/// nested classes without a mapping follow their outer class.
+const TINY: &str = "\
+tiny\t2\t0\tcompiled\trenamed
+c\tRemapperSample\trenamed/Main
+\tf\tI\tsecret\thidden
+c\tRemapperSample$Named\trenamed/Main$Titled
+\tm\t()Ljava/lang/String;\tlabel\ttitle
+c\tRemapperSample$Shape\trenamed/Main$Form
+\tm\t()D\tarea\tsurface
+\tm\t()Ljava/lang/String;\tdescribe\texplain
+c\tRemapperSample$Circle\trenamed/Disk
+\tf\tD\tradius\tsize
+\tm\t()D\tradius\tsize
+c\tRemapperSample$Square\trenamed/Main$Square
+\tf\tI\tcreated\tcount
+c\tRemapperSample$Counter\trenamed/Main$Tally
+\tf\tI\ttotal\tamount
+\tm\t(Ljava/lang/Object;)V\taccept\ttake
+\tm\t()I\ttotal\tsum
+c\tRemapperSample$LengthCounter\trenamed/Main$SizeTally
+\tm\t(Ljava/lang/String;)V\taccept\ttake
+c\tRemapperSample$Tag\trenamed/Main$Label
+\tm\t()Ljava/lang/String;\tvalue\ttext
+\tm\t()LRemapperSample$Level;\tlevel\trank
+\tm\t()Ljava/lang/Class;\ttype\tkind
+c\tRemapperSample$Level\trenamed/Main$Grade
+";
+
+/// The same renaming as a ProGuard mapping, whose original names are the renamed ones.
+const PROGUARD: &str = "\
+renamed.Main -> RemapperSample:
+    int hidden -> secret
+renamed.Main$Titled -> RemapperSample$Named:
+    java.lang.String title() -> label
+renamed.Main$Form -> RemapperSample$Shape:
+    double surface() -> area
+    java.lang.String explain() -> describe
+renamed.Disk -> RemapperSample$Circle:
+    double size -> radius
+    1:1:double size():38:38 -> radius
+renamed.Main$Square -> RemapperSample$Square:
+    int count -> created
+renamed.Main$Tally -> RemapperSample$Counter:
+    int amount -> total
+    void take(java.lang.Object) -> accept
+    int sum() -> total
+renamed.Main$SizeTally -> RemapperSample$LengthCounter:
+    void take(java.lang.String) -> accept
+renamed.Main$Label -> RemapperSample$Tag:
+    java.lang.String text() -> value
+    renamed.Main$Grade rank() -> level
+    java.lang.Class kind() -> type
+renamed.Main$Grade -> RemapperSample$Level:
+";
+
+/// Compiles the sample into `directory`, returning its classes.
+fn compile(directory: &str) -> &'static [ClassFile] {
+    let mut options = JavaCompilerOptions::new();
+    options.use_g().use_output_dir(directory);
+    compiled_classes(Path::new("tests/resources/RemapperSample.java"), &options)
+}
+
+fn write_jar(path: &str, classes: &[ClassFile]) {
+    let mut writer = ZipWriter::new(File::create(path).unwrap());
+    writer.start_file("META-INF/MANIFEST.MF", SimpleFileOptions::default()).unwrap();
+    writer.write_all(b"Manifest-Version: 1.0\r\nMain-Class: RemapperSample\r\n\r\n").unwrap();
+    for class in classes {
+        writer
+            .start_file(format!("{}.class", class.this_class), SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&ClassFileWriter::write(class).unwrap()).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn test_remapped_classes_run() {
+    let directory = "target/classes/remapper";
+    let classes = compile(directory);
+    assert_eq!(run_java(&["-cp", directory, "RemapperSample"]), ORIGINAL_OUTPUT);
+
+    let mappings = Mappings::from_tiny(TINY, "compiled", "renamed").unwrap();
+    let mut remapper = Remapper::new(mappings);
+    remapper.add_classes(classes);
+    let renamed_directory = format!("{}_renamed", directory);
+    let _ = fs::remove_dir_all(&renamed_directory);
+    for class in classes {
+        let mut class = class.clone();
+        remapper.remap(&mut class).unwrap();
+        let path = format!("{}/{}.class", renamed_directory, class.this_class);
+        fs::create_dir_all(Path::new(&path).parent().unwrap()).unwrap();
+        fs::write(path, ClassFileWriter::write(&class).unwrap()).unwrap();
+    }
+
+    assert!(Path::new(&format!("{}/renamed/Disk.class", renamed_directory)).exists());
+    assert!(Path::new(&format!("{}/renamed/Main$Inner.class", renamed_directory)).exists());
+    assert_eq!(
+        run_java(&["-Xverify:all", "-cp", &renamed_directory, "renamed.Main"]),
+        RENAMED_OUTPUT
+    );
+}
+
+#[test]
+fn test_remapped_jar_round_trip() {
+    let directory = "target/classes/remapper_jar";
+    let classes = compile(directory);
+    let jar = format!("{}/sample.jar", directory);
+    write_jar(&jar, classes);
+
+    let mappings = Mappings::from_proguard(PROGUARD).unwrap();
+    let renamed_jar = format!("{}/renamed.jar", directory);
+    Remapper::new(mappings.clone())
+        .remap_jar(File::open(&jar).unwrap(), File::create(&renamed_jar).unwrap())
+        .unwrap();
+
+    let jar_file = JarFile::open(&renamed_jar).unwrap();
+    assert!(jar_file.contains("renamed.Main$Form"));
+    assert!(!jar_file.contains("RemapperSample"));
+    assert_eq!(
+        jar_file.manifest().and_then(|manifest| manifest.main_class()),
+        Some("renamed.Main")
+    );
+    assert_eq!(run_java(&["-Xverify:all", "-jar", &renamed_jar]), RENAMED_OUTPUT);
+
+    let original_jar = format!("{}/original.jar", directory);
+    Remapper::new(mappings.reversed())
+        .remap_jar(File::open(&renamed_jar).unwrap(), File::create(&original_jar).unwrap())
+        .unwrap();
+    assert_eq!(run_java(&["-Xverify:all", "-jar", &original_jar]), ORIGINAL_OUTPUT);
+}
+
+#[test]
+fn test_retrace_remapped_stack_trace() {
+    let mappings = Mappings::from_proguard(PROGUARD).unwrap();
+    let trace = "java.lang.ArithmeticException: RemapperSample$Circle\n\
+                 \tat RemapperSample$Circle.radius(SourceFile:1)\n\
+                 \tat RemapperSample$Counter.accept(Unknown Source)\n\
+                 \tat RemapperSample.main(SourceFile:99)\n";
+    let expected = "java.lang.ArithmeticException: renamed.Disk\n\
+                    \tat renamed.Disk.size(Disk.java:38)\n\
+                    \tat renamed.Main$Tally.take(Unknown Source)\n\
+                    \tat renamed.Main.main(Main.java:99)\n";
+    assert_eq!(mappings.retrace(trace), expected);
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "serde")]
+
+use common::{CompileConfig, check_javac_version};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::instruction::Instruction;
+use rsjvm_class_reader::serde_support::WithPool;
+use serde_json::Value;
+
+mod common;
+
+#[test]
+fn test_json_round_trip_feeds_writer() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("SerdeSample.java".to_string()).run().unwrap();
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+
+    let json = serde_json::to_string_pretty(&class_file).unwrap();
+    let deserialized: ClassFile = serde_json::from_str(&json).unwrap();
+    assert!(ClassFileWriter::write(&deserialized).unwrap() == bytes);
+
+    let value: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"]["major"], class_file.version.major());
+    assert_eq!(value["flags"]["names"], serde_json::json!(["public", "super"]));
+    assert_eq!(value["interfaces"], serde_json::json!(["java/lang/Comparable"]));
+
+    let symbols = value["constant_pool"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|entry| entry["symbol"].as_str())
+        .collect::<Vec<_>>();
+    assert!(symbols.contains(&"java/io/PrintStream.println:(Ljava/lang/String;)V"));
+    assert!(symbols.iter().any(|symbol| symbol.starts_with("#0:getAsInt:")));
+    assert!(symbols.iter().any(|symbol| symbol.starts_with("REF_invokeStatic ")));
+
+    let names = value["fields"].as_array().unwrap().iter().find(|field| field["name"] == "names");
+    assert_eq!(names.unwrap()["type_descriptor"], "[Ljava/lang/String;");
+}
+
+#[test]
+fn test_instruction_operands_resolve_to_symbols() {
+    if let Err(e) = check_javac_version() {
+        panic!("{}", e);
+    }
+
+    let bytes = CompileConfig::new("SerdeSample.java".to_string()).run().unwrap();
+    let class_file = ClassFileReader::read_class(&bytes).unwrap();
+    let value = serde_json::to_value(&class_file).unwrap();
+
+    let method =
+        value["methods"].as_array().unwrap().iter().find(|method| method["name"] == "describe");
+    let code = method.unwrap()["attributes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find_map(|attribute| attribute["Code"]["code"].as_array())
+        .unwrap();
+    let symbol = |mnemonic: &str| {
+        code.iter()
+            .filter(|entry| entry["instruction"].get(mnemonic).is_some())
+            .map(|entry| entry["symbol"].as_str().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(symbol("Invokestatic"), ["java/lang/System.nanoTime:()J"]);
+    assert_eq!(symbol("Ldc2_w"), ["1125899906842624L"]);
+    assert_eq!(symbol("Instanceof"), ["java/lang/String"]);
+    assert_eq!(symbol("Checkcast"), ["java/lang/String"]);
+    assert_eq!(symbol("New"), ["java/lang/StringBuilder"]);
+    assert_eq!(symbol("Ldc"), ["!", "at "]);
+    assert_eq!(
+        symbol("Invokevirtual"),
+        [
+            "java/lang/String.concat:(Ljava/lang/String;)Ljava/lang/String;",
+            "java/lang/StringBuilder.append:(J)Ljava/lang/StringBuilder;",
+        ]
+    );
+
+    // Instructions without constant pool operands carry no symbol, and pcs are explicit.
+    assert_eq!(code[0]["pc"], 0);
+    let areturn = code.iter().find(|entry| entry["instruction"] == "Areturn").unwrap();
+    assert!(areturn.get("symbol").is_none());
+    let entries = serde_json::to_string(&class_file.methods).unwrap();
+    assert!(!entries.contains("\"symbol\""));
+
+    // A method or its code serialized with the constant pool reads as it does in its class.
+    let describe = class_file.methods.iter().find(|method| method.name == "describe").unwrap();
+    let pool = &class_file.constant_pool;
+    assert_eq!(serde_json::to_value(WithPool::new(pool, describe)).unwrap(), *method.unwrap());
+    let code_value = serde_json::to_value(WithPool::new(pool, describe.code().unwrap())).unwrap();
+    assert_eq!(code_value["code"].as_array().unwrap(), code);
+
+    // The symbols are ignored when reading the instructions back.
+    let deserialized: ClassFile = serde_json::from_value(value.clone()).unwrap();
+    let method = deserialized.methods.iter().find(|method| method.name == "describe").unwrap();
+    let instructions = method.code().unwrap().code.iter().map(|(instruction, _)| instruction);
+    assert!(instructions.clone().any(|instruction| matches!(instruction, Instruction::Ldc2_w(_))));
+    assert_eq!(instructions.count(), code.len());
+}
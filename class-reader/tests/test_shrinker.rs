@@ -0,0 +1,169 @@
+use common::{JavaCompilerOptions, compiled_classes, run_java};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_reader::ClassFileReader;
+use rsjvm_class_reader::jar_file::JarFile;
+use rsjvm_class_reader::shrinker::{ShrinkCategory, ShrinkReport, Shrinker};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+#[allow(dead_code)]
+mod common;
+
+/// Compiles the sample with all debug information into `directory`, returning the names of
+/// its class files.
+fn compile(directory: &str) -> Vec<String> {
+    let mut options = JavaCompilerOptions::new();
+    options.use_g().use_output_dir(directory);
+    let classes = compiled_classes(Path::new("tests/resources/ShrinkerSample.java"), &options);
+    classes.iter().map(|class| format!("{}.class", class.this_class)).collect()
+}
+
+fn run(class_path: &str) -> String {
+    run_java(&["-cp", class_path, "ShrinkerSample"])
+}
+
+/// Shrinks every class of `directory` into `<directory>_shrunk`.
+fn shrink_directory(shrinker: &Shrinker, directory: &str, names: &[String]) -> ShrinkReport {
+    let shrunk_directory = format!("{}_shrunk", directory);
+    let _ = fs::remove_dir_all(&shrunk_directory);
+    fs::create_dir_all(&shrunk_directory).unwrap();
+    let mut report = ShrinkReport::default();
+    for name in names {
+        let data = fs::read(format!("{}/{}", directory, name)).unwrap();
+        let (shrunk, class_report) = shrinker.shrink_bytes(&data).unwrap();
+        assert!(shrunk.len() <= data.len(), "{}", name);
+        fs::write(format!("{}/{}", shrunk_directory, name), shrunk).unwrap();
+        report.merge(class_report);
+    }
+    report
+}
+
+fn attribute_names(class: &ClassFile) -> Vec<String> {
+    let mut names = Vec::new();
+    let members = class.fields.iter().map(|field| &field.attributes);
+    let methods = class.methods.iter().map(|method| &method.attributes);
+    for attributes in members.chain(methods).chain([&class.attributes]) {
+        for attribute in attributes {
+            names.push(attribute.name().to_string());
+            if let Attribute::Code(code) = attribute {
+                names.extend(code.attributes.iter().map(|attribute| attribute.name().to_string()));
+            }
+        }
+    }
+    names
+}
+
+#[test]
+fn test_shrunk_classes_run() {
+    let directory = "target/classes/shrinker";
+    let names = compile(directory);
+    let report = shrink_directory(&Shrinker::new(), directory, &names);
+
+    assert_eq!(report.classes, names.len());
+    assert!(report.uncompacted.is_empty());
+    for category in [
+        ShrinkCategory::LineNumberTable,
+        ShrinkCategory::LocalVariableTable,
+        ShrinkCategory::LocalVariableTypeTable,
+        ShrinkCategory::SourceFile,
+        ShrinkCategory::InvisibleAnnotations,
+        ShrinkCategory::ConstantPool,
+    ] {
+        assert!(report.saved(category) > 0, "{}", category);
+    }
+    assert_eq!(report.saved(ShrinkCategory::UnknownAttributes), 0);
+    assert_eq!(report.saved.values().sum::<u64>(), report.total_saved());
+
+    // Annotations, generic signatures, records and inner classes are still visible to
+    // reflection, whose results the sample prints.
+    let shrunk_directory = format!("{}_shrunk", directory);
+    assert_eq!(run(&shrunk_directory), run(directory));
+    for name in &names {
+        let bytes = fs::read(format!("{}/{}", shrunk_directory, name)).unwrap();
+        let class = ClassFileReader::read_class_checked(&bytes).unwrap();
+        let attributes = attribute_names(&class);
+        for stripped in ["LineNumberTable", "LocalVariableTable", "SourceFile"] {
+            assert!(!attributes.iter().any(|name| name == stripped), "{} in {}", stripped, name);
+        }
+        assert!(!attributes.iter().any(|name| name.starts_with("RuntimeInvisible")));
+    }
+}
+
+#[test]
+fn test_kept_categories() {
+    let directory = "target/classes/shrinker_kept";
+    let names = compile(directory);
+    let mut shrinker = Shrinker::new();
+    shrinker.strip(ShrinkCategory::LineNumberTable, false).strip(ShrinkCategory::SourceFile, false);
+    let report = shrink_directory(&shrinker, directory, &names);
+    assert_eq!(report.saved(ShrinkCategory::LineNumberTable), 0);
+    assert_eq!(report.saved(ShrinkCategory::SourceFile), 0);
+    assert!(report.saved(ShrinkCategory::ConstantPool) > 0);
+
+    let shrunk_directory = format!("{}_shrunk", directory);
+    assert_eq!(run(&shrunk_directory), run(directory));
+    let bytes = fs::read(format!("{}/ShrinkerSample.class", shrunk_directory)).unwrap();
+    let attributes = attribute_names(&ClassFileReader::read_class(&bytes).unwrap());
+    assert!(attributes.iter().any(|name| name == "LineNumberTable"));
+    assert!(attributes.iter().any(|name| name == "SourceFile"));
+    assert!(!attributes.iter().any(|name| name == "LocalVariableTable"));
+}
+
+#[test]
+fn test_shrunk_jar_runs() {
+    let directory = "target/classes/shrinker_jar";
+    let names = compile(directory);
+    let jar = format!("{}/sample.jar", directory);
+    let mut zip = ZipWriter::new(File::create(&jar).unwrap());
+    zip.start_file("config/settings.txt", SimpleFileOptions::default()).unwrap();
+    zip.write_all(b"mode=embedded\n").unwrap();
+    for name in &names {
+        zip.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+        zip.write_all(&fs::read(format!("{}/{}", directory, name)).unwrap()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let shrunk = format!("{}/sample_shrunk.jar", directory);
+    let report = Shrinker::new()
+        .shrink_jar(File::open(&jar).unwrap(), File::create(&shrunk).unwrap())
+        .unwrap();
+    assert_eq!(report.classes, names.len());
+    assert!(report.total_saved() > 0);
+    assert!(fs::metadata(&shrunk).unwrap().len() < fs::metadata(&jar).unwrap().len());
+    assert_eq!(run(&shrunk), run(directory));
+
+    let mut shrunk_jar = JarFile::open(&shrunk).unwrap();
+    assert_eq!(shrunk_jar.len(), names.len());
+    assert_eq!(shrunk_jar.resource("config/settings.txt").unwrap().unwrap(), b"mode=embedded\n");
+}
+
+#[test]
+fn test_shrunk_tool_jar_runs() {
+    let directory = "target/classes/shrinker_tool_jar";
+    let names = compile(directory);
+    // The `jar` tool marks its first entry with the JavaJar extra field, which is copied over.
+    let jar = format!("{}.jar", directory);
+    let status = Command::new("jar")
+        .args(["--create", "--file", &jar, "--main-class", "ShrinkerSample", "-C", directory, "."])
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let shrunk = format!("{}_shrunk.jar", directory);
+    let report = Shrinker::new()
+        .shrink_jar(File::open(&jar).unwrap(), File::create(&shrunk).unwrap())
+        .unwrap();
+    assert_eq!(report.classes, names.len());
+    assert_eq!(run_java(&["-jar", &shrunk]), run(directory));
+
+    let shrunk_jar = JarFile::open(&shrunk).unwrap();
+    assert_eq!(
+        shrunk_jar.manifest().and_then(|manifest| manifest.main_class()),
+        Some("ShrinkerSample")
+    );
+}
@@ -0,0 +1,118 @@
+use common::{JavaCompilerOptions, compiled_class, java_home, run_java};
+use rsjvm_class_reader::attribute::Attribute;
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::class_file_version::ClassFileVersion;
+use rsjvm_class_reader::class_file_writer::ClassFileWriter;
+use rsjvm_class_reader::jimage::JImage;
+use rsjvm_class_reader::ssa::{Operation, SsaCode, Terminator};
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/ssa");
+    compiled_class(Path::new("tests/resources/SsaSample.java"), &options)
+}
+
+fn lift(name: &str) -> SsaCode {
+    let class = sample_class();
+    let method = class.methods.iter().find(|method| method.name == name).unwrap();
+    SsaCode::lift(class, method).unwrap()
+}
+
+#[test]
+fn test_loop_phis() {
+    let ssa = lift("fibonacci");
+    let text = ssa.to_string();
+
+    // a, b and i each get a phi in the loop header, and the temporary does not.
+    let header = ssa.blocks.iter().find(|block| block.phis.len() == 3).unwrap();
+    assert_eq!(ssa.predecessors()[header.id].len(), 2);
+    assert!(text.contains("v0: int = parameter 0\n"), "{}", text);
+    assert!(text.contains("Iadd"));
+    assert!(!text.contains("load") && !text.contains("store"), "{}", text);
+}
+
+#[test]
+fn test_exception_edges() {
+    let ssa = lift("parse");
+
+    let throwing = ssa.blocks.iter().filter(|block| !block.handlers.is_empty()).collect::<Vec<_>>();
+    assert_eq!(throwing.len(), 1, "{}", ssa);
+    let statement = &throwing[0].statements[0];
+    assert!(matches!(statement.operation, Operation::Instruction(_)));
+    for handler in &throwing[0].handlers {
+        let block = &ssa.blocks[handler.block];
+        assert_eq!(block.statements[0].operation, Operation::CaughtException);
+    }
+    // The finally handler rethrows what it caught.
+    assert!(ssa.blocks.iter().any(|block| matches!(block.terminator, Terminator::Throw(_))));
+}
+
+#[test]
+fn test_switch_up_to_max_key() {
+    let ssa = lift("largest");
+
+    let keys = ssa
+        .blocks
+        .iter()
+        .find_map(|block| match &block.terminator {
+            Terminator::Switch { cases, .. } => Some(cases.iter().map(|(key, _)| *key).collect()),
+            _ => None,
+        })
+        .unwrap_or_else(Vec::new);
+    assert_eq!(keys, [i32::MAX - 2, i32::MAX - 1, i32::MAX]);
+}
+
+#[test]
+fn test_lower_round_trip() {
+    let mut class = sample_class().clone();
+    for method in &mut class.methods {
+        let ssa = SsaCode::lift(sample_class(), method)
+            .unwrap_or_else(|error| panic!("{}: {}", method.name, error));
+        let code = ssa.lower().unwrap_or_else(|error| panic!("{}: {}", method.name, error));
+        let relifted = SsaCode::lift(sample_class(), &{
+            let mut lowered = method.clone();
+            lowered.attributes = vec![Attribute::Code(code.clone())];
+            lowered
+        });
+        assert!(relifted.is_ok(), "{}: {:?}\n{}", method.name, relifted, ssa);
+        for attribute in &mut method.attributes {
+            if let Attribute::Code(original) = attribute {
+                *original = code.clone();
+            }
+        }
+    }
+    // Without stack map frames, the lowered code is checked by the type inference verifier.
+    class.version = ClassFileVersion::from(49, 0).unwrap();
+    fs::create_dir_all("target/classes/ssa_lowered").unwrap();
+    let bytes = ClassFileWriter::write(&class).unwrap();
+    fs::write("target/classes/ssa_lowered/SsaSample.class", bytes).unwrap();
+
+    assert_eq!(
+        run_java(&["-cp", "target/classes/ssa_lowered", "SsaSample"]),
+        run_java(&["-cp", "target/classes/ssa", "SsaSample"])
+    );
+}
+
+#[test]
+fn test_lower_large_methods() {
+    // Large methods of the runtime image, which only fit when the lowered code reuses locals
+    // and keeps temporaries on the operand stack.
+    let image = JImage::open(java_home().unwrap().join("lib/modules")).unwrap();
+    for (class_name, method_name) in [
+        ("java/lang/Character$UnicodeScript", "<clinit>"),
+        ("sun/util/resources/LocaleNames", "getContents"),
+    ] {
+        let class = image.read_class(class_name).unwrap().unwrap();
+        let method = class.methods.iter().find(|method| method.name == method_name).unwrap();
+        let code = SsaCode::lift(&class, method).unwrap().lower().unwrap();
+
+        let mut lowered = method.clone();
+        lowered.attributes = vec![Attribute::Code(code)];
+        assert!(SsaCode::lift(&class, &lowered).is_ok(), "{}.{}", class_name, method_name);
+    }
+}
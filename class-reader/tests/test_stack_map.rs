@@ -0,0 +1,76 @@
+use common::{JavaCompilerOptions, compiled_class};
+use rsjvm_class_reader::class_file::ClassFile;
+use rsjvm_class_reader::code_view::CodeView;
+use rsjvm_class_reader::instruction::Instruction;
+use rsjvm_class_reader::stack_map::{StackMap, VerificationType};
+use std::path::Path;
+
+#[allow(dead_code)]
+mod common;
+
+fn sample_class() -> &'static ClassFile {
+    let mut options = JavaCompilerOptions::new();
+    options.use_output_dir("target/classes/stack_map");
+    compiled_class(Path::new("tests/resources/StackMapSample.java"), &options)
+}
+
+fn stack_map(name: &str) -> StackMap {
+    let class = sample_class();
+    let method = class.methods.iter().find(|method| method.name == name).unwrap();
+    let stack_map = StackMap::new(class, method).unwrap();
+    let view = CodeView::new(method.code().unwrap());
+    for frame in &stack_map.frames {
+        assert!(view.instruction_at(frame.pc).is_some(), "frame at pc {}", frame.pc);
+    }
+    stack_map
+}
+
+fn object(name: &str) -> VerificationType {
+    VerificationType::Object(name.to_string())
+}
+
+#[test]
+fn test_constructor() {
+    let stack_map = stack_map("<init>");
+    assert_eq!(
+        stack_map.initial.locals,
+        [VerificationType::UninitializedThis, VerificationType::Integer]
+    );
+    for frame in &stack_map.frames {
+        assert_eq!(frame.locals, [object("StackMapSample"), VerificationType::Integer]);
+        assert_eq!(frame.stack[0], object("StackMapSample"));
+    }
+}
+
+#[test]
+fn test_uninitialized_operands() {
+    let class = sample_class();
+    let stack_map = stack_map("wrap");
+    let frame = &stack_map.frames[0];
+    assert_eq!(
+        frame.locals,
+        [VerificationType::Integer, VerificationType::Long, VerificationType::Top]
+    );
+    let VerificationType::Uninitialized { pc, class: name } = &frame.stack[0] else {
+        panic!("expected an uninitialized value, found {}", frame.stack[0]);
+    };
+    assert_eq!(name, "java/lang/StringBuilder");
+    assert_eq!(frame.stack, [frame.stack[0].clone(), frame.stack[0].clone()]);
+
+    let method = class.methods.iter().find(|method| method.name == "wrap").unwrap();
+    let view = CodeView::new(method.code().unwrap());
+    assert!(matches!(view.instruction_at(*pc), Some(Instruction::New(_))));
+}
+
+#[test]
+fn test_loop_locals() {
+    let stack_map = stack_map("sum");
+    let header = &stack_map.frames[0];
+    assert_eq!(
+        header.locals[..4],
+        [VerificationType::Long, VerificationType::Top, object("[I"), VerificationType::Integer]
+    );
+    assert!(header.stack.is_empty());
+    let exit = stack_map.frames.last().unwrap();
+    assert_eq!(exit.locals.len(), 4);
+}
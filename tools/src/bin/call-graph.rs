@@ -0,0 +1,88 @@
+//! Prints the call graph of a set of classes as text, DOT or JSON.
+//!
+//! `--callers-of` narrows the output to the calls of methods whose `owner.name:descriptor`
+//! starts with the given prefix, e.g. `com/example/Legacy.` for every method of a class.
+
+use std::error::Error;
+use std::ffi::OsString;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::call_graph::{CallGraphBuilder, MethodId, Precision};
+use tools::read_class_path;
+
+const USAGE: &str = "usage: call-graph [--rta] [--entry <owner.name:descriptor>]... \
+                     [--library <paths>] [--callers-of <prefix>] [--dot | --json] <paths>";
+
+enum Format {
+    Text,
+    Dot,
+    Json,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("call-graph: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut format = Format::Text;
+    let mut precision = Precision::Cha;
+    let mut entry_points = Vec::new();
+    let mut library = None;
+    let mut callers_of = None;
+    let mut paths = None::<OsString>;
+    let mut arguments = std::env::args_os().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.to_str() {
+            Some("--rta") => precision = Precision::Rta,
+            Some("--dot") => format = Format::Dot,
+            Some("--json") => format = Format::Json,
+            Some("--entry") => {
+                let method = arguments.next().ok_or(USAGE)?;
+                let method = method.to_str().and_then(MethodId::parse);
+                entry_points.push(method.ok_or("entry points are written owner.name:descriptor")?);
+            }
+            Some("--library") => library = Some(arguments.next().ok_or(USAGE)?),
+            Some("--callers-of") => {
+                let prefix = arguments.next().ok_or(USAGE)?;
+                callers_of = Some(prefix.into_string().map_err(|_| "invalid --callers-of")?);
+            }
+            Some("-h" | "--help") => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            Some(option) if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            _ if paths.is_none() => paths = Some(argument),
+            _ => return Err(USAGE.into()),
+        }
+    }
+    let classes = read_class_path(&paths.ok_or(USAGE)?)?;
+    let library = match library {
+        Some(library) => read_class_path(&library)?,
+        None => Vec::new(),
+    };
+
+    let mut builder = CallGraphBuilder::new(&classes);
+    builder.library_classes(&library).precision(precision);
+    for entry_point in entry_points {
+        builder.entry_point(entry_point);
+    }
+    let mut graph = builder.build();
+    if let Some(prefix) = callers_of {
+        graph.edges.retain(|edge| edge.callee.to_string().starts_with(&prefix));
+    }
+
+    match format {
+        Format::Text => print!("{}", graph),
+        Format::Dot => print!("{}", graph.to_dot()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&graph)?),
+    }
+    Ok(())
+}
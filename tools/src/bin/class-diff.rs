@@ -0,0 +1,66 @@
+//! Compares two versions of a class file, directory or jar.
+//!
+//! Exits with 0 if they are equivalent, 1 if they differ and 2 on errors, like `diff`.
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::class_diff::ClassDiffer;
+use tools::read_classes;
+
+const USAGE: &str = "usage: class-diff [--json] [--ignore-debug-info] <old> <new>";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("class-diff: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Prints the differences and returns whether the inputs are equivalent.
+fn run() -> Result<bool, Box<dyn Error>> {
+    let mut json = false;
+    let mut differ = ClassDiffer::new();
+    let mut paths = Vec::new();
+    for argument in std::env::args().skip(1) {
+        match argument.as_str() {
+            "--json" => json = true,
+            "--ignore-debug-info" => {
+                differ.ignore_debug_info(true);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(true);
+            }
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            path => paths.push(PathBuf::from(path)),
+        }
+    }
+    let [old, new] = &paths[..] else {
+        return Err(USAGE.into());
+    };
+    let (old, new) = (read_classes(old)?, read_classes(new)?);
+
+    // Two single classes are compared even if they were renamed.
+    if let ([old], [new]) = (&old[..], &new[..]) {
+        let diff = differ.diff(old, new);
+        match json {
+            true => println!("{}", serde_json::to_string_pretty(&diff)?),
+            false => print!("{}", diff),
+        }
+        return Ok(diff.is_empty());
+    }
+    let diff = differ.diff_archives(&old, &new);
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&diff)?),
+        false => print!("{}", diff),
+    }
+    Ok(diff.is_empty())
+}
@@ -0,0 +1,67 @@
+//! Checks that a new version of a library is binary compatible with the old one.
+//!
+//! Both versions are path lists of class directories and jars. Exits with 1 if there are
+//! findings at or above the `--fail-on` severity, which defaults to `error`, and with 2 on
+//! errors, so that it can gate a CI pipeline.
+
+use std::error::Error;
+use std::ffi::OsString;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::binary_compat::{Severity, check_compatibility};
+use tools::read_class_path;
+
+const USAGE: &str =
+    "usage: compat-check [--json] [--fail-on error|warning|info|never] <old-path> <new-path>";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("compat-check: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Prints the report and returns whether the gate passes.
+fn run() -> Result<bool, Box<dyn Error>> {
+    let mut json = false;
+    let mut fail_on = Some(Severity::Error);
+    let mut paths = Vec::<OsString>::new();
+    let mut arguments = std::env::args_os().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.to_str() {
+            Some("--json") => json = true,
+            Some("--fail-on") => {
+                let severity = arguments.next().ok_or(USAGE)?;
+                fail_on = match severity.to_str() {
+                    Some("error") => Some(Severity::Error),
+                    Some("warning") => Some(Severity::Warning),
+                    Some("info") => Some(Severity::Info),
+                    Some("never") => None,
+                    _ => return Err(format!("unknown severity {:?}\n{}", severity, USAGE).into()),
+                };
+            }
+            Some("-h" | "--help") => {
+                println!("{}", USAGE);
+                return Ok(true);
+            }
+            Some(option) if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            _ => paths.push(argument),
+        }
+    }
+    let [old, new] = &paths[..] else {
+        return Err(USAGE.into());
+    };
+
+    let report = check_compatibility(&read_class_path(old)?, &read_class_path(new)?);
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&report)?),
+        false => print!("{}", report),
+    }
+    Ok(fail_on.is_none_or(|threshold| !report.has_findings(threshold)))
+}
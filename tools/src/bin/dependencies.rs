@@ -0,0 +1,115 @@
+//! Prints the dependencies of jars and class directories, in the style of `jdeps`.
+//!
+//! Each path is one archive, named after the module it declares or else after its file name.
+//! Cycles between the nodes of the printed graph are reported, and with `--rules` the class
+//! dependencies are checked against a rules file. Exits with 1 if a rule is violated and with
+//! 2 on errors.
+
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::dependency_analysis::{DependencyAnalyzer, DependencyRules, Granularity};
+use serde_json::json;
+use tools::read_classes;
+
+const USAGE: &str = "usage: dependencies [--level class|package|archive] [--exclude <prefix>]... \
+                     [--rules <file>] [--dot | --json] <path>...";
+
+enum Format {
+    Text,
+    Dot,
+    Json,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("dependencies: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Prints the report and returns whether every rule holds.
+fn run() -> Result<bool, Box<dyn Error>> {
+    let mut format = Format::Text;
+    let mut granularity = Granularity::Package;
+    let mut analyzer = DependencyAnalyzer::new();
+    let mut rules = None;
+    let mut paths = Vec::<PathBuf>::new();
+    let mut arguments = std::env::args_os().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.to_str() {
+            Some("--dot") => format = Format::Dot,
+            Some("--json") => format = Format::Json,
+            Some("--level") => {
+                let level = arguments.next().ok_or(USAGE)?;
+                granularity = match level.to_str() {
+                    Some("class") => Granularity::Class,
+                    Some("package") => Granularity::Package,
+                    Some("archive") => Granularity::Archive,
+                    _ => return Err(format!("unknown level {:?}\n{}", level, USAGE).into()),
+                };
+            }
+            Some("--exclude") => {
+                let prefix = arguments.next().ok_or(USAGE)?;
+                analyzer.exclude(&prefix.to_str().ok_or("invalid --exclude")?.replace('.', "/"));
+            }
+            Some("--rules") => {
+                let path = arguments.next().ok_or(USAGE)?;
+                let text = fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {}", PathBuf::from(&path).display(), err))?;
+                rules = Some(DependencyRules::parse(&text)?);
+            }
+            Some("-h" | "--help") => {
+                println!("{}", USAGE);
+                return Ok(true);
+            }
+            Some(option) if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            _ => paths.push(argument.into()),
+        }
+    }
+    if paths.is_empty() {
+        return Err(USAGE.into());
+    }
+
+    for path in &paths {
+        let classes = read_classes(path)?;
+        let file_name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+        let archive = classes
+            .iter()
+            .find_map(|class| class.module_name())
+            .map_or(file_name.to_string(), str::to_string);
+        for class in &classes {
+            analyzer.add_class(class, &archive);
+        }
+    }
+    let report = analyzer.analyze();
+    let graph = report.graph(granularity);
+    let cycles = graph.cycles();
+    let violations = rules.map(|rules| rules.check(&report)).unwrap_or_default();
+
+    match format {
+        Format::Text => {
+            print!("{}", graph);
+            for cycle in &cycles {
+                println!("cycle: {}", cycle.join(", "));
+            }
+            for violation in &violations {
+                println!("violation: {}", violation);
+            }
+        }
+        Format::Dot => print!("{}", graph.to_dot()),
+        Format::Json => {
+            let value = json!({ "graph": graph, "cycles": cycles, "violations": violations });
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+    }
+    Ok(violations.is_empty())
+}
@@ -0,0 +1,96 @@
+//! Renames the classes and members of a class file or jar after ProGuard or Tiny v2 mappings,
+//! or deobfuscates a stack trace with a ProGuard mapping.
+//!
+//! ProGuard mappings deobfuscate unless reversed with `--reverse`. A single class file is
+//! remapped without its hierarchy, so only the mappings of the classes it names apply to it.
+//! The stack trace is read from the given file or from standard input. Exits with 1 on errors.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::remapper::{Mappings, Remapper};
+
+const USAGE: &str = "usage: remap (--proguard <mapping.txt> | --tiny <mappings.tiny> --from <namespace> --to <namespace>)
+             [--reverse] <input.class|input.jar> <output>
+       remap --proguard <mapping.txt> [--reverse] --retrace [<stacktrace.txt>]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("remap: {}", err);
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut proguard = None;
+    let mut tiny = None;
+    let mut namespaces = (None, None);
+    let mut reverse = false;
+    let mut retrace = false;
+    let mut paths = Vec::new();
+    let mut arguments = std::env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--proguard" => proguard = Some(arguments.next().ok_or(USAGE)?),
+            "--tiny" => tiny = Some(arguments.next().ok_or(USAGE)?),
+            "--from" => namespaces.0 = Some(arguments.next().ok_or(USAGE)?),
+            "--to" => namespaces.1 = Some(arguments.next().ok_or(USAGE)?),
+            "--reverse" => reverse = true,
+            "--retrace" => retrace = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            _ => paths.push(PathBuf::from(argument)),
+        }
+    }
+
+    let mappings = match (proguard, tiny, namespaces) {
+        (Some(path), None, (None, None)) => Mappings::from_proguard(&fs::read_to_string(path)?)?,
+        (None, Some(path), (Some(from), Some(to))) => {
+            Mappings::from_tiny(&fs::read_to_string(path)?, &from, &to)?
+        }
+        _ => return Err(USAGE.into()),
+    };
+    let mappings = if reverse { mappings.reversed() } else { mappings };
+
+    if retrace {
+        let trace = match &paths[..] {
+            [] => {
+                let mut trace = String::new();
+                std::io::stdin().read_to_string(&mut trace)?;
+                trace
+            }
+            [path] => fs::read_to_string(path)?,
+            _ => return Err(USAGE.into()),
+        };
+        print!("{}", mappings.retrace(&trace));
+        return Ok(());
+    }
+
+    let [input, output] = &paths[..] else {
+        return Err(USAGE.into());
+    };
+    if input == output {
+        return Err("the output must not overwrite the input".into());
+    }
+    let remapper = Remapper::new(mappings);
+    match input.extension().and_then(|extension| extension.to_str()) {
+        Some("jar") => {
+            let reader = BufReader::new(File::open(input)?);
+            remapper.remap_jar(reader, BufWriter::new(File::create(output)?))?
+        }
+        Some("class") => fs::write(output, remapper.remap_bytes(&fs::read(input)?)?)?,
+        _ => return Err(format!("{}: not a class file or jar", input.display()).into()),
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Shrinks a class file or jar for deployment by stripping debug information and other
+//! attributes the JVM does not need, then compacting the constant pools.
+//!
+//! Every category is stripped unless kept with `--keep`; `constant_pool` keeps the constant
+//! pools as they are. Prints the bytes saved per category and exits with 1 on errors.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use rsjvm_class_reader::shrinker::{ShrinkCategory, Shrinker};
+
+const USAGE: &str = "usage: shrink [--json] [--keep <category>]... <input.class|input.jar> <output>
+categories: line_number_table, local_variable_table, local_variable_type_table, source_file,
+            invisible_annotations, unknown_attributes, constant_pool";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("shrink: {}", err);
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut json = false;
+    let mut shrinker = Shrinker::new();
+    let mut paths = Vec::new();
+    let mut arguments = std::env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--json" => json = true,
+            "--keep" => {
+                let name = arguments.next().ok_or(USAGE)?;
+                let category = ShrinkCategory::ALL
+                    .into_iter()
+                    .find(|category| category.name() == name)
+                    .ok_or_else(|| format!("unknown category {}\n{}", name, USAGE))?;
+                shrinker.strip(category, false);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            option if option.starts_with('-') => {
+                return Err(format!("unknown option {}\n{}", option, USAGE).into());
+            }
+            _ => paths.push(PathBuf::from(argument)),
+        }
+    }
+    let [input, output] = &paths[..] else {
+        return Err(USAGE.into());
+    };
+    if input == output {
+        return Err("the output must not overwrite the input".into());
+    }
+
+    let report = match input.extension().and_then(|extension| extension.to_str()) {
+        Some("jar") => {
+            let reader = BufReader::new(File::open(input)?);
+            shrinker.shrink_jar(reader, BufWriter::new(File::create(output)?))?
+        }
+        Some("class") => {
+            let (bytes, report) = shrinker.shrink_bytes(&fs::read(input)?)?;
+            fs::write(output, bytes)?;
+            report
+        }
+        _ => return Err(format!("{}: not a class file or jar", input.display()).into()),
+    };
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&report)?),
+        false => print!("{}", report),
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+//! Command line tools built on the class reader. Each tool is a binary in `src/bin`; this
+//! library holds what they share.
+
+use std::error::Error;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use rsjvm_class_reader::bulk_reader::BulkReader;
+use rsjvm_class_reader::class_file::ClassFile;
+
+/// Reads every class of a class file, a directory of class files or a jar, failing on the
+/// first entry that cannot be parsed and on files of any other kind.
+pub fn read_classes(path: &Path) -> Result<Vec<ClassFile>, Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("{}: no such file or directory", path.display()).into());
+    }
+    let extension = path.extension().and_then(OsStr::to_str);
+    if !path.is_dir() && !matches!(extension, Some("class" | "jar" | "zip")) {
+        return Err(format!("{}: not a class file, directory or jar", path.display()).into());
+    }
+    let result = BulkReader::new().add_path(path).read();
+    if let Some(failure) = result.failures.into_iter().next() {
+        let location = match &failure.location.entry {
+            Some(entry) => format!("{}!{}", failure.location.path.display(), entry),
+            None => failure.location.path.display().to_string(),
+        };
+        return Err(format!("{}: {}", location, failure.error).into());
+    }
+    Ok(result.classes.into_iter().map(|parsed| parsed.class).collect())
+}
+
+/// Reads the classes of every entry of a platform path list, such as a `CLASSPATH` value.
+pub fn read_class_path(paths: &OsStr) -> Result<Vec<ClassFile>, Box<dyn Error>> {
+    let mut classes = Vec::new();
+    for path in std::env::split_paths(paths) {
+        classes.extend(read_classes(&path)?);
+    }
+    Ok(classes)
+}